@@ -1,6 +1,7 @@
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use rustls::crypto::CryptoProvider;
 use rustls::{RootCertStore, SupportedCipherSuite};
 
@@ -8,6 +9,17 @@ use crate::console::ShellOutputWriter;
 use trust0_common::crypto::file::{load_certificates, load_private_key};
 use trust0_common::error::AppError;
 
+/// Which socket is used to send UDP service replies back to the (UDP) service client.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum UdpReplyMode {
+    /// Reply from the shared client proxy (listener) socket
+    #[default]
+    Listener,
+
+    /// Reply from a socket dedicated to the service client, bound to the client proxy port and connected to the service client
+    PerPeer,
+}
+
 /// Connects to the TLS server at HOSTNAME:PORT.  The default PORT
 /// is 443.  By default, this reads a request from stdin (to EOF)
 /// before making the connection.
@@ -68,6 +80,29 @@ pub struct AppConfigArgs {
     #[arg(required = false, long = "insecure", env)]
     pub insecure: bool,
 
+    /// Socket used to send UDP service replies to service clients
+    #[arg(required = false, value_enum, long = "udp-reply-mode", env)]
+    pub udp_reply_mode: Option<UdpReplyMode>,
+
+    /// Allow existing service connections up to <SHUTDOWN_GRACE_PERIOD> milliseconds to complete, after a shutdown
+    /// request, prior to them being force-closed
+    #[arg(
+        required = false,
+        long = "shutdown-grace-period",
+        env,
+        default_value_t = 0
+    )]
+    pub shutdown_grace_period: u64,
+
+    /// Fail a service proxy startup if its requested client port is unavailable (by default, a free ephemeral port
+    /// is used instead)
+    #[arg(required = false, long = "strict-client-port", env)]
+    pub strict_client_port: bool,
+
+    /// Number of worker threads used to poll service proxy listeners
+    #[arg(required = false, long = "worker-threads", env, default_value_t = 4)]
+    pub worker_threads: usize,
+
     /// Enable verbose logging
     #[arg(required = false, long = "verbose", env)]
     pub verbose: bool,
@@ -78,6 +113,10 @@ pub struct AppConfig {
     pub gateway_port: u16,
     pub tls_client_config: rustls::ClientConfig,
     pub verbose_logging: bool,
+    pub udp_reply_mode: UdpReplyMode,
+    pub shutdown_grace_period: Duration,
+    pub strict_client_port: bool,
+    pub worker_threads: usize,
     pub console_shell_output: Arc<Mutex<ShellOutputWriter>>,
 }
 
@@ -156,6 +195,10 @@ impl AppConfig {
             gateway_port: config_args.gateway_port,
             tls_client_config,
             verbose_logging: config_args.verbose,
+            udp_reply_mode: config_args.udp_reply_mode.unwrap_or_default(),
+            shutdown_grace_period: Duration::from_millis(config_args.shutdown_grace_period),
+            strict_client_port: config_args.strict_client_port,
+            worker_threads: config_args.worker_threads,
             console_shell_output: Arc::new(Mutex::new(ShellOutputWriter::new(None))),
         })
     }
@@ -273,6 +316,10 @@ pub mod tests {
             gateway_port: 2000,
             tls_client_config,
             verbose_logging: false,
+            udp_reply_mode: UdpReplyMode::Listener,
+            shutdown_grace_period: Duration::ZERO,
+            strict_client_port: false,
+            worker_threads: 2,
             console_shell_output: Arc::new(Mutex::new(shell_output_writer)),
         })
     }
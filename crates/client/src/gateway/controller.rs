@@ -2,6 +2,7 @@ use anyhow::Result;
 use std::borrow::Borrow;
 use std::io::Write;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use crate::config::AppConfig;
 use crate::console::ShellOutputWriter;
@@ -13,6 +14,7 @@ use trust0_common::error::AppError;
 pub struct ControlPlane {
     processor: request::RequestProcessor,
     console_shell_output: Arc<Mutex<ShellOutputWriter>>,
+    shutdown_grace_period: Duration,
 }
 
 impl ControlPlane {
@@ -21,6 +23,7 @@ impl ControlPlane {
         Self {
             processor: request::RequestProcessor::new(),
             console_shell_output: app_config.console_shell_output.clone(),
+            shutdown_grace_period: app_config.shutdown_grace_period,
         }
     }
 
@@ -53,17 +56,18 @@ impl ControlPlane {
         Ok(())
     }
 
-    /// Process 'start' response
+    /// Process 'start' response. The response reports the client port actually used (which may differ from the
+    /// requested port, per the client port strategy)
     fn process_response_start(
         &self,
         service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
         gateway_response: &mut response::Response,
     ) -> Result<(), AppError> {
-        let proxy_container =
+        let mut proxy_container =
             response::Proxy::from_serde_value(gateway_response.data.as_ref().unwrap())?;
-        let proxy = proxy_container.get(0).unwrap();
+        let proxy = proxy_container.get_mut(0).unwrap();
 
-        let _ = service_mgr.lock().unwrap().startup(
+        let proxy_addrs = service_mgr.lock().unwrap().startup(
             &proxy.service.clone().into(),
             &manager::ProxyAddrs(
                 proxy.client_port.unwrap(),
@@ -72,6 +76,16 @@ impl ControlPlane {
             ),
         )?;
 
+        if proxy.client_port != Some(proxy_addrs.get_client_port()) {
+            proxy.client_port = Some(proxy_addrs.get_client_port());
+            gateway_response.data = Some(serde_json::to_value(proxy).map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    "Failed converting Proxy to serde Value".to_string(),
+                    Box::new(err),
+                )
+            })?);
+        }
+
         Ok(())
     }
 
@@ -80,7 +94,8 @@ impl ControlPlane {
         &self,
         service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
     ) -> Result<(), AppError> {
-        service_mgr.lock().unwrap().shutdown()
+        manager::ClientServiceMgr::shutdown_with_grace(service_mgr, self.shutdown_grace_period)
+            .map(|_| ())
     }
 }
 
@@ -480,6 +495,90 @@ pub mod tests {
         assert_eq!(String::from_utf8(output_data).unwrap(), expected_data);
     }
 
+    #[test]
+    fn ctlplane_process_response_when_valid_start_response_and_fallback_client_port() {
+        let output_channel = mpsc::channel();
+        let output_writer = ShellOutputWriter::new(Some(Box::new(ChannelWriter {
+            channel_sender: output_channel.0,
+        })));
+        let app_config = config::tests::create_app_config(Some(output_writer)).unwrap();
+
+        let mut service_mgr = manager::tests::MockSvcMgr::new();
+        service_mgr
+            .expect_startup()
+            .with(
+                predicate::eq(model::service::Service::new(
+                    203,
+                    "chat-tcp",
+                    &Transport::TCP,
+                    "chathost1",
+                    8500,
+                )),
+                predicate::eq(ProxyAddrs(8501, "gwhost1".to_string(), 8400)),
+            )
+            .times(1)
+            .return_once(|_, _| Ok(ProxyAddrs(40123, "gwhost1".to_string(), 8400)));
+        let service_mgr: Arc<Mutex<dyn ServiceMgr + 'static>> = Arc::new(Mutex::new(service_mgr));
+
+        let mut control_plane = ControlPlane::new(Arc::new(app_config));
+
+        let response_str = "{
+              \"code\": 200,
+              \"message\": null,
+              \"request\": {
+                \"Start\": {
+                  \"service_name\": \"chat-tcp\",
+                  \"local_port\": 8501
+                }
+              },
+              \"data\": {
+                \"client_port\": 8501,
+                \"gateway_host\": \"gwhost1\",
+                \"gateway_port\": 8400,
+                \"service\": {
+                  \"address\": \"chathost1:8500\",
+                  \"id\": 203,
+                  \"name\": \"chat-tcp\",
+                  \"transport\": \"TCP\"
+                }
+              }
+            }";
+
+        let result = control_plane.process_response(&service_mgr, response_str);
+
+        match &result {
+            Ok(response) => {
+                assert_eq!(
+                    response.code, 200,
+                    "Unexpected process response code: resp={:?}",
+                    response
+                );
+                assert_eq!(
+                    response.message, None,
+                    "Unexpected process response msg: resp={:?}",
+                    response
+                );
+                assert_eq!(
+                    response.request,
+                    Request::Start {
+                        service_name: "chat-tcp".to_string(),
+                        local_port: 8501
+                    },
+                    "Unexpected process response request: resp={:?}",
+                    response
+                );
+            }
+            Err(err) => {
+                panic!("Unexpected validate result: err={:?}", err);
+            }
+        }
+
+        let expected_data ="{\n  \"code\": 200,\n  \"message\": null,\n  \"request\": {\n    \"Start\": {\n      \"service_name\": \"chat-tcp\",\n      \"local_port\": 8501\n    }\n  },\n  \"data\": {\n    \"client_port\": 40123,\n    \"gateway_host\": \"gwhost1\",\n    \"gateway_port\": 8400,\n    \"service\": {\n      \"address\": \"chathost1:8500\",\n      \"id\": 203,\n      \"name\": \"chat-tcp\",\n      \"transport\": \"TCP\"\n    }\n  }\n}\n".to_string();
+        let output_data = testutils::gather_rcvd_bytearr_channel_data(&output_channel.1);
+
+        assert_eq!(String::from_utf8(output_data).unwrap(), expected_data);
+    }
+
     #[test]
     fn ctlplane_process_response_when_valid_quit_response() {
         let output_channel = mpsc::channel();
@@ -492,7 +591,7 @@ pub mod tests {
         service_mgr
             .expect_shutdown()
             .times(1)
-            .return_once(|| Ok(()));
+            .return_once(|| Ok(vec![]));
         let service_mgr: Arc<Mutex<dyn ServiceMgr + 'static>> = Arc::new(Mutex::new(service_mgr));
 
         let mut control_plane = ControlPlane::new(Arc::new(app_config));
@@ -15,6 +15,7 @@ pub mod api {
     use super::*;
     pub use crate::config::AppConfig;
     pub use crate::console::write_shell_prompt;
+    pub use crate::service::manager::DrainStatus;
     use trust0_common::error::AppError;
     use trust0_common::logging::error;
     use trust0_common::proxy::executor::ProxyExecutor;
@@ -30,7 +31,7 @@ pub mod api {
     }
 
     pub struct MainProcessor {
-        _app_config: Arc<AppConfig>,
+        app_config: Arc<AppConfig>,
         service_mgr: Arc<Mutex<dyn service::manager::ServiceMgr>>,
         _proxy_executor_handle: thread::JoinHandle<Result<(), AppError>>,
         _proxy_events_processor_handle: thread::JoinHandle<Result<(), AppError>>,
@@ -50,11 +51,17 @@ pub mod api {
 
             let (proxy_events_sender, proxy_events_receiver) = sync::mpsc::channel();
 
-            let service_mgr = Arc::new(Mutex::new(service::manager::ClientServiceMgr::new(
+            let mut service_mgr = service::manager::ClientServiceMgr::new(
                 app_config.clone(),
                 proxy_tasks_sender,
                 proxy_events_sender,
-            )));
+            );
+            if app_config.strict_client_port {
+                service_mgr.set_client_port_strategy(Arc::new(
+                    service::port_strategy::RequestedPortStrategy,
+                ));
+            }
+            let service_mgr = Arc::new(Mutex::new(service_mgr));
 
             let service_mgr_copy = service_mgr.clone();
             let proxy_events_processor_handle = thread::spawn(move || {
@@ -67,7 +74,7 @@ pub mod api {
             // Construct processor object
 
             Self {
-                _app_config: app_config.clone(),
+                app_config: app_config.clone(),
                 service_mgr: service_mgr.clone(),
                 _proxy_executor_handle: proxy_executor_handle,
                 _proxy_events_processor_handle: proxy_events_processor_handle,
@@ -75,11 +82,21 @@ pub mod api {
             }
         }
 
+        /// Get a function to query service proxy drain progress (usable during a shutdown)
+        pub fn get_drain_progress_function(&self) -> impl Fn() -> Vec<DrainStatus> {
+            let drain_monitor = self.service_mgr.lock().unwrap().clone_drain_monitor();
+            move || drain_monitor.drain_progress()
+        }
+
         /// Get a function to shutdown proces
         pub fn get_shutdown_function(&self) -> impl Fn() {
             let service_mgr = self.service_mgr.clone();
+            let shutdown_grace_period = self.app_config.shutdown_grace_period;
             move || {
-                if let Err(err) = service_mgr.lock().unwrap().shutdown() {
+                if let Err(err) = service::manager::ClientServiceMgr::shutdown_with_grace(
+                    &service_mgr,
+                    shutdown_grace_period,
+                ) {
                     error(&target!(), &format!("{:?}", err));
                 }
             }
@@ -96,7 +113,11 @@ pub mod api {
 
         /// Component stop: stop trust client
         fn stop(&mut self) -> Result<(), AppError> {
-            self.service_mgr.lock().unwrap().shutdown()
+            service::manager::ClientServiceMgr::shutdown_with_grace(
+                &self.service_mgr,
+                self.app_config.shutdown_grace_period,
+            )
+            .map(|_| ())
         }
     }
 }
@@ -1,25 +1,34 @@
 use std::collections::HashMap;
 use std::ops::DerefMut;
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 
 use super::proxy::proxy_base::ClientServiceProxy;
 use super::proxy::tcp_proxy::TcpClientProxy;
 use crate::config::AppConfig;
+use crate::service::port_strategy::{ClientPortStrategy, EphemeralFallbackPortStrategy};
 use crate::service::proxy::proxy_base::ClientServiceProxyVisitor;
 use crate::service::proxy::tcp_proxy::TcpClientProxyServerVisitor;
 use crate::service::proxy::udp_proxy::{UdpClientProxy, UdpClientProxyServerVisitor};
 use trust0_common::error::AppError;
-use trust0_common::logging::info;
+use trust0_common::logging::{error, info};
 use trust0_common::model::service::{Service, Transport};
+use trust0_common::net::worker_pool::WorkerPool;
 use trust0_common::proxy::event::ProxyEvent;
 use trust0_common::proxy::executor::ProxyExecutorEvent;
+use trust0_common::proxy::proxy_key::ProxyKey;
+use trust0_common::proxy::proxy_key_store::{InMemProxyKeyStore, ProxyKeyStore};
 use trust0_common::target;
 
+/// Interval between drain progress checks during a shutdown grace period
+const SHUTDOWN_GRACE_POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Maximum interval between reaps of ended service proxy listener tasks (while processing proxy events)
+const PROXY_THREAD_REAP_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Simple tuple to hold proxy address information for connected session
 #[derive(Clone, PartialEq, Debug, Default)]
 pub struct ProxyAddrs(pub u16, pub String, pub u16);
@@ -41,10 +50,103 @@ impl ProxyAddrs {
     }
 }
 
+/// Ordered phases of a service manager shutdown
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ShutdownPhase {
+    /// Stop all service proxy listeners, so no new connections are accepted
+    StopListeners,
+    /// Close all existing service proxy connections
+    CloseConnections,
+    /// Wait for the service proxy listener (worker pool) tasks to end
+    JoinThreads,
+}
+
+/// Drain progress for a service proxy
+#[derive(Clone, PartialEq, Debug)]
+pub struct DrainStatus {
+    pub service_id: u64,
+    pub connections_remaining: usize,
+    pub listener_stopped: bool,
+}
+
+/// Reports drain progress of the service proxies. This is cloneable and does not require the service manager
+/// lock, so may be queried from another thread while a shutdown is in progress.
+#[allow(clippy::type_complexity)]
+#[derive(Clone, Default)]
+pub struct DrainMonitor {
+    service_proxy_visitors: Arc<Mutex<HashMap<u64, Arc<Mutex<dyn ClientServiceProxyVisitor>>>>>,
+}
+
+impl DrainMonitor {
+    /// Track drain progress for given service proxy
+    fn add_service_proxy_visitor(
+        &self,
+        service_id: u64,
+        service_proxy_visitor: Arc<Mutex<dyn ClientServiceProxyVisitor>>,
+    ) {
+        self.service_proxy_visitors
+            .lock()
+            .unwrap()
+            .insert(service_id, service_proxy_visitor);
+    }
+
+    /// Stop tracking drain progress for given service proxy
+    fn remove_service_proxy_visitor(&self, service_id: u64) {
+        self.service_proxy_visitors
+            .lock()
+            .unwrap()
+            .remove(&service_id);
+    }
+
+    /// Current drain status for each service proxy (sorted by service ID)
+    pub fn drain_progress(&self) -> Vec<DrainStatus> {
+        let mut drain_statuses: Vec<DrainStatus> = self
+            .service_proxy_visitors
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(service_id, proxy_visitor)| {
+                let proxy_visitor = proxy_visitor.lock().unwrap();
+                DrainStatus {
+                    service_id: *service_id,
+                    connections_remaining: proxy_visitor.get_connection_count(),
+                    listener_stopped: proxy_visitor.is_listener_stopped(),
+                }
+            })
+            .collect();
+
+        drain_statuses.sort_by_key(|drain_status| drain_status.service_id);
+        drain_statuses
+    }
+
+    /// Wait (up to given grace period) for all service proxy connections to complete. Returns whether all
+    /// connections completed within the grace period.
+    pub fn wait_for_drain(&self, grace_period: Duration) -> bool {
+        let start_time = Instant::now();
+
+        loop {
+            if self
+                .drain_progress()
+                .iter()
+                .all(|drain_status| drain_status.connections_remaining == 0)
+            {
+                return true;
+            }
+
+            let elapsed = start_time.elapsed();
+            if elapsed >= grace_period {
+                return false;
+            }
+
+            thread::sleep(SHUTDOWN_GRACE_POLL_INTERVAL.min(grace_period - elapsed));
+        }
+    }
+}
+
 /// Handles management of service proxy connections
 pub trait ServiceMgr: Send {
     /// Active proxy service's ID for given proxy key
-    fn get_proxy_service_for_proxy_key(&self, proxy_key: &str) -> Option<u64>;
+    fn get_proxy_service_for_proxy_key(&self, proxy_key: &ProxyKey) -> Option<u64>;
 
     /// Proxy addresses for active service proxy
     fn get_proxy_addrs_for_service(&self, service_id: u64) -> Option<ProxyAddrs>;
@@ -58,15 +160,33 @@ pub trait ServiceMgr: Send {
     /// Clone proxy tasks sender
     fn clone_proxy_tasks_sender(&self) -> Sender<ProxyExecutorEvent>;
 
-    /// Startup new proxy service to allow clients to connect/communicate to given service
+    /// Startup new proxy service to allow clients to connect/communicate to given service. The client port is
+    /// selected by the client port strategy, and the resulting proxy addresses are returned
     fn startup(
         &mut self,
         service: &Service,
         proxy_addrs: &ProxyAddrs,
     ) -> Result<ProxyAddrs, AppError>;
 
-    /// Shutdown all connected services, and respective proxy connections/listeners
-    fn shutdown(&mut self) -> Result<(), AppError>;
+    /// Clone drain monitor (to query drain progress without the service manager lock)
+    fn clone_drain_monitor(&self) -> DrainMonitor;
+
+    /// Current drain status for each service proxy (sorted by service ID)
+    fn drain_progress(&self) -> Vec<DrainStatus>;
+
+    /// Stop all service proxy listeners, so no new connections are accepted (`ShutdownPhase::StopListeners`).
+    /// Existing connections are left open.
+    fn stop_listeners(&mut self);
+
+    /// Shutdown all connected services, and respective proxy connections/listeners. This is performed
+    /// in order of the `ShutdownPhase` phases (all listeners are stopped prior to closing any connections).
+    /// Returns the resulting drain status for each service proxy.
+    fn shutdown(&mut self) -> Result<Vec<DrainStatus>, AppError>;
+
+    /// Reap service proxy listener tasks which have ended (for instance, on a listener error) and whose proxies
+    /// have no remaining connections, removing the respective service proxies (so they may be restarted). Skipped
+    /// while listeners are stopped (during a shutdown). Returns the reaped service IDs.
+    fn reap_finished_proxy_threads(&mut self) -> Vec<u64>;
 }
 
 /// Manage service connections for client session.  Only one of these should be constructed.
@@ -74,9 +194,13 @@ pub struct ClientServiceMgr {
     app_config: Arc<AppConfig>,
     service_proxies: HashMap<u64, Arc<Mutex<dyn ClientServiceProxy>>>,
     service_proxy_visitors: HashMap<u64, Arc<Mutex<dyn ClientServiceProxyVisitor>>>,
-    service_proxy_threads: HashMap<u64, JoinHandle<Result<(), AppError>>>,
+    service_proxy_tasks: HashMap<u64, Receiver<Result<(), AppError>>>,
+    worker_pool: WorkerPool,
     service_addrs: HashMap<u64, ProxyAddrs>,
-    services_by_proxy_key: Arc<Mutex<HashMap<String, u64>>>,
+    services_by_proxy_key: Arc<dyn ProxyKeyStore>,
+    client_port_strategy: Arc<dyn ClientPortStrategy>,
+    drain_monitor: DrainMonitor,
+    listeners_stopped: bool,
     proxy_events_sender: Sender<ProxyEvent>,
     proxy_tasks_sender: Sender<ProxyExecutorEvent>,
     testing_mode: bool,
@@ -89,37 +213,126 @@ impl ClientServiceMgr {
         proxy_tasks_sender: Sender<ProxyExecutorEvent>,
         proxy_events_sender: Sender<ProxyEvent>,
     ) -> Self {
+        let worker_pool = WorkerPool::new(app_config.worker_threads);
+
         Self {
             app_config,
             service_proxies: HashMap::new(),
             service_proxy_visitors: HashMap::new(),
-            service_proxy_threads: HashMap::new(),
+            service_proxy_tasks: HashMap::new(),
+            worker_pool,
             service_addrs: HashMap::new(),
-            services_by_proxy_key: Arc::new(Mutex::new(HashMap::new())),
+            services_by_proxy_key: Arc::new(InMemProxyKeyStore::new()),
+            client_port_strategy: Arc::new(EphemeralFallbackPortStrategy),
+            drain_monitor: DrainMonitor::default(),
+            listeners_stopped: false,
             proxy_events_sender,
             proxy_tasks_sender,
             testing_mode: false,
         }
     }
 
-    /// Listen and process any proxy events (blocking)
+    /// Set client-facing proxy port selection strategy (defaults to ephemeral port fallback)
+    pub fn set_client_port_strategy(&mut self, client_port_strategy: Arc<dyn ClientPortStrategy>) {
+        self.client_port_strategy = client_port_strategy;
+    }
+
+    /// Listen and process any proxy events (blocking), until a shutdown event is received. An unexpected
+    /// disconnect of the proxy events channel ends processing in error.
     pub fn poll_proxy_events(
         service_mgr: Arc<Mutex<dyn ServiceMgr>>,
         proxy_events_receiver: Receiver<ProxyEvent>,
     ) -> Result<(), AppError> {
-        loop {
-            Self::process_next_proxy_event(&service_mgr, &proxy_events_receiver)?;
+        while Self::process_next_proxy_event(&service_mgr, &proxy_events_receiver)?.is_some() {}
+
+        info(&target!(), "Proxy events processing shutdown");
+        Ok(())
+    }
+
+    /// Shutdown service manager, allowing existing connections up to the given grace period to complete (after
+    /// the listeners are stopped). Connections still active after the grace period are force-closed. The service
+    /// manager is not locked while waiting, so proxy events (connection closures) continue to be processed.
+    pub fn shutdown_with_grace(
+        service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
+        grace_period: Duration,
+    ) -> Result<Vec<DrainStatus>, AppError> {
+        if !grace_period.is_zero() {
+            let drain_monitor = {
+                let mut service_mgr = service_mgr.lock().unwrap();
+                service_mgr.stop_listeners();
+                service_mgr.clone_drain_monitor()
+            };
+
+            if !drain_monitor.wait_for_drain(grace_period) {
+                info(
+                    &target!(),
+                    &format!(
+                        "Shutdown grace period elapsed, closing remaining connections: grace_ms={}",
+                        grace_period.as_millis()
+                    ),
+                );
+            }
         }
+
+        service_mgr.lock().unwrap().shutdown()
+    }
+
+    /// Log start of given shutdown phase
+    fn log_shutdown_phase(shutdown_phase: ShutdownPhase) {
+        info(
+            &target!(),
+            &format!("Service manager shutdown phase: phase={:?}", shutdown_phase),
+        );
+    }
+
+    /// Startup service proxy listener, and submit its polling to the worker pool. The returned receiver is sent the
+    /// listener's result, once its polling ends
+    fn start_service_proxy_listener(
+        &self,
+        service_proxy: &Arc<Mutex<dyn ClientServiceProxy>>,
+    ) -> Result<Receiver<Result<(), AppError>>, AppError> {
+        service_proxy.lock().unwrap().startup()?;
+
+        let (result_sender, result_receiver) = mpsc::channel();
+        let service_proxy = service_proxy.clone();
+
+        self.worker_pool.submit(Box::new(move || {
+            match service_proxy.lock().unwrap().poll_connections() {
+                Ok(true) => Ok(true),
+                result => {
+                    let _ = result_sender.send(result.map(|_| ()));
+                    Ok(false)
+                }
+            }
+        }))?;
+
+        Ok(result_receiver)
     }
 
-    /// Process next queued proxy event (blocking). Returns whether processing occurred
+    /// Process next queued proxy event (blocking). Returns whether processing occurred (None for a shutdown event).
+    /// Ended service proxy listener tasks are reaped, if no event arrives within the reap interval.
     fn process_next_proxy_event(
         service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
         proxy_events_receiver: &Receiver<ProxyEvent>,
-    ) -> Result<bool, AppError> {
-        let proxy_event = proxy_events_receiver.recv().map_err(|err| {
-            AppError::GenWithMsgAndErr("Error receiving proxy event".to_string(), Box::new(err))
-        })?;
+    ) -> Result<Option<bool>, AppError> {
+        let proxy_event = match proxy_events_receiver.recv_timeout(PROXY_THREAD_REAP_INTERVAL) {
+            Ok(proxy_event) => proxy_event,
+            Err(RecvTimeoutError::Timeout) => {
+                service_mgr.lock().unwrap().reap_finished_proxy_threads();
+                return Ok(Some(false));
+            }
+            Err(err) => {
+                error(&target!(), "Proxy events channel disconnected unexpectedly");
+                return Err(AppError::GenWithMsgAndErr(
+                    "Error receiving proxy event".to_string(),
+                    Box::new(err),
+                ));
+            }
+        };
+
+        if let ProxyEvent::Shutdown = proxy_event {
+            return Ok(None);
+        }
 
         if let ProxyEvent::Closed(proxy_key) = proxy_event {
             let service_id = service_mgr
@@ -138,22 +351,18 @@ impl ClientServiceMgr {
                     .unwrap()
                     .remove_proxy_for_key(&proxy_key)
                 {
-                    return Ok(true);
+                    return Ok(Some(true));
                 }
             }
         }
 
-        Ok(false)
+        Ok(Some(false))
     }
 }
 
 impl ServiceMgr for ClientServiceMgr {
-    fn get_proxy_service_for_proxy_key(&self, proxy_key: &str) -> Option<u64> {
-        self.services_by_proxy_key
-            .lock()
-            .unwrap()
-            .get(proxy_key)
-            .cloned()
+    fn get_proxy_service_for_proxy_key(&self, proxy_key: &ProxyKey) -> Option<u64> {
+        self.services_by_proxy_key.get(proxy_key)
     }
 
     fn get_proxy_addrs_for_service(&self, service_id: u64) -> Option<ProxyAddrs> {
@@ -190,6 +399,13 @@ impl ServiceMgr for ClientServiceMgr {
 
         // Startup new proxy for service
         // - - - - - - - - - - - - - - -
+        let proxy_addrs = &ProxyAddrs(
+            self.client_port_strategy
+                .select_port(&service.transport, proxy_addrs.get_client_port())?,
+            proxy_addrs.get_gateway_host().to_string(),
+            proxy_addrs.get_gateway_port(),
+        );
+
         let service_proxy: Arc<Mutex<dyn ClientServiceProxy>>;
         let service_proxy_visitor: Arc<Mutex<dyn ClientServiceProxyVisitor>>;
 
@@ -214,14 +430,6 @@ impl ServiceMgr for ClientServiceMgr {
                 )));
 
                 service_proxy_visitor = tcp_proxy_visitor;
-
-                if !self.testing_mode {
-                    let service_proxy_closure = service_proxy.clone();
-                    let service_proxy_thread =
-                        thread::spawn(move || service_proxy_closure.lock().unwrap().startup());
-                    self.service_proxy_threads
-                        .insert(service.service_id, service_proxy_thread);
-                }
             }
 
             // Starts up UDP service proxy
@@ -249,38 +457,70 @@ impl ServiceMgr for ClientServiceMgr {
                 )?));
 
                 service_proxy_visitor = udp_proxy_visitor;
-
-                if !self.testing_mode {
-                    let service_proxy_closure = service_proxy.clone();
-                    let service_proxy_thread =
-                        thread::spawn(move || service_proxy_closure.lock().unwrap().startup());
-                    self.service_proxy_threads
-                        .insert(service.service_id, service_proxy_thread);
-                }
             }
         }
 
+        if !self.testing_mode {
+            let service_proxy_task = self.start_service_proxy_listener(&service_proxy)?;
+            self.service_proxy_tasks
+                .insert(service.service_id, service_proxy_task);
+        }
+
         self.service_addrs
             .insert(service.service_id, proxy_addrs.clone());
         self.service_proxies
             .insert(service.service_id, service_proxy);
+        self.drain_monitor
+            .add_service_proxy_visitor(service.service_id, service_proxy_visitor.clone());
         self.service_proxy_visitors
             .insert(service.service_id, service_proxy_visitor);
 
         Ok(proxy_addrs.clone())
     }
 
-    fn shutdown(&mut self) -> Result<(), AppError> {
+    fn clone_drain_monitor(&self) -> DrainMonitor {
+        self.drain_monitor.clone()
+    }
+
+    fn drain_progress(&self) -> Vec<DrainStatus> {
+        self.drain_monitor.drain_progress()
+    }
+
+    fn stop_listeners(&mut self) {
+        if self.listeners_stopped {
+            return;
+        }
+
+        Self::log_shutdown_phase(ShutdownPhase::StopListeners);
+
+        self.service_proxy_visitors
+            .values()
+            .for_each(|proxy_visitor| {
+                proxy_visitor
+                    .lock()
+                    .unwrap()
+                    .deref_mut()
+                    .set_shutdown_requested()
+            });
+
+        self.listeners_stopped = true;
+    }
+
+    fn shutdown(&mut self) -> Result<Vec<DrainStatus>, AppError> {
         let mut errors: Vec<String> = vec![];
 
+        // Stop listeners (prevents new connections during remaining phases)
+        self.stop_listeners();
+
+        // Close connections
+        Self::log_shutdown_phase(ShutdownPhase::CloseConnections);
+
         self.service_proxy_visitors
             .iter()
             .for_each(|(proxy_service_id, proxy_visitor)| {
-                let mut proxy_visitor = proxy_visitor.lock().unwrap();
-
-                proxy_visitor.deref_mut().set_shutdown_requested();
-
                 if let Err(err) = proxy_visitor
+                    .lock()
+                    .unwrap()
                     .deref_mut()
                     .shutdown_connections(self.clone_proxy_tasks_sender())
                 {
@@ -296,6 +536,31 @@ impl ServiceMgr for ClientServiceMgr {
                 }
             });
 
+        // Wait for listener tasks
+        Self::log_shutdown_phase(ShutdownPhase::JoinThreads);
+
+        for (proxy_service_id, service_proxy_task) in self.service_proxy_tasks.drain() {
+            match service_proxy_task.recv() {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => error(
+                    &target!(),
+                    &format!(
+                        "Service proxy listener ended in error: svc_id={}, err={:?}",
+                        proxy_service_id, err
+                    ),
+                ),
+                Err(_) => errors.push(format!(
+                    "Service proxy listener task ended without result: svc_id={}",
+                    proxy_service_id
+                )),
+            }
+        }
+
+        self.listeners_stopped = false;
+
+        // End proxy events processing
+        let _ = self.proxy_events_sender.send(ProxyEvent::Shutdown);
+
         if !errors.is_empty() {
             return Err(AppError::General(format!(
                 "Error shutting down services: err(s)={}",
@@ -303,7 +568,66 @@ impl ServiceMgr for ClientServiceMgr {
             )));
         }
 
-        Ok(())
+        Ok(self.drain_progress())
+    }
+
+    fn reap_finished_proxy_threads(&mut self) -> Vec<u64> {
+        if self.listeners_stopped {
+            return vec![];
+        }
+
+        let finished_service_tasks: Vec<(u64, Option<Result<(), AppError>>)> = self
+            .service_proxy_tasks
+            .iter()
+            .filter(|(service_id, _)| {
+                self.service_proxy_visitors
+                    .get(service_id)
+                    .is_none_or(|proxy_visitor| {
+                        proxy_visitor.lock().unwrap().get_connection_count() == 0
+                    })
+            })
+            .filter_map(
+                |(service_id, service_proxy_task)| match service_proxy_task.try_recv() {
+                    Ok(result) => Some((*service_id, Some(result))),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => Some((*service_id, None)),
+                },
+            )
+            .collect();
+
+        let mut finished_service_ids = vec![];
+
+        for (service_id, result) in finished_service_tasks {
+            match result {
+                Some(Ok(())) => info(
+                    &target!(),
+                    &format!("Service proxy listener ended: svc_id={}", service_id),
+                ),
+                Some(Err(err)) => error(
+                    &target!(),
+                    &format!(
+                        "Service proxy listener ended in error: svc_id={}, err={:?}",
+                        service_id, err
+                    ),
+                ),
+                None => error(
+                    &target!(),
+                    &format!(
+                        "Service proxy listener task ended without result: svc_id={}",
+                        service_id
+                    ),
+                ),
+            }
+
+            self.service_proxy_tasks.remove(&service_id);
+            self.service_addrs.remove(&service_id);
+            self.service_proxies.remove(&service_id);
+            self.service_proxy_visitors.remove(&service_id);
+            self.drain_monitor.remove_service_proxy_visitor(service_id);
+            finished_service_ids.push(service_id);
+        }
+
+        finished_service_ids
     }
 }
 
@@ -313,10 +637,13 @@ pub mod tests {
 
     use super::*;
     use crate::config;
+    use crate::service::port_strategy::RequestedPortStrategy;
     use crate::service::proxy::proxy_base::tests::MockCliSvcProxyVisitor;
     use mockall::{mock, predicate};
     use std::net::SocketAddr;
     use std::str::FromStr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use trust0_common::proxy::proxy_base::ProxyType;
 
     // mocks
     // =====
@@ -324,12 +651,16 @@ pub mod tests {
     mock! {
         pub SvcMgr {}
         impl ServiceMgr for SvcMgr {
-            fn get_proxy_service_for_proxy_key(&self, proxy_key: &str) -> Option<u64>;
+            fn get_proxy_service_for_proxy_key(&self, proxy_key: &ProxyKey) -> Option<u64>;
             fn get_proxy_addrs_for_service(&self, service_id: u64) -> Option<ProxyAddrs>;
             fn get_proxy_visitor_for_service(&self, service_id: u64) -> Option<&'static Arc<Mutex<dyn ClientServiceProxyVisitor>>>;
             fn clone_proxy_tasks_sender(&self) -> Sender<ProxyExecutorEvent>;
             fn startup(&mut self, service: &Service, proxy_addrs: &ProxyAddrs) -> Result<ProxyAddrs, AppError>;
-            fn shutdown(&mut self) -> Result<(), AppError>;
+            fn clone_drain_monitor(&self) -> DrainMonitor;
+            fn drain_progress(&self) -> Vec<DrainStatus>;
+            fn stop_listeners(&mut self);
+            fn shutdown(&mut self) -> Result<Vec<DrainStatus>, AppError>;
+            fn reap_finished_proxy_threads(&mut self) -> Vec<u64>;
         }
     }
 
@@ -340,7 +671,7 @@ pub mod tests {
     fn clisvcmgr_process_next_proxy_event_when_ignorable_evt() {
         let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
         let events_channel = mpsc::channel();
-        let proxy_key = "proxykey1".to_string();
+        let proxy_key = ProxyKey::new(ProxyType::TcpAndTcp, 123, None, None);
         let proxy_svc_id = 123;
 
         let mut proxy_visitor = MockCliSvcProxyVisitor::new();
@@ -354,9 +685,7 @@ pub mod tests {
         service_mgr.testing_mode = true;
         service_mgr
             .services_by_proxy_key
-            .lock()
-            .unwrap()
-            .insert(proxy_key.clone(), proxy_svc_id);
+            .put(&proxy_key, proxy_svc_id);
         service_mgr
             .service_proxy_visitors
             .insert(proxy_svc_id, Arc::new(Mutex::new(proxy_visitor)));
@@ -370,9 +699,10 @@ pub mod tests {
         events_channel.0.send(msg).unwrap();
 
         match ClientServiceMgr::process_next_proxy_event(&service_mgr, &events_channel.1) {
-            Ok(processed) => {
+            Ok(Some(processed)) => {
                 assert_eq!(processed, false);
             }
+            Ok(None) => panic!("Unexpected shutdown result"),
             Err(err) => panic!("Unexpected result: err={:?}", &err),
         }
     }
@@ -381,7 +711,7 @@ pub mod tests {
     fn clisvcmgr_process_next_proxy_event_when_closed_evt() {
         let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
         let events_channel = mpsc::channel();
-        let proxy_key = "proxykey1".to_string();
+        let proxy_key = ProxyKey::new(ProxyType::TcpAndTcp, 123, None, None);
         let proxy_svc_id = 123;
 
         let mut proxy_visitor = MockCliSvcProxyVisitor::new();
@@ -396,9 +726,7 @@ pub mod tests {
         service_mgr.testing_mode = true;
         service_mgr
             .services_by_proxy_key
-            .lock()
-            .unwrap()
-            .insert(proxy_key.clone(), proxy_svc_id);
+            .put(&proxy_key, proxy_svc_id);
         service_mgr
             .service_proxy_visitors
             .insert(proxy_svc_id, Arc::new(Mutex::new(proxy_visitor)));
@@ -408,13 +736,53 @@ pub mod tests {
         events_channel.0.send(msg).unwrap();
 
         match ClientServiceMgr::process_next_proxy_event(&service_mgr, &events_channel.1) {
-            Ok(processed) => {
+            Ok(Some(processed)) => {
                 assert_eq!(processed, true);
             }
+            Ok(None) => panic!("Unexpected shutdown result"),
             Err(err) => panic!("Unexpected result: err={:?}", &err),
         }
     }
 
+    #[test]
+    fn clisvcmgr_poll_proxy_events_when_shutdown_evt() {
+        let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
+        let events_channel = mpsc::channel();
+        let proxy_key = ProxyKey::new(ProxyType::TcpAndTcp, 123, None, None);
+
+        let mut service_mgr =
+            ClientServiceMgr::new(app_config, mpsc::channel().0, events_channel.0.clone());
+        service_mgr.testing_mode = true;
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(service_mgr));
+
+        events_channel
+            .0
+            .send(ProxyEvent::Closed(proxy_key))
+            .unwrap();
+        events_channel.0.send(ProxyEvent::Shutdown).unwrap();
+
+        if let Err(err) = ClientServiceMgr::poll_proxy_events(service_mgr, events_channel.1) {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+    }
+
+    #[test]
+    fn clisvcmgr_poll_proxy_events_when_channel_disconnected() {
+        let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
+        let events_channel = mpsc::channel();
+
+        let mut service_mgr =
+            ClientServiceMgr::new(app_config, mpsc::channel().0, mpsc::channel().0);
+        service_mgr.testing_mode = true;
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(service_mgr));
+
+        drop(events_channel.0);
+
+        if ClientServiceMgr::poll_proxy_events(service_mgr, events_channel.1).is_ok() {
+            panic!("Unexpected successful result");
+        }
+    }
+
     #[test]
     fn clisvcmgr_startup_when_already_started() {
         let service = Service {
@@ -423,6 +791,20 @@ pub mod tests {
             transport: Transport::TCP,
             host: "localhost".to_string(),
             port: 8200,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
         };
         let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
         let proxy_addrs = ProxyAddrs(3000, "gwhost1".to_string(), 8000);
@@ -463,6 +845,20 @@ pub mod tests {
             transport: Transport::TCP,
             host: "localhost".to_string(),
             port: 8200,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
         };
         let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
         let proxy_addrs = ProxyAddrs(3000, "gwhost1".to_string(), 8000);
@@ -492,6 +888,52 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn clisvcmgr_start_when_busy_client_port() {
+        let service = Service::new(200, "Service200", &Transport::TCP, "localhost", 8200);
+        let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
+        let busy_listener = std::net::TcpListener::bind("[::]:0").unwrap();
+        let busy_port = busy_listener.local_addr().unwrap().port();
+        let proxy_addrs = ProxyAddrs(busy_port, "gwhost1".to_string(), 8000);
+
+        let mut service_mgr =
+            ClientServiceMgr::new(app_config, mpsc::channel().0, mpsc::channel().0);
+        service_mgr.testing_mode = true;
+
+        let result_proxy_addrs = match service_mgr.startup(&service, &proxy_addrs) {
+            Ok(result_proxy_addrs) => result_proxy_addrs,
+            Err(err) => panic!("Unexpected startup result: err={:?}", &err),
+        };
+
+        assert_ne!(result_proxy_addrs.get_client_port(), busy_port);
+        assert_ne!(result_proxy_addrs.get_client_port(), 0);
+        assert_eq!(result_proxy_addrs.get_gateway_host(), "gwhost1");
+        assert_eq!(result_proxy_addrs.get_gateway_port(), 8000);
+        assert_eq!(
+            service_mgr.get_proxy_addrs_for_service(200),
+            Some(result_proxy_addrs)
+        );
+    }
+
+    #[test]
+    fn clisvcmgr_start_when_requested_port_strategy_and_busy_client_port() {
+        let service = Service::new(200, "Service200", &Transport::TCP, "localhost", 8200);
+        let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
+        let busy_listener = std::net::TcpListener::bind("[::]:0").unwrap();
+        let busy_port = busy_listener.local_addr().unwrap().port();
+        let proxy_addrs = ProxyAddrs(busy_port, "gwhost1".to_string(), 8000);
+
+        let mut service_mgr =
+            ClientServiceMgr::new(app_config, mpsc::channel().0, mpsc::channel().0);
+        service_mgr.testing_mode = true;
+        service_mgr.set_client_port_strategy(Arc::new(RequestedPortStrategy));
+
+        match service_mgr.startup(&service, &proxy_addrs) {
+            Ok(result_proxy_addrs) => assert_eq!(result_proxy_addrs, proxy_addrs),
+            Err(err) => panic!("Unexpected startup result: err={:?}", &err),
+        }
+    }
+
     #[test]
     fn clisvcmgr_start_when_udp_service() {
         let service = Service {
@@ -500,6 +942,20 @@ pub mod tests {
             transport: Transport::UDP,
             host: "localhost".to_string(),
             port: 8200,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
         };
         let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
         let proxy_addrs = ProxyAddrs(3000, "gwhost1".to_string(), 8000);
@@ -532,38 +988,412 @@ pub mod tests {
     #[test]
     fn clisvcmgr_shutdown_when_2_service_proxies() {
         let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
+        let shutdown_calls = Arc::new(Mutex::new(Vec::new()));
 
         let mut proxy_visitor200 = MockCliSvcProxyVisitor::new();
-        proxy_visitor200
-            .expect_set_shutdown_requested()
-            .times(1)
-            .return_once(|| ());
-        proxy_visitor200
-            .expect_shutdown_connections()
-            .times(1)
-            .return_once(|_| Ok(()));
         let mut proxy_visitor201 = MockCliSvcProxyVisitor::new();
-        proxy_visitor201
+        for proxy_visitor in [&mut proxy_visitor200, &mut proxy_visitor201] {
+            proxy_visitor
+                .expect_get_connection_count()
+                .times(1)
+                .return_const(0usize);
+            proxy_visitor
+                .expect_is_listener_stopped()
+                .times(1)
+                .return_const(true);
+            let shutdown_calls_copy = shutdown_calls.clone();
+            proxy_visitor
+                .expect_set_shutdown_requested()
+                .times(1)
+                .return_once(move || shutdown_calls_copy.lock().unwrap().push("stop"));
+            let shutdown_calls_copy = shutdown_calls.clone();
+            proxy_visitor
+                .expect_shutdown_connections()
+                .times(1)
+                .return_once(move |_| {
+                    shutdown_calls_copy.lock().unwrap().push("close");
+                    Ok(())
+                });
+        }
+
+        let mut service_mgr =
+            ClientServiceMgr::new(app_config, mpsc::channel().0, mpsc::channel().0);
+        service_mgr.testing_mode = true;
+        let proxy_visitor200: Arc<Mutex<dyn ClientServiceProxyVisitor>> =
+            Arc::new(Mutex::new(proxy_visitor200));
+        let proxy_visitor201: Arc<Mutex<dyn ClientServiceProxyVisitor>> =
+            Arc::new(Mutex::new(proxy_visitor201));
+        for (service_id, proxy_visitor) in [(200, proxy_visitor200), (201, proxy_visitor201)] {
+            service_mgr
+                .drain_monitor
+                .add_service_proxy_visitor(service_id, proxy_visitor.clone());
+            service_mgr
+                .service_proxy_visitors
+                .insert(service_id, proxy_visitor);
+        }
+        let (task_result_sender, task_result_receiver) = mpsc::channel();
+        task_result_sender.send(Ok(())).unwrap();
+        service_mgr
+            .service_proxy_tasks
+            .insert(200, task_result_receiver);
+
+        let drain_statuses = match service_mgr.shutdown() {
+            Ok(drain_statuses) => drain_statuses,
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        };
+
+        assert_eq!(
+            drain_statuses,
+            vec![
+                DrainStatus {
+                    service_id: 200,
+                    connections_remaining: 0,
+                    listener_stopped: true,
+                },
+                DrainStatus {
+                    service_id: 201,
+                    connections_remaining: 0,
+                    listener_stopped: true,
+                },
+            ]
+        );
+
+        assert_eq!(
+            *shutdown_calls.lock().unwrap(),
+            vec!["stop", "stop", "close", "close"]
+        );
+        assert!(service_mgr.service_proxy_tasks.is_empty());
+    }
+
+    fn add_service_proxy_with_task(
+        service_mgr: &mut ClientServiceMgr,
+        service_id: u64,
+        connection_count: usize,
+        service_proxy_task: Receiver<Result<(), AppError>>,
+    ) {
+        let mut proxy_visitor = MockCliSvcProxyVisitor::new();
+        proxy_visitor
+            .expect_get_connection_count()
+            .return_const(connection_count);
+        let proxy_visitor: Arc<Mutex<dyn ClientServiceProxyVisitor>> =
+            Arc::new(Mutex::new(proxy_visitor));
+
+        service_mgr
+            .service_addrs
+            .insert(service_id, ProxyAddrs(3000, "gwhost1".to_string(), 8000));
+        service_mgr
+            .drain_monitor
+            .add_service_proxy_visitor(service_id, proxy_visitor.clone());
+        service_mgr
+            .service_proxy_visitors
+            .insert(service_id, proxy_visitor);
+        service_mgr
+            .service_proxy_tasks
+            .insert(service_id, service_proxy_task);
+    }
+
+    fn create_finished_task(result: Result<(), AppError>) -> Receiver<Result<(), AppError>> {
+        let (task_result_sender, task_result_receiver) = mpsc::channel();
+        task_result_sender.send(result).unwrap();
+        task_result_receiver
+    }
+
+    #[test]
+    fn clisvcmgr_reap_finished_proxy_threads_when_finished_and_running_tasks() {
+        let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
+        let mut service_mgr =
+            ClientServiceMgr::new(app_config, mpsc::channel().0, mpsc::channel().0);
+        service_mgr.testing_mode = true;
+
+        let (task_result_sender, task_result_receiver) = mpsc::channel();
+        add_service_proxy_with_task(
+            &mut service_mgr,
+            200,
+            0,
+            create_finished_task(Err(AppError::General("listener failed".to_string()))),
+        );
+        add_service_proxy_with_task(&mut service_mgr, 201, 0, task_result_receiver);
+        add_service_proxy_with_task(&mut service_mgr, 202, 1, create_finished_task(Ok(())));
+
+        assert_eq!(service_mgr.reap_finished_proxy_threads(), vec![200]);
+        assert!(!service_mgr.service_proxy_tasks.contains_key(&200));
+        assert!(service_mgr.get_proxy_addrs_for_service(200).is_none());
+        assert!(service_mgr.get_proxy_visitor_for_service(200).is_none());
+        let mut drain_service_ids: Vec<u64> = service_mgr
+            .drain_monitor
+            .service_proxy_visitors
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        drain_service_ids.sort();
+        assert_eq!(drain_service_ids, vec![201, 202]);
+
+        task_result_sender.send(Ok(())).unwrap();
+
+        assert_eq!(service_mgr.reap_finished_proxy_threads(), vec![201]);
+        assert_eq!(
+            service_mgr
+                .service_proxy_tasks
+                .keys()
+                .cloned()
+                .collect::<Vec<u64>>(),
+            vec![202]
+        );
+    }
+
+    #[test]
+    fn clisvcmgr_reap_finished_proxy_threads_when_listeners_stopped() {
+        let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
+        let mut service_mgr =
+            ClientServiceMgr::new(app_config, mpsc::channel().0, mpsc::channel().0);
+        service_mgr.testing_mode = true;
+        add_service_proxy_with_task(&mut service_mgr, 200, 0, create_finished_task(Ok(())));
+        service_mgr.listeners_stopped = true;
+
+        assert!(service_mgr.reap_finished_proxy_threads().is_empty());
+        assert!(service_mgr.service_proxy_tasks.contains_key(&200));
+    }
+
+    #[test]
+    fn clisvcmgr_start_when_listener_bind_fails() {
+        let service = Service::new(200, "Service200", &Transport::TCP, "localhost", 8200);
+        let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
+        let busy_listener = std::net::TcpListener::bind("[::]:0").unwrap();
+        let busy_port = busy_listener.local_addr().unwrap().port();
+        let proxy_addrs = ProxyAddrs(busy_port, "gwhost1".to_string(), 8000);
+
+        let mut service_mgr =
+            ClientServiceMgr::new(app_config, mpsc::channel().0, mpsc::channel().0);
+        service_mgr.set_client_port_strategy(Arc::new(RequestedPortStrategy));
+
+        if let Ok(proxy_addrs) = service_mgr.startup(&service, &proxy_addrs) {
+            panic!(
+                "Unexpected successful startup result: addrs={:?}",
+                &proxy_addrs
+            );
+        }
+
+        assert!(service_mgr.service_proxy_tasks.is_empty());
+        assert!(service_mgr.service_proxies.is_empty());
+        assert!(service_mgr.get_proxy_addrs_for_service(200).is_none());
+        assert_eq!(service_mgr.worker_pool.get_active_task_count(), 0);
+    }
+
+    fn find_free_port() -> u16 {
+        std::net::TcpListener::bind("[::]:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    #[test]
+    fn clisvcmgr_startup_and_shutdown_when_small_worker_pool() {
+        let mut app_config = config::tests::create_app_config(None).unwrap();
+        app_config.worker_threads = 1;
+        let app_config = Arc::new(app_config);
+        let services = [
+            Service::new(200, "Service200", &Transport::TCP, "localhost", 8200),
+            Service::new(201, "Service201", &Transport::TCP, "localhost", 8201),
+            Service::new(202, "Service202", &Transport::UDP, "localhost", 8202),
+        ];
+
+        let mut service_mgr =
+            ClientServiceMgr::new(app_config, mpsc::channel().0, mpsc::channel().0);
+        service_mgr.set_client_port_strategy(Arc::new(RequestedPortStrategy));
+
+        let mut client_ports = vec![];
+        for service in &services {
+            let proxy_addrs = ProxyAddrs(find_free_port(), "gwhost1".to_string(), 8000);
+            match service_mgr.startup(service, &proxy_addrs) {
+                Ok(proxy_addrs) => client_ports.push(proxy_addrs.get_client_port()),
+                Err(err) => panic!("Unexpected startup result: err={:?}", &err),
+            }
+        }
+
+        assert_eq!(service_mgr.worker_pool.get_worker_count(), 1);
+        assert_eq!(service_mgr.worker_pool.get_active_task_count(), 3);
+        assert!(std::net::TcpListener::bind(("::", client_ports[0])).is_err());
+        assert!(std::net::TcpListener::bind(("::", client_ports[1])).is_err());
+        assert!(std::net::UdpSocket::bind(("::", client_ports[2])).is_err());
+
+        if let Err(err) = service_mgr.shutdown() {
+            panic!("Unexpected shutdown result: err={:?}", &err);
+        }
+
+        assert!(service_mgr.service_proxy_tasks.is_empty());
+        for _ in 0..250 {
+            if service_mgr.worker_pool.get_active_task_count() == 0 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(service_mgr.worker_pool.get_active_task_count(), 0);
+        assert!(std::net::TcpListener::bind(("::", client_ports[0])).is_ok());
+        assert!(std::net::TcpListener::bind(("::", client_ports[1])).is_ok());
+        assert!(std::net::UdpSocket::bind(("::", client_ports[2])).is_ok());
+    }
+
+    fn create_service_mgr_for_grace(
+        connection_count: Arc<AtomicUsize>,
+        closed_connection_counts: Arc<Mutex<Vec<usize>>>,
+    ) -> Arc<Mutex<dyn ServiceMgr>> {
+        let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
+
+        let mut proxy_visitor = MockCliSvcProxyVisitor::new();
+        let connection_count_copy = connection_count.clone();
+        proxy_visitor
+            .expect_get_connection_count()
+            .returning(move || connection_count_copy.load(Ordering::SeqCst));
+        proxy_visitor
+            .expect_is_listener_stopped()
+            .return_const(true);
+        proxy_visitor
             .expect_set_shutdown_requested()
             .times(1)
-            .return_once(|| ());
-        proxy_visitor201
+            .return_const(());
+        proxy_visitor
             .expect_shutdown_connections()
             .times(1)
-            .return_once(|_| Ok(()));
+            .return_once(move |_| {
+                closed_connection_counts
+                    .lock()
+                    .unwrap()
+                    .push(connection_count.swap(0, Ordering::SeqCst));
+                Ok(())
+            });
 
         let mut service_mgr =
             ClientServiceMgr::new(app_config, mpsc::channel().0, mpsc::channel().0);
         service_mgr.testing_mode = true;
+        let proxy_visitor: Arc<Mutex<dyn ClientServiceProxyVisitor>> =
+            Arc::new(Mutex::new(proxy_visitor));
         service_mgr
-            .service_proxy_visitors
-            .insert(200, Arc::new(Mutex::new(proxy_visitor200)));
+            .drain_monitor
+            .add_service_proxy_visitor(200, proxy_visitor.clone());
         service_mgr
             .service_proxy_visitors
-            .insert(201, Arc::new(Mutex::new(proxy_visitor201)));
+            .insert(200, proxy_visitor);
 
-        if let Err(err) = service_mgr.shutdown() {
+        Arc::new(Mutex::new(service_mgr))
+    }
+
+    #[test]
+    fn clisvcmgr_shutdown_with_grace_when_connections_complete_within_grace() {
+        let connection_count = Arc::new(AtomicUsize::new(2));
+        let closed_connection_counts = Arc::new(Mutex::new(Vec::new()));
+        let service_mgr = create_service_mgr_for_grace(
+            connection_count.clone(),
+            closed_connection_counts.clone(),
+        );
+
+        // connections complete (via proxy closed events) shortly after shutdown request
+        let connection_count_copy = connection_count.clone();
+        let connections_handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            connection_count_copy.store(0, Ordering::SeqCst);
+        });
+
+        let start_time = Instant::now();
+        let result = ClientServiceMgr::shutdown_with_grace(&service_mgr, Duration::from_secs(5));
+        connections_handle.join().unwrap();
+
+        if let Err(err) = result {
             panic!("Unexpected result: err={:?}", &err);
         }
+        assert!(start_time.elapsed() < Duration::from_secs(5));
+        assert_eq!(*closed_connection_counts.lock().unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn clisvcmgr_shutdown_with_grace_when_connections_remain_after_grace() {
+        let connection_count = Arc::new(AtomicUsize::new(2));
+        let closed_connection_counts = Arc::new(Mutex::new(Vec::new()));
+        let service_mgr = create_service_mgr_for_grace(
+            connection_count.clone(),
+            closed_connection_counts.clone(),
+        );
+
+        let start_time = Instant::now();
+        let drain_statuses =
+            match ClientServiceMgr::shutdown_with_grace(&service_mgr, Duration::from_millis(100)) {
+                Ok(drain_statuses) => drain_statuses,
+                Err(err) => panic!("Unexpected result: err={:?}", &err),
+            };
+
+        assert!(start_time.elapsed() >= Duration::from_millis(100));
+        assert_eq!(*closed_connection_counts.lock().unwrap(), vec![2]);
+        assert_eq!(
+            drain_statuses,
+            vec![DrainStatus {
+                service_id: 200,
+                connections_remaining: 0,
+                listener_stopped: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn clisvcmgr_drain_progress_when_connections_decreasing() {
+        let app_config = Arc::new(config::tests::create_app_config(None).unwrap());
+
+        let mut proxy_visitor200 = MockCliSvcProxyVisitor::new();
+        let mut connection_counts = vec![0usize, 1, 3];
+        proxy_visitor200
+            .expect_get_connection_count()
+            .times(3)
+            .returning(move || connection_counts.pop().unwrap());
+        proxy_visitor200
+            .expect_is_listener_stopped()
+            .times(3)
+            .return_const(true);
+        let mut proxy_visitor201 = MockCliSvcProxyVisitor::new();
+        proxy_visitor201
+            .expect_get_connection_count()
+            .times(3)
+            .return_const(0usize);
+        proxy_visitor201
+            .expect_is_listener_stopped()
+            .times(3)
+            .return_const(false);
+
+        let service_mgr = ClientServiceMgr::new(app_config, mpsc::channel().0, mpsc::channel().0);
+        service_mgr
+            .drain_monitor
+            .add_service_proxy_visitor(201, Arc::new(Mutex::new(proxy_visitor201)));
+        service_mgr
+            .drain_monitor
+            .add_service_proxy_visitor(200, Arc::new(Mutex::new(proxy_visitor200)));
+        let drain_monitor = service_mgr.clone_drain_monitor();
+        let service_mgr = Arc::new(Mutex::new(service_mgr));
+
+        // query progress from another thread, while service manager is locked
+        let _service_mgr_guard = service_mgr.lock().unwrap();
+        let drain_progress_handle = thread::spawn(move || {
+            (0..3)
+                .map(|_| drain_monitor.drain_progress())
+                .collect::<Vec<Vec<DrainStatus>>>()
+        });
+        let drain_progress = drain_progress_handle.join().unwrap();
+
+        for (drain_statuses, connections_remaining) in drain_progress.iter().zip([3, 1, 0]) {
+            assert_eq!(
+                *drain_statuses,
+                vec![
+                    DrainStatus {
+                        service_id: 200,
+                        connections_remaining,
+                        listener_stopped: true,
+                    },
+                    DrainStatus {
+                        service_id: 201,
+                        connections_remaining: 0,
+                        listener_stopped: false,
+                    },
+                ]
+            );
+        }
     }
 }
@@ -1,2 +1,3 @@
 pub mod manager;
+pub mod port_strategy;
 pub mod proxy;
@@ -0,0 +1,137 @@
+use std::io;
+use std::net::{SocketAddr, TcpListener, UdpSocket};
+
+use trust0_common::error::AppError;
+use trust0_common::logging::info;
+use trust0_common::model::service::Transport;
+use trust0_common::target;
+
+/// Client-facing proxy port selection strategy, used when starting a service proxy
+pub trait ClientPortStrategy: Send + Sync {
+    /// Select the local port to listen on, given the service transport and the requested port (0 requests any port)
+    fn select_port(&self, transport: &Transport, requested_port: u16) -> Result<u16, AppError>;
+}
+
+/// Uses the requested port as-is (binding errors surface when the proxy listener is started)
+pub struct RequestedPortStrategy;
+
+impl ClientPortStrategy for RequestedPortStrategy {
+    fn select_port(&self, _transport: &Transport, requested_port: u16) -> Result<u16, AppError> {
+        Ok(requested_port)
+    }
+}
+
+/// Uses the requested port if it is free, else (or if port 0 is requested) a free ephemeral port. Ports are probed
+/// on the same (wildcard) address the proxy listeners bind to.
+pub struct EphemeralFallbackPortStrategy;
+
+impl EphemeralFallbackPortStrategy {
+    /// Bind (and release) a probe socket for given transport and port. Returns the bound port
+    fn probe_port(transport: &Transport, port: u16) -> io::Result<u16> {
+        let probe_addr = SocketAddr::from(([0u16; 8], port));
+        match transport {
+            Transport::TCP => TcpListener::bind(probe_addr)?.local_addr(),
+            Transport::UDP => UdpSocket::bind(probe_addr)?.local_addr(),
+        }
+        .map(|local_addr| local_addr.port())
+    }
+}
+
+impl ClientPortStrategy for EphemeralFallbackPortStrategy {
+    fn select_port(&self, transport: &Transport, requested_port: u16) -> Result<u16, AppError> {
+        if requested_port != 0 {
+            match Self::probe_port(transport, requested_port) {
+                Ok(port) => return Ok(port),
+                Err(err) => info(
+                    &target!(),
+                    &format!(
+                        "Requested client proxy port unavailable, using ephemeral port: port={}, err={:?}",
+                        requested_port, &err
+                    ),
+                ),
+            }
+        }
+
+        Self::probe_port(transport, 0).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                format!(
+                    "Failed selecting ephemeral client proxy port: transport={:?}",
+                    transport
+                ),
+                Box::new(err),
+            )
+        })
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn reqportstrat_select_port_when_busy_port() {
+        let busy_listener = TcpListener::bind("[::]:0").unwrap();
+        let busy_port = busy_listener.local_addr().unwrap().port();
+
+        assert_eq!(
+            RequestedPortStrategy
+                .select_port(&Transport::TCP, busy_port)
+                .unwrap(),
+            busy_port
+        );
+    }
+
+    #[test]
+    fn ephemportstrat_select_port_when_explicit_free_port() {
+        let free_port = TcpListener::bind("[::]:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        assert_eq!(
+            EphemeralFallbackPortStrategy
+                .select_port(&Transport::TCP, free_port)
+                .unwrap(),
+            free_port
+        );
+    }
+
+    #[test]
+    fn ephemportstrat_select_port_when_busy_tcp_port() {
+        let busy_listener = TcpListener::bind("[::]:0").unwrap();
+        let busy_port = busy_listener.local_addr().unwrap().port();
+
+        let selected_port = EphemeralFallbackPortStrategy
+            .select_port(&Transport::TCP, busy_port)
+            .unwrap();
+
+        assert_ne!(selected_port, busy_port);
+        assert_ne!(selected_port, 0);
+    }
+
+    #[test]
+    fn ephemportstrat_select_port_when_busy_udp_port() {
+        let busy_socket = UdpSocket::bind("[::]:0").unwrap();
+        let busy_port = busy_socket.local_addr().unwrap().port();
+
+        let selected_port = EphemeralFallbackPortStrategy
+            .select_port(&Transport::UDP, busy_port)
+            .unwrap();
+
+        assert_ne!(selected_port, busy_port);
+        assert_ne!(selected_port, 0);
+    }
+
+    #[test]
+    fn ephemportstrat_select_port_when_port_0() {
+        let selected_port = EphemeralFallbackPortStrategy
+            .select_port(&Transport::TCP, 0)
+            .unwrap();
+
+        assert_ne!(selected_port, 0);
+        TcpListener::bind(SocketAddr::from(([0u16; 8], selected_port))).unwrap();
+    }
+}
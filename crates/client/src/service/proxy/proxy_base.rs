@@ -4,11 +4,16 @@ use std::sync::mpsc::Sender;
 use trust0_common::error::AppError;
 use trust0_common::model::service::Service;
 use trust0_common::proxy::executor::ProxyExecutorEvent;
+use trust0_common::proxy::proxy_key::ProxyKey;
 
 /// Service proxy trait for the client end of the proxy (implementations are transport-layer,... specific)
 pub trait ClientServiceProxy: Send {
-    /// Startup proxy listener (for clients to connect to gateway proxy for service)
+    /// Startup proxy listener (for clients to connect to gateway proxy for service). The listener is bound and
+    /// enters polling state, its polling is then driven via `poll_connections`
     fn startup(&mut self) -> Result<(), AppError>;
+
+    /// Perform a single (non-blocking) poll for new connections (or messages). Returns whether polling should continue
+    fn poll_connections(&mut self) -> Result<bool, AppError>;
 }
 
 /// Client service proxy visitor trait (implementations are transport-layer,... specific)
@@ -28,6 +33,12 @@ pub trait ClientServiceProxyVisitor: Send {
     /// Request a server shutdown
     fn set_shutdown_requested(&mut self);
 
+    /// Returns whether the listener has been stopped (shutdown requested), so no new connections are accepted
+    fn is_listener_stopped(&self) -> bool;
+
+    /// Number of active proxy connections for service
+    fn get_connection_count(&self) -> usize;
+
     /// Shutdown proxy connection for service
     fn shutdown_connections(
         &mut self,
@@ -35,7 +46,7 @@ pub trait ClientServiceProxyVisitor: Send {
     ) -> Result<(), AppError>;
 
     /// Remove proxy for given proxy key. Returns whether removed else not found
-    fn remove_proxy_for_key(&mut self, proxy_key: &str) -> bool;
+    fn remove_proxy_for_key(&mut self, proxy_key: &ProxyKey) -> bool;
 }
 
 /// Unit tests
@@ -56,8 +67,10 @@ pub mod tests {
             fn get_gateway_proxy_host(&self) -> &str;
             fn get_gateway_proxy_port(&self) -> u16;
             fn set_shutdown_requested(&mut self);
+            fn is_listener_stopped(&self) -> bool;
+            fn get_connection_count(&self) -> usize;
             fn shutdown_connections(&mut self, proxy_tasks_sender: Sender<ProxyExecutorEvent>) -> Result<(), AppError>;
-            fn remove_proxy_for_key(&mut self, proxy_key: &str) -> bool;
+            fn remove_proxy_for_key(&mut self, proxy_key: &ProxyKey) -> bool;
         }
     }
 }
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::net::TcpStream;
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
@@ -15,8 +15,11 @@ use trust0_common::net::tcp_server::{conn_std, server_std};
 use trust0_common::net::tls_client::client_std;
 use trust0_common::net::tls_client::conn_std::TlsClientConnection;
 use trust0_common::proxy::event::ProxyEvent;
-use trust0_common::proxy::executor::{ProxyExecutorEvent, ProxyKey};
+use trust0_common::proxy::executor::{ProxyExecutorEvent, TcpProxyOptions};
 use trust0_common::proxy::proxy_base::ProxyType;
+use trust0_common::proxy::proxy_key::ProxyKey;
+use trust0_common::proxy::proxy_key_store::ProxyKeyStore;
+use trust0_common::proxy::proxy_tcp_and_tcp::RelayMode;
 
 /// Client service proxy (TCP service client <-> TCP trust0 client)
 pub struct TcpClientProxy {
@@ -41,7 +44,11 @@ impl TcpClientProxy {
 impl ClientServiceProxy for TcpClientProxy {
     fn startup(&mut self) -> Result<(), AppError> {
         self.tcp_server.bind_listener()?;
-        self.tcp_server.poll_new_connections()
+        self.tcp_server.start_polling()
+    }
+
+    fn poll_connections(&mut self) -> Result<bool, AppError> {
+        self.tcp_server.poll_once()
     }
 }
 
@@ -56,7 +63,7 @@ pub struct TcpClientProxyServerVisitor {
     gateway_proxy_port: u16,
     proxy_tasks_sender: Sender<ProxyExecutorEvent>,
     proxy_events_sender: Sender<ProxyEvent>,
-    services_by_proxy_key: Arc<Mutex<HashMap<String, u64>>>,
+    services_by_proxy_key: Arc<dyn ProxyKeyStore>,
     proxy_keys: HashSet<ProxyKey>,
     shutdown_requested: bool,
 }
@@ -72,7 +79,7 @@ impl TcpClientProxyServerVisitor {
         gateway_proxy_port: u16,
         proxy_tasks_sender: Sender<ProxyExecutorEvent>,
         proxy_events_sender: Sender<ProxyEvent>,
-        services_by_proxy_key: Arc<Mutex<HashMap<String, u64>>>,
+        services_by_proxy_key: Arc<dyn ProxyKeyStore>,
     ) -> Result<Self, AppError> {
         Ok(Self {
             app_config,
@@ -102,6 +109,15 @@ impl server_std::ServerVisitor for TcpClientProxyServerVisitor {
     }
 
     fn on_conn_accepted(&mut self, connection: conn_std::Connection) -> Result<(), AppError> {
+        // Reject connections accepted after shutdown was requested
+
+        if self.shutdown_requested {
+            return Err(AppError::General(format!(
+                "Service proxy shutting down, rejecting connection: svc_id={}",
+                self.service.service_id
+            )));
+        }
+
         // Make connection to gateway proxy
 
         let mut tls_client_config = self.app_config.tls_client_config.clone();
@@ -134,8 +150,9 @@ impl server_std::ServerVisitor for TcpClientProxyServerVisitor {
         // Send request to proxy executor to startup new proxy
 
         let tcp_stream = connection.get_tcp_stream_as_ref();
-        let proxy_key = ProxyEvent::key_value(
-            &ProxyType::TcpAndTcp,
+        let proxy_key = ProxyKey::new(
+            ProxyType::TcpAndTcp,
+            self.service.service_id,
             tcp_stream.peer_addr().ok(),
             tcp_stream.local_addr().ok(),
         );
@@ -159,6 +176,15 @@ impl server_std::ServerVisitor for TcpClientProxyServerVisitor {
                     tls_client.into(),
                 ))),
                 self.proxy_events_sender.clone(),
+                TcpProxyOptions {
+                    relay_mode: match self.service.fast_relay {
+                        true => RelayMode::Fast,
+                        false => RelayMode::Standard,
+                    },
+                    log_lifecycle: true,
+                    abortive_close_on_error: true,
+                    ..Default::default()
+                },
             ),
         );
 
@@ -174,9 +200,7 @@ impl server_std::ServerVisitor for TcpClientProxyServerVisitor {
         // Setup proxy maps
 
         self.services_by_proxy_key
-            .lock()
-            .unwrap()
-            .insert(proxy_key.clone(), self.service.service_id);
+            .put(&proxy_key, self.service.service_id);
         self.proxy_keys.insert(proxy_key);
 
         Ok(())
@@ -208,6 +232,14 @@ impl ClientServiceProxyVisitor for TcpClientProxyServerVisitor {
         self.shutdown_requested = true;
     }
 
+    fn is_listener_stopped(&self) -> bool {
+        self.shutdown_requested
+    }
+
+    fn get_connection_count(&self) -> usize {
+        self.proxy_keys.len()
+    }
+
     fn shutdown_connections(
         &mut self,
         proxy_tasks_sender: Sender<ProxyExecutorEvent>,
@@ -220,7 +252,7 @@ impl ClientServiceProxyVisitor for TcpClientProxyServerVisitor {
                 errors.push(format!("Error while sending request to close a TCP proxy connection: proxy_stream={}, err={:?}", &proxy_key, err));
             }
 
-            self.services_by_proxy_key.lock().unwrap().remove(proxy_key);
+            self.services_by_proxy_key.remove(proxy_key);
         }
 
         if errors.is_empty() {
@@ -235,16 +267,11 @@ impl ClientServiceProxyVisitor for TcpClientProxyServerVisitor {
         Ok(())
     }
 
-    fn remove_proxy_for_key(&mut self, proxy_key: &str) -> bool {
-        let proxy_key = proxy_key.to_string();
-
-        return match self.proxy_keys.contains(&proxy_key) {
+    fn remove_proxy_for_key(&mut self, proxy_key: &ProxyKey) -> bool {
+        return match self.proxy_keys.contains(proxy_key) {
             true => {
-                self.services_by_proxy_key
-                    .lock()
-                    .unwrap()
-                    .remove(&proxy_key);
-                self.proxy_keys.remove(&proxy_key);
+                self.services_by_proxy_key.remove(proxy_key);
+                self.proxy_keys.remove(proxy_key);
                 true
             }
 
@@ -1,76 +1,116 @@
 use std::collections::{HashMap, HashSet};
+use std::io;
 use std::net::{SocketAddr, UdpSocket};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
 
 use anyhow::Result;
 
-use crate::config::AppConfig;
+use crate::config::{AppConfig, UdpReplyMode};
 use crate::service::proxy::proxy_base::{ClientServiceProxy, ClientServiceProxyVisitor};
 use crate::service::proxy::proxy_client::ClientVisitor;
 use trust0_common::crypto::alpn;
 use trust0_common::error::AppError;
-use trust0_common::logging::error;
+use trust0_common::logging::{debug, error};
 use trust0_common::model::service::Service;
 use trust0_common::net::tls_client::client_std;
 use trust0_common::net::tls_client::conn_std::TlsClientConnection;
 use trust0_common::net::udp_server::server_std;
 use trust0_common::net::udp_server::server_std::Server;
 use trust0_common::proxy::event::ProxyEvent;
-use trust0_common::proxy::executor::{ProxyExecutorEvent, ProxyKey};
+use trust0_common::proxy::executor::ProxyExecutorEvent;
 use trust0_common::proxy::proxy_base::ProxyType;
+use trust0_common::proxy::proxy_key::ProxyKey;
+use trust0_common::proxy::proxy_key_store::ProxyKeyStore;
 use trust0_common::target;
 
+const PEER_SOCKET_READ_TIMEOUT_MSECS: u64 = 1000;
+const PEER_SOCKET_RECV_BUFFER_SIZE: usize = 64 * 1024;
+
 /// Client service proxy (UDP service client <-> TCP trust0 client)
 pub struct UdpClientProxy {
     udp_server: server_std::Server,
-    server_socket_channel_receiver: Arc<Mutex<Receiver<ProxyEvent>>>,
+    server_socket: Option<UdpSocket>,
+    server_socket_channel_receiver: Receiver<ProxyEvent>,
+    peer_sockets_by_proxy_key: Arc<Mutex<HashMap<ProxyKey, UdpSocket>>>,
     _server_visitor: Arc<Mutex<UdpClientProxyServerVisitor>>,
 }
 
 impl UdpClientProxy {
     /// UdpClientProxy constructor
     pub fn new(
-        _app_config: Arc<AppConfig>,
+        app_config: Arc<AppConfig>,
         server_socket_channel_receiver: Receiver<ProxyEvent>,
         server_visitor: Arc<Mutex<UdpClientProxyServerVisitor>>,
         proxy_port: u16,
     ) -> Result<Self, AppError> {
+        let mut udp_server = server_std::Server::new(server_visitor.clone(), proxy_port)?;
+        udp_server.set_reuse_port(app_config.udp_reply_mode == UdpReplyMode::PerPeer);
+        let peer_sockets_by_proxy_key = server_visitor
+            .lock()
+            .unwrap()
+            .peer_sockets_by_proxy_key
+            .clone();
+
         Ok(Self {
-            udp_server: server_std::Server::new(server_visitor.clone(), proxy_port)?,
-            server_socket_channel_receiver: Arc::new(Mutex::new(server_socket_channel_receiver)),
+            udp_server,
+            server_socket: None,
+            server_socket_channel_receiver,
+            peer_sockets_by_proxy_key,
             _server_visitor: server_visitor,
         })
     }
 
-    /// Startup client-bound message processor thread
-    fn spawn_client_bound_message_processor(&self, server_socket: UdpSocket) {
-        let server_socket_channel_receiver = self.server_socket_channel_receiver.clone();
+    /// Send client-bound message. Uses the peer's dedicated socket (if available), else the server socket.
+    fn send_client_bound_message(
+        server_socket: &UdpSocket,
+        peer_sockets_by_proxy_key: &Arc<Mutex<HashMap<ProxyKey, UdpSocket>>>,
+        proxy_key: &ProxyKey,
+        socket_addr: &SocketAddr,
+        data: &Vec<u8>,
+    ) -> Result<usize, AppError> {
+        match peer_sockets_by_proxy_key.lock().unwrap().get(proxy_key) {
+            Some(peer_socket) => peer_socket.send(data.as_slice()).map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    format!(
+                        "Error while sending message on UDP peer socket: dest={:?}",
+                        socket_addr
+                    ),
+                    Box::new(err),
+                )
+            }),
+            None => Server::send_message(server_socket, socket_addr, data),
+        }
+    }
 
-        thread::spawn(move || loop {
-            match server_socket_channel_receiver.lock().unwrap().recv() {
-                Err(err) => error(
-                    &target!(),
-                    &format!("Error receiving socket event: err={:?}", err),
-                ),
+    /// Relay (queued) client-bound messages, until none remain
+    fn relay_client_bound_messages(&self) {
+        let server_socket = match &self.server_socket {
+            Some(server_socket) => server_socket,
+            None => return,
+        };
 
-                Ok(proxy_event) => {
-                    if let ProxyEvent::Message(proxy_key, socket_addr, data) = proxy_event {
-                        if let Err(err) = Server::send_message(&server_socket, &socket_addr, &data)
-                        {
-                            error(
-                                &target!(),
-                                &format!(
-                                    "Error processing message channel: proxy_stream={}, err={:?}",
-                                    &proxy_key, &err
-                                ),
-                            );
-                        }
-                    }
+        while let Ok(proxy_event) = self.server_socket_channel_receiver.try_recv() {
+            if let ProxyEvent::Message(proxy_key, socket_addr, data) = proxy_event {
+                if let Err(err) = Self::send_client_bound_message(
+                    server_socket,
+                    &self.peer_sockets_by_proxy_key,
+                    &proxy_key,
+                    &socket_addr,
+                    &data,
+                ) {
+                    error(
+                        &target!(),
+                        &format!(
+                            "Error processing message channel: proxy_stream={}, err={:?}",
+                            &proxy_key, &err
+                        ),
+                    );
                 }
             }
-        });
+        }
     }
 }
 
@@ -78,12 +118,24 @@ impl ClientServiceProxy for UdpClientProxy {
     fn startup(&mut self) -> Result<(), AppError> {
         // bind UDP (server) socket
         self.udp_server.bind_listener()?;
+        self.server_socket = Some(self.udp_server.clone_server_socket()?);
+
+        self.udp_server.start_polling()
+    }
 
-        // Start thread to listen/relay client-destined messages
-        self.spawn_client_bound_message_processor(self.udp_server.clone_server_socket()?);
+    fn poll_connections(&mut self) -> Result<bool, AppError> {
+        // Relay client-destined messages
+        self.relay_client_bound_messages();
 
-        // Poll for new service-destined messages (blocking)
-        self.udp_server.poll_new_messages()
+        // Poll for new service-destined messages
+        let polling = self.udp_server.poll_once()?;
+
+        // Release the cloned server socket once polling has ended
+        if !polling {
+            self.server_socket = None;
+        }
+
+        Ok(polling)
     }
 }
 
@@ -99,8 +151,9 @@ pub struct UdpClientProxyServerVisitor {
     server_socket_channel_sender: Sender<ProxyEvent>,
     proxy_tasks_sender: Sender<ProxyExecutorEvent>,
     proxy_events_sender: Sender<ProxyEvent>,
-    services_by_proxy_key: Arc<Mutex<HashMap<String, u64>>>,
-    socket_channel_senders_by_proxy_key: HashMap<String, Sender<ProxyEvent>>,
+    services_by_proxy_key: Arc<dyn ProxyKeyStore>,
+    socket_channel_senders_by_proxy_key: HashMap<ProxyKey, Sender<ProxyEvent>>,
+    peer_sockets_by_proxy_key: Arc<Mutex<HashMap<ProxyKey, UdpSocket>>>,
     proxy_keys: HashSet<ProxyKey>,
     shutdown_requested: bool,
 }
@@ -117,7 +170,7 @@ impl UdpClientProxyServerVisitor {
         server_socket_channel_sender: Sender<ProxyEvent>,
         proxy_tasks_sender: Sender<ProxyExecutorEvent>,
         proxy_events_sender: Sender<ProxyEvent>,
-        services_by_proxy_key: Arc<Mutex<HashMap<String, u64>>>,
+        services_by_proxy_key: Arc<dyn ProxyKeyStore>,
     ) -> Result<Self, AppError> {
         Ok(Self {
             app_config,
@@ -130,10 +183,108 @@ impl UdpClientProxyServerVisitor {
             proxy_events_sender,
             services_by_proxy_key,
             socket_channel_senders_by_proxy_key: HashMap::new(),
+            peer_sockets_by_proxy_key: Arc::new(Mutex::new(HashMap::new())),
             proxy_keys: HashSet::new(),
             shutdown_requested: false,
         })
     }
+
+    /// Setup socket dedicated to the given peer (bound to the client proxy port), and startup thread to relay
+    /// the peer's (service-bound) messages, which will now be delivered to this socket
+    fn setup_peer_socket(
+        &mut self,
+        proxy_key: &ProxyKey,
+        local_addr: &SocketAddr,
+        peer_addr: &SocketAddr,
+        socket_channel_sender: Sender<ProxyEvent>,
+    ) -> Result<(), AppError> {
+        let peer_socket = Server::bind_peer_socket(local_addr, peer_addr)?;
+        let peer_socket_reader = peer_socket.try_clone().map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                format!("Failed to clone UDP peer socket: peer_addr={:?}", peer_addr),
+                Box::new(err),
+            )
+        })?;
+        peer_socket_reader
+            .set_read_timeout(Some(Duration::from_millis(PEER_SOCKET_READ_TIMEOUT_MSECS)))
+            .map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    format!(
+                        "Failed setting UDP peer socket read timeout: peer_addr={:?}",
+                        peer_addr
+                    ),
+                    Box::new(err),
+                )
+            })?;
+
+        self.peer_sockets_by_proxy_key
+            .lock()
+            .unwrap()
+            .insert(proxy_key.clone(), peer_socket);
+
+        Self::spawn_peer_socket_reader(
+            proxy_key.clone(),
+            *peer_addr,
+            self.service.forward_empty_datagrams,
+            peer_socket_reader,
+            socket_channel_sender,
+            self.peer_sockets_by_proxy_key.clone(),
+        );
+
+        Ok(())
+    }
+
+    /// Startup thread to relay service-bound messages received on a peer socket. Runs until the peer socket
+    /// is removed (or the socket channel is closed). Zero-length messages are dropped, unless forwarding is requested.
+    fn spawn_peer_socket_reader(
+        proxy_key: ProxyKey,
+        peer_addr: SocketAddr,
+        forward_empty_datagrams: bool,
+        peer_socket: UdpSocket,
+        socket_channel_sender: Sender<ProxyEvent>,
+        peer_sockets_by_proxy_key: Arc<Mutex<HashMap<ProxyKey, UdpSocket>>>,
+    ) {
+        thread::spawn(move || {
+            let mut buffer = vec![0; PEER_SOCKET_RECV_BUFFER_SIZE];
+
+            while peer_sockets_by_proxy_key
+                .lock()
+                .unwrap()
+                .contains_key(&proxy_key)
+            {
+                match peer_socket.recv(&mut buffer) {
+                    Ok(0) if !forward_empty_datagrams => {}
+                    Ok(message_size) => {
+                        if socket_channel_sender
+                            .send(ProxyEvent::Message(
+                                proxy_key.clone(),
+                                peer_addr,
+                                buffer[..message_size].to_vec(),
+                            ))
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Err(err)
+                        if err.kind() == io::ErrorKind::WouldBlock
+                            || err.kind() == io::ErrorKind::TimedOut => {}
+                    Err(err) => {
+                        error(
+                            &target!(),
+                            &format!(
+                                "Error receiving UDP peer socket message: proxy_stream={}, err={:?}",
+                                &proxy_key, &err
+                            ),
+                        );
+                        break;
+                    }
+                }
+            }
+
+            peer_sockets_by_proxy_key.lock().unwrap().remove(&proxy_key);
+        });
+    }
 }
 
 impl server_std::ServerVisitor for UdpClientProxyServerVisitor {
@@ -143,8 +294,9 @@ impl server_std::ServerVisitor for UdpClientProxyServerVisitor {
         peer_addr: &SocketAddr,
         data: Vec<u8>,
     ) -> Result<(), AppError> {
-        let proxy_key = ProxyEvent::key_value(
-            &ProxyType::ChannelAndTcp,
+        let proxy_key = ProxyKey::new(
+            ProxyType::ChannelAndTcp,
+            self.service.service_id,
             Some(*peer_addr),
             Some(*local_addr),
         );
@@ -155,6 +307,14 @@ impl server_std::ServerVisitor for UdpClientProxyServerVisitor {
             .socket_channel_senders_by_proxy_key
             .contains_key(&proxy_key)
         {
+            // Reject new client sockets after shutdown was requested
+            if self.shutdown_requested {
+                return Err(AppError::General(format!(
+                    "Service proxy shutting down, rejecting client: svc_id={}, peer_addr={:?}",
+                    self.service.service_id, peer_addr
+                )));
+            }
+
             let (socket_channel_sender, socket_channel_receiver) = mpsc::channel();
 
             // Make connection to gateway proxy
@@ -209,13 +369,21 @@ impl server_std::ServerVisitor for UdpClientProxyServerVisitor {
                     ))
                 })?;
 
+            // Setup dedicated peer socket (if configured)
+            if self.app_config.udp_reply_mode == UdpReplyMode::PerPeer {
+                self.setup_peer_socket(
+                    &proxy_key,
+                    local_addr,
+                    peer_addr,
+                    socket_channel_sender.clone(),
+                )?;
+            }
+
             // Setup proxy maps
             self.socket_channel_senders_by_proxy_key
                 .insert(proxy_key.clone(), socket_channel_sender);
             self.services_by_proxy_key
-                .lock()
-                .unwrap()
-                .insert(proxy_key.clone(), self.service.service_id);
+                .put(&proxy_key, self.service.service_id);
             self.proxy_keys.insert(proxy_key.clone());
         }
 
@@ -242,6 +410,25 @@ impl server_std::ServerVisitor for UdpClientProxyServerVisitor {
         Ok(())
     }
 
+    fn on_empty_message_received(
+        &mut self,
+        local_addr: &SocketAddr,
+        peer_addr: &SocketAddr,
+    ) -> Result<(), AppError> {
+        if self.service.forward_empty_datagrams {
+            return self.on_message_received(local_addr, peer_addr, vec![]);
+        }
+
+        debug(
+            &target!(),
+            &format!(
+                "Dropping zero-length client message: svc_id={}, peer_addr={:?}",
+                self.service.service_id, peer_addr
+            ),
+        );
+        Ok(())
+    }
+
     fn get_shutdown_requested(&self) -> bool {
         self.shutdown_requested
     }
@@ -268,6 +455,14 @@ impl ClientServiceProxyVisitor for UdpClientProxyServerVisitor {
         self.shutdown_requested = true;
     }
 
+    fn is_listener_stopped(&self) -> bool {
+        self.shutdown_requested
+    }
+
+    fn get_connection_count(&self) -> usize {
+        self.proxy_keys.len()
+    }
+
     fn shutdown_connections(
         &mut self,
         proxy_tasks_sender: Sender<ProxyExecutorEvent>,
@@ -280,7 +475,11 @@ impl ClientServiceProxyVisitor for UdpClientProxyServerVisitor {
                 errors.push(format!("Error while sending request to close a UDP proxy connection: proxy_stream={}, err={:?}", &proxy_key, err));
             }
 
-            self.services_by_proxy_key.lock().unwrap().remove(proxy_key);
+            self.services_by_proxy_key.remove(proxy_key);
+            self.peer_sockets_by_proxy_key
+                .lock()
+                .unwrap()
+                .remove(proxy_key);
         }
 
         if errors.is_empty() {
@@ -295,16 +494,15 @@ impl ClientServiceProxyVisitor for UdpClientProxyServerVisitor {
         Ok(())
     }
 
-    fn remove_proxy_for_key(&mut self, proxy_key: &str) -> bool {
-        let proxy_key = proxy_key.to_string();
-
-        return match self.proxy_keys.contains(&proxy_key) {
+    fn remove_proxy_for_key(&mut self, proxy_key: &ProxyKey) -> bool {
+        return match self.proxy_keys.contains(proxy_key) {
             true => {
-                self.services_by_proxy_key
+                self.services_by_proxy_key.remove(proxy_key);
+                self.peer_sockets_by_proxy_key
                     .lock()
                     .unwrap()
-                    .remove(&proxy_key);
-                self.proxy_keys.remove(&proxy_key);
+                    .remove(proxy_key);
+                self.proxy_keys.remove(proxy_key);
                 true
             }
 
@@ -312,3 +510,263 @@ impl ClientServiceProxyVisitor for UdpClientProxyServerVisitor {
         };
     }
 }
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::config;
+    use server_std::ServerVisitor;
+    use trust0_common::model::service::Transport;
+    use trust0_common::proxy::proxy_key_store::InMemProxyKeyStore;
+
+    const RECV_TIMEOUT_MSECS: u64 = 2000;
+
+    fn create_server_visitor(forward_empty_datagrams: bool) -> UdpClientProxyServerVisitor {
+        let mut service = Service::new(200, "svc200", &Transport::UDP, "localhost", 8200);
+        service.forward_empty_datagrams = forward_empty_datagrams;
+
+        UdpClientProxyServerVisitor::new(
+            Arc::new(config::tests::create_app_config(None).unwrap()),
+            service,
+            3000,
+            "gwhost1",
+            2000,
+            mpsc::channel().0,
+            mpsc::channel().0,
+            mpsc::channel().0,
+            Arc::new(InMemProxyKeyStore::new()),
+        )
+        .unwrap()
+    }
+
+    fn create_peer_socket() -> UdpSocket {
+        let peer_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer_socket
+            .set_read_timeout(Some(Duration::from_millis(RECV_TIMEOUT_MSECS)))
+            .unwrap();
+        peer_socket
+    }
+
+    #[test]
+    fn udpcliproxy_send_client_bound_message_when_listener_reply_mode() {
+        let server_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = server_socket.local_addr().unwrap();
+        let peer_socket = create_peer_socket();
+        let peer_addr = peer_socket.local_addr().unwrap();
+        let proxy_key = ProxyKey::new(ProxyType::ChannelAndTcp, 200, Some(peer_addr), None);
+        let peer_sockets_by_proxy_key = Arc::new(Mutex::new(HashMap::new()));
+
+        let result = UdpClientProxy::send_client_bound_message(
+            &server_socket,
+            &peer_sockets_by_proxy_key,
+            &proxy_key,
+            &peer_addr,
+            &"reply1".as_bytes().to_vec(),
+        );
+
+        if let Err(err) = result {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+
+        let mut buffer = [0u8; 64];
+        let (message_size, source_addr) = peer_socket.recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..message_size], "reply1".as_bytes());
+        assert_eq!(source_addr, server_addr);
+    }
+
+    #[test]
+    fn udpcliproxy_send_client_bound_message_when_per_peer_reply_mode() {
+        let server_socket = Server::bind_reusable_socket(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        server_socket
+            .set_read_timeout(Some(Duration::from_millis(200)))
+            .unwrap();
+        let server_addr = server_socket.local_addr().unwrap();
+        let peer_socket = create_peer_socket();
+        let peer_addr = peer_socket.local_addr().unwrap();
+        let proxy_key = ProxyKey::new(ProxyType::ChannelAndTcp, 200, Some(peer_addr), None);
+        let dedicated_socket = Server::bind_peer_socket(&server_addr, &peer_addr).unwrap();
+        dedicated_socket
+            .set_read_timeout(Some(Duration::from_millis(RECV_TIMEOUT_MSECS)))
+            .unwrap();
+        let dedicated_socket_reader = dedicated_socket.try_clone().unwrap();
+        let peer_sockets_by_proxy_key = Arc::new(Mutex::new(HashMap::from([(
+            proxy_key.clone(),
+            dedicated_socket,
+        )])));
+
+        let result = UdpClientProxy::send_client_bound_message(
+            &server_socket,
+            &peer_sockets_by_proxy_key,
+            &proxy_key,
+            &peer_addr,
+            &"reply1".as_bytes().to_vec(),
+        );
+
+        if let Err(err) = result {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+
+        let mut buffer = [0u8; 64];
+        let (message_size, source_addr) = peer_socket.recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..message_size], "reply1".as_bytes());
+        assert_eq!(source_addr, server_addr);
+
+        peer_socket.send_to("msg2".as_bytes(), source_addr).unwrap();
+
+        let message_size = dedicated_socket_reader.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..message_size], "msg2".as_bytes());
+        assert!(server_socket.recv_from(&mut buffer).is_err());
+    }
+
+    #[test]
+    fn udpcliproxysvrvisit_on_empty_message_received_when_forwarding() {
+        let local_addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let peer_addr: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let proxy_key = ProxyKey::new(
+            ProxyType::ChannelAndTcp,
+            200,
+            Some(peer_addr),
+            Some(local_addr),
+        );
+        let (socket_channel_sender, socket_channel_receiver) = mpsc::channel();
+        let mut server_visitor = create_server_visitor(true);
+        server_visitor
+            .socket_channel_senders_by_proxy_key
+            .insert(proxy_key.clone(), socket_channel_sender);
+
+        if let Err(err) = server_visitor.on_empty_message_received(&local_addr, &peer_addr) {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+        if let Err(err) =
+            server_visitor.on_message_received(&local_addr, &peer_addr, "msg1".as_bytes().to_vec())
+        {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+
+        for expected_data in [vec![], "msg1".as_bytes().to_vec()] {
+            match socket_channel_receiver.try_recv() {
+                Ok(ProxyEvent::Message(event_key, event_addr, data)) => {
+                    assert_eq!(event_key, proxy_key);
+                    assert_eq!(event_addr, peer_addr);
+                    assert_eq!(data, expected_data);
+                }
+                Ok(_) => panic!("Unexpected proxy event"),
+                Err(err) => panic!("Unexpected channel result: err={:?}", &err),
+            }
+        }
+        assert!(socket_channel_receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn udpcliproxysvrvisit_on_empty_message_received_when_not_forwarding() {
+        let local_addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let peer_addr: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let proxy_key = ProxyKey::new(
+            ProxyType::ChannelAndTcp,
+            200,
+            Some(peer_addr),
+            Some(local_addr),
+        );
+        let (socket_channel_sender, socket_channel_receiver) = mpsc::channel();
+        let mut server_visitor = create_server_visitor(false);
+        server_visitor
+            .socket_channel_senders_by_proxy_key
+            .insert(proxy_key.clone(), socket_channel_sender);
+
+        if let Err(err) = server_visitor.on_empty_message_received(&local_addr, &peer_addr) {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+        if let Err(err) =
+            server_visitor.on_message_received(&local_addr, &peer_addr, "msg1".as_bytes().to_vec())
+        {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+
+        match socket_channel_receiver.try_recv() {
+            Ok(ProxyEvent::Message(event_key, event_addr, data)) => {
+                assert_eq!(event_key, proxy_key);
+                assert_eq!(event_addr, peer_addr);
+                assert_eq!(data, "msg1".as_bytes().to_vec());
+            }
+            Ok(_) => panic!("Unexpected proxy event"),
+            Err(err) => panic!("Unexpected channel result: err={:?}", &err),
+        }
+        assert!(socket_channel_receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn udpcliproxysvrvisit_spawn_peer_socket_reader_when_messages_then_removed() {
+        let server_socket = Server::bind_reusable_socket(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let server_addr = server_socket.local_addr().unwrap();
+        let peer_socket = create_peer_socket();
+        let peer_addr = peer_socket.local_addr().unwrap();
+        let proxy_key = ProxyKey::new(ProxyType::ChannelAndTcp, 200, Some(peer_addr), None);
+        let dedicated_socket = Server::bind_peer_socket(&server_addr, &peer_addr).unwrap();
+        let dedicated_socket_reader = dedicated_socket.try_clone().unwrap();
+        dedicated_socket_reader
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        let peer_sockets_by_proxy_key = Arc::new(Mutex::new(HashMap::from([(
+            proxy_key.clone(),
+            dedicated_socket,
+        )])));
+        let (socket_channel_sender, socket_channel_receiver) = mpsc::channel();
+
+        UdpClientProxyServerVisitor::spawn_peer_socket_reader(
+            proxy_key.clone(),
+            peer_addr,
+            false,
+            dedicated_socket_reader,
+            socket_channel_sender,
+            peer_sockets_by_proxy_key.clone(),
+        );
+
+        peer_socket.send_to(&[], server_addr).unwrap();
+        peer_socket.send_to("msg1".as_bytes(), server_addr).unwrap();
+
+        match socket_channel_receiver.recv_timeout(Duration::from_millis(RECV_TIMEOUT_MSECS)) {
+            Ok(ProxyEvent::Message(event_key, event_addr, data)) => {
+                assert_eq!(event_key, proxy_key);
+                assert_eq!(event_addr, peer_addr);
+                assert_eq!(data, "msg1".as_bytes().to_vec());
+            }
+            Ok(_) => panic!("Unexpected proxy event"),
+            Err(err) => panic!("Unexpected channel result: err={:?}", &err),
+        }
+
+        peer_sockets_by_proxy_key.lock().unwrap().remove(&proxy_key);
+
+        if socket_channel_receiver
+            .recv_timeout(Duration::from_millis(RECV_TIMEOUT_MSECS))
+            .is_ok()
+        {
+            panic!("Unexpected proxy event");
+        }
+    }
+
+    #[test]
+    fn udpcliproxysvrvisit_on_message_received_when_shutdown_requested() {
+        let local_addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let peer_addr: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let (proxy_tasks_sender, proxy_tasks_receiver) = mpsc::channel();
+        let mut server_visitor = create_server_visitor(false);
+        server_visitor.proxy_tasks_sender = proxy_tasks_sender;
+        server_visitor.set_shutdown_requested();
+
+        if server_visitor
+            .on_message_received(&local_addr, &peer_addr, "msg1".as_bytes().to_vec())
+            .is_ok()
+        {
+            panic!("Unexpected successful result");
+        }
+
+        assert!(proxy_tasks_receiver.try_recv().is_err());
+        assert!(server_visitor.proxy_keys.is_empty());
+        assert!(server_visitor
+            .socket_channel_senders_by_proxy_key
+            .is_empty());
+        assert!(server_visitor.services_by_proxy_key.is_empty());
+    }
+}
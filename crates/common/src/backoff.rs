@@ -0,0 +1,188 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Retry delay strategy, shared by retrying operations (upstream dials, reloads, reconnects, ...)
+pub trait BackoffStrategy: Send + Sync {
+    /// Delay to wait before the given retry attempt (the first retry is attempt 1)
+    fn next_delay(&self, attempt: u32) -> Duration;
+}
+
+/// Same delay before every retry attempt
+#[derive(Clone, Debug)]
+pub struct FixedBackoff {
+    delay: Duration,
+}
+
+impl FixedBackoff {
+    /// FixedBackoff constructor
+    pub fn new(delay: Duration) -> Self {
+        Self { delay }
+    }
+}
+
+impl BackoffStrategy for FixedBackoff {
+    fn next_delay(&self, _attempt: u32) -> Duration {
+        self.delay
+    }
+}
+
+/// Delay grows by `multiplier` for each retry attempt (starting at `initial_delay`), never exceeding `max_delay`
+#[derive(Clone, Debug)]
+pub struct ExponentialBackoff {
+    initial_delay: Duration,
+    multiplier: u32,
+    max_delay: Duration,
+}
+
+impl ExponentialBackoff {
+    /// ExponentialBackoff constructor
+    pub fn new(initial_delay: Duration, multiplier: u32, max_delay: Duration) -> Self {
+        Self {
+            initial_delay,
+            multiplier,
+            max_delay,
+        }
+    }
+}
+
+impl BackoffStrategy for ExponentialBackoff {
+    fn next_delay(&self, attempt: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .checked_pow(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+
+        self.initial_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+/// Exponential backoff with "full" jitter: a random delay between zero and the exponential delay (for the attempt).
+/// Spreads out retries from many clients, which failed at the same time.
+#[derive(Clone, Debug)]
+pub struct ExponentialJitterBackoff {
+    exponential_backoff: ExponentialBackoff,
+}
+
+impl ExponentialJitterBackoff {
+    /// ExponentialJitterBackoff constructor
+    pub fn new(initial_delay: Duration, multiplier: u32, max_delay: Duration) -> Self {
+        Self {
+            exponential_backoff: ExponentialBackoff::new(initial_delay, multiplier, max_delay),
+        }
+    }
+
+    /// Random value (std's hasher keys are randomly seeded per instance)
+    fn random_u64() -> u64 {
+        RandomState::new().build_hasher().finish()
+    }
+}
+
+impl BackoffStrategy for ExponentialJitterBackoff {
+    fn next_delay(&self, attempt: u32) -> Duration {
+        let max_delay_nanos = self.exponential_backoff.next_delay(attempt).as_nanos() as u64;
+
+        Duration::from_nanos(Self::random_u64() % max_delay_nanos.saturating_add(1))
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn delay_sequence(backoff: &dyn BackoffStrategy, attempts: u32) -> Vec<Duration> {
+        (1..=attempts)
+            .map(|attempt| backoff.next_delay(attempt))
+            .collect()
+    }
+
+    #[test]
+    fn fixedbackoff_next_delay() {
+        let backoff = FixedBackoff::new(Duration::from_millis(250));
+
+        assert_eq!(
+            delay_sequence(&backoff, 4),
+            vec![Duration::from_millis(250); 4]
+        );
+    }
+
+    #[test]
+    fn expbackoff_next_delay() {
+        let backoff =
+            ExponentialBackoff::new(Duration::from_millis(100), 2, Duration::from_secs(60));
+
+        assert_eq!(
+            delay_sequence(&backoff, 5),
+            vec![
+                Duration::from_millis(100),
+                Duration::from_millis(200),
+                Duration::from_millis(400),
+                Duration::from_millis(800),
+                Duration::from_millis(1600),
+            ]
+        );
+    }
+
+    #[test]
+    fn expbackoff_next_delay_when_max_delay_reached() {
+        let backoff =
+            ExponentialBackoff::new(Duration::from_millis(100), 3, Duration::from_millis(1000));
+
+        assert_eq!(
+            delay_sequence(&backoff, 5),
+            vec![
+                Duration::from_millis(100),
+                Duration::from_millis(300),
+                Duration::from_millis(900),
+                Duration::from_millis(1000),
+                Duration::from_millis(1000),
+            ]
+        );
+        assert_eq!(backoff.next_delay(u32::MAX), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn expjitterbackoff_next_delay_within_bounds() {
+        let backoff = ExponentialJitterBackoff::new(
+            Duration::from_millis(100),
+            2,
+            Duration::from_millis(1000),
+        );
+
+        for attempt in 1..=6 {
+            let max_delay =
+                Duration::from_millis(100 * 2u64.pow(attempt - 1)).min(Duration::from_millis(1000));
+            for _ in 0..50 {
+                let delay = backoff.next_delay(attempt);
+                if delay > max_delay {
+                    panic!(
+                        "Delay exceeds bound: attempt={}, delay={:?}, max={:?}",
+                        attempt, delay, max_delay
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn expjitterbackoff_next_delay_is_jittered() {
+        let backoff =
+            ExponentialJitterBackoff::new(Duration::from_secs(1), 2, Duration::from_secs(60));
+
+        let delays: Vec<Duration> = (0..20).map(|_| backoff.next_delay(3)).collect();
+
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+    }
+
+    #[test]
+    fn expjitterbackoff_next_delay_when_zero_delay() {
+        let backoff = ExponentialJitterBackoff::new(Duration::ZERO, 2, Duration::from_secs(1));
+
+        assert_eq!(delay_sequence(&backoff, 3), vec![Duration::ZERO; 3]);
+    }
+}
@@ -0,0 +1,118 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Time source for time-based features. Interval/expiry checks (for instance, service re-authorization) must use
+/// the monotonic `now()`, so wall clock changes neither extend nor retroactively revoke access. Wall clock time is
+/// only used for timestamps (for instance, connection events) and may jump in either direction.
+pub trait Clock: Send + Sync {
+    /// Monotonic time (unaffected by wall clock changes)
+    fn now(&self) -> Instant;
+
+    /// Wall clock time
+    fn system_time(&self) -> SystemTime;
+
+    /// Wall clock time as milliseconds since the UNIX epoch (a wall clock set prior to the epoch yields 0)
+    fn unix_millis(&self) -> u64 {
+        self.system_time()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64)
+    }
+}
+
+/// Clock using the system's monotonic and wall clocks
+#[derive(Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock whose times only change when explicitly advanced (or, for the wall clock, set). Used to simulate
+/// elapsed time and wall clock jumps.
+pub struct ManualClock {
+    times: Mutex<(Instant, SystemTime)>,
+}
+
+impl ManualClock {
+    /// ManualClock constructor (starting at the current system times)
+    pub fn new() -> Self {
+        Self {
+            times: Mutex::new((Instant::now(), SystemTime::now())),
+        }
+    }
+
+    /// Advance both monotonic and wall clocks by given duration
+    pub fn advance(&self, duration: Duration) {
+        let mut times = self.times.lock().unwrap();
+        times.0 += duration;
+        times.1 += duration;
+    }
+
+    /// Set wall clock to given time (monotonic clock is unaffected)
+    pub fn set_system_time(&self, system_time: SystemTime) {
+        self.times.lock().unwrap().1 = system_time;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.times.lock().unwrap().0
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.times.lock().unwrap().1
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn manualclock_advance() {
+        let clock = ManualClock::new();
+        let (start_instant, start_system_time) = (clock.now(), clock.system_time());
+
+        clock.advance(Duration::from_secs(30));
+
+        assert_eq!(clock.now(), start_instant + Duration::from_secs(30));
+        assert_eq!(
+            clock.system_time(),
+            start_system_time + Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn manualclock_set_system_time_when_backward_jump() {
+        let clock = ManualClock::new();
+        let start_instant = clock.now();
+
+        clock.set_system_time(UNIX_EPOCH + Duration::from_millis(1500));
+
+        assert_eq!(clock.now(), start_instant);
+        assert_eq!(clock.unix_millis(), 1500);
+    }
+
+    #[test]
+    fn clock_unix_millis_when_before_epoch() {
+        let clock = ManualClock::new();
+
+        clock.set_system_time(UNIX_EPOCH - Duration::from_secs(60));
+
+        assert_eq!(clock.unix_millis(), 0);
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::hash_map::RandomState;
+use std::fs::{File, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::Write;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Mutex;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::clock::Clock;
+use crate::error::AppError;
+use crate::logging::debug;
+use crate::net::tls_server::conn_std::TlsSessionInfo;
+use crate::target;
+
+/// Connection lifecycle event type
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnEventType {
+    /// Connection authorized and opened
+    ConnectionOpened,
+    /// Connection closed
+    ConnectionClosed,
+    /// Connection authorization allowed
+    AuthAllowed,
+    /// Connection authorization denied
+    AuthDenied,
+}
+
+/// Connection lifecycle event (for security/audit ingestion, distinct from human-readable logs)
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ConnEvent {
+    /// Event (wall clock) time, in milliseconds since UNIX epoch. Reflects wall clock changes, so consecutive
+    /// events may not be in timestamp order after a backward clock jump
+    pub timestamp: u64,
+    pub event_type: ConnEventType,
+    pub user_id: Option<u64>,
+    pub service_id: Option<u64>,
+    pub client_addr: Option<String>,
+    pub response_code: Option<u16>,
+    /// Denial reason (for authorization denied events)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Negotiated TLS protocol version (for connection opened events)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_protocol_version: Option<String>,
+    /// Negotiated TLS cipher suite (for connection opened events)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_cipher_suite: Option<String>,
+    /// Negotiated ALPN protocol (for connection opened events)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpn_protocol: Option<String>,
+    /// Connection trace ID (shared by all events for the connection)
+    pub trace_id: String,
+}
+
+impl ConnEvent {
+    /// ConnEvent constructor (event is timestamped as of the clock's current wall clock time)
+    pub fn new(
+        clock: &dyn Clock,
+        event_type: ConnEventType,
+        user_id: Option<u64>,
+        service_id: Option<u64>,
+        client_addr: Option<SocketAddr>,
+        response_code: Option<u16>,
+        trace_id: &str,
+    ) -> Self {
+        Self {
+            timestamp: clock.unix_millis(),
+            event_type,
+            user_id,
+            service_id,
+            client_addr: client_addr.map(|addr| addr.to_string()),
+            response_code,
+            reason: None,
+            tls_protocol_version: None,
+            tls_cipher_suite: None,
+            alpn_protocol: None,
+            trace_id: trace_id.to_string(),
+        }
+    }
+
+    /// Set denial reason
+    pub fn set_reason(&mut self, reason: Option<String>) {
+        self.reason = reason;
+    }
+
+    /// Set negotiated TLS session parameters (protocol version, cipher suite and ALPN protocol)
+    pub fn set_tls_session_info(&mut self, tls_session_info: &TlsSessionInfo) {
+        self.tls_protocol_version = tls_session_info.protocol_version.clone();
+        self.tls_cipher_suite = tls_session_info.cipher_suite.clone();
+        self.alpn_protocol = tls_session_info.alpn_protocol.clone();
+    }
+
+    /// Serialize event as an NDJSON line (JSON object, newline terminated)
+    pub fn to_ndjson(&self) -> Result<String, AppError> {
+        serde_json::to_string(self)
+            .map(|json| format!("{}\n", json))
+            .map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    "Error serializing connection event".to_string(),
+                    Box::new(err),
+                )
+            })
+    }
+}
+
+/// Denial reason for a connection authorization error (its message, without the response code)
+pub fn denial_reason(err: &AppError) -> String {
+    match err {
+        AppError::GenWithCodeAndMsg(_, msg) | AppError::GenWithCodeAndMsgAndErr(_, msg, _) => {
+            msg.clone()
+        }
+        _ => err.to_string(),
+    }
+}
+
+/// Create new (random) connection trace ID
+pub fn create_trace_id() -> String {
+    format!("{:016x}", RandomState::new().build_hasher().finish())
+}
+
+/// Destination for connection lifecycle events. Implementations must not fail (or block) the caller.
+pub trait ConnEventSink: Send + Sync {
+    /// Emit event
+    fn emit(&self, event: &ConnEvent);
+}
+
+/// Connection event sink, which discards all events
+#[derive(Default)]
+pub struct NoOpConnEventSink;
+
+impl ConnEventSink for NoOpConnEventSink {
+    fn emit(&self, _event: &ConnEvent) {}
+}
+
+/// Connection event sink, which appends events (as NDJSON) to a file
+pub struct NdjsonFileConnEventSink {
+    file: Mutex<File>,
+}
+
+impl NdjsonFileConnEventSink {
+    /// NdjsonFileConnEventSink constructor. File is created if it doesn't exist.
+    pub fn new(filepath: &str) -> Result<Self, AppError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(filepath)
+            .map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    format!("Error opening connection events file: path={}", filepath),
+                    Box::new(err),
+                )
+            })?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl ConnEventSink for NdjsonFileConnEventSink {
+    fn emit(&self, event: &ConnEvent) {
+        let result = event.to_ndjson().and_then(|line| {
+            self.file
+                .lock()
+                .unwrap()
+                .write_all(line.as_bytes())
+                .map_err(|err| {
+                    AppError::GenWithMsgAndErr(
+                        "Error writing connection event".to_string(),
+                        Box::new(err),
+                    )
+                })
+        });
+
+        if let Err(err) = result {
+            debug(
+                &target!(),
+                &format!("Error emitting connection event: err={:?}", &err),
+            );
+        }
+    }
+}
+
+/// Connection event sink, which sends events (as NDJSON, one event per datagram) to a UDP server
+pub struct NdjsonUdpConnEventSink {
+    socket: UdpSocket,
+}
+
+impl NdjsonUdpConnEventSink {
+    /// NdjsonUdpConnEventSink constructor
+    pub fn new(server_addr: &str) -> Result<Self, AppError> {
+        let socket = UdpSocket::bind("[::]:0")
+            .or_else(|_| UdpSocket::bind("0.0.0.0:0"))
+            .map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    "Error binding connection events UDP socket".to_string(),
+                    Box::new(err),
+                )
+            })?;
+        socket.connect(server_addr).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                format!(
+                    "Error connecting connection events UDP socket: addr={}",
+                    server_addr
+                ),
+                Box::new(err),
+            )
+        })?;
+        socket.set_nonblocking(true).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                "Failed making connection events UDP socket non-blocking".to_string(),
+                Box::new(err),
+            )
+        })?;
+
+        Ok(Self { socket })
+    }
+}
+
+impl ConnEventSink for NdjsonUdpConnEventSink {
+    fn emit(&self, event: &ConnEvent) {
+        let result = event.to_ndjson().and_then(|line| {
+            self.socket
+                .send(line.as_bytes())
+                .map(|_| ())
+                .map_err(|err| {
+                    AppError::GenWithMsgAndErr(
+                        "Error sending connection event".to_string(),
+                        Box::new(err),
+                    )
+                })
+        });
+
+        if let Err(err) = result {
+            debug(
+                &target!(),
+                &format!("Error emitting connection event: err={:?}", &err),
+            );
+        }
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::clock::SystemClock;
+    use std::io::Read;
+    use std::time::Duration;
+
+    fn create_event(event_type: ConnEventType, response_code: Option<u16>) -> ConnEvent {
+        ConnEvent::new(
+            &SystemClock,
+            event_type,
+            Some(100),
+            Some(200),
+            Some("10.0.0.5:41000".parse().unwrap()),
+            response_code,
+            "00000000000000ab",
+        )
+    }
+
+    #[test]
+    fn connevent_to_ndjson() {
+        let event = create_event(ConnEventType::AuthDenied, Some(403));
+
+        let line = event.to_ndjson().unwrap();
+
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+
+        let value: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(value["event_type"], "auth_denied");
+        assert_eq!(value["user_id"], 100);
+        assert_eq!(value["service_id"], 200);
+        assert_eq!(value["client_addr"], "10.0.0.5:41000");
+        assert_eq!(value["response_code"], 403);
+        assert_eq!(value["trace_id"], "00000000000000ab");
+        assert!(value["timestamp"].as_u64().unwrap() > 0);
+
+        assert_eq!(
+            serde_json::from_str::<ConnEvent>(line.trim_end()).unwrap(),
+            event
+        );
+    }
+
+    #[test]
+    fn connevent_to_ndjson_when_reason() {
+        let mut event = create_event(ConnEventType::AuthDenied, Some(403));
+        event.set_reason(Some("User is not authorized for service".to_string()));
+
+        let line = event.to_ndjson().unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(value["reason"], "User is not authorized for service");
+        assert!(!create_event(ConnEventType::AuthAllowed, Some(200))
+            .to_ndjson()
+            .unwrap()
+            .contains("reason"));
+    }
+
+    #[test]
+    fn connevent_to_ndjson_when_tls_session_info() {
+        let mut event = create_event(ConnEventType::ConnectionOpened, Some(200));
+        event.set_tls_session_info(&TlsSessionInfo {
+            protocol_version: Some("TLSv1_3".to_string()),
+            cipher_suite: Some("TLS13_AES_256_GCM_SHA384".to_string()),
+            alpn_protocol: Some("T0SRV200".to_string()),
+            resumed: false,
+        });
+
+        let line = event.to_ndjson().unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(value["tls_protocol_version"], "TLSv1_3");
+        assert_eq!(value["tls_cipher_suite"], "TLS13_AES_256_GCM_SHA384");
+        assert_eq!(value["alpn_protocol"], "T0SRV200");
+        assert_eq!(
+            serde_json::from_str::<ConnEvent>(line.trim_end()).unwrap(),
+            event
+        );
+        assert!(!create_event(ConnEventType::AuthAllowed, Some(200))
+            .to_ndjson()
+            .unwrap()
+            .contains("tls_"));
+    }
+
+    #[test]
+    fn connevent_denial_reason() {
+        assert_eq!(
+            denial_reason(&AppError::GenWithCodeAndMsg(
+                403,
+                "Access is forbidden".to_string()
+            )),
+            "Access is forbidden"
+        );
+        assert_eq!(
+            denial_reason(&AppError::General("General error".to_string())),
+            "General error"
+        );
+    }
+
+    #[test]
+    fn connevent_create_trace_id() {
+        let trace_id = create_trace_id();
+
+        assert_eq!(trace_id.len(), 16);
+        assert!(trace_id.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn ndjsonfilesink_emit() {
+        let filepath =
+            std::env::temp_dir().join(format!("trust0-conn-events-{}.ndjson", create_trace_id()));
+        let sink = NdjsonFileConnEventSink::new(filepath.to_str().unwrap()).unwrap();
+
+        let events = vec![
+            create_event(ConnEventType::AuthAllowed, Some(200)),
+            create_event(ConnEventType::ConnectionOpened, Some(200)),
+            create_event(ConnEventType::ConnectionClosed, None),
+        ];
+        for event in &events {
+            sink.emit(event);
+        }
+
+        let mut contents = String::new();
+        File::open(&filepath)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        let _ = std::fs::remove_file(&filepath);
+
+        let emitted_events: Vec<ConnEvent> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(emitted_events, events);
+    }
+
+    #[test]
+    fn ndjsonudpsink_emit() {
+        let server_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server_socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let sink =
+            NdjsonUdpConnEventSink::new(&server_socket.local_addr().unwrap().to_string()).unwrap();
+
+        let event = create_event(ConnEventType::ConnectionOpened, Some(200));
+        sink.emit(&event);
+
+        let mut buffer = [0u8; 1024];
+        let size = server_socket.recv(&mut buffer).unwrap();
+        let line = String::from_utf8(buffer[..size].to_vec()).unwrap();
+
+        assert!(line.ends_with('\n'));
+        assert_eq!(
+            serde_json::from_str::<ConnEvent>(line.trim_end()).unwrap(),
+            event
+        );
+    }
+}
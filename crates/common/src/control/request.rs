@@ -4,6 +4,7 @@ use serde_derive::{Deserialize, Serialize};
 
 use crate::control::response;
 use crate::error::AppError;
+use crate::model::user::Status;
 
 // Protocol text
 pub const PROTOCOL_REQUEST_ABOUT: &str = "about";
@@ -11,9 +12,18 @@ pub const PROTOCOL_REQUEST_CONNECTIONS: &str = "connections";
 pub const PROTOCOL_REQUEST_HELP: &str = "help";
 pub const PROTOCOL_REQUEST_PING: &str = "ping";
 pub const PROTOCOL_REQUEST_PROXIES: &str = "proxies";
+pub const PROTOCOL_REQUEST_SERVICE: &str = "service";
 pub const PROTOCOL_REQUEST_SERVICES: &str = "services";
+pub const PROTOCOL_REQUEST_SERVICE_STATS: &str = "service-stats";
+pub const PROTOCOL_REQUEST_SESSIONS: &str = "sessions";
+pub const PROTOCOL_REQUEST_CLOSE_SESSION: &str = "close-session";
 pub const PROTOCOL_REQUEST_START: &str = "start";
 pub const PROTOCOL_REQUEST_STOP: &str = "stop";
+pub const PROTOCOL_REQUEST_USER_STATUS: &str = "user-status";
+pub const PROTOCOL_REQUEST_SET_USER_STATUS: &str = "set-user-status";
+pub const PROTOCOL_REQUEST_RELOAD: &str = "reload";
+pub const PROTOCOL_REQUEST_CONFIG: &str = "config";
+pub const PROTOCOL_REQUEST_MAINTENANCE: &str = "maintenance";
 pub const PROTOCOL_REQUEST_VERSION: &str = "version";
 pub const PROTOCOL_REQUEST_QUIT: &str = "quit";
 pub const PROTOCOL_REQUEST_EXIT: &str = "exit";
@@ -38,7 +48,17 @@ pub enum Request {
     Connections,
     Ping,
     Proxies,
+    Service {
+        service: String,
+    },
     Services,
+    ServiceStats {
+        service: String,
+    },
+    Sessions,
+    CloseSession {
+        handle: String,
+    },
     Start {
         service_name: String,
         local_port: u16,
@@ -46,6 +66,18 @@ pub enum Request {
     Stop {
         service_name: String,
     },
+    UserStatus {
+        user_id: u64,
+    },
+    SetUserStatus {
+        user_id: u64,
+        status: Status,
+    },
+    Reload,
+    Config,
+    Maintenance {
+        message: Option<String>,
+    },
     Quit,
 }
 
@@ -97,9 +129,28 @@ impl RequestProcessor {
             Some((PROTOCOL_REQUEST_CONNECTIONS, _matches)) => Ok(Request::Connections),
             Some((PROTOCOL_REQUEST_PING, _matches)) => Ok(Request::Ping),
             Some((PROTOCOL_REQUEST_PROXIES, _matches)) => Ok(Request::Proxies),
+            Some((PROTOCOL_REQUEST_SERVICE, matches)) => Self::parse_service_request(matches),
             Some((PROTOCOL_REQUEST_SERVICES, _matches)) => Ok(Request::Services),
+            Some((PROTOCOL_REQUEST_SERVICE_STATS, matches)) => {
+                Self::parse_service_stats_request(matches)
+            }
+            Some((PROTOCOL_REQUEST_SESSIONS, _matches)) => Ok(Request::Sessions),
+            Some((PROTOCOL_REQUEST_CLOSE_SESSION, matches)) => {
+                Self::parse_close_session_request(matches)
+            }
             Some((PROTOCOL_REQUEST_START, matches)) => Self::parse_start_request(matches),
             Some((PROTOCOL_REQUEST_STOP, matches)) => Self::parse_stop_request(matches),
+            Some((PROTOCOL_REQUEST_USER_STATUS, matches)) => {
+                Self::parse_user_status_request(matches)
+            }
+            Some((PROTOCOL_REQUEST_SET_USER_STATUS, matches)) => {
+                Self::parse_set_user_status_request(matches)
+            }
+            Some((PROTOCOL_REQUEST_RELOAD, _matches)) => Ok(Request::Reload),
+            Some((PROTOCOL_REQUEST_CONFIG, _matches)) => Ok(Request::Config),
+            Some((PROTOCOL_REQUEST_MAINTENANCE, matches)) => {
+                Self::parse_maintenance_request(matches)
+            }
             Some((PROTOCOL_REQUEST_QUIT, _matches)) => Ok(Request::Quit),
             Some((name, _matches)) => {
                 if name.is_empty() {
@@ -115,6 +166,38 @@ impl RequestProcessor {
         }
     }
 
+    /// Parse "service" request
+    fn parse_service_request(arg_matches: &ArgMatches) -> Result<Request, AppError> {
+        let service = arg_matches.get_one::<String>("service");
+
+        if service.is_none() {
+            return Err(AppError::General(format!(
+                "Service ID or name is required for the \"{}\" command",
+                PROTOCOL_REQUEST_SERVICE
+            )));
+        }
+
+        Ok(Request::Service {
+            service: service.unwrap().clone(),
+        })
+    }
+
+    /// Parse "service-stats" request
+    fn parse_service_stats_request(arg_matches: &ArgMatches) -> Result<Request, AppError> {
+        let service = arg_matches.get_one::<String>("service");
+
+        if service.is_none() {
+            return Err(AppError::General(format!(
+                "Service ID or name is required for the \"{}\" command",
+                PROTOCOL_REQUEST_SERVICE_STATS
+            )));
+        }
+
+        Ok(Request::ServiceStats {
+            service: service.unwrap().clone(),
+        })
+    }
+
     /// Parse "start" request
     fn parse_start_request(arg_matches: &ArgMatches) -> Result<Request, AppError> {
         let service_name = arg_matches.get_one::<String>("service");
@@ -155,6 +238,84 @@ impl RequestProcessor {
         })
     }
 
+    /// Parse "close-session" request
+    fn parse_close_session_request(arg_matches: &ArgMatches) -> Result<Request, AppError> {
+        let handle = arg_matches.get_one::<String>("handle");
+
+        if handle.is_none() {
+            return Err(AppError::General(format!(
+                "Session handle is required for the \"{}\" command",
+                PROTOCOL_REQUEST_CLOSE_SESSION
+            )));
+        }
+
+        Ok(Request::CloseSession {
+            handle: handle.unwrap().clone(),
+        })
+    }
+
+    /// Parse "user-status" request
+    fn parse_user_status_request(arg_matches: &ArgMatches) -> Result<Request, AppError> {
+        let user_id = arg_matches.get_one::<u64>("user");
+
+        if user_id.is_none() {
+            return Err(AppError::General(format!(
+                "User ID is required for the \"{}\" command",
+                PROTOCOL_REQUEST_USER_STATUS
+            )));
+        }
+
+        Ok(Request::UserStatus {
+            user_id: *user_id.unwrap(),
+        })
+    }
+
+    /// Parse "set-user-status" request
+    fn parse_set_user_status_request(arg_matches: &ArgMatches) -> Result<Request, AppError> {
+        let user_id = arg_matches.get_one::<u64>("user");
+        let status = arg_matches.get_one::<String>("status");
+
+        if user_id.is_none() {
+            return Err(AppError::General(format!(
+                "User ID is required for the \"{}\" command",
+                PROTOCOL_REQUEST_SET_USER_STATUS
+            )));
+        }
+
+        let status = match status.map(|status| status.as_str()) {
+            Some("active") => Status::Active,
+            Some("inactive") => Status::Inactive,
+            _ => {
+                return Err(AppError::General(format!(
+                    "User status is required for the \"{}\" command",
+                    PROTOCOL_REQUEST_SET_USER_STATUS
+                )))
+            }
+        };
+
+        Ok(Request::SetUserStatus {
+            user_id: *user_id.unwrap(),
+            status,
+        })
+    }
+
+    /// Parse "maintenance" request
+    fn parse_maintenance_request(arg_matches: &ArgMatches) -> Result<Request, AppError> {
+        let message = arg_matches.get_one::<String>("message");
+        let clear = arg_matches.get_flag("clear");
+
+        match (message, clear) {
+            (Some(message), false) => Ok(Request::Maintenance {
+                message: Some(message.clone()),
+            }),
+            (None, true) => Ok(Request::Maintenance { message: None }),
+            _ => Err(AppError::General(format!(
+                "Either a message or the clear flag is required for the \"{}\" command",
+                PROTOCOL_REQUEST_MAINTENANCE
+            ))),
+        }
+    }
+
     /// Create command processor
     fn create_command() -> Command {
         Command::new("repl")
@@ -185,11 +346,40 @@ impl RequestProcessor {
                     .about("List active service proxies, ready for new connections")
                     .help_template(COMMAND_TEMPLATE),
             )
+            .subcommand(
+                Command::new(PROTOCOL_REQUEST_SERVICE)
+                    .about("Display authorized service details for connected mTLS device user")
+                    .help_template(COMMAND_TEMPLATE)
+                    .args(&[
+                        clap::arg!(-s --service <SERVICE> "Service ID or name")
+                    ])
+            )
             .subcommand(
                 Command::new(PROTOCOL_REQUEST_SERVICES)
                     .about("List authorized services for connected mTLS device user")
                     .help_template(COMMAND_TEMPLATE),
             )
+            .subcommand(
+                Command::new(PROTOCOL_REQUEST_SERVICE_STATS)
+                    .about("Display live proxy statistics for given service (admin only)")
+                    .help_template(COMMAND_TEMPLATE)
+                    .args(&[
+                        clap::arg!(-s --service <SERVICE> "Service ID or name")
+                    ])
+            )
+            .subcommand(
+                Command::new(PROTOCOL_REQUEST_SESSIONS)
+                    .about("List own active service proxy connections (with session handles)")
+                    .help_template(COMMAND_TEMPLATE),
+            )
+            .subcommand(
+                Command::new(PROTOCOL_REQUEST_CLOSE_SESSION)
+                    .about("Close own active service proxy connection")
+                    .help_template(COMMAND_TEMPLATE)
+                    .args(&[
+                        clap::arg!(-H --handle <SESSION_HANDLE> "Session handle (as listed by sessions command)")
+                    ])
+            )
             .subcommand(
                 Command::new(PROTOCOL_REQUEST_START)
                     .about("Startup proxy to authorized service via secure client-gateway proxy")
@@ -208,6 +398,45 @@ impl RequestProcessor {
                         clap::arg!(-s --service <SERVICE_NAME> "Corresponding service name for proxy")
                     ])
             )
+            .subcommand(
+                Command::new(PROTOCOL_REQUEST_USER_STATUS)
+                    .about("Display status for given user (admin only)")
+                    .help_template(COMMAND_TEMPLATE)
+                    .args(&[
+                        clap::arg!(-u --user <USER_ID> "User ID")
+                            .value_parser(clap::value_parser!(u64))
+                    ])
+            )
+            .subcommand(
+                Command::new(PROTOCOL_REQUEST_SET_USER_STATUS)
+                    .about("Set status for given user, inactive users are disconnected (admin only)")
+                    .help_template(COMMAND_TEMPLATE)
+                    .args(&[
+                        clap::arg!(-u --user <USER_ID> "User ID")
+                            .value_parser(clap::value_parser!(u64)),
+                        clap::arg!(-s --status <STATUS> "New user status")
+                            .value_parser(["active", "inactive"])
+                    ])
+            )
+            .subcommand(
+                Command::new(PROTOCOL_REQUEST_RELOAD)
+                    .about("Reload datasources now, reporting the changes applied (admin only)")
+                    .help_template(COMMAND_TEMPLATE),
+            )
+            .subcommand(
+                Command::new(PROTOCOL_REQUEST_CONFIG)
+                    .about("Display effective gateway configuration, secrets redacted (admin only)")
+                    .help_template(COMMAND_TEMPLATE),
+            )
+            .subcommand(
+                Command::new(PROTOCOL_REQUEST_MAINTENANCE)
+                    .about("Enter maintenance mode, rejecting new non-admin connections with given message, or clear it (admin only)")
+                    .help_template(COMMAND_TEMPLATE)
+                    .args(&[
+                        clap::arg!(-m --message <MESSAGE> "Maintenance message returned to rejected connections"),
+                        clap::arg!(-c --clear "Clear maintenance mode")
+                    ])
+            )
             .subcommand(
                 Command::new(PROTOCOL_REQUEST_QUIT)
                     .alias(PROTOCOL_REQUEST_EXIT)
@@ -257,7 +486,7 @@ mod tests {
         assert!(parse_error.get_code().is_some());
         assert_eq!(response::CODE_OK, parse_error.get_code().unwrap());
 
-        let expected_msg = "Response: code=200, msg=COMMANDS:\n  about        Display context information for connected mTLS device user\n  connections  List current service proxy connections\n  ping         Simple gateway heartbeat request\n  proxies      List active service proxies, ready for new connections\n  services     List authorized services for connected mTLS device user\n  start        Startup proxy to authorized service via secure client-gateway proxy\n  stop         Shutdown active service proxy (previously started)\n  quit         Quit the control plane (and corresponding service connections)\n  help         Print this message or the help of the given subcommand(s)\n".to_string();
+        let expected_msg = "Response: code=200, msg=COMMANDS:\n  about            Display context information for connected mTLS device user\n  connections      List current service proxy connections\n  ping             Simple gateway heartbeat request\n  proxies          List active service proxies, ready for new connections\n  service          Display authorized service details for connected mTLS device user\n  services         List authorized services for connected mTLS device user\n  service-stats    Display live proxy statistics for given service (admin only)\n  sessions         List own active service proxy connections (with session handles)\n  close-session    Close own active service proxy connection\n  start            Startup proxy to authorized service via secure client-gateway proxy\n  stop             Shutdown active service proxy (previously started)\n  user-status      Display status for given user (admin only)\n  set-user-status  Set status for given user, inactive users are disconnected (admin only)\n  reload           Reload datasources now, reporting the changes applied (admin only)\n  config           Display effective gateway configuration, secrets redacted (admin only)\n  maintenance      Enter maintenance mode, rejecting new non-admin connections with given message, or clear it (admin only)\n  quit             Quit the control plane (and corresponding service connections)\n  help             Print this message or the help of the given subcommand(s)\n".to_string();
 
         assert_eq!(parse_error.to_string(), expected_msg);
     }
@@ -370,6 +599,176 @@ mod tests {
         }
     }
 
+    #[test]
+    fn reqproc_parse_when_service_request() {
+        let request_processor = RequestProcessor::new();
+
+        let request_str = format!("{} -s Service200", PROTOCOL_REQUEST_SERVICE);
+
+        match request_processor.parse(&request_str) {
+            Ok(request) => assert_eq!(
+                request,
+                Request::Service {
+                    service: "Service200".to_string()
+                }
+            ),
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn reqproc_parse_when_sessions_request() {
+        let request_processor = RequestProcessor::new();
+
+        match request_processor.parse(PROTOCOL_REQUEST_SESSIONS) {
+            Ok(request) => assert_eq!(request, Request::Sessions),
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn reqproc_parse_when_close_session_request() {
+        let request_processor = RequestProcessor::new();
+
+        let request_str = format!("{} -H 0123456789abcdef", PROTOCOL_REQUEST_CLOSE_SESSION);
+
+        match request_processor.parse(&request_str) {
+            Ok(request) => assert_eq!(
+                request,
+                Request::CloseSession {
+                    handle: "0123456789abcdef".to_string()
+                }
+            ),
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn reqproc_parse_when_reload_request() {
+        let request_processor = RequestProcessor::new();
+
+        match request_processor.parse(PROTOCOL_REQUEST_RELOAD) {
+            Ok(request) => assert_eq!(request, Request::Reload),
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn reqproc_parse_when_config_request() {
+        let request_processor = RequestProcessor::new();
+
+        match request_processor.parse(PROTOCOL_REQUEST_CONFIG) {
+            Ok(request) => assert_eq!(request, Request::Config),
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn reqproc_parse_when_maintenance_request() {
+        let request_processor = RequestProcessor::new();
+
+        let request_str = format!(
+            "{} -m \"Upgrade in progress, back at 10:00 UTC\"",
+            PROTOCOL_REQUEST_MAINTENANCE
+        );
+
+        match request_processor.parse(&request_str) {
+            Ok(request) => assert_eq!(
+                request,
+                Request::Maintenance {
+                    message: Some("Upgrade in progress, back at 10:00 UTC".to_string())
+                }
+            ),
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+
+        match request_processor.parse(&format!("{} --clear", PROTOCOL_REQUEST_MAINTENANCE)) {
+            Ok(request) => assert_eq!(request, Request::Maintenance { message: None }),
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn reqproc_parse_when_maintenance_request_and_invalid_args() {
+        let request_processor = RequestProcessor::new();
+
+        for request_str in [
+            PROTOCOL_REQUEST_MAINTENANCE.to_string(),
+            format!("{} -m down -c", PROTOCOL_REQUEST_MAINTENANCE),
+        ] {
+            if let Ok(request) = request_processor.parse(&request_str) {
+                panic!("Unexpected successful result: req={:?}", request);
+            }
+        }
+    }
+
+    #[test]
+    fn reqproc_parse_when_service_stats_request() {
+        let request_processor = RequestProcessor::new();
+
+        let request_str = format!("{} -s Service200", PROTOCOL_REQUEST_SERVICE_STATS);
+
+        match request_processor.parse(&request_str) {
+            Ok(request) => assert_eq!(
+                request,
+                Request::ServiceStats {
+                    service: "Service200".to_string()
+                }
+            ),
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+
+        if let Ok(request) = request_processor.parse(PROTOCOL_REQUEST_SERVICE_STATS) {
+            panic!("Unexpected successful result: req={:?}", request);
+        }
+    }
+
+    #[test]
+    fn reqproc_parse_when_user_status_request() {
+        let request_processor = RequestProcessor::new();
+
+        let request_str = format!("{} -u 100", PROTOCOL_REQUEST_USER_STATUS);
+
+        let result = request_processor.parse(&request_str);
+
+        match result {
+            Ok(request) => assert_eq!(request, Request::UserStatus { user_id: 100 }),
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn reqproc_parse_when_set_user_status_request() {
+        let request_processor = RequestProcessor::new();
+
+        let request_str = format!("{} -u 100 -s inactive", PROTOCOL_REQUEST_SET_USER_STATUS);
+
+        let result = request_processor.parse(&request_str);
+
+        match result {
+            Ok(request) => assert_eq!(
+                request,
+                Request::SetUserStatus {
+                    user_id: 100,
+                    status: Status::Inactive
+                }
+            ),
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn reqproc_parse_when_set_user_status_request_and_invalid_status() {
+        let request_processor = RequestProcessor::new();
+
+        let request_str = format!("{} -u 100 -s disabled", PROTOCOL_REQUEST_SET_USER_STATUS);
+
+        match request_processor.parse(&request_str) {
+            Ok(request) => panic!("Unexpected successful result: req={:?}", request),
+            Err(err) => assert_eq!(err.get_code(), Some(response::CODE_BAD_REQUEST)),
+        }
+    }
+
     #[test]
     fn reqproc_parse_when_quit_request() {
         let request_processor = RequestProcessor::new();
@@ -1,11 +1,15 @@
 use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::io::Write;
 
+use serde::ser::{Error, SerializeSeq, Serializer};
 use serde_derive::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::control::request::Request;
 use crate::error::AppError;
 use crate::model;
+use crate::net::tls_server::conn_std::TlsSessionInfo;
 
 /// Container struct for controller responses
 pub const CODE_OK: u16 = 200;
@@ -40,6 +44,35 @@ impl Response {
         }
     }
 
+    /// Serialize (JSON) response to the given writer, where the data is the list of the given items. The items
+    /// are serialized one at a time, so the entire response is never materialized in memory.
+    pub fn write_streamed<W, I, T>(
+        writer: &mut W,
+        code: u16,
+        message: &Option<String>,
+        request: &Request,
+        data_items: I,
+    ) -> Result<(), AppError>
+    where
+        W: Write,
+        I: IntoIterator<Item = T>,
+        T: TryInto<Value, Error = AppError>,
+    {
+        let response = StreamedResponse {
+            code,
+            message,
+            request,
+            data: StreamedItems(RefCell::new(Some(data_items.into_iter()))),
+        };
+
+        serde_json::to_writer(writer, &response).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                "Error serializing streamed response".to_string(),
+                Box::new(err),
+            )
+        })
+    }
+
     /// Process command response text
     pub fn parse(data: &str) -> Result<Response, AppError> {
         serde_json::from_str(data).map_err(|err| {
@@ -51,6 +84,41 @@ impl Response {
     }
 }
 
+/// Response counterpart, used to serialize a response with streamed data items (field layout must match `Response`)
+#[derive(Serialize)]
+struct StreamedResponse<'a, D> {
+    code: u16,
+    message: &'a Option<String>,
+    request: &'a Request,
+    data: D,
+}
+
+/// Data items iterator, which is serialized as a JSON array (may only be serialized once)
+struct StreamedItems<I>(RefCell<Option<I>>);
+
+impl<I, T> serde::Serialize for StreamedItems<I>
+where
+    I: Iterator<Item = T>,
+    T: TryInto<Value, Error = AppError>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data_items = self
+            .0
+            .borrow_mut()
+            .take()
+            .ok_or_else(|| S::Error::custom("Streamed data items already serialized"))?;
+
+        let mut seq = serializer.serialize_seq(None)?;
+        for data_item in data_items {
+            let value: Value = data_item
+                .try_into()
+                .map_err(|err| S::Error::custom(err.to_string()))?;
+            seq.serialize_element(&value)?;
+        }
+        seq.end()
+    }
+}
+
 /// Represents the contextual mTLS client connection
 #[derive(Serialize, Deserialize, Clone, PartialEq, Default, Debug)]
 pub struct User {
@@ -99,6 +167,10 @@ pub struct About {
     cert_alt_subj: Option<String>,
     cert_context: Option<String>,
     user: Option<User>,
+    tls_session: Option<TlsSessionInfo>,
+    /// Gateway service catalog hash (changes whenever the service catalog changes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    config_hash: Option<String>,
 }
 
 impl About {
@@ -108,14 +180,22 @@ impl About {
         cert_alt_subj: &Option<String>,
         cert_context: &Option<String>,
         user: &Option<User>,
+        tls_session: &Option<TlsSessionInfo>,
     ) -> Self {
         Self {
             cert_subject: cert_subject.clone(),
             cert_alt_subj: cert_alt_subj.clone(),
             cert_context: cert_context.clone(),
             user: user.clone(),
+            tls_session: tls_session.clone(),
+            config_hash: None,
         }
     }
+
+    /// Set gateway service catalog hash
+    pub fn set_config_hash(&mut self, config_hash: &str) {
+        self.config_hash = Some(config_hash.to_string());
+    }
 }
 
 unsafe impl Send for About {}
@@ -141,6 +221,54 @@ impl TryInto<Value> for &About {
     }
 }
 
+/// Represents gateway heartbeat details
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default, Debug)]
+pub struct Ping {
+    /// Gateway service catalog hash (changes whenever the service catalog changes), allowing clients to detect
+    /// stale cached service lists
+    pub config_hash: String,
+}
+
+impl Ping {
+    /// Ping constructor
+    pub fn new(config_hash: &str) -> Self {
+        Self {
+            config_hash: config_hash.to_string(),
+        }
+    }
+
+    /// Construct Ping from serde Value
+    pub fn from_serde_value(value: &Value) -> Result<Ping, AppError> {
+        serde_json::from_value(value.clone()).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                "Error converting serde Value to Ping".to_string(),
+                Box::new(err),
+            )
+        })
+    }
+}
+
+impl TryInto<Value> for Ping {
+    type Error = AppError;
+
+    fn try_into(self) -> Result<Value, Self::Error> {
+        self.borrow().try_into()
+    }
+}
+
+impl TryInto<Value> for &Ping {
+    type Error = AppError;
+
+    fn try_into(self) -> Result<Value, Self::Error> {
+        serde_json::to_value(self).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                "Error converting Ping to serde Value".to_string(),
+                Box::new(err),
+            )
+        })
+    }
+}
+
 /// Represents an active service proxy, available for use by client
 #[derive(Serialize, Deserialize, Clone, PartialEq, Default, Debug)]
 pub struct Proxy {
@@ -223,6 +351,8 @@ pub struct Service {
     pub name: String,
     pub transport: model::service::Transport,
     pub address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub justification: Option<String>,
 }
 
 impl Service {
@@ -238,6 +368,7 @@ impl Service {
             name: name.to_string(),
             transport: transport.clone(),
             address,
+            justification: None,
         }
     }
 
@@ -333,6 +464,15 @@ impl TryInto<Value> for &Service {
 pub struct Connection {
     pub service_name: String,
     pub binds: Vec<Vec<String>>,
+    /// Byte count of queued (un-flushed) writes, for connections reporting their outbound queue depth
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_write_bytes: Option<usize>,
+    /// Count of queued events, for connections reporting their outbound queue depth
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queued_events: Option<usize>,
+    /// Rolling throughput (bytes/sec) across the connection binds, for connections reporting their throughput
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_per_sec: Option<u64>,
 }
 
 impl Connection {
@@ -341,9 +481,23 @@ impl Connection {
         Self {
             service_name: service_name.to_string(),
             binds,
+            pending_write_bytes: None,
+            queued_events: None,
+            bytes_per_sec: None,
         }
     }
 
+    /// Set connection rolling throughput (bytes/sec)
+    pub fn set_bytes_per_sec(&mut self, bytes_per_sec: u64) {
+        self.bytes_per_sec = Some(bytes_per_sec);
+    }
+
+    /// Set connection outbound queue depth
+    pub fn set_outbound_queue_depth(&mut self, pending_write_bytes: usize, queued_events: usize) {
+        self.pending_write_bytes = Some(pending_write_bytes);
+        self.queued_events = Some(queued_events);
+    }
+
     /// Construct Connection(s) from serde Value
     pub fn from_serde_value(value: &Value) -> Result<Vec<Connection>, AppError> {
         if let Value::Array(values) = &value {
@@ -394,6 +548,83 @@ impl TryInto<Value> for &Connection {
     }
 }
 
+/// Represents live proxy statistics for a service (counts are since its service proxy was started)
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default, Debug)]
+pub struct ServiceStats {
+    pub service_id: u64,
+    pub service_name: String,
+    pub active_connections: usize,
+    pub connections_opened: u64,
+    pub connections_closed: u64,
+    pub bytes_transferred: u64,
+}
+
+impl ServiceStats {
+    /// ServiceStats constructor
+    pub fn new(service_id: u64, service_name: &str) -> Self {
+        Self {
+            service_id,
+            service_name: service_name.to_string(),
+            active_connections: 0,
+            connections_opened: 0,
+            connections_closed: 0,
+            bytes_transferred: 0,
+        }
+    }
+}
+
+impl TryInto<Value> for ServiceStats {
+    type Error = AppError;
+
+    fn try_into(self) -> Result<Value, Self::Error> {
+        serde_json::to_value(&self).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                "Error converting ServiceStats to serde Value".to_string(),
+                Box::new(err),
+            )
+        })
+    }
+}
+
+/// Represents an active service proxy connection (session) for connected mTLS device user
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default, Debug)]
+pub struct Session {
+    pub handle: String,
+    pub service_name: String,
+    pub gateway_address: Option<String>,
+    pub duration_secs: u64,
+}
+
+impl Session {
+    /// Session constructor
+    pub fn new(
+        handle: &str,
+        service_name: &str,
+        gateway_address: &Option<String>,
+        duration_secs: u64,
+    ) -> Self {
+        Self {
+            handle: handle.to_string(),
+            service_name: service_name.to_string(),
+            gateway_address: gateway_address.clone(),
+            duration_secs,
+        }
+    }
+}
+
+impl TryInto<Value> for Session {
+    type Error = AppError;
+
+    fn try_into(self) -> Result<Value, Self::Error> {
+        serde_json::to_value(&self).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                "Error converting Session to serde Value".to_string(),
+                Box::new(err),
+            )
+        })
+    }
+}
+
 /// Unit tests
 #[cfg(test)]
 mod tests {
@@ -468,6 +699,12 @@ mod tests {
             &Some("casubj1".to_string()),
             &Some("cctxt1".to_string()),
             &Some(user),
+            &Some(TlsSessionInfo {
+                protocol_version: Some("TLSv1_3".to_string()),
+                cipher_suite: Some("TLS13_AES_256_GCM_SHA384".to_string()),
+                alpn_protocol: Some("T0CP".to_string()),
+                resumed: false,
+            }),
         );
 
         let result: Result<Value, AppError> = about.try_into();
@@ -475,13 +712,45 @@ mod tests {
             Ok(value) => {
                 assert_eq!(
                     value,
-                    json!({"cert_subject": "csubj1", "cert_alt_subj": "casubj1", "cert_context": "cctxt1", "user": {"user_id": 100, "name": "user100", "status": "Active"}})
+                    json!({"cert_subject": "csubj1", "cert_alt_subj": "casubj1", "cert_context": "cctxt1", "user": {"user_id": 100, "name": "user100", "status": "Active"}, "tls_session": {"protocol_version": "TLSv1_3", "cipher_suite": "TLS13_AES_256_GCM_SHA384", "alpn_protocol": "T0CP", "resumed": false}})
                 );
             }
             Err(err) => panic!("Unexpected result: err={:?}", err),
         }
     }
 
+    #[test]
+    fn about_try_into_when_config_hash() {
+        let mut about = About::new(&None, &None, &None, &None, &None);
+        about.set_config_hash("abc123");
+
+        let result: Result<Value, AppError> = about.try_into();
+        match result {
+            Ok(value) => {
+                assert_eq!(
+                    value,
+                    json!({"cert_subject": null, "cert_alt_subj": null, "cert_context": null, "user": null, "tls_session": null, "config_hash": "abc123"})
+                );
+            }
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn ping_try_into_and_from_serde_value() {
+        let ping = Ping::new("abc123");
+
+        let result: Result<Value, AppError> = ping.clone().try_into();
+        match result {
+            Ok(value) => {
+                assert_eq!(value, json!({"config_hash": "abc123"}));
+                assert_eq!(Ping::from_serde_value(&value).unwrap(), ping);
+            }
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+        assert!(Ping::from_serde_value(&json!({"hash_INVALID": 1})).is_err());
+    }
+
     #[test]
     fn proxy_from_serde_value_when_invalid() {
         let proxy_json = json!({"service_INVALID": {"id": 200, "name": "svc1", "transport": "TCP", "address": "host:9000"}, "gateway_host": "gwhost1", "gateway_port": 8400, "client_port": 8501});
@@ -586,6 +855,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn session_try_into() {
+        let session = Session::new(
+            "0123456789abcdef",
+            "svc1",
+            &Some("gw1:8200".to_string()),
+            42,
+        );
+
+        let result: Result<Value, AppError> = session.try_into();
+        match result {
+            Ok(value) => {
+                assert_eq!(
+                    value,
+                    json!({"handle": "0123456789abcdef", "service_name": "svc1", "gateway_address": "gw1:8200", "duration_secs": 42})
+                );
+            }
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn servicestats_try_into() {
+        let mut service_stats = ServiceStats::new(200, "svc1");
+        service_stats.active_connections = 2;
+        service_stats.connections_opened = 5;
+        service_stats.connections_closed = 3;
+        service_stats.bytes_transferred = 1024;
+
+        let result: Result<Value, AppError> = service_stats.try_into();
+        match result {
+            Ok(value) => {
+                assert_eq!(
+                    value,
+                    json!({"service_id": 200, "service_name": "svc1", "active_connections": 2, "connections_opened": 5, "connections_closed": 3, "bytes_transferred": 1024})
+                );
+            }
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+    }
+
     #[test]
     fn connection_from_serde_value_when_invalid() {
         let conn_json = json!({"service_name_INVALID": "svc1", "binds": [["b0","b1"],["b2","b3"]]});
@@ -637,4 +947,103 @@ mod tests {
             Err(err) => panic!("Unexpected result: err={:?}", err),
         }
     }
+
+    #[test]
+    fn connection_try_into_when_outbound_queue_depth() {
+        let mut conn = Connection::new("control-plane", vec![]);
+        conn.set_outbound_queue_depth(1024, 3);
+
+        let result: Result<Value, AppError> = conn.clone().try_into();
+        match result {
+            Ok(value) => {
+                assert_eq!(
+                    value,
+                    json!({"service_name": "control-plane", "binds": [], "pending_write_bytes": 1024, "queued_events": 3})
+                );
+                assert_eq!(Connection::from_serde_value(&value).unwrap(), vec![conn]);
+            }
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn connection_try_into_when_bytes_per_sec() {
+        let mut conn = Connection::new("svc1", vec![vec!["b0".to_string(), "b1".to_string()]]);
+        conn.set_bytes_per_sec(2048);
+
+        let result: Result<Value, AppError> = conn.clone().try_into();
+        match result {
+            Ok(value) => {
+                assert_eq!(
+                    value,
+                    json!({"service_name": "svc1", "binds": [["b0","b1"]], "bytes_per_sec": 2048})
+                );
+                assert_eq!(Connection::from_serde_value(&value).unwrap(), vec![conn]);
+            }
+            Err(err) => panic!("Unexpected result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn response_write_streamed_when_matches_buffered_response() {
+        let services = vec![
+            Service::new(
+                200,
+                "svc200",
+                &model::service::Transport::TCP,
+                Some("localhost:8200".to_string()),
+            ),
+            Service::new(201, "svc201", &model::service::Transport::UDP, None),
+        ];
+        let values: Vec<Value> = services
+            .iter()
+            .map(|service| service.try_into().unwrap())
+            .collect();
+        let buffered_response = serde_json::to_string(&Response::new(
+            CODE_OK,
+            &None,
+            &Request::Services,
+            &Some(values.into()),
+        ))
+        .unwrap();
+
+        let mut streamed_response: Vec<u8> = vec![];
+        let result = Response::write_streamed(
+            &mut streamed_response,
+            CODE_OK,
+            &None,
+            &Request::Services,
+            services,
+        );
+
+        if let Err(err) = result {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+
+        assert_eq!(
+            String::from_utf8(streamed_response).unwrap(),
+            buffered_response
+        );
+    }
+
+    #[test]
+    fn response_write_streamed_when_no_items() {
+        let mut streamed_response: Vec<u8> = vec![];
+        let result = Response::write_streamed(
+            &mut streamed_response,
+            CODE_OK,
+            &Some("msg1".to_string()),
+            &Request::Services,
+            Vec::<Service>::new(),
+        );
+
+        if let Err(err) = result {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+
+        assert_eq!(
+            String::from_utf8(streamed_response).unwrap(),
+            "{\"code\":200,\"message\":\"msg1\",\"request\":\"Services\",\"data\":[]}"
+        );
+    }
 }
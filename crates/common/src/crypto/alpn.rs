@@ -2,40 +2,100 @@ use std::fmt;
 
 use regex::Regex;
 
+use crate::error::AppError;
+
 pub const PROTOCOL_CONTROL_PLANE: &str = "T0CP";
 pub const PROTOCOL_SERVICE: &str = "T0SRV";
 pub const PROTOCOL_SERVICE_PARSE_REGEX: &str = r"^T0SRV(\d+)$";
+pub const PROTOCOL_SERVICE_NAME: &str = "T0SRVNAME-";
+pub const PROTOCOL_SERVICE_NAME_PARSE_REGEX: &str = r"^T0SRVNAME-([A-Za-z0-9_.\-]+)$";
+pub const PROTOCOL_REVERSE_SERVICE: &str = "T0REVSRV";
+pub const PROTOCOL_REVERSE_SERVICE_PARSE_REGEX: &str = r"^T0REVSRV(\d+)$";
+
+/// Maximum length of an ALPN protocol identifier (as per RFC 7301)
+pub const PROTOCOL_MAX_LEN: usize = 255;
 
 /// Trust0 utilized ALPN protocol negotiation to determine connection type: Control Plane; Service Proxy
-#[derive(Debug, PartialEq)]
+/// (by service ID or by service name); Reverse Service Proxy client session (by service ID)
+#[derive(Clone, Debug, PartialEq)]
 pub enum Protocol {
     ControlPlane,
     Service(u64),
+    ServiceName(String),
+    ReverseService(u64),
 }
 
 impl Protocol {
     /// Parse ALPN string
     pub fn parse(alpn_str: &str) -> Option<Protocol> {
+        Self::try_parse(alpn_str).ok()
+    }
+
+    /// Parse and validate ALPN string. Malformed protocols (unknown, overly long, out of range service ID, invalid
+    /// service name) return an error.
+    pub fn try_parse(alpn_str: &str) -> Result<Protocol, AppError> {
+        if alpn_str.len() > PROTOCOL_MAX_LEN {
+            return Err(AppError::General(format!(
+                "ALPN protocol exceeds maximum length: len={}",
+                alpn_str.len()
+            )));
+        }
+
         if alpn_str.eq(PROTOCOL_CONTROL_PLANE) {
-            return Some(Protocol::ControlPlane);
+            return Ok(Protocol::ControlPlane);
         }
 
         let service_regex = Regex::new(PROTOCOL_SERVICE_PARSE_REGEX).unwrap();
-        if service_regex.is_match(alpn_str) {
-            return Some(Protocol::Service(
-                service_regex.captures(alpn_str).unwrap()[1]
-                    .parse()
-                    .unwrap(),
-            ));
+        if let Some(captures) = service_regex.captures(alpn_str) {
+            return captures[1].parse().map(Protocol::Service).map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    format!("Invalid ALPN protocol service ID: proto={}", alpn_str),
+                    Box::new(err),
+                )
+            });
         }
 
-        None
+        let reverse_service_regex = Regex::new(PROTOCOL_REVERSE_SERVICE_PARSE_REGEX).unwrap();
+        if let Some(captures) = reverse_service_regex.captures(alpn_str) {
+            return captures[1]
+                .parse()
+                .map(Protocol::ReverseService)
+                .map_err(|err| {
+                    AppError::GenWithMsgAndErr(
+                        format!(
+                            "Invalid ALPN protocol reverse service ID: proto={}",
+                            alpn_str
+                        ),
+                        Box::new(err),
+                    )
+                });
+        }
+
+        let service_name_regex = Regex::new(PROTOCOL_SERVICE_NAME_PARSE_REGEX).unwrap();
+        if let Some(captures) = service_name_regex.captures(alpn_str) {
+            return Ok(Protocol::ServiceName(captures[1].to_string()));
+        }
+
+        Err(AppError::General(format!(
+            "Unknown ALPN protocol: proto={}",
+            alpn_str
+        )))
     }
 
     /// Create service protocol ALPN string
     pub fn create_service_protocol(service_id: u64) -> String {
         format!("{}{}", PROTOCOL_SERVICE, service_id)
     }
+
+    /// Create reverse service protocol ALPN string
+    pub fn create_reverse_service_protocol(service_id: u64) -> String {
+        format!("{}{}", PROTOCOL_REVERSE_SERVICE, service_id)
+    }
+
+    /// Create service (by name) protocol ALPN string
+    pub fn create_service_name_protocol(service_name: &str) -> String {
+        format!("{}{}", PROTOCOL_SERVICE_NAME, service_name)
+    }
 }
 
 impl fmt::Display for Protocol {
@@ -43,6 +103,10 @@ impl fmt::Display for Protocol {
         let protocol_str = match self {
             Protocol::ControlPlane => PROTOCOL_CONTROL_PLANE.to_string(),
             Protocol::Service(service_id) => Self::create_service_protocol(*service_id),
+            Protocol::ServiceName(service_name) => Self::create_service_name_protocol(service_name),
+            Protocol::ReverseService(service_id) => {
+                Self::create_reverse_service_protocol(*service_id)
+            }
         };
         write!(fmt, "{}", &protocol_str)
     }
@@ -77,6 +141,94 @@ mod tests {
         assert!(protocol.is_none());
     }
 
+    #[test]
+    fn protocol_try_parse_when_round_trip() {
+        for protocol in [
+            Protocol::ControlPlane,
+            Protocol::Service(0),
+            Protocol::Service(u64::MAX),
+            Protocol::ServiceName("chat-svc_1.internal".to_string()),
+            Protocol::ReverseService(200),
+        ] {
+            assert_eq!(
+                Protocol::try_parse(&protocol.to_string()).unwrap(),
+                protocol
+            );
+        }
+    }
+
+    #[test]
+    fn protocol_try_parse_when_malformed_control_plane() {
+        for alpn_str in ["", "T0C", "t0cp", "T0CP ", "T0CPX"] {
+            if let Ok(protocol) = Protocol::try_parse(alpn_str) {
+                panic!("Unexpected successful result: val={:?}", &protocol);
+            }
+        }
+    }
+
+    #[test]
+    fn protocol_try_parse_when_malformed_service() {
+        for alpn_str in [
+            "T0SRV",
+            "T0SRV-1",
+            "T0SRV1a",
+            "T0SRV 1",
+            "T0SRV18446744073709551616",
+        ] {
+            if let Ok(protocol) = Protocol::try_parse(alpn_str) {
+                panic!("Unexpected successful result: val={:?}", &protocol);
+            }
+        }
+    }
+
+    #[test]
+    fn protocol_try_parse_when_malformed_service_name() {
+        let overly_long_name =
+            Protocol::create_service_name_protocol(&"a".repeat(PROTOCOL_MAX_LEN));
+
+        for alpn_str in [
+            "T0SRVNAME-",
+            "T0SRVNAME-chat svc",
+            "T0SRVNAME-chat/svc",
+            "T0SRVNAMEchat",
+            overly_long_name.as_str(),
+        ] {
+            if let Ok(protocol) = Protocol::try_parse(alpn_str) {
+                panic!("Unexpected successful result: val={:?}", &protocol);
+            }
+        }
+    }
+
+    #[test]
+    fn protocol_try_parse_when_malformed_reverse_service() {
+        for alpn_str in [
+            "T0REVSRV",
+            "T0REVSRV-1",
+            "T0REVSRV1a",
+            "T0REVSRV18446744073709551616",
+        ] {
+            if let Ok(protocol) = Protocol::try_parse(alpn_str) {
+                panic!("Unexpected successful result: val={:?}", &protocol);
+            }
+        }
+    }
+
+    #[test]
+    fn protocol_create_reverse_service_protocol() {
+        assert_eq!(
+            Protocol::create_reverse_service_protocol(200),
+            format!("{}{}", PROTOCOL_REVERSE_SERVICE, 200)
+        );
+    }
+
+    #[test]
+    fn protocol_create_service_name_protocol() {
+        assert_eq!(
+            Protocol::create_service_name_protocol("chat"),
+            format!("{}{}", PROTOCOL_SERVICE_NAME, "chat")
+        );
+    }
+
     #[test]
     fn protocol_create_service_protocol() {
         assert_eq!(
@@ -1,11 +1,33 @@
 use std::fmt::Write;
 
 use oid_registry::{format_oid, Oid as DerOid, OidRegistry};
-use x509_parser::der_parser::asn1_rs::{Any, Tag};
+use x509_parser::der_parser::asn1_rs::{Any, FromDer, Tag};
 
 use crate::error::AppError;
 
+/// Default maximum nesting depth of (recursively stringified) ASN sequences/sets
+pub const DEFAULT_MAX_ASN_DEPTH: usize = 32;
+
+/// Stringify ASN value (sequences/sets are limited to the default maximum nesting depth)
 pub fn stringify_asn_value(asn_attr: &Any<'_>) -> Result<String, AppError> {
+    stringify_asn_value_with_max_depth(asn_attr, DEFAULT_MAX_ASN_DEPTH)
+}
+
+/// Stringify ASN value. Sequences/sets are stringified recursively, as a list of their stringified elements. Values
+/// with sequences/sets nested deeper than `max_depth` are rejected (rather than risking stack exhaustion).
+pub fn stringify_asn_value_with_max_depth(
+    asn_attr: &Any<'_>,
+    max_depth: usize,
+) -> Result<String, AppError> {
+    stringify_nested_asn_value(asn_attr, 0, max_depth)
+}
+
+/// Stringify ASN value, which is nested within `depth` sequences/sets
+fn stringify_nested_asn_value(
+    asn_attr: &Any<'_>,
+    depth: usize,
+    max_depth: usize,
+) -> Result<String, AppError> {
     let convert_err_fn = |err| {
         Err(AppError::GenWithMsgAndErr(
             "Failed ASN value conversion".to_string(),
@@ -88,9 +110,101 @@ pub fn stringify_asn_value(asn_attr: &Any<'_>) -> Result<String, AppError> {
             .utf8string()
             .map(|v| v.string())
             .or_else(convert_err_fn),
+        Tag::Sequence | Tag::Set => {
+            if depth >= max_depth {
+                return Err(AppError::General(format!(
+                    "ASN value exceeds maximum nesting depth: max_depth={}",
+                    max_depth
+                )));
+            }
+
+            let mut elements = vec![];
+            let mut remaining = asn_attr.data;
+            while !remaining.is_empty() {
+                let (rem, element) = Any::from_der(remaining).map_err(|err| {
+                    AppError::GenWithMsgAndErr(
+                        "Failed ASN value conversion".to_string(),
+                        Box::new(err),
+                    )
+                })?;
+                elements.push(stringify_nested_asn_value(&element, depth + 1, max_depth)?);
+                remaining = rem;
+            }
+
+            match asn_attr.header.tag() {
+                Tag::Sequence => Ok(format!("[{}]", elements.join(", "))),
+                _ => Ok(format!("{{{}}}", elements.join(", "))),
+            }
+        }
         _ => Err(AppError::General(format!(
             "unsupported tag {}",
             asn_attr.clone().header.tag()
         ))),
     }
 }
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// DER encoding of an integer (1), nested within given number of sequences
+    fn create_nested_sequence_der(nesting: usize) -> Vec<u8> {
+        let mut der = vec![0x02, 0x01, 0x01];
+        for _ in 0..nesting {
+            let mut sequence_der = vec![0x30, der.len() as u8];
+            sequence_der.append(&mut der);
+            der = sequence_der;
+        }
+        der
+    }
+
+    #[test]
+    fn asn_stringify_asn_value_when_sequence_and_set() {
+        let der = [
+            0x30, 0x0b, 0x02, 0x01, 0x07, 0x31, 0x06, 0x01, 0x01, 0xff, 0x02, 0x01, 0x02,
+        ];
+        let (_, asn_value) = Any::from_der(&der).unwrap();
+
+        assert_eq!(
+            stringify_asn_value(&asn_value).unwrap(),
+            "[7, {true, 2}]".to_string()
+        );
+    }
+
+    #[test]
+    fn asn_stringify_asn_value_when_within_max_depth() {
+        let der = create_nested_sequence_der(DEFAULT_MAX_ASN_DEPTH);
+        let (_, asn_value) = Any::from_der(&der).unwrap();
+
+        assert_eq!(
+            stringify_asn_value(&asn_value).unwrap(),
+            format!(
+                "{}1{}",
+                "[".repeat(DEFAULT_MAX_ASN_DEPTH),
+                "]".repeat(DEFAULT_MAX_ASN_DEPTH)
+            )
+        );
+    }
+
+    #[test]
+    fn asn_stringify_asn_value_when_exceeds_max_depth() {
+        let der = create_nested_sequence_der(DEFAULT_MAX_ASN_DEPTH + 1);
+        let (_, asn_value) = Any::from_der(&der).unwrap();
+
+        match stringify_asn_value(&asn_value) {
+            Err(err) => assert!(format!("{:?}", err).contains("maximum nesting depth")),
+            Ok(value) => panic!("Unexpected successful result: val={}", value),
+        }
+    }
+
+    #[test]
+    fn asn_stringify_asn_value_with_max_depth_when_exceeds_max_depth() {
+        let der = create_nested_sequence_der(3);
+        let (_, asn_value) = Any::from_der(&der).unwrap();
+
+        assert!(stringify_asn_value_with_max_depth(&asn_value, 3).is_ok());
+        assert!(stringify_asn_value_with_max_depth(&asn_value, 2).is_err());
+    }
+}
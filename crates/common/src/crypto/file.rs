@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::io::{BufReader, Read};
 use std::ops::{Deref, DerefMut};
 use std::path::PathBuf;
@@ -7,6 +8,8 @@ use std::{fs, io, thread};
 
 use anyhow::Result;
 use pki_types::{CertificateDer, CertificateRevocationListDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use x509_parser::parse_x509_crl;
+use x509_parser::pem::parse_x509_pem;
 
 use crate::error::AppError;
 use crate::logging::{error, info};
@@ -120,14 +123,46 @@ pub fn load_crl_list(filepath: &str) -> Result<Vec<u8>, AppError> {
     }
 }
 
+/// Parse the revoked certificate serials (formatted as colon-separated hex bytes, for instance `01:2c`) from the
+/// given certificate revocation list (CRL), which may be PEM or DER encoded
+pub fn parse_crl_revoked_serials(crl_bytes: &[u8]) -> Result<HashSet<String>, AppError> {
+    let pem;
+    let crl_der = if crl_bytes.starts_with(b"-----BEGIN") {
+        pem = parse_x509_pem(crl_bytes)
+            .map_err(|err| {
+                AppError::GenWithMsgAndErr("Failed to parse CRL PEM".to_string(), Box::new(err))
+            })?
+            .1;
+        pem.contents.as_slice()
+    } else {
+        crl_bytes
+    };
+
+    let crl = parse_x509_crl(crl_der)
+        .map_err(|err| {
+            AppError::GenWithMsgAndErr("Failed to parse CRL".to_string(), Box::new(err))
+        })?
+        .1;
+
+    Ok(crl
+        .iter_revoked_certificates()
+        .map(|revoked_cert| revoked_cert.raw_serial_as_string())
+        .collect())
+}
+
 /// Error handler function
 pub type ErrorHandlerFn = Box<dyn Fn(&AppError) + Send + 'static>;
 
+/// Newly-revoked certificate serials handler function
+pub type RevokedSerialsHandlerFn = Box<dyn Fn(&[String]) + Send + 'static>;
+
 /// Represents a certificate revocation list (CRL) file.
 /// Exposes the ability to re-parse entries when file has changed.
 pub struct CRLFile {
     path: String,
     crl_list: Arc<Mutex<Option<CertificateRevocationListDer<'static>>>>,
+    revoked_serials: Arc<Mutex<HashSet<String>>>,
+    on_revoked_serials_fn: Arc<Mutex<Option<RevokedSerialsHandlerFn>>>,
     reloading: Arc<Mutex<bool>>,
 }
 
@@ -137,10 +172,18 @@ impl CRLFile {
         CRLFile {
             path: filepath.to_string(),
             crl_list: Arc::new(Mutex::new(None)),
+            revoked_serials: Arc::new(Mutex::new(HashSet::new())),
+            on_revoked_serials_fn: Arc::new(Mutex::new(None)),
             reloading: Arc::new(Mutex::new(false)),
         }
     }
 
+    /// Set the function to handle certificate serials, which were newly revoked by a (re-)loaded list. This may be
+    /// set after the reloader has been spawned.
+    pub fn set_revoked_serials_handler(&self, on_revoked_serials_fn: RevokedSerialsHandlerFn) {
+        *self.on_revoked_serials_fn.lock().unwrap() = Some(on_revoked_serials_fn);
+    }
+
     /// file path accessor
     pub fn filepath(&self) -> &str {
         &self.path
@@ -168,6 +211,8 @@ impl CRLFile {
     ) {
         let crlfile_pathbuf = PathBuf::from(self.path.as_str());
         let crl_list = self.crl_list.clone();
+        let revoked_serials = self.revoked_serials.clone();
+        let on_revoked_serials_fn = self.on_revoked_serials_fn.clone();
         let is_reloading = self.reloading.clone();
         let recheck_delay = recheck_delay.unwrap_or(CRLFILE_RECHECK_DELAY_MSECS);
 
@@ -185,6 +230,8 @@ impl CRLFile {
                     &mut last_mtime,
                     &crlfile_pathbuf,
                     &crl_list,
+                    &revoked_serials,
+                    &on_revoked_serials_fn,
                     &on_critical_err_fn,
                 ) {
                     Ok(reloaded) => {
@@ -205,11 +252,14 @@ impl CRLFile {
         });
     }
 
-    /// Reload list if file has changed. Returns true if file was reloaded
+    /// Reload list if file has changed. Returns true if file was reloaded. Serials not revoked by the prior list
+    /// are passed to the revoked serials handler (if set).
     fn process_list_reload(
         last_mtime: &mut SystemTime,
         crlfile_pathbuf: &PathBuf,
         crl_list: &Arc<Mutex<Option<CertificateRevocationListDer<'static>>>>,
+        revoked_serials: &Arc<Mutex<HashSet<String>>>,
+        on_revoked_serials_fn: &Arc<Mutex<Option<RevokedSerialsHandlerFn>>>,
         on_critical_err_fn: &Option<ErrorHandlerFn>,
     ) -> Result<bool, AppError> {
         // Check if file has changed
@@ -231,11 +281,36 @@ impl CRLFile {
         // Parse/reload CRL list
         match load_crl_list(crlfile_pathbuf.to_str().unwrap()) {
             Ok(list) => {
+                let list_revoked_serials = parse_crl_revoked_serials(&list).map_err(|err| {
+                    AppError::GenWithMsgAndErr(
+                        format!("Error parsing CRL file: file={:?}", crlfile_pathbuf),
+                        Box::new(err),
+                    )
+                })?;
                 let _ = crl_list
                     .lock()
                     .unwrap()
                     .deref_mut()
                     .replace(CertificateRevocationListDer::from(list));
+
+                let mut new_revoked_serials: Vec<String> = {
+                    let mut revoked_serials = revoked_serials.lock().unwrap();
+                    let new_revoked_serials = list_revoked_serials
+                        .difference(&revoked_serials)
+                        .cloned()
+                        .collect();
+                    *revoked_serials = list_revoked_serials;
+                    new_revoked_serials
+                };
+                new_revoked_serials.sort();
+
+                if !new_revoked_serials.is_empty() {
+                    if let Some(on_revoked_serials_fn) =
+                        on_revoked_serials_fn.lock().unwrap().as_ref()
+                    {
+                        on_revoked_serials_fn(&new_revoked_serials);
+                    }
+                }
                 Ok(true)
             }
             Err(err) => Err(AppError::GenWithMsgAndErr(
@@ -273,6 +348,11 @@ mod crl_tests {
         "testdata",
         "revoked-crts-0-1.crl.pem",
     ];
+    const CRLFILE_REVOKED_CERTS_1_PATHPARTS: [&str; 3] = [
+        env!("CARGO_MANIFEST_DIR"),
+        "testdata",
+        "revoked-crts-1.crl.pem",
+    ];
     const CRLFILE_INVALID_PATHPARTS: [&str; 3] =
         [env!("CARGO_MANIFEST_DIR"), "testdata", "invalid.crl.pem"];
     const CRLFILE_MISSING_PATHPARTS: [&str; 3] =
@@ -451,6 +531,8 @@ mod crl_tests {
             &mut last_mtime,
             &crl_filepath,
             &crl_list,
+            &Arc::new(Mutex::new(HashSet::new())),
+            &Arc::new(Mutex::new(None)),
             &on_critical_error_fn,
         );
 
@@ -487,6 +569,8 @@ mod crl_tests {
             &mut last_mtime,
             &crl_filepath,
             &crl_list,
+            &Arc::new(Mutex::new(HashSet::new())),
+            &Arc::new(Mutex::new(None)),
             &on_critical_error_fn,
         );
 
@@ -505,6 +589,65 @@ mod crl_tests {
         assert_eq!(*invoked_error_fn.lock().unwrap(), false);
     }
 
+    #[test]
+    fn crlfile_process_list_reload_when_newly_revoked_serials() {
+        let crl_filepath: PathBuf = CRLFILE_REVOKED_CERTS_1_PATHPARTS.iter().collect();
+        let crl_list = Arc::new(Mutex::new(None));
+        let revoked_serials = Arc::new(Mutex::new(HashSet::from(["01:2c".to_string()])));
+        let handled_serials = Arc::new(Mutex::new(vec![]));
+        let handled_serials_copy = handled_serials.clone();
+        let on_revoked_serials_fn: Arc<Mutex<Option<RevokedSerialsHandlerFn>>> =
+            Arc::new(Mutex::new(Some(Box::new(move |serials: &[String]| {
+                handled_serials_copy
+                    .lock()
+                    .unwrap()
+                    .extend_from_slice(serials);
+            }))));
+        let mut last_mtime = SystemTime::now();
+
+        let result = CRLFile::process_list_reload(
+            &mut last_mtime,
+            &crl_filepath,
+            &crl_list,
+            &revoked_serials,
+            &on_revoked_serials_fn,
+            &None,
+        );
+
+        assert!(result.unwrap());
+        assert_eq!(*handled_serials.lock().unwrap(), vec!["01:2d".to_string()]);
+        assert_eq!(
+            *revoked_serials.lock().unwrap(),
+            HashSet::from(["01:2c".to_string(), "01:2d".to_string()])
+        );
+
+        // Unchanged list (re-read) has no newly-revoked serials
+        let mut last_mtime = SystemTime::now();
+        handled_serials.lock().unwrap().clear();
+
+        assert!(CRLFile::process_list_reload(
+            &mut last_mtime,
+            &crl_filepath,
+            &crl_list,
+            &revoked_serials,
+            &on_revoked_serials_fn,
+            &None,
+        )
+        .unwrap());
+        assert!(handled_serials.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn file_parse_crl_revoked_serials_when_valid_crlfile() {
+        let crl_filepath: PathBuf = CRLFILE_REVOKED_CERTS_1_PATHPARTS.iter().collect();
+        let crl_bytes = load_crl_list(crl_filepath.to_str().unwrap()).unwrap();
+
+        assert_eq!(
+            parse_crl_revoked_serials(&crl_bytes).unwrap(),
+            HashSet::from(["01:2c".to_string(), "01:2d".to_string()])
+        );
+    }
+
     #[test]
     fn crlfile_process_list_reload_when_invalid_filepath() {
         let crl_filepath: PathBuf = CRLFILE_MISSING_PATHPARTS.iter().collect();
@@ -522,6 +665,8 @@ mod crl_tests {
             &mut last_mtime,
             &crl_filepath,
             &crl_list,
+            &Arc::new(Mutex::new(HashSet::new())),
+            &Arc::new(Mutex::new(None)),
             &on_critical_error_fn,
         );
 
@@ -554,6 +699,8 @@ mod crl_tests {
             &mut last_mtime,
             &crl_filepath,
             &crl_list,
+            &Arc::new(Mutex::new(HashSet::new())),
+            &Arc::new(Mutex::new(None)),
             &on_critical_error_fn,
         );
 
@@ -1,9 +1,13 @@
+pub mod backoff;
+pub mod clock;
 pub mod config;
+pub mod conn_events;
 pub mod control;
 pub mod crypto;
 pub mod error;
 pub mod file;
 pub mod logging;
+pub mod metrics;
 pub mod model;
 pub mod net;
 pub mod proxy;
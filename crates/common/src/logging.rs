@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use log::{debug, error, info, log_enabled, warn, Level, LevelFilter};
 use log4rs::append::console::ConsoleAppender;
@@ -7,6 +10,9 @@ use log4rs::encode::json::JsonEncoder;
 use log4rs::Handle;
 use once_cell::sync::Lazy;
 
+/// Window, during which duplicate throttled log messages are suppressed
+pub const LOG_THROTTLE_WINDOW: Duration = Duration::from_secs(60);
+
 /// Logger singleton
 pub static LOG: Lazy<Mutex<Logger>> = Lazy::new(|| {
     Mutex::new(Logger {
@@ -15,6 +21,10 @@ pub static LOG: Lazy<Mutex<Logger>> = Lazy::new(|| {
     })
 });
 
+/// Log throttler singleton (used by `log_throttled`)
+pub static LOG_THROTTLER: Lazy<Mutex<LogThrottler>> =
+    Lazy::new(|| Mutex::new(LogThrottler::new(LOG_THROTTLE_WINDOW)));
+
 /// Logger debug log function
 pub fn debug(target: &str, msg: &str) {
     LOG.lock().unwrap().debug(target, msg);
@@ -35,6 +45,21 @@ pub fn error(target: &str, msg: &str) {
     LOG.lock().unwrap().error(target, msg);
 }
 
+/// Throttled logging (for hot error paths): identical messages for the same key (typically the `target!()` call
+/// site, which is also used as the log target) are suppressed for the throttle window. Once the window closes, a
+/// summary of the suppressed message count is logged.
+pub fn log_throttled(key: &str, level: LogLevel, msg: &str) {
+    let log_records = LOG_THROTTLER
+        .lock()
+        .unwrap()
+        .throttle(key, level, msg, Instant::now());
+
+    let logger = LOG.lock().unwrap();
+    for (level, msg) in log_records {
+        logger.log(key, level, &msg);
+    }
+}
+
 /// Simplify code location macro usage for log target
 #[macro_export]
 macro_rules! target {
@@ -46,6 +71,7 @@ macro_rules! target {
 pub use target;
 
 /// Construct logging implementation
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum LogLevel {
     DEBUG,
     INFO,
@@ -80,6 +106,16 @@ impl Logger {
         self.visitor = visitor;
     }
 
+    /// logging at given level
+    pub fn log(&self, target: &str, level: LogLevel, msg: &str) {
+        match level {
+            LogLevel::DEBUG => self.debug(target, msg),
+            LogLevel::INFO => self.info(target, msg),
+            LogLevel::WARN => self.warn(target, msg),
+            LogLevel::ERROR => self.error(target, msg),
+        }
+    }
+
     /// debug-level logging
     pub fn debug(&self, target: &str, msg: &str) {
         if log_enabled!(Level::Debug) {
@@ -120,3 +156,226 @@ impl Logger {
         }
     }
 }
+
+/// Samples high-volume log events (for instance, connection lifecycle events), selecting 1 in every N events.
+/// A rate of 0 or 1 selects all events.
+#[derive(Debug, Default)]
+pub struct LogSampler {
+    rate: u64,
+    event_count: AtomicU64,
+}
+
+impl LogSampler {
+    /// LogSampler constructor (1 in every `rate` events is selected)
+    pub fn new(rate: u64) -> Self {
+        Self {
+            rate,
+            event_count: AtomicU64::new(0),
+        }
+    }
+
+    /// Count new event, returning whether it is selected for logging. The first event is always selected.
+    pub fn sample(&self) -> bool {
+        let event_count = self.event_count.fetch_add(1, Ordering::Relaxed);
+        (self.rate <= 1) || (event_count.is_multiple_of(self.rate))
+    }
+}
+
+/// Duplicate message state for a throttle window
+#[derive(Debug)]
+struct ThrottledMessage {
+    level: LogLevel,
+    window_start: Instant,
+    suppressed_count: u64,
+}
+
+/// Collapses duplicate log messages (by key and message) within a time window
+#[derive(Debug)]
+pub struct LogThrottler {
+    window: Duration,
+    messages: HashMap<(String, String), ThrottledMessage>,
+}
+
+impl LogThrottler {
+    /// LogThrottler constructor
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            messages: HashMap::new(),
+        }
+    }
+
+    /// Process new log message (as of `now`). Returns the log records (level and message) to emit: summaries for
+    /// any closed windows with suppressed duplicates, followed by the message itself, unless it is a duplicate
+    /// within its window.
+    pub fn throttle(
+        &mut self,
+        key: &str,
+        level: LogLevel,
+        msg: &str,
+        now: Instant,
+    ) -> Vec<(LogLevel, String)> {
+        let mut log_records = self.flush(now);
+
+        match self.messages.get_mut(&(key.to_string(), msg.to_string())) {
+            Some(throttled_message) => throttled_message.suppressed_count += 1,
+            None => {
+                self.messages.insert(
+                    (key.to_string(), msg.to_string()),
+                    ThrottledMessage {
+                        level,
+                        window_start: now,
+                        suppressed_count: 0,
+                    },
+                );
+                log_records.push((level, msg.to_string()));
+            }
+        }
+
+        log_records
+    }
+
+    /// Close windows which have elapsed (as of `now`). Returns summary log records for those windows, which had
+    /// suppressed duplicate messages.
+    pub fn flush(&mut self, now: Instant) -> Vec<(LogLevel, String)> {
+        let window = self.window;
+        let mut log_records = Vec::new();
+
+        self.messages.retain(|(_, msg), throttled_message| {
+            if now.saturating_duration_since(throttled_message.window_start) < window {
+                return true;
+            }
+            if throttled_message.suppressed_count > 0 {
+                log_records.push((
+                    throttled_message.level,
+                    format!(
+                        "Suppressed duplicate log messages: count={}, msg={}",
+                        throttled_message.suppressed_count, msg
+                    ),
+                ));
+            }
+            false
+        });
+
+        log_records
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn count_sampled(log_sampler: &LogSampler, events: usize) -> usize {
+        (0..events).filter(|_| log_sampler.sample()).count()
+    }
+
+    #[test]
+    fn logsampler_sample_when_log_all() {
+        assert_eq!(count_sampled(&LogSampler::default(), 100), 100);
+        assert_eq!(count_sampled(&LogSampler::new(1), 100), 100);
+    }
+
+    #[test]
+    fn logsampler_sample_when_rate() {
+        let log_sampler = LogSampler::new(10);
+
+        assert!(log_sampler.sample());
+        assert_eq!(count_sampled(&log_sampler, 999), 99);
+        assert_eq!(count_sampled(&LogSampler::new(3), 10), 4);
+    }
+
+    #[test]
+    fn logsampler_sample_when_concurrent() {
+        let log_sampler = std::sync::Arc::new(LogSampler::new(50));
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let log_sampler = log_sampler.clone();
+                std::thread::spawn(move || count_sampled(&log_sampler, 2500))
+            })
+            .collect();
+        let sampled: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+        assert_eq!(sampled, 200);
+    }
+
+    #[test]
+    fn logthrottler_throttle_when_duplicate_messages() {
+        let mut log_throttler = LogThrottler::new(Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert_eq!(
+            log_throttler.throttle("key1", LogLevel::ERROR, "msg1", start),
+            vec![(LogLevel::ERROR, "msg1".to_string())]
+        );
+        for secs in 1..5 {
+            assert!(log_throttler
+                .throttle(
+                    "key1",
+                    LogLevel::ERROR,
+                    "msg1",
+                    start + Duration::from_secs(secs)
+                )
+                .is_empty());
+        }
+
+        assert_eq!(
+            log_throttler.throttle(
+                "key1",
+                LogLevel::ERROR,
+                "msg1",
+                start + Duration::from_secs(10)
+            ),
+            vec![
+                (
+                    LogLevel::ERROR,
+                    "Suppressed duplicate log messages: count=4, msg=msg1".to_string()
+                ),
+                (LogLevel::ERROR, "msg1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn logthrottler_throttle_when_distinct_messages() {
+        let mut log_throttler = LogThrottler::new(Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert_eq!(
+            log_throttler.throttle("key1", LogLevel::WARN, "msg1", start),
+            vec![(LogLevel::WARN, "msg1".to_string())]
+        );
+        assert_eq!(
+            log_throttler.throttle("key1", LogLevel::WARN, "msg2", start),
+            vec![(LogLevel::WARN, "msg2".to_string())]
+        );
+        assert_eq!(
+            log_throttler.throttle("key2", LogLevel::WARN, "msg1", start),
+            vec![(LogLevel::WARN, "msg1".to_string())]
+        );
+    }
+
+    #[test]
+    fn logthrottler_flush_when_window_closed() {
+        let mut log_throttler = LogThrottler::new(Duration::from_secs(10));
+        let start = Instant::now();
+
+        log_throttler.throttle("key1", LogLevel::ERROR, "msg1", start);
+        log_throttler.throttle("key1", LogLevel::ERROR, "msg1", start);
+        log_throttler.throttle("key2", LogLevel::WARN, "msg2", start);
+
+        assert!(log_throttler
+            .flush(start + Duration::from_secs(9))
+            .is_empty());
+        assert_eq!(
+            log_throttler.flush(start + Duration::from_secs(10)),
+            vec![(
+                LogLevel::ERROR,
+                "Suppressed duplicate log messages: count=1, msg=msg1".to_string()
+            )]
+        );
+        assert!(log_throttler.messages.is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use std::net::UdpSocket;
+
+use crate::error::AppError;
+use crate::logging::debug;
+use crate::proxy::proxy_key::ProxyKey;
+use crate::target;
+
+/// Metric names
+pub const METRIC_CONNECTIONS_OPENED: &str = "connections.opened";
+pub const METRIC_CONNECTIONS_CLOSED: &str = "connections.closed";
+pub const METRIC_AUTH_DENIED: &str = "auth.denied";
+pub const METRIC_BYTES_TRANSFERRED: &str = "bytes.transferred";
+pub const METRIC_PROXIES_ACTIVE: &str = "proxies.active";
+pub const METRIC_CONNECTION_PENDING_WRITE_BYTES: &str = "connection.pending_write_bytes";
+pub const METRIC_CONNECTION_QUEUED_EVENTS: &str = "connection.queued_events";
+pub const METRIC_SESSIONS_ACTIVE: &str = "sessions.active";
+pub const METRIC_SERVICE_THROUGHPUT: &str = "service.bytes_per_sec";
+
+/// Create auth denial counter name for the given response code
+pub fn auth_denied_metric_name(code: u16) -> String {
+    format!("{}.{}", METRIC_AUTH_DENIED, code)
+}
+
+/// Create per-user gauge name for the given connection metric (for instance, outbound queue depth)
+pub fn user_connection_metric_name(name: &str, user_id: u64) -> String {
+    format!("{}.user.{}", name, user_id)
+}
+
+/// Create per-service gauge name for the given metric (for instance, throughput)
+pub fn service_metric_name(name: &str, service_id: u64) -> String {
+    format!("{}.service.{}", name, service_id)
+}
+
+/// Destination for metrics (counters/gauges). Implementations decide the exposition format/transport,
+/// and must not fail (or block) the caller.
+pub trait MetricsSink: Send + Sync {
+    /// Increment counter by given value
+    fn incr_counter(&self, name: &str, value: u64);
+
+    /// Set gauge to given value
+    fn set_gauge(&self, name: &str, value: i64);
+
+    /// Increment bytes transferred counter for given proxy. Sinks needing per-proxy accounting may override this
+    fn incr_proxy_bytes(&self, _proxy_key: &ProxyKey, value: u64) {
+        self.incr_counter(METRIC_BYTES_TRANSFERRED, value);
+    }
+}
+
+/// Metrics sink, which discards all metrics
+#[derive(Default)]
+pub struct NoOpMetricsSink;
+
+impl MetricsSink for NoOpMetricsSink {
+    fn incr_counter(&self, _name: &str, _value: u64) {}
+
+    fn set_gauge(&self, _name: &str, _value: i64) {}
+}
+
+/// Metrics sink, which sends metrics to a statsd server (via UDP)
+pub struct StatsdMetricsSink {
+    socket: UdpSocket,
+    prefix: String,
+}
+
+impl StatsdMetricsSink {
+    /// StatsdMetricsSink constructor. Metric names will be prefixed with the given prefix (if non-empty).
+    pub fn new(server_addr: &str, prefix: &str) -> Result<Self, AppError> {
+        let socket = UdpSocket::bind("[::]:0")
+            .or_else(|_| UdpSocket::bind("0.0.0.0:0"))
+            .map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    "Error binding statsd UDP socket".to_string(),
+                    Box::new(err),
+                )
+            })?;
+        socket.connect(server_addr).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                format!("Error connecting statsd UDP socket: addr={}", server_addr),
+                Box::new(err),
+            )
+        })?;
+        socket.set_nonblocking(true).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                "Failed making statsd UDP socket non-blocking".to_string(),
+                Box::new(err),
+            )
+        })?;
+
+        Ok(Self {
+            socket,
+            prefix: prefix.to_string(),
+        })
+    }
+
+    /// Format statsd metric line
+    fn format_metric(&self, name: &str, value: &str, metric_type: &str) -> String {
+        if self.prefix.is_empty() {
+            format!("{}:{}|{}", name, value, metric_type)
+        } else {
+            format!("{}.{}:{}|{}", self.prefix, name, value, metric_type)
+        }
+    }
+
+    /// Send metric line to statsd server. Errors are logged and otherwise ignored.
+    fn send_metric(&self, metric: &str) {
+        if let Err(err) = self.socket.send(metric.as_bytes()) {
+            debug(
+                &target!(),
+                &format!(
+                    "Error sending statsd metric: metric={}, err={:?}",
+                    metric, &err
+                ),
+            );
+        }
+    }
+}
+
+impl MetricsSink for StatsdMetricsSink {
+    fn incr_counter(&self, name: &str, value: u64) {
+        self.send_metric(&self.format_metric(name, &value.to_string(), "c"));
+    }
+
+    fn set_gauge(&self, name: &str, value: i64) {
+        self.send_metric(&self.format_metric(name, &value.to_string(), "g"));
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::time::Duration;
+
+    fn create_statsd_server() -> UdpSocket {
+        let server_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server_socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        server_socket
+    }
+
+    fn recv_metric(server_socket: &UdpSocket) -> String {
+        let mut buffer = [0u8; 256];
+        let message_size = server_socket.recv(&mut buffer).unwrap();
+        String::from_utf8(buffer[..message_size].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn statsdsink_incr_counter_and_set_gauge_when_prefixed() {
+        let server_socket = create_statsd_server();
+        let server_addr = server_socket.local_addr().unwrap().to_string();
+        let metrics_sink = StatsdMetricsSink::new(&server_addr, "trust0.gateway").unwrap();
+
+        metrics_sink.incr_counter(METRIC_CONNECTIONS_OPENED, 1);
+        assert_eq!(
+            recv_metric(&server_socket),
+            "trust0.gateway.connections.opened:1|c"
+        );
+
+        metrics_sink.set_gauge(METRIC_PROXIES_ACTIVE, 5);
+        assert_eq!(
+            recv_metric(&server_socket),
+            "trust0.gateway.proxies.active:5|g"
+        );
+    }
+
+    #[test]
+    fn statsdsink_incr_counter_when_not_prefixed() {
+        let server_socket = create_statsd_server();
+        let server_addr = server_socket.local_addr().unwrap().to_string();
+        let metrics_sink = StatsdMetricsSink::new(&server_addr, "").unwrap();
+
+        metrics_sink.incr_counter(&auth_denied_metric_name(421), 1);
+        assert_eq!(recv_metric(&server_socket), "auth.denied.421:1|c");
+    }
+
+    #[test]
+    fn statsdsink_new_when_invalid_server_addr() {
+        if StatsdMetricsSink::new("invalid-addr", "").is_ok() {
+            panic!("Unexpected successful result");
+        }
+    }
+}
@@ -5,6 +5,11 @@ use serde_derive::{Deserialize, Serialize};
 pub struct ServiceAccess {
     pub user_id: u64,
     pub service_id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub justification: Option<String>,
+    /// Explicitly deny (rather than grant) access to the service
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deny: bool,
 }
 
 impl ServiceAccess {
@@ -13,6 +18,60 @@ impl ServiceAccess {
         Self {
             user_id,
             service_id,
+            justification: None,
+            deny: false,
         }
     }
 }
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn svcaccess_deserialize_when_no_justification() {
+        let access: ServiceAccess =
+            serde_json::from_str(r#"{"userId": 100, "serviceId": 200}"#).unwrap();
+
+        assert_eq!(access, ServiceAccess::new(100, 200));
+        assert!(access.justification.is_none());
+    }
+
+    #[test]
+    fn svcaccess_serialize_and_deserialize_when_justification() {
+        let access = ServiceAccess {
+            user_id: 100,
+            service_id: 200,
+            justification: Some("On-call support".to_string()),
+            deny: false,
+        };
+
+        let access_json = serde_json::to_string(&access).unwrap();
+
+        assert_eq!(
+            access_json,
+            r#"{"user_id":100,"service_id":200,"justification":"On-call support"}"#
+        );
+
+        let access_json = access_json
+            .replace("user_id", "userId")
+            .replace("service_id", "serviceId");
+        let roundtrip_access: ServiceAccess = serde_json::from_str(&access_json).unwrap();
+
+        assert_eq!(roundtrip_access, access);
+    }
+
+    #[test]
+    fn svcaccess_deserialize_when_deny() {
+        let access: ServiceAccess =
+            serde_json::from_str(r#"{"userId": 100, "serviceId": 200, "deny": true}"#).unwrap();
+
+        assert!(access.deny);
+        assert_eq!(
+            serde_json::to_string(&access).unwrap(),
+            r#"{"user_id":100,"service_id":200,"deny":true}"#
+        );
+    }
+}
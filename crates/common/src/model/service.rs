@@ -1,5 +1,14 @@
+use std::net::IpAddr;
+
 use serde_derive::{Deserialize, Serialize};
 
+use crate::error::AppError;
+
+/// Maximum length of a hostname
+const MAX_HOSTNAME_LEN: usize = 253;
+/// Maximum length of a hostname label
+const MAX_HOSTNAME_LABEL_LEN: usize = 63;
+
 #[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
 pub enum Transport {
     #[default]
@@ -7,6 +16,40 @@ pub enum Transport {
     UDP,
 }
 
+/// UDP service upstream target kind (absent is a unicast target)
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum UdpTarget {
+    /// Service host is a broadcast address
+    Broadcast,
+    /// Service host is a multicast group address
+    Multicast,
+}
+
+/// TLS client certificate authentication requirement
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ClientAuth {
+    /// Connections must present a valid client certificate
+    #[default]
+    Required,
+    /// Connections may omit a client certificate (and are mapped to the anonymous user)
+    Optional,
+}
+
+impl clap::ValueEnum for ClientAuth {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Required, Self::Optional]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        Some(match self {
+            Self::Required => clap::builder::PossibleValue::new("required"),
+            Self::Optional => clap::builder::PossibleValue::new("optional"),
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
 #[serde(rename_all(serialize = "snake_case", deserialize = "camelCase"))]
 pub struct Service {
@@ -15,6 +58,56 @@ pub struct Service {
     pub transport: Transport,
     pub host: String,
     pub port: u16,
+    #[serde(default, skip_serializing_if = "Service::is_zero")]
+    pub relay_retries: u16,
+    #[serde(default, skip_serializing_if = "Service::is_false")]
+    pub forward_empty_datagrams: bool,
+    /// Client source networks (CIDR notation) allowed to connect to the service (empty is unrestricted)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_client_cidrs: Vec<String>,
+    /// Upstream address resolution cache TTL (in seconds), overriding the gateway default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns_cache_ttl: Option<u64>,
+    /// Interval (in seconds) after which the user must re-authorize (via a control plane `start`) before new
+    /// connections to the service are allowed (absent means no re-authorization is required)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reauth_interval: Option<u64>,
+    /// Local (source) address to bind upstream connections to, overriding the gateway default (absent means the
+    /// OS selects the source address)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_bind_addr: Option<IpAddr>,
+    /// Relay TCP proxy data using the fast (tight read-write loop) relay mode, for high-throughput services
+    #[serde(default, skip_serializing_if = "Service::is_false")]
+    pub fast_relay: bool,
+    /// TLS client certificate authentication requirement, overriding the gateway default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_auth: Option<ClientAuth>,
+    /// UDP upstream target kind, for services relaying to a broadcast address or multicast group (absent is a
+    /// unicast target). Replies are accepted from any responder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub udp_target: Option<UdpTarget>,
+    /// Connection lifecycle (open/close) log sampling rate: 1 in every N connections is logged (absent logs all)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_sample_rate: Option<u64>,
+    /// Maximum bytes relayed from the (TCP) service to the client, per connection. The connection is closed when
+    /// exceeded (absent is unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<u64>,
+    /// Whether the service may be proxied. Non-proxyable services are (control plane only) catalog entries, which are
+    /// listed, but can't be started and aren't advertised as ALPN protocols
+    #[serde(
+        default = "Service::default_proxyable",
+        skip_serializing_if = "Service::is_default_proxyable"
+    )]
+    pub proxyable: bool,
+    /// Maximum new connections per second to the service, for each user. Connections over the rate are denied
+    /// (absent is unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_qps: Option<u32>,
+    /// Static bytes written to the (TCP) service upstream immediately after connecting, before any client data is
+    /// relayed (base64 in JSON, empty means none)
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "base64_bytes")]
+    pub upstream_prefix: Vec<u8>,
 }
 
 impl Service {
@@ -26,8 +119,227 @@ impl Service {
             transport: transport.clone(),
             host: host.to_string(),
             port,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
         }
     }
 }
 
+impl Service {
+    /// Default (serde) value for `proxyable`
+    fn default_proxyable() -> bool {
+        true
+    }
+
+    /// Whether `proxyable` is its default value (not serialized)
+    fn is_default_proxyable(proxyable: &bool) -> bool {
+        *proxyable
+    }
+
+    /// Whether a count is zero (not serialized)
+    fn is_zero(count: &u16) -> bool {
+        *count == 0
+    }
+
+    /// Whether a flag is false (not serialized)
+    fn is_false(flag: &bool) -> bool {
+        !*flag
+    }
+
+    /// Validate and normalize service host (whitespace trimmed, lowercased). The host must be a hostname or an IP
+    /// literal (no scheme, port, path, ...). Validation errors include the service ID.
+    pub fn normalize_host(&mut self) -> Result<(), AppError> {
+        let host = self.host.trim().to_lowercase();
+
+        if host.parse::<IpAddr>().is_err() && !Self::is_valid_hostname(&host) {
+            return Err(AppError::General(format!(
+                "Invalid service host (expecting a hostname or IP address): svc_id={}, host={:?}",
+                self.service_id, &self.host
+            )));
+        }
+
+        self.host = host;
+        Ok(())
+    }
+
+    /// Returns whether given (lowercased) value is a valid hostname: dot-separated labels of alphanumerics and
+    /// (interior) hyphens
+    fn is_valid_hostname(host: &str) -> bool {
+        !host.is_empty()
+            && (host.len() <= MAX_HOSTNAME_LEN)
+            && host.split('.').all(|label| {
+                !label.is_empty()
+                    && (label.len() <= MAX_HOSTNAME_LABEL_LEN)
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || (c == '-'))
+            })
+    }
+}
+
 unsafe impl Send for Service {}
+
+/// Serde (de)serialization of a byte vector as a base64 (standard alphabet) string
+mod base64_bytes {
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded.as_bytes())
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn create_service(host: &str) -> Service {
+        Service::new(200, "Service200", &Transport::TCP, host, 8200)
+    }
+
+    #[test]
+    fn service_deserialize_when_proxyable_absent_and_false() {
+        let service: Service = serde_json::from_str(
+            r#"{"serviceId":200,"name":"Service200","transport":"TCP","host":"localhost","port":8200}"#,
+        )
+        .unwrap();
+        assert!(service.proxyable);
+        assert!(!serde_json::to_string(&service)
+            .unwrap()
+            .contains("proxyable"));
+
+        let service: Service = serde_json::from_str(
+            r#"{"serviceId":200,"name":"Service200","transport":"TCP","host":"localhost","port":8200,"proxyable":false}"#,
+        )
+        .unwrap();
+        assert!(!service.proxyable);
+        assert!(serde_json::to_string(&service)
+            .unwrap()
+            .contains("\"proxyable\":false"));
+    }
+
+    #[test]
+    fn service_deserialize_when_upstream_prefix_absent_and_present() {
+        let service: Service = serde_json::from_str(
+            r#"{"serviceId":200,"name":"Service200","transport":"TCP","host":"localhost","port":8200}"#,
+        )
+        .unwrap();
+        assert!(service.upstream_prefix.is_empty());
+        assert!(!serde_json::to_string(&service)
+            .unwrap()
+            .contains("upstream_prefix"));
+
+        let service: Service = serde_json::from_str(
+            r#"{"serviceId":200,"name":"Service200","transport":"TCP","host":"localhost","port":8200,"upstreamPrefix":"UFJPWFkgVENQNAo="}"#,
+        )
+        .unwrap();
+        assert_eq!(service.upstream_prefix, b"PROXY TCP4\n".to_vec());
+        assert!(serde_json::to_string(&service)
+            .unwrap()
+            .contains("\"upstream_prefix\":\"UFJPWFkgVENQNAo=\""));
+    }
+
+    #[test]
+    fn service_serialize_when_relay_options_default_and_set() {
+        let mut service = create_service("localhost");
+        let service_json = serde_json::to_string(&service).unwrap();
+        assert!(!service_json.contains("relay_retries"));
+        assert!(!service_json.contains("forward_empty_datagrams"));
+        assert!(!service_json.contains("fast_relay"));
+
+        service.relay_retries = 3;
+        service.forward_empty_datagrams = true;
+        service.fast_relay = true;
+        let service_json = serde_json::to_string(&service).unwrap();
+        assert!(service_json.contains("\"relay_retries\":3"));
+        assert!(service_json.contains("\"forward_empty_datagrams\":true"));
+        assert!(service_json.contains("\"fast_relay\":true"));
+    }
+
+    #[test]
+    fn service_deserialize_when_upstream_prefix_invalid_base64() {
+        let result: Result<Service, _> = serde_json::from_str(
+            r#"{"serviceId":200,"name":"Service200","transport":"TCP","host":"localhost","port":8200,"upstreamPrefix":"not base64!"}"#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn service_normalize_host_when_valid_hostname() {
+        let mut service = create_service("  Echo-1.Example.COM \t");
+
+        service.normalize_host().unwrap();
+
+        assert_eq!(service.host, "echo-1.example.com");
+
+        let mut service = create_service("localhost");
+
+        service.normalize_host().unwrap();
+
+        assert_eq!(service.host, "localhost");
+    }
+
+    #[test]
+    fn service_normalize_host_when_ip_address() {
+        let mut service = create_service(" 10.0.0.5 ");
+
+        service.normalize_host().unwrap();
+
+        assert_eq!(service.host, "10.0.0.5");
+
+        let mut service = create_service("FE80::1");
+
+        service.normalize_host().unwrap();
+
+        assert_eq!(service.host, "fe80::1");
+    }
+
+    #[test]
+    fn service_normalize_host_when_malformed_hosts() {
+        for host in [
+            "",
+            "   ",
+            "http://echohost",
+            "echohost/path",
+            "echohost:8200",
+            "[::1]",
+            "echo host",
+            "-echohost",
+            "echohost-.example.com",
+            "echohost..example.com",
+            "echo_host",
+            &format!("{}.com", "a".repeat(64)),
+        ] {
+            let mut service = create_service(host);
+
+            match service.normalize_host() {
+                Ok(()) => panic!("Unexpected successful result: host={:?}", host),
+                Err(err) => assert!(err.to_string().contains("svc_id=200")),
+            }
+            assert_eq!(service.host, host);
+        }
+    }
+}
@@ -6,6 +6,9 @@ pub struct User {
     pub user_id: u64,
     pub name: String,
     pub status: Status,
+    /// Maximum bytes transferred (per quota window) by user's service proxies. Overrides gateway default (if supplied)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_quota: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
@@ -23,6 +26,7 @@ impl User {
             user_id,
             name: name.to_string(),
             status,
+            byte_quota: None,
         }
     }
 }
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use crate::error::AppError;
+
+/// IP network in CIDR notation (for instance "10.1.0.0/16" or "fd00::/8"). A bare address is treated as a
+/// single host network.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct IpCidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpCidr {
+    /// Parse CIDR notation network
+    pub fn parse(cidr: &str) -> Result<Self, AppError> {
+        let invalid_cidr_err =
+            || AppError::General(format!("Invalid CIDR network: cidr={}", cidr.trim()));
+
+        let (network, prefix_len) = match cidr.trim().split_once('/') {
+            Some((network, prefix_len)) => (
+                IpAddr::from_str(network).map_err(|_| invalid_cidr_err())?,
+                Some(u8::from_str(prefix_len).map_err(|_| invalid_cidr_err())?),
+            ),
+            None => (
+                IpAddr::from_str(cidr.trim()).map_err(|_| invalid_cidr_err())?,
+                None,
+            ),
+        };
+
+        let max_prefix_len = Self::max_prefix_len(&network);
+        let prefix_len = prefix_len.unwrap_or(max_prefix_len);
+
+        if prefix_len > max_prefix_len {
+            return Err(invalid_cidr_err());
+        }
+
+        Ok(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    /// Returns whether given address is within this network. IPv4-mapped IPv6 addresses are treated as IPv4.
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        match (self.network, addr.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(addr)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                (u32::from(network) & mask) == (u32::from(addr) & mask)
+            }
+            (IpAddr::V6(network), IpAddr::V6(addr)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                (u128::from(network) & mask) == (u128::from(addr) & mask)
+            }
+            _ => false,
+        }
+    }
+
+    /// Maximum prefix length for given address' family
+    fn max_prefix_len(addr: &IpAddr) -> u8 {
+        match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+}
+
+impl fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+/// Returns whether given address is within any of the given CIDR networks. Invalid networks result in an error.
+pub fn contains_addr(cidrs: &[String], addr: &IpAddr) -> Result<bool, AppError> {
+    for cidr in cidrs {
+        if IpCidr::parse(cidr)?.contains(addr) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn ipcidr_parse_when_valid_networks() {
+        assert_eq!(
+            IpCidr::parse("10.1.0.0/16").unwrap().to_string(),
+            "10.1.0.0/16"
+        );
+        assert_eq!(IpCidr::parse(" fd00::/8 ").unwrap().to_string(), "fd00::/8");
+        assert_eq!(
+            IpCidr::parse("192.168.1.10").unwrap().to_string(),
+            "192.168.1.10/32"
+        );
+        assert_eq!(IpCidr::parse("0.0.0.0/0").unwrap().to_string(), "0.0.0.0/0");
+    }
+
+    #[test]
+    fn ipcidr_parse_when_invalid_networks() {
+        for cidr in [
+            "10.1.0.0/33",
+            "fd00::/129",
+            "10.1.0/16",
+            "10.1.0.0/x",
+            "host1/8",
+            "",
+        ] {
+            if IpCidr::parse(cidr).is_ok() {
+                panic!("Unexpected successful result: cidr={}", cidr);
+            }
+        }
+    }
+
+    #[test]
+    fn ipcidr_contains() {
+        let cidr_v4 = IpCidr::parse("10.1.0.0/16").unwrap();
+        let cidr_v6 = IpCidr::parse("fd00::/8").unwrap();
+        let cidr_all = IpCidr::parse("0.0.0.0/0").unwrap();
+
+        assert!(cidr_v4.contains(&IpAddr::from_str("10.1.200.3").unwrap()));
+        assert!(cidr_v4.contains(&IpAddr::from_str("::ffff:10.1.0.1").unwrap()));
+        assert!(!cidr_v4.contains(&IpAddr::from_str("10.2.0.1").unwrap()));
+        assert!(!cidr_v4.contains(&IpAddr::from_str("fd00::1").unwrap()));
+        assert!(cidr_v6.contains(&IpAddr::from_str("fd12::1").unwrap()));
+        assert!(!cidr_v6.contains(&IpAddr::from_str("fe80::1").unwrap()));
+        assert!(cidr_all.contains(&IpAddr::from_str("203.0.113.9").unwrap()));
+    }
+
+    #[test]
+    fn cidr_contains_addr() {
+        let cidrs = vec!["10.1.0.0/16".to_string(), "192.168.1.10".to_string()];
+
+        assert!(contains_addr(&cidrs, &IpAddr::from_str("192.168.1.10").unwrap()).unwrap());
+        assert!(!contains_addr(&cidrs, &IpAddr::from_str("192.168.1.11").unwrap()).unwrap());
+        assert!(
+            contains_addr(&["bad".to_string()], &IpAddr::from_str("10.1.0.1").unwrap()).is_err()
+        );
+    }
+}
@@ -1,5 +1,7 @@
+pub mod cidr;
 pub mod stream_utils;
 pub mod tcp_server;
 pub mod tls_client;
 pub mod tls_server;
 pub mod udp_server;
+pub mod worker_pool;
@@ -1,9 +1,11 @@
 use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::{Arc, Mutex};
 
 use rustls::{ClientConnection, ServerConnection, StreamOwned};
 
 use crate::error::AppError;
+use crate::model::service::UdpTarget;
 
 const TCP_READ_BLOCK_SIZE: usize = 1024;
 const UDP_RECV_BUFFER_SIZE: usize = 64 * 1024;
@@ -14,7 +16,7 @@ impl StreamReaderWriter for std::net::TcpStream {}
 impl StreamReaderWriter for StreamOwned<ClientConnection, std::net::TcpStream> {}
 impl StreamReaderWriter for StreamOwned<ServerConnection, std::net::TcpStream> {}
 
-/// Read TCP stream content
+/// Read TCP stream content (a zero-byte read, without any prior data, is a stream EOF)
 pub fn read_tcp_stream(
     stream_reader: &mut Arc<Mutex<Box<dyn StreamReaderWriter>>>,
 ) -> Result<Vec<u8>, AppError> {
@@ -22,6 +24,7 @@ pub fn read_tcp_stream(
     let mut buff_chunk = [0; TCP_READ_BLOCK_SIZE];
     loop {
         let bytes_read = match stream_reader.lock().unwrap().read(&mut buff_chunk) {
+            Ok(0) if buffer.is_empty() => return Err(AppError::StreamEOF),
             Ok(bytes_read) => bytes_read,
 
             Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
@@ -109,6 +112,115 @@ pub fn write_mio_udp_socket(
     }
 }
 
+/// Write UDP socket content to given target address (for sockets not connected to a single peer)
+pub fn write_mio_udp_socket_to(
+    udp_socket: &mio::net::UdpSocket,
+    buffer: &[u8],
+    target_addr: &SocketAddr,
+) -> Result<(), AppError> {
+    match udp_socket.send_to(buffer, *target_addr) {
+        Ok(_) => Ok(()),
+
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Err(AppError::StreamEOF),
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Err(AppError::StreamEOF),
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => Err(AppError::WouldBlock),
+        Err(err) => Err(AppError::GenWithMsgAndErr(
+            format!(
+                "Error writing to udp socket: socket={:?}, target={:?}",
+                &udp_socket, target_addr
+            ),
+            Box::new(err),
+        )),
+    }
+}
+
+/// Setup UDP socket to relay to a broadcast address (enabling `SO_BROADCAST`) or a multicast group (joining the
+/// group on the default interface). The socket isn't connected to the target, so replies from any responder are
+/// received.
+pub fn setup_udp_target_socket(
+    udp_socket: &std::net::UdpSocket,
+    udp_target: UdpTarget,
+    target_addr: &SocketAddr,
+) -> Result<(), AppError> {
+    let result = match (udp_target, target_addr.ip()) {
+        (UdpTarget::Broadcast, _) => udp_socket.set_broadcast(true),
+        (UdpTarget::Multicast, IpAddr::V4(group_addr)) if group_addr.is_multicast() => {
+            udp_socket.join_multicast_v4(&group_addr, &Ipv4Addr::UNSPECIFIED)
+        }
+        (UdpTarget::Multicast, IpAddr::V6(group_addr)) if group_addr.is_multicast() => {
+            udp_socket.join_multicast_v6(&group_addr, 0)
+        }
+        (UdpTarget::Multicast, _) => {
+            return Err(AppError::General(format!(
+                "Target is not a multicast group address: target={:?}",
+                target_addr
+            )))
+        }
+    };
+
+    result.map_err(|err| {
+        AppError::GenWithMsgAndErr(
+            format!(
+                "Error setting up udp socket for target: socket={:?}, target_kind={:?}, target={:?}",
+                &udp_socket, udp_target, target_addr
+            ),
+            Box::new(err),
+        )
+    })
+}
+
+/// Connect TCP stream to given remote address. If a bind address is given, the stream is bound to it (any local
+/// port) prior to connecting, so the connection originates from that (source) address. Otherwise, the OS selects
+/// the source address.
+pub fn connect_tcp_stream(
+    remote_addr: &SocketAddr,
+    bind_addr: Option<IpAddr>,
+) -> Result<std::net::TcpStream, AppError> {
+    let map_connect_err = |err: io::Error| {
+        AppError::GenWithMsgAndErr(
+            format!(
+                "Error connecting tcp stream: remote_addr={:?}, bind_addr={:?}",
+                remote_addr, bind_addr
+            ),
+            Box::new(err),
+        )
+    };
+
+    let bind_addr = match bind_addr {
+        Some(bind_addr) => SocketAddr::new(bind_addr, 0),
+        None => return std::net::TcpStream::connect(remote_addr).map_err(map_connect_err),
+    };
+
+    let socket = socket2::Socket::new(
+        socket2::Domain::for_address(*remote_addr),
+        socket2::Type::STREAM,
+        Some(socket2::Protocol::TCP),
+    )
+    .map_err(map_connect_err)?;
+    socket.bind(&bind_addr.into()).map_err(map_connect_err)?;
+    socket
+        .connect(&(*remote_addr).into())
+        .map_err(map_connect_err)?;
+
+    Ok(socket.into())
+}
+
+/// Set TCP stream to close abortively (SO_LINGER of zero): once the socket is closed, the connection is reset (RST),
+/// rather than gracefully closed (FIN)
+pub fn set_abortive_close(tcp_stream: &std::net::TcpStream) -> Result<(), AppError> {
+    socket2::SockRef::from(tcp_stream)
+        .set_linger(Some(std::time::Duration::ZERO))
+        .map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                format!(
+                    "Error setting tcp stream abortive close: stream={:?}",
+                    &tcp_stream
+                ),
+                Box::new(err),
+            )
+        })
+}
+
 /// Clone std TcpStream
 pub fn clone_std_tcp_stream(
     tcp_stream: &std::net::TcpStream,
@@ -158,4 +270,105 @@ pub mod tests {
             fn write_all(&mut self, buf: &[u8]) -> io::Result<()>;
         }
     }
+
+    // tests
+    // =====
+
+    #[test]
+    fn streamutils_connect_tcp_stream_when_no_bind_addr() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let tcp_stream = connect_tcp_stream(&listener.local_addr().unwrap(), None).unwrap();
+
+        assert_eq!(
+            tcp_stream.peer_addr().unwrap(),
+            listener.local_addr().unwrap()
+        );
+    }
+
+    #[test]
+    fn streamutils_connect_tcp_stream_when_bind_addr() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let bind_addr: IpAddr = "127.0.0.2".parse().unwrap();
+
+        let tcp_stream =
+            connect_tcp_stream(&listener.local_addr().unwrap(), Some(bind_addr)).unwrap();
+        let (_, accepted_peer_addr) = listener.accept().unwrap();
+
+        assert_eq!(tcp_stream.local_addr().unwrap().ip(), bind_addr);
+        assert_eq!(accepted_peer_addr.ip(), bind_addr);
+    }
+
+    #[test]
+    fn streamutils_connect_tcp_stream_when_unusable_bind_addr() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let result = connect_tcp_stream(
+            &listener.local_addr().unwrap(),
+            Some("192.0.2.1".parse().unwrap()),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn streamutils_setup_udp_target_socket_when_broadcast() {
+        let udp_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        assert!(!udp_socket.broadcast().unwrap());
+
+        setup_udp_target_socket(
+            &udp_socket,
+            UdpTarget::Broadcast,
+            &"255.255.255.255:8600".parse().unwrap(),
+        )
+        .unwrap();
+
+        assert!(udp_socket.broadcast().unwrap());
+    }
+
+    #[test]
+    fn streamutils_setup_udp_target_socket_when_multicast() {
+        let udp_socket = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        let group_addr: SocketAddr = "239.255.10.1:8600".parse().unwrap();
+
+        setup_udp_target_socket(&udp_socket, UdpTarget::Multicast, &group_addr).unwrap();
+
+        // joining twice fails (already a member of the group)
+        assert!(udp_socket
+            .join_multicast_v4(&"239.255.10.1".parse().unwrap(), &Ipv4Addr::UNSPECIFIED)
+            .is_err());
+        udp_socket
+            .leave_multicast_v4(&"239.255.10.1".parse().unwrap(), &Ipv4Addr::UNSPECIFIED)
+            .unwrap();
+    }
+
+    #[test]
+    fn streamutils_setup_udp_target_socket_when_multicast_and_unicast_target() {
+        let udp_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        let result = setup_udp_target_socket(
+            &udp_socket,
+            UdpTarget::Multicast,
+            &"127.0.0.1:8600".parse().unwrap(),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn streamutils_write_mio_udp_socket_to() {
+        let target_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        target_socket
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+        let udp_socket =
+            mio::net::UdpSocket::from_std(std::net::UdpSocket::bind("127.0.0.1:0").unwrap());
+
+        write_mio_udp_socket_to(&udp_socket, b"hello", &target_socket.local_addr().unwrap())
+            .unwrap();
+
+        let mut buffer = [0u8; 16];
+        let (size, _) = target_socket.recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..size], b"hello");
+    }
 }
@@ -85,6 +85,18 @@ impl Server {
 
     /// Poll and dispatch new listener connections
     pub fn poll_new_connections(&mut self) -> Result<(), AppError> {
+        self.start_polling()?;
+
+        while self.poll_once()? {
+            // Add delay between accepts
+            thread::sleep(Duration::from_millis(30));
+        }
+
+        Ok(())
+    }
+
+    /// Enter polling state, for when the poll loop is driven externally (via `poll_once`)
+    pub fn start_polling(&mut self) -> Result<(), AppError> {
         self.assert_listening()?;
 
         if self.polling {
@@ -104,27 +116,28 @@ impl Server {
             ),
         );
 
-        loop {
-            // Accept new connection (non-blocking)
-            if let Err(err) = self.accept() {
-                match err {
-                    AppError::WouldBlock => {}
-                    _ => error(&target!(), &format!("{:?}", err)),
-                }
-            }
+        Ok(())
+    }
 
-            // Check if shutdown requested
-            if self.visitor.lock().unwrap().get_shutdown_requested() {
-                self.polling = false;
-                self.closing = true;
+    /// Perform a single (non-blocking) poll iteration: accept new connection and check for shutdown.
+    /// Returns whether polling should continue.
+    pub fn poll_once(&mut self) -> Result<bool, AppError> {
+        // Accept new connection (non-blocking)
+        if let Err(err) = self.accept() {
+            match err {
+                AppError::WouldBlock => {}
+                _ => error(&target!(), &format!("{:?}", err)),
             }
+        }
 
-            if !self.polling {
-                break;
-            }
+        // Check if shutdown requested
+        if self.visitor.lock().unwrap().get_shutdown_requested() {
+            self.polling = false;
+            self.closing = true;
+        }
 
-            // Add delay between accepts
-            thread::sleep(Duration::from_millis(30));
+        if self.polling {
+            return Ok(true);
         }
 
         info(
@@ -139,7 +152,7 @@ impl Server {
             self.perform_shutdown();
         }
 
-        Ok(())
+        Ok(false)
     }
 
     /// shutdown for poller and listener
@@ -1,16 +1,22 @@
+use std::collections::VecDeque;
+use std::fmt;
 use std::io::{Read, Write};
-use std::net::{Shutdown, TcpStream};
-use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
-use std::time::Duration;
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{io, thread};
 
 use crate::crypto::alpn;
 use anyhow::Result;
 use pki_types::CertificateDer;
 use rustls::{self, StreamOwned};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::error::AppError;
-use crate::logging::error;
+use crate::logging::{debug, error};
+use crate::net::stream_utils;
 use crate::target;
 
 const READ_BLOCK_SIZE: usize = 1024;
@@ -25,6 +31,12 @@ pub trait TlsConnection {
 
     /// Retrieves the protocol agreed with the peer via ALPN.
     fn alpn_protocol(&self) -> Option<Vec<u8>>;
+
+    /// Retrieves the negotiated TLS session parameters.
+    fn session_info(&self) -> TlsSessionInfo;
+
+    /// Retrieves the peer's (socket) address.
+    fn peer_addr(&self) -> Option<SocketAddr>;
 }
 
 impl TlsConnection for TlsServerConnection {
@@ -37,6 +49,69 @@ impl TlsConnection for TlsServerConnection {
             .alpn_protocol()
             .map(|proto_bytes| proto_bytes.to_vec())
     }
+
+    fn session_info(&self) -> TlsSessionInfo {
+        TlsSessionInfo::new(&self.conn)
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.sock.peer_addr().ok()
+    }
+}
+
+/// Negotiated TLS session parameters (available once handshake has completed)
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default, Debug)]
+pub struct TlsSessionInfo {
+    pub protocol_version: Option<String>,
+    pub cipher_suite: Option<String>,
+    pub alpn_protocol: Option<String>,
+    /// Whether a prior (TLS 1.3) session was resumed. Early (0-RTT) data is never accepted on resumed sessions, as
+    /// server configs leave `max_early_data_size` at zero.
+    #[serde(default)]
+    pub resumed: bool,
+}
+
+impl TlsSessionInfo {
+    /// TlsSessionInfo constructor
+    pub fn new(tls_conn: &rustls::ServerConnection) -> Self {
+        Self {
+            protocol_version: tls_conn
+                .protocol_version()
+                .map(|version| format!("{:?}", version)),
+            cipher_suite: tls_conn
+                .negotiated_cipher_suite()
+                .map(|suite| format!("{:?}", suite.suite())),
+            alpn_protocol: tls_conn
+                .alpn_protocol()
+                .map(|proto_bytes| String::from_utf8_lossy(proto_bytes).to_string()),
+            resumed: tls_conn.received_resumption_data().is_some(),
+        }
+    }
+}
+
+impl fmt::Display for TlsSessionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "version={}, cipher={}, alpn={}, resumed={}",
+            self.protocol_version.as_deref().unwrap_or("None"),
+            self.cipher_suite.as_deref().unwrap_or("None"),
+            self.alpn_protocol.as_deref().unwrap_or("None"),
+            self.resumed
+        )
+    }
+}
+
+/// Outcome of a connection visitor's handshake hook (invoked with the connection's first read content)
+pub enum HandshakeOutcome {
+    /// Continue with normal processing of the given content (the initial content, less any consumed preamble)
+    Proceed(Vec<u8>),
+
+    /// Reject the connection (it will be closed), for given reason
+    Reject(String),
+
+    /// Switch connection handling to given visitor, which processes the given content
+    Switch(Box<dyn ConnectionVisitor>, Vec<u8>),
 }
 
 /// Connection event message channel
@@ -44,6 +119,17 @@ pub enum ConnectionEvent {
     Closing,
     Closed,
     Write(Vec<u8>),
+    /// Update connection's write rate limit (bytes per second), None removes the limit
+    SetRateLimit(Option<u64>),
+}
+
+/// Reason for closing a connection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CloseReason {
+    /// Regular close (for instance, client quit or connection error)
+    Normal,
+    /// Connection denied by (security) policy
+    PolicyDenial,
 }
 
 impl ConnectionEvent {
@@ -53,6 +139,130 @@ impl ConnectionEvent {
     }
 }
 
+/// Writer, which sends the written content (in chunks of at most `chunk_size` bytes) as connection write
+/// events. Used to incrementally emit large responses, rather than buffering them entirely.
+pub struct ChunkedEventWriter {
+    event_channel_sender: Sender<ConnectionEvent>,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+}
+
+impl ChunkedEventWriter {
+    /// ChunkedEventWriter constructor
+    pub fn new(event_channel_sender: Sender<ConnectionEvent>, chunk_size: usize) -> Self {
+        let chunk_size = chunk_size.max(1);
+        Self {
+            event_channel_sender,
+            chunk_size,
+            buffer: Vec::with_capacity(chunk_size),
+        }
+    }
+
+    /// Send buffered content as a write event
+    fn send_buffer(&mut self) -> io::Result<()> {
+        let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(self.chunk_size));
+        self.event_channel_sender
+            .send(ConnectionEvent::Write(chunk))
+            .map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    format!("Error sending write event: err={:?}", &err),
+                )
+            })
+    }
+}
+
+impl Write for ChunkedEventWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let write_size = buf.len().min(self.chunk_size - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..write_size]);
+        if self.buffer.len() == self.chunk_size {
+            self.send_buffer()?;
+        }
+        Ok(write_size)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.send_buffer()?;
+        }
+        Ok(())
+    }
+}
+
+/// Connection outbound queue depth: events received from the event channel, which are not yet (fully) processed.
+/// Shared, so a slow consumer's write backlog may be observed while the connection is busy writing.
+#[derive(Default, Debug)]
+pub struct OutboundQueueDepth {
+    pending_write_bytes: AtomicUsize,
+    queued_events: AtomicUsize,
+}
+
+impl OutboundQueueDepth {
+    /// Byte count of queued (un-flushed) writes
+    pub fn get_pending_write_bytes(&self) -> usize {
+        self.pending_write_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Count of queued events (of all types)
+    pub fn get_queued_events(&self) -> usize {
+        self.queued_events.load(Ordering::Relaxed)
+    }
+
+    /// Account for event added to the queue
+    fn record_enqueued(&self, event: &ConnectionEvent) {
+        self.queued_events.fetch_add(1, Ordering::Relaxed);
+        if let ConnectionEvent::Write(data) = event {
+            self.pending_write_bytes
+                .fetch_add(data.len(), Ordering::Relaxed);
+        }
+    }
+
+    /// Account for event processed (and removed from the queue)
+    fn record_processed(&self, event: &ConnectionEvent) {
+        self.queued_events.fetch_sub(1, Ordering::Relaxed);
+        if let ConnectionEvent::Write(data) = event {
+            self.pending_write_bytes
+                .fetch_sub(data.len(), Ordering::Relaxed);
+        }
+    }
+}
+
+/// Paces connection writes to a maximum rate (bytes per second), measured from when the limit was set
+struct WriteThrottle {
+    bytes_per_sec: u64,
+    start_time: Instant,
+    bytes_written: u64,
+}
+
+impl WriteThrottle {
+    /// WriteThrottle constructor
+    fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            start_time: Instant::now(),
+            bytes_written: 0,
+        }
+    }
+
+    /// Account for write of given size, returning delay required (as of `now`) to stay within the rate limit
+    fn reserve(&mut self, bytes: usize, now: Instant) -> Duration {
+        self.bytes_written = self.bytes_written.saturating_add(bytes as u64);
+        let write_deadline =
+            Duration::from_secs_f64(self.bytes_written as f64 / self.bytes_per_sec as f64);
+
+        write_deadline.saturating_sub(now.saturating_duration_since(self.start_time))
+    }
+}
+
+/// Read backpressure thresholds, on the visitor's pending (accepted, but not yet consumed) read bytes. Reads are
+/// paused once the pending bytes reach the high-water mark, and resumed once they drain to the low-water mark
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ReadWatermarks {
+    pub high: usize,
+    pub low: usize,
+}
+
 /// This is a TLS client connection which has been accepted by the server, and is currently being served.
 ///
 /// It has a TCP-level stream, a TLS-level connection state, and some other state/metadata.
@@ -60,7 +270,15 @@ pub struct Connection {
     visitor: Box<dyn ConnectionVisitor>,
     tls_conn: TlsServerConnection,
     event_channel: (Sender<ConnectionEvent>, Receiver<ConnectionEvent>),
+    outbound_queue: VecDeque<ConnectionEvent>,
+    outbound_queue_depth: Arc<OutboundQueueDepth>,
     alpn_protocol: alpn::Protocol,
+    tls_session_info: TlsSessionInfo,
+    write_throttle: Option<WriteThrottle>,
+    read_watermarks: Option<ReadWatermarks>,
+    reads_paused: bool,
+    abortive_close_reasons: Vec<CloseReason>,
+    handshake_completed: bool,
     closed: bool,
 }
 
@@ -72,14 +290,25 @@ impl Connection {
         alpn_protocol: alpn::Protocol,
     ) -> Result<Self, AppError> {
         let event_channel = ConnectionEvent::create_channel();
+        let outbound_queue_depth = Arc::new(OutboundQueueDepth::default());
+        visitor.set_outbound_queue_depth(outbound_queue_depth.clone());
         visitor.set_event_channel_sender(event_channel.0.clone())?;
         visitor.on_connected()?;
+        let tls_session_info = tls_conn.session_info();
 
         Ok(Self {
             visitor,
             tls_conn,
             event_channel,
+            outbound_queue: VecDeque::new(),
+            outbound_queue_depth,
             alpn_protocol,
+            tls_session_info,
+            write_throttle: None,
+            read_watermarks: None,
+            reads_paused: false,
+            abortive_close_reasons: vec![CloseReason::PolicyDenial],
+            handshake_completed: false,
             closed: false,
         })
     }
@@ -109,76 +338,154 @@ impl Connection {
         &self.alpn_protocol
     }
 
+    /// Connection 'tls_session_info' accessor
+    pub fn get_tls_session_info(&self) -> &TlsSessionInfo {
+        &self.tls_session_info
+    }
+
+    /// Connection write rate limit (bytes per second) accessor
+    pub fn get_rate_limit(&self) -> Option<u64> {
+        self.write_throttle
+            .as_ref()
+            .map(|write_throttle| write_throttle.bytes_per_sec)
+    }
+
+    /// Connection write rate limit (bytes per second) mutator. None removes the limit
+    pub fn set_rate_limit(&mut self, rate_limit: Option<u64>) {
+        self.write_throttle = rate_limit.map(WriteThrottle::new);
+    }
+
+    /// Connection read backpressure thresholds mutator. None disables read backpressure (and resumes paused reads)
+    pub fn set_read_watermarks(&mut self, read_watermarks: Option<ReadWatermarks>) {
+        self.read_watermarks = read_watermarks;
+        if read_watermarks.is_none() {
+            self.reads_paused = false;
+        }
+    }
+
+    /// Returns whether connection reads are currently paused (read backpressure)
+    pub fn is_reads_paused(&self) -> bool {
+        self.reads_paused
+    }
+
+    /// Close reasons, for which the connection is reset (RST) rather than gracefully closed (FIN). Defaults to
+    /// policy denials
+    pub fn set_abortive_close_reasons(&mut self, abortive_close_reasons: Vec<CloseReason>) {
+        self.abortive_close_reasons = abortive_close_reasons;
+    }
+
     /// Get copy of event channel sender
     pub fn clone_event_channel_sender(&self) -> Sender<ConnectionEvent> {
         self.event_channel.0.clone()
     }
 
+    /// Get shared outbound queue depth (updated as events are processed)
+    pub fn clone_outbound_queue_depth(&self) -> Arc<OutboundQueueDepth> {
+        self.outbound_queue_depth.clone()
+    }
+
+    /// Byte count of queued (un-flushed) writes
+    pub fn get_pending_write_bytes(&self) -> usize {
+        self.outbound_queue_depth.get_pending_write_bytes()
+    }
+
+    /// Count of queued events
+    pub fn get_queued_events(&self) -> usize {
+        self.outbound_queue_depth.get_queued_events()
+    }
+
     /// Poll connection events loop
     pub fn poll_connection(&mut self) -> Result<(), AppError> {
         loop {
             // Read connection data (if avail)
             if let Err(err) = self.read() {
-                error(&target!(), &format!("{:?}", err));
+                error(&target!(), &self.visitor.tag_log_msg(&format!("{:?}", err)));
             }
 
             // Custom polling cycle handler
             if let Err(err) = self.visitor.on_polling_cycle() {
-                error(&target!(), &format!("{:?}", err));
+                error(&target!(), &self.visitor.tag_log_msg(&format!("{:?}", err)));
             }
 
             // Poll connection event
-            'EVENTS: loop {
-                match self.event_channel.1.try_recv() {
-                    // Handle write request
-                    Ok(ConnectionEvent::Write(data)) => {
-                        if let Err(err) = self.write(&data) {
-                            error(&target!(), &format!("{:?}", err));
-                        }
-                    }
+            self.process_events();
 
-                    // Handle connection shutdown request
-                    Ok(ConnectionEvent::Closing) => {
-                        if let Err(err) = self.shutdown() {
-                            error(&target!(), &format!("{:?}", err));
-                        }
-                    }
+            if self.closed {
+                break;
+            }
 
-                    Ok(ConnectionEvent::Closed) => break,
+            // End of poll cycle
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        Ok(())
+    }
 
-                    // No event
-                    Err(TryRecvError::Empty) => break,
+    /// Process queued connection events
+    fn process_events(&mut self) {
+        loop {
+            self.enqueue_events();
 
-                    // Channel closed
-                    Err(TryRecvError::Disconnected) => break 'EVENTS,
+            // No event
+            let event = match self.outbound_queue.pop_front() {
+                Some(event) => event,
+                None => break,
+            };
+
+            match &event {
+                // Handle write request
+                ConnectionEvent::Write(data) => {
+                    if let Err(err) = self.write(data) {
+                        error(&target!(), &self.visitor.tag_log_msg(&format!("{:?}", err)));
+                    }
                 }
 
-                thread::sleep(Duration::from_millis(10));
-            }
+                // Handle rate limit update (applies to subsequent writes)
+                ConnectionEvent::SetRateLimit(rate_limit) => self.set_rate_limit(*rate_limit),
 
-            if self.closed {
-                break;
+                // Handle connection shutdown request
+                ConnectionEvent::Closing => {
+                    if let Err(err) = self.shutdown() {
+                        error(&target!(), &self.visitor.tag_log_msg(&format!("{:?}", err)));
+                    }
+                }
+
+                ConnectionEvent::Closed => {
+                    self.outbound_queue_depth.record_processed(&event);
+                    break;
+                }
             }
 
-            // End of poll cycle
-            thread::sleep(Duration::from_millis(50));
+            self.outbound_queue_depth.record_processed(&event);
+
+            thread::sleep(Duration::from_millis(10));
         }
+    }
 
-        Ok(())
+    /// Move events received on the event channel to the outbound queue
+    fn enqueue_events(&mut self) {
+        while let Ok(event) = self.event_channel.1.try_recv() {
+            self.outbound_queue_depth.record_enqueued(&event);
+            self.outbound_queue.push_back(event);
+        }
     }
 
-    /// Read and process client connection content
+    /// Read and process client connection content. No content is read while reads are paused (read backpressure)
     pub fn read(&mut self) -> Result<Vec<u8>, AppError> {
         let mut return_buffer = vec![];
         let mut error: Option<AppError> = None;
 
+        let read_headroom = self.update_read_headroom();
+        if read_headroom == Some(0) {
+            return Ok(return_buffer);
+        }
+
         // Attempt connection read
-        match self.read_tls_conn() {
+        match self.read_tls_conn(read_headroom) {
             Ok(buffer) => {
                 if !buffer.is_empty() {
-                    match self.visitor.on_connection_read(&buffer) {
-                        Ok(()) => {}
-                        Err(err) => error = Some(err),
+                    if let Err(err) = self.process_read_content(&buffer) {
+                        error = Some(err);
                     }
                     return_buffer = buffer;
                 }
@@ -204,10 +511,84 @@ impl Connection {
         Ok(return_buffer)
     }
 
-    /// Write content to client connection
+    /// Pause or resume reads, given the visitor's pending read bytes and the read watermarks. Returns the read
+    /// headroom: max bytes to read below the high watermark (0 while paused), or None if reads are not bounded
+    fn update_read_headroom(&mut self) -> Option<usize> {
+        let read_watermarks = match &self.read_watermarks {
+            Some(read_watermarks) => *read_watermarks,
+            None => return None,
+        };
+        self.enqueue_events();
+        let pending_read_bytes = self.visitor.get_pending_read_bytes();
+
+        if self.reads_paused && (pending_read_bytes <= read_watermarks.low) {
+            self.reads_paused = false;
+            debug(
+                &target!(),
+                &self.visitor.tag_log_msg(&format!(
+                    "Resuming connection reads: pending={}",
+                    pending_read_bytes
+                )),
+            );
+        } else if !self.reads_paused && (pending_read_bytes >= read_watermarks.high) {
+            self.reads_paused = true;
+            debug(
+                &target!(),
+                &self.visitor.tag_log_msg(&format!(
+                    "Pausing connection reads: pending={}",
+                    pending_read_bytes
+                )),
+            );
+        }
+
+        match self.reads_paused {
+            true => Some(0),
+            false => Some(read_watermarks.high.saturating_sub(pending_read_bytes)),
+        }
+    }
+
+    /// Process read content. The first read content is given to the visitor's handshake hook, which decides how
+    /// (and whether) processing continues.
+    fn process_read_content(&mut self, buffer: &[u8]) -> Result<(), AppError> {
+        if self.handshake_completed {
+            return self.visitor.on_connection_read(buffer);
+        }
+        self.handshake_completed = true;
+
+        let content = match self.visitor.on_handshake(buffer)? {
+            HandshakeOutcome::Proceed(content) => content,
+            HandshakeOutcome::Reject(reason) => {
+                return Err(AppError::General(format!(
+                    "Connection handshake rejected: reason={}",
+                    reason
+                )));
+            }
+            HandshakeOutcome::Switch(mut visitor, content) => {
+                visitor.set_outbound_queue_depth(self.outbound_queue_depth.clone());
+                visitor.set_event_channel_sender(self.event_channel.0.clone())?;
+                visitor.on_connected()?;
+                self.visitor = visitor;
+                content
+            }
+        };
+
+        if content.is_empty() {
+            return Ok(());
+        }
+        self.visitor.on_connection_read(&content)
+    }
+
+    /// Write content to client connection (paced by the rate limit, if set)
     pub fn write(&mut self, buffer: &[u8]) -> Result<(), AppError> {
         let mut error: Option<AppError> = None;
 
+        if let Some(write_throttle) = self.write_throttle.as_mut() {
+            let delay = write_throttle.reserve(buffer.len(), Instant::now());
+            if !delay.is_zero() {
+                thread::sleep(delay);
+            }
+        }
+
         // Attempt connection write
         match self.write_tls_conn(buffer) {
             Ok(()) => {}
@@ -231,18 +612,28 @@ impl Connection {
         Ok(())
     }
 
-    /// Shut down TLS connection
+    /// Shut down TLS connection (gracefully)
     pub fn shutdown(&mut self) -> Result<(), AppError> {
+        self.shutdown_with_reason(CloseReason::Normal)
+    }
+
+    /// Shut down TLS connection for given reason. Abortive close reasons reset the connection (once the socket is
+    /// closed), otherwise the connection is gracefully shut down.
+    pub fn shutdown_with_reason(&mut self, reason: CloseReason) -> Result<(), AppError> {
         if self.closed {
             return Ok(());
         }
 
-        self.tls_conn.sock.shutdown(Shutdown::Both).map_err(|err| {
-            AppError::GenWithMsgAndErr(
-                "Error shutting down TLS connection".to_string(),
-                Box::new(err),
-            )
-        })?;
+        if self.abortive_close_reasons.contains(&reason) {
+            stream_utils::set_abortive_close(&self.tls_conn.sock)?;
+        } else {
+            self.tls_conn.sock.shutdown(Shutdown::Both).map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    "Error shutting down TLS connection".to_string(),
+                    Box::new(err),
+                )
+            })?;
+        }
 
         self.closed = true;
 
@@ -254,18 +645,20 @@ impl Connection {
                 AppError::GenWithMsgAndErr("Error sending closed event".to_string(), Box::new(err))
             })
         {
-            error(&target!(), &format!("{:?}", err));
+            error(&target!(), &self.visitor.tag_log_msg(&format!("{:?}", err)));
         }
 
         self.visitor.on_shutdown()
     }
 
-    /// Read client connection content
-    fn read_tls_conn(&mut self) -> Result<Vec<u8>, AppError> {
+    /// Read client connection content (up to `max_bytes`, if given)
+    fn read_tls_conn(&mut self, max_bytes: Option<usize>) -> Result<Vec<u8>, AppError> {
         let mut buffer = Vec::new();
         let mut buff_chunk = [0; READ_BLOCK_SIZE];
-        loop {
-            let bytes_read = match self.tls_conn.read(&mut buff_chunk) {
+        let mut remaining_bytes = max_bytes.unwrap_or(usize::MAX);
+        while remaining_bytes > 0 {
+            let chunk_size = remaining_bytes.min(READ_BLOCK_SIZE);
+            let bytes_read = match self.tls_conn.read(&mut buff_chunk[..chunk_size]) {
                 Ok(bytes_read) => bytes_read,
 
                 Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
@@ -290,11 +683,11 @@ impl Connection {
                     ))
                 }
             };
-            if bytes_read < READ_BLOCK_SIZE {
-                buffer.append(&mut buff_chunk[..bytes_read].to_vec());
+            buffer.append(&mut buff_chunk[..bytes_read].to_vec());
+            if bytes_read < chunk_size {
                 break;
             }
-            buffer.append(&mut buff_chunk.to_vec());
+            remaining_bytes -= bytes_read;
         }
 
         Ok(buffer)
@@ -354,6 +747,9 @@ pub trait ConnectionVisitor: Send {
         Ok(())
     }
 
+    /// Setup connection outbound queue depth (for observing the connection's write backlog)
+    fn set_outbound_queue_depth(&mut self, _outbound_queue_depth: Arc<OutboundQueueDepth>) {}
+
     /// Setup event channel sender
     fn set_event_channel_sender(
         &mut self,
@@ -362,11 +758,23 @@ pub trait ConnectionVisitor: Send {
         Ok(())
     }
 
+    /// Connection handshake hook, invoked with the first read content (before it is processed by
+    /// `on_connection_read`). May inspect/consume an application sub-protocol preamble, and decide whether to proceed,
+    /// reject the connection or switch its handling. Default passes the content through untouched.
+    fn on_handshake(&mut self, initial: &[u8]) -> Result<HandshakeOutcome, AppError> {
+        Ok(HandshakeOutcome::Proceed(initial.to_vec()))
+    }
+
     /// Incoming connection content processing event handler
     fn on_connection_read(&mut self, _data: &[u8]) -> Result<(), AppError> {
         Ok(())
     }
 
+    /// Byte count of read content accepted by the visitor, but not yet consumed (used for read backpressure)
+    fn get_pending_read_bytes(&self) -> usize {
+        0
+    }
+
     /// Polling cycle tick handler
     fn on_polling_cycle(&mut self) -> Result<(), AppError> {
         Ok(())
@@ -379,4 +787,816 @@ pub trait ConnectionVisitor: Send {
 
     /// Send error response message to client
     fn send_error_response(&mut self, err: &AppError);
+
+    /// Connection context (for instance user and service identifiers) used to tag the connection's log lines
+    fn get_log_context(&self) -> Option<String> {
+        None
+    }
+
+    /// Prefix given log message with the connection's log context (if any)
+    fn tag_log_msg(&self, msg: &str) -> String {
+        match self.get_log_context() {
+            Some(log_context) => format!("[{}] {}", log_context, msg),
+            None => msg.to_string(),
+        }
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::crypto::file::{load_certificates, load_private_key};
+    use mockall::{mock, predicate};
+    use pki_types::{ServerName, UnixTime};
+    use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified};
+    use rustls::server::WebPkiClientVerifier;
+    use rustls::DigitallySignedStruct;
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    const CERTFILE_ROOT_CA_PATHPARTS: [&str; 3] = [
+        env!("CARGO_MANIFEST_DIR"),
+        "testdata",
+        "root-ca.local.crt.pem",
+    ];
+    const KEYFILE_ROOT_CA_PATHPARTS: [&str; 3] = [
+        env!("CARGO_MANIFEST_DIR"),
+        "testdata",
+        "root-ca.local.key.pem",
+    ];
+    const CERTFILE_CLIENT0_PATHPARTS: [&str; 3] = [
+        env!("CARGO_MANIFEST_DIR"),
+        "testdata",
+        "client0.local.crt.pem",
+    ];
+    const KEYFILE_CLIENT0_PATHPARTS: [&str; 3] = [
+        env!("CARGO_MANIFEST_DIR"),
+        "testdata",
+        "client0.local.key.pem",
+    ];
+
+    // mocks
+    // =====
+
+    mock! {
+        pub ConnVisit {}
+        impl ConnectionVisitor for ConnVisit {
+            fn on_connected(&mut self) -> Result<(), AppError>;
+            fn set_event_channel_sender(&mut self, event_channel_sender: Sender<ConnectionEvent>) -> Result<(), AppError>;
+            fn on_handshake(&mut self, initial: &[u8]) -> Result<HandshakeOutcome, AppError>;
+            fn on_connection_read(&mut self, data: &[u8]) -> Result<(), AppError>;
+            fn get_pending_read_bytes(&self) -> usize;
+            fn on_polling_cycle(&mut self) -> Result<(), AppError>;
+            fn on_shutdown(&mut self) -> Result<(), AppError>;
+            fn send_error_response(&mut self, err: &AppError);
+            fn get_log_context(&self) -> Option<String>;
+        }
+    }
+
+    #[derive(Debug)]
+    struct NoServerCertVerification {}
+
+    impl rustls::client::danger::ServerCertVerifier for NoServerCertVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+            rustls::crypto::ring::default_provider()
+                .signature_verification_algorithms
+                .supported_schemes()
+        }
+    }
+
+    // utils
+    // =====
+
+    fn load_pki(
+        cert_pathparts: &[&str; 3],
+        key_pathparts: &[&str; 3],
+    ) -> (
+        Vec<CertificateDer<'static>>,
+        pki_types::PrivateKeyDer<'static>,
+    ) {
+        let cert_file: PathBuf = cert_pathparts.iter().collect();
+        let key_file: PathBuf = key_pathparts.iter().collect();
+        (
+            load_certificates(cert_file.to_str().unwrap().to_string()).unwrap(),
+            load_private_key(key_file.to_str().unwrap().to_string()).unwrap(),
+        )
+    }
+
+    /// Perform a TLS handshake over a local TCP socket pair, returning the server end
+    pub fn create_handshaked_tls_conn(alpn_protocol: &[u8]) -> TlsServerConnection {
+        let (server_config, client_config) = create_tls_configs(alpn_protocol);
+        perform_tls_handshake(server_config, client_config, false)
+    }
+
+    /// Create (mutually-authenticating) TLS server and client configurations
+    fn create_tls_configs(
+        alpn_protocol: &[u8],
+    ) -> (Arc<rustls::ServerConfig>, Arc<rustls::ClientConfig>) {
+        let (server_certs, server_key) =
+            load_pki(&CERTFILE_ROOT_CA_PATHPARTS, &KEYFILE_ROOT_CA_PATHPARTS);
+        let (client_certs, client_key) =
+            load_pki(&CERTFILE_CLIENT0_PATHPARTS, &KEYFILE_CLIENT0_PATHPARTS);
+
+        let mut auth_root_certs = rustls::RootCertStore::empty();
+        auth_root_certs.add(server_certs[0].clone()).unwrap();
+
+        let mut server_config = rustls::ServerConfig::builder()
+            .with_client_cert_verifier(
+                WebPkiClientVerifier::builder(Arc::new(auth_root_certs))
+                    .build()
+                    .unwrap(),
+            )
+            .with_single_cert(server_certs, server_key)
+            .unwrap();
+        server_config.alpn_protocols = vec![alpn_protocol.to_vec()];
+
+        let mut client_config = rustls::ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoServerCertVerification {}))
+            .with_client_auth_cert(client_certs, client_key)
+            .unwrap();
+        client_config.alpn_protocols = vec![alpn_protocol.to_vec()];
+
+        (Arc::new(server_config), Arc::new(client_config))
+    }
+
+    /// Perform a TLS handshake over a local TCP socket pair, returning the server end. The client may be made to
+    /// wait for (and store) the server's session tickets, to allow later connections to resume the session.
+    fn perform_tls_handshake(
+        server_config: Arc<rustls::ServerConfig>,
+        client_config: Arc<rustls::ClientConfig>,
+        await_session_tickets: bool,
+    ) -> TlsServerConnection {
+        perform_tls_handshake_with_client(server_config, client_config, await_session_tickets).0
+    }
+
+    /// Perform a TLS handshake over a local TCP socket pair (see `perform_tls_handshake`), returning both the server
+    /// and client ends
+    fn perform_tls_handshake_with_client(
+        server_config: Arc<rustls::ServerConfig>,
+        client_config: Arc<rustls::ClientConfig>,
+        await_session_tickets: bool,
+    ) -> (
+        TlsServerConnection,
+        StreamOwned<rustls::ClientConnection, TcpStream>,
+    ) {
+        let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server_addr = tcp_listener.local_addr().unwrap();
+
+        let client_thread = thread::spawn(move || {
+            let mut tcp_stream = TcpStream::connect(server_addr).unwrap();
+            let mut tls_cli_conn = rustls::ClientConnection::new(
+                client_config,
+                ServerName::try_from("localhost").unwrap(),
+            )
+            .unwrap();
+            while tls_cli_conn.is_handshaking() {
+                tls_cli_conn.complete_io(&mut tcp_stream).unwrap();
+            }
+            if await_session_tickets {
+                tls_cli_conn.read_tls(&mut tcp_stream).unwrap();
+                tls_cli_conn.process_new_packets().unwrap();
+            }
+            StreamOwned::new(tls_cli_conn, tcp_stream)
+        });
+
+        let (mut tcp_stream, _) = tcp_listener.accept().unwrap();
+        let mut tls_srv_conn = rustls::ServerConnection::new(server_config).unwrap();
+        while tls_srv_conn.is_handshaking() {
+            tls_srv_conn.complete_io(&mut tcp_stream).unwrap();
+        }
+
+        let tls_cli_stream = client_thread.join().unwrap();
+
+        (StreamOwned::new(tls_srv_conn, tcp_stream), tls_cli_stream)
+    }
+
+    // tests
+    // =====
+
+    #[test]
+    fn tlssessinfo_display() {
+        let session_info = TlsSessionInfo {
+            protocol_version: Some("TLSv1_3".to_string()),
+            cipher_suite: None,
+            alpn_protocol: Some("T0CP".to_string()),
+            resumed: true,
+        };
+
+        assert_eq!(
+            session_info.to_string(),
+            "version=TLSv1_3, cipher=None, alpn=T0CP, resumed=true"
+        );
+    }
+
+    #[test]
+    fn connvisit_tag_log_msg_when_context_available() {
+        let mut conn_visitor = MockConnVisit::new();
+        conn_visitor
+            .expect_get_log_context()
+            .times(1)
+            .return_once(|| Some("uid=100, svc_id=200".to_string()));
+
+        assert_eq!(
+            conn_visitor.tag_log_msg("Connection closed"),
+            "[uid=100, svc_id=200] Connection closed"
+        );
+    }
+
+    #[test]
+    fn connvisit_tag_log_msg_when_no_context() {
+        let mut conn_visitor = MockConnVisit::new();
+        conn_visitor
+            .expect_get_log_context()
+            .times(1)
+            .return_once(|| None);
+
+        assert_eq!(
+            conn_visitor.tag_log_msg("Connection closed"),
+            "Connection closed"
+        );
+    }
+
+    #[test]
+    fn conn_new_when_handshake_completed() {
+        let tls_conn = create_handshaked_tls_conn(alpn::PROTOCOL_CONTROL_PLANE.as_bytes());
+
+        let mut conn_visitor = MockConnVisit::new();
+        conn_visitor
+            .expect_set_event_channel_sender()
+            .times(1)
+            .return_once(|_| Ok(()));
+        conn_visitor
+            .expect_on_connected()
+            .times(1)
+            .return_once(|| Ok(()));
+
+        let conn = Connection::new(
+            Box::new(conn_visitor),
+            tls_conn,
+            alpn::Protocol::ControlPlane,
+        )
+        .unwrap();
+
+        let session_info = conn.get_tls_session_info();
+        assert!(session_info
+            .protocol_version
+            .as_ref()
+            .unwrap()
+            .starts_with("TLSv1_"));
+        assert!(session_info
+            .cipher_suite
+            .as_ref()
+            .unwrap()
+            .starts_with("TLS"));
+        assert_eq!(
+            session_info.alpn_protocol,
+            Some(alpn::PROTOCOL_CONTROL_PLANE.to_string())
+        );
+    }
+
+    #[test]
+    fn tlssessinfo_new_when_fresh_and_resumed_sessions() {
+        let (server_config, client_config) =
+            create_tls_configs(alpn::PROTOCOL_CONTROL_PLANE.as_bytes());
+
+        let fresh_tls_conn =
+            perform_tls_handshake(server_config.clone(), client_config.clone(), true);
+        let resumed_tls_conn = perform_tls_handshake(server_config, client_config, false);
+
+        let fresh_session_info = fresh_tls_conn.session_info();
+        let resumed_session_info = resumed_tls_conn.session_info();
+
+        assert!(!fresh_session_info.resumed);
+        assert!(resumed_session_info.resumed);
+        assert_eq!(
+            resumed_session_info.protocol_version,
+            Some("TLSv1_3".to_string())
+        );
+        assert_eq!(
+            resumed_session_info.alpn_protocol,
+            Some(alpn::PROTOCOL_CONTROL_PLANE.to_string())
+        );
+    }
+
+    #[test]
+    fn conn_process_read_content_when_handshake_consumes_preamble() {
+        let tls_conn = create_handshaked_tls_conn(alpn::PROTOCOL_CONTROL_PLANE.as_bytes());
+
+        let mut conn_visitor = MockConnVisit::new();
+        conn_visitor
+            .expect_set_event_channel_sender()
+            .times(1)
+            .return_once(|_| Ok(()));
+        conn_visitor
+            .expect_on_connected()
+            .times(1)
+            .return_once(|| Ok(()));
+        conn_visitor
+            .expect_on_handshake()
+            .with(predicate::eq("SUBPROTO/1\nhello".as_bytes()))
+            .times(1)
+            .return_once(|initial| {
+                Ok(HandshakeOutcome::Proceed(
+                    initial["SUBPROTO/1\n".len()..].to_vec(),
+                ))
+            });
+        conn_visitor
+            .expect_on_connection_read()
+            .with(predicate::eq("hello".as_bytes()))
+            .times(1)
+            .return_once(|_| Ok(()));
+        conn_visitor
+            .expect_on_connection_read()
+            .with(predicate::eq("world".as_bytes()))
+            .times(1)
+            .return_once(|_| Ok(()));
+
+        let mut conn = Connection::new(
+            Box::new(conn_visitor),
+            tls_conn,
+            alpn::Protocol::ControlPlane,
+        )
+        .unwrap();
+
+        conn.process_read_content("SUBPROTO/1\nhello".as_bytes())
+            .unwrap();
+        conn.process_read_content("world".as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn conn_read_when_read_watermarks_reached_by_slow_visitor() {
+        let (server_config, client_config) =
+            create_tls_configs(alpn::PROTOCOL_CONTROL_PLANE.as_bytes());
+        let (tls_conn, mut tls_cli_stream) =
+            perform_tls_handshake_with_client(server_config, client_config, false);
+        tls_conn.sock.set_nonblocking(true).unwrap();
+
+        let pending_read_bytes = Arc::new(AtomicUsize::new(0));
+        let reads = Arc::new(Mutex::new(Vec::new()));
+
+        let mut conn_visitor = MockConnVisit::new();
+        conn_visitor
+            .expect_set_event_channel_sender()
+            .times(1)
+            .return_once(|_| Ok(()));
+        conn_visitor
+            .expect_on_connected()
+            .times(1)
+            .return_once(|| Ok(()));
+        conn_visitor
+            .expect_on_handshake()
+            .times(1)
+            .return_once(|initial| Ok(HandshakeOutcome::Proceed(initial.to_vec())));
+        let pending_read_bytes_copy = pending_read_bytes.clone();
+        let reads_copy = reads.clone();
+        conn_visitor
+            .expect_on_connection_read()
+            .returning(move |data| {
+                pending_read_bytes_copy.fetch_add(data.len(), Ordering::SeqCst);
+                reads_copy.lock().unwrap().push(data.to_vec());
+                Ok(())
+            });
+        let pending_read_bytes_copy = pending_read_bytes.clone();
+        conn_visitor
+            .expect_get_pending_read_bytes()
+            .returning(move || pending_read_bytes_copy.load(Ordering::SeqCst));
+        conn_visitor.expect_get_log_context().returning(|| None);
+
+        let mut conn = Connection::new(
+            Box::new(conn_visitor),
+            tls_conn,
+            alpn::Protocol::ControlPlane,
+        )
+        .unwrap();
+        conn.set_read_watermarks(Some(ReadWatermarks { high: 10, low: 4 }));
+
+        let read_until_len = |conn: &mut Connection, expected_len: usize| -> Vec<u8> {
+            let mut content = Vec::new();
+            for _ in 0..100 {
+                content.append(&mut conn.read().unwrap());
+                if content.len() >= expected_len {
+                    return content;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            panic!("Timed out waiting for connection content");
+        };
+
+        // Visitor accepts (without consuming) content, reads are capped at the high-water mark
+        tls_cli_stream
+            .write_all("0123456789ABmore".as_bytes())
+            .unwrap();
+        tls_cli_stream.flush().unwrap();
+        assert_eq!(read_until_len(&mut conn, 10), "0123456789".as_bytes());
+
+        // Reads pause at the high-water mark, and stay paused above the low-water mark
+        assert!(conn.read().unwrap().is_empty());
+        assert!(conn.is_reads_paused());
+
+        pending_read_bytes.store(6, Ordering::SeqCst);
+        assert!(conn.read().unwrap().is_empty());
+        assert!(conn.is_reads_paused());
+
+        // Reads resume once visitor drains to the low-water mark
+        pending_read_bytes.store(4, Ordering::SeqCst);
+        assert_eq!(read_until_len(&mut conn, 6), "ABmore".as_bytes());
+        assert!(!conn.is_reads_paused());
+        assert_eq!(
+            reads.lock().unwrap().concat(),
+            "0123456789ABmore".as_bytes().to_vec()
+        );
+    }
+
+    #[test]
+    fn conn_set_read_watermarks_when_disabled_while_paused() {
+        let tls_conn = create_handshaked_tls_conn(alpn::PROTOCOL_CONTROL_PLANE.as_bytes());
+
+        let mut conn_visitor = MockConnVisit::new();
+        conn_visitor
+            .expect_set_event_channel_sender()
+            .times(1)
+            .return_once(|_| Ok(()));
+        conn_visitor
+            .expect_on_connected()
+            .times(1)
+            .return_once(|| Ok(()));
+        conn_visitor
+            .expect_get_pending_read_bytes()
+            .times(1)
+            .return_once(|| 100);
+        conn_visitor.expect_get_log_context().returning(|| None);
+
+        let mut conn = Connection::new(
+            Box::new(conn_visitor),
+            tls_conn,
+            alpn::Protocol::ControlPlane,
+        )
+        .unwrap();
+        conn.set_read_watermarks(Some(ReadWatermarks { high: 10, low: 4 }));
+
+        assert_eq!(conn.update_read_headroom(), Some(0));
+        assert!(conn.is_reads_paused());
+
+        conn.set_read_watermarks(None);
+
+        assert!(!conn.is_reads_paused());
+        assert_eq!(conn.update_read_headroom(), None);
+    }
+
+    #[test]
+    fn conn_process_read_content_when_handshake_rejects_preamble() {
+        let tls_conn = create_handshaked_tls_conn(alpn::PROTOCOL_CONTROL_PLANE.as_bytes());
+
+        let mut conn_visitor = MockConnVisit::new();
+        conn_visitor
+            .expect_set_event_channel_sender()
+            .times(1)
+            .return_once(|_| Ok(()));
+        conn_visitor
+            .expect_on_connected()
+            .times(1)
+            .return_once(|| Ok(()));
+        conn_visitor
+            .expect_on_handshake()
+            .times(1)
+            .return_once(|_| Ok(HandshakeOutcome::Reject("bad preamble".to_string())));
+        conn_visitor.expect_on_connection_read().never();
+
+        let mut conn = Connection::new(
+            Box::new(conn_visitor),
+            tls_conn,
+            alpn::Protocol::ControlPlane,
+        )
+        .unwrap();
+
+        match conn.process_read_content("BOGUS".as_bytes()) {
+            Ok(()) => panic!("Unexpected successful result"),
+            Err(err) => assert!(err.to_string().contains("bad preamble")),
+        }
+    }
+
+    #[test]
+    fn conn_process_read_content_when_handshake_switches_visitor() {
+        let tls_conn = create_handshaked_tls_conn(alpn::PROTOCOL_CONTROL_PLANE.as_bytes());
+
+        let mut switched_visitor = MockConnVisit::new();
+        switched_visitor
+            .expect_set_event_channel_sender()
+            .times(1)
+            .return_once(|_| Ok(()));
+        switched_visitor
+            .expect_on_connected()
+            .times(1)
+            .return_once(|| Ok(()));
+        switched_visitor
+            .expect_on_connection_read()
+            .with(predicate::eq("hello".as_bytes()))
+            .times(1)
+            .return_once(|_| Ok(()));
+
+        let mut conn_visitor = MockConnVisit::new();
+        conn_visitor
+            .expect_set_event_channel_sender()
+            .times(1)
+            .return_once(|_| Ok(()));
+        conn_visitor
+            .expect_on_connected()
+            .times(1)
+            .return_once(|| Ok(()));
+        conn_visitor
+            .expect_on_handshake()
+            .times(1)
+            .return_once(|_| {
+                Ok(HandshakeOutcome::Switch(
+                    Box::new(switched_visitor),
+                    "hello".as_bytes().to_vec(),
+                ))
+            });
+        conn_visitor.expect_on_connection_read().never();
+
+        let mut conn = Connection::new(
+            Box::new(conn_visitor),
+            tls_conn,
+            alpn::Protocol::ControlPlane,
+        )
+        .unwrap();
+
+        conn.process_read_content("ALT/1\nhello".as_bytes())
+            .unwrap();
+    }
+
+    /// Create connection (prior to TLS handshake), over a local TCP socket pair. The peer (client) end is also returned,
+    /// to keep the connection open.
+    fn create_connection_for_shutdown() -> (Connection, TcpStream) {
+        let (server_config, _) = create_tls_configs(alpn::PROTOCOL_CONTROL_PLANE.as_bytes());
+        let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let peer_stream = TcpStream::connect(tcp_listener.local_addr().unwrap()).unwrap();
+        let (tcp_stream, _) = tcp_listener.accept().unwrap();
+        let tls_conn = StreamOwned::new(
+            rustls::ServerConnection::new(server_config).unwrap(),
+            tcp_stream,
+        );
+
+        let mut conn_visitor = MockConnVisit::new();
+        conn_visitor
+            .expect_set_event_channel_sender()
+            .times(1)
+            .return_once(|_| Ok(()));
+        conn_visitor
+            .expect_on_connected()
+            .times(1)
+            .return_once(|| Ok(()));
+        conn_visitor
+            .expect_on_shutdown()
+            .times(1)
+            .return_once(|| Ok(()));
+
+        let conn = Connection::new(
+            Box::new(conn_visitor),
+            tls_conn,
+            alpn::Protocol::ControlPlane,
+        )
+        .unwrap();
+
+        (conn, peer_stream)
+    }
+
+    #[test]
+    fn conn_shutdown_with_reason_when_policy_denial() {
+        let (mut conn, _peer_stream) = create_connection_for_shutdown();
+
+        conn.shutdown_with_reason(CloseReason::PolicyDenial)
+            .unwrap();
+
+        assert!(conn.is_closed());
+        assert_eq!(
+            socket2::SockRef::from(&conn.tls_conn.sock)
+                .linger()
+                .unwrap(),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn conn_shutdown_when_graceful() {
+        let (mut conn, _peer_stream) = create_connection_for_shutdown();
+
+        conn.shutdown().unwrap();
+
+        assert!(conn.is_closed());
+        assert_eq!(
+            socket2::SockRef::from(&conn.tls_conn.sock)
+                .linger()
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn conn_shutdown_with_reason_when_policy_denial_not_abortive() {
+        let (mut conn, _peer_stream) = create_connection_for_shutdown();
+        conn.set_abortive_close_reasons(vec![]);
+
+        conn.shutdown_with_reason(CloseReason::PolicyDenial)
+            .unwrap();
+
+        assert!(conn.is_closed());
+        assert_eq!(
+            socket2::SockRef::from(&conn.tls_conn.sock)
+                .linger()
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn conn_process_events_when_rate_limit_set_mid_stream() {
+        let tls_conn = create_handshaked_tls_conn(alpn::PROTOCOL_CONTROL_PLANE.as_bytes());
+
+        let mut conn_visitor = MockConnVisit::new();
+        conn_visitor
+            .expect_set_event_channel_sender()
+            .times(1)
+            .return_once(|_| Ok(()));
+        conn_visitor
+            .expect_on_connected()
+            .times(1)
+            .return_once(|| Ok(()));
+        conn_visitor.expect_get_log_context().returning(|| None);
+        conn_visitor.expect_on_shutdown().returning(|| Ok(()));
+
+        let mut conn = Connection::new(
+            Box::new(conn_visitor),
+            tls_conn,
+            alpn::Protocol::ControlPlane,
+        )
+        .unwrap();
+        let event_sender = conn.clone_event_channel_sender();
+
+        // unthrottled write
+        let start_time = Instant::now();
+        let _ = conn.write(&[0u8; 200]);
+        assert!(start_time.elapsed() < Duration::from_millis(150));
+
+        // set rate limit mid-stream
+        event_sender
+            .send(ConnectionEvent::SetRateLimit(Some(1000)))
+            .unwrap();
+        conn.process_events();
+        assert_eq!(conn.get_rate_limit(), Some(1000));
+
+        // throttled write (200 bytes at 1000 bytes/sec)
+        let start_time = Instant::now();
+        let _ = conn.write(&[0u8; 200]);
+        assert!(start_time.elapsed() >= Duration::from_millis(180));
+
+        // remove rate limit
+        event_sender
+            .send(ConnectionEvent::SetRateLimit(None))
+            .unwrap();
+        conn.process_events();
+        assert_eq!(conn.get_rate_limit(), None);
+    }
+
+    #[test]
+    fn conn_process_events_when_writes_queued() {
+        let tls_conn = create_handshaked_tls_conn(alpn::PROTOCOL_CONTROL_PLANE.as_bytes());
+
+        let mut conn_visitor = MockConnVisit::new();
+        conn_visitor
+            .expect_set_event_channel_sender()
+            .times(1)
+            .return_once(|_| Ok(()));
+        conn_visitor
+            .expect_on_connected()
+            .times(1)
+            .return_once(|| Ok(()));
+        conn_visitor.expect_get_log_context().returning(|| None);
+        conn_visitor.expect_on_shutdown().returning(|| Ok(()));
+
+        let mut conn = Connection::new(
+            Box::new(conn_visitor),
+            tls_conn,
+            alpn::Protocol::ControlPlane,
+        )
+        .unwrap();
+        let event_sender = conn.clone_event_channel_sender();
+        let outbound_queue_depth = conn.clone_outbound_queue_depth();
+
+        event_sender
+            .send(ConnectionEvent::SetRateLimit(Some(2000)))
+            .unwrap();
+        for _ in 0..3 {
+            event_sender
+                .send(ConnectionEvent::Write(vec![0u8; 200]))
+                .unwrap();
+        }
+
+        conn.enqueue_events();
+        assert_eq!(conn.get_pending_write_bytes(), 600);
+        assert_eq!(conn.get_queued_events(), 4);
+
+        // observe depth while throttled writes are in progress (200 bytes at 2000 bytes/sec per write)
+        let observer_handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(150));
+            (
+                outbound_queue_depth.get_pending_write_bytes(),
+                outbound_queue_depth.get_queued_events(),
+            )
+        });
+
+        conn.process_events();
+
+        let (pending_write_bytes, queued_events) = observer_handle.join().unwrap();
+        assert!(pending_write_bytes > 0 && pending_write_bytes < 600);
+        assert_eq!(pending_write_bytes, 200 * queued_events);
+        assert_eq!(conn.get_pending_write_bytes(), 0);
+        assert_eq!(conn.get_queued_events(), 0);
+    }
+
+    #[test]
+    fn writethrottle_reserve() {
+        let mut write_throttle = WriteThrottle::new(1000);
+        let start_time = write_throttle.start_time;
+
+        assert_eq!(
+            write_throttle.reserve(500, start_time),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            write_throttle.reserve(500, start_time + Duration::from_millis(400)),
+            Duration::from_millis(600)
+        );
+        assert_eq!(
+            write_throttle.reserve(100, start_time + Duration::from_secs(2)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn chunkedwriter_write_when_content_spans_multiple_chunks() {
+        let (event_sender, event_receiver) = ConnectionEvent::create_channel();
+        let mut writer = ChunkedEventWriter::new(event_sender, 4);
+
+        writer.write_all("0123456789".as_bytes()).unwrap();
+
+        let mut chunks: Vec<Vec<u8>> = vec![];
+        while let Ok(ConnectionEvent::Write(chunk)) = event_receiver.try_recv() {
+            chunks.push(chunk);
+        }
+        assert_eq!(
+            chunks,
+            vec!["0123".as_bytes().to_vec(), "4567".as_bytes().to_vec()]
+        );
+
+        writer.flush().unwrap();
+
+        match event_receiver.try_recv() {
+            Ok(ConnectionEvent::Write(chunk)) => assert_eq!(chunk, "89".as_bytes().to_vec()),
+            Ok(_) => panic!("Unexpected connection event"),
+            Err(err) => panic!("Unexpected channel recv result: err={:?}", &err),
+        }
+        assert!(event_receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn chunkedwriter_write_when_channel_closed() {
+        let (event_sender, event_receiver) = ConnectionEvent::create_channel();
+        let mut writer = ChunkedEventWriter::new(event_sender, 4);
+        drop(event_receiver);
+
+        assert!(writer.write_all("0123456789".as_bytes()).is_err());
+    }
 }
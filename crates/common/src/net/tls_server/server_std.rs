@@ -1,16 +1,31 @@
-use std::net::{SocketAddr, TcpListener};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{io, thread};
 
 use anyhow::Result;
 use rustls::server::{Accepted, Acceptor};
 
 use crate::error::AppError;
-use crate::logging::{error, info};
+use crate::logging::{error, info, warn};
+use crate::net::stream_utils;
 use crate::net::tls_server::conn_std::{self, TlsServerConnection};
 use crate::target;
 
+const ACCEPT_DELAY_MSECS: u64 = 30;
+const TLS_HANDSHAKE_TIMEOUT_MSECS: u64 = 10_000;
+const TLS_HANDSHAKE_POLL_MSECS: u64 = 100;
+const TLS_HANDSHAKE_WORKERS: usize = 4;
+const TLS_HANDSHAKE_QUEUE_SIZE_PER_WORKER: usize = 8;
+
+/// Upstream stream dialer for a newly-accepted connection (invoked without holding the visitor lock)
+pub type UpstreamDialer = Box<dyn FnOnce() -> Result<TcpStream, AppError> + Send>;
+
+/// Accepted connection, queued for a handshake worker (with its handshake deadline)
+type PendingHandshake = (TcpStream, SocketAddr, Instant);
+
 /// This is a TLS server, which will listen/accept client connections
 ///
 /// It has a TCP-level stream, a TLS-level connection state, and some other state/metadata.
@@ -19,6 +34,11 @@ pub struct Server {
     _server_port: u16,
     tcp_listener: Option<TcpListener>,
     listen_addr: String,
+    accept_workers: usize,
+    accept_workers_stopping: Arc<AtomicBool>,
+    handshake_workers: usize,
+    handshake_sender: Option<SyncSender<PendingHandshake>>,
+    handshake_timeout: Duration,
     polling: bool,
     closing: bool,
     closed: bool,
@@ -32,12 +52,36 @@ impl Server {
             _server_port: server_port,
             tcp_listener: None,
             listen_addr: format!("[::]:{}", server_port),
+            accept_workers: 1,
+            accept_workers_stopping: Arc::new(AtomicBool::new(true)),
+            handshake_workers: TLS_HANDSHAKE_WORKERS,
+            handshake_sender: None,
+            handshake_timeout: Duration::from_millis(TLS_HANDSHAKE_TIMEOUT_MSECS),
             polling: false,
             closing: false,
             closed: false,
         }
     }
 
+    /// Set number of accept workers (minimum 1), each accepting new connections from the shared listening socket (and
+    /// queueing them for the handshake workers). The additional workers (threads) are spawned when polling starts, and stop when it ends.
+    pub fn set_accept_workers(&mut self, accept_workers: usize) {
+        self.accept_workers = accept_workers.max(1);
+    }
+
+    /// Set number of TLS handshake workers (minimum 1). Accepted connections are queued (bounded, per worker) for
+    /// a free worker, and closed if the queue is full. The workers (threads) are spawned when polling starts, and
+    /// stop when it ends.
+    pub fn set_handshake_workers(&mut self, handshake_workers: usize) {
+        self.handshake_workers = handshake_workers.max(1);
+    }
+
+    /// Set TLS handshake timeout. Accepted connections, which have not completed their handshake within this time,
+    /// are closed
+    pub fn set_handshake_timeout(&mut self, handshake_timeout: Duration) {
+        self.handshake_timeout = handshake_timeout;
+    }
+
     /// Bind/listen on port
     pub fn bind_listener(&mut self) -> Result<(), AppError> {
         let server_addr: SocketAddr = self.listen_addr.parse()?;
@@ -58,6 +102,8 @@ impl Server {
             )
         })?;
 
+        let server_addr = tcp_listener.local_addr().unwrap_or(server_addr);
+
         self.tcp_listener = Some(tcp_listener);
         self.listen_addr = format!("{:?}", &server_addr);
         self.closing = false;
@@ -72,22 +118,44 @@ impl Server {
         self.visitor.lock().unwrap().on_listening()
     }
 
-    /// Request shutdown for poller and listener
+    /// Actual bound listener port (if bound). Useful when constructed with port 0 (OS-assigned ephemeral port)
+    pub fn get_bound_port(&self) -> Option<u16> {
+        self.tcp_listener
+            .as_ref()
+            .and_then(|tcp_listener| tcp_listener.local_addr().ok())
+            .map(|local_addr| local_addr.port())
+    }
+
+    /// Request shutdown for poller and listener. In-progress TLS handshakes are cut off
     pub fn shutdown(&mut self) {
         if !self.polling {
             self.perform_shutdown();
         } else {
             self.polling = false;
+            self.accept_workers_stopping.store(true, Ordering::SeqCst);
         }
     }
 
-    /// Request shutdown for poller
+    /// Request shutdown for poller. In-progress TLS handshakes are cut off
     pub fn stop_poller(&mut self) {
         self.polling = false;
+        self.accept_workers_stopping.store(true, Ordering::SeqCst);
     }
 
     /// Poll and dispatch new listener connections
     pub fn poll_new_connections(&mut self) -> Result<(), AppError> {
+        self.start_polling()?;
+
+        while self.poll_once()? {
+            // Add delay between accepts
+            thread::sleep(Duration::from_millis(ACCEPT_DELAY_MSECS));
+        }
+
+        Ok(())
+    }
+
+    /// Enter polling state, for when the poll loop is driven externally (via `poll_once`)
+    pub fn start_polling(&mut self) -> Result<(), AppError> {
         self.assert_listening()?;
 
         if self.polling {
@@ -97,39 +165,45 @@ impl Server {
             )));
         }
 
+        self.accept_workers_stopping = Arc::new(AtomicBool::new(false));
+        self.spawn_handshake_workers();
+        self.spawn_accept_workers()?;
         self.polling = true;
 
         info(
             &target!(),
             &format!(
-                "Polling connections started: server_addr={:?}",
-                &self.listen_addr
+                "Polling connections started: server_addr={:?}, accept_workers={}, handshake_workers={}",
+                &self.listen_addr, self.accept_workers, self.handshake_workers
             ),
         );
 
-        loop {
-            // Accept new connection (non-blocking
-            if let Err(err) = self.accept() {
-                match err {
-                    AppError::WouldBlock => {}
-                    _ => error(&target!(), &format!("{:?}", err)),
-                }
-            }
+        Ok(())
+    }
 
-            // Check if shutdown requested
-            if self.visitor.lock().unwrap().get_shutdown_requested() {
-                self.polling = false;
-                self.closing = true;
+    /// Perform a single (non-blocking) poll iteration: accept new connection and check for shutdown.
+    /// Returns whether polling should continue.
+    pub fn poll_once(&mut self) -> Result<bool, AppError> {
+        // Accept new connection (non-blocking
+        if let Err(err) = self.accept() {
+            match err {
+                AppError::WouldBlock => {}
+                _ => error(&target!(), &format!("{:?}", err)),
             }
+        }
 
-            if !self.polling {
-                break;
-            }
+        // Check if shutdown requested
+        if self.visitor.lock().unwrap().get_shutdown_requested() {
+            self.polling = false;
+            self.closing = true;
+        }
 
-            // Add delay between accepts
-            thread::sleep(Duration::from_millis(30));
+        if self.polling {
+            return Ok(true);
         }
 
+        self.accept_workers_stopping.store(true, Ordering::SeqCst);
+
         info(
             &target!(),
             &format!(
@@ -142,7 +216,7 @@ impl Server {
             self.perform_shutdown();
         }
 
-        Ok(())
+        Ok(false)
     }
 
     /// Spawn a thread to handle connection processing
@@ -190,6 +264,8 @@ impl Server {
         self.closed = true;
         self.polling = false;
         self.tcp_listener = None;
+        self.handshake_sender = None;
+        self.accept_workers_stopping.store(true, Ordering::SeqCst);
 
         info(
             &target!(),
@@ -199,35 +275,202 @@ impl Server {
 
     /// New connection acceptance processor
     fn accept(&mut self) -> Result<(), AppError> {
-        // Accept new connection
-        let (mut tcp_stream, peer_addr) =
-            self.tcp_listener
+        Self::accept_connection(
+            self.tcp_listener.as_ref().unwrap(),
+            &self.listen_addr,
+            self.handshake_sender.as_ref().unwrap(),
+            self.handshake_timeout,
+        )
+    }
+
+    /// Spawn the handshake workers, each TLS handshaking (and dispatching) queued accepted connections until polling
+    /// ends. Connections still queued then are closed
+    fn spawn_handshake_workers(&mut self) {
+        let (handshake_sender, handshake_receiver) =
+            mpsc::sync_channel(self.handshake_workers * TLS_HANDSHAKE_QUEUE_SIZE_PER_WORKER);
+        let handshake_receiver = Arc::new(Mutex::new(handshake_receiver));
+
+        for _ in 0..self.handshake_workers {
+            let handshake_receiver = handshake_receiver.clone();
+            let listen_addr = self.listen_addr.clone();
+            let visitor = self.visitor.clone();
+            let stopping = self.accept_workers_stopping.clone();
+
+            thread::spawn(move || {
+                Self::run_handshake_worker(&handshake_receiver, &listen_addr, &visitor, &stopping)
+            });
+        }
+
+        self.handshake_sender = Some(handshake_sender);
+    }
+
+    /// Handshake worker loop (runs until polling ends)
+    fn run_handshake_worker(
+        handshake_receiver: &Mutex<Receiver<PendingHandshake>>,
+        listen_addr: &str,
+        visitor: &Arc<Mutex<dyn ServerVisitor>>,
+        stopping: &AtomicBool,
+    ) {
+        while !stopping.load(Ordering::SeqCst) {
+            let pending_handshake = handshake_receiver
+                .lock()
+                .unwrap()
+                .recv_timeout(Duration::from_millis(TLS_HANDSHAKE_POLL_MSECS));
+
+            match pending_handshake {
+                Ok((tcp_stream, peer_addr, handshake_deadline)) => {
+                    if let Err(err) = Self::handshake_connection(
+                        tcp_stream,
+                        &peer_addr,
+                        listen_addr,
+                        visitor,
+                        stopping,
+                        handshake_deadline,
+                    ) {
+                        error(&target!(), &format!("{:?}", err));
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    }
+
+    /// Spawn the additional accept workers (if any), each accepting new connections (on a copy of the listener)
+    /// until polling ends
+    fn spawn_accept_workers(&mut self) -> Result<(), AppError> {
+        for _ in 1..self.accept_workers {
+            let tcp_listener = self
+                .tcp_listener
                 .as_ref()
                 .unwrap()
-                .accept()
+                .try_clone()
                 .map_err(|err| {
-                    if err.kind() == io::ErrorKind::WouldBlock {
-                        AppError::WouldBlock
-                    } else {
-                        AppError::GenWithMsgAndErr(
-                            format!(
-                                "Error accepting connection: server_addr={:?}",
-                                &self.listen_addr
-                            ),
-                            Box::new(err),
-                        )
-                    }
+                    AppError::GenWithMsgAndErr(
+                        format!(
+                            "Error cloning listener for accept worker: server_addr={:?}",
+                            &self.listen_addr
+                        ),
+                        Box::new(err),
+                    )
                 })?;
+            let listen_addr = self.listen_addr.clone();
+            let handshake_sender = self.handshake_sender.as_ref().unwrap().clone();
+            let stopping = self.accept_workers_stopping.clone();
+            let handshake_timeout = self.handshake_timeout;
+
+            thread::spawn(move || {
+                while !stopping.load(Ordering::SeqCst) {
+                    match Self::accept_connection(
+                        &tcp_listener,
+                        &listen_addr,
+                        &handshake_sender,
+                        handshake_timeout,
+                    ) {
+                        Ok(()) => {}
+                        Err(AppError::WouldBlock) => {
+                            thread::sleep(Duration::from_millis(ACCEPT_DELAY_MSECS))
+                        }
+                        Err(err) => error(&target!(), &format!("{:?}", err)),
+                    }
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Accept new connection on given listener (non-blocking), queueing it for the handshake workers to TLS handshake
+    /// and dispatch it to the visitor (see `handshake_connection`). The connection is closed if the queue is full
+    fn accept_connection(
+        tcp_listener: &TcpListener,
+        listen_addr: &str,
+        handshake_sender: &SyncSender<PendingHandshake>,
+        handshake_timeout: Duration,
+    ) -> Result<(), AppError> {
+        // Accept new connection
+        let (tcp_stream, peer_addr) = tcp_listener.accept().map_err(|err| {
+            if err.kind() == io::ErrorKind::WouldBlock {
+                AppError::WouldBlock
+            } else {
+                AppError::GenWithMsgAndErr(
+                    format!("Error accepting connection: server_addr={:?}", &listen_addr),
+                    Box::new(err),
+                )
+            }
+        })?;
+
+        match handshake_sender.try_send((tcp_stream, peer_addr, Instant::now() + handshake_timeout)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                warn(
+                    &target!(),
+                    &format!(
+                        "TLS handshake queue full, connection closed: server_addr={:?}, peer_addr={:?}",
+                        &listen_addr, &peer_addr
+                    ),
+                );
+                Ok(())
+            }
+            Err(TrySendError::Disconnected(_)) => Err(AppError::General(format!(
+                "TLS handshake workers stopped, connection closed: server_addr={:?}, peer_addr={:?}",
+                &listen_addr, &peer_addr
+            ))),
+        }
+    }
+
+    /// TLS handshake new connection (cut off if not completed by the deadline or if the server is stopping), then
+    /// dispatch it to the visitor. The visitor lock is not held during socket I/O or while dialing upstream
+    fn handshake_connection(
+        mut tcp_stream: TcpStream,
+        peer_addr: &SocketAddr,
+        listen_addr: &str,
+        visitor: &Arc<Mutex<dyn ServerVisitor>>,
+        stopping: &AtomicBool,
+        handshake_deadline: Instant,
+    ) -> Result<(), AppError> {
+        Self::set_stream_timeouts(
+            &tcp_stream,
+            Some(Duration::from_millis(TLS_HANDSHAKE_POLL_MSECS)),
+            listen_addr,
+            peer_addr,
+        )?;
 
         let mut acceptor = Acceptor::default();
 
         let accepted = loop {
-            acceptor.read_tls(&mut tcp_stream).unwrap();
+            let read_size = match acceptor.read_tls(&mut tcp_stream) {
+                Ok(read_size) => read_size,
+                Err(err) if Self::is_timed_out(&err) => {
+                    Self::check_handshake_cutoff(
+                        stopping,
+                        handshake_deadline,
+                        listen_addr,
+                        peer_addr,
+                    )?;
+                    continue;
+                }
+                Err(err) => {
+                    return Err(AppError::GenWithMsgAndErr(
+                        format!(
+                            "Error reading TLS client hello: server_addr={:?}, peer_addr={:?}",
+                            &listen_addr, &peer_addr
+                        ),
+                        Box::new(err),
+                    ))
+                }
+            };
+            if read_size == 0 {
+                return Err(AppError::General(format!(
+                    "Connection closed before TLS client hello: server_addr={:?}, peer_addr={:?}",
+                    &listen_addr, &peer_addr
+                )));
+            }
             if let Some(accepted) = acceptor.accept().map_err(|err| {
                 AppError::GenWithMsgAndErr(
                     format!(
                         "Error reading TLS client hello: server_addr={:?}, peer_addr={:?}",
-                        &self.listen_addr, &peer_addr
+                        &listen_addr, &peer_addr
                     ),
                     Box::new(err.clone()),
                 )
@@ -236,53 +479,147 @@ impl Server {
             }
         };
 
-        let tls_server_config =
-            Arc::new(self.visitor.lock().unwrap().on_tls_handshaking(&accepted)?);
+        let tls_server_config = Arc::new(visitor.lock().unwrap().on_tls_handshaking(&accepted)?);
 
         let mut tls_srv_conn = accepted.into_connection(tls_server_config).map_err(|err| {
             AppError::GenWithMsgAndErr(
                 format!(
                     "Error creating TLS server connection: server_addr={:?}, peer_addr={:?}",
-                    &self.listen_addr, &peer_addr
+                    &listen_addr, &peer_addr
                 ),
                 Box::new(err),
             )
         })?;
 
-        let _ = tls_srv_conn.complete_io(&mut tcp_stream).map_err(|err| {
-            AppError::GenWithMsgAndErr(
-                format!(
-                    "Error completing TLS server connection: server_addr={:?}, peer_addr={:?}",
-                    &self.listen_addr, &peer_addr
-                ),
-                Box::new(err),
-            )
-        })?;
+        loop {
+            match tls_srv_conn.complete_io(&mut tcp_stream) {
+                Ok(_) => break,
+                Err(err) if Self::is_timed_out(&err) => Self::check_handshake_cutoff(
+                    stopping,
+                    handshake_deadline,
+                    listen_addr,
+                    peer_addr,
+                )?,
+                Err(err) => {
+                    return Err(AppError::GenWithMsgAndErr(
+                        format!(
+                        "Error completing TLS server connection: server_addr={:?}, peer_addr={:?}",
+                        &listen_addr, &peer_addr
+                    ),
+                        Box::new(err),
+                    ))
+                }
+            }
+        }
+
+        Self::set_stream_timeouts(&tcp_stream, None, listen_addr, peer_addr)?;
+        Self::check_handshake_cutoff(stopping, handshake_deadline, listen_addr, peer_addr)?;
 
         tcp_stream.set_nonblocking(true).map_err(|err| {
             AppError::GenWithMsgAndErr(
                 format!(
                     "Failed making socket non-blocking: server_addr={:?}, peer_addr={:?}",
-                    &self.listen_addr, &peer_addr
+                    &listen_addr, &peer_addr
                 ),
                 Box::new(err),
             )
         })?;
 
+        let abortive_close_stream = match visitor.lock().unwrap().is_abortive_close_on_denial() {
+            true => Some(stream_utils::clone_std_tcp_stream(&tcp_stream)?),
+            false => None,
+        };
+
         let tls_conn = rustls::StreamOwned::new(tls_srv_conn, tcp_stream);
 
-        let connection = self.visitor.lock().unwrap().create_client_conn(tls_conn)?;
+        let connection = visitor
+            .lock()
+            .unwrap()
+            .create_client_conn(tls_conn)
+            .inspect_err(|_| {
+                if let Some(tcp_stream) = &abortive_close_stream {
+                    let _ = stream_utils::set_abortive_close(tcp_stream);
+                }
+            })?;
+
+        if visitor.lock().unwrap().is_conn_log_sampled() {
+            info(
+                &target!(),
+                &format!(
+                    "Client connected: peer_addr={:?}, tls_session=({})",
+                    &peer_addr,
+                    connection.get_tls_session_info()
+                ),
+            );
+        }
 
-        info(
-            &target!(),
-            &format!("Client connected: peer_addr={:?}", &peer_addr),
-        );
+        let upstream_dialer = visitor.lock().unwrap().get_upstream_dialer(&connection)?;
 
-        self.visitor.lock().unwrap().on_conn_accepted(connection)?;
+        match upstream_dialer {
+            Some(upstream_dialer) => {
+                let upstream_stream = upstream_dialer()?;
+                visitor
+                    .lock()
+                    .unwrap()
+                    .on_conn_dialed(connection, upstream_stream)?;
+            }
+            None => visitor.lock().unwrap().on_conn_accepted(connection)?,
+        }
 
         Ok(())
     }
 
+    /// Returns error if the in-progress TLS handshake should be cut off (server stopping or handshake deadline reached)
+    fn check_handshake_cutoff(
+        stopping: &AtomicBool,
+        handshake_deadline: Instant,
+        listen_addr: &str,
+        peer_addr: &SocketAddr,
+    ) -> Result<(), AppError> {
+        if stopping.load(Ordering::SeqCst) {
+            Err(AppError::General(format!(
+                "TLS handshake cut off, server stopping: server_addr={:?}, peer_addr={:?}",
+                &listen_addr, &peer_addr
+            )))
+        } else if Instant::now() >= handshake_deadline {
+            Err(AppError::General(format!(
+                "TLS handshake timed out: server_addr={:?}, peer_addr={:?}",
+                &listen_addr, &peer_addr
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns whether the I/O error was due to a (blocking) socket read/write timeout
+    fn is_timed_out(err: &io::Error) -> bool {
+        matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        )
+    }
+
+    /// Set (or clear) the stream's read and write timeouts
+    fn set_stream_timeouts(
+        tcp_stream: &TcpStream,
+        timeout: Option<Duration>,
+        listen_addr: &str,
+        peer_addr: &SocketAddr,
+    ) -> Result<(), AppError> {
+        tcp_stream
+            .set_read_timeout(timeout)
+            .and_then(|_| tcp_stream.set_write_timeout(timeout))
+            .map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    format!(
+                        "Failed setting socket timeouts: server_addr={:?}, peer_addr={:?}",
+                        &listen_addr, &peer_addr
+                    ),
+                    Box::new(err),
+                )
+            })
+    }
+
     fn assert_listening(&self) -> Result<(), AppError> {
         if self.tcp_listener.is_none() {
             return Err(AppError::General("Gateway not listening".to_string()));
@@ -318,8 +655,312 @@ pub trait ServerVisitor: Send {
         Ok(())
     }
 
+    /// Upstream dialer for the accepted connection (if it requires one). When given, the dialer is invoked without
+    /// the visitor lock held, and the connection is then passed (with the dialed stream) to `on_conn_dialed`,
+    /// instead of `on_conn_accepted`
+    fn get_upstream_dialer(
+        &mut self,
+        _connection: &conn_std::Connection,
+    ) -> Result<Option<UpstreamDialer>, AppError> {
+        Ok(None)
+    }
+
+    /// Connection accepted, along with its dialed upstream stream (see `get_upstream_dialer`)
+    fn on_conn_dialed(
+        &mut self,
+        connection: conn_std::Connection,
+        _upstream_stream: TcpStream,
+    ) -> Result<(), AppError> {
+        self.on_conn_accepted(connection)
+    }
+
     /// Returns whether listener shutdown is required
     fn get_shutdown_requested(&self) -> bool {
         false
     }
+
+    /// Returns whether the (last created) client connection's lifecycle is logged (see `logging::LogSampler`)
+    fn is_conn_log_sampled(&self) -> bool {
+        true
+    }
+
+    /// Returns whether connections, which fail client connection creation (for instance, denied by authorization),
+    /// are reset (RST) rather than gracefully closed (FIN)
+    fn is_abortive_close_on_denial(&self) -> bool {
+        false
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use mockall::mock;
+
+    // mocks
+    // =====
+
+    mock! {
+        pub ServerVisit {}
+        impl ServerVisitor for ServerVisit {
+            fn create_client_conn(&mut self, tls_conn: TlsServerConnection) -> Result<conn_std::Connection, AppError>;
+            fn on_listening(&mut self) -> Result<(), AppError>;
+            fn on_tls_handshaking(&mut self, _accepted: &Accepted) -> Result<rustls::ServerConfig, AppError>;
+            fn on_conn_accepted(&mut self, connection: conn_std::Connection) -> Result<(), AppError>;
+            fn get_shutdown_requested(&self) -> bool;
+        }
+    }
+
+    // tests
+    // =====
+
+    #[test]
+    fn server_bind_listener_when_ephemeral_port() {
+        let mut visitor = MockServerVisit::new();
+        visitor
+            .expect_on_listening()
+            .times(1)
+            .return_once(|| Ok(()));
+        let mut server = Server::new(Arc::new(Mutex::new(visitor)), 0);
+
+        assert!(server.get_bound_port().is_none());
+
+        if let Err(err) = server.bind_listener() {
+            panic!("Unexpected bind result: err={:?}", &err);
+        }
+
+        let bound_port = server.get_bound_port();
+
+        assert!(bound_port.is_some());
+        assert_ne!(bound_port.unwrap(), 0);
+        assert_eq!(server.listen_addr, format!("[::]:{}", bound_port.unwrap()));
+    }
+
+    fn send_client_hello(server_port: u16, server_name: &str) -> std::net::TcpStream {
+        let client_config = rustls::ClientConfig::builder_with_provider(
+            rustls::crypto::ring::default_provider().into(),
+        )
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(rustls::RootCertStore::empty())
+        .with_no_client_auth();
+        let mut client_conn = rustls::ClientConnection::new(
+            Arc::new(client_config),
+            rustls::pki_types::ServerName::try_from(server_name.to_string()).unwrap(),
+        )
+        .unwrap();
+
+        let mut tcp_stream = std::net::TcpStream::connect(("127.0.0.1", server_port)).unwrap();
+        client_conn.write_tls(&mut tcp_stream).unwrap();
+        tcp_stream
+    }
+
+    #[test]
+    fn server_poll_new_connections_when_multiple_accept_workers() {
+        const CLIENT_COUNT: usize = 6;
+
+        let handshake_server_names = Arc::new(Mutex::new(Vec::new()));
+        let shutdown_requested = Arc::new(AtomicBool::new(false));
+
+        let mut visitor = MockServerVisit::new();
+        visitor
+            .expect_on_listening()
+            .times(1)
+            .return_once(|| Ok(()));
+        let server_names = handshake_server_names.clone();
+        visitor
+            .expect_on_tls_handshaking()
+            .times(CLIENT_COUNT)
+            .returning(move |accepted| {
+                server_names
+                    .lock()
+                    .unwrap()
+                    .push(accepted.client_hello().server_name().unwrap().to_string());
+                Err(AppError::General("Handshake rejected".to_string()))
+            });
+        visitor.expect_create_client_conn().never();
+        let shutdown_flag = shutdown_requested.clone();
+        visitor
+            .expect_get_shutdown_requested()
+            .returning(move || shutdown_flag.load(Ordering::SeqCst));
+
+        let mut server = Server::new(Arc::new(Mutex::new(visitor)), 0);
+        server.set_accept_workers(3);
+        server.bind_listener().unwrap();
+        let server_port = server.get_bound_port().unwrap();
+
+        let poll_handle = thread::spawn(move || server.poll_new_connections());
+
+        // Connection, which never sends its client hello, must not hold up other accepts
+        let stalled_stream = std::net::TcpStream::connect(("127.0.0.1", server_port)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        let client_streams: Vec<_> = (0..CLIENT_COUNT)
+            .map(|client_num| send_client_hello(server_port, &format!("client{}.test", client_num)))
+            .collect();
+
+        let started_at = std::time::Instant::now();
+        while handshake_server_names.lock().unwrap().len() < CLIENT_COUNT {
+            if started_at.elapsed() > Duration::from_secs(5) {
+                panic!(
+                    "Timed out waiting for concurrent accepts: handshakes={:?}",
+                    handshake_server_names.lock().unwrap()
+                );
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let mut server_names = handshake_server_names.lock().unwrap().clone();
+        server_names.sort();
+        assert_eq!(
+            server_names,
+            (0..CLIENT_COUNT)
+                .map(|client_num| format!("client{}.test", client_num))
+                .collect::<Vec<String>>()
+        );
+
+        drop(stalled_stream);
+        drop(client_streams);
+        shutdown_requested.store(true, Ordering::SeqCst);
+
+        if let Err(err) = poll_handle.join().unwrap() {
+            panic!("Unexpected poll result: err={:?}", &err);
+        }
+    }
+
+    #[test]
+    fn server_poll_once_when_client_hello_stalled() {
+        let mut visitor = MockServerVisit::new();
+        visitor
+            .expect_on_listening()
+            .times(1)
+            .return_once(|| Ok(()));
+        visitor.expect_on_tls_handshaking().never();
+        visitor.expect_create_client_conn().never();
+        visitor.expect_get_shutdown_requested().returning(|| false);
+
+        let mut server = Server::new(Arc::new(Mutex::new(visitor)), 0);
+        server.set_handshake_timeout(Duration::from_millis(300));
+        server.bind_listener().unwrap();
+        server.start_polling().unwrap();
+        let server_port = server.get_bound_port().unwrap();
+
+        let mut stalled_stream = std::net::TcpStream::connect(("127.0.0.1", server_port)).unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        let started_at = Instant::now();
+        assert!(server.poll_once().unwrap());
+        assert!(server.poll_once().unwrap());
+        assert!(started_at.elapsed() < Duration::from_millis(200));
+
+        // Handshake timeout elapses, closing the stalled connection
+        stalled_stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let started_at = Instant::now();
+        let mut buffer = [0u8; 16];
+        match io::Read::read(&mut stalled_stream, &mut buffer) {
+            Ok(read_size) => assert_eq!(read_size, 0),
+            Err(err) => assert_eq!(err.kind(), io::ErrorKind::ConnectionReset),
+        }
+        assert!(started_at.elapsed() < Duration::from_secs(2));
+
+        server.shutdown();
+    }
+
+    #[test]
+    fn server_poll_once_when_handshake_queue_full() {
+        let mut visitor = MockServerVisit::new();
+        visitor
+            .expect_on_listening()
+            .times(1)
+            .return_once(|| Ok(()));
+        visitor.expect_on_tls_handshaking().never();
+        visitor.expect_create_client_conn().never();
+        visitor.expect_get_shutdown_requested().returning(|| false);
+
+        let mut server = Server::new(Arc::new(Mutex::new(visitor)), 0);
+        server.set_handshake_workers(1);
+        server.set_handshake_timeout(Duration::from_secs(30));
+        server.bind_listener().unwrap();
+        server.start_polling().unwrap();
+        let server_port = server.get_bound_port().unwrap();
+
+        // Stalled connections occupy the (single) handshake worker and fill its queue
+        let mut stalled_streams = vec![];
+        for _ in 0..(1 + TLS_HANDSHAKE_QUEUE_SIZE_PER_WORKER) {
+            stalled_streams.push(std::net::TcpStream::connect(("127.0.0.1", server_port)).unwrap());
+            thread::sleep(Duration::from_millis(20));
+            assert!(server.poll_once().unwrap());
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let mut rejected_stream = std::net::TcpStream::connect(("127.0.0.1", server_port)).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(server.poll_once().unwrap());
+
+        // Connection beyond the queue is closed straight away
+        rejected_stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let started_at = Instant::now();
+        let mut buffer = [0u8; 16];
+        match io::Read::read(&mut rejected_stream, &mut buffer) {
+            Ok(read_size) => assert_eq!(read_size, 0),
+            Err(err) => assert_eq!(err.kind(), io::ErrorKind::ConnectionReset),
+        }
+        assert!(started_at.elapsed() < Duration::from_secs(2));
+
+        // Queued connections remain open
+        let queued_stream = stalled_streams.last_mut().unwrap();
+        queued_stream
+            .set_read_timeout(Some(Duration::from_millis(200)))
+            .unwrap();
+        match io::Read::read(queued_stream, &mut buffer) {
+            Ok(read_size) => panic!("Unexpected queued connection read: size={}", read_size),
+            Err(err) => assert!(
+                (err.kind() == io::ErrorKind::WouldBlock)
+                    || (err.kind() == io::ErrorKind::TimedOut)
+            ),
+        }
+
+        server.shutdown();
+    }
+
+    #[test]
+    fn server_shutdown_when_handshake_in_progress() {
+        let mut visitor = MockServerVisit::new();
+        visitor
+            .expect_on_listening()
+            .times(1)
+            .return_once(|| Ok(()));
+        visitor.expect_on_tls_handshaking().never();
+        visitor.expect_create_client_conn().never();
+        visitor.expect_get_shutdown_requested().returning(|| false);
+
+        let mut server = Server::new(Arc::new(Mutex::new(visitor)), 0);
+        server.set_handshake_timeout(Duration::from_secs(30));
+        server.bind_listener().unwrap();
+        server.start_polling().unwrap();
+        let server_port = server.get_bound_port().unwrap();
+
+        let mut stalled_stream = std::net::TcpStream::connect(("127.0.0.1", server_port)).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(server.poll_once().unwrap());
+
+        server.shutdown();
+
+        // Stalled handshake is cut off well before its timeout
+        stalled_stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let started_at = Instant::now();
+        let mut buffer = [0u8; 16];
+        match io::Read::read(&mut stalled_stream, &mut buffer) {
+            Ok(read_size) => assert_eq!(read_size, 0),
+            Err(err) => assert_eq!(err.kind(), io::ErrorKind::ConnectionReset),
+        }
+        assert!(started_at.elapsed() < Duration::from_secs(2));
+    }
 }
@@ -15,6 +15,18 @@ const POLL_SERVER_SOCKET_TOKEN: mio::Token = mio::Token(0);
 const POLL_DURATION_MSECS: u64 = 1000;
 
 const RECV_BUFFER_SIZE: usize = 64 * 1024;
+const POLL_ONCE_MAX_MESSAGES: usize = 64;
+
+/// Server socket message receive mode
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RecvMode {
+    /// Non-blocking socket, polled for read readiness (via MIO)
+    #[default]
+    NonBlocking,
+    /// Blocking socket receives, with given read timeout (between shutdown request checks). May give lower
+    /// latency jitter for low-rate services
+    Blocking(Duration),
+}
 
 /// This is a UDP server, which will listen/accept client connections
 pub struct Server {
@@ -22,6 +34,8 @@ pub struct Server {
     _server_port: u16,
     server_socket: Option<UdpSocket>,
     server_addr: SocketAddr,
+    recv_mode: RecvMode,
+    reuse_port: bool,
     polling: bool,
     closing: bool,
     closed: bool,
@@ -30,6 +44,15 @@ pub struct Server {
 impl Server {
     /// Server constructor
     pub fn new(visitor: Arc<Mutex<dyn ServerVisitor>>, server_port: u16) -> Result<Self, AppError> {
+        Self::new_with_recv_mode(visitor, server_port, RecvMode::default())
+    }
+
+    /// Server constructor, using given server socket message receive mode
+    pub fn new_with_recv_mode(
+        visitor: Arc<Mutex<dyn ServerVisitor>>,
+        server_port: u16,
+        recv_mode: RecvMode,
+    ) -> Result<Self, AppError> {
         let server_addr_str = format!("[::]:{}", server_port);
         let server_addr = SocketAddr::from_str(&server_addr_str).map_err(|err| {
             AppError::GenWithMsgAndErr(
@@ -46,32 +69,56 @@ impl Server {
             _server_port: server_port,
             server_socket: None,
             server_addr,
+            recv_mode,
+            reuse_port: false,
             polling: false,
             closing: false,
             closed: false,
         })
     }
 
+    /// Allow other (per-peer) sockets to bind to the server port. Must be set prior to binding listener.
+    pub fn set_reuse_port(&mut self, reuse_port: bool) {
+        self.reuse_port = reuse_port;
+    }
+
     /// Bind/listen on port
     pub fn bind_listener(&mut self) -> Result<(), AppError> {
-        let server_socket = UdpSocket::bind(self.server_addr).map_err(|err| {
-            AppError::GenWithMsgAndErr(
-                format!(
-                    "Error binding UDP socket: server_addr={:?}",
-                    &self.server_addr
-                ),
-                Box::new(err),
-            )
-        })?;
-        server_socket.set_nonblocking(true).map_err(|err| {
-            AppError::GenWithMsgAndErr(
-                format!(
-                    "Failed making UDP server socket non-blocking: server_addr={:?}",
-                    &self.server_addr
-                ),
-                Box::new(err),
-            )
-        })?;
+        let server_socket = if self.reuse_port {
+            Self::bind_reusable_socket(&self.server_addr)
+        } else {
+            UdpSocket::bind(self.server_addr).map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    format!(
+                        "Error binding UDP socket: server_addr={:?}",
+                        &self.server_addr
+                    ),
+                    Box::new(err),
+                )
+            })
+        }?;
+        match self.recv_mode {
+            RecvMode::NonBlocking => server_socket.set_nonblocking(true).map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    format!(
+                        "Failed making UDP server socket non-blocking: server_addr={:?}",
+                        &self.server_addr
+                    ),
+                    Box::new(err),
+                )
+            }),
+            RecvMode::Blocking(recv_timeout) => server_socket
+                .set_read_timeout(Some(recv_timeout))
+                .map_err(|err| {
+                    AppError::GenWithMsgAndErr(
+                        format!(
+                            "Failed setting UDP server socket read timeout: server_addr={:?}",
+                            &self.server_addr
+                        ),
+                        Box::new(err),
+                    )
+                }),
+        }?;
 
         self.server_socket = Some(server_socket);
         self.closing = false;
@@ -116,23 +163,86 @@ impl Server {
         self.polling = false;
     }
 
-    /// Send message to client socket
+    /// Send message to client socket. A short send (fewer bytes sent than given) is treated as an error.
     pub fn send_message(
         server_socket: &UdpSocket,
         socket_addr: &SocketAddr,
         data: &Vec<u8>,
     ) -> Result<usize, AppError> {
-        server_socket
-            .send_to(data.as_slice(), socket_addr)
-            .map_err(|err| {
-                AppError::GenWithMsgAndErr(
-                    format!(
-                        "Error while sending message on UDP socket: dest={:?}",
-                        socket_addr
-                    ),
-                    Box::new(err),
-                )
-            })
+        Self::process_send_result(
+            server_socket.send_to(data.as_slice(), socket_addr),
+            socket_addr,
+            data.len(),
+        )
+    }
+
+    /// Convert UDP send result, returning an error on send failure or if message was truncated
+    fn process_send_result(
+        send_result: io::Result<usize>,
+        socket_addr: &SocketAddr,
+        expected_size: usize,
+    ) -> Result<usize, AppError> {
+        match send_result {
+            Ok(sent_size) if sent_size < expected_size => Err(AppError::General(format!(
+                "Short send on UDP socket, message truncated: dest={:?}, expected={}, actual={}",
+                socket_addr, expected_size, sent_size
+            ))),
+            Ok(sent_size) => Ok(sent_size),
+            Err(err) => Err(AppError::GenWithMsgAndErr(
+                format!(
+                    "Error while sending message on UDP socket: dest={:?}",
+                    socket_addr
+                ),
+                Box::new(err),
+            )),
+        }
+    }
+
+    /// Bind UDP socket, allowing other sockets to (also) bind to the same address
+    pub fn bind_reusable_socket(local_addr: &SocketAddr) -> Result<UdpSocket, AppError> {
+        let map_bind_err = |err: io::Error| {
+            AppError::GenWithMsgAndErr(
+                format!(
+                    "Error binding reusable UDP socket: local_addr={:?}",
+                    local_addr
+                ),
+                Box::new(err),
+            )
+        };
+
+        let socket = socket2::Socket::new(
+            socket2::Domain::for_address(*local_addr),
+            socket2::Type::DGRAM,
+            Some(socket2::Protocol::UDP),
+        )
+        .map_err(map_bind_err)?;
+        socket.set_reuse_address(true).map_err(map_bind_err)?;
+        socket.set_reuse_port(true).map_err(map_bind_err)?;
+        socket.bind(&(*local_addr).into()).map_err(map_bind_err)?;
+
+        Ok(socket.into())
+    }
+
+    /// Create socket, bound to the server's (reusable) address and connected to the given peer. Replies sent
+    /// on this socket will originate from the server port, and the peer's subsequent messages will be delivered
+    /// to this socket (rather than the server socket).
+    pub fn bind_peer_socket(
+        local_addr: &SocketAddr,
+        peer_addr: &SocketAddr,
+    ) -> Result<UdpSocket, AppError> {
+        let peer_socket = Self::bind_reusable_socket(local_addr)?;
+
+        peer_socket.connect(peer_addr).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                format!(
+                    "Error connecting UDP peer socket: local_addr={:?}, peer_addr={:?}",
+                    local_addr, peer_addr
+                ),
+                Box::new(err),
+            )
+        })?;
+
+        Ok(peer_socket)
     }
 
     /// Shutdown for poller and listener
@@ -159,6 +269,10 @@ impl Server {
             )));
         }
 
+        if let RecvMode::Blocking(_) = self.recv_mode {
+            return self.poll_new_messages_blocking();
+        }
+
         // Setup MIO poller
         let mut server_socket = mio::net::UdpSocket::from_std(stream_utils::clone_std_udp_socket(
             self.server_socket.as_ref().unwrap(),
@@ -254,37 +368,177 @@ impl Server {
         Ok(())
     }
 
-    /// New client message acceptance processor
+    /// Enter polling state, for when the poll loop is driven externally (via `poll_once`). Requires the non-blocking
+    /// receive mode
+    pub fn start_polling(&mut self) -> Result<(), AppError> {
+        self.assert_listening()?;
+
+        if self.polling {
+            return Err(AppError::General(format!(
+                "Already polling for new messages: server_addr={:?}",
+                &self.server_addr
+            )));
+        }
+
+        if let RecvMode::Blocking(_) = self.recv_mode {
+            return Err(AppError::General(format!(
+                "Externally driven polling requires non-blocking receive mode: server_addr={:?}",
+                &self.server_addr
+            )));
+        }
+
+        self.polling = true;
+
+        info(
+            &target!(),
+            &format!(
+                "Polling messages started: server_addr={:?}",
+                &self.server_addr
+            ),
+        );
+
+        Ok(())
+    }
+
+    /// Perform a single (non-blocking) poll iteration: receive and dispatch pending messages (up to a maximum per
+    /// poll) and check for shutdown. Returns whether polling should continue.
+    pub fn poll_once(&mut self) -> Result<bool, AppError> {
+        for _ in 0..POLL_ONCE_MAX_MESSAGES {
+            match self.accept_message() {
+                Ok(()) => {}
+                Err(AppError::WouldBlock) => break,
+                Err(err) => error(&target!(), &format!("{:?}", err)),
+            }
+        }
+
+        // Check if shutdown requested
+        if self.visitor.lock().unwrap().get_shutdown_requested() {
+            self.polling = false;
+            self.closing = true;
+        }
+
+        if self.polling {
+            return Ok(true);
+        }
+
+        info(
+            &target!(),
+            &format!(
+                "Polling messages ended: server_addr={:?}",
+                &self.server_addr
+            ),
+        );
+
+        if self.closing {
+            self.perform_shutdown();
+        }
+
+        Ok(false)
+    }
+
+    /// Receive and dispatch new incoming messages, using blocking (with timeout) server socket receives
+    fn poll_new_messages_blocking(&mut self) -> Result<(), AppError> {
+        self.polling = true;
+
+        info(
+            &target!(),
+            &format!(
+                "Polling (blocking) messages started: server_addr={:?}",
+                &self.server_addr
+            ),
+        );
+
+        while self.polling {
+            if let Err(err) = self.accept_message() {
+                match err {
+                    AppError::WouldBlock => {}
+                    _ => error(&target!(), &format!("{:?}", err)),
+                }
+            }
+
+            // Check if shutdown requested
+            if self.visitor.lock().unwrap().get_shutdown_requested() {
+                self.polling = false;
+                self.closing = true;
+            }
+        }
+
+        info(
+            &target!(),
+            &format!(
+                "Polling (blocking) messages ended: server_addr={:?}",
+                &self.server_addr
+            ),
+        );
+
+        if self.closing {
+            self.perform_shutdown();
+        }
+
+        Ok(())
+    }
+
+    /// New client message acceptance processor. If the server socket was taken (shutdown while polling), polling is
+    /// stopped and a would-block result is returned
     fn accept_message(&mut self) -> Result<(), AppError> {
+        let server_socket = match self.server_socket.as_ref() {
+            Some(server_socket) => server_socket,
+            None => {
+                debug(
+                    &target!(),
+                    &format!(
+                        "Server socket closed, stopping message acceptance: server_addr={:?}",
+                        &self.server_addr
+                    ),
+                );
+                self.polling = false;
+                return Err(AppError::WouldBlock);
+            }
+        };
+
         // Accept message
         let mut buffer = [0; RECV_BUFFER_SIZE];
 
-        let (message_size, peer_addr) = self
-            .server_socket
-            .as_ref()
-            .unwrap()
-            .recv_from(&mut buffer)
-            .map_err(|err| {
-                if err.kind() == io::ErrorKind::WouldBlock {
-                    AppError::WouldBlock
-                } else {
-                    AppError::GenWithMsgAndErr(
-                        format!(
-                            "Error receiving message: server_addr={:?}",
-                            &self.server_addr
-                        ),
-                        Box::new(err),
-                    )
-                }
-            })?;
+        let (message_size, peer_addr) = server_socket.recv_from(&mut buffer).map_err(|err| {
+            if (err.kind() == io::ErrorKind::WouldBlock) || (err.kind() == io::ErrorKind::TimedOut)
+            {
+                AppError::WouldBlock
+            } else {
+                AppError::GenWithMsgAndErr(
+                    format!(
+                        "Error receiving message: server_addr={:?}",
+                        &self.server_addr
+                    ),
+                    Box::new(err),
+                )
+            }
+        })?;
 
         debug(
             &target!(),
             &format!("Client message recvd: size={}", message_size),
         );
 
+        let local_addr = server_socket.local_addr().map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                format!(
+                    "Error retrieving server socket address: server_addr={:?}",
+                    &self.server_addr
+                ),
+                Box::new(err),
+            )
+        })?;
+
+        if message_size == 0 {
+            return self
+                .visitor
+                .lock()
+                .unwrap()
+                .on_empty_message_received(&local_addr, &peer_addr);
+        }
+
         self.visitor.lock().unwrap().on_message_received(
-            &self.server_socket.as_ref().unwrap().local_addr().unwrap(),
+            &local_addr,
             &peer_addr,
             buffer[..message_size].to_vec(),
         )
@@ -315,6 +569,381 @@ pub trait ServerVisitor: Send {
         data: Vec<u8>,
     ) -> Result<(), AppError>;
 
+    /// Client zero-length message (datagram) received. These are distinguished from messages with content,
+    /// as an empty message may otherwise be mistaken (downstream) for no data/EOF. Default behavior drops
+    /// the message.
+    fn on_empty_message_received(
+        &mut self,
+        _local_addr: &SocketAddr,
+        _peer_addr: &SocketAddr,
+    ) -> Result<(), AppError> {
+        Ok(())
+    }
+
     /// Returns whether listener shutdown is required
     fn get_shutdown_requested(&self) -> bool;
 }
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use mockall::{mock, predicate};
+    use std::thread;
+
+    // mocks
+    // =====
+
+    mock! {
+        pub ServerVisit {}
+        impl ServerVisitor for ServerVisit {
+            fn on_listening(&mut self) -> Result<(), AppError>;
+            fn on_message_received(&mut self, local_addr: &SocketAddr, peer_addr: &SocketAddr, data: Vec<u8>) -> Result<(), AppError>;
+            fn on_empty_message_received(&mut self, local_addr: &SocketAddr, peer_addr: &SocketAddr) -> Result<(), AppError>;
+            fn get_shutdown_requested(&self) -> bool;
+        }
+    }
+
+    // utils
+    // =====
+
+    fn accept_next_message(server: &mut Server) -> Result<(), AppError> {
+        for _ in 0..50 {
+            match server.accept_message() {
+                Err(AppError::WouldBlock) => thread::sleep(Duration::from_millis(20)),
+                result => return result,
+            }
+        }
+        Err(AppError::WouldBlock)
+    }
+
+    // tests
+    // =====
+
+    #[test]
+    fn server_accept_message_when_empty_and_nonempty_messages() {
+        let mut visitor = MockServerVisit::new();
+        visitor
+            .expect_on_listening()
+            .times(1)
+            .return_once(|| Ok(()));
+        visitor
+            .expect_on_empty_message_received()
+            .times(1)
+            .return_once(|_, _| Ok(()));
+        visitor
+            .expect_on_message_received()
+            .with(
+                predicate::always(),
+                predicate::always(),
+                predicate::eq("msg1".as_bytes().to_vec()),
+            )
+            .times(1)
+            .return_once(|_, _, _| Ok(()));
+
+        let mut server = Server::new(Arc::new(Mutex::new(visitor)), 0).unwrap();
+        server.bind_listener().unwrap();
+        let server_port = server
+            .clone_server_socket()
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        peer.send_to(&[], ("127.0.0.1", server_port)).unwrap();
+        if let Err(err) = accept_next_message(&mut server) {
+            panic!("Unexpected empty message result: err={:?}", &err);
+        }
+
+        peer.send_to("msg1".as_bytes(), ("127.0.0.1", server_port))
+            .unwrap();
+        if let Err(err) = accept_next_message(&mut server) {
+            panic!("Unexpected message result: err={:?}", &err);
+        }
+    }
+
+    #[test]
+    fn server_accept_message_when_server_socket_closed_while_polling() {
+        let mut visitor = MockServerVisit::new();
+        visitor
+            .expect_on_listening()
+            .times(1)
+            .return_once(|| Ok(()));
+        visitor.expect_on_message_received().never();
+        visitor.expect_on_empty_message_received().never();
+
+        let mut server = Server::new(Arc::new(Mutex::new(visitor)), 0).unwrap();
+        server.bind_listener().unwrap();
+        let server_port = server
+            .clone_server_socket()
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.send_to("msg1".as_bytes(), ("127.0.0.1", server_port))
+            .unwrap();
+
+        server.polling = true;
+        server.server_socket = None;
+
+        match server.accept_message() {
+            Err(AppError::WouldBlock) => {}
+            result => panic!("Unexpected result: val={:?}", &result),
+        }
+        assert!(!server.polling);
+    }
+
+    #[test]
+    fn server_accept_message_when_blocking_recv_mode() {
+        let mut visitor = MockServerVisit::new();
+        visitor
+            .expect_on_listening()
+            .times(1)
+            .return_once(|| Ok(()));
+        visitor
+            .expect_on_message_received()
+            .with(
+                predicate::always(),
+                predicate::always(),
+                predicate::eq("msg1".as_bytes().to_vec()),
+            )
+            .times(1)
+            .return_once(|_, _, _| Ok(()));
+
+        let mut server = Server::new_with_recv_mode(
+            Arc::new(Mutex::new(visitor)),
+            0,
+            RecvMode::Blocking(Duration::from_millis(100)),
+        )
+        .unwrap();
+        server.bind_listener().unwrap();
+        let server_port = server
+            .clone_server_socket()
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        match server.accept_message() {
+            Err(AppError::WouldBlock) => {}
+            result => panic!("Unexpected receive timeout result: val={:?}", &result),
+        }
+
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.send_to("msg1".as_bytes(), ("127.0.0.1", server_port))
+            .unwrap();
+
+        if let Err(err) = server.accept_message() {
+            panic!("Unexpected message result: err={:?}", &err);
+        }
+    }
+
+    #[test]
+    fn server_poll_new_messages_when_blocking_recv_mode() {
+        let mut visitor = MockServerVisit::new();
+        visitor
+            .expect_on_listening()
+            .times(1)
+            .return_once(|| Ok(()));
+        visitor
+            .expect_on_message_received()
+            .with(
+                predicate::always(),
+                predicate::always(),
+                predicate::eq("msg1".as_bytes().to_vec()),
+            )
+            .times(1)
+            .return_once(|_, _, _| Ok(()));
+        visitor
+            .expect_get_shutdown_requested()
+            .times(1)
+            .return_const(true);
+
+        let mut server = Server::new_with_recv_mode(
+            Arc::new(Mutex::new(visitor)),
+            0,
+            RecvMode::Blocking(Duration::from_secs(5)),
+        )
+        .unwrap();
+        server.bind_listener().unwrap();
+        let server_port = server
+            .clone_server_socket()
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.send_to("msg1".as_bytes(), ("127.0.0.1", server_port))
+            .unwrap();
+
+        if let Err(err) = server.poll_new_messages() {
+            panic!("Unexpected poll result: err={:?}", &err);
+        }
+
+        assert!(server.closed);
+        assert!(server.server_socket.is_none());
+    }
+
+    #[test]
+    fn server_poll_once_when_messages_pending_then_shutdown_requested() {
+        let shutdown_requested = Arc::new(Mutex::new(false));
+
+        let mut visitor = MockServerVisit::new();
+        visitor
+            .expect_on_listening()
+            .times(1)
+            .return_once(|| Ok(()));
+        visitor
+            .expect_on_message_received()
+            .times(2)
+            .returning(|_, _, _| Ok(()));
+        let shutdown_flag = shutdown_requested.clone();
+        visitor
+            .expect_get_shutdown_requested()
+            .returning(move || *shutdown_flag.lock().unwrap());
+
+        let mut server = Server::new(Arc::new(Mutex::new(visitor)), 0).unwrap();
+        server.bind_listener().unwrap();
+        server.start_polling().unwrap();
+        let server_port = server
+            .clone_server_socket()
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.send_to("msg1".as_bytes(), ("127.0.0.1", server_port))
+            .unwrap();
+        peer.send_to("msg2".as_bytes(), ("127.0.0.1", server_port))
+            .unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        match server.poll_once() {
+            Ok(polling) => assert!(polling),
+            Err(err) => panic!("Unexpected poll result: err={:?}", &err),
+        }
+
+        *shutdown_requested.lock().unwrap() = true;
+
+        match server.poll_once() {
+            Ok(polling) => assert!(!polling),
+            Err(err) => panic!("Unexpected poll result: err={:?}", &err),
+        }
+        assert!(server.closed);
+        assert!(server.server_socket.is_none());
+    }
+
+    #[test]
+    fn server_start_polling_when_blocking_recv_mode() {
+        let mut visitor = MockServerVisit::new();
+        visitor
+            .expect_on_listening()
+            .times(1)
+            .return_once(|| Ok(()));
+
+        let mut server = Server::new_with_recv_mode(
+            Arc::new(Mutex::new(visitor)),
+            0,
+            RecvMode::Blocking(Duration::from_millis(100)),
+        )
+        .unwrap();
+        server.bind_listener().unwrap();
+
+        if let Ok(()) = server.start_polling() {
+            panic!("Unexpected successful start polling result");
+        }
+        assert!(!server.polling);
+    }
+
+    #[test]
+    fn server_bind_peer_socket_when_reusable_server_socket() {
+        let server_socket =
+            Server::bind_reusable_socket(&SocketAddr::from_str("127.0.0.1:0").unwrap()).unwrap();
+        let server_addr = server_socket.local_addr().unwrap();
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let peer_addr = peer.local_addr().unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let peer_socket = Server::bind_peer_socket(&server_addr, &peer_addr).unwrap();
+        peer_socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        assert_eq!(peer_socket.local_addr().unwrap(), server_addr);
+
+        peer_socket.send("reply".as_bytes()).unwrap();
+
+        let mut buffer = [0; 16];
+        let (message_size, reply_addr) = peer.recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..message_size], "reply".as_bytes());
+        assert_eq!(reply_addr, server_addr);
+
+        peer.send_to("request".as_bytes(), server_addr).unwrap();
+
+        let message_size = peer_socket.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..message_size], "request".as_bytes());
+    }
+
+    #[test]
+    fn server_bind_peer_socket_when_non_reusable_server_socket() {
+        let server_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = server_socket.local_addr().unwrap();
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        if let Ok(peer_socket) = Server::bind_peer_socket(&server_addr, &peer.local_addr().unwrap())
+        {
+            panic!("Unexpected successful result: socket={:?}", &peer_socket);
+        }
+    }
+
+    #[test]
+    fn server_send_message_when_full_send() {
+        let server_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let result = Server::send_message(
+            &server_socket,
+            &peer.local_addr().unwrap(),
+            &"message".as_bytes().to_vec(),
+        );
+
+        match result {
+            Ok(sent_size) => assert_eq!(sent_size, 7),
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        }
+
+        let mut buffer = [0; 16];
+        let message_size = peer.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..message_size], "message".as_bytes());
+    }
+
+    #[test]
+    fn server_process_send_result_when_short_send() {
+        let socket_addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+
+        match Server::process_send_result(Ok(5), &socket_addr, 8) {
+            Ok(sent_size) => panic!("Unexpected successful result: size={}", sent_size),
+            Err(err) => assert_eq!(
+                err.to_string(),
+                "Short send on UDP socket, message truncated: dest=127.0.0.1:3000, expected=8, actual=5"
+            ),
+        }
+    }
+
+    #[test]
+    fn server_process_send_result_when_send_error() {
+        let socket_addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+
+        if let Ok(sent_size) = Server::process_send_result(
+            Err(io::Error::from(io::ErrorKind::ConnectionRefused)),
+            &socket_addr,
+            8,
+        ) {
+            panic!("Unexpected successful result: size={}", sent_size);
+        }
+    }
+}
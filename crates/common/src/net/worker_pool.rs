@@ -0,0 +1,206 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::error::AppError;
+use crate::logging::error;
+use crate::target;
+
+const WORKER_POLLING_DELAY_MSECS: u64 = 30;
+
+/// Worker task, invoked once per worker event loop iteration. Returns whether the task should continue
+/// to be polled (a return of false or an error, will remove the task from the worker).
+pub type WorkerTask = Box<dyn FnMut() -> Result<bool, AppError> + Send>;
+
+/// Bounded pool of worker threads, each running an event loop which repeatedly polls its assigned
+/// (non-blocking) tasks. New tasks are assigned to the worker with the fewest active tasks.
+pub struct WorkerPool {
+    task_senders: Vec<Sender<WorkerTask>>,
+    task_counts: Vec<Arc<AtomicUsize>>,
+}
+
+impl WorkerPool {
+    /// WorkerPool constructor. Spawns the given number of worker threads (minimum of 1)
+    pub fn new(worker_threads: usize) -> Self {
+        let mut task_senders = vec![];
+        let mut task_counts = vec![];
+
+        for _ in 0..worker_threads.max(1) {
+            let (task_sender, task_receiver) = mpsc::channel();
+            let task_count = Arc::new(AtomicUsize::new(0));
+            let worker_task_count = task_count.clone();
+
+            thread::spawn(move || Self::run_worker(task_receiver, worker_task_count));
+
+            task_senders.push(task_sender);
+            task_counts.push(task_count);
+        }
+
+        Self {
+            task_senders,
+            task_counts,
+        }
+    }
+
+    /// Submit task to (least busy) worker
+    pub fn submit(&self, task: WorkerTask) -> Result<(), AppError> {
+        let worker_idx = self
+            .task_counts
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, task_count)| task_count.load(Ordering::SeqCst))
+            .map(|(worker_idx, _)| worker_idx)
+            .unwrap();
+
+        self.task_counts[worker_idx].fetch_add(1, Ordering::SeqCst);
+
+        self.task_senders[worker_idx].send(task).map_err(|err| {
+            self.task_counts[worker_idx].fetch_sub(1, Ordering::SeqCst);
+            AppError::General(format!(
+                "Error submitting task to worker: worker={}, err={:?}",
+                worker_idx, &err
+            ))
+        })
+    }
+
+    /// Number of worker threads
+    pub fn get_worker_count(&self) -> usize {
+        self.task_senders.len()
+    }
+
+    /// Number of tasks currently assigned across all workers
+    pub fn get_active_task_count(&self) -> usize {
+        self.task_counts
+            .iter()
+            .map(|task_count| task_count.load(Ordering::SeqCst))
+            .sum()
+    }
+
+    /// Worker event loop (runs until pool is dropped and all assigned tasks have completed)
+    fn run_worker(task_receiver: Receiver<WorkerTask>, task_count: Arc<AtomicUsize>) {
+        let mut tasks: Vec<WorkerTask> = vec![];
+        let mut pool_dropped = false;
+
+        loop {
+            // Block for new task, when idle
+            if tasks.is_empty() {
+                if pool_dropped {
+                    break;
+                }
+                match task_receiver.recv() {
+                    Ok(task) => tasks.push(task),
+                    Err(_) => break,
+                }
+            }
+
+            // Pick up any other newly submitted tasks
+            loop {
+                match task_receiver.try_recv() {
+                    Ok(task) => tasks.push(task),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        pool_dropped = true;
+                        break;
+                    }
+                }
+            }
+
+            // Poll tasks, removing those which are finished
+            let prior_task_len = tasks.len();
+
+            tasks.retain_mut(|task| match task() {
+                Ok(keep_polling) => keep_polling,
+                Err(err) => {
+                    error(&target!(), &format!("Worker task failed: err={:?}", &err));
+                    false
+                }
+            });
+
+            task_count.fetch_sub(prior_task_len - tasks.len(), Ordering::SeqCst);
+
+            // Add delay between polls
+            if !tasks.is_empty() {
+                thread::sleep(Duration::from_millis(WORKER_POLLING_DELAY_MSECS));
+            }
+        }
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    fn wait_for_active_task_count(worker_pool: &WorkerPool, task_count: usize) {
+        let start = Instant::now();
+        while worker_pool.get_active_task_count() != task_count {
+            if start.elapsed() > Duration::from_secs(5) {
+                panic!(
+                    "Timed out waiting for task count: expected={}, actual={}",
+                    task_count,
+                    worker_pool.get_active_task_count()
+                );
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn workerpool_new_when_zero_threads() {
+        let worker_pool = WorkerPool::new(0);
+
+        assert_eq!(worker_pool.get_worker_count(), 1);
+        assert_eq!(worker_pool.get_active_task_count(), 0);
+    }
+
+    #[test]
+    fn workerpool_submit_when_more_tasks_than_workers() {
+        let worker_pool = WorkerPool::new(2);
+        let poll_counts = Arc::new(Mutex::new(vec![0usize; 5]));
+        let stop_polling = Arc::new(Mutex::new(false));
+
+        for task_idx in 0..5 {
+            let poll_counts = poll_counts.clone();
+            let stop_polling = stop_polling.clone();
+            worker_pool
+                .submit(Box::new(move || {
+                    poll_counts.lock().unwrap()[task_idx] += 1;
+                    Ok(!*stop_polling.lock().unwrap())
+                }))
+                .unwrap();
+        }
+
+        assert_eq!(worker_pool.get_active_task_count(), 5);
+
+        let start = Instant::now();
+        while poll_counts.lock().unwrap().iter().any(|count| *count < 2) {
+            if start.elapsed() > Duration::from_secs(5) {
+                panic!("Timed out waiting for all tasks to be polled");
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        *stop_polling.lock().unwrap() = true;
+
+        wait_for_active_task_count(&worker_pool, 0);
+    }
+
+    #[test]
+    fn workerpool_submit_when_task_completes_or_fails() {
+        let worker_pool = WorkerPool::new(1);
+
+        worker_pool.submit(Box::new(|| Ok(false))).unwrap();
+        worker_pool
+            .submit(Box::new(|| {
+                Err(AppError::General("task failed".to_string()))
+            }))
+            .unwrap();
+
+        wait_for_active_task_count(&worker_pool, 0);
+    }
+}
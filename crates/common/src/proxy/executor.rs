@@ -7,17 +7,16 @@ use anyhow::Result;
 
 use crate::error::AppError;
 use crate::logging::{error, warn};
+use crate::metrics::{MetricsSink, NoOpMetricsSink};
 use crate::net::stream_utils::StreamReaderWriter;
 use crate::proxy::event::ProxyEvent;
 use crate::proxy::proxy_base::ProxyStream;
 use crate::proxy::proxy_channel_and_tcp::ChannelAndTcpStreamProxy;
-use crate::proxy::proxy_tcp_and_tcp::TcpAndTcpStreamProxy;
+use crate::proxy::proxy_key::ProxyKey;
+use crate::proxy::proxy_tcp_and_tcp::{RelayMode, RelayRetry, TcpAndTcpStreamProxy};
 use crate::proxy::proxy_tcp_and_udp::TcpAndUdpStreamProxy;
 use crate::target;
 
-/// Used to (uniquely) represent an active proxy session
-pub type ProxyKey = String;
-
 /// Used to represent the context for the (Socket Channel <-> TCP) streams proxy
 pub type ChannelAndTcpProxyContext = (
     SocketAddr,                              // Channel's respective socket address
@@ -35,16 +34,42 @@ pub type TcpAndTcpProxyContext = (
     Arc<Mutex<Box<dyn StreamReaderWriter>>>, // 1st stream reader/writer
     Arc<Mutex<Box<dyn StreamReaderWriter>>>, // 2nd stream reader/writer
     sync::mpsc::Sender<ProxyEvent>,          // channel sender to send back proxy events
+    TcpProxyOptions,                         // proxy options
 );
 
+/// Options for the (TCP <-> TCP) streams proxy
+#[derive(Clone, Default)]
+pub struct TcpProxyOptions {
+    /// 2nd stream relay retry policy (if enabled)
+    pub relay_retry: Option<RelayRetry>,
+    /// Relay strategy between the streams
+    pub relay_mode: RelayMode,
+    /// Whether proxy lifecycle is logged
+    pub log_lifecycle: bool,
+    /// Max bytes relayed from 2nd stream to 1st stream (if limited)
+    pub max_stream2_bytes: Option<u64>,
+    /// Whether 1st stream is closed abortively (RST) when stopping on an error
+    pub abortive_close_on_error: bool,
+}
+
 /// Used to represent the context for the (TCP <-> UDP) streams proxy
 pub type TcpAndUdpProxyContext = (
     std::net::TcpStream,                     // TCP stream
     std::net::UdpSocket,                     // UDP socket
     Arc<Mutex<Box<dyn StreamReaderWriter>>>, // tcp stream reader/writer
     sync::mpsc::Sender<ProxyEvent>,          // channel sender to send back proxy events
+    UdpProxyOptions,                         // proxy options
 );
 
+/// Options for the (TCP <-> UDP) streams proxy
+#[derive(Clone, Default)]
+pub struct UdpProxyOptions {
+    /// UDP target address (if socket isn't connected)
+    pub udp_target_addr: Option<SocketAddr>,
+    /// Whether proxy lifecycle is logged
+    pub log_lifecycle: bool,
+}
+
 /// Proxy executor event message
 pub enum ProxyExecutorEvent {
     OpenChannelAndTcpProxy(ProxyKey, ChannelAndTcpProxyContext),
@@ -58,6 +83,7 @@ pub struct ProxyExecutor {
     proxy_tasks_sender: std::sync::mpsc::Sender<ProxyExecutorEvent>,
     proxy_tasks_receiver: std::sync::mpsc::Receiver<ProxyExecutorEvent>,
     proxy_streams: HashMap<ProxyKey, Arc<Mutex<dyn ProxyStream>>>,
+    metrics_sink: Arc<dyn MetricsSink>,
 }
 
 impl ProxyExecutor {
@@ -69,9 +95,15 @@ impl ProxyExecutor {
             proxy_tasks_sender,
             proxy_tasks_receiver,
             proxy_streams: HashMap::new(),
+            metrics_sink: Arc::new(NoOpMetricsSink),
         }
     }
 
+    /// Set sink used by new proxies to emit metrics
+    pub fn set_metrics_sink(&mut self, metrics_sink: Arc<dyn MetricsSink>) {
+        self.metrics_sink = metrics_sink;
+    }
+
     /// Get a copy of the tasks sender
     pub fn clone_proxy_tasks_sender(&self) -> std::sync::mpsc::Sender<ProxyExecutorEvent> {
         self.proxy_tasks_sender.clone()
@@ -103,7 +135,8 @@ impl ProxyExecutor {
                         proxy_context.4,
                         proxy_context.5,
                     ) {
-                        Ok(proxy_stream) => {
+                        Ok(mut proxy_stream) => {
+                            proxy_stream.set_metrics_sink(self.metrics_sink.clone());
                             let proxy_stream = Arc::new(Mutex::new(proxy_stream));
 
                             if let Err(err) = proxy_stream.lock().unwrap().connect() {
@@ -136,6 +169,7 @@ impl ProxyExecutor {
                 // Open new TCP stream <-> TCP stream proxy
                 ProxyExecutorEvent::OpenTcpAndTcpProxy(proxy_key, proxy_context) => {
                     let proxy_channel_sender = proxy_context.4.clone();
+                    let proxy_options = proxy_context.5;
 
                     match TcpAndTcpStreamProxy::new(
                         &proxy_key,
@@ -144,8 +178,15 @@ impl ProxyExecutor {
                         proxy_context.2,
                         proxy_context.3,
                         proxy_context.4,
+                        proxy_options.relay_retry,
                     ) {
-                        Ok(proxy_stream) => {
+                        Ok(mut proxy_stream) => {
+                            proxy_stream.set_metrics_sink(self.metrics_sink.clone());
+                            proxy_stream.set_relay_mode(proxy_options.relay_mode);
+                            proxy_stream.set_log_lifecycle(proxy_options.log_lifecycle);
+                            proxy_stream.set_max_stream2_bytes(proxy_options.max_stream2_bytes);
+                            proxy_stream
+                                .set_abortive_close_on_error(proxy_options.abortive_close_on_error);
                             let proxy_stream = Arc::new(Mutex::new(proxy_stream));
 
                             if let Err(err) = proxy_stream.lock().unwrap().connect() {
@@ -186,7 +227,10 @@ impl ProxyExecutor {
                         proxy_context.2,
                         proxy_context.3,
                     ) {
-                        Ok(proxy_stream) => {
+                        Ok(mut proxy_stream) => {
+                            proxy_stream.set_metrics_sink(self.metrics_sink.clone());
+                            proxy_stream.set_udp_target_addr(proxy_context.4.udp_target_addr);
+                            proxy_stream.set_log_lifecycle(proxy_context.4.log_lifecycle);
                             let proxy_stream = Arc::new(Mutex::new(proxy_stream));
 
                             if let Err(err) = proxy_stream.lock().unwrap().connect() {
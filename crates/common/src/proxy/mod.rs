@@ -2,5 +2,7 @@ pub mod event;
 pub mod executor;
 pub mod proxy_base;
 pub mod proxy_channel_and_tcp;
+pub mod proxy_key;
+pub mod proxy_key_store;
 pub mod proxy_tcp_and_tcp;
 pub mod proxy_tcp_and_udp;
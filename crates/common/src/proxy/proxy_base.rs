@@ -1,6 +1,7 @@
 use crate::error::AppError;
 
 /// Types of proxies
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ProxyType {
     ChannelAndTcp,
     TcpAndTcp,
@@ -16,6 +17,16 @@ impl ProxyType {
             ProxyType::TcpAndUdp => "T&U".to_string(),
         }
     }
+
+    /// Proxy type for given short unique key (if valid)
+    pub fn from_key_value(key_value: &str) -> Option<Self> {
+        match key_value {
+            "C&T" => Some(ProxyType::ChannelAndTcp),
+            "T&T" => Some(ProxyType::TcpAndTcp),
+            "T&U" => Some(ProxyType::TcpAndUdp),
+            _ => None,
+        }
+    }
 }
 
 /// Trait implemented by all proxy stream types
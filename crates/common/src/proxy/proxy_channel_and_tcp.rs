@@ -6,11 +6,13 @@ use std::{io, sync, thread};
 use anyhow::Result;
 
 use crate::error::AppError;
-use crate::logging::{error, info, warn};
+use crate::logging::{error, info, log_throttled, warn, LogLevel};
+use crate::metrics::{MetricsSink, NoOpMetricsSink};
 use crate::net::stream_utils;
 use crate::net::stream_utils::StreamReaderWriter;
 use crate::proxy::event::ProxyEvent;
 use crate::proxy::proxy_base::ProxyStream;
+use crate::proxy::proxy_key::ProxyKey;
 use crate::target;
 
 const TCP_STREAM_TOKEN: mio::Token = mio::Token(0);
@@ -18,7 +20,7 @@ const POLLING_DURATION_MSECS: u64 = 1000;
 
 /// Proxy based on a sync channel and a TCP stream
 pub struct ChannelAndTcpStreamProxy {
-    proxy_key: String,
+    proxy_key: ProxyKey,
     socket_channel_addr: SocketAddr,
     socket_channel_receiver: Arc<Mutex<sync::mpsc::Receiver<ProxyEvent>>>,
     server_socket_channel_sender: sync::mpsc::Sender<ProxyEvent>,
@@ -27,12 +29,13 @@ pub struct ChannelAndTcpStreamProxy {
     proxy_channel_sender: sync::mpsc::Sender<ProxyEvent>,
     closing: Arc<Mutex<bool>>,
     closed: Arc<Mutex<bool>>,
+    metrics_sink: Arc<dyn MetricsSink>,
 }
 
 impl ChannelAndTcpStreamProxy {
     /// ChannelAndTcpStreamProxy constructor
     pub fn new(
-        proxy_key: &str,
+        proxy_key: &ProxyKey,
         socket_channel_addr: SocketAddr,
         socket_channel_receiver: sync::mpsc::Receiver<ProxyEvent>,
         server_socket_channel_sender: sync::mpsc::Sender<ProxyEvent>,
@@ -55,7 +58,7 @@ impl ChannelAndTcpStreamProxy {
 
         // Instantiate TcpStreamProxy
         Ok(ChannelAndTcpStreamProxy {
-            proxy_key: proxy_key.to_string(),
+            proxy_key: proxy_key.clone(),
             socket_channel_addr,
             socket_channel_receiver: Arc::new(Mutex::new(socket_channel_receiver)),
             server_socket_channel_sender,
@@ -64,9 +67,15 @@ impl ChannelAndTcpStreamProxy {
             proxy_channel_sender,
             closing: Arc::new(Mutex::new(false)),
             closed: Arc::new(Mutex::new(false)),
+            metrics_sink: Arc::new(NoOpMetricsSink),
         })
     }
 
+    /// Set sink used to emit proxy metrics (bytes transferred)
+    pub fn set_metrics_sink(&mut self, metrics_sink: Arc<dyn MetricsSink>) {
+        self.metrics_sink = metrics_sink;
+    }
+
     /// Connect client and server IO streams (spawn tasks to bidirectionally copy data)
     pub fn connect(&mut self) -> Result<(), AppError> {
         info(
@@ -86,6 +95,7 @@ impl ChannelAndTcpStreamProxy {
             )?);
             let mut tcp_stream_reader_writer = self.tcp_stream_reader_writer.clone();
             let proxy_key = self.proxy_key.clone();
+            let metrics_sink = self.metrics_sink.clone();
             let proxy_channel_sender = self.proxy_channel_sender.clone();
 
             thread::spawn(move || {
@@ -113,7 +123,7 @@ impl ChannelAndTcpStreamProxy {
                             &mut tcp_stream_reader_writer,
                             data.as_slice(),
                         ) {
-                            Ok(()) => {}
+                            Ok(()) => metrics_sink.incr_proxy_bytes(&proxy_key, data.len() as u64),
                             Err(err) => match err {
                                 AppError::WouldBlock => continue,
                                 AppError::StreamEOF => break 'EVENTS,
@@ -148,6 +158,7 @@ impl ChannelAndTcpStreamProxy {
             )?);
             let mut tcp_stream_reader_writer = self.tcp_stream_reader_writer.clone();
             let proxy_key = self.proxy_key.clone();
+            let metrics_sink = self.metrics_sink.clone();
             let proxy_channel_sender = self.proxy_channel_sender.clone();
 
             thread::spawn(move || {
@@ -208,6 +219,7 @@ impl ChannelAndTcpStreamProxy {
                             match stream_utils::read_tcp_stream(&mut tcp_stream_reader_writer) {
                                 Ok(data) => {
                                     if !data.is_empty() {
+                                        let data_len = data.len() as u64;
                                         match server_socket_channel_sender.send(
                                             ProxyEvent::Message(
                                                 proxy_key.clone(),
@@ -215,7 +227,9 @@ impl ChannelAndTcpStreamProxy {
                                                 data,
                                             ),
                                         ) {
-                                            Ok(()) => {}
+                                            Ok(()) => {
+                                                metrics_sink.incr_proxy_bytes(&proxy_key, data_len)
+                                            }
                                             Err(err) => {
                                                 proxy_error = Some(AppError::GenWithMsgAndErr(
                                                     format!("Error sending socket message to channel: proxy_stream={}", &proxy_key),
@@ -276,7 +290,7 @@ impl ChannelAndTcpStreamProxy {
             if let Err(err) = join_result.unwrap() {
                 match err {
                     AppError::StreamEOF => {}
-                    _ => error(&target!(), &format!("{:?}", err)),
+                    _ => log_throttled(&target!(), LogLevel::ERROR, &format!("{:?}", err)),
                 }
             }
 
@@ -293,7 +307,7 @@ impl ChannelAndTcpStreamProxy {
             if let Err(err) = join_result.unwrap() {
                 match err {
                     AppError::StreamEOF => {}
-                    _ => error(&target!(), &format!("{:?}", err)),
+                    _ => log_throttled(&target!(), LogLevel::ERROR, &format!("{:?}", err)),
                 }
             }
 
@@ -310,7 +324,7 @@ impl ChannelAndTcpStreamProxy {
 
     /// Shutdown proxy resources (called by proxy thread on termination)
     fn perform_shutdown(
-        proxy_key: &str,
+        proxy_key: &ProxyKey,
         tcp_stream: &mio::net::TcpStream,
         proxy_channel_sender: &sync::mpsc::Sender<ProxyEvent>,
         closed_state: &Arc<Mutex<bool>>,
@@ -328,7 +342,7 @@ impl ChannelAndTcpStreamProxy {
             ),
         }
 
-        if let Err(err) = proxy_channel_sender.send(ProxyEvent::Closed(proxy_key.to_string())) {
+        if let Err(err) = proxy_channel_sender.send(ProxyEvent::Closed(proxy_key.clone())) {
             error(
                 &target!(),
                 &format!(
@@ -0,0 +1,230 @@
+use std::fmt::{self, Display, Formatter};
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+use crate::error::AppError;
+use crate::proxy::proxy_base::ProxyType;
+
+const ADDR_NOT_AVAILABLE: &str = "NA";
+
+/// Unique key for an active proxy connection. Used by both the client and gateway to track proxies (across
+/// the proxy executor, proxy events and service managers). The textual form is:
+/// `<PROXY_TYPE>:<SERVICE_ID>:<CLIENT_ADDR>,<SERVER_ADDR>` (unavailable addresses are given as "NA").
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ProxyKey {
+    proxy_type: ProxyType,
+    service_id: u64,
+    client_addr: Option<SocketAddr>,
+    server_addr: Option<SocketAddr>,
+}
+
+impl ProxyKey {
+    /// ProxyKey constructor
+    pub fn new(
+        proxy_type: ProxyType,
+        service_id: u64,
+        client_addr: Option<SocketAddr>,
+        server_addr: Option<SocketAddr>,
+    ) -> Self {
+        Self {
+            proxy_type,
+            service_id,
+            client_addr,
+            server_addr,
+        }
+    }
+
+    /// Proxy type accessor
+    pub fn get_proxy_type(&self) -> ProxyType {
+        self.proxy_type
+    }
+
+    /// Service ID accessor
+    pub fn get_service_id(&self) -> u64 {
+        self.service_id
+    }
+
+    /// Client (1st proxy stream) address accessor
+    pub fn get_client_addr(&self) -> Option<SocketAddr> {
+        self.client_addr
+    }
+
+    /// Server (2nd proxy stream) address accessor
+    pub fn get_server_addr(&self) -> Option<SocketAddr> {
+        self.server_addr
+    }
+
+    /// Parse textual socket address component
+    fn parse_addr(key: &str, addr: &str) -> Result<Option<SocketAddr>, AppError> {
+        if addr == ADDR_NOT_AVAILABLE {
+            return Ok(None);
+        }
+        addr.parse::<SocketAddr>().map(Some).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                format!("Invalid proxy key address: key={}, addr={}", key, addr),
+                Box::new(err),
+            )
+        })
+    }
+
+    /// Format socket address component
+    fn format_addr(addr: &Option<SocketAddr>) -> String {
+        match addr {
+            Some(addr) => addr.to_string(),
+            None => ADDR_NOT_AVAILABLE.to_string(),
+        }
+    }
+}
+
+impl Display for ProxyKey {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{},{}",
+            self.proxy_type.key_value(),
+            self.service_id,
+            Self::format_addr(&self.client_addr),
+            Self::format_addr(&self.server_addr)
+        )
+    }
+}
+
+impl FromStr for ProxyKey {
+    type Err = AppError;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        let invalid_key_err = || AppError::General(format!("Invalid proxy key: key={}", key));
+
+        let (proxy_type, remaining) = key.split_once(':').ok_or_else(invalid_key_err)?;
+        let (service_id, addrs) = remaining.split_once(':').ok_or_else(invalid_key_err)?;
+        let (client_addr, server_addr) = addrs.split_once(',').ok_or_else(invalid_key_err)?;
+
+        Ok(Self {
+            proxy_type: ProxyType::from_key_value(proxy_type).ok_or_else(invalid_key_err)?,
+            service_id: service_id.parse().map_err(|_| invalid_key_err())?,
+            client_addr: Self::parse_addr(key, client_addr)?,
+            server_addr: Self::parse_addr(key, server_addr)?,
+        })
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn proxykey_display_when_all_addrs_available() {
+        let proxy_key = ProxyKey::new(
+            ProxyType::TcpAndTcp,
+            200,
+            Some("127.0.0.1:4000".parse().unwrap()),
+            Some("[::1]:8200".parse().unwrap()),
+        );
+
+        assert_eq!(proxy_key.to_string(), "T&T:200:127.0.0.1:4000,[::1]:8200");
+    }
+
+    #[test]
+    fn proxykey_display_when_addrs_not_available() {
+        let proxy_key = ProxyKey::new(ProxyType::TcpAndUdp, 201, None, None);
+
+        assert_eq!(proxy_key.to_string(), "T&U:201:NA,NA");
+    }
+
+    #[test]
+    fn proxykey_parse_when_round_trip() {
+        let proxy_keys = vec![
+            ProxyKey::new(
+                ProxyType::ChannelAndTcp,
+                200,
+                Some("127.0.0.1:4000".parse().unwrap()),
+                Some("[::1]:8200".parse().unwrap()),
+            ),
+            ProxyKey::new(
+                ProxyType::TcpAndUdp,
+                201,
+                None,
+                Some("10.0.0.1:53".parse().unwrap()),
+            ),
+        ];
+
+        for proxy_key in proxy_keys {
+            let parsed_key: ProxyKey = proxy_key.to_string().parse().unwrap();
+
+            assert_eq!(parsed_key, proxy_key);
+            assert_eq!(parsed_key.to_string(), proxy_key.to_string());
+        }
+    }
+
+    #[test]
+    fn proxykey_parse_when_invalid_keys() {
+        for key in [
+            "",
+            "T&T",
+            "X&Y:200:NA,NA",
+            "T&T:svc:NA,NA",
+            "T&T:200:NA",
+            "T&T:200:localhost,NA",
+        ] {
+            if let Ok(proxy_key) = key.parse::<ProxyKey>() {
+                panic!(
+                    "Unexpected successful result: key={}, val={:?}",
+                    key, &proxy_key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn proxykey_eq_when_same_and_different_components() {
+        let client_addr: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let server_addr: SocketAddr = "127.0.0.1:8200".parse().unwrap();
+        let proxy_key = ProxyKey::new(
+            ProxyType::TcpAndTcp,
+            200,
+            Some(client_addr),
+            Some(server_addr),
+        );
+
+        assert_eq!(
+            proxy_key,
+            ProxyKey::new(
+                ProxyType::TcpAndTcp,
+                200,
+                Some(client_addr),
+                Some(server_addr)
+            )
+        );
+        assert_ne!(
+            proxy_key,
+            ProxyKey::new(
+                ProxyType::TcpAndTcp,
+                201,
+                Some(client_addr),
+                Some(server_addr)
+            )
+        );
+        assert_ne!(
+            proxy_key,
+            ProxyKey::new(
+                ProxyType::TcpAndUdp,
+                200,
+                Some(client_addr),
+                Some(server_addr)
+            )
+        );
+        assert_ne!(
+            proxy_key,
+            ProxyKey::new(ProxyType::TcpAndTcp, 200, Some(client_addr), None)
+        );
+
+        let services_by_proxy_key = HashMap::from([(proxy_key.clone(), 200)]);
+        assert_eq!(
+            services_by_proxy_key.get(&proxy_key.to_string().parse().unwrap()),
+            Some(&200)
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::proxy::proxy_key::ProxyKey;
+
+/// Store for the proxy key to service ID mapping, shared by a service manager and its service proxies. The in-memory
+/// store is used by default, whereas a shared store (for instance, backed by an external cache) would allow a proxy
+/// key to be looked up by other processes of a multi-process/HA deployment. Implementations handle (and report) their
+/// own backend failures.
+pub trait ProxyKeyStore: Send + Sync {
+    /// Map proxy key to service.
+    ///
+    /// Returns the service ID previously mapped to the proxy key (if any).
+    fn put(&self, proxy_key: &ProxyKey, service_id: u64) -> Option<u64>;
+
+    /// Gets the service ID mapped to the proxy key (if any).
+    fn get(&self, proxy_key: &ProxyKey) -> Option<u64>;
+
+    /// Removes a proxy key mapping.
+    ///
+    /// Returns the service ID mapped to the removed proxy key (if any).
+    fn remove(&self, proxy_key: &ProxyKey) -> Option<u64>;
+
+    /// Returns a copy of all (proxy key, service ID) mappings.
+    fn get_all(&self) -> Vec<(ProxyKey, u64)>;
+
+    /// Returns the number of mapped proxy keys.
+    fn len(&self) -> usize;
+
+    /// Returns whether there are no mapped proxy keys.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// In-memory (single process) proxy key store
+pub struct InMemProxyKeyStore {
+    services_by_proxy_key: Mutex<HashMap<ProxyKey, u64>>,
+}
+
+impl InMemProxyKeyStore {
+    /// Creates a new in-memory proxy key store.
+    pub fn new() -> Self {
+        Self {
+            services_by_proxy_key: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for InMemProxyKeyStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProxyKeyStore for InMemProxyKeyStore {
+    fn put(&self, proxy_key: &ProxyKey, service_id: u64) -> Option<u64> {
+        self.services_by_proxy_key
+            .lock()
+            .unwrap()
+            .insert(proxy_key.clone(), service_id)
+    }
+
+    fn get(&self, proxy_key: &ProxyKey) -> Option<u64> {
+        self.services_by_proxy_key
+            .lock()
+            .unwrap()
+            .get(proxy_key)
+            .cloned()
+    }
+
+    fn remove(&self, proxy_key: &ProxyKey) -> Option<u64> {
+        self.services_by_proxy_key.lock().unwrap().remove(proxy_key)
+    }
+
+    fn get_all(&self) -> Vec<(ProxyKey, u64)> {
+        self.services_by_proxy_key
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(proxy_key, service_id)| (proxy_key.clone(), *service_id))
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.services_by_proxy_key.lock().unwrap().len()
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+    use crate::proxy::proxy_base::ProxyType;
+
+    fn create_proxy_key(client_port: u16) -> ProxyKey {
+        ProxyKey::new(
+            ProxyType::TcpAndTcp,
+            200,
+            Some(format!("10.0.0.1:{}", client_port).parse().unwrap()),
+            Some("127.0.0.1:8200".parse().unwrap()),
+        )
+    }
+
+    #[test]
+    fn inmemproxykeystore_put_and_get() {
+        let proxy_key_store = InMemProxyKeyStore::new();
+
+        assert!(proxy_key_store.is_empty());
+        assert_eq!(proxy_key_store.put(&create_proxy_key(5000), 200), None);
+        assert_eq!(proxy_key_store.put(&create_proxy_key(5001), 201), None);
+        assert_eq!(proxy_key_store.put(&create_proxy_key(5000), 202), Some(200));
+
+        assert_eq!(proxy_key_store.get(&create_proxy_key(5000)), Some(202));
+        assert_eq!(proxy_key_store.get(&create_proxy_key(5001)), Some(201));
+        assert_eq!(proxy_key_store.get(&create_proxy_key(5002)), None);
+        assert_eq!(proxy_key_store.len(), 2);
+        assert!(!proxy_key_store.is_empty());
+    }
+
+    #[test]
+    fn inmemproxykeystore_remove() {
+        let proxy_key_store = InMemProxyKeyStore::new();
+        proxy_key_store.put(&create_proxy_key(5000), 200);
+        proxy_key_store.put(&create_proxy_key(5001), 201);
+
+        assert_eq!(proxy_key_store.remove(&create_proxy_key(5000)), Some(200));
+        assert_eq!(proxy_key_store.remove(&create_proxy_key(5000)), None);
+        assert_eq!(proxy_key_store.remove(&create_proxy_key(5002)), None);
+
+        assert_eq!(proxy_key_store.get(&create_proxy_key(5000)), None);
+        assert_eq!(proxy_key_store.len(), 1);
+    }
+
+    #[test]
+    fn inmemproxykeystore_get_all() {
+        let proxy_key_store = InMemProxyKeyStore::new();
+        proxy_key_store.put(&create_proxy_key(5000), 200);
+        proxy_key_store.put(&create_proxy_key(5001), 201);
+
+        let mut mappings = proxy_key_store.get_all();
+        mappings.sort_by_key(|(_, service_id)| *service_id);
+
+        assert_eq!(
+            mappings,
+            vec![(create_proxy_key(5000), 200), (create_proxy_key(5001), 201)]
+        );
+    }
+}
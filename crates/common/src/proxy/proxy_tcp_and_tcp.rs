@@ -1,3 +1,4 @@
+use std::io::{Read, Write};
 use std::net::Shutdown;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
@@ -5,39 +6,92 @@ use std::{io, sync, thread};
 
 use anyhow::Result;
 
+use crate::backoff::BackoffStrategy;
 use crate::error::AppError;
-use crate::logging::{error, info, warn};
+use crate::logging::{error, info, log_throttled, warn, LogLevel};
+use crate::metrics::{MetricsSink, NoOpMetricsSink};
 use crate::net::stream_utils;
 use crate::net::stream_utils::StreamReaderWriter;
 use crate::proxy::event::ProxyEvent;
 use crate::proxy::proxy_base::ProxyStream;
+use crate::proxy::proxy_key::ProxyKey;
 use crate::target;
 
 const STREAM1_TOKEN: mio::Token = mio::Token(0);
 const STREAM2_TOKEN: mio::Token = mio::Token(1);
 const POLLING_DURATION_MSECS: u64 = 1000;
+const FAST_RELAY_BUFFER_SIZE: usize = 64 * 1024;
+const FAST_RELAY_MAX_READS_PER_PASS: usize = 16;
+const FAST_RELAY_WRITE_RETRY_MSECS: u64 = 1;
 
-/// Proxy based on 2 connected TCP streams
+/// Factory to (re)establish the stream 2 (upstream) connection
+pub type UpstreamConnector = Arc<dyn Fn() -> Result<std::net::TcpStream, AppError> + Send + Sync>;
+
+/// Upstream relay retry policy. On a stream 2 (upstream) IO error, the upstream connection will be
+/// re-established (up to the max attempts), while preserving the stream 1 (client-facing) connection.
+#[derive(Clone)]
+pub struct RelayRetry {
+    max_attempts: u16,
+    backoff: Arc<dyn BackoffStrategy>,
+    upstream_connector: UpstreamConnector,
+}
+
+impl RelayRetry {
+    /// RelayRetry constructor
+    pub fn new(
+        max_attempts: u16,
+        backoff: Arc<dyn BackoffStrategy>,
+        upstream_connector: UpstreamConnector,
+    ) -> Self {
+        Self {
+            max_attempts,
+            backoff,
+            upstream_connector,
+        }
+    }
+}
+
+/// Strategy used to relay data between the proxy streams
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum RelayMode {
+    /// Each stream's pending data is read (into a new buffer) and written, as IO events are signaled
+    #[default]
+    Standard,
+    /// Tight read-write loop through a reused buffer, only waiting on IO events when both streams are idle. Intended
+    /// for high-throughput services (no per-chunk handling). Not used when a relay retry policy or a stream 2 byte
+    /// limit is set.
+    Fast,
+}
+
+/// Proxy based on 2 connected TCP streams. The streams (and their reader/writers) are handed over to the proxy
+/// thread on connect, so they are closed when the proxy is stopped.
 pub struct TcpAndTcpStreamProxy {
-    proxy_key: String,
-    tcp_stream1: std::net::TcpStream,
-    tcp_stream2: std::net::TcpStream,
-    stream1_reader_writer: Arc<Mutex<Box<dyn StreamReaderWriter>>>,
-    stream2_reader_writer: Arc<Mutex<Box<dyn StreamReaderWriter>>>,
+    proxy_key: ProxyKey,
+    tcp_stream1: Option<std::net::TcpStream>,
+    tcp_stream2: Option<std::net::TcpStream>,
+    stream1_reader_writer: Option<Arc<Mutex<Box<dyn StreamReaderWriter>>>>,
+    stream2_reader_writer: Option<Arc<Mutex<Box<dyn StreamReaderWriter>>>>,
     proxy_channel_sender: sync::mpsc::Sender<ProxyEvent>,
+    relay_retry: Option<RelayRetry>,
+    relay_mode: RelayMode,
+    log_lifecycle: bool,
+    max_stream2_bytes: Option<u64>,
+    abortive_close_on_error: bool,
     closing: Arc<Mutex<bool>>,
     closed: Arc<Mutex<bool>>,
+    metrics_sink: Arc<dyn MetricsSink>,
 }
 
 impl TcpAndTcpStreamProxy {
     /// TcpAndTcpStreamProxy constructor
     pub fn new(
-        proxy_key: &str,
+        proxy_key: &ProxyKey,
         tcp_stream1: std::net::TcpStream,
         tcp_stream2: std::net::TcpStream,
         stream1_reader_writer: Arc<Mutex<Box<dyn StreamReaderWriter>>>,
         stream2_reader_writer: Arc<Mutex<Box<dyn StreamReaderWriter>>>,
         proxy_channel_sender: sync::mpsc::Sender<ProxyEvent>,
+        relay_retry: Option<RelayRetry>,
     ) -> Result<Self, AppError> {
         // Convert streams to non-blocking
         let tcp_stream1 = stream_utils::clone_std_tcp_stream(&tcp_stream1)?;
@@ -64,35 +118,92 @@ impl TcpAndTcpStreamProxy {
 
         // Instantiate TcpStreamProxy
         Ok(TcpAndTcpStreamProxy {
-            proxy_key: proxy_key.to_string(),
-            tcp_stream1,
-            tcp_stream2,
-            stream1_reader_writer,
-            stream2_reader_writer,
+            proxy_key: proxy_key.clone(),
+            tcp_stream1: Some(tcp_stream1),
+            tcp_stream2: Some(tcp_stream2),
+            stream1_reader_writer: Some(stream1_reader_writer),
+            stream2_reader_writer: Some(stream2_reader_writer),
             proxy_channel_sender,
+            relay_retry,
+            relay_mode: RelayMode::Standard,
+            log_lifecycle: true,
+            max_stream2_bytes: None,
+            abortive_close_on_error: false,
             closing: Arc::new(Mutex::new(false)),
             closed: Arc::new(Mutex::new(false)),
+            metrics_sink: Arc::new(NoOpMetricsSink),
         })
     }
 
+    /// Set sink used to emit proxy metrics (bytes transferred)
+    pub fn set_metrics_sink(&mut self, metrics_sink: Arc<dyn MetricsSink>) {
+        self.metrics_sink = metrics_sink;
+    }
+
+    /// Set strategy used to relay data between the streams
+    pub fn set_relay_mode(&mut self, relay_mode: RelayMode) {
+        self.relay_mode = relay_mode;
+    }
+
+    /// Set whether proxy lifecycle (start/stop) is logged (see `logging::LogSampler`)
+    pub fn set_log_lifecycle(&mut self, log_lifecycle: bool) {
+        self.log_lifecycle = log_lifecycle;
+    }
+
+    /// Set maximum number of bytes relayed from stream 2 to stream 1. When exceeded, the proxy is closed (absent is
+    /// unlimited)
+    pub fn set_max_stream2_bytes(&mut self, max_stream2_bytes: Option<u64>) {
+        self.max_stream2_bytes = max_stream2_bytes;
+    }
+
+    /// Set whether stream 1 is closed abortively (RST, via a zero linger timeout) when the proxy stops due to an
+    /// IO/relay error. Otherwise (and on a clean stream EOF), stream 1 is shut down gracefully (FIN).
+    pub fn set_abortive_close_on_error(&mut self, abortive_close_on_error: bool) {
+        self.abortive_close_on_error = abortive_close_on_error;
+    }
+
     /// Connect tcp IO streams (spawn task to bidirectionally copy data)
     pub fn connect(&mut self) -> Result<(), AppError> {
-        info(
-            &target!(),
-            &format!("Starting proxy: proxy_stream={}", &self.proxy_key),
-        );
+        let (
+            Some(tcp_stream1),
+            Some(tcp_stream2),
+            Some(mut stream1_reader_writer),
+            Some(mut stream2_reader_writer),
+        ) = (
+            self.tcp_stream1.take(),
+            self.tcp_stream2.take(),
+            self.stream1_reader_writer.take(),
+            self.stream2_reader_writer.take(),
+        )
+        else {
+            return Err(AppError::General(format!(
+                "Proxy already connected: proxy_stream={}",
+                &self.proxy_key
+            )));
+        };
+
+        if self.log_lifecycle {
+            info(
+                &target!(),
+                &format!("Starting proxy: proxy_stream={}", &self.proxy_key),
+            );
+        }
 
         *self.closing.lock().unwrap() = false;
 
         // Spawn bidirectional stream IO copy task
         let closing = self.closing.clone();
         let closed = self.closed.clone();
-        let tcp_stream1 = stream_utils::clone_std_tcp_stream(&self.tcp_stream1)?;
-        let tcp_stream2 = stream_utils::clone_std_tcp_stream(&self.tcp_stream2)?;
-        let mut stream1_reader_writer = self.stream1_reader_writer.clone();
-        let mut stream2_reader_writer = self.stream2_reader_writer.clone();
         let proxy_key = self.proxy_key.clone();
+        let metrics_sink = self.metrics_sink.clone();
         let proxy_channel_sender = self.proxy_channel_sender.clone();
+        let relay_retry = self.relay_retry.clone();
+        let max_stream2_bytes = self.max_stream2_bytes;
+        let abortive_close_on_error = self.abortive_close_on_error;
+        let log_lifecycle = self.log_lifecycle;
+        let fast_relay = (self.relay_mode == RelayMode::Fast)
+            && relay_retry.is_none()
+            && max_stream2_bytes.is_none();
 
         let bidirectional_iocopy_handle = thread::spawn(move || {
             let mut tcp_stream1 = mio::net::TcpStream::from_std(tcp_stream1);
@@ -110,6 +221,7 @@ impl TcpAndTcpStreamProxy {
                         &tcp_stream2,
                         &proxy_channel_sender,
                         &closed,
+                        abortive_close_on_error,
                     );
                     return Err(AppError::GenWithMsgAndErr(
                         "Error creating new MIO poller".to_string(),
@@ -128,6 +240,7 @@ impl TcpAndTcpStreamProxy {
                     &tcp_stream2,
                     &proxy_channel_sender,
                     &closed,
+                    abortive_close_on_error,
                 );
                 return Err(AppError::GenWithMsgAndErr(
                     "Error registering tcp stream 1 in MIO registry".to_string(),
@@ -145,6 +258,7 @@ impl TcpAndTcpStreamProxy {
                     &tcp_stream2,
                     &proxy_channel_sender,
                     &closed,
+                    abortive_close_on_error,
                 );
                 return Err(AppError::GenWithMsgAndErr(
                     "Error registering tcp stream 2 in MIO registry".to_string(),
@@ -154,9 +268,25 @@ impl TcpAndTcpStreamProxy {
 
             let mut events = mio::Events::with_capacity(256);
             let mut proxy_error = None;
+            let mut stream2_bytes: u64 = 0;
+
+            // Fast relay loop
+            if fast_relay {
+                match Self::relay_fast(
+                    &proxy_key,
+                    &closing,
+                    &mut poll,
+                    &stream1_reader_writer,
+                    &stream2_reader_writer,
+                    &metrics_sink,
+                ) {
+                    Ok(()) | Err(AppError::StreamEOF) => {}
+                    Err(err) => proxy_error = Some(err),
+                }
+            }
 
             // IO events processing loop
-            'EVENTS: while !*closing.lock().unwrap() {
+            'EVENTS: while !fast_relay && !*closing.lock().unwrap() {
                 match poll.poll(
                     &mut events,
                     Some(Duration::from_millis(POLLING_DURATION_MSECS)),
@@ -182,9 +312,30 @@ impl TcpAndTcpStreamProxy {
                                         &mut stream2_reader_writer,
                                         data.as_slice(),
                                     ) {
-                                        Ok(()) => {}
+                                        Ok(()) => metrics_sink
+                                            .incr_proxy_bytes(&proxy_key, data.len() as u64),
                                         Err(err) => match err {
                                             AppError::WouldBlock => continue,
+                                            _ if relay_retry.is_some() => {
+                                                if let Err(err) = Self::retry_stream2_relay(
+                                                    &proxy_key,
+                                                    relay_retry.as_ref().unwrap(),
+                                                    &poll,
+                                                    &mut tcp_stream2,
+                                                    &mut stream2_reader_writer,
+                                                    data.as_slice(),
+                                                    err,
+                                                ) {
+                                                    proxy_error = Some(err);
+                                                    *closing.lock().unwrap() = true;
+                                                    continue 'EVENTS;
+                                                } else {
+                                                    metrics_sink.incr_proxy_bytes(
+                                                        &proxy_key,
+                                                        data.len() as u64,
+                                                    );
+                                                }
+                                            }
                                             AppError::StreamEOF => break 'EVENTS,
                                             _ => {
                                                 proxy_error = Some(err);
@@ -218,11 +369,25 @@ impl TcpAndTcpStreamProxy {
                         STREAM2_TOKEN => {
                             match stream_utils::read_tcp_stream(&mut stream2_reader_writer) {
                                 Ok(data) => {
+                                    stream2_bytes += data.len() as u64;
+                                    if let Some(max_bytes) = max_stream2_bytes {
+                                        if stream2_bytes > max_bytes {
+                                            warn(
+                                                &target!(),
+                                                &format!(
+                                                    "Stream 2 byte limit exceeded, closing proxy: proxy_stream={}, limit={}",
+                                                    &proxy_key, max_bytes
+                                                ),
+                                            );
+                                            break 'EVENTS;
+                                        }
+                                    }
                                     match stream_utils::write_tcp_stream(
                                         &mut stream1_reader_writer,
                                         data.as_slice(),
                                     ) {
-                                        Ok(()) => {}
+                                        Ok(()) => metrics_sink
+                                            .incr_proxy_bytes(&proxy_key, data.len() as u64),
                                         Err(err) => match err {
                                             AppError::WouldBlock => continue,
                                             AppError::StreamEOF => break 'EVENTS,
@@ -235,7 +400,22 @@ impl TcpAndTcpStreamProxy {
                                     }
                                 }
                                 Err(err) => {
-                                    proxy_error = Some(err);
+                                    if let Some(relay_retry) = &relay_retry {
+                                        match Self::retry_stream2_relay(
+                                            &proxy_key,
+                                            relay_retry,
+                                            &poll,
+                                            &mut tcp_stream2,
+                                            &mut stream2_reader_writer,
+                                            &[],
+                                            err,
+                                        ) {
+                                            Ok(()) => continue,
+                                            Err(err) => proxy_error = Some(err),
+                                        }
+                                    } else {
+                                        proxy_error = Some(err);
+                                    }
                                     *closing.lock().unwrap() = true;
                                     continue 'EVENTS;
                                 }
@@ -260,13 +440,29 @@ impl TcpAndTcpStreamProxy {
                 }
             }
 
-            // Shutdown proxy resources
+            // Shutdown proxy resources (abortively, if stopping due to an error)
+            let abortive_close = abortive_close_on_error
+                && proxy_error
+                    .as_ref()
+                    .is_some_and(|err| !matches!(err, AppError::StreamEOF));
+
+            if matches!(proxy_error, Some(AppError::StreamEOF)) && log_lifecycle {
+                info(
+                    &target!(),
+                    &format!(
+                        "Proxy stream closed by peer, shutting down gracefully: proxy_stream={}",
+                        &proxy_key
+                    ),
+                );
+            }
+
             Self::perform_shutdown(
                 &proxy_key,
                 &tcp_stream1,
                 &tcp_stream2,
                 &proxy_channel_sender,
                 &closed,
+                abortive_close,
             );
 
             match proxy_error {
@@ -277,6 +473,7 @@ impl TcpAndTcpStreamProxy {
 
         // Spawn thread to join IO copy thread
         let proxy_key = self.proxy_key.clone();
+        let log_lifecycle = self.log_lifecycle;
 
         thread::spawn(move || {
             let join_result = bidirectional_iocopy_handle.join();
@@ -292,14 +489,16 @@ impl TcpAndTcpStreamProxy {
             if let Err(err) = join_result.unwrap() {
                 match err {
                     AppError::StreamEOF => {}
-                    _ => error(&target!(), &format!("{:?}", err)),
+                    _ => log_throttled(&target!(), LogLevel::ERROR, &format!("{:?}", err)),
                 }
             }
 
-            info(
-                &target!(),
-                &format!("Stopped proxy: proxy_stream={}", &proxy_key),
-            );
+            if log_lifecycle {
+                info(
+                    &target!(),
+                    &format!("Stopped proxy: proxy_stream={}", &proxy_key),
+                );
+            }
         });
 
         *self.closed.lock().unwrap() = false;
@@ -307,25 +506,265 @@ impl TcpAndTcpStreamProxy {
         Ok(())
     }
 
-    /// Shutdown proxy resources (called by proxy thread on termination)
+    /// Relay data between the streams, until closing or a stream EOF/error (called by proxy thread in fast relay mode).
+    /// Each pass drains both streams, and IO events are only polled (up to the polling duration) when both were idle.
+    fn relay_fast(
+        proxy_key: &ProxyKey,
+        closing: &Arc<Mutex<bool>>,
+        poll: &mut mio::Poll,
+        stream1_reader_writer: &Arc<Mutex<Box<dyn StreamReaderWriter>>>,
+        stream2_reader_writer: &Arc<Mutex<Box<dyn StreamReaderWriter>>>,
+        metrics_sink: &Arc<dyn MetricsSink>,
+    ) -> Result<(), AppError> {
+        let mut events = mio::Events::with_capacity(256);
+        let mut buffer = vec![0u8; FAST_RELAY_BUFFER_SIZE];
+
+        while !*closing.lock().unwrap() {
+            let bytes_relayed = Self::relay_fast_pending(
+                closing,
+                stream1_reader_writer,
+                stream2_reader_writer,
+                &mut buffer,
+            )? + Self::relay_fast_pending(
+                closing,
+                stream2_reader_writer,
+                stream1_reader_writer,
+                &mut buffer,
+            )?;
+
+            if bytes_relayed > 0 {
+                metrics_sink.incr_proxy_bytes(proxy_key, bytes_relayed as u64);
+                continue;
+            }
+
+            match poll.poll(
+                &mut events,
+                Some(Duration::from_millis(POLLING_DURATION_MSECS)),
+            ) {
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                Err(err) => {
+                    return Err(AppError::GenWithMsgAndErr(
+                        "Error while polling for IO events".to_string(),
+                        Box::new(err),
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Relay data currently readable from the source stream to the destination stream (up to a maximum number of
+    /// reads, so neither direction is starved). Returns the number of bytes relayed.
+    fn relay_fast_pending(
+        closing: &Arc<Mutex<bool>>,
+        source_reader_writer: &Arc<Mutex<Box<dyn StreamReaderWriter>>>,
+        dest_reader_writer: &Arc<Mutex<Box<dyn StreamReaderWriter>>>,
+        buffer: &mut [u8],
+    ) -> Result<usize, AppError> {
+        let mut bytes_relayed = 0;
+
+        for _ in 0..FAST_RELAY_MAX_READS_PER_PASS {
+            let read_result = source_reader_writer.lock().unwrap().read(buffer);
+            let bytes_read = match read_result {
+                Ok(0) => return Err(AppError::StreamEOF),
+                Ok(bytes_read) => bytes_read,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(Self::map_relay_io_error("reading from", err)),
+            };
+
+            Self::write_fast(closing, dest_reader_writer, &buffer[..bytes_read])?;
+            bytes_relayed += bytes_read;
+        }
+
+        Ok(bytes_relayed)
+    }
+
+    /// Write (and flush) all data to the stream, waiting out any stream backpressure (unless closing)
+    fn write_fast(
+        closing: &Arc<Mutex<bool>>,
+        stream_reader_writer: &Arc<Mutex<Box<dyn StreamReaderWriter>>>,
+        data: &[u8],
+    ) -> Result<(), AppError> {
+        let mut bytes_written = 0;
+        let mut flushed = false;
+
+        while !flushed {
+            let write_result = if bytes_written < data.len() {
+                stream_reader_writer
+                    .lock()
+                    .unwrap()
+                    .write(&data[bytes_written..])
+            } else {
+                stream_reader_writer.lock().unwrap().flush().map(|()| 0)
+            };
+
+            match write_result {
+                Ok(0) if bytes_written < data.len() => return Err(AppError::StreamEOF),
+                Ok(0) => flushed = true,
+                Ok(size) => bytes_written += size,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    if *closing.lock().unwrap() {
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(FAST_RELAY_WRITE_RETRY_MSECS));
+                }
+                Err(err) => return Err(Self::map_relay_io_error("writing to", err)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Convert fast relay IO error (disconnection errors are considered a stream EOF)
+    fn map_relay_io_error(operation: &str, err: io::Error) -> AppError {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::NotConnected => AppError::StreamEOF,
+            _ => AppError::GenWithMsgAndErr(format!("Error {} stream", operation), Box::new(err)),
+        }
+    }
+
+    /// Re-establish stream 2 (upstream) connection (called by proxy thread on upstream IO error).
+    /// Any pending data, which failed to be relayed, will be written to the new connection.
+    #[allow(clippy::too_many_arguments)]
+    fn retry_stream2_relay(
+        proxy_key: &ProxyKey,
+        relay_retry: &RelayRetry,
+        poll: &mio::Poll,
+        tcp_stream2: &mut mio::net::TcpStream,
+        stream2_reader_writer: &mut Arc<Mutex<Box<dyn StreamReaderWriter>>>,
+        pending_data: &[u8],
+        relay_err: AppError,
+    ) -> Result<(), AppError> {
+        warn(
+            &target!(),
+            &format!(
+                "Upstream relay error, reconnecting: proxy_stream={}, err={:?}",
+                &proxy_key, &relay_err
+            ),
+        );
+
+        let _ = poll.registry().deregister(tcp_stream2);
+        let _ = tcp_stream2.shutdown(Shutdown::Both);
+
+        let mut last_err = relay_err;
+
+        for attempt in 1..=relay_retry.max_attempts {
+            match Self::reconnect_stream2(relay_retry, poll, stream2_reader_writer, pending_data) {
+                Ok(new_tcp_stream2) => {
+                    *tcp_stream2 = new_tcp_stream2;
+                    info(
+                        &target!(),
+                        &format!(
+                            "Upstream relay reconnected: proxy_stream={}, attempt={}",
+                            &proxy_key, attempt
+                        ),
+                    );
+                    return Ok(());
+                }
+                Err(err) => {
+                    warn(
+                        &target!(),
+                        &format!(
+                            "Upstream relay reconnect failed: proxy_stream={}, attempt={}, err={:?}",
+                            &proxy_key, attempt, &err
+                        ),
+                    );
+                    last_err = err;
+                    thread::sleep(relay_retry.backoff.next_delay(attempt as u32));
+                }
+            }
+        }
+
+        Err(AppError::GenWithMsgAndErr(
+            format!(
+                "Upstream relay retries exhausted: proxy_stream={}, attempts={}",
+                &proxy_key, relay_retry.max_attempts
+            ),
+            Box::new(last_err),
+        ))
+    }
+
+    /// Establish new stream 2 (upstream) connection and register it for IO events
+    fn reconnect_stream2(
+        relay_retry: &RelayRetry,
+        poll: &mio::Poll,
+        stream2_reader_writer: &mut Arc<Mutex<Box<dyn StreamReaderWriter>>>,
+        pending_data: &[u8],
+    ) -> Result<mio::net::TcpStream, AppError> {
+        let tcp_stream2 = (relay_retry.upstream_connector)()?;
+        tcp_stream2.set_nonblocking(true).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                "Failed making reconnected stream 2 socket non-blocking".to_string(),
+                Box::new(err),
+            )
+        })?;
+
+        *stream2_reader_writer = Arc::new(Mutex::new(Box::new(
+            stream_utils::clone_std_tcp_stream(&tcp_stream2)?,
+        )));
+
+        if !pending_data.is_empty() {
+            stream_utils::write_tcp_stream(stream2_reader_writer, pending_data)?;
+        }
+
+        let mut tcp_stream2 = mio::net::TcpStream::from_std(tcp_stream2);
+
+        poll.registry()
+            .register(&mut tcp_stream2, STREAM2_TOKEN, mio::Interest::READABLE)
+            .map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    "Error registering reconnected tcp stream 2 in MIO registry".to_string(),
+                    Box::new(err),
+                )
+            })?;
+
+        Ok(tcp_stream2)
+    }
+
+    /// Shutdown proxy resources (called by proxy thread on termination). For an abortive close, stream 1 isn't shut
+    /// down, rather a zero linger timeout is set so that a RST is sent when the stream is closed.
     fn perform_shutdown(
-        proxy_key: &str,
+        proxy_key: &ProxyKey,
         tcp_stream1: &mio::net::TcpStream,
         tcp_stream2: &mio::net::TcpStream,
         proxy_channel_sender: &sync::mpsc::Sender<ProxyEvent>,
         closed_state: &Arc<Mutex<bool>>,
+        abortive_close: bool,
     ) {
         // Close proxy connection streams
-        match tcp_stream1.shutdown(Shutdown::Both) {
-            Ok(_) => {}
-            Err(err) if err.kind() == io::ErrorKind::NotConnected => {}
-            Err(err) => error(
+        if abortive_close {
+            warn(
                 &target!(),
                 &format!(
-                    "Error shutting down proxy tcp stream 1: proxy_stream={}, err={:?}",
-                    &proxy_key, err
+                    "Proxy stopped on error, aborting stream 1 connection: proxy_stream={}",
+                    &proxy_key
                 ),
-            ),
+            );
+            if let Err(err) = socket2::SockRef::from(tcp_stream1).set_linger(Some(Duration::ZERO)) {
+                error(
+                    &target!(),
+                    &format!(
+                        "Error setting proxy tcp stream 1 linger: proxy_stream={}, err={:?}",
+                        &proxy_key, err
+                    ),
+                );
+            }
+        } else {
+            match tcp_stream1.shutdown(Shutdown::Both) {
+                Ok(_) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotConnected => {}
+                Err(err) => error(
+                    &target!(),
+                    &format!(
+                        "Error shutting down proxy tcp stream 1: proxy_stream={}, err={:?}",
+                        &proxy_key, err
+                    ),
+                ),
+            }
         }
 
         match tcp_stream2.shutdown(Shutdown::Both) {
@@ -340,7 +779,7 @@ impl TcpAndTcpStreamProxy {
             ),
         }
 
-        if let Err(err) = proxy_channel_sender.send(ProxyEvent::Closed(proxy_key.to_string())) {
+        if let Err(err) = proxy_channel_sender.send(ProxyEvent::Closed(proxy_key.clone())) {
             error(
                 &target!(),
                 &format!(
@@ -361,7 +800,7 @@ impl ProxyStream for TcpAndTcpStreamProxy {
                 &target!(),
                 &format!("Proxy already stopped: proxy_stream={}", &self.proxy_key),
             );
-        } else {
+        } else if self.log_lifecycle {
             info(
                 &target!(),
                 &format!("Stopping proxy: proxy_stream={}", &self.proxy_key),
@@ -375,3 +814,317 @@ impl ProxyStream for TcpAndTcpStreamProxy {
 }
 
 unsafe impl Send for TcpAndTcpStreamProxy {}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::backoff::FixedBackoff;
+    use crate::proxy::proxy_base::ProxyType;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    fn create_proxy_key() -> ProxyKey {
+        ProxyKey::new(ProxyType::TcpAndTcp, 200, None, None)
+    }
+
+    fn create_connected_streams(listener: &TcpListener) -> (TcpStream, TcpStream) {
+        let connected_stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (accepted_stream, _) = listener.accept().unwrap();
+        (connected_stream, accepted_stream)
+    }
+
+    struct ProxyTestContext {
+        proxy: TcpAndTcpStreamProxy,
+        client_stream: TcpStream,
+        upstream_stream: TcpStream,
+        proxy_channel_receiver: sync::mpsc::Receiver<ProxyEvent>,
+    }
+
+    fn create_proxy(relay_retry: Option<RelayRetry>) -> ProxyTestContext {
+        let client_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (client_stream, proxy_stream1) = create_connected_streams(&client_listener);
+        let (proxy_stream2, upstream_stream) = create_connected_streams(&upstream_listener);
+        let (proxy_channel_sender, proxy_channel_receiver) = sync::mpsc::channel();
+
+        let proxy = TcpAndTcpStreamProxy::new(
+            &create_proxy_key(),
+            proxy_stream1.try_clone().unwrap(),
+            proxy_stream2.try_clone().unwrap(),
+            Arc::new(Mutex::new(Box::new(proxy_stream1))),
+            Arc::new(Mutex::new(Box::new(proxy_stream2))),
+            proxy_channel_sender,
+            relay_retry,
+        )
+        .unwrap();
+
+        ProxyTestContext {
+            proxy,
+            client_stream,
+            upstream_stream,
+            proxy_channel_receiver,
+        }
+    }
+
+    fn reset_upstream_stream(upstream_stream: TcpStream) {
+        // Closing with unread data will send a RST to the proxy
+        let mut buffer = [0u8; 16];
+        upstream_stream.peek(&mut buffer).unwrap();
+        drop(upstream_stream);
+    }
+
+    fn read_exact_with_timeout(stream: &mut TcpStream, len: usize) -> Vec<u8> {
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut buffer = vec![0u8; len];
+        stream.read_exact(&mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn tcptcpproxy_connect_when_upstream_recoverable_blip() {
+        let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream_addr = upstream_listener.local_addr().unwrap();
+        let relay_retry = RelayRetry::new(
+            3,
+            Arc::new(FixedBackoff::new(Duration::from_millis(10))),
+            Arc::new(move || TcpStream::connect(upstream_addr).map_err(AppError::Io)),
+        );
+
+        let ProxyTestContext {
+            mut proxy,
+            mut client_stream,
+            upstream_stream,
+            proxy_channel_receiver,
+        } = create_proxy(Some(relay_retry));
+
+        proxy.connect().unwrap();
+
+        client_stream.write_all(b"hello").unwrap();
+        reset_upstream_stream(upstream_stream);
+
+        let (mut upstream_stream, _) = upstream_listener.accept().unwrap();
+
+        client_stream.write_all(b"world").unwrap();
+        assert_eq!(read_exact_with_timeout(&mut upstream_stream, 5), b"world");
+
+        upstream_stream.write_all(b"reply").unwrap();
+        assert_eq!(read_exact_with_timeout(&mut client_stream, 5), b"reply");
+
+        assert!(proxy_channel_receiver.try_recv().is_err());
+        assert!(!*proxy.closed.lock().unwrap());
+
+        proxy.disconnect().unwrap();
+    }
+
+    #[test]
+    fn tcptcpproxy_connect_when_upstream_unrecoverable_failure() {
+        let relay_retry = RelayRetry::new(
+            2,
+            Arc::new(FixedBackoff::new(Duration::from_millis(10))),
+            Arc::new(|| Err(AppError::General("upstream unavailable".to_string()))),
+        );
+
+        let ProxyTestContext {
+            mut proxy,
+            mut client_stream,
+            upstream_stream,
+            proxy_channel_receiver,
+        } = create_proxy(Some(relay_retry));
+
+        proxy.connect().unwrap();
+
+        client_stream.write_all(b"hello").unwrap();
+        reset_upstream_stream(upstream_stream);
+
+        match proxy_channel_receiver.recv_timeout(Duration::from_secs(5)) {
+            Ok(ProxyEvent::Closed(proxy_key)) => assert_eq!(proxy_key, create_proxy_key()),
+            Ok(_) => panic!("Unexpected proxy event"),
+            Err(err) => panic!("Proxy not closed: err={:?}", &err),
+        }
+
+        client_stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut buffer = [0u8; 16];
+        assert_eq!(client_stream.read(&mut buffer).unwrap(), 0);
+    }
+
+    #[test]
+    fn tcptcpproxy_connect_when_stream2_bytes_under_limit() {
+        let ProxyTestContext {
+            mut proxy,
+            mut client_stream,
+            mut upstream_stream,
+            proxy_channel_receiver,
+        } = create_proxy(None);
+
+        proxy.set_max_stream2_bytes(Some(10));
+        proxy.connect().unwrap();
+
+        upstream_stream.write_all(b"reply1").unwrap();
+        assert_eq!(read_exact_with_timeout(&mut client_stream, 6), b"reply1");
+        upstream_stream.write_all(b"rep2").unwrap();
+        assert_eq!(read_exact_with_timeout(&mut client_stream, 4), b"rep2");
+
+        client_stream.write_all(b"request").unwrap();
+        assert_eq!(read_exact_with_timeout(&mut upstream_stream, 7), b"request");
+
+        assert!(proxy_channel_receiver.try_recv().is_err());
+        assert!(!*proxy.closed.lock().unwrap());
+
+        proxy.disconnect().unwrap();
+    }
+
+    #[test]
+    fn tcptcpproxy_connect_when_stream2_bytes_over_limit() {
+        let ProxyTestContext {
+            mut proxy,
+            mut client_stream,
+            mut upstream_stream,
+            proxy_channel_receiver,
+        } = create_proxy(None);
+
+        proxy.set_max_stream2_bytes(Some(10));
+        proxy.set_relay_mode(RelayMode::Fast);
+        proxy.connect().unwrap();
+
+        upstream_stream.write_all(b"reply1").unwrap();
+        assert_eq!(read_exact_with_timeout(&mut client_stream, 6), b"reply1");
+        upstream_stream.write_all(b"reply2").unwrap();
+
+        match proxy_channel_receiver.recv_timeout(Duration::from_secs(5)) {
+            Ok(ProxyEvent::Closed(proxy_key)) => assert_eq!(proxy_key, create_proxy_key()),
+            Ok(_) => panic!("Unexpected proxy event"),
+            Err(err) => panic!("Proxy not closed: err={:?}", &err),
+        }
+
+        client_stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut buffer = [0u8; 16];
+        assert_eq!(client_stream.read(&mut buffer).unwrap(), 0);
+    }
+
+    fn recv_proxy_closed(proxy_channel_receiver: &sync::mpsc::Receiver<ProxyEvent>) {
+        match proxy_channel_receiver.recv_timeout(Duration::from_secs(5)) {
+            Ok(ProxyEvent::Closed(proxy_key)) => assert_eq!(proxy_key, create_proxy_key()),
+            Ok(_) => panic!("Unexpected proxy event"),
+            Err(err) => panic!("Proxy not closed: err={:?}", &err),
+        }
+    }
+
+    #[test]
+    fn tcptcpproxy_connect_when_stream2_eof_and_abortive_close_on_error() {
+        let ProxyTestContext {
+            mut proxy,
+            mut client_stream,
+            mut upstream_stream,
+            proxy_channel_receiver,
+        } = create_proxy(None);
+
+        proxy.set_abortive_close_on_error(true);
+        proxy.connect().unwrap();
+
+        upstream_stream.write_all(b"reply").unwrap();
+        assert_eq!(read_exact_with_timeout(&mut client_stream, 5), b"reply");
+        upstream_stream.shutdown(Shutdown::Both).unwrap();
+
+        recv_proxy_closed(&proxy_channel_receiver);
+
+        client_stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut buffer = [0u8; 16];
+        assert_eq!(client_stream.read(&mut buffer).unwrap(), 0);
+    }
+
+    #[test]
+    fn tcptcpproxy_connect_when_stream2_error_and_abortive_close_on_error() {
+        let ProxyTestContext {
+            mut proxy,
+            mut client_stream,
+            upstream_stream,
+            proxy_channel_receiver,
+        } = create_proxy(None);
+
+        proxy.set_abortive_close_on_error(true);
+        proxy.connect().unwrap();
+
+        client_stream.write_all(b"hello").unwrap();
+        reset_upstream_stream(upstream_stream);
+
+        recv_proxy_closed(&proxy_channel_receiver);
+
+        client_stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut buffer = [0u8; 16];
+        match client_stream.read(&mut buffer) {
+            Ok(bytes_read) => panic!("Unexpected successful read: bytes={}", bytes_read),
+            Err(err) => assert_eq!(err.kind(), io::ErrorKind::ConnectionReset),
+        }
+    }
+
+    #[test]
+    fn tcptcpproxy_connect_when_already_connected() {
+        let ProxyTestContext { mut proxy, .. } = create_proxy(None);
+
+        proxy.connect().unwrap();
+
+        assert!(proxy.connect().is_err());
+
+        proxy.disconnect().unwrap();
+    }
+
+    fn relay_large_payload(relay_mode: RelayMode, payload: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let ProxyTestContext {
+            mut proxy,
+            mut client_stream,
+            mut upstream_stream,
+            proxy_channel_receiver: _proxy_channel_receiver,
+        } = create_proxy(None);
+
+        proxy.set_relay_mode(relay_mode);
+        proxy.connect().unwrap();
+
+        let mut client_writer = client_stream.try_clone().unwrap();
+        let client_payload = payload.to_vec();
+        let client_writer_handle =
+            thread::spawn(move || client_writer.write_all(&client_payload).unwrap());
+        let mut upstream_writer = upstream_stream.try_clone().unwrap();
+        let upstream_payload = payload.to_vec();
+        let upstream_writer_handle =
+            thread::spawn(move || upstream_writer.write_all(&upstream_payload).unwrap());
+
+        let payload_len = payload.len();
+        let client_reader_handle =
+            thread::spawn(move || read_exact_with_timeout(&mut client_stream, payload_len));
+
+        let upstream_received = read_exact_with_timeout(&mut upstream_stream, payload_len);
+        let client_received = client_reader_handle.join().unwrap();
+
+        client_writer_handle.join().unwrap();
+        upstream_writer_handle.join().unwrap();
+        proxy.disconnect().unwrap();
+
+        (upstream_received, client_received)
+    }
+
+    #[test]
+    fn tcptcpproxy_connect_relay_modes_when_large_payload() {
+        let payload: Vec<u8> = (0..(1024 * 1024))
+            .map(|index| (index % 251) as u8)
+            .collect();
+
+        for relay_mode in [RelayMode::Standard, RelayMode::Fast] {
+            let (upstream_received, client_received) = relay_large_payload(relay_mode, &payload);
+
+            assert!(upstream_received == payload, "mode={:?}", relay_mode);
+            assert!(client_received == payload, "mode={:?}", relay_mode);
+        }
+    }
+}
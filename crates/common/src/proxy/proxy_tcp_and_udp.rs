@@ -1,4 +1,4 @@
-use std::net::Shutdown;
+use std::net::{Shutdown, SocketAddr};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::{io, sync, thread};
@@ -6,11 +6,13 @@ use std::{io, sync, thread};
 use anyhow::Result;
 
 use crate::error::AppError;
-use crate::logging::{error, info, warn};
+use crate::logging::{error, info, log_throttled, warn, LogLevel};
+use crate::metrics::{MetricsSink, NoOpMetricsSink};
 use crate::net::stream_utils;
 use crate::net::stream_utils::StreamReaderWriter;
 use crate::proxy::event::ProxyEvent;
 use crate::proxy::proxy_base::ProxyStream;
+use crate::proxy::proxy_key::ProxyKey;
 use crate::target;
 
 const TCP_STREAM_TOKEN: mio::Token = mio::Token(0);
@@ -19,26 +21,27 @@ const POLLING_DURATION_MSECS: u64 = 1000;
 
 /// Proxy based on 2 connected sockets: TCP stream and a UDP socket
 pub struct TcpAndUdpStreamProxy {
-    proxy_key: String,
+    proxy_key: ProxyKey,
     tcp_stream: std::net::TcpStream,
     udp_socket: std::net::UdpSocket,
     tcp_stream_reader_writer: Arc<Mutex<Box<dyn StreamReaderWriter>>>,
     proxy_channel_sender: sync::mpsc::Sender<ProxyEvent>,
     closing: Arc<Mutex<bool>>,
     closed: Arc<Mutex<bool>>,
+    metrics_sink: Arc<dyn MetricsSink>,
+    udp_target_addr: Option<SocketAddr>,
+    log_lifecycle: bool,
 }
 
 impl TcpAndUdpStreamProxy {
     /// TcpAndUdpStreamProxy constructor
     pub fn new(
-        proxy_key: &str,
+        proxy_key: &ProxyKey,
         tcp_stream: std::net::TcpStream,
         udp_socket: std::net::UdpSocket,
         tcp_stream_reader_writer: Arc<Mutex<Box<dyn StreamReaderWriter>>>,
         proxy_channel_sender: sync::mpsc::Sender<ProxyEvent>,
     ) -> Result<Self, AppError> {
-        //let proxy_key = ProxyEvent::key_value(&ProxyType::TcpAndUdp, tcp_stream.peer_addr().ok(), udp_socket.peer_addr().ok());
-
         // Convert streams to non-blocking
         let tcp_stream = stream_utils::clone_std_tcp_stream(&tcp_stream)?;
         let udp_socket = stream_utils::clone_std_udp_socket(&udp_socket)?;
@@ -64,22 +67,43 @@ impl TcpAndUdpStreamProxy {
 
         // Instantiate TcpStreamProxy
         Ok(TcpAndUdpStreamProxy {
-            proxy_key: proxy_key.to_string(),
+            proxy_key: proxy_key.clone(),
             tcp_stream,
             udp_socket,
             tcp_stream_reader_writer,
             proxy_channel_sender,
             closing: Arc::new(Mutex::new(false)),
             closed: Arc::new(Mutex::new(false)),
+            metrics_sink: Arc::new(NoOpMetricsSink),
+            udp_target_addr: None,
+            log_lifecycle: true,
         })
     }
 
+    /// Set sink used to emit proxy metrics (bytes transferred)
+    pub fn set_metrics_sink(&mut self, metrics_sink: Arc<dyn MetricsSink>) {
+        self.metrics_sink = metrics_sink;
+    }
+
+    /// Set target address for UDP sends, for UDP sockets not connected to a single peer (broadcast/multicast
+    /// targets). Replies from any responder are relayed back.
+    pub fn set_udp_target_addr(&mut self, udp_target_addr: Option<SocketAddr>) {
+        self.udp_target_addr = udp_target_addr;
+    }
+
+    /// Set whether proxy lifecycle (start/stop) is logged (see `logging::LogSampler`)
+    pub fn set_log_lifecycle(&mut self, log_lifecycle: bool) {
+        self.log_lifecycle = log_lifecycle;
+    }
+
     /// Connect tcp IO streams (spawn task to bidirectionally copy data)
     pub fn connect(&mut self) -> Result<(), AppError> {
-        info(
-            &target!(),
-            &format!("Starting proxy: proxy_stream={}", &self.proxy_key),
-        );
+        if self.log_lifecycle {
+            info(
+                &target!(),
+                &format!("Starting proxy: proxy_stream={}", &self.proxy_key),
+            );
+        }
 
         *self.closing.lock().unwrap() = false;
 
@@ -90,7 +114,9 @@ impl TcpAndUdpStreamProxy {
         let udp_socket = stream_utils::clone_std_udp_socket(&self.udp_socket)?;
         let mut tcp_stream_reader_writer = self.tcp_stream_reader_writer.clone();
         let proxy_key = self.proxy_key.clone();
+        let metrics_sink = self.metrics_sink.clone();
         let proxy_channel_sender = self.proxy_channel_sender.clone();
+        let udp_target_addr = self.udp_target_addr;
 
         let bidirectional_iocopy_handle = thread::spawn(move || {
             let mut tcp_stream = mio::net::TcpStream::from_std(tcp_stream);
@@ -176,11 +202,22 @@ impl TcpAndUdpStreamProxy {
                         TCP_STREAM_TOKEN => {
                             match stream_utils::read_tcp_stream(&mut tcp_stream_reader_writer) {
                                 Ok(data) => {
-                                    match stream_utils::write_mio_udp_socket(
-                                        &udp_socket,
-                                        data.as_slice(),
-                                    ) {
-                                        Ok(()) => {}
+                                    let write_result = match &udp_target_addr {
+                                        Some(udp_target_addr) => {
+                                            stream_utils::write_mio_udp_socket_to(
+                                                &udp_socket,
+                                                data.as_slice(),
+                                                udp_target_addr,
+                                            )
+                                        }
+                                        None => stream_utils::write_mio_udp_socket(
+                                            &udp_socket,
+                                            data.as_slice(),
+                                        ),
+                                    };
+                                    match write_result {
+                                        Ok(()) => metrics_sink
+                                            .incr_proxy_bytes(&proxy_key, data.len() as u64),
                                         Err(err) => match err {
                                             AppError::WouldBlock => continue,
                                             AppError::StreamEOF => break 'EVENTS,
@@ -220,7 +257,8 @@ impl TcpAndUdpStreamProxy {
                                         &mut tcp_stream_reader_writer,
                                         data.as_slice(),
                                     ) {
-                                        Ok(()) => {}
+                                        Ok(()) => metrics_sink
+                                            .incr_proxy_bytes(&proxy_key, data.len() as u64),
                                         Err(err) => match err {
                                             AppError::WouldBlock => continue,
                                             AppError::StreamEOF => break 'EVENTS,
@@ -275,6 +313,7 @@ impl TcpAndUdpStreamProxy {
 
         // Spawn thread to join IO copy thread
         let proxy_key = self.proxy_key.clone();
+        let log_lifecycle = self.log_lifecycle;
 
         thread::spawn(move || {
             let join_result = bidirectional_iocopy_handle.join();
@@ -290,14 +329,16 @@ impl TcpAndUdpStreamProxy {
             if let Err(err) = join_result.unwrap() {
                 match err {
                     AppError::StreamEOF => {}
-                    _ => error(&target!(), &format!("{:?}", err)),
+                    _ => log_throttled(&target!(), LogLevel::ERROR, &format!("{:?}", err)),
                 }
             }
 
-            info(
-                &target!(),
-                &format!("Stopped proxy: proxy_stream={}", &proxy_key),
-            );
+            if log_lifecycle {
+                info(
+                    &target!(),
+                    &format!("Stopped proxy: proxy_stream={}", &proxy_key),
+                );
+            }
         });
 
         *self.closed.lock().unwrap() = false;
@@ -307,7 +348,7 @@ impl TcpAndUdpStreamProxy {
 
     /// Shutdown proxy resources (called by proxy thread on termination)
     fn perform_shutdown(
-        proxy_key: &str,
+        proxy_key: &ProxyKey,
         tcp_stream: &mio::net::TcpStream,
         _udp_socket: &mio::net::UdpSocket,
         proxy_channel_sender: &sync::mpsc::Sender<ProxyEvent>,
@@ -326,7 +367,7 @@ impl TcpAndUdpStreamProxy {
             ),
         }
 
-        if let Err(err) = proxy_channel_sender.send(ProxyEvent::Closed(proxy_key.to_string())) {
+        if let Err(err) = proxy_channel_sender.send(ProxyEvent::Closed(proxy_key.clone())) {
             error(
                 &target!(),
                 &format!(
@@ -347,7 +388,7 @@ impl ProxyStream for TcpAndUdpStreamProxy {
                 &target!(),
                 &format!("Proxy already stopped: proxy_stream={}", &self.proxy_key),
             );
-        } else {
+        } else if self.log_lifecycle {
             info(
                 &target!(),
                 &format!("Stopping proxy: proxy_stream={}", &self.proxy_key),
@@ -361,3 +402,67 @@ impl ProxyStream for TcpAndUdpStreamProxy {
 }
 
 unsafe impl Send for TcpAndUdpStreamProxy {}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::proxy::proxy_base::ProxyType;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream, UdpSocket};
+
+    fn read_exact_with_timeout(stream: &mut TcpStream, len: usize) -> Vec<u8> {
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut buffer = vec![0u8; len];
+        stream.read_exact(&mut buffer).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn tcpudpproxy_connect_when_udp_target_addr_and_multiple_responders() {
+        let client_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client_stream = TcpStream::connect(client_listener.local_addr().unwrap()).unwrap();
+        let (proxy_tcp_stream, _) = client_listener.accept().unwrap();
+        let proxy_udp_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let proxy_udp_addr = proxy_udp_socket.local_addr().unwrap();
+        let responders = [
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+            UdpSocket::bind("127.0.0.1:0").unwrap(),
+        ];
+        responders[0]
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let (proxy_channel_sender, _proxy_channel_receiver) = sync::mpsc::channel();
+
+        let mut proxy = TcpAndUdpStreamProxy::new(
+            &ProxyKey::new(ProxyType::TcpAndUdp, 200, Some(proxy_udp_addr), None),
+            proxy_tcp_stream.try_clone().unwrap(),
+            proxy_udp_socket,
+            Arc::new(Mutex::new(Box::new(proxy_tcp_stream))),
+            proxy_channel_sender,
+        )
+        .unwrap();
+        proxy.set_udp_target_addr(Some(responders[0].local_addr().unwrap()));
+        proxy.connect().unwrap();
+
+        client_stream.write_all(b"query").unwrap();
+
+        let mut buffer = [0u8; 16];
+        let (size, source_addr) = responders[0].recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..size], b"query");
+        assert_eq!(source_addr, proxy_udp_addr);
+
+        responders[0].send_to(b"reply1", proxy_udp_addr).unwrap();
+        let reply1 = read_exact_with_timeout(&mut client_stream, 6);
+        responders[1].send_to(b"reply2", proxy_udp_addr).unwrap();
+        let reply2 = read_exact_with_timeout(&mut client_stream, 6);
+
+        assert_eq!(reply1, b"reply1");
+        assert_eq!(reply2, b"reply2");
+
+        proxy.disconnect().unwrap();
+    }
+}
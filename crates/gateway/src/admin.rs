@@ -0,0 +1,528 @@
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::config::AppConfig;
+use crate::service::manager::ServiceMgr;
+use trust0_common::control::response;
+use trust0_common::error::AppError;
+use trust0_common::model::access::ServiceAccess;
+use trust0_common::model::service::Service;
+use trust0_common::model::user::User;
+
+/// Active service proxy connection (session) for a user
+#[derive(Clone, PartialEq, Debug)]
+pub struct Session {
+    /// Opaque session handle (as used by the control plane 'close-session' command)
+    pub handle: String,
+    pub service_id: u64,
+    pub client_addr: String,
+    pub gateway_addr: String,
+    pub started_at: Instant,
+}
+
+/// In-process administration of a running gateway (for instance, when embedding the gateway as a library).
+/// Manages users, services and access (via the gateway's repositories) and service proxy sessions, without
+/// the control plane protocol. Repository changes are subject to the same validation as datasource loads.
+/// In read-only gateway mode, mutating operations are rejected with a 403 error.
+pub trait AdminApi: Send + Sync {
+    /// Creates/updates a user. Returns the previous user (if any)
+    fn put_user(&self, user: User) -> Result<Option<User>, AppError>;
+
+    /// Gets a user
+    fn get_user(&self, user_id: u64) -> Result<Option<User>, AppError>;
+
+    /// Returns the list of all users
+    fn get_users(&self) -> Result<Vec<User>, AppError>;
+
+    /// Deletes a user. Returns the previous user (if any)
+    fn delete_user(&self, user_id: u64) -> Result<Option<User>, AppError>;
+
+    /// Creates/updates a service (host is validated and normalized). Returns the previous service (if any)
+    fn put_service(&self, service: Service) -> Result<Option<Service>, AppError>;
+
+    /// Gets a service
+    fn get_service(&self, service_id: u64) -> Result<Option<Service>, AppError>;
+
+    /// Returns the list of all services
+    fn get_services(&self) -> Result<Vec<Service>, AppError>;
+
+    /// Deletes a service. Returns the previous service (if any)
+    fn delete_service(&self, service_id: u64) -> Result<Option<Service>, AppError>;
+
+    /// Creates/updates a service access. Returns the previous access (if any)
+    fn put_access(&self, access: ServiceAccess) -> Result<Option<ServiceAccess>, AppError>;
+
+    /// Gets a service access
+    fn get_access(&self, user_id: u64, service_id: u64) -> Result<Option<ServiceAccess>, AppError>;
+
+    /// Returns the list of service accesses for given user
+    fn get_accesses_for_user(&self, user_id: u64) -> Result<Vec<ServiceAccess>, AppError>;
+
+    /// Deletes a service access. Returns the previous access (if any)
+    fn delete_access(
+        &self,
+        user_id: u64,
+        service_id: u64,
+    ) -> Result<Option<ServiceAccess>, AppError>;
+
+    /// Returns the active service proxy connections (sessions) for given user
+    fn get_sessions_for_user(&self, user_id: u64) -> Vec<Session>;
+
+    /// Shutdown the user's session for given session handle (404 error if unknown)
+    fn shutdown_session(&self, user_id: u64, handle: &str) -> Result<(), AppError>;
+
+    /// Shutdown service proxy connections. Consider all connections or by user and/or service (if supplied)
+    fn shutdown_connections(
+        &self,
+        user_id: Option<u64>,
+        service_id: Option<u64>,
+    ) -> Result<(), AppError>;
+}
+
+/// Admin API implementation backed by the running gateway's repositories and service manager
+pub struct GatewayAdminApi {
+    app_config: Arc<AppConfig>,
+    service_mgr: Arc<Mutex<dyn ServiceMgr>>,
+}
+
+impl GatewayAdminApi {
+    /// GatewayAdminApi constructor
+    pub fn new(app_config: Arc<AppConfig>, service_mgr: Arc<Mutex<dyn ServiceMgr>>) -> Self {
+        Self {
+            app_config,
+            service_mgr,
+        }
+    }
+}
+
+impl AdminApi for GatewayAdminApi {
+    fn put_user(&self, user: User) -> Result<Option<User>, AppError> {
+        self.app_config.validate_writable()?;
+        self.app_config.user_repo.lock().unwrap().put(user)
+    }
+
+    fn get_user(&self, user_id: u64) -> Result<Option<User>, AppError> {
+        self.app_config.user_repo.lock().unwrap().get(user_id)
+    }
+
+    fn get_users(&self) -> Result<Vec<User>, AppError> {
+        self.app_config.user_repo.lock().unwrap().get_all()
+    }
+
+    fn delete_user(&self, user_id: u64) -> Result<Option<User>, AppError> {
+        self.app_config.validate_writable()?;
+        self.app_config.user_repo.lock().unwrap().delete(user_id)
+    }
+
+    fn put_service(&self, mut service: Service) -> Result<Option<Service>, AppError> {
+        self.app_config.validate_writable()?;
+        service.normalize_host()?;
+        self.app_config.service_repo.lock().unwrap().put(service)
+    }
+
+    fn get_service(&self, service_id: u64) -> Result<Option<Service>, AppError> {
+        self.app_config.service_repo.lock().unwrap().get(service_id)
+    }
+
+    fn get_services(&self) -> Result<Vec<Service>, AppError> {
+        self.app_config.service_repo.lock().unwrap().get_all()
+    }
+
+    fn delete_service(&self, service_id: u64) -> Result<Option<Service>, AppError> {
+        self.app_config.validate_writable()?;
+        self.app_config
+            .service_repo
+            .lock()
+            .unwrap()
+            .delete(service_id)
+    }
+
+    fn put_access(&self, access: ServiceAccess) -> Result<Option<ServiceAccess>, AppError> {
+        self.app_config.validate_writable()?;
+        self.app_config.access_repo.lock().unwrap().put(access)
+    }
+
+    fn get_access(&self, user_id: u64, service_id: u64) -> Result<Option<ServiceAccess>, AppError> {
+        self.app_config
+            .access_repo
+            .lock()
+            .unwrap()
+            .get(user_id, service_id)
+    }
+
+    fn get_accesses_for_user(&self, user_id: u64) -> Result<Vec<ServiceAccess>, AppError> {
+        self.app_config
+            .access_repo
+            .lock()
+            .unwrap()
+            .get_all_for_user(user_id)
+    }
+
+    fn delete_access(
+        &self,
+        user_id: u64,
+        service_id: u64,
+    ) -> Result<Option<ServiceAccess>, AppError> {
+        self.app_config.validate_writable()?;
+        self.app_config
+            .access_repo
+            .lock()
+            .unwrap()
+            .delete(user_id, service_id)
+    }
+
+    fn get_sessions_for_user(&self, user_id: u64) -> Vec<Session> {
+        let service_proxies = self.service_mgr.lock().unwrap().get_service_proxies();
+
+        service_proxies
+            .iter()
+            .flat_map(|service_proxy| {
+                let service_proxy = service_proxy.lock().unwrap();
+                let service_id = service_proxy.get_service().service_id;
+
+                service_proxy
+                    .get_proxy_sessions_for_user(user_id)
+                    .into_iter()
+                    .map(move |session| Session {
+                        handle: session.handle(),
+                        service_id,
+                        client_addr: session.proxy_addrs.0,
+                        gateway_addr: session.proxy_addrs.1,
+                        started_at: session.started_at,
+                    })
+                    .collect::<Vec<Session>>()
+            })
+            .collect()
+    }
+
+    fn shutdown_session(&self, user_id: u64, handle: &str) -> Result<(), AppError> {
+        self.app_config.validate_writable()?;
+        let service_mgr = self.service_mgr.lock().unwrap();
+
+        let (service_proxy, session) = service_mgr
+            .get_service_proxies()
+            .into_iter()
+            .find_map(|service_proxy| {
+                let session = service_proxy
+                    .lock()
+                    .unwrap()
+                    .get_proxy_sessions_for_user(user_id)
+                    .into_iter()
+                    .find(|session| session.handle() == handle)?;
+                Some((service_proxy, session))
+            })
+            .ok_or(AppError::GenWithCodeAndMsg(
+                response::CODE_NOT_FOUND,
+                format!("Unknown session: handle={}", handle),
+            ))?;
+
+        let result = service_proxy
+            .lock()
+            .unwrap()
+            .shutdown_connection(service_mgr.clone_proxy_tasks_sender(), &session.proxy_key);
+        result
+    }
+
+    fn shutdown_connections(
+        &self,
+        user_id: Option<u64>,
+        service_id: Option<u64>,
+    ) -> Result<(), AppError> {
+        self.app_config.validate_writable()?;
+        self.service_mgr
+            .lock()
+            .unwrap()
+            .shutdown_connections(user_id, service_id)
+            .map_err(|errs| errs.into())
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::config;
+    use crate::repository::access_repo::in_memory_repo::InMemAccessRepo;
+    use crate::repository::service_repo::in_memory_repo::InMemServiceRepo;
+    use crate::repository::user_repo::in_memory_repo::InMemUserRepo;
+    use crate::service::manager::tests::MockSvcMgr;
+    use crate::service::proxy::proxy_base::tests::MockGwSvcProxyVisitor;
+    use crate::service::proxy::proxy_base::ProxySession;
+    use mockall::predicate;
+    use std::sync::mpsc;
+    use trust0_common::model::service::Transport;
+    use trust0_common::model::user::Status;
+    use trust0_common::proxy::proxy_base::ProxyType;
+    use trust0_common::proxy::proxy_key::ProxyKey;
+
+    fn create_admin_api(service_mgr: MockSvcMgr) -> GatewayAdminApi {
+        create_admin_api_with_read_only(service_mgr, false)
+    }
+
+    fn create_admin_api_with_read_only(
+        service_mgr: MockSvcMgr,
+        read_only: bool,
+    ) -> GatewayAdminApi {
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(InMemUserRepo::new())),
+            Arc::new(Mutex::new(InMemServiceRepo::new())),
+            Arc::new(Mutex::new(InMemAccessRepo::new())),
+        )
+        .unwrap();
+        app_config.read_only = read_only;
+
+        GatewayAdminApi::new(Arc::new(app_config), Arc::new(Mutex::new(service_mgr)))
+    }
+
+    fn create_session(service_id: u64) -> ProxySession {
+        ProxySession {
+            proxy_key: ProxyKey::new(
+                ProxyType::TcpAndTcp,
+                service_id,
+                Some("127.0.0.1:41000".parse().unwrap()),
+                Some("127.0.0.1:8200".parse().unwrap()),
+            ),
+            proxy_addrs: ("addr1".to_string(), "addr2".to_string()),
+            started_at: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn adminapi_user_crud() {
+        let admin_api = create_admin_api(MockSvcMgr::new());
+
+        assert_eq!(
+            admin_api
+                .put_user(User::new(100, "user100", Status::Active))
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            admin_api.get_user(100).unwrap(),
+            Some(User::new(100, "user100", Status::Active))
+        );
+
+        assert_eq!(
+            admin_api
+                .put_user(User::new(100, "user100", Status::Inactive))
+                .unwrap(),
+            Some(User::new(100, "user100", Status::Active))
+        );
+        assert_eq!(
+            admin_api.get_users().unwrap(),
+            vec![User::new(100, "user100", Status::Inactive)]
+        );
+
+        assert_eq!(
+            admin_api.delete_user(100).unwrap(),
+            Some(User::new(100, "user100", Status::Inactive))
+        );
+        assert_eq!(admin_api.get_user(100).unwrap(), None);
+    }
+
+    #[test]
+    fn adminapi_service_crud() {
+        let admin_api = create_admin_api(MockSvcMgr::new());
+
+        assert_eq!(
+            admin_api
+                .put_service(Service::new(
+                    200,
+                    "Service200",
+                    &Transport::TCP,
+                    " LocalHost ",
+                    8200
+                ))
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            admin_api.get_service(200).unwrap(),
+            Some(Service::new(
+                200,
+                "Service200",
+                &Transport::TCP,
+                "localhost",
+                8200
+            ))
+        );
+
+        assert_eq!(
+            admin_api
+                .put_service(Service::new(
+                    200,
+                    "Service200",
+                    &Transport::TCP,
+                    "localhost",
+                    8201
+                ))
+                .unwrap()
+                .map(|service| service.port),
+            Some(8200)
+        );
+        assert_eq!(admin_api.get_services().unwrap().len(), 1);
+
+        assert_eq!(
+            admin_api
+                .delete_service(200)
+                .unwrap()
+                .map(|service| service.port),
+            Some(8201)
+        );
+        assert_eq!(admin_api.get_service(200).unwrap(), None);
+    }
+
+    #[test]
+    fn adminapi_put_service_when_invalid_host() {
+        let admin_api = create_admin_api(MockSvcMgr::new());
+
+        assert!(admin_api
+            .put_service(Service::new(
+                200,
+                "Service200",
+                &Transport::TCP,
+                "http://localhost",
+                8200
+            ))
+            .is_err());
+        assert_eq!(admin_api.get_service(200).unwrap(), None);
+    }
+
+    #[test]
+    fn adminapi_access_crud() {
+        let admin_api = create_admin_api(MockSvcMgr::new());
+
+        assert_eq!(
+            admin_api.put_access(ServiceAccess::new(100, 200)).unwrap(),
+            None
+        );
+        admin_api.put_access(ServiceAccess::new(100, 201)).unwrap();
+        admin_api.put_access(ServiceAccess::new(101, 200)).unwrap();
+        assert_eq!(
+            admin_api.get_access(100, 200).unwrap(),
+            Some(ServiceAccess::new(100, 200))
+        );
+
+        let mut deny_access = ServiceAccess::new(100, 200);
+        deny_access.deny = true;
+        assert_eq!(
+            admin_api.put_access(deny_access.clone()).unwrap(),
+            Some(ServiceAccess::new(100, 200))
+        );
+
+        let mut user_accesses = admin_api.get_accesses_for_user(100).unwrap();
+        user_accesses.sort_by_key(|access| access.service_id);
+        assert_eq!(
+            user_accesses,
+            vec![deny_access.clone(), ServiceAccess::new(100, 201)]
+        );
+
+        assert_eq!(
+            admin_api.delete_access(100, 200).unwrap(),
+            Some(deny_access)
+        );
+        assert_eq!(admin_api.get_access(100, 200).unwrap(), None);
+    }
+
+    #[test]
+    fn adminapi_shutdown_session() {
+        let session = create_session(200);
+        let expected_proxy_key = session.proxy_key.clone();
+        let sessions = vec![session.clone()];
+        let (proxy_tasks_sender, _proxy_tasks_receiver) = mpsc::channel();
+
+        let mut service_proxy = MockGwSvcProxyVisitor::new();
+        service_proxy
+            .expect_get_service()
+            .return_const(Service::new(
+                200,
+                "Service200",
+                &Transport::TCP,
+                "localhost",
+                8200,
+            ));
+        service_proxy
+            .expect_get_proxy_sessions_for_user()
+            .with(predicate::eq(100))
+            .returning(move |_| sessions.clone());
+        service_proxy
+            .expect_shutdown_connection()
+            .withf(move |_, proxy_key| *proxy_key == expected_proxy_key)
+            .times(1)
+            .return_once(|_, _| Ok(()));
+        let service_proxy = Arc::new(Mutex::new(service_proxy));
+
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr
+            .expect_get_service_proxies()
+            .returning(move || vec![service_proxy.clone()]);
+        service_mgr
+            .expect_clone_proxy_tasks_sender()
+            .return_once(move || proxy_tasks_sender);
+
+        let admin_api = create_admin_api(service_mgr);
+
+        let sessions = admin_api.get_sessions_for_user(100);
+        assert_eq!(
+            sessions,
+            vec![Session {
+                handle: session.handle(),
+                service_id: 200,
+                client_addr: "addr1".to_string(),
+                gateway_addr: "addr2".to_string(),
+                started_at: session.started_at,
+            }]
+        );
+
+        admin_api.shutdown_session(100, &session.handle()).unwrap();
+
+        match admin_api.shutdown_session(100, "unknown") {
+            Err(err) => assert_eq!(err.get_code(), Some(response::CODE_NOT_FOUND)),
+            Ok(()) => panic!("Unexpected successful result"),
+        }
+    }
+
+    #[test]
+    fn adminapi_mutations_when_read_only() {
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_get_service_proxies().never();
+        service_mgr.expect_shutdown_connections().never();
+        let admin_api = create_admin_api_with_read_only(service_mgr, true);
+
+        let assert_read_only_err = |result: Result<(), AppError>| match result {
+            Ok(()) => panic!("Unexpected successful read-only mutation"),
+            Err(err) => assert_eq!(err.get_code(), Some(config::RESPCODE_0403_FORBIDDEN)),
+        };
+        assert_read_only_err(
+            admin_api
+                .put_user(User::new(100, "user100", Status::Active))
+                .map(|_| ()),
+        );
+        assert_read_only_err(admin_api.delete_user(100).map(|_| ()));
+        assert_read_only_err(
+            admin_api
+                .put_service(Service::new(
+                    200,
+                    "Service200",
+                    &Transport::TCP,
+                    "localhost",
+                    8200,
+                ))
+                .map(|_| ()),
+        );
+        assert_read_only_err(admin_api.delete_service(200).map(|_| ()));
+        assert_read_only_err(
+            admin_api
+                .put_access(ServiceAccess::new(100, 200))
+                .map(|_| ()),
+        );
+        assert_read_only_err(admin_api.delete_access(100, 200).map(|_| ()));
+        assert_read_only_err(admin_api.shutdown_session(100, "0123456789abcdef"));
+        assert_read_only_err(admin_api.shutdown_connections(Some(100), None));
+
+        assert!(admin_api.get_users().unwrap().is_empty());
+        assert!(admin_api.get_services().unwrap().is_empty());
+        assert_eq!(admin_api.get_access(100, 200).unwrap(), None);
+    }
+}
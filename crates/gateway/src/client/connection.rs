@@ -1,3 +1,4 @@
+use std::net::SocketAddr;
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
 
@@ -6,18 +7,34 @@ use pki_types::CertificateDer;
 
 use crate::client::controller::{ControlPlane, RequestProcessor};
 use crate::client::device::Device;
-use crate::config::{self, AppConfig};
-use crate::repository::access_repo::AccessRepository;
+use crate::config::{self, AccessDefault, AppConfig, UnrecognizedAlpnPolicy};
+use crate::repository::access_repo::{self, AccessRepository};
 use crate::repository::service_repo::ServiceRepository;
 use crate::repository::user_repo::UserRepository;
 use crate::service::manager::ServiceMgr;
+use trust0_common::conn_events::{self, ConnEvent, ConnEventType};
+use trust0_common::control::response;
 use trust0_common::crypto::alpn;
 use trust0_common::error::AppError;
-use trust0_common::logging::error;
+use trust0_common::logging::{error, info};
+use trust0_common::metrics::{
+    auth_denied_metric_name, user_connection_metric_name, METRIC_CONNECTIONS_CLOSED,
+    METRIC_CONNECTIONS_OPENED, METRIC_CONNECTION_PENDING_WRITE_BYTES,
+    METRIC_CONNECTION_QUEUED_EVENTS,
+};
+use trust0_common::model::service::{ClientAuth, Service};
 use trust0_common::model::user::{Status, User};
-use trust0_common::net::tls_server::conn_std::{self, TlsConnection};
+use trust0_common::net::cidr;
+use trust0_common::net::tls_server::conn_std::{
+    self, ConnectionVisitor, OutboundQueueDepth, TlsConnection, TlsSessionInfo,
+};
 use trust0_common::{crypto, target};
 
+/// User ID for connections authorized without a client certificate (see `ClientAuth::Optional`)
+pub const ANONYMOUS_USER_ID: u64 = 0;
+/// User name for connections authorized without a client certificate
+pub const ANONYMOUS_USER_NAME: &str = "anonymous";
+
 /// tls_server::std_conn::Connection strategy visitor pattern implementation
 pub struct ClientConnVisitor {
     app_config: Arc<AppConfig>,
@@ -26,10 +43,17 @@ pub struct ClientConnVisitor {
     service_repo: Arc<Mutex<dyn ServiceRepository>>,
     user_repo: Arc<Mutex<dyn UserRepository>>,
     event_channel_sender: Option<Sender<conn_std::ConnectionEvent>>,
+    outbound_queue_depth: Option<Arc<OutboundQueueDepth>>,
+    reported_outbound_queue_depth: Option<(usize, usize)>,
     request_processor: Option<Box<dyn RequestProcessor>>,
     device: Option<Device>,
     user: Option<User>,
+    tls_session_info: Option<TlsSessionInfo>,
+    service: Option<Service>,
     service_mgr: Arc<Mutex<dyn ServiceMgr>>,
+    client_addr: Option<SocketAddr>,
+    trace_id: String,
+    log_sampled: bool,
 }
 
 impl ClientConnVisitor {
@@ -47,36 +71,266 @@ impl ClientConnVisitor {
             service_repo,
             user_repo,
             event_channel_sender: None,
+            outbound_queue_depth: None,
+            reported_outbound_queue_depth: None,
             request_processor: None,
             device: None,
             user: None,
+            tls_session_info: None,
+            service: None,
             service_mgr,
+            client_addr: None,
+            trace_id: conn_events::create_trace_id(),
+            log_sampled: true,
         }
     }
 
-    /// Create device and user from peer certificate
+    /// Create device and user from peer certificate. Connection opened (or auth denial) metric and connection
+    /// events are emitted.
     pub fn process_authorization(
         &mut self,
         tls_conn: &dyn TlsConnection,
         service_id: Option<u64>,
     ) -> Result<alpn::Protocol, AppError> {
-        // parse certificate context details
-        let peer_certificates: Vec<CertificateDer<'static>> = tls_conn
-            .peer_certificates()
+        let result = self.authorize_connection(tls_conn, service_id);
+
+        match &result {
+            Ok(_) => {
+                self.app_config
+                    .metrics_sink
+                    .incr_counter(METRIC_CONNECTIONS_OPENED, 1);
+                self.emit_conn_event(
+                    ConnEventType::AuthAllowed,
+                    service_id,
+                    Some(response::CODE_OK),
+                    None,
+                );
+                self.emit_conn_event(
+                    ConnEventType::ConnectionOpened,
+                    service_id,
+                    Some(response::CODE_OK),
+                    None,
+                );
+            }
+            Err(err) => {
+                let code = err.get_code().unwrap_or(config::RESPCODE_0500_SYSTEM_ERROR);
+                self.app_config
+                    .metrics_sink
+                    .incr_counter(&auth_denied_metric_name(code), 1);
+                self.emit_conn_event(
+                    ConnEventType::AuthDenied,
+                    service_id,
+                    Some(code),
+                    Some(conn_events::denial_reason(err)),
+                );
+            }
+        }
+
+        result
+    }
+
+    /// Emit connection lifecycle event (to the configured connection events sink)
+    fn emit_conn_event(
+        &self,
+        event_type: ConnEventType,
+        service_id: Option<u64>,
+        response_code: Option<u16>,
+        reason: Option<String>,
+    ) {
+        let user_id = self.user.as_ref().map(|user| user.user_id).or(self
+            .device
+            .as_ref()
+            .map(|device| device.get_cert_access_context().user_id));
+
+        let mut conn_event = ConnEvent::new(
+            self.app_config.clock.as_ref(),
+            event_type,
+            user_id,
+            service_id.or(self.service.as_ref().map(|service| service.service_id)),
+            self.client_addr,
+            response_code,
+            &self.trace_id,
+        );
+        conn_event.set_reason(reason);
+        if let Some(tls_session_info) = self
+            .tls_session_info
+            .as_ref()
+            .filter(|_| event_type == ConnEventType::ConnectionOpened)
+        {
+            conn_event.set_tls_session_info(tls_session_info);
+        }
+
+        self.app_config.conn_event_sink.emit(&conn_event);
+    }
+
+    /// Validate connection's peer certificate (user), ALPN protocol and (if given) service access
+    fn authorize_connection(
+        &mut self,
+        tls_conn: &dyn TlsConnection,
+        service_id: Option<u64>,
+    ) -> Result<alpn::Protocol, AppError> {
+        // repositories are consistent (not mid-reload) for the duration of the authorization
+        let datasource_lock = self.app_config.datasource_lock.clone();
+        let _datasource_guard = datasource_lock.read().unwrap();
+
+        // deny connections while datasource is unavailable (fail-closed policy)
+        if !*self.app_config.datasource_available.lock().unwrap() {
+            return Err(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0500_SYSTEM_ERROR,
+                "Datasource is unavailable, pending successful reload".to_string(),
+            ));
+        }
+
+        // resolve user: by certificate, or (if client authentication is optional) anonymous
+        let user = match tls_conn.peer_certificates() {
+            Some(peer_certificates) if !peer_certificates.is_empty() => {
+                self.resolve_certificate_user(peer_certificates)?
+            }
+            _ if service_id.is_some() && (self.get_client_auth() == ClientAuth::Optional) => {
+                User::new(ANONYMOUS_USER_ID, ANONYMOUS_USER_NAME, Status::Active)
+            }
+            _ => {
+                return Err(AppError::GenWithCodeAndMsg(
+                    config::RESPCODE_0420_INVALID_CLIENT_CERTIFICATE,
+                    "Empty client certificate chain".to_string(),
+                ))
+            }
+        };
+        let user_id = user.user_id;
+
+        // deny (non-admin) connections while in maintenance mode
+        if let Some(maintenance_message) =
+            self.app_config.maintenance_message.lock().unwrap().as_ref()
+        {
+            if !self.app_config.admin_user_ids.contains(&user_id) {
+                return Err(AppError::GenWithCodeAndMsg(
+                    config::RESPCODE_0503_GATEWAY_MAINTENANCE,
+                    maintenance_message.clone(),
+                ));
+            }
+        }
+
+        // validate user byte quota
+        if self
+            .app_config
+            .user_byte_quotas
+            .lock()
+            .unwrap()
+            .is_quota_exceeded(user_id, user.byte_quota)
+        {
+            return Err(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0426_USER_QUOTA_EXCEEDED,
+                format!("User byte quota exceeded: uid={}", user_id),
+            ));
+        }
+
+        // determine (ALPN) connection protocol (control plane connections may fall back per the unrecognized ALPN policy)
+        let alpn_protocol = match service_id {
+            Some(_) => Self::parse_alpn_protocol(&tls_conn.alpn_protocol())?,
+            None => Self::resolve_alpn_protocol(
+                &tls_conn.alpn_protocol(),
+                self.app_config.unrecognized_alpn_policy,
+            )?,
+        };
+
+        // validate service (if necessary)
+        let mut access = None;
+
+        if service_id.is_some() {
+            let service_id = service_id.unwrap();
+
+            let invalid_service = match alpn_protocol {
+                alpn::Protocol::ControlPlane => true,
+                alpn::Protocol::Service(alpn_svc_id)
+                | alpn::Protocol::ReverseService(alpn_svc_id) => service_id != alpn_svc_id,
+                alpn::Protocol::ServiceName(ref alpn_svc_name) => {
+                    !self.service.as_ref().is_some_and(|service| {
+                        (service.service_id == service_id) && (service.name == *alpn_svc_name)
+                    })
+                }
+            };
+
+            if invalid_service {
+                return Err(AppError::GenWithCodeAndMsg(
+                    config::RESPCODE_0424_INVALID_ALPN_PROTOCOL,
+                    format!(
+                        "ALPN has wrong service ID: alpn={:?}, svc_id={}",
+                        alpn_protocol, service_id
+                    ),
+                ));
+            }
+
+            // anonymous user is only granted access by explicit access entries (never by the access default)
+            let access_default = match user_id {
+                ANONYMOUS_USER_ID => AccessDefault::Deny,
+                _ => self.app_config.access_default,
+            };
+
+            access = access_repo::resolve_access(
+                &*self.access_repo.lock().unwrap(),
+                access_default,
+                user_id,
+                service_id,
+            )?
             .ok_or(AppError::GenWithCodeAndMsg(
-                config::RESPCODE_0420_INVALID_CLIENT_CERTIFICATE,
-                "Empty client certificate chain".to_string(),
-            ))?
+                config::RESPCODE_0403_FORBIDDEN,
+                format!(
+                    "User is not authorized for service: uid={}, svc_id={}",
+                    user_id, service_id
+                ),
+            ))
+            .map(Some)?;
+
+            self.validate_client_network(tls_conn, user_id)?;
+            self.validate_service_reauth(user_id)?;
+            self.validate_service_rate(user_id)?;
+        }
+
+        self.user = Some(user);
+        self.tls_session_info = Some(tls_conn.session_info());
+
+        if let Some(access) = access.filter(|_| self.log_sampled) {
+            info(
+                &target!(),
+                &self.tag_log_msg(&format!(
+                    "Service access authorized: justification={:?}",
+                    &access.justification
+                )),
+            );
+        }
+
+        Ok(alpn_protocol)
+    }
+
+    /// Create device from peer certificate, and retrieve (active) certificate user from user repository
+    fn resolve_certificate_user(
+        &mut self,
+        peer_certificates: Vec<CertificateDer>,
+    ) -> Result<User, AppError> {
+        // parse certificate context details
+        let peer_certificates: Vec<CertificateDer<'static>> = peer_certificates
             .iter()
             .map(|c| crypto::x509::create_der_certificate(c.to_vec()))
             .collect();
 
         let device = Device::new(peer_certificates)?;
 
+        // validate certificate usage (if required)
+        if self.app_config.require_client_auth_eku && !device.is_client_auth_usage() {
+            return Err(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0428_INVALID_CERTIFICATE_USAGE,
+                format!(
+                    "Client certificate lacks client authentication usage: serial={}",
+                    device.get_cert_serial()
+                ),
+            ));
+        }
+
         // validate user
         let user_id = device.get_cert_access_context().user_id;
+        self.device = Some(device);
 
-        if user_id == 0 {
+        if user_id == ANONYMOUS_USER_ID {
             return Err(AppError::GenWithCodeAndMsg(
                 config::RESPCODE_0420_INVALID_CLIENT_CERTIFICATE,
                 "Invalid certificate user identity".to_string(),
@@ -112,49 +366,115 @@ impl ClientConnVisitor {
             ));
         }
 
-        // determine (ALPN) connection protocol
-        let alpn_protocol = Self::parse_alpn_protocol(&tls_conn.alpn_protocol())?;
+        Ok(user)
+    }
 
-        // validate service (if necessary)
-        if service_id.is_some() {
-            let service_id = service_id.unwrap();
+    /// Client certificate authentication requirement for the connection: the (tagged) service's override (if any),
+    /// otherwise the gateway default
+    fn get_client_auth(&self) -> ClientAuth {
+        self.service
+            .as_ref()
+            .and_then(|service| service.client_auth)
+            .unwrap_or(self.app_config.tls_server_config_builder.client_auth)
+    }
 
-            let invalid_service = match alpn_protocol {
-                alpn::Protocol::ControlPlane => true,
-                alpn::Protocol::Service(alpn_svc_id) => service_id != alpn_svc_id,
-            };
+    /// Validate connection's source address against the (tagged) service's allowed client networks (if any)
+    fn validate_client_network(
+        &self,
+        tls_conn: &dyn TlsConnection,
+        user_id: u64,
+    ) -> Result<(), AppError> {
+        let service = match &self.service {
+            Some(service) if !service.allowed_client_cidrs.is_empty() => service,
+            _ => return Ok(()),
+        };
 
-            if invalid_service {
-                return Err(AppError::GenWithCodeAndMsg(
-                    config::RESPCODE_0424_INVALID_ALPN_PROTOCOL,
-                    format!(
-                        "ALPN has wrong service ID: alpn={:?}, svc_id={}",
-                        alpn_protocol, service_id
-                    ),
-                ));
-            }
+        let client_addr = tls_conn.peer_addr().ok_or(AppError::GenWithCodeAndMsg(
+            config::RESPCODE_0403_FORBIDDEN,
+            format!(
+                "Unknown client address for restricted service: uid={}, svc_id={}",
+                user_id, service.service_id
+            ),
+        ))?;
 
-            if self
-                .access_repo
-                .lock()
-                .unwrap()
-                .get(user_id, service_id)?
-                .is_none()
-            {
-                return Err(AppError::GenWithCodeAndMsg(
+        let allowed = cidr::contains_addr(&service.allowed_client_cidrs, &client_addr.ip())
+            .map_err(|err| {
+                AppError::GenWithCodeAndMsg(
                     config::RESPCODE_0403_FORBIDDEN,
                     format!(
-                        "User is not authorized for service: uid={}, svc_id={}",
-                        user_id, service_id
+                        "Invalid service allowed client networks: svc_id={}, err={:?}",
+                        service.service_id, err
                     ),
-                ));
-            }
+                )
+            })?;
+
+        if !allowed {
+            return Err(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0403_FORBIDDEN,
+                format!(
+                    "Client address is not allowed for service: uid={}, svc_id={}, addr={}",
+                    user_id,
+                    service.service_id,
+                    client_addr.ip()
+                ),
+            ));
         }
 
-        self.device = Some(device);
-        self.user = Some(user);
+        Ok(())
+    }
 
-        Ok(alpn_protocol)
+    /// Validate user has (re-)authorized for the (tagged) service within its re-authorization interval (if any)
+    fn validate_service_reauth(&self, user_id: u64) -> Result<(), AppError> {
+        let service = match &self.service {
+            Some(service) => service,
+            None => return Ok(()),
+        };
+
+        if self
+            .app_config
+            .service_auth_times
+            .lock()
+            .unwrap()
+            .is_reauth_required(user_id, service, self.app_config.clock.now())
+        {
+            return Err(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0427_REAUTH_REQUIRED,
+                format!(
+                    "Service re-authorization required: uid={}, svc_id={}",
+                    user_id, service.service_id
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Validate user's new connection is within the (tagged) service's max QPS (if any)
+    fn validate_service_rate(&self, user_id: u64) -> Result<(), AppError> {
+        let service = match &self.service {
+            Some(service) => service,
+            None => return Ok(()),
+        };
+
+        if !self
+            .app_config
+            .service_rate_limits
+            .lock()
+            .unwrap()
+            .try_acquire(user_id, service, self.app_config.clock.now())
+        {
+            return Err(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0429_SERVICE_RATE_EXCEEDED,
+                format!(
+                    "Service request rate exceeded: uid={}, svc_id={}, max_qps={}",
+                    user_id,
+                    service.service_id,
+                    service.max_qps.unwrap_or_default()
+                ),
+            ));
+        }
+
+        Ok(())
     }
 
     /// User accessor
@@ -162,6 +482,26 @@ impl ClientConnVisitor {
         &self.user
     }
 
+    /// Device accessor
+    pub fn get_device(&self) -> &Option<Device> {
+        &self.device
+    }
+
+    /// Tag connection with the service it was dispatched to (included in the connection's log lines)
+    pub fn set_service(&mut self, service: &Service) {
+        self.service = Some(service.clone());
+    }
+
+    /// Set whether the connection's lifecycle is logged (see `logging::LogSampler`)
+    pub fn set_log_sampled(&mut self, log_sampled: bool) {
+        self.log_sampled = log_sampled;
+    }
+
+    /// Set connection's client (peer) address (included in the connection's lifecycle events)
+    pub fn set_client_addr(&mut self, client_addr: Option<SocketAddr>) {
+        self.client_addr = client_addr;
+    }
+
     /// Parse TLS ALPN protocol
     pub fn parse_alpn_protocol(
         protocol_name: &Option<Vec<u8>>,
@@ -174,24 +514,44 @@ impl ClientConnVisitor {
 
             Some(protocol_name_bytes) => {
                 let protocol_name = String::from_utf8_lossy(protocol_name_bytes);
-                match alpn::Protocol::parse(protocol_name.as_ref()) {
-                    None => Err(AppError::GenWithCodeAndMsg(
+                alpn::Protocol::try_parse(protocol_name.as_ref()).map_err(|err| {
+                    AppError::GenWithCodeAndMsg(
                         config::RESPCODE_0424_INVALID_ALPN_PROTOCOL,
-                        format!("Invalid ALPN protocol: proto={}", protocol_name.as_ref()),
-                    )),
-                    Some(alpn_protocol) => Ok(alpn_protocol),
-                }
+                        format!(
+                            "Invalid ALPN protocol: proto={}, err={:?}",
+                            protocol_name.as_ref(),
+                            err
+                        ),
+                    )
+                })
+            }
+        }
+    }
+
+    /// Parse TLS ALPN protocol, applying the unrecognized ALPN policy for missing/unrecognizable protocols
+    pub fn resolve_alpn_protocol(
+        protocol_name: &Option<Vec<u8>>,
+        unrecognized_alpn_policy: UnrecognizedAlpnPolicy,
+    ) -> Result<alpn::Protocol, AppError> {
+        match Self::parse_alpn_protocol(protocol_name) {
+            Err(_) if unrecognized_alpn_policy == UnrecognizedAlpnPolicy::ControlPlane => {
+                Ok(alpn::Protocol::ControlPlane)
             }
+            result => result,
         }
     }
 }
 
 impl conn_std::ConnectionVisitor for ClientConnVisitor {
+    fn set_outbound_queue_depth(&mut self, outbound_queue_depth: Arc<OutboundQueueDepth>) {
+        self.outbound_queue_depth = Some(outbound_queue_depth);
+    }
+
     fn set_event_channel_sender(
         &mut self,
         event_channel_sender: Sender<conn_std::ConnectionEvent>,
     ) -> Result<(), AppError> {
-        self.request_processor = Some(Box::new(ControlPlane::new(
+        let mut control_plane = ControlPlane::new(
             self.app_config.clone(),
             self.access_repo.clone(),
             self.service_repo.clone(),
@@ -199,7 +559,12 @@ impl conn_std::ConnectionVisitor for ClientConnVisitor {
             event_channel_sender.clone(),
             self.device.as_ref().unwrap_or(&Device::default()).clone(),
             self.user.as_ref().unwrap_or(&User::default()).clone(),
-        )?));
+            self.tls_session_info.clone(),
+        )?;
+        if let Some(outbound_queue_depth) = &self.outbound_queue_depth {
+            control_plane.set_outbound_queue_depth(outbound_queue_depth.clone());
+        }
+        self.request_processor = Some(Box::new(control_plane));
 
         self.event_channel_sender = Some(event_channel_sender);
 
@@ -229,11 +594,53 @@ impl conn_std::ConnectionVisitor for ClientConnVisitor {
         Ok(())
     }
 
+    fn get_pending_read_bytes(&self) -> usize {
+        // Requests are processed on read, so unflushed responses stand for read content not yet consumed
+        self.outbound_queue_depth
+            .as_ref()
+            .map_or(0, |outbound_queue_depth| {
+                outbound_queue_depth.get_pending_write_bytes()
+            })
+    }
+
+    fn on_polling_cycle(&mut self) -> Result<(), AppError> {
+        // Report (changes to) the control plane connection's outbound queue depth
+        if let (Some(outbound_queue_depth), Some(user)) = (&self.outbound_queue_depth, &self.user) {
+            let current_outbound_queue_depth = (
+                outbound_queue_depth.get_pending_write_bytes(),
+                outbound_queue_depth.get_queued_events(),
+            );
+
+            if Some(current_outbound_queue_depth) != self.reported_outbound_queue_depth {
+                self.app_config.metrics_sink.set_gauge(
+                    &user_connection_metric_name(
+                        METRIC_CONNECTION_PENDING_WRITE_BYTES,
+                        user.user_id,
+                    ),
+                    current_outbound_queue_depth.0 as i64,
+                );
+                self.app_config.metrics_sink.set_gauge(
+                    &user_connection_metric_name(METRIC_CONNECTION_QUEUED_EVENTS, user.user_id),
+                    current_outbound_queue_depth.1 as i64,
+                );
+                self.reported_outbound_queue_depth = Some(current_outbound_queue_depth);
+            }
+        }
+
+        Ok(())
+    }
+
     fn on_shutdown(&mut self) -> Result<(), AppError> {
+        self.app_config
+            .metrics_sink
+            .incr_counter(METRIC_CONNECTIONS_CLOSED, 1);
+        self.emit_conn_event(ConnEventType::ConnectionClosed, None, None, None);
+
         self.service_mgr
             .lock()
             .unwrap()
             .shutdown_connections(Some(self.user.as_ref().unwrap().user_id), None)
+            .map_err(|errs| errs.into())
     }
 
     fn send_error_response(&mut self, err: &AppError) {
@@ -248,6 +655,11 @@ impl conn_std::ConnectionVisitor for ClientConnVisitor {
         };
 
         let msg = match err {
+            AppError::GenWithCodeAndMsg(code, banner)
+                if *code == config::RESPCODE_0503_GATEWAY_MAINTENANCE =>
+            {
+                format!("{}: {}", resp_msgs.get(code).unwrap(), banner)
+            }
             AppError::GenWithCode(code) => resp_msgs
                 .get(code)
                 .map_or(unknown_msg(code), |m| m.to_string()),
@@ -275,13 +687,28 @@ impl conn_std::ConnectionVisitor for ClientConnVisitor {
 
             error(
                 &target!(),
-                &format!(
+                &self.tag_log_msg(&format!(
                     "Error sending error message response: err={:?}, respmsg={}",
                     err, msg
-                ),
+                )),
             );
         }
     }
+
+    fn get_log_context(&self) -> Option<String> {
+        let user_id = self
+            .user
+            .as_ref()
+            .map_or("(NA)".to_string(), |user| user.user_id.to_string());
+
+        match &self.service {
+            Some(service) => Some(format!(
+                "uid={}, svc_id={}, svc_name={}",
+                user_id, service.service_id, service.name
+            )),
+            None => Some(format!("uid={}, svc_id=(NA)", user_id)),
+        }
+    }
 }
 
 unsafe impl Send for ClientConnVisitor {}
@@ -294,14 +721,28 @@ mod tests {
     use crate::repository::service_repo::tests::MockServiceRepo;
     use crate::repository::user_repo::tests::MockUserRepo;
     use crate::service::manager::GatewayServiceMgr;
-    use crate::testutils::MockTlsSvrConn;
+    use crate::testutils::{
+        create_handshaked_tls_conn_pair, CapturingConnEventSink, CapturingMetricsSink,
+        MockTlsSvrConn,
+    };
     use mockall::predicate;
+    use std::io::Write;
+    use std::net::SocketAddr;
     use std::path::PathBuf;
+    use std::str::FromStr;
     use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+    use trust0_common::clock::{Clock, ManualClock};
     use trust0_common::crypto::file::load_certificates;
+    use trust0_common::metrics::{MetricsSink, NoOpMetricsSink};
     use trust0_common::model::access::ServiceAccess;
+    use trust0_common::model::service::Transport;
+    use trust0_common::net::tls_server::conn_std::ReadWatermarks;
     use trust0_common::proxy::event::ProxyEvent;
     use trust0_common::proxy::executor::ProxyExecutorEvent;
+    use trust0_common::proxy::proxy_base::ProxyType;
+    use trust0_common::proxy::proxy_key::ProxyKey;
 
     const CERTFILE_CLIENT_UID100_PATHPARTS: [&str; 3] = [
         env!("CARGO_MANIFEST_DIR"),
@@ -310,6 +751,8 @@ mod tests {
     ];
     const CERTFILE_NON_CLIENT_PATHPARTS: [&str; 3] =
         [env!("CARGO_MANIFEST_DIR"), "testdata", "non-client.crt.pem"];
+    const CERTFILE_GATEWAY_PATHPARTS: [&str; 3] =
+        [env!("CARGO_MANIFEST_DIR"), "testdata", "gateway.crt.pem"];
 
     // ClientConnVisitor tests
     // =======================
@@ -319,11 +762,24 @@ mod tests {
         service_repo: Arc<Mutex<dyn ServiceRepository>>,
         access_repo: Arc<Mutex<dyn AccessRepository>>,
     ) -> Result<ClientConnVisitor, AppError> {
-        let app_config = Arc::new(config::tests::create_app_config_with_repos(
+        create_cliconnvis_with_metrics_sink(
             user_repo,
             service_repo,
             access_repo,
-        )?);
+            Arc::new(NoOpMetricsSink),
+        )
+    }
+
+    fn create_cliconnvis_with_metrics_sink(
+        user_repo: Arc<Mutex<dyn UserRepository>>,
+        service_repo: Arc<Mutex<dyn ServiceRepository>>,
+        access_repo: Arc<Mutex<dyn AccessRepository>>,
+        metrics_sink: Arc<dyn MetricsSink>,
+    ) -> Result<ClientConnVisitor, AppError> {
+        let mut app_config =
+            config::tests::create_app_config_with_repos(user_repo, service_repo, access_repo)?;
+        app_config.metrics_sink = metrics_sink;
+        let app_config = Arc::new(app_config);
         let proxy_tasks_sender: Sender<ProxyExecutorEvent> = mpsc::channel().0;
         let proxy_events_sender: Sender<ProxyEvent> = mpsc::channel().0;
         let service_mgr = Arc::new(Mutex::new(GatewayServiceMgr::new(
@@ -350,6 +806,10 @@ mod tests {
             .expect_alpn_protocol()
             .times(1)
             .return_once(move || Some(alpn_proto));
+        tls_conn
+            .expect_session_info()
+            .times(1)
+            .return_once(TlsSessionInfo::default);
 
         let mut user_repo = MockUserRepo::new();
         user_repo
@@ -361,6 +821,7 @@ mod tests {
                     user_id: 100,
                     name: "".to_string(),
                     status: Status::Active,
+                    byte_quota: None,
                 }))
             });
         let mut access_repo = MockAccessRepo::new();
@@ -375,10 +836,12 @@ mod tests {
         )?;
 
         let result = cli_conn_visitor.process_authorization(&tls_conn, None);
-        if let Ok(protocol) = &result {
-            if let alpn::Protocol::ControlPlane = protocol {
-                return Ok(());
-            }
+        assert_eq!(
+            cli_conn_visitor.tls_session_info,
+            Some(TlsSessionInfo::default())
+        );
+        if let Ok(alpn::Protocol::ControlPlane) = &result {
+            return Ok(());
         }
 
         panic!("Unexpected result: val={:?}", &result);
@@ -402,6 +865,10 @@ mod tests {
             .expect_alpn_protocol()
             .times(1)
             .return_once(move || Some(alpn_proto));
+        tls_conn
+            .expect_session_info()
+            .times(1)
+            .return_once(TlsSessionInfo::default);
 
         let mut user_repo = MockUserRepo::new();
         user_repo
@@ -413,6 +880,7 @@ mod tests {
                     user_id: 100,
                     name: "".to_string(),
                     status: Status::Active,
+                    byte_quota: None,
                 }))
             });
         let mut access_repo = MockAccessRepo::new();
@@ -424,6 +892,8 @@ mod tests {
                 Ok(Some(ServiceAccess {
                     user_id: 100,
                     service_id: 200,
+                    justification: None,
+                    deny: false,
                 }))
             });
         let mut service_repo = MockServiceRepo::new();
@@ -436,20 +906,21 @@ mod tests {
         )?;
 
         let result = cli_conn_visitor.process_authorization(&tls_conn, Some(200));
-        if let Ok(protocol) = &result {
-            if let alpn::Protocol::Service(service_id) = protocol {
-                if *service_id == 200 {
-                    return Ok(());
-                }
-            }
+        assert_eq!(
+            cli_conn_visitor.tls_session_info,
+            Some(TlsSessionInfo::default())
+        );
+        if let Ok(alpn::Protocol::Service(200)) = &result {
+            return Ok(());
         }
 
         panic!("Unexpected result: val={:?}", &result);
     }
 
-    #[test]
-    fn cliconnvis_process_authorization_fn_when_wrongsvc_and_gooduser_and_goodproto(
-    ) -> Result<(), AppError> {
+    fn create_cliconnvis_for_reauth_service(
+        clock: Arc<ManualClock>,
+        conn_event_sink: Arc<CapturingConnEventSink>,
+    ) -> Result<(ClientConnVisitor, MockTlsSvrConn), AppError> {
         let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
         let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
         let alpn_proto = alpn::Protocol::create_service_protocol(200)
@@ -459,60 +930,179 @@ mod tests {
         let mut tls_conn = MockTlsSvrConn::new();
         tls_conn
             .expect_peer_certificates()
-            .times(1)
-            .return_once(move || Some(peer_certs));
+            .returning(move || Some(peer_certs.clone()));
         tls_conn
             .expect_alpn_protocol()
-            .times(1)
-            .return_once(move || Some(alpn_proto));
+            .returning(move || Some(alpn_proto.clone()));
+        tls_conn
+            .expect_session_info()
+            .returning(TlsSessionInfo::default);
 
         let mut user_repo = MockUserRepo::new();
         user_repo
             .expect_get()
             .with(predicate::eq(100))
-            .times(1)
-            .return_once(move |_| {
+            .returning(|_| {
                 Ok(Some(User {
                     user_id: 100,
                     name: "".to_string(),
                     status: Status::Active,
+                    byte_quota: None,
                 }))
             });
         let mut access_repo = MockAccessRepo::new();
         access_repo
             .expect_get()
             .with(predicate::eq(100), predicate::eq(200))
-            .never();
-        let mut service_repo = MockServiceRepo::new();
-        service_repo.expect_get().never();
+            .returning(|_, _| {
+                Ok(Some(ServiceAccess {
+                    user_id: 100,
+                    service_id: 200,
+                    justification: None,
+                    deny: false,
+                }))
+            });
 
-        let mut cli_conn_visitor = create_cliconnvis(
+        let mut app_config = config::tests::create_app_config_with_repos(
             Arc::new(Mutex::new(user_repo)),
-            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
             Arc::new(Mutex::new(access_repo)),
         )?;
+        app_config.clock = clock;
+        app_config.conn_event_sink = conn_event_sink;
+        let app_config = Arc::new(app_config);
+        let service_mgr = Arc::new(Mutex::new(GatewayServiceMgr::new(
+            app_config.clone(),
+            mpsc::channel().0,
+            mpsc::channel().0,
+        )));
 
-        let result = cli_conn_visitor.process_authorization(&tls_conn, Some(201));
-        if let Err(err) = &result {
-            if let AppError::GenWithCodeAndMsg(code, _) = err {
-                if *code == config::RESPCODE_0424_INVALID_ALPN_PROTOCOL {
-                    return Ok(());
-                }
+        let mut cli_conn_visitor = ClientConnVisitor::new(app_config, service_mgr);
+        let mut service = Service::new(200, "Service200", &Transport::TCP, "localhost", 8200);
+        service.reauth_interval = Some(3600);
+        cli_conn_visitor.set_service(&service);
+
+        Ok((cli_conn_visitor, tls_conn))
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_exceeding_service_max_qps() -> Result<(), AppError>
+    {
+        let clock = Arc::new(ManualClock::new());
+        let (mut cli_conn_visitor, tls_conn) = create_cliconnvis_for_reauth_service(
+            clock.clone(),
+            Arc::new(CapturingConnEventSink::default()),
+        )?;
+        let mut service = Service::new(200, "Service200", &Transport::TCP, "localhost", 8200);
+        service.max_qps = Some(2);
+        cli_conn_visitor.set_service(&service);
+
+        for _ in 0..2 {
+            if let Err(err) = cli_conn_visitor.process_authorization(&tls_conn, Some(200)) {
+                panic!("Unexpected result: err={:?}", err);
             }
         }
 
-        panic!("Unexpected result: val={:?}", &result);
+        match cli_conn_visitor.process_authorization(&tls_conn, Some(200)) {
+            Err(err) => assert_eq!(
+                err.get_code(),
+                Some(config::RESPCODE_0429_SERVICE_RATE_EXCEEDED)
+            ),
+            Ok(protocol) => panic!("Unexpected successful result: proto={:?}", protocol),
+        }
+
+        clock.advance(Duration::from_secs(1));
+
+        if let Err(err) = cli_conn_visitor.process_authorization(&tls_conn, Some(200)) {
+            panic!("Unexpected result: err={:?}", err);
+        }
+
+        Ok(())
     }
 
     #[test]
-    fn cliconnvis_process_authorization_fn_when_goodsvc_and_gooduser_and_wrongproto(
+    fn cliconnvis_process_authorization_fn_when_crossing_service_reauth_interval(
     ) -> Result<(), AppError> {
-        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
-        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
-        let alpn_proto = alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec();
+        let clock = Arc::new(ManualClock::new());
+        let (mut cli_conn_visitor, tls_conn) = create_cliconnvis_for_reauth_service(
+            clock.clone(),
+            Arc::new(CapturingConnEventSink::default()),
+        )?;
+        let service_auth_times = cli_conn_visitor.app_config.service_auth_times.clone();
 
-        let mut tls_conn = MockTlsSvrConn::new();
-        tls_conn
+        service_auth_times
+            .lock()
+            .unwrap()
+            .record_auth(100, 200, clock.now());
+        clock.advance(Duration::from_secs(3599));
+
+        if let Err(err) = cli_conn_visitor.process_authorization(&tls_conn, Some(200)) {
+            panic!("Unexpected result: err={:?}", err);
+        }
+
+        clock.advance(Duration::from_secs(1));
+
+        match cli_conn_visitor.process_authorization(&tls_conn, Some(200)) {
+            Err(err) => assert_eq!(err.get_code(), Some(config::RESPCODE_0427_REAUTH_REQUIRED)),
+            Ok(protocol) => panic!("Unexpected successful result: proto={:?}", protocol),
+        }
+
+        service_auth_times
+            .lock()
+            .unwrap()
+            .record_auth(100, 200, clock.now());
+
+        if let Err(err) = cli_conn_visitor.process_authorization(&tls_conn, Some(200)) {
+            panic!("Unexpected result: err={:?}", err);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_wall_clock_jumps_backward() -> Result<(), AppError>
+    {
+        let clock = Arc::new(ManualClock::new());
+        let conn_event_sink = Arc::new(CapturingConnEventSink::default());
+        let (mut cli_conn_visitor, tls_conn) =
+            create_cliconnvis_for_reauth_service(clock.clone(), conn_event_sink.clone())?;
+
+        cli_conn_visitor
+            .app_config
+            .service_auth_times
+            .lock()
+            .unwrap()
+            .record_auth(100, 200, clock.now());
+        clock.advance(Duration::from_secs(600));
+        clock.set_system_time(
+            clock
+                .system_time()
+                .checked_sub(Duration::from_secs(86400))
+                .unwrap(),
+        );
+
+        if let Err(err) = cli_conn_visitor.process_authorization(&tls_conn, Some(200)) {
+            panic!("Unexpected result: err={:?}", err);
+        }
+
+        let conn_events = parse_conn_events(&conn_event_sink);
+        assert_eq!(conn_events.len(), 2);
+        assert_eq!(conn_events[0].timestamp, clock.unix_millis());
+
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_service_tagged_logs_carry_service(
+    ) -> Result<(), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+        let alpn_proto = alpn::Protocol::create_service_protocol(200)
+            .as_bytes()
+            .to_vec();
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
             .expect_peer_certificates()
             .times(1)
             .return_once(move || Some(peer_certs));
@@ -520,99 +1110,174 @@ mod tests {
             .expect_alpn_protocol()
             .times(1)
             .return_once(move || Some(alpn_proto));
+        tls_conn
+            .expect_session_info()
+            .times(1)
+            .return_once(TlsSessionInfo::default);
 
         let mut user_repo = MockUserRepo::new();
         user_repo
             .expect_get()
             .with(predicate::eq(100))
             .times(1)
-            .return_once(move |_| {
-                Ok(Some(User {
-                    user_id: 100,
-                    name: "".to_string(),
-                    status: Status::Active,
-                }))
-            });
+            .return_once(move |_| Ok(Some(User::new(100, "user100", Status::Active))));
         let mut access_repo = MockAccessRepo::new();
-        access_repo.expect_get().never();
-        let mut service_repo = MockServiceRepo::new();
-        service_repo.expect_get().never();
+        access_repo
+            .expect_get()
+            .with(predicate::eq(100), predicate::eq(200))
+            .times(1)
+            .return_once(move |_, _| Ok(Some(ServiceAccess::new(100, 200))));
 
         let mut cli_conn_visitor = create_cliconnvis(
             Arc::new(Mutex::new(user_repo)),
-            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
             Arc::new(Mutex::new(access_repo)),
         )?;
 
-        let result = cli_conn_visitor.process_authorization(&tls_conn, Some(200));
-        if let Err(err) = &result {
-            if let AppError::GenWithCodeAndMsg(code, _) = err {
-                if *code == config::RESPCODE_0424_INVALID_ALPN_PROTOCOL {
-                    return Ok(());
-                }
-            }
-        }
+        assert_eq!(
+            cli_conn_visitor.tag_log_msg("Connection closed"),
+            "[uid=(NA), svc_id=(NA)] Connection closed"
+        );
 
-        panic!("Unexpected result: val={:?}", &result);
+        cli_conn_visitor.set_service(&Service::new(
+            200,
+            "Service200",
+            &Transport::TCP,
+            "localhost",
+            8200,
+        ));
+        cli_conn_visitor.process_authorization(&tls_conn, Some(200))?;
+
+        assert_eq!(
+            cli_conn_visitor.tag_log_msg("Connection closed"),
+            "[uid=100, svc_id=200, svc_name=Service200] Connection closed"
+        );
+
+        Ok(())
     }
 
-    #[test]
-    fn cliconnvis_process_authorization_fn_when_nosvc_and_badcert() -> Result<(), AppError> {
-        let peer_certs_file: PathBuf = CERTFILE_NON_CLIENT_PATHPARTS.iter().collect();
+    fn create_cliconnvis_for_restricted_service(
+        client_addr: &str,
+    ) -> Result<(ClientConnVisitor, MockTlsSvrConn), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
         let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+        let alpn_proto = alpn::Protocol::create_service_protocol(200)
+            .as_bytes()
+            .to_vec();
+        let client_addr = SocketAddr::from_str(client_addr).unwrap();
 
         let mut tls_conn = MockTlsSvrConn::new();
         tls_conn
             .expect_peer_certificates()
             .times(1)
             .return_once(move || Some(peer_certs));
-        tls_conn.expect_alpn_protocol().never();
+        tls_conn
+            .expect_alpn_protocol()
+            .times(1)
+            .return_once(move || Some(alpn_proto));
+        tls_conn
+            .expect_peer_addr()
+            .times(1)
+            .return_once(move || Some(client_addr));
+        tls_conn
+            .expect_session_info()
+            .return_once(TlsSessionInfo::default);
 
         let mut user_repo = MockUserRepo::new();
-        user_repo.expect_get().never();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| Ok(Some(User::new(100, "user100", Status::Active))));
         let mut access_repo = MockAccessRepo::new();
-        access_repo.expect_get().never();
-        let mut service_repo = MockServiceRepo::new();
-        service_repo.expect_get().never();
+        access_repo
+            .expect_get()
+            .with(predicate::eq(100), predicate::eq(200))
+            .times(1)
+            .return_once(move |_, _| Ok(Some(ServiceAccess::new(100, 200))));
 
         let mut cli_conn_visitor = create_cliconnvis(
             Arc::new(Mutex::new(user_repo)),
-            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
             Arc::new(Mutex::new(access_repo)),
         )?;
 
+        let mut service = Service::new(200, "Service200", &Transport::TCP, "localhost", 8200);
+        service.allowed_client_cidrs = vec!["10.1.0.0/16".to_string(), "fd00::/8".to_string()];
+        cli_conn_visitor.set_service(&service);
+
+        Ok((cli_conn_visitor, tls_conn))
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_restricted_svc_and_client_in_range(
+    ) -> Result<(), AppError> {
+        let (mut cli_conn_visitor, tls_conn) =
+            create_cliconnvis_for_restricted_service("10.1.20.30:40000")?;
+
         let result = cli_conn_visitor.process_authorization(&tls_conn, Some(200));
-        if let Err(err) = &result {
-            if let AppError::GenWithCodeAndMsg(code, _) = err {
-                if *code == config::RESPCODE_0420_INVALID_CLIENT_CERTIFICATE {
-                    return Ok(());
-                }
-            }
+
+        if let Ok(alpn::Protocol::Service(200)) = &result {
+            return Ok(());
         }
 
         panic!("Unexpected result: val={:?}", &result);
     }
 
     #[test]
-    fn cliconnvis_process_authorization_fn_when_nosvc_and_baduid() -> Result<(), AppError> {
+    fn cliconnvis_process_authorization_fn_when_restricted_svc_and_client_out_of_range(
+    ) -> Result<(), AppError> {
+        let (mut cli_conn_visitor, tls_conn) =
+            create_cliconnvis_for_restricted_service("10.2.20.30:40000")?;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, Some(200));
+
+        match &result {
+            Err(err) => assert_eq!(err.get_code(), Some(config::RESPCODE_0403_FORBIDDEN)),
+            Ok(_) => panic!("Unexpected successful result: val={:?}", &result),
+        }
+        assert!(cli_conn_visitor.get_user().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_wrongsvc_and_gooduser_and_goodproto(
+    ) -> Result<(), AppError> {
         let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
         let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+        let alpn_proto = alpn::Protocol::create_service_protocol(200)
+            .as_bytes()
+            .to_vec();
 
         let mut tls_conn = MockTlsSvrConn::new();
         tls_conn
             .expect_peer_certificates()
             .times(1)
             .return_once(move || Some(peer_certs));
-        tls_conn.expect_alpn_protocol().never();
+        tls_conn
+            .expect_alpn_protocol()
+            .times(1)
+            .return_once(move || Some(alpn_proto));
 
         let mut user_repo = MockUserRepo::new();
         user_repo
             .expect_get()
             .with(predicate::eq(100))
             .times(1)
-            .return_once(move |_| Ok(None));
+            .return_once(move |_| {
+                Ok(Some(User {
+                    user_id: 100,
+                    name: "".to_string(),
+                    status: Status::Active,
+                    byte_quota: None,
+                }))
+            });
         let mut access_repo = MockAccessRepo::new();
-        access_repo.expect_get().never();
+        access_repo
+            .expect_get()
+            .with(predicate::eq(100), predicate::eq(200))
+            .never();
         let mut service_repo = MockServiceRepo::new();
         service_repo.expect_get().never();
 
@@ -622,12 +1287,10 @@ mod tests {
             Arc::new(Mutex::new(access_repo)),
         )?;
 
-        let result = cli_conn_visitor.process_authorization(&tls_conn, None);
-        if let Err(err) = &result {
-            if let AppError::GenWithCodeAndMsg(code, _) = err {
-                if *code == config::RESPCODE_0421_UNKNOWN_USER {
-                    return Ok(());
-                }
+        let result = cli_conn_visitor.process_authorization(&tls_conn, Some(201));
+        if let Err(AppError::GenWithCodeAndMsg(code, _)) = &result {
+            if *code == config::RESPCODE_0424_INVALID_ALPN_PROTOCOL {
+                return Ok(());
             }
         }
 
@@ -635,16 +1298,21 @@ mod tests {
     }
 
     #[test]
-    fn cliconnvis_process_authorization_fn_when_nosvc_and_inactiveuser() -> Result<(), AppError> {
+    fn cliconnvis_process_authorization_fn_when_goodsvc_and_gooduser_and_wrongproto(
+    ) -> Result<(), AppError> {
         let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
         let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+        let alpn_proto = alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec();
 
         let mut tls_conn = MockTlsSvrConn::new();
         tls_conn
             .expect_peer_certificates()
             .times(1)
             .return_once(move || Some(peer_certs));
-        tls_conn.expect_alpn_protocol().never();
+        tls_conn
+            .expect_alpn_protocol()
+            .times(1)
+            .return_once(move || Some(alpn_proto));
 
         let mut user_repo = MockUserRepo::new();
         user_repo
@@ -655,7 +1323,8 @@ mod tests {
                 Ok(Some(User {
                     user_id: 100,
                     name: "".to_string(),
-                    status: Status::Inactive,
+                    status: Status::Active,
+                    byte_quota: None,
                 }))
             });
         let mut access_repo = MockAccessRepo::new();
@@ -669,64 +1338,1249 @@ mod tests {
             Arc::new(Mutex::new(access_repo)),
         )?;
 
-        let result = cli_conn_visitor.process_authorization(&tls_conn, None);
-        if let Err(err) = &result {
-            if let AppError::GenWithCodeAndMsg(code, _) = err {
-                if *code == config::RESPCODE_0422_INACTIVE_USER {
-                    return Ok(());
-                }
+        let result = cli_conn_visitor.process_authorization(&tls_conn, Some(200));
+        if let Err(AppError::GenWithCodeAndMsg(code, _)) = &result {
+            if *code == config::RESPCODE_0424_INVALID_ALPN_PROTOCOL {
+                return Ok(());
             }
         }
 
         panic!("Unexpected result: val={:?}", &result);
     }
 
-    // ClientConnVisitor::parse_alpn_protocol tests
+    fn create_cliconnvis_for_client_auth(
+        client_auth: ClientAuth,
+        service_client_auth: Option<ClientAuth>,
+        access_repo: MockAccessRepo,
+    ) -> Result<(ClientConnVisitor, MockTlsSvrConn), AppError> {
+        create_cliconnvis_for_client_auth_and_access_default(
+            client_auth,
+            service_client_auth,
+            access_repo,
+            AccessDefault::Deny,
+        )
+    }
 
-    #[test]
-    fn cliconnvis_parse_alpn_protocol_fn_when_invalid_value() {
-        assert!(
-            ClientConnVisitor::parse_alpn_protocol(&Some("INVALID".as_bytes().to_vec())).is_err()
-        );
+    fn create_cliconnvis_for_client_auth_and_access_default(
+        client_auth: ClientAuth,
+        service_client_auth: Option<ClientAuth>,
+        access_repo: MockAccessRepo,
+        access_default: AccessDefault,
+    ) -> Result<(ClientConnVisitor, MockTlsSvrConn), AppError> {
+        let alpn_proto = alpn::Protocol::create_service_protocol(200)
+            .as_bytes()
+            .to_vec();
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn.expect_peer_certificates().return_once(|| None);
+        tls_conn
+            .expect_alpn_protocol()
+            .return_once(move || Some(alpn_proto));
+        tls_conn
+            .expect_session_info()
+            .return_once(TlsSessionInfo::default);
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo.expect_get().never();
+        let mut service_repo = MockServiceRepo::new();
+        service_repo.expect_get().never();
+
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(access_repo)),
+        )?;
+        app_config.tls_server_config_builder.client_auth = client_auth;
+        app_config.access_default = access_default;
+        let app_config = Arc::new(app_config);
+        let service_mgr = Arc::new(Mutex::new(GatewayServiceMgr::new(
+            app_config.clone(),
+            mpsc::channel().0,
+            mpsc::channel().0,
+        )));
+
+        let mut service = Service::new(200, "Service200", &Transport::TCP, "localhost", 8200);
+        service.client_auth = service_client_auth;
+
+        let mut cli_conn_visitor = ClientConnVisitor::new(app_config, service_mgr);
+        cli_conn_visitor.set_service(&service);
+
+        Ok((cli_conn_visitor, tls_conn))
+    }
+
+    fn create_anonymous_access_repo() -> MockAccessRepo {
+        let mut access_repo = MockAccessRepo::new();
+        access_repo
+            .expect_get()
+            .with(predicate::eq(ANONYMOUS_USER_ID), predicate::eq(200))
+            .return_once(move |_, _| Ok(Some(ServiceAccess::new(ANONYMOUS_USER_ID, 200))));
+        access_repo
     }
 
     #[test]
-    fn cliconnvis_parse_alpn_protocol_fn_when_no_value() {
-        assert!(ClientConnVisitor::parse_alpn_protocol(&None).is_err());
+    fn cliconnvis_process_authorization_fn_when_client_auth_required_and_nocert(
+    ) -> Result<(), AppError> {
+        let mut access_repo = MockAccessRepo::new();
+        access_repo.expect_get().never();
+
+        let (mut cli_conn_visitor, tls_conn) =
+            create_cliconnvis_for_client_auth(ClientAuth::Required, None, access_repo)?;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, Some(200));
+
+        assert!(cli_conn_visitor.get_user().is_none());
+        if let Err(AppError::GenWithCodeAndMsg(code, _)) = &result {
+            if *code == config::RESPCODE_0420_INVALID_CLIENT_CERTIFICATE {
+                return Ok(());
+            }
+        }
+
+        panic!("Unexpected result: val={:?}", &result);
     }
 
     #[test]
-    fn cliconnvis_parse_alpn_protocol_fn_when_control_plane() -> Result<(), AppError> {
+    fn cliconnvis_process_authorization_fn_when_client_auth_optional_and_nocert(
+    ) -> Result<(), AppError> {
+        let (mut cli_conn_visitor, tls_conn) = create_cliconnvis_for_client_auth(
+            ClientAuth::Optional,
+            None,
+            create_anonymous_access_repo(),
+        )?;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, Some(200));
+
+        assert!(cli_conn_visitor.get_device().is_none());
         assert_eq!(
-            ClientConnVisitor::parse_alpn_protocol(&Some(
-                alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec()
-            ))?,
-            alpn::Protocol::ControlPlane
+            cli_conn_visitor
+                .get_user()
+                .as_ref()
+                .map(|user| user.user_id),
+            Some(ANONYMOUS_USER_ID)
         );
-        Ok(())
+        if let Ok(alpn::Protocol::Service(200)) = &result {
+            return Ok(());
+        }
+
+        panic!("Unexpected result: val={:?}", &result);
     }
 
     #[test]
-    fn cliconnvis_parse_alpn_protocol_fn_when_valid_service() -> Result<(), AppError> {
-        let protocol = ClientConnVisitor::parse_alpn_protocol(&Some(
-            alpn::Protocol::create_service_protocol(123)
-                .as_bytes()
-                .to_vec(),
-        ))?;
-        match protocol {
-            alpn::Protocol::Service(service_id) => assert_eq!(service_id, 123),
-            _ => panic!("Protocol is not Service(123)"),
+    fn cliconnvis_process_authorization_fn_when_client_auth_optional_and_nocert_and_access_default_allow(
+    ) -> Result<(), AppError> {
+        let mut access_repo = MockAccessRepo::new();
+        access_repo
+            .expect_get()
+            .with(predicate::eq(ANONYMOUS_USER_ID), predicate::eq(200))
+            .return_once(|_, _| Ok(None));
+
+        let (mut cli_conn_visitor, tls_conn) =
+            create_cliconnvis_for_client_auth_and_access_default(
+                ClientAuth::Optional,
+                None,
+                access_repo,
+                AccessDefault::Allow,
+            )?;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, Some(200));
+
+        if let Err(AppError::GenWithCodeAndMsg(code, _)) = &result {
+            if *code == config::RESPCODE_0403_FORBIDDEN {
+                return Ok(());
+            }
         }
-        Ok(())
+
+        panic!("Unexpected result: val={:?}", &result);
     }
 
     #[test]
-    fn cliconnvis_parse_alpn_protocol_fn_when_invalid_service() {
-        assert!(ClientConnVisitor::parse_alpn_protocol(&Some(
-            format!("{}{}", alpn::PROTOCOL_SERVICE, "INVALID")
-                .as_bytes()
-                .to_vec()
-        ))
-        .is_err());
+    fn cliconnvis_process_authorization_fn_when_client_auth_optional_and_nocert_and_nosvc(
+    ) -> Result<(), AppError> {
+        let mut access_repo = MockAccessRepo::new();
+        access_repo.expect_get().never();
+
+        let (mut cli_conn_visitor, tls_conn) =
+            create_cliconnvis_for_client_auth(ClientAuth::Optional, None, access_repo)?;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, None);
+
+        if let Err(AppError::GenWithCodeAndMsg(code, _)) = &result {
+            if *code == config::RESPCODE_0420_INVALID_CLIENT_CERTIFICATE {
+                return Ok(());
+            }
+        }
+
+        panic!("Unexpected result: val={:?}", &result);
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_client_auth_service_overrides(
+    ) -> Result<(), AppError> {
+        let mut access_repo = MockAccessRepo::new();
+        access_repo.expect_get().never();
+
+        let (mut cli_conn_visitor, tls_conn) = create_cliconnvis_for_client_auth(
+            ClientAuth::Optional,
+            Some(ClientAuth::Required),
+            access_repo,
+        )?;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, Some(200));
+
+        match &result {
+            Err(AppError::GenWithCodeAndMsg(code, _))
+                if *code == config::RESPCODE_0420_INVALID_CLIENT_CERTIFICATE => {}
+            _ => panic!("Unexpected required result: val={:?}", &result),
+        }
+
+        let (mut cli_conn_visitor, tls_conn) = create_cliconnvis_for_client_auth(
+            ClientAuth::Required,
+            Some(ClientAuth::Optional),
+            create_anonymous_access_repo(),
+        )?;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, Some(200));
+
+        if let Ok(alpn::Protocol::Service(200)) = &result {
+            return Ok(());
+        }
+
+        panic!("Unexpected optional result: val={:?}", &result);
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_datasource_unavailable() -> Result<(), AppError> {
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn.expect_peer_certificates().never();
+        tls_conn.expect_alpn_protocol().never();
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo.expect_get().never();
+        let mut access_repo = MockAccessRepo::new();
+        access_repo.expect_get().never();
+        let mut service_repo = MockServiceRepo::new();
+        service_repo.expect_get().never();
+
+        let mut cli_conn_visitor = create_cliconnvis(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(access_repo)),
+        )?;
+        *cli_conn_visitor
+            .app_config
+            .datasource_available
+            .lock()
+            .unwrap() = false;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, None);
+        if let Err(AppError::GenWithCodeAndMsg(code, _)) = &result {
+            if *code == config::RESPCODE_0500_SYSTEM_ERROR {
+                return Ok(());
+            }
+        }
+
+        panic!("Unexpected result: val={:?}", &result);
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_nosvc_and_badcert() -> Result<(), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_NON_CLIENT_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(1)
+            .return_once(move || Some(peer_certs));
+        tls_conn.expect_alpn_protocol().never();
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo.expect_get().never();
+        let mut access_repo = MockAccessRepo::new();
+        access_repo.expect_get().never();
+        let mut service_repo = MockServiceRepo::new();
+        service_repo.expect_get().never();
+
+        let mut cli_conn_visitor = create_cliconnvis(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(access_repo)),
+        )?;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, Some(200));
+        if let Err(AppError::GenWithCodeAndMsg(code, _)) = &result {
+            if *code == config::RESPCODE_0420_INVALID_CLIENT_CERTIFICATE {
+                return Ok(());
+            }
+        }
+
+        panic!("Unexpected result: val={:?}", &result);
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_nosvc_and_baduid() -> Result<(), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(1)
+            .return_once(move || Some(peer_certs));
+        tls_conn.expect_alpn_protocol().never();
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| Ok(None));
+        let mut access_repo = MockAccessRepo::new();
+        access_repo.expect_get().never();
+        let mut service_repo = MockServiceRepo::new();
+        service_repo.expect_get().never();
+
+        let mut cli_conn_visitor = create_cliconnvis(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(access_repo)),
+        )?;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, None);
+        if let Err(AppError::GenWithCodeAndMsg(code, _)) = &result {
+            if *code == config::RESPCODE_0421_UNKNOWN_USER {
+                return Ok(());
+            }
+        }
+
+        panic!("Unexpected result: val={:?}", &result);
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_nosvc_and_inactiveuser() -> Result<(), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(1)
+            .return_once(move || Some(peer_certs));
+        tls_conn.expect_alpn_protocol().never();
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| {
+                Ok(Some(User {
+                    user_id: 100,
+                    name: "".to_string(),
+                    status: Status::Inactive,
+                    byte_quota: None,
+                }))
+            });
+        let mut access_repo = MockAccessRepo::new();
+        access_repo.expect_get().never();
+        let mut service_repo = MockServiceRepo::new();
+        service_repo.expect_get().never();
+
+        let mut cli_conn_visitor = create_cliconnvis(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(access_repo)),
+        )?;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, None);
+        if let Err(AppError::GenWithCodeAndMsg(code, _)) = &result {
+            if *code == config::RESPCODE_0422_INACTIVE_USER {
+                return Ok(());
+            }
+        }
+
+        panic!("Unexpected result: val={:?}", &result);
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_crossing_user_quota() -> Result<(), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+        let alpn_proto = alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec();
+        let proxy_key = ProxyKey::new(ProxyType::TcpAndTcp, 200, None, None);
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(2)
+            .returning(move || Some(peer_certs.clone()));
+        tls_conn
+            .expect_alpn_protocol()
+            .times(1)
+            .return_once(move || Some(alpn_proto));
+        tls_conn
+            .expect_session_info()
+            .times(1)
+            .return_once(TlsSessionInfo::default);
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(100))
+            .times(2)
+            .returning(|_| {
+                Ok(Some(User {
+                    user_id: 100,
+                    name: "".to_string(),
+                    status: Status::Active,
+                    byte_quota: Some(1000),
+                }))
+            });
+        let mut access_repo = MockAccessRepo::new();
+        access_repo.expect_get().never();
+        let mut service_repo = MockServiceRepo::new();
+        service_repo.expect_get().never();
+
+        let mut cli_conn_visitor = create_cliconnvis(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(access_repo)),
+        )?;
+        let user_byte_quotas = cli_conn_visitor.app_config.user_byte_quotas.clone();
+        user_byte_quotas
+            .lock()
+            .unwrap()
+            .register_proxy(&proxy_key, 100);
+
+        user_byte_quotas
+            .lock()
+            .unwrap()
+            .record_bytes(&proxy_key, 999);
+        let result = cli_conn_visitor.process_authorization(&tls_conn, None);
+        if let Err(err) = &result {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+
+        user_byte_quotas.lock().unwrap().record_bytes(&proxy_key, 1);
+        let result = cli_conn_visitor.process_authorization(&tls_conn, None);
+        if let Err(AppError::GenWithCodeAndMsg(code, _)) = &result {
+            if *code == config::RESPCODE_0426_USER_QUOTA_EXCEEDED {
+                return Ok(());
+            }
+        }
+
+        panic!("Unexpected result: val={:?}", &result);
+    }
+
+    // ClientConnVisitor::parse_alpn_protocol tests
+
+    #[test]
+    fn cliconnvis_parse_alpn_protocol_fn_when_invalid_value() {
+        assert!(
+            ClientConnVisitor::parse_alpn_protocol(&Some("INVALID".as_bytes().to_vec())).is_err()
+        );
+    }
+
+    #[test]
+    fn cliconnvis_parse_alpn_protocol_fn_when_no_value() {
+        assert!(ClientConnVisitor::parse_alpn_protocol(&None).is_err());
+    }
+
+    #[test]
+    fn cliconnvis_resolve_alpn_protocol_fn_when_reject_policy() -> Result<(), AppError> {
+        assert!(
+            ClientConnVisitor::resolve_alpn_protocol(&None, UnrecognizedAlpnPolicy::Reject)
+                .is_err()
+        );
+        assert_eq!(
+            ClientConnVisitor::resolve_alpn_protocol(
+                &Some(alpn::Protocol::create_service_protocol(123).into_bytes()),
+                UnrecognizedAlpnPolicy::Reject
+            )?,
+            alpn::Protocol::Service(123)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_resolve_alpn_protocol_fn_when_control_plane_policy() -> Result<(), AppError> {
+        assert_eq!(
+            ClientConnVisitor::resolve_alpn_protocol(&None, UnrecognizedAlpnPolicy::ControlPlane)?,
+            alpn::Protocol::ControlPlane
+        );
+        assert_eq!(
+            ClientConnVisitor::resolve_alpn_protocol(
+                &Some("h2".as_bytes().to_vec()),
+                UnrecognizedAlpnPolicy::ControlPlane
+            )?,
+            alpn::Protocol::ControlPlane
+        );
+        assert_eq!(
+            ClientConnVisitor::resolve_alpn_protocol(
+                &Some(alpn::Protocol::create_service_protocol(123).into_bytes()),
+                UnrecognizedAlpnPolicy::ControlPlane
+            )?,
+            alpn::Protocol::Service(123)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_parse_alpn_protocol_fn_when_control_plane() -> Result<(), AppError> {
+        assert_eq!(
+            ClientConnVisitor::parse_alpn_protocol(&Some(
+                alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec()
+            ))?,
+            alpn::Protocol::ControlPlane
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_parse_alpn_protocol_fn_when_valid_service() -> Result<(), AppError> {
+        let protocol = ClientConnVisitor::parse_alpn_protocol(&Some(
+            alpn::Protocol::create_service_protocol(123)
+                .as_bytes()
+                .to_vec(),
+        ))?;
+        match protocol {
+            alpn::Protocol::Service(service_id) => assert_eq!(service_id, 123),
+            _ => panic!("Protocol is not Service(123)"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_parse_alpn_protocol_fn_when_invalid_service() {
+        assert!(ClientConnVisitor::parse_alpn_protocol(&Some(
+            format!("{}{}", alpn::PROTOCOL_SERVICE, "INVALID")
+                .as_bytes()
+                .to_vec()
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_authorized_emits_metrics() -> Result<(), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+        let alpn_proto = alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec();
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(1)
+            .return_once(move || Some(peer_certs));
+        tls_conn
+            .expect_alpn_protocol()
+            .times(1)
+            .return_once(move || Some(alpn_proto));
+        tls_conn
+            .expect_session_info()
+            .times(1)
+            .return_once(TlsSessionInfo::default);
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| {
+                Ok(Some(User {
+                    user_id: 100,
+                    name: "".to_string(),
+                    status: Status::Active,
+                    byte_quota: None,
+                }))
+            });
+        let metrics_sink = Arc::new(CapturingMetricsSink::default());
+
+        let mut cli_conn_visitor = create_cliconnvis_with_metrics_sink(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+            metrics_sink.clone(),
+        )?;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, None);
+        if let Err(err) = &result {
+            panic!("Unexpected result: err={:?}", err);
+        }
+
+        assert_eq!(
+            *metrics_sink.metrics.lock().unwrap(),
+            vec![("connections.opened".to_string(), 1)]
+        );
+
+        conn_std::ConnectionVisitor::on_shutdown(&mut cli_conn_visitor)?;
+
+        assert_eq!(
+            *metrics_sink.metrics.lock().unwrap(),
+            vec![
+                ("connections.opened".to_string(), 1),
+                ("connections.closed".to_string(), 1)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_denied_emits_metrics() -> Result<(), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(1)
+            .return_once(move || Some(peer_certs));
+        tls_conn.expect_alpn_protocol().never();
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| {
+                Ok(Some(User {
+                    user_id: 100,
+                    name: "".to_string(),
+                    status: Status::Inactive,
+                    byte_quota: None,
+                }))
+            });
+        let metrics_sink = Arc::new(CapturingMetricsSink::default());
+
+        let mut cli_conn_visitor = create_cliconnvis_with_metrics_sink(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+            metrics_sink.clone(),
+        )?;
+
+        if cli_conn_visitor
+            .process_authorization(&tls_conn, None)
+            .is_ok()
+        {
+            panic!("Unexpected successful result");
+        }
+
+        assert_eq!(
+            *metrics_sink.metrics.lock().unwrap(),
+            vec![("auth.denied.422".to_string(), 1)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_on_polling_cycle_reports_outbound_queue_depth() -> Result<(), AppError> {
+        let metrics_sink = Arc::new(CapturingMetricsSink::default());
+
+        let mut cli_conn_visitor = create_cliconnvis_with_metrics_sink(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+            metrics_sink.clone(),
+        )?;
+        cli_conn_visitor.user = Some(User::new(100, "user100", Status::Active));
+
+        conn_std::ConnectionVisitor::on_polling_cycle(&mut cli_conn_visitor)?;
+        assert!(metrics_sink.metrics.lock().unwrap().is_empty());
+
+        conn_std::ConnectionVisitor::set_outbound_queue_depth(
+            &mut cli_conn_visitor,
+            Arc::new(OutboundQueueDepth::default()),
+        );
+        conn_std::ConnectionVisitor::on_polling_cycle(&mut cli_conn_visitor)?;
+        conn_std::ConnectionVisitor::on_polling_cycle(&mut cli_conn_visitor)?;
+
+        assert_eq!(
+            *metrics_sink.metrics.lock().unwrap(),
+            vec![
+                ("connection.pending_write_bytes.user.100".to_string(), 0),
+                ("connection.queued_events.user.100".to_string(), 0)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_get_pending_read_bytes_when_connection_reads_paused() -> Result<(), AppError> {
+        let mut service_repo = MockServiceRepo::new();
+        service_repo.expect_get_all().returning(|| Ok(vec![]));
+        let mut cli_conn_visitor = create_cliconnvis(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )?;
+        cli_conn_visitor.user = Some(User::new(100, "user100", Status::Active));
+        assert_eq!(
+            conn_std::ConnectionVisitor::get_pending_read_bytes(&cli_conn_visitor),
+            0
+        );
+
+        let (tls_conn, mut tls_cli_stream) =
+            create_handshaked_tls_conn_pair(alpn::PROTOCOL_CONTROL_PLANE.as_bytes());
+        tls_conn.sock.set_nonblocking(true).unwrap();
+        let mut connection = conn_std::Connection::new(
+            Box::new(cli_conn_visitor),
+            tls_conn,
+            alpn::Protocol::ControlPlane,
+        )?;
+        connection.set_read_watermarks(Some(ReadWatermarks { high: 16, low: 0 }));
+
+        tls_cli_stream.write_all("ping\n".as_bytes()).unwrap();
+        tls_cli_stream.flush().unwrap();
+
+        let mut request = Vec::new();
+        for _ in 0..100 {
+            request.append(&mut connection.read()?);
+            if !request.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(request, "ping\n".as_bytes());
+
+        // Unsent ping response exceeds the high watermark, so reads pause
+        tls_cli_stream.write_all("ping\n".as_bytes()).unwrap();
+        tls_cli_stream.flush().unwrap();
+        thread::sleep(Duration::from_millis(50));
+
+        assert!(connection.read()?.is_empty());
+        assert!(connection.is_reads_paused());
+        assert!(connection.get_pending_write_bytes() >= 16);
+
+        Ok(())
+    }
+
+    fn create_cliconnvis_with_conn_event_sink(
+        user_repo: Arc<Mutex<dyn UserRepository>>,
+        access_repo: Arc<Mutex<dyn AccessRepository>>,
+        conn_event_sink: Arc<CapturingConnEventSink>,
+    ) -> Result<ClientConnVisitor, AppError> {
+        let mut app_config = config::tests::create_app_config_with_repos(
+            user_repo,
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            access_repo,
+        )?;
+        app_config.conn_event_sink = conn_event_sink;
+        let app_config = Arc::new(app_config);
+        let service_mgr = Arc::new(Mutex::new(GatewayServiceMgr::new(
+            app_config.clone(),
+            mpsc::channel().0,
+            mpsc::channel().0,
+        )));
+        Ok(ClientConnVisitor::new(app_config, service_mgr))
+    }
+
+    fn parse_conn_events(conn_event_sink: &CapturingConnEventSink) -> Vec<ConnEvent> {
+        conn_event_sink
+            .lines
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|line| {
+                assert!(line.ends_with('\n'));
+                serde_json::from_str(line.trim_end()).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_authorized_emits_conn_events(
+    ) -> Result<(), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+        let alpn_proto = alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec();
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(1)
+            .return_once(move || Some(peer_certs));
+        tls_conn
+            .expect_alpn_protocol()
+            .times(1)
+            .return_once(move || Some(alpn_proto));
+        tls_conn
+            .expect_session_info()
+            .times(1)
+            .return_once(|| TlsSessionInfo {
+                protocol_version: Some("TLSv1_3".to_string()),
+                cipher_suite: Some("TLS13_AES_256_GCM_SHA384".to_string()),
+                alpn_protocol: Some(alpn::PROTOCOL_CONTROL_PLANE.to_string()),
+                resumed: false,
+            });
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| {
+                Ok(Some(User {
+                    user_id: 100,
+                    name: "".to_string(),
+                    status: Status::Active,
+                    byte_quota: None,
+                }))
+            });
+        let conn_event_sink = Arc::new(CapturingConnEventSink::default());
+
+        let mut cli_conn_visitor = create_cliconnvis_with_conn_event_sink(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+            conn_event_sink.clone(),
+        )?;
+        cli_conn_visitor.set_client_addr(Some(SocketAddr::from_str("10.0.0.5:41000").unwrap()));
+        let trace_id = cli_conn_visitor.trace_id.clone();
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, None);
+        if let Err(err) = &result {
+            panic!("Unexpected result: err={:?}", err);
+        }
+        conn_std::ConnectionVisitor::on_shutdown(&mut cli_conn_visitor)?;
+
+        let conn_events = parse_conn_events(&conn_event_sink);
+
+        assert_eq!(
+            conn_events
+                .iter()
+                .map(|event| event.event_type)
+                .collect::<Vec<ConnEventType>>(),
+            vec![
+                ConnEventType::AuthAllowed,
+                ConnEventType::ConnectionOpened,
+                ConnEventType::ConnectionClosed
+            ]
+        );
+        for event in &conn_events {
+            assert!(event.timestamp > 0);
+            assert_eq!(event.user_id, Some(100));
+            assert_eq!(event.service_id, None);
+            assert_eq!(event.client_addr, Some("10.0.0.5:41000".to_string()));
+            assert_eq!(event.trace_id, trace_id);
+        }
+        assert_eq!(conn_events[0].response_code, Some(200));
+        assert_eq!(conn_events[1].response_code, Some(200));
+        assert_eq!(conn_events[2].response_code, None);
+        assert_eq!(
+            conn_events[1].tls_protocol_version,
+            Some("TLSv1_3".to_string())
+        );
+        assert_eq!(
+            conn_events[1].tls_cipher_suite,
+            Some("TLS13_AES_256_GCM_SHA384".to_string())
+        );
+        assert_eq!(
+            conn_events[1].alpn_protocol,
+            Some(alpn::PROTOCOL_CONTROL_PLANE.to_string())
+        );
+        assert!(conn_events[0].tls_protocol_version.is_none());
+        assert!(conn_events[2].tls_cipher_suite.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_denied_emits_conn_events() -> Result<(), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(1)
+            .return_once(move || Some(peer_certs));
+        tls_conn.expect_alpn_protocol().never();
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| {
+                Ok(Some(User {
+                    user_id: 100,
+                    name: "".to_string(),
+                    status: Status::Inactive,
+                    byte_quota: None,
+                }))
+            });
+        let conn_event_sink = Arc::new(CapturingConnEventSink::default());
+
+        let mut cli_conn_visitor = create_cliconnvis_with_conn_event_sink(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+            conn_event_sink.clone(),
+        )?;
+        cli_conn_visitor.set_service(&Service::new(
+            200,
+            "Service200",
+            &Transport::TCP,
+            "localhost",
+            8200,
+        ));
+
+        if cli_conn_visitor
+            .process_authorization(&tls_conn, Some(200))
+            .is_ok()
+        {
+            panic!("Unexpected successful result");
+        }
+
+        let conn_events = parse_conn_events(&conn_event_sink);
+
+        assert_eq!(conn_events.len(), 1);
+        assert_eq!(conn_events[0].event_type, ConnEventType::AuthDenied);
+        assert_eq!(conn_events[0].user_id, Some(100));
+        assert_eq!(conn_events[0].service_id, Some(200));
+        assert_eq!(conn_events[0].client_addr, None);
+        assert_eq!(conn_events[0].response_code, Some(422));
+        assert_eq!(
+            conn_events[0].reason,
+            Some("User is not active: uid=100, status=Inactive".to_string())
+        );
+        assert_eq!(conn_events[0].trace_id, cli_conn_visitor.trace_id);
+
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_unknown_user_emits_conn_events(
+    ) -> Result<(), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(1)
+            .return_once(move || Some(peer_certs));
+        tls_conn.expect_alpn_protocol().never();
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| Ok(None));
+        let conn_event_sink = Arc::new(CapturingConnEventSink::default());
+
+        let mut cli_conn_visitor = create_cliconnvis_with_conn_event_sink(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+            conn_event_sink.clone(),
+        )?;
+        cli_conn_visitor.set_client_addr(Some(SocketAddr::from_str("10.0.0.5:41000").unwrap()));
+
+        if cli_conn_visitor
+            .process_authorization(&tls_conn, None)
+            .is_ok()
+        {
+            panic!("Unexpected successful result");
+        }
+
+        let conn_events = parse_conn_events(&conn_event_sink);
+
+        assert_eq!(conn_events.len(), 1);
+        assert_eq!(conn_events[0].event_type, ConnEventType::AuthDenied);
+        assert_eq!(conn_events[0].user_id, Some(100));
+        assert_eq!(conn_events[0].service_id, None);
+        assert_eq!(
+            conn_events[0].client_addr,
+            Some("10.0.0.5:41000".to_string())
+        );
+        assert_eq!(conn_events[0].response_code, Some(421));
+        assert_eq!(
+            conn_events[0].reason,
+            Some("User is not found in user repo: uid=100".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_forbidden_emits_conn_events() -> Result<(), AppError>
+    {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+        let alpn_proto = alpn::Protocol::create_service_protocol(200)
+            .as_bytes()
+            .to_vec();
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(1)
+            .return_once(move || Some(peer_certs));
+        tls_conn
+            .expect_alpn_protocol()
+            .times(1)
+            .return_once(move || Some(alpn_proto));
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| Ok(Some(User::new(100, "user100", Status::Active))));
+        let mut access_repo = MockAccessRepo::new();
+        access_repo
+            .expect_get()
+            .with(predicate::eq(100), predicate::eq(200))
+            .times(1)
+            .return_once(move |_, _| Ok(None));
+        let conn_event_sink = Arc::new(CapturingConnEventSink::default());
+
+        let mut cli_conn_visitor = create_cliconnvis_with_conn_event_sink(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(access_repo)),
+            conn_event_sink.clone(),
+        )?;
+        cli_conn_visitor.set_service(&Service::new(
+            200,
+            "Service200",
+            &Transport::TCP,
+            "localhost",
+            8200,
+        ));
+        cli_conn_visitor.set_client_addr(Some(SocketAddr::from_str("10.0.0.5:41000").unwrap()));
+
+        if cli_conn_visitor
+            .process_authorization(&tls_conn, Some(200))
+            .is_ok()
+        {
+            panic!("Unexpected successful result");
+        }
+
+        let conn_events = parse_conn_events(&conn_event_sink);
+
+        assert_eq!(conn_events.len(), 1);
+        assert_eq!(conn_events[0].event_type, ConnEventType::AuthDenied);
+        assert_eq!(conn_events[0].user_id, Some(100));
+        assert_eq!(conn_events[0].service_id, Some(200));
+        assert_eq!(
+            conn_events[0].client_addr,
+            Some("10.0.0.5:41000".to_string())
+        );
+        assert_eq!(conn_events[0].response_code, Some(403));
+        assert_eq!(
+            conn_events[0].reason,
+            Some("User is not authorized for service: uid=100, svc_id=200".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_maintenance_and_nonadmin_user(
+    ) -> Result<(), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+        let alpn_proto = alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec();
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(2)
+            .returning(move || Some(peer_certs.clone()));
+        tls_conn
+            .expect_alpn_protocol()
+            .times(1)
+            .return_once(move || Some(alpn_proto));
+        tls_conn
+            .expect_session_info()
+            .times(1)
+            .return_once(TlsSessionInfo::default);
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(100))
+            .times(2)
+            .returning(move |_| Ok(Some(User::new(100, "user100", Status::Active))));
+        let conn_event_sink = Arc::new(CapturingConnEventSink::default());
+
+        let mut cli_conn_visitor = create_cliconnvis_with_conn_event_sink(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+            conn_event_sink.clone(),
+        )?;
+        *cli_conn_visitor
+            .app_config
+            .maintenance_message
+            .lock()
+            .unwrap() = Some("Upgrade in progress".to_string());
+
+        match cli_conn_visitor.process_authorization(&tls_conn, None) {
+            Err(AppError::GenWithCodeAndMsg(code, msg)) => {
+                assert_eq!(code, config::RESPCODE_0503_GATEWAY_MAINTENANCE);
+                assert_eq!(msg, "Upgrade in progress");
+            }
+            result => panic!("Unexpected result: val={:?}", &result),
+        }
+
+        let conn_events = parse_conn_events(&conn_event_sink);
+        assert_eq!(conn_events.len(), 1);
+        assert_eq!(conn_events[0].event_type, ConnEventType::AuthDenied);
+        assert_eq!(conn_events[0].response_code, Some(503));
+        assert_eq!(
+            conn_events[0].reason,
+            Some("Upgrade in progress".to_string())
+        );
+
+        *cli_conn_visitor
+            .app_config
+            .maintenance_message
+            .lock()
+            .unwrap() = None;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, None);
+        if let Err(err) = &result {
+            panic!("Unexpected result: err={:?}", err);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_maintenance_and_admin_user() -> Result<(), AppError>
+    {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+        let alpn_proto = alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec();
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(1)
+            .return_once(move || Some(peer_certs));
+        tls_conn
+            .expect_alpn_protocol()
+            .times(1)
+            .return_once(move || Some(alpn_proto));
+        tls_conn
+            .expect_session_info()
+            .times(1)
+            .return_once(TlsSessionInfo::default);
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| Ok(Some(User::new(100, "user100", Status::Active))));
+
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )?;
+        app_config.admin_user_ids = vec![100];
+        *app_config.maintenance_message.lock().unwrap() = Some("Upgrade in progress".to_string());
+        let app_config = Arc::new(app_config);
+        let service_mgr = Arc::new(Mutex::new(GatewayServiceMgr::new(
+            app_config.clone(),
+            mpsc::channel().0,
+            mpsc::channel().0,
+        )));
+        let mut cli_conn_visitor = ClientConnVisitor::new(app_config, service_mgr);
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, None);
+        if let Err(err) = &result {
+            panic!("Unexpected result: err={:?}", err);
+        }
+
+        Ok(())
+    }
+
+    fn create_cliconnvis_requiring_client_auth_eku(
+        user_repo: Arc<Mutex<dyn UserRepository>>,
+    ) -> Result<ClientConnVisitor, AppError> {
+        let mut app_config = config::tests::create_app_config_with_repos(
+            user_repo,
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )?;
+        app_config.require_client_auth_eku = true;
+        let app_config = Arc::new(app_config);
+        let service_mgr = Arc::new(Mutex::new(GatewayServiceMgr::new(
+            app_config.clone(),
+            mpsc::channel().0,
+            mpsc::channel().0,
+        )));
+        Ok(ClientConnVisitor::new(app_config, service_mgr))
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_client_auth_eku_required_and_present(
+    ) -> Result<(), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+        let alpn_proto = alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec();
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(1)
+            .return_once(move || Some(peer_certs));
+        tls_conn
+            .expect_alpn_protocol()
+            .times(1)
+            .return_once(move || Some(alpn_proto));
+        tls_conn
+            .expect_session_info()
+            .times(1)
+            .return_once(TlsSessionInfo::default);
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| Ok(Some(User::new(100, "user100", Status::Active))));
+
+        let mut cli_conn_visitor =
+            create_cliconnvis_requiring_client_auth_eku(Arc::new(Mutex::new(user_repo)))?;
+
+        let result = cli_conn_visitor.process_authorization(&tls_conn, None);
+        if let Err(err) = &result {
+            panic!("Unexpected result: err={:?}", err);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn cliconnvis_process_authorization_fn_when_client_auth_eku_required_and_missing(
+    ) -> Result<(), AppError> {
+        let peer_certs_file: PathBuf = CERTFILE_GATEWAY_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string())?;
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_peer_certificates()
+            .times(1)
+            .return_once(move || Some(peer_certs));
+        tls_conn.expect_alpn_protocol().never();
+
+        let mut user_repo = MockUserRepo::new();
+        user_repo.expect_get().never();
+
+        let mut cli_conn_visitor =
+            create_cliconnvis_requiring_client_auth_eku(Arc::new(Mutex::new(user_repo)))?;
+
+        match cli_conn_visitor.process_authorization(&tls_conn, None) {
+            Err(AppError::GenWithCodeAndMsg(code, _)) => {
+                assert_eq!(code, config::RESPCODE_0428_INVALID_CERTIFICATE_USAGE)
+            }
+            result => panic!("Unexpected result: val={:?}", &result),
+        }
+
+        Ok(())
     }
 }
@@ -1,6 +1,8 @@
 use std::collections::{HashMap, HashSet};
+use std::io::Write;
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use anyhow::Result;
 use rustls::server::Accepted;
@@ -10,31 +12,42 @@ use serde_json::Value;
 use crate::client::connection::ClientConnVisitor;
 use crate::client::device::Device;
 use crate::config::AppConfig;
-use crate::repository::access_repo::AccessRepository;
+use crate::repository::access_repo::{self, AccessRepository};
+use crate::repository::config_hash;
 use crate::repository::service_repo::ServiceRepository;
 use crate::repository::user_repo::UserRepository;
-use crate::service::manager::ServiceMgr;
+use crate::service::manager::{GatewayServiceMgr, ServiceMgr};
 use trust0_common::control::{request, response};
 use trust0_common::error::AppError;
 use trust0_common::model;
-use trust0_common::net::tls_server::conn_std::{ConnectionEvent, TlsServerConnection};
+use trust0_common::net::tls_server::conn_std::{
+    ChunkedEventWriter, ConnectionEvent, OutboundQueueDepth, TlsServerConnection, TlsSessionInfo,
+};
 use trust0_common::net::tls_server::{conn_std, server_std};
 
+/// Maximum size of a (streamed) response chunk
+const RESPONSE_CHUNK_SIZE: usize = 16 * 1024;
+/// Name used to list the control plane connection (in the 'connections' command)
+const CONTROL_PLANE_CONNECTION_NAME: &str = "control-plane";
+
 /// Process control plane commands. Clients use a connection REPL shell to issue requests.
 pub struct ControlPlane {
     app_config: Arc<AppConfig>,
     processor: request::RequestProcessor,
     access_repo: Arc<Mutex<dyn AccessRepository>>,
-    _service_repo: Arc<Mutex<dyn ServiceRepository>>,
+    service_repo: Arc<Mutex<dyn ServiceRepository>>,
     user_repo: Arc<Mutex<dyn UserRepository>>,
     event_channel_sender: Sender<ConnectionEvent>,
     device: Device,
     user: model::user::User,
+    tls_session_info: Option<TlsSessionInfo>,
+    outbound_queue_depth: Option<Arc<OutboundQueueDepth>>,
     services_by_id: HashMap<u64, model::service::Service>,
     services_by_name: HashMap<String, model::service::Service>,
 }
 
 impl ControlPlane {
+    #[allow(clippy::too_many_arguments)]
     /// ControlPlane constructor
     pub fn new(
         app_config: Arc<AppConfig>,
@@ -44,6 +57,7 @@ impl ControlPlane {
         event_channel_sender: Sender<ConnectionEvent>,
         device: Device,
         user: model::user::User,
+        tls_session_info: Option<TlsSessionInfo>,
     ) -> Result<Self, AppError> {
         let (services_by_id, services_by_name) = Self::setup_services_maps(&service_repo)?;
 
@@ -51,16 +65,23 @@ impl ControlPlane {
             app_config,
             processor: request::RequestProcessor::new(),
             access_repo,
-            _service_repo: service_repo,
+            service_repo,
             user_repo,
             event_channel_sender,
             device,
             user,
+            tls_session_info,
+            outbound_queue_depth: None,
             services_by_id,
             services_by_name,
         })
     }
 
+    /// Set control plane connection's outbound queue depth (reported in the 'connections' command)
+    pub fn set_outbound_queue_depth(&mut self, outbound_queue_depth: Arc<OutboundQueueDepth>) {
+        self.outbound_queue_depth = Some(outbound_queue_depth);
+    }
+
     /// Prepare response stringified JSON
     fn prepare_response(
         code: u16,
@@ -82,18 +103,32 @@ impl ControlPlane {
             .get(user_id)?
             .map(|u| response::User::new(u.user_id, &u.name, &format!("{:?}", u.status)));
 
+        let mut about = response::About::new(
+            &Some(format!("{:?}", device.get_cert_subj())),
+            &Some(format!("{:?}", device.get_cert_alt_subj())),
+            &Some(format!("{:?}", device.get_cert_access_context())),
+            &user,
+            &self.tls_session_info,
+        );
+        about.set_config_hash(&config_hash::compute_config_hash(&self.service_repo, None)?);
+
         Self::prepare_response(
             response::CODE_OK,
             &None,
             &request::Request::About,
+            &Some(about.try_into()?),
+        )
+    }
+
+    /// Process 'ping' command
+    fn process_cmd_ping(&self) -> Result<String, AppError> {
+        Self::prepare_response(
+            response::CODE_OK,
+            &Some("pong".to_string()),
+            &request::Request::Ping,
             &Some(
-                response::About::new(
-                    &Some(format!("{:?}", device.get_cert_subj())),
-                    &Some(format!("{:?}", device.get_cert_alt_subj())),
-                    &Some(format!("{:?}", device.get_cert_access_context())),
-                    &user,
-                )
-                .try_into()?,
+                response::Ping::new(&config_hash::compute_config_hash(&self.service_repo, None)?)
+                    .try_into()?,
             ),
         )
     }
@@ -104,15 +139,25 @@ impl ControlPlane {
         service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
     ) -> Result<String, AppError> {
         let mask_addrs = self.app_config.mask_addresses;
+        let now = Instant::now();
 
         let service_proxies = service_mgr.lock().unwrap().get_service_proxies();
 
-        let connections: Vec<Value> = service_proxies
+        let mut connections: Vec<Value> = service_proxies
             .iter()
             .map(|service_proxy| {
                 let service_proxy = service_proxy.lock().unwrap();
 
                 let proxy_addrs_list = service_proxy.get_proxy_addrs_for_user(self.user.user_id);
+                let bytes_per_sec: f64 = service_proxy
+                    .get_proxy_sessions_for_user(self.user.user_id)
+                    .iter()
+                    .map(|session| {
+                        self.app_config
+                            .service_throughput
+                            .get_connection_rate(&session.proxy_key, now)
+                    })
+                    .sum();
 
                 let binds = proxy_addrs_list
                     .iter()
@@ -125,10 +170,22 @@ impl ControlPlane {
                     })
                     .collect();
 
-                response::Connection::new(&service_proxy.get_service().name, binds).try_into()
+                let mut connection =
+                    response::Connection::new(&service_proxy.get_service().name, binds);
+                connection.set_bytes_per_sec(bytes_per_sec.round() as u64);
+                connection.try_into()
             })
             .collect::<Result<Vec<Value>, AppError>>()?;
 
+        if let Some(outbound_queue_depth) = &self.outbound_queue_depth {
+            let mut connection = response::Connection::new(CONTROL_PLANE_CONNECTION_NAME, vec![]);
+            connection.set_outbound_queue_depth(
+                outbound_queue_depth.get_pending_write_bytes(),
+                outbound_queue_depth.get_queued_events(),
+            );
+            connections.push(connection.try_into()?);
+        }
+
         Self::prepare_response(
             response::CODE_OK,
             &None,
@@ -137,19 +194,113 @@ impl ControlPlane {
         )
     }
 
+    /// Process 'sessions' command
+    fn process_cmd_sessions(
+        &self,
+        service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
+    ) -> Result<String, AppError> {
+        let mask_addrs = self.app_config.mask_addresses;
+
+        let service_proxies = service_mgr.lock().unwrap().get_service_proxies();
+
+        let mut sessions: Vec<(u64, response::Session)> = service_proxies
+            .iter()
+            .flat_map(|service_proxy| {
+                let service_proxy = service_proxy.lock().unwrap();
+                let service_name = service_proxy.get_service().name;
+
+                service_proxy
+                    .get_proxy_sessions_for_user(self.user.user_id)
+                    .into_iter()
+                    .map(|session| {
+                        let duration_secs = session.started_at.elapsed().as_secs();
+                        (
+                            duration_secs,
+                            response::Session::new(
+                                &session.handle(),
+                                &service_name,
+                                &(!mask_addrs).then(|| session.proxy_addrs.1.clone()),
+                                duration_secs,
+                            ),
+                        )
+                    })
+                    .collect::<Vec<(u64, response::Session)>>()
+            })
+            .collect();
+        sessions.sort_by(|(duration1, session1), (duration2, session2)| {
+            duration2
+                .cmp(duration1)
+                .then_with(|| session1.handle.cmp(&session2.handle))
+        });
+
+        let sessions: Vec<Value> = sessions
+            .into_iter()
+            .map(|(_, session)| session.try_into())
+            .collect::<Result<Vec<Value>, AppError>>()?;
+
+        Self::prepare_response(
+            response::CODE_OK,
+            &None,
+            &request::Request::Sessions,
+            &Some(sessions.into()),
+        )
+    }
+
+    /// Process 'close-session' command (only the user's own sessions may be closed)
+    fn process_cmd_close_session(
+        &self,
+        service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
+        handle: &str,
+    ) -> Result<String, AppError> {
+        let service_mgr = service_mgr.lock().unwrap();
+
+        let (service_proxy, session) = service_mgr
+            .get_service_proxies()
+            .into_iter()
+            .find_map(|service_proxy| {
+                let session = service_proxy
+                    .lock()
+                    .unwrap()
+                    .get_proxy_sessions_for_user(self.user.user_id)
+                    .into_iter()
+                    .find(|session| session.handle() == handle)?;
+                Some((service_proxy, session))
+            })
+            .ok_or(AppError::GenWithCodeAndMsg(
+                response::CODE_NOT_FOUND,
+                format!("Unknown session: handle={}", handle),
+            ))?;
+
+        service_proxy
+            .lock()
+            .unwrap()
+            .shutdown_connection(service_mgr.clone_proxy_tasks_sender(), &session.proxy_key)?;
+
+        Self::prepare_response(
+            response::CODE_OK,
+            &None,
+            &request::Request::CloseSession {
+                handle: handle.to_string(),
+            },
+            &None,
+        )
+    }
+
     /// Process 'proxies' command
     fn process_cmd_proxies(
         &mut self,
         service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
     ) -> Result<String, AppError> {
-        let user_services: HashSet<u64> = self
-            .access_repo
-            .lock()
-            .unwrap()
-            .get_all_for_user(self.user.user_id)?
-            .iter()
-            .map(|access| access.service_id)
-            .collect();
+        let service_ids: Vec<u64> = self.services_by_id.keys().cloned().collect();
+        let user_services: HashSet<u64> = access_repo::resolve_access_bulk(
+            &*self.access_repo.lock().unwrap(),
+            self.app_config.access_default,
+            self.user.user_id,
+            &service_ids,
+        )?
+        .into_iter()
+        .filter_map(|(service_id, access)| access.map(|_| service_id))
+        .collect();
 
         let service_proxies = service_mgr.lock().unwrap().get_service_proxies();
 
@@ -182,29 +333,92 @@ impl ControlPlane {
         )
     }
 
-    /// Process 'services' command
+    /// Process 'service' command. Service may be given by name or ID
+    fn process_cmd_service(&mut self, service_key: &str) -> Result<String, AppError> {
+        let service = self
+            .services_by_name
+            .get(service_key)
+            .or_else(|| {
+                service_key
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|service_id| self.services_by_id.get(&service_id))
+            })
+            .ok_or(AppError::GenWithCodeAndMsg(
+                response::CODE_NOT_FOUND,
+                format!("Unknown service: svc={}", service_key),
+            ))?;
+
+        let access = access_repo::resolve_access(
+            &*self.access_repo.lock().unwrap(),
+            self.app_config.access_default,
+            self.user.user_id,
+            service.service_id,
+        )?
+        .ok_or(AppError::GenWithCodeAndMsg(
+            response::CODE_FORBIDDEN,
+            format!(
+                "User is not authorized for service: user_id={}, svc_id={}",
+                self.user.user_id, service.service_id
+            ),
+        ))?;
+
+        let mut service = Self::prepare_response_service(service, self.app_config.mask_addresses);
+        service.justification = access.justification;
+
+        Self::prepare_response(
+            response::CODE_OK,
+            &None,
+            &request::Request::Service {
+                service: service_key.to_string(),
+            },
+            &Some(service.try_into()?),
+        )
+    }
+
+    /// Process 'services' command. Response is streamed (in chunks) to the client, hence the (successful)
+    /// returned response text is empty.
     fn process_cmd_services(&mut self) -> Result<String, AppError> {
         let mask_addrs = self.app_config.mask_addresses;
 
-        let user_services: Vec<Value> = self
-            .access_repo
-            .lock()
-            .unwrap()
-            .get_all_for_user(self.user.user_id)?
-            .iter()
-            .filter_map(|access| self.services_by_id.get(&access.service_id))
-            .map(|service| {
-                let service = Self::prepare_response_service(service, mask_addrs);
-                service.try_into()
+        let service_ids: Vec<u64> = self.services_by_id.keys().cloned().collect();
+        let user_accesses = access_repo::resolve_all_access_for_user(
+            &*self.access_repo.lock().unwrap(),
+            self.app_config.access_default,
+            self.user.user_id,
+            &service_ids,
+        )?;
+
+        let user_services = user_accesses.iter().filter_map(|access| {
+            self.services_by_id.get(&access.service_id).map(|service| {
+                let mut service = Self::prepare_response_service(service, mask_addrs);
+                service.justification = access.justification.clone();
+                service
             })
-            .collect::<Result<Vec<Value>, AppError>>()?;
+        });
 
-        Self::prepare_response(
+        let mut response_writer =
+            ChunkedEventWriter::new(self.event_channel_sender.clone(), RESPONSE_CHUNK_SIZE);
+
+        response::Response::write_streamed(
+            &mut response_writer,
             response::CODE_OK,
             &None,
             &request::Request::Services,
-            &Some(user_services.into()),
-        )
+            user_services,
+        )?;
+
+        response_writer
+            .write_all("\n".as_bytes())
+            .and_then(|_| response_writer.flush())
+            .map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    "Error writing streamed response".to_string(),
+                    Box::new(err),
+                )
+            })?;
+
+        Ok(String::new())
     }
 
     /// Process 'start' command
@@ -223,12 +437,13 @@ impl ControlPlane {
                     format!("Unknown service: svc_name={}", service_name),
                 ))?;
 
-        if self
-            .access_repo
-            .lock()
-            .unwrap()
-            .get(self.user.user_id, service.service_id)?
-            .is_none()
+        if access_repo::resolve_access(
+            &*self.access_repo.lock().unwrap(),
+            self.app_config.access_default,
+            self.user.user_id,
+            service.service_id,
+        )?
+        .is_none()
         {
             return Err(AppError::GenWithCodeAndMsg(
                 response::CODE_FORBIDDEN,
@@ -239,12 +454,19 @@ impl ControlPlane {
             ));
         }
 
-        // Start up service proxy
-        let service_mgr_copy = service_mgr.clone();
-        let (gateway_service_host, gateway_service_port) = service_mgr
+        self.app_config
+            .service_auth_times
             .lock()
             .unwrap()
-            .startup(service_mgr_copy, service)?;
+            .record_auth(
+                self.user.user_id,
+                service.service_id,
+                self.app_config.clock.now(),
+            );
+
+        // Start up service proxy
+        let (gateway_service_host, gateway_service_port) =
+            GatewayServiceMgr::startup_service(service_mgr, service)?;
 
         // Return service proxy connection
         let service = Self::prepare_response_service(service, self.app_config.mask_addresses);
@@ -314,6 +536,171 @@ impl ControlPlane {
         )
     }
 
+    /// Process 'user-status' command
+    fn process_cmd_user_status(&self, user_id: u64) -> Result<String, AppError> {
+        self.validate_admin_user()?;
+
+        let user = self.get_target_user(user_id)?;
+
+        Self::prepare_response(
+            response::CODE_OK,
+            &None,
+            &request::Request::UserStatus { user_id },
+            &Some(
+                response::User::new(user.user_id, &user.name, &format!("{:?}", user.status))
+                    .try_into()?,
+            ),
+        )
+    }
+
+    /// Process 'set-user-status' command. Inactivated users will have their service proxy connections shutdown.
+    fn process_cmd_set_user_status(
+        &self,
+        service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
+        user_id: u64,
+        status: &model::user::Status,
+    ) -> Result<String, AppError> {
+        self.validate_admin_user()?;
+        self.app_config.validate_writable()?;
+
+        let mut user = self.get_target_user(user_id)?;
+        user.status = status.clone();
+
+        self.user_repo.lock().unwrap().put(user.clone())?;
+
+        if user.status == model::user::Status::Inactive {
+            service_mgr
+                .lock()
+                .unwrap()
+                .shutdown_connections(Some(user_id), None)?;
+        }
+
+        Self::prepare_response(
+            response::CODE_OK,
+            &None,
+            &request::Request::SetUserStatus {
+                user_id,
+                status: status.clone(),
+            },
+            &Some(
+                response::User::new(user.user_id, &user.name, &format!("{:?}", user.status))
+                    .try_into()?,
+            ),
+        )
+    }
+
+    /// Process 'reload' command. Datasources are only changed if all of their files load successfully.
+    fn process_cmd_reload(&self) -> Result<String, AppError> {
+        self.validate_admin_user()?;
+        self.app_config.validate_writable()?;
+
+        let datasource_reloader =
+            self.app_config
+                .datasource_reloader
+                .as_ref()
+                .ok_or(AppError::GenWithCodeAndMsg(
+                    response::CODE_BAD_REQUEST,
+                    "Datasource does not support reloading".to_string(),
+                ))?;
+
+        let datasource_diff = datasource_reloader.reload_all_with_diff()?;
+
+        Self::prepare_response(
+            response::CODE_OK,
+            &None,
+            &request::Request::Reload,
+            &Some(serde_json::to_value(&datasource_diff).map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    "Error serializing datasource changes".to_string(),
+                    Box::new(err),
+                )
+            })?),
+        )
+    }
+
+    /// Process 'config' command
+    fn process_cmd_config(&self) -> Result<String, AppError> {
+        self.validate_admin_user()?;
+
+        Self::prepare_response(
+            response::CODE_OK,
+            &None,
+            &request::Request::Config,
+            &Some(self.app_config.to_redacted_json()?),
+        )
+    }
+
+    /// Process 'service-stats' command. Statistics are aggregated across the service's proxies. Service may be
+    /// given by name or ID
+    fn process_cmd_service_stats(
+        &self,
+        service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
+        service_key: &str,
+    ) -> Result<String, AppError> {
+        self.validate_admin_user()?;
+
+        let service = self
+            .services_by_name
+            .get(service_key)
+            .or_else(|| {
+                service_key
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|service_id| self.services_by_id.get(&service_id))
+            })
+            .ok_or(AppError::GenWithCodeAndMsg(
+                response::CODE_NOT_FOUND,
+                format!("Unknown service: svc={}", service_key),
+            ))?;
+
+        let mut service_stats = response::ServiceStats::new(service.service_id, &service.name);
+
+        for service_proxy in service_mgr.lock().unwrap().get_service_proxies() {
+            let service_proxy = service_proxy.lock().unwrap();
+            if service_proxy.get_service().service_id != service.service_id {
+                continue;
+            }
+            let proxy_stats = service_proxy.get_proxy_stats();
+            service_stats.active_connections += proxy_stats.active_connections;
+            service_stats.connections_opened += proxy_stats.connections_opened;
+            service_stats.connections_closed += proxy_stats.connections_closed;
+        }
+        service_stats.bytes_transferred = self
+            .app_config
+            .service_throughput
+            .get_service_bytes(service.service_id);
+
+        Self::prepare_response(
+            response::CODE_OK,
+            &None,
+            &request::Request::ServiceStats {
+                service: service_key.to_string(),
+            },
+            &Some(service_stats.try_into()?),
+        )
+    }
+
+    /// Process 'maintenance' command. New (non-admin) connections are rejected while a maintenance message is set,
+    /// existing connections are unaffected
+    fn process_cmd_maintenance(&self, message: &Option<String>) -> Result<String, AppError> {
+        self.validate_admin_user()?;
+        self.app_config.validate_writable()?;
+
+        *self.app_config.maintenance_message.lock().unwrap() = message.clone();
+
+        Self::prepare_response(
+            response::CODE_OK,
+            &None,
+            &request::Request::Maintenance {
+                message: message.clone(),
+            },
+            &Some(serde_json::json!({
+                "maintenance": message.is_some(),
+                "message": message,
+            })),
+        )
+    }
+
     /// Process 'quit' command
     fn process_cmd_quit(&self) -> Result<String, AppError> {
         self.event_channel_sender
@@ -330,6 +717,32 @@ impl ControlPlane {
         )
     }
 
+    /// Ensure connected user is permitted to issue administrative commands
+    fn validate_admin_user(&self) -> Result<(), AppError> {
+        if !self.app_config.admin_user_ids.contains(&self.user.user_id) {
+            return Err(AppError::GenWithCodeAndMsg(
+                response::CODE_FORBIDDEN,
+                format!(
+                    "User is not authorized for admin commands: user_id={}",
+                    self.user.user_id
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Retrieve user targeted by an administrative command
+    fn get_target_user(&self, user_id: u64) -> Result<model::user::User, AppError> {
+        self.user_repo
+            .lock()
+            .unwrap()
+            .get(user_id)?
+            .ok_or(AppError::GenWithCodeAndMsg(
+                response::CODE_NOT_FOUND,
+                format!("Unknown user: user_id={}", user_id),
+            ))
+    }
+
     /// Convert model service to response service
     fn prepare_response_service(
         service: &model::service::Service,
@@ -396,21 +809,38 @@ impl RequestProcessor for ControlPlane {
             }
             Ok(request::Request::Ping) => {
                 client_request = request::Request::Ping;
-                client_response = Self::prepare_response(
-                    response::CODE_OK,
-                    &Some("pong".to_string()),
-                    &client_request,
-                    &None,
-                );
+                client_response = self.process_cmd_ping();
             }
             Ok(request::Request::Proxies) => {
                 client_request = request::Request::Proxies;
                 client_response = self.process_cmd_proxies(service_mgr);
             }
+            Ok(request::Request::Service { service }) => {
+                client_request = request::Request::Service {
+                    service: service.clone(),
+                };
+                client_response = self.process_cmd_service(&service);
+            }
             Ok(request::Request::Services) => {
                 client_request = request::Request::Services;
                 client_response = self.process_cmd_services();
             }
+            Ok(request::Request::ServiceStats { service }) => {
+                client_request = request::Request::ServiceStats {
+                    service: service.clone(),
+                };
+                client_response = self.process_cmd_service_stats(service_mgr, &service);
+            }
+            Ok(request::Request::Sessions) => {
+                client_request = request::Request::Sessions;
+                client_response = self.process_cmd_sessions(service_mgr);
+            }
+            Ok(request::Request::CloseSession { handle }) => {
+                client_request = request::Request::CloseSession {
+                    handle: handle.clone(),
+                };
+                client_response = self.process_cmd_close_session(service_mgr, &handle);
+            }
             Ok(request::Request::Start {
                 service_name,
                 local_port,
@@ -427,6 +857,31 @@ impl RequestProcessor for ControlPlane {
                 };
                 client_response = self.process_cmd_stop(service_mgr, &service_name);
             }
+            Ok(request::Request::UserStatus { user_id }) => {
+                client_request = request::Request::UserStatus { user_id };
+                client_response = self.process_cmd_user_status(user_id);
+            }
+            Ok(request::Request::SetUserStatus { user_id, status }) => {
+                client_request = request::Request::SetUserStatus {
+                    user_id,
+                    status: status.clone(),
+                };
+                client_response = self.process_cmd_set_user_status(service_mgr, user_id, &status);
+            }
+            Ok(request::Request::Reload) => {
+                client_request = request::Request::Reload;
+                client_response = self.process_cmd_reload();
+            }
+            Ok(request::Request::Config) => {
+                client_request = request::Request::Config;
+                client_response = self.process_cmd_config();
+            }
+            Ok(request::Request::Maintenance { message }) => {
+                client_request = request::Request::Maintenance {
+                    message: message.clone(),
+                };
+                client_response = self.process_cmd_maintenance(&message);
+            }
             Ok(request::Request::Quit) => {
                 client_request = request::Request::Quit;
                 client_response = self.process_cmd_quit();
@@ -520,11 +975,13 @@ impl server_std::ServerVisitor for ControlPlaneServerVisitor {
     ) -> Result<conn_std::Connection, AppError> {
         let mut conn_visitor =
             ClientConnVisitor::new(self.app_config.clone(), self.service_mgr.clone());
+        conn_visitor.set_client_addr(tls_conn.sock.peer_addr().ok());
 
         let alpn_protocol = conn_visitor.process_authorization(&tls_conn, None)?;
 
-        let connection =
+        let mut connection =
             conn_std::Connection::new(Box::new(conn_visitor), tls_conn, alpn_protocol)?;
+        connection.set_read_watermarks(self.app_config.read_watermarks);
 
         Ok(connection)
     }
@@ -545,17 +1002,28 @@ impl server_std::ServerVisitor for ControlPlaneServerVisitor {
 mod tests {
     use super::*;
     use crate::client::controller::RequestProcessor;
-    use crate::config;
+    use crate::config::{self, DatasourceErrorPolicy, InMemoryDb};
+    use crate::repository::access_repo::in_memory_repo::InMemAccessRepo;
     use crate::repository::access_repo::tests::MockAccessRepo;
+    use crate::repository::reloader::tests::create_temp_datasource;
+    use crate::repository::reloader::DatasourceReloader;
+    use crate::repository::service_repo::in_memory_repo::InMemServiceRepo;
     use crate::repository::service_repo::tests::MockServiceRepo;
+    use crate::repository::user_repo::in_memory_repo::InMemUserRepo;
     use crate::repository::user_repo::tests::MockUserRepo;
     use crate::service::manager::tests::MockSvcMgr;
     use crate::service::proxy::proxy_base::tests::MockGwSvcProxyVisitor;
+    use crate::service::proxy::proxy_base::{GatewayServiceProxyVisitor, ProxySession, ProxyStats};
     use mockall::predicate;
+    use std::fs;
+    use std::net::SocketAddr;
     use std::path::PathBuf;
-    use std::sync::mpsc;
+    use std::sync::mpsc::{self, Receiver};
+    use std::time::{Duration, Instant};
     use trust0_common::crypto::file::load_certificates;
     use trust0_common::model::access::ServiceAccess;
+    use trust0_common::proxy::proxy_base::ProxyType;
+    use trust0_common::proxy::proxy_key::ProxyKey;
 
     const CERTFILE_CLIENT_UID100_PATHPARTS: [&str; 3] = [
         env!("CARGO_MANIFEST_DIR"),
@@ -574,6 +1042,7 @@ mod tests {
             user_id: 100,
             name: "user100".to_string(),
             status: model::user::Status::Active,
+            byte_quota: None,
         }
     }
 
@@ -585,6 +1054,25 @@ mod tests {
         Arc<Mutex<dyn UserRepository>>,
         Arc<Mutex<dyn ServiceRepository>>,
         Arc<Mutex<dyn AccessRepository>>,
+    ) {
+        create_repos_with_service_get_all_times(
+            expect_user_get,
+            expect_access_get_all_for_user,
+            expect_access_get,
+            1,
+        )
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn create_repos_with_service_get_all_times(
+        expect_user_get: bool,
+        expect_access_get_all_for_user: bool,
+        expect_access_get: bool,
+        service_get_all_times: usize,
+    ) -> (
+        Arc<Mutex<dyn UserRepository>>,
+        Arc<Mutex<dyn ServiceRepository>>,
+        Arc<Mutex<dyn AccessRepository>>,
     ) {
         let mut user_repo = MockUserRepo::new();
         if expect_user_get {
@@ -596,45 +1084,118 @@ mod tests {
         }
 
         let mut service_repo = MockServiceRepo::new();
-        service_repo.expect_get_all().times(1).return_once(move || {
-            Ok(vec![
-                model::service::Service {
-                    service_id: 200,
-                    name: "Service200".to_string(),
-                    transport: model::service::Transport::TCP,
-                    host: "localhost".to_string(),
-                    port: 8200,
-                },
-                model::service::Service {
-                    service_id: 201,
-                    name: "Service201".to_string(),
-                    transport: model::service::Transport::TCP,
-                    host: "localhost".to_string(),
-                    port: 8201,
-                },
-                model::service::Service {
-                    service_id: 202,
-                    name: "Service202".to_string(),
-                    transport: model::service::Transport::TCP,
-                    host: "localhost".to_string(),
-                    port: 8202,
-                },
-                model::service::Service {
-                    service_id: 203,
-                    name: "chat-tcp".to_string(),
-                    transport: model::service::Transport::TCP,
-                    host: "localhost".to_string(),
-                    port: 8500,
-                },
-                model::service::Service {
-                    service_id: 204,
-                    name: "echo-udp".to_string(),
-                    transport: model::service::Transport::UDP,
-                    host: "localhost".to_string(),
-                    port: 8600,
-                },
-            ])
-        });
+        service_repo
+            .expect_get_all()
+            .times(service_get_all_times)
+            .returning(move || {
+                Ok(vec![
+                    model::service::Service {
+                        service_id: 200,
+                        name: "Service200".to_string(),
+                        transport: model::service::Transport::TCP,
+                        host: "localhost".to_string(),
+                        port: 8200,
+                        relay_retries: 0,
+                        forward_empty_datagrams: false,
+                        allowed_client_cidrs: vec![],
+                        dns_cache_ttl: None,
+                        reauth_interval: None,
+                        upstream_bind_addr: None,
+                        fast_relay: false,
+                        client_auth: None,
+                        udp_target: None,
+                        log_sample_rate: None,
+                        max_response_bytes: None,
+                        proxyable: true,
+                        max_qps: None,
+                        upstream_prefix: vec![],
+                    },
+                    model::service::Service {
+                        service_id: 201,
+                        name: "Service201".to_string(),
+                        transport: model::service::Transport::TCP,
+                        host: "localhost".to_string(),
+                        port: 8201,
+                        relay_retries: 0,
+                        forward_empty_datagrams: false,
+                        allowed_client_cidrs: vec![],
+                        dns_cache_ttl: None,
+                        reauth_interval: None,
+                        upstream_bind_addr: None,
+                        fast_relay: false,
+                        client_auth: None,
+                        udp_target: None,
+                        log_sample_rate: None,
+                        max_response_bytes: None,
+                        proxyable: true,
+                        max_qps: None,
+                        upstream_prefix: vec![],
+                    },
+                    model::service::Service {
+                        service_id: 202,
+                        name: "Service202".to_string(),
+                        transport: model::service::Transport::TCP,
+                        host: "localhost".to_string(),
+                        port: 8202,
+                        relay_retries: 0,
+                        forward_empty_datagrams: false,
+                        allowed_client_cidrs: vec![],
+                        dns_cache_ttl: None,
+                        reauth_interval: None,
+                        upstream_bind_addr: None,
+                        fast_relay: false,
+                        client_auth: None,
+                        udp_target: None,
+                        log_sample_rate: None,
+                        max_response_bytes: None,
+                        proxyable: true,
+                        max_qps: None,
+                        upstream_prefix: vec![],
+                    },
+                    model::service::Service {
+                        service_id: 203,
+                        name: "chat-tcp".to_string(),
+                        transport: model::service::Transport::TCP,
+                        host: "localhost".to_string(),
+                        port: 8500,
+                        relay_retries: 0,
+                        forward_empty_datagrams: false,
+                        allowed_client_cidrs: vec![],
+                        dns_cache_ttl: None,
+                        reauth_interval: None,
+                        upstream_bind_addr: None,
+                        fast_relay: false,
+                        client_auth: None,
+                        udp_target: None,
+                        log_sample_rate: None,
+                        max_response_bytes: None,
+                        proxyable: true,
+                        max_qps: None,
+                        upstream_prefix: vec![],
+                    },
+                    model::service::Service {
+                        service_id: 204,
+                        name: "echo-udp".to_string(),
+                        transport: model::service::Transport::UDP,
+                        host: "localhost".to_string(),
+                        port: 8600,
+                        relay_retries: 0,
+                        forward_empty_datagrams: false,
+                        allowed_client_cidrs: vec![],
+                        dns_cache_ttl: None,
+                        reauth_interval: None,
+                        upstream_bind_addr: None,
+                        fast_relay: false,
+                        client_auth: None,
+                        udp_target: None,
+                        log_sample_rate: None,
+                        max_response_bytes: None,
+                        proxyable: true,
+                        max_qps: None,
+                        upstream_prefix: vec![],
+                    },
+                ])
+            });
 
         let mut access_repo = MockAccessRepo::new();
         if expect_access_get_all_for_user {
@@ -647,22 +1208,32 @@ mod tests {
                         ServiceAccess {
                             user_id: 100,
                             service_id: 200,
+                            justification: None,
+                            deny: false,
                         },
                         ServiceAccess {
                             user_id: 100,
                             service_id: 203,
+                            justification: Some("Team chat".to_string()),
+                            deny: false,
                         },
                         ServiceAccess {
                             user_id: 100,
                             service_id: 204,
+                            justification: None,
+                            deny: false,
                         },
                         ServiceAccess {
                             user_id: 101,
                             service_id: 202,
+                            justification: None,
+                            deny: false,
                         },
                         ServiceAccess {
                             user_id: 101,
                             service_id: 203,
+                            justification: None,
+                            deny: false,
                         },
                     ])
                 });
@@ -675,6 +1246,8 @@ mod tests {
                     Ok(Some(ServiceAccess {
                         user_id: 100,
                         service_id: 200,
+                        justification: None,
+                        deny: false,
                     }))
                 });
         }
@@ -705,6 +1278,20 @@ mod tests {
                     transport: model::service::Transport::TCP,
                     host: "localhost".to_string(),
                     port: 8200,
+                    relay_retries: 0,
+                    forward_empty_datagrams: false,
+                    allowed_client_cidrs: vec![],
+                    dns_cache_ttl: None,
+                    reauth_interval: None,
+                    upstream_bind_addr: None,
+                    fast_relay: false,
+                    client_auth: None,
+                    udp_target: None,
+                    log_sample_rate: None,
+                    max_response_bytes: None,
+                    proxyable: true,
+                    max_qps: None,
+                    upstream_prefix: vec![],
                 });
             if expect_connection_details {
                 service_proxy
@@ -712,6 +1299,11 @@ mod tests {
                     .with(predicate::eq(100))
                     .times(1)
                     .return_once(move |_| vec![("addr1".to_string(), "addr2".to_string())]);
+                service_proxy
+                    .expect_get_proxy_sessions_for_user()
+                    .with(predicate::eq(100))
+                    .times(1)
+                    .return_once(move |_| vec![]);
             }
             if expect_proxy_details {
                 service_proxy
@@ -736,6 +1328,20 @@ mod tests {
                 transport: model::service::Transport::TCP,
                 host: "localhost".to_string(),
                 port: 8200,
+                relay_retries: 0,
+                forward_empty_datagrams: false,
+                allowed_client_cidrs: vec![],
+                dns_cache_ttl: None,
+                reauth_interval: None,
+                upstream_bind_addr: None,
+                fast_relay: false,
+                client_auth: None,
+                udp_target: None,
+                log_sample_rate: None,
+                max_response_bytes: None,
+                proxyable: true,
+                max_qps: None,
+                upstream_prefix: vec![],
             };
             service_mgr
                 .expect_startup()
@@ -760,6 +1366,69 @@ mod tests {
         Arc::new(Mutex::new(service_mgr))
     }
 
+    fn create_service_mgr_with_sessions(
+        sessions: Vec<ProxySession>,
+        expect_closed_proxy_key: Option<ProxyKey>,
+    ) -> Arc<Mutex<dyn ServiceMgr>> {
+        let mut service_mgr = MockSvcMgr::new();
+
+        let mut service_proxy = MockGwSvcProxyVisitor::new();
+        service_proxy
+            .expect_get_service()
+            .return_const(model::service::Service::new(
+                200,
+                "Service200",
+                &model::service::Transport::TCP,
+                "localhost",
+                8200,
+            ));
+        service_proxy
+            .expect_get_proxy_sessions_for_user()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| sessions);
+
+        match expect_closed_proxy_key {
+            Some(proxy_key) => {
+                service_proxy
+                    .expect_shutdown_connection()
+                    .with(predicate::always(), predicate::eq(proxy_key))
+                    .times(1)
+                    .return_once(|_, _| Ok(()));
+                service_mgr
+                    .expect_clone_proxy_tasks_sender()
+                    .times(1)
+                    .return_once(|| mpsc::channel().0);
+            }
+            None => {
+                service_proxy.expect_shutdown_connection().never();
+            }
+        }
+
+        service_mgr
+            .expect_get_service_proxies()
+            .times(1)
+            .return_once(move || vec![Arc::new(Mutex::new(service_proxy))]);
+
+        Arc::new(Mutex::new(service_mgr))
+    }
+
+    fn create_proxy_session(client_port: u16, age: Duration) -> ProxySession {
+        ProxySession {
+            proxy_key: ProxyKey::new(
+                ProxyType::TcpAndTcp,
+                200,
+                Some(format!("10.0.0.1:{}", client_port).parse().unwrap()),
+                Some("127.0.0.1:8200".parse().unwrap()),
+            ),
+            proxy_addrs: (
+                format!("10.0.0.1:{}", client_port),
+                "gwhost1:6000".to_string(),
+            ),
+            started_at: Instant::now().checked_sub(age).unwrap(),
+        }
+    }
+
     fn create_control_plane(
         event_channel_sender: Sender<ConnectionEvent>,
         user_repo: &Arc<Mutex<dyn UserRepository>>,
@@ -768,28 +1437,90 @@ mod tests {
         device: Device,
         user: model::user::User,
     ) -> Result<ControlPlane, AppError> {
-        let app_config = Arc::new(config::tests::create_app_config_with_repos(
-            user_repo.clone(),
-            service_repo.clone(),
-            access_repo.clone(),
-        )?);
-
-        Ok(ControlPlane::new(
-            app_config,
-            access_repo.clone(),
+        create_control_plane_with_read_only(
+            event_channel_sender,
+            user_repo,
+            service_repo,
+            access_repo,
+            device,
+            user,
+            false,
+        )
+    }
+
+    fn create_control_plane_with_read_only(
+        event_channel_sender: Sender<ConnectionEvent>,
+        user_repo: &Arc<Mutex<dyn UserRepository>>,
+        service_repo: &Arc<Mutex<dyn ServiceRepository>>,
+        access_repo: &Arc<Mutex<dyn AccessRepository>>,
+        device: Device,
+        user: model::user::User,
+        read_only: bool,
+    ) -> Result<ControlPlane, AppError> {
+        let mut app_config = config::tests::create_app_config_with_repos(
+            user_repo.clone(),
+            service_repo.clone(),
+            access_repo.clone(),
+        )?;
+        app_config.admin_user_ids = vec![100];
+        app_config.read_only = read_only;
+
+        Ok(ControlPlane::new(
+            Arc::new(app_config),
+            access_repo.clone(),
             service_repo.clone(),
             user_repo.clone(),
             event_channel_sender,
             device,
             user,
+            Some(TlsSessionInfo {
+                protocol_version: Some("TLSv1_3".to_string()),
+                cipher_suite: Some("TLS13_AES_256_GCM_SHA384".to_string()),
+                alpn_protocol: Some("T0CP".to_string()),
+                resumed: false,
+            }),
         )?)
     }
 
+    fn create_user_repo_for_status_change(
+        target_user: Option<model::user::User>,
+        expected_put_user: Option<model::user::User>,
+    ) -> Arc<Mutex<dyn UserRepository>> {
+        let mut user_repo = MockUserRepo::new();
+        user_repo
+            .expect_get()
+            .with(predicate::eq(101))
+            .times(1)
+            .return_once(move |_| Ok(target_user));
+        if let Some(put_user) = expected_put_user {
+            user_repo
+                .expect_put()
+                .with(predicate::eq(put_user))
+                .times(1)
+                .return_once(move |_| Ok(None));
+        }
+        Arc::new(Mutex::new(user_repo))
+    }
+
+    fn assert_write_event(event_channel_receiver: &Receiver<ConnectionEvent>, expected: &str) {
+        match event_channel_receiver.try_recv() {
+            Ok(ConnectionEvent::Write(response_bytes)) => {
+                assert_eq!(String::from_utf8(response_bytes).unwrap(), expected);
+            }
+            Ok(ConnectionEvent::Closing) => panic!("Unexpected connection event: val=Closing"),
+            Ok(ConnectionEvent::Closed) => panic!("Unexpected connection event: val=Closed"),
+            Ok(ConnectionEvent::SetRateLimit(_)) => {
+                panic!("Unexpected connection event: val=SetRateLimit")
+            }
+            Err(err) => panic!("Unexpected channel recv result: err={:?}", err),
+        }
+    }
+
     #[test]
     fn ctlplane_process_request_when_valid_about() {
         let device = create_device().unwrap();
         let user = create_user();
-        let repos = create_repos(true, false, false);
+        let repos = create_repos_with_service_get_all_times(true, false, false, 2);
         let event_channel = mpsc::channel();
         let service_mgr = create_service_mgr(false, false, false, false);
 
@@ -819,6 +1550,9 @@ mod tests {
             ConnectionEvent::Closed => {
                 panic!("Unexpected connection event: val=Closed");
             }
+            ConnectionEvent::SetRateLimit(_) => {
+                panic!("Unexpected connection event: val=SetRateLimit");
+            }
             ConnectionEvent::Write(response_bytes) => {
                 let actual_response_str = String::from_utf8(response_bytes.clone()).unwrap();
                 assert!(actual_response_str.contains(
@@ -826,6 +1560,10 @@ mod tests {
                 assert!(actual_response_str.contains(
                     "user\":{\"name\":\"user100\",\"status\":\"Active\",\"user_id\":100}"
                 ));
+                assert!(actual_response_str.contains(
+                    "tls_session\":{\"alpn_protocol\":\"T0CP\",\"cipher_suite\":\"TLS13_AES_256_GCM_SHA384\",\"protocol_version\":\"TLSv1_3\",\"resumed\":false}"
+                ));
+                assert!(actual_response_str.contains("\"config_hash\":\""));
             }
         }
     }
@@ -865,19 +1603,217 @@ mod tests {
             ConnectionEvent::Closed => {
                 panic!("Unexpected connection event: val=Closed");
             }
+            ConnectionEvent::SetRateLimit(_) => {
+                panic!("Unexpected connection event: val=SetRateLimit");
+            }
             ConnectionEvent::Write(response_bytes) => {
                 assert_eq!(String::from_utf8(response_bytes.clone()).unwrap(),
-                           "{\"code\":200,\"message\":null,\"request\":\"Connections\",\"data\":[{\"binds\":[[\"addr1\",\"addr2\"]],\"service_name\":\"Service200\"}]}\n");
+                           "{\"code\":200,\"message\":null,\"request\":\"Connections\",\"data\":[{\"binds\":[[\"addr1\",\"addr2\"]],\"bytes_per_sec\":0,\"service_name\":\"Service200\"}]}\n");
             }
         }
     }
 
     #[test]
-    fn ctlplane_process_request_when_valid_ping() {
+    fn ctlplane_process_request_when_valid_connections_and_outbound_queue_depth() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(true, false, false, false);
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+        control_plane.set_outbound_queue_depth(Arc::new(OutboundQueueDepth::default()));
+
+        let result =
+            control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_CONNECTIONS);
+
+        if let Err(err) = &result {
+            panic!("Unexpected process request result: err={:?}", err);
+        }
+
+        match event_channel.1.try_recv() {
+            Ok(ConnectionEvent::Write(response_bytes)) => {
+                assert_eq!(String::from_utf8(response_bytes.clone()).unwrap(),
+                           "{\"code\":200,\"message\":null,\"request\":\"Connections\",\"data\":[{\"binds\":[[\"addr1\",\"addr2\"]],\"bytes_per_sec\":0,\"service_name\":\"Service200\"},{\"binds\":[],\"pending_write_bytes\":0,\"queued_events\":0,\"service_name\":\"control-plane\"}]}\n");
+            }
+            Ok(_) => panic!("Unexpected connection event"),
+            Err(err) => panic!("Unexpected channel recv result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_valid_connections_and_throughput() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let session1 = create_proxy_session(40001, Duration::from_secs(5));
+        let session2 = create_proxy_session(40002, Duration::from_secs(5));
+        let (proxy_key1, proxy_key2) = (session1.proxy_key.clone(), session2.proxy_key.clone());
+
+        let mut service_proxy = MockGwSvcProxyVisitor::new();
+        service_proxy
+            .expect_get_service()
+            .return_const(model::service::Service::new(
+                200,
+                "Service200",
+                &model::service::Transport::TCP,
+                "localhost",
+                8200,
+            ));
+        service_proxy
+            .expect_get_proxy_addrs_for_user()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| vec![("addr1".to_string(), "addr2".to_string())]);
+        service_proxy
+            .expect_get_proxy_sessions_for_user()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| vec![session1, session2]);
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr
+            .expect_get_service_proxies()
+            .times(1)
+            .return_once(move || vec![Arc::new(Mutex::new(service_proxy))]);
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(service_mgr));
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+        let service_throughput = control_plane.app_config.service_throughput.clone();
+        service_throughput.record_bytes(&proxy_key1, 100_000, Instant::now());
+        service_throughput.record_bytes(&proxy_key2, 50_000, Instant::now());
+
+        let result =
+            control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_CONNECTIONS);
+
+        assert_eq!(result.unwrap(), request::Request::Connections);
+        let response = recv_write_event_json(&event_channel.1);
+        assert_eq!(response["code"], 200);
+        let bytes_per_sec = response["data"][0]["bytes_per_sec"].as_u64().unwrap();
+        assert!(bytes_per_sec > 0);
+        assert!(bytes_per_sec <= 15_000);
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_sessions_and_several_sessions() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let recent_session = create_proxy_session(40001, Duration::from_secs(5));
+        let older_session = create_proxy_session(40002, Duration::from_secs(120));
+        let (recent_handle, older_handle) = (recent_session.handle(), older_session.handle());
+        let service_mgr =
+            create_service_mgr_with_sessions(vec![recent_session, older_session], None);
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
+        let result =
+            control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_SESSIONS);
+
+        assert_eq!(result.unwrap(), request::Request::Sessions);
+        assert_write_event(&event_channel.1,
+                           &format!("{{\"code\":200,\"message\":null,\"request\":\"Sessions\",\"data\":[{{\"duration_secs\":120,\"gateway_address\":\"gwhost1:6000\",\"handle\":\"{}\",\"service_name\":\"Service200\"}},{{\"duration_secs\":5,\"gateway_address\":\"gwhost1:6000\",\"handle\":\"{}\",\"service_name\":\"Service200\"}}]}}\n",
+                                   older_handle, recent_handle));
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_sessions_and_no_sessions() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr_with_sessions(vec![], None);
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
+        let result =
+            control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_SESSIONS);
+
+        assert_eq!(result.unwrap(), request::Request::Sessions);
+        assert_write_event(
+            &event_channel.1,
+            "{\"code\":200,\"message\":null,\"request\":\"Sessions\",\"data\":[]}\n",
+        );
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_close_session_and_own_session() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let session = create_proxy_session(40001, Duration::from_secs(5));
+        let handle = session.handle();
+        let service_mgr =
+            create_service_mgr_with_sessions(vec![session.clone()], Some(session.proxy_key));
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!("{} -H {}", request::PROTOCOL_REQUEST_CLOSE_SESSION, &handle),
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            request::Request::CloseSession {
+                handle: handle.clone()
+            }
+        );
+        assert_write_event(&event_channel.1,
+                           &format!("{{\"code\":200,\"message\":null,\"request\":{{\"CloseSession\":{{\"handle\":\"{}\"}}}},\"data\":null}}\n", handle));
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_close_session_and_unknown_session() {
         let device = create_device().unwrap();
         let user = create_user();
         let repos = create_repos(false, false, false);
         let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr_with_sessions(
+            vec![create_proxy_session(40001, Duration::from_secs(5))],
+            None,
+        );
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!(
+                "{} -H ffffffffffffffff",
+                request::PROTOCOL_REQUEST_CLOSE_SESSION
+            ),
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            request::Request::CloseSession {
+                handle: "ffffffffffffffff".to_string()
+            }
+        );
+        assert_write_event(&event_channel.1,
+                           "{\"code\":404,\"message\":\"Response: code=404, msg=Unknown session: handle=ffffffffffffffff\",\"request\":{\"CloseSession\":{\"handle\":\"ffffffffffffffff\"}},\"data\":null}\n");
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_valid_ping() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos_with_service_get_all_times(false, false, false, 3);
+        let event_channel = mpsc::channel();
         let service_mgr = create_service_mgr(false, false, false, false);
 
         let mut control_plane =
@@ -906,10 +1842,16 @@ mod tests {
             ConnectionEvent::Closed => {
                 panic!("Unexpected connection event: val=Closed");
             }
+            ConnectionEvent::SetRateLimit(_) => {
+                panic!("Unexpected connection event: val=SetRateLimit");
+            }
             ConnectionEvent::Write(response_bytes) => {
                 assert_eq!(
                     String::from_utf8(response_bytes.clone()).unwrap(),
-                    "{\"code\":200,\"message\":\"pong\",\"request\":\"Ping\",\"data\":null}\n"
+                    format!(
+                        "{{\"code\":200,\"message\":\"pong\",\"request\":\"Ping\",\"data\":{{\"config_hash\":\"{}\"}}}}\n",
+                        config_hash::compute_config_hash(&repos.1, None).unwrap()
+                    )
                 );
             }
         }
@@ -949,6 +1891,9 @@ mod tests {
             ConnectionEvent::Closed => {
                 panic!("Unexpected connection event: val=Closed");
             }
+            ConnectionEvent::SetRateLimit(_) => {
+                panic!("Unexpected connection event: val=SetRateLimit");
+            }
             ConnectionEvent::Write(response_bytes) => {
                 assert_eq!(String::from_utf8(response_bytes.clone()).unwrap(),
                            "{\"code\":200,\"message\":null,\"request\":\"Proxies\",\"data\":[{\"client_port\":null,\"gateway_host\":\"proxyhost1\",\"gateway_port\":6000,\"service\":{\"address\":\"localhost:8200\",\"id\":200,\"name\":\"Service200\",\"transport\":\"TCP\"}}]}\n");
@@ -990,6 +1935,9 @@ mod tests {
             ConnectionEvent::Closed => {
                 panic!("Unexpected connection event: val=Closed");
             }
+            ConnectionEvent::SetRateLimit(_) => {
+                panic!("Unexpected connection event: val=SetRateLimit");
+            }
             ConnectionEvent::Write(response_bytes) => {
                 panic!(
                     "Unexpected connection event: val=Write, resp={}",
@@ -1005,6 +1953,9 @@ mod tests {
             ConnectionEvent::Closed => {
                 panic!("Unexpected connection event: val=Closed");
             }
+            ConnectionEvent::SetRateLimit(_) => {
+                panic!("Unexpected connection event: val=SetRateLimit");
+            }
             ConnectionEvent::Write(response_bytes) => {
                 assert_eq!(
                     String::from_utf8(response_bytes.clone()).unwrap(),
@@ -1015,10 +1966,10 @@ mod tests {
     }
 
     #[test]
-    fn ctlplane_process_request_when_valid_services() {
+    fn ctlplane_process_request_when_service_and_accessible() {
         let device = create_device().unwrap();
         let user = create_user();
-        let repos = create_repos(false, true, false);
+        let repos = create_repos(false, false, true);
         let event_channel = mpsc::channel();
         let service_mgr = create_service_mgr(false, false, false, false);
 
@@ -1026,78 +1977,109 @@ mod tests {
             create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
                 .unwrap();
 
-        let result =
-            control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_SERVICES);
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!("{} -s 200", request::PROTOCOL_REQUEST_SERVICE),
+        );
 
         if let Err(err) = &result {
             panic!("Unexpected process request result: err={:?}", err);
         }
 
-        let processed_request = result.unwrap();
-        assert_eq!(processed_request, request::Request::Services);
+        assert_eq!(
+            result.unwrap(),
+            request::Request::Service {
+                service: "200".to_string()
+            }
+        );
+        assert_write_event(&event_channel.1,
+                           "{\"code\":200,\"message\":null,\"request\":{\"Service\":{\"service\":\"200\"}},\"data\":{\"address\":\"localhost:8200\",\"id\":200,\"name\":\"Service200\",\"transport\":\"TCP\"}}\n");
+    }
 
-        let result = event_channel.1.try_recv();
+    #[test]
+    fn ctlplane_process_request_when_service_and_inaccessible() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let mut access_repo = MockAccessRepo::new();
+        access_repo
+            .expect_get()
+            .with(predicate::eq(100), predicate::eq(201))
+            .times(1)
+            .return_once(|_, _| Ok(None));
+        let access_repo: Arc<Mutex<dyn AccessRepository>> = Arc::new(Mutex::new(access_repo));
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+
+        let mut control_plane = create_control_plane(
+            event_channel.0,
+            &repos.0,
+            &repos.1,
+            &access_repo,
+            device,
+            user,
+        )
+        .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!("{} -s Service201", request::PROTOCOL_REQUEST_SERVICE),
+        );
 
         if let Err(err) = &result {
-            panic!("Unexpected channel recv result: err={:?}", err);
+            panic!("Unexpected process request result: err={:?}", err);
         }
 
-        match &result.unwrap() {
-            ConnectionEvent::Closing => {
-                panic!("Unexpected connection event: val=Closing");
-            }
-            ConnectionEvent::Closed => {
-                panic!("Unexpected connection event: val=Closed");
-            }
-            ConnectionEvent::Write(response_bytes) => {
-                assert_eq!(String::from_utf8(response_bytes.clone()).unwrap(),
-                           "{\"code\":200,\"message\":null,\"request\":\"Services\",\"data\":[{\"address\":\"localhost:8200\",\"id\":200,\"name\":\"Service200\",\"transport\":\"TCP\"},{\"address\":\"localhost:8500\",\"id\":203,\"name\":\"chat-tcp\",\"transport\":\"TCP\"},{\"address\":\"localhost:8600\",\"id\":204,\"name\":\"echo-udp\",\"transport\":\"UDP\"},{\"address\":\"localhost:8202\",\"id\":202,\"name\":\"Service202\",\"transport\":\"TCP\"},{\"address\":\"localhost:8500\",\"id\":203,\"name\":\"chat-tcp\",\"transport\":\"TCP\"}]}\n");
-            }
-        }
+        assert_write_event(&event_channel.1,
+                           "{\"code\":403,\"message\":\"Response: code=403, msg=User is not authorized for service: user_id=100, svc_id=201\",\"request\":{\"Service\":{\"service\":\"Service201\"}},\"data\":null}\n");
     }
 
     #[test]
-    fn ctlplane_process_request_when_valid_start() {
+    fn ctlplane_process_request_when_service_and_unknown() {
         let device = create_device().unwrap();
         let user = create_user();
-        let repos = create_repos(false, false, true);
+        let repos = create_repos(false, false, false);
         let event_channel = mpsc::channel();
-        let service_mgr = create_service_mgr(false, false, true, false);
+        let service_mgr = create_service_mgr(false, false, false, false);
 
         let mut control_plane =
             create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
                 .unwrap();
 
-        let service = model::service::Service {
-            service_id: 200,
-            name: "Service200".to_string(),
-            transport: model::service::Transport::TCP,
-            host: "localhost".to_string(),
-            port: 8200,
-        };
-
         let result = control_plane.process_request(
             &service_mgr,
-            &format!(
-                "{} -s {} -p {}",
-                request::PROTOCOL_REQUEST_START,
-                &service.name,
-                3000
-            ),
+            &format!("{} -s 999", request::PROTOCOL_REQUEST_SERVICE),
         );
 
         if let Err(err) = &result {
             panic!("Unexpected process request result: err={:?}", err);
         }
 
+        assert_write_event(&event_channel.1,
+                           "{\"code\":404,\"message\":\"Response: code=404, msg=Unknown service: svc=999\",\"request\":{\"Service\":{\"service\":\"999\"}},\"data\":null}\n");
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_valid_services() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, true, false);
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
+        let result =
+            control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_SERVICES);
+
+        if let Err(err) = &result {
+            panic!("Unexpected process request result: err={:?}", err);
+        }
+
         let processed_request = result.unwrap();
-        assert_eq!(
-            processed_request,
-            request::Request::Start {
-                service_name: service.name.to_string(),
-                local_port: 3000
-            }
-        );
+        assert_eq!(processed_request, request::Request::Services);
 
         let result = event_channel.1.try_recv();
 
@@ -1112,25 +2094,266 @@ mod tests {
             ConnectionEvent::Closed => {
                 panic!("Unexpected connection event: val=Closed");
             }
+            ConnectionEvent::SetRateLimit(_) => {
+                panic!("Unexpected connection event: val=SetRateLimit");
+            }
             ConnectionEvent::Write(response_bytes) => {
                 assert_eq!(String::from_utf8(response_bytes.clone()).unwrap(),
-                           "{\"code\":200,\"message\":null,\"request\":{\"Start\":{\"service_name\":\"Service200\",\"local_port\":3000}},\"data\":{\"client_port\":3000,\"gateway_host\":\"proxyhost1\",\"gateway_port\":6000,\"service\":{\"address\":\"localhost:8200\",\"id\":200,\"name\":\"Service200\",\"transport\":\"TCP\"}}}\n");
+                           "{\"code\":200,\"message\":null,\"request\":\"Services\",\"data\":[{\"address\":\"localhost:8200\",\"id\":200,\"name\":\"Service200\",\"transport\":\"TCP\"},{\"address\":\"localhost:8500\",\"id\":203,\"justification\":\"Team chat\",\"name\":\"chat-tcp\",\"transport\":\"TCP\"},{\"address\":\"localhost:8600\",\"id\":204,\"name\":\"echo-udp\",\"transport\":\"UDP\"},{\"address\":\"localhost:8202\",\"id\":202,\"name\":\"Service202\",\"transport\":\"TCP\"},{\"address\":\"localhost:8500\",\"id\":203,\"name\":\"chat-tcp\",\"transport\":\"TCP\"}]}\n");
             }
         }
     }
 
     #[test]
-    fn ctlplane_process_request_when_invalid_start() {
+    fn ctlplane_process_request_when_services_and_not_proxyable_service() {
         let device = create_device().unwrap();
         let user = create_user();
-        let repos = create_repos(false, false, true);
+        let user_repo: Arc<Mutex<dyn UserRepository>> = Arc::new(Mutex::new(MockUserRepo::new()));
+        let mut service_repo = MockServiceRepo::new();
+        service_repo.expect_get_all().times(1).return_once(move || {
+            let mut service = model::service::Service::new(
+                205,
+                "catalog-only",
+                &model::service::Transport::TCP,
+                "localhost",
+                8700,
+            );
+            service.proxyable = false;
+            Ok(vec![service])
+        });
+        let service_repo: Arc<Mutex<dyn ServiceRepository>> = Arc::new(Mutex::new(service_repo));
+        let mut access_repo = MockAccessRepo::new();
+        access_repo
+            .expect_get_all_for_user()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| Ok(vec![ServiceAccess::new(100, 205)]));
+        let access_repo: Arc<Mutex<dyn AccessRepository>> = Arc::new(Mutex::new(access_repo));
         let event_channel = mpsc::channel();
         let service_mgr = create_service_mgr(false, false, false, false);
 
-        let mut control_plane =
-            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
-                .unwrap();
-
+        let mut control_plane = create_control_plane(
+            event_channel.0,
+            &user_repo,
+            &service_repo,
+            &access_repo,
+            device,
+            user,
+        )
+        .unwrap();
+
+        let result =
+            control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_SERVICES);
+
+        if let Err(err) = &result {
+            panic!("Unexpected process request result: err={:?}", err);
+        }
+
+        assert_write_event(&event_channel.1,
+                           "{\"code\":200,\"message\":null,\"request\":\"Services\",\"data\":[{\"address\":\"localhost:8700\",\"id\":205,\"name\":\"catalog-only\",\"transport\":\"TCP\"}]}\n");
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_valid_services_and_large_response() {
+        const SERVICE_COUNT: u64 = 50_000;
+
+        let device = create_device().unwrap();
+        let user = create_user();
+        let user_repo: Arc<Mutex<dyn UserRepository>> = Arc::new(Mutex::new(MockUserRepo::new()));
+        let mut service_repo = MockServiceRepo::new();
+        service_repo.expect_get_all().times(1).return_once(move || {
+            Ok((0..SERVICE_COUNT)
+                .map(|service_id| model::service::Service {
+                    service_id,
+                    name: format!("Service{}", service_id),
+                    transport: model::service::Transport::TCP,
+                    host: "localhost".to_string(),
+                    port: 8200,
+                    relay_retries: 0,
+                    forward_empty_datagrams: false,
+                    allowed_client_cidrs: vec![],
+                    dns_cache_ttl: None,
+                    reauth_interval: None,
+                    upstream_bind_addr: None,
+                    fast_relay: false,
+                    client_auth: None,
+                    udp_target: None,
+                    log_sample_rate: None,
+                    max_response_bytes: None,
+                    proxyable: true,
+                    max_qps: None,
+                    upstream_prefix: vec![],
+                })
+                .collect())
+        });
+        let service_repo: Arc<Mutex<dyn ServiceRepository>> = Arc::new(Mutex::new(service_repo));
+        let mut access_repo = MockAccessRepo::new();
+        access_repo
+            .expect_get_all_for_user()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| {
+                Ok((0..SERVICE_COUNT)
+                    .map(|service_id| ServiceAccess {
+                        user_id: 100,
+                        service_id,
+                        justification: None,
+                        deny: false,
+                    })
+                    .collect())
+            });
+        let access_repo: Arc<Mutex<dyn AccessRepository>> = Arc::new(Mutex::new(access_repo));
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+
+        let mut control_plane = create_control_plane(
+            event_channel.0,
+            &user_repo,
+            &service_repo,
+            &access_repo,
+            device,
+            user,
+        )
+        .unwrap();
+
+        let result =
+            control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_SERVICES);
+
+        if let Err(err) = &result {
+            panic!("Unexpected process request result: err={:?}", err);
+        }
+
+        let mut chunk_count = 0;
+        let mut response_bytes: Vec<u8> = vec![];
+        while let Ok(event) = event_channel.1.try_recv() {
+            match event {
+                ConnectionEvent::Write(chunk) => {
+                    assert!(chunk.len() <= RESPONSE_CHUNK_SIZE);
+                    chunk_count += 1;
+                    response_bytes.extend(chunk);
+                }
+                ConnectionEvent::Closing => panic!("Unexpected connection event: val=Closing"),
+                ConnectionEvent::Closed => panic!("Unexpected connection event: val=Closed"),
+                ConnectionEvent::SetRateLimit(_) => {
+                    panic!("Unexpected connection event: val=SetRateLimit")
+                }
+            }
+        }
+
+        assert!(chunk_count > 1);
+        assert_eq!(response_bytes.last(), Some(&b'\n'));
+
+        let response =
+            response::Response::parse(std::str::from_utf8(&response_bytes).unwrap()).unwrap();
+        assert_eq!(response.code, response::CODE_OK);
+        assert_eq!(response.request, request::Request::Services);
+        match response.data {
+            Some(Value::Array(services)) => assert_eq!(services.len(), SERVICE_COUNT as usize),
+            data => panic!("Unexpected response data: data={:?}", &data),
+        }
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_valid_start() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, true);
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, true, false);
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
+        let service = model::service::Service {
+            service_id: 200,
+            name: "Service200".to_string(),
+            transport: model::service::Transport::TCP,
+            host: "localhost".to_string(),
+            port: 8200,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
+        };
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!(
+                "{} -s {} -p {}",
+                request::PROTOCOL_REQUEST_START,
+                &service.name,
+                3000
+            ),
+        );
+
+        if let Err(err) = &result {
+            panic!("Unexpected process request result: err={:?}", err);
+        }
+
+        let processed_request = result.unwrap();
+        assert_eq!(
+            processed_request,
+            request::Request::Start {
+                service_name: service.name.to_string(),
+                local_port: 3000
+            }
+        );
+
+        let result = event_channel.1.try_recv();
+
+        if let Err(err) = &result {
+            panic!("Unexpected channel recv result: err={:?}", err);
+        }
+
+        match &result.unwrap() {
+            ConnectionEvent::Closing => {
+                panic!("Unexpected connection event: val=Closing");
+            }
+            ConnectionEvent::Closed => {
+                panic!("Unexpected connection event: val=Closed");
+            }
+            ConnectionEvent::SetRateLimit(_) => {
+                panic!("Unexpected connection event: val=SetRateLimit");
+            }
+            ConnectionEvent::Write(response_bytes) => {
+                assert_eq!(String::from_utf8(response_bytes.clone()).unwrap(),
+                           "{\"code\":200,\"message\":null,\"request\":{\"Start\":{\"service_name\":\"Service200\",\"local_port\":3000}},\"data\":{\"client_port\":3000,\"gateway_host\":\"proxyhost1\",\"gateway_port\":6000,\"service\":{\"address\":\"localhost:8200\",\"id\":200,\"name\":\"Service200\",\"transport\":\"TCP\"}}}\n");
+            }
+        }
+
+        let mut reauth_service = service.clone();
+        reauth_service.reauth_interval = Some(3600);
+        assert!(!control_plane
+            .app_config
+            .service_auth_times
+            .lock()
+            .unwrap()
+            .is_reauth_required(100, &reauth_service, Instant::now()));
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_invalid_start() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, true);
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
         let service_name = "INVALID_SERVICE";
         let local_port = 3000;
 
@@ -1170,6 +2393,9 @@ mod tests {
             ConnectionEvent::Closed => {
                 panic!("Unexpected connection event: val=Closed");
             }
+            ConnectionEvent::SetRateLimit(_) => {
+                panic!("Unexpected connection event: val=SetRateLimit");
+            }
             ConnectionEvent::Write(response_bytes) => {
                 assert_eq!(String::from_utf8(response_bytes.clone()).unwrap(),
                            "{\"code\":404,\"message\":\"Response: code=404, msg=Unknown service: svc_name=INVALID_SERVICE\",\"request\":{\"Start\":{\"service_name\":\"INVALID_SERVICE\",\"local_port\":3000}},\"data\":null}\n");
@@ -1216,6 +2442,9 @@ mod tests {
             ConnectionEvent::Closed => {
                 panic!("Unexpected connection event: val=Closed");
             }
+            ConnectionEvent::SetRateLimit(_) => {
+                panic!("Unexpected connection event: val=SetRateLimit");
+            }
             ConnectionEvent::Write(response_bytes) => {
                 assert_eq!(String::from_utf8(response_bytes.clone()).unwrap(),
                            "{\"code\":200,\"message\":null,\"request\":{\"Stop\":{\"service_name\":\"Service200\"}},\"data\":null}\n");
@@ -1262,10 +2491,731 @@ mod tests {
             ConnectionEvent::Closed => {
                 panic!("Unexpected connection event: val=Closed");
             }
+            ConnectionEvent::SetRateLimit(_) => {
+                panic!("Unexpected connection event: val=SetRateLimit");
+            }
             ConnectionEvent::Write(response_bytes) => {
                 assert_eq!(String::from_utf8(response_bytes.clone()).unwrap(),
                            "{\"code\":404,\"message\":\"Response: code=404, msg=Unknown service: svc_name=INVALID_SERVICE\",\"request\":{\"Stop\":{\"service_name\":\"INVALID_SERVICE\"}},\"data\":null}\n");
             }
         }
     }
+
+    #[test]
+    fn ctlplane_process_request_when_valid_user_status() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let user_repo = create_user_repo_for_status_change(
+            Some(model::user::User::new(
+                101,
+                "user101",
+                model::user::Status::Active,
+            )),
+            None,
+        );
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+
+        let mut control_plane = create_control_plane(
+            event_channel.0,
+            &user_repo,
+            &repos.1,
+            &repos.2,
+            device,
+            user,
+        )
+        .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!("{} -u 101", request::PROTOCOL_REQUEST_USER_STATUS),
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            request::Request::UserStatus { user_id: 101 }
+        );
+        assert_write_event(&event_channel.1,
+                           "{\"code\":200,\"message\":null,\"request\":{\"UserStatus\":{\"user_id\":101}},\"data\":{\"name\":\"user101\",\"status\":\"Active\",\"user_id\":101}}\n");
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_set_user_status_inactive() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let user_repo = create_user_repo_for_status_change(
+            Some(model::user::User::new(
+                101,
+                "user101",
+                model::user::Status::Active,
+            )),
+            Some(model::user::User::new(
+                101,
+                "user101",
+                model::user::Status::Inactive,
+            )),
+        );
+        let event_channel = mpsc::channel();
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr
+            .expect_shutdown_connections()
+            .with(predicate::eq(Some(101)), predicate::eq(None))
+            .times(1)
+            .return_once(move |_, _| Ok(()));
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(service_mgr));
+
+        let mut control_plane = create_control_plane(
+            event_channel.0,
+            &user_repo,
+            &repos.1,
+            &repos.2,
+            device,
+            user,
+        )
+        .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!(
+                "{} -u 101 -s inactive",
+                request::PROTOCOL_REQUEST_SET_USER_STATUS
+            ),
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            request::Request::SetUserStatus {
+                user_id: 101,
+                status: model::user::Status::Inactive
+            }
+        );
+        assert_write_event(&event_channel.1,
+                           "{\"code\":200,\"message\":null,\"request\":{\"SetUserStatus\":{\"user_id\":101,\"status\":\"inactive\"}},\"data\":{\"name\":\"user101\",\"status\":\"Inactive\",\"user_id\":101}}\n");
+    }
+
+    fn create_control_plane_with_reloader(
+        event_channel_sender: Sender<ConnectionEvent>,
+        datasource: &InMemoryDb,
+    ) -> Result<(ControlPlane, Arc<Mutex<dyn UserRepository>>), AppError> {
+        let access_repo: Arc<Mutex<dyn AccessRepository>> =
+            Arc::new(Mutex::new(InMemAccessRepo::new()));
+        let service_repo: Arc<Mutex<dyn ServiceRepository>> =
+            Arc::new(Mutex::new(InMemServiceRepo::new()));
+        let user_repo: Arc<Mutex<dyn UserRepository>> = Arc::new(Mutex::new(InMemUserRepo::new()));
+
+        let mut app_config = config::tests::create_app_config_with_repos(
+            user_repo.clone(),
+            service_repo.clone(),
+            access_repo.clone(),
+        )?;
+        app_config.admin_user_ids = vec![100];
+        let datasource_reloader = DatasourceReloader::new(
+            datasource,
+            &access_repo,
+            &service_repo,
+            &user_repo,
+            DatasourceErrorPolicy::FailOpen,
+            &app_config.datasource_available,
+            &app_config.datasource_lock,
+        );
+        datasource_reloader.reload_all()?;
+        app_config.datasource_reloader = Some(Arc::new(datasource_reloader));
+        app_config.datasource = config::DataSource::InMemoryDb(datasource.clone());
+
+        let control_plane = ControlPlane::new(
+            Arc::new(app_config),
+            access_repo,
+            service_repo,
+            user_repo.clone(),
+            event_channel_sender,
+            create_device()?,
+            create_user(),
+            None,
+        )?;
+
+        Ok((control_plane, user_repo))
+    }
+
+    fn recv_write_event_json(event_channel_receiver: &Receiver<ConnectionEvent>) -> Value {
+        match event_channel_receiver.try_recv() {
+            Ok(ConnectionEvent::Write(response_bytes)) => {
+                serde_json::from_slice(&response_bytes).unwrap()
+            }
+            Ok(_) => panic!("Unexpected connection event"),
+            Err(err) => panic!("Unexpected channel recv result: err={:?}", err),
+        }
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_valid_reload() {
+        let datasource = create_temp_datasource("ctlplane-reload-valid");
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+        let (mut control_plane, user_repo) =
+            create_control_plane_with_reloader(event_channel.0, &datasource).unwrap();
+
+        fs::write(
+            &datasource.user_db_file,
+            "[{\"userId\": 100, \"name\": \"User100\", \"status\": \"inactive\"}, {\"userId\": 102, \"name\": \"User102\", \"status\": \"active\"}]",
+        )
+        .unwrap();
+
+        let result = control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_RELOAD);
+
+        assert_eq!(result.unwrap(), request::Request::Reload);
+
+        let response = recv_write_event_json(&event_channel.1);
+        assert_eq!(response["code"], 200);
+        assert_eq!(response["request"], "Reload");
+        assert_eq!(
+            response["data"]["users"],
+            serde_json::json!({"added": [102], "removed": [101], "modified": [100]})
+        );
+        assert_eq!(
+            response["data"]["services"],
+            serde_json::json!({"added": [], "removed": [], "modified": []})
+        );
+        assert_eq!(user_repo.lock().unwrap().get_all().unwrap().len(), 2);
+        assert!(user_repo.lock().unwrap().get(101).unwrap().is_none());
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_reload_with_invalid_file() {
+        let datasource = create_temp_datasource("ctlplane-reload-invalid");
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+        let (mut control_plane, user_repo) =
+            create_control_plane_with_reloader(event_channel.0, &datasource).unwrap();
+
+        fs::write(
+            &datasource.user_db_file,
+            "[{\"userId\": 100, \"name\": \"User100\", \"status\": \"inactive\"}]",
+        )
+        .unwrap();
+        fs::write(&datasource.service_db_file, "[{\"serviceId\": 200,").unwrap();
+
+        let result = control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_RELOAD);
+
+        assert_eq!(result.unwrap(), request::Request::Reload);
+
+        let response = recv_write_event_json(&event_channel.1);
+        assert_eq!(response["code"], 500);
+        assert!(response["message"].is_string());
+        assert!(response["data"].is_null());
+
+        let users = user_repo.lock().unwrap().get_all().unwrap();
+        assert_eq!(users.len(), 2);
+        assert_eq!(
+            user_repo.lock().unwrap().get(100).unwrap().unwrap().status,
+            model::user::Status::Active
+        );
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_config() {
+        let datasource = create_temp_datasource("ctlplane-config");
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+        let (mut control_plane, _) =
+            create_control_plane_with_reloader(event_channel.0, &datasource).unwrap();
+
+        let result = control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_CONFIG);
+
+        assert_eq!(result.unwrap(), request::Request::Config);
+
+        let response = recv_write_event_json(&event_channel.1);
+        assert_eq!(response["code"], 200);
+        assert_eq!(response["request"], "Config");
+        assert_eq!(response["data"]["server_port"], 2000);
+        assert_eq!(response["data"]["datasource"]["type"], "in-memory-db");
+        assert_eq!(response["data"]["admin_user_ids"], serde_json::json!([100]));
+        assert_eq!(response["data"]["tls"]["key"], "<redacted>");
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_config_and_not_admin() {
+        let device = create_device().unwrap();
+        let user = model::user::User::new(101, "user101", model::user::Status::Active);
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
+        let result = control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_CONFIG);
+
+        assert_eq!(result.unwrap(), request::Request::Config);
+
+        let response = recv_write_event_json(&event_channel.1);
+        assert_eq!(response["code"], 403);
+        assert!(response["data"].is_null());
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_maintenance_set_and_cleared() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_shutdown_connections().never();
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(service_mgr));
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!(
+                "{} -m \"Upgrade in progress\"",
+                request::PROTOCOL_REQUEST_MAINTENANCE
+            ),
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            request::Request::Maintenance {
+                message: Some("Upgrade in progress".to_string())
+            }
+        );
+        let response = recv_write_event_json(&event_channel.1);
+        assert_eq!(response["code"], 200);
+        assert_eq!(response["data"]["maintenance"], true);
+        assert_eq!(response["data"]["message"], "Upgrade in progress");
+        assert_eq!(
+            *control_plane.app_config.maintenance_message.lock().unwrap(),
+            Some("Upgrade in progress".to_string())
+        );
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!("{} --clear", request::PROTOCOL_REQUEST_MAINTENANCE),
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            request::Request::Maintenance { message: None }
+        );
+        let response = recv_write_event_json(&event_channel.1);
+        assert_eq!(response["code"], 200);
+        assert_eq!(response["data"]["maintenance"], false);
+        assert!(control_plane
+            .app_config
+            .maintenance_message
+            .lock()
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_maintenance_and_not_admin() {
+        let device = create_device().unwrap();
+        let user = model::user::User::new(101, "user101", model::user::Status::Active);
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!("{} -m down", request::PROTOCOL_REQUEST_MAINTENANCE),
+        );
+
+        assert!(result.is_ok());
+        let response = recv_write_event_json(&event_channel.1);
+        assert_eq!(response["code"], 403);
+        assert!(control_plane
+            .app_config
+            .maintenance_message
+            .lock()
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_maintenance_and_read_only() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+
+        let mut control_plane = create_control_plane_with_read_only(
+            event_channel.0,
+            &repos.0,
+            &repos.1,
+            &repos.2,
+            device,
+            user,
+            true,
+        )
+        .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!("{} -m down", request::PROTOCOL_REQUEST_MAINTENANCE),
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(recv_write_event_json(&event_channel.1)["code"], 403);
+        assert!(control_plane
+            .app_config
+            .maintenance_message
+            .lock()
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_service_stats() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+
+        let mut service_mgr = MockSvcMgr::new();
+        let service_proxies: Vec<Arc<Mutex<dyn GatewayServiceProxyVisitor>>> =
+            [(200, 2, 5, 3), (200, 1, 1, 0), (201, 4, 4, 0)]
+                .into_iter()
+                .map(|(service_id, active, opened, closed)| {
+                    let mut service_proxy = MockGwSvcProxyVisitor::new();
+                    service_proxy
+                        .expect_get_service()
+                        .return_const(model::service::Service::new(
+                            service_id,
+                            &format!("Service{}", service_id),
+                            &model::service::Transport::TCP,
+                            "localhost",
+                            8200,
+                        ));
+                    service_proxy
+                        .expect_get_proxy_stats()
+                        .return_const(ProxyStats {
+                            active_connections: active,
+                            connections_opened: opened,
+                            connections_closed: closed,
+                        });
+                    let service_proxy: Arc<Mutex<dyn GatewayServiceProxyVisitor>> =
+                        Arc::new(Mutex::new(service_proxy));
+                    service_proxy
+                })
+                .collect();
+        service_mgr
+            .expect_get_service_proxies()
+            .times(1)
+            .return_once(move || service_proxies);
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(service_mgr));
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+        let proxy_key = ProxyKey::new(
+            ProxyType::TcpAndTcp,
+            200,
+            Some(SocketAddr::from(([127, 0, 0, 1], 41000))),
+            None,
+        );
+        control_plane
+            .app_config
+            .service_throughput
+            .record_bytes(&proxy_key, 1024, Instant::now());
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!("{} -s Service200", request::PROTOCOL_REQUEST_SERVICE_STATS),
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            request::Request::ServiceStats {
+                service: "Service200".to_string()
+            }
+        );
+        let response = recv_write_event_json(&event_channel.1);
+        assert_eq!(response["code"], 200);
+        assert_eq!(
+            response["data"],
+            serde_json::json!({
+                "service_id": 200,
+                "service_name": "Service200",
+                "active_connections": 3,
+                "connections_opened": 6,
+                "connections_closed": 3,
+                "bytes_transferred": 1024
+            })
+        );
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_service_stats_and_unknown_service() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_get_service_proxies().never();
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(service_mgr));
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!("{} -s 999", request::PROTOCOL_REQUEST_SERVICE_STATS),
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(recv_write_event_json(&event_channel.1)["code"], 404);
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_service_stats_and_not_admin() {
+        let device = create_device().unwrap();
+        let user = model::user::User::new(101, "user101", model::user::Status::Active);
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_get_service_proxies().never();
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(service_mgr));
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!("{} -s Service200", request::PROTOCOL_REQUEST_SERVICE_STATS),
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(recv_write_event_json(&event_channel.1)["code"], 403);
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_reload_and_not_admin() {
+        let device = create_device().unwrap();
+        let user = model::user::User::new(101, "user101", model::user::Status::Active);
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
+        let result = control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_RELOAD);
+
+        assert_eq!(result.unwrap(), request::Request::Reload);
+        assert_eq!(recv_write_event_json(&event_channel.1)["code"], 403);
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_set_user_status_active() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let user_repo = create_user_repo_for_status_change(
+            Some(model::user::User::new(
+                101,
+                "user101",
+                model::user::Status::Inactive,
+            )),
+            Some(model::user::User::new(
+                101,
+                "user101",
+                model::user::Status::Active,
+            )),
+        );
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+
+        let mut control_plane = create_control_plane(
+            event_channel.0,
+            &user_repo,
+            &repos.1,
+            &repos.2,
+            device,
+            user,
+        )
+        .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!(
+                "{} -u 101 -s active",
+                request::PROTOCOL_REQUEST_SET_USER_STATUS
+            ),
+        );
+
+        assert!(result.is_ok());
+        assert_write_event(&event_channel.1,
+                           "{\"code\":200,\"message\":null,\"request\":{\"SetUserStatus\":{\"user_id\":101,\"status\":\"active\"}},\"data\":{\"name\":\"user101\",\"status\":\"Active\",\"user_id\":101}}\n");
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_set_user_status_and_unknown_user() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let user_repo = create_user_repo_for_status_change(None, None);
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+
+        let mut control_plane = create_control_plane(
+            event_channel.0,
+            &user_repo,
+            &repos.1,
+            &repos.2,
+            device,
+            user,
+        )
+        .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!(
+                "{} -u 101 -s inactive",
+                request::PROTOCOL_REQUEST_SET_USER_STATUS
+            ),
+        );
+
+        assert!(result.is_ok());
+        assert_write_event(&event_channel.1,
+                           "{\"code\":404,\"message\":\"Response: code=404, msg=Unknown user: user_id=101\",\"request\":{\"SetUserStatus\":{\"user_id\":101,\"status\":\"inactive\"}},\"data\":null}\n");
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_set_user_status_and_not_admin() {
+        let device = create_device().unwrap();
+        let user = model::user::User::new(101, "user101", model::user::Status::Active);
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+
+        let mut control_plane =
+            create_control_plane(event_channel.0, &repos.0, &repos.1, &repos.2, device, user)
+                .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!(
+                "{} -u 100 -s inactive",
+                request::PROTOCOL_REQUEST_SET_USER_STATUS
+            ),
+        );
+
+        assert!(result.is_ok());
+        assert_write_event(&event_channel.1,
+                           "{\"code\":403,\"message\":\"Response: code=403, msg=User is not authorized for admin commands: user_id=101\",\"request\":{\"SetUserStatus\":{\"user_id\":100,\"status\":\"inactive\"}},\"data\":null}\n");
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_set_user_status_and_read_only() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_shutdown_connections().never();
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(service_mgr));
+
+        let mut control_plane = create_control_plane_with_read_only(
+            event_channel.0,
+            &repos.0,
+            &repos.1,
+            &repos.2,
+            device,
+            user,
+            true,
+        )
+        .unwrap();
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!(
+                "{} -u 101 -s inactive",
+                request::PROTOCOL_REQUEST_SET_USER_STATUS
+            ),
+        );
+
+        assert!(result.is_ok());
+        assert_write_event(&event_channel.1,
+                           "{\"code\":403,\"message\":\"Response: code=403, msg=Gateway is in read-only mode, mutating operations are not permitted\",\"request\":{\"SetUserStatus\":{\"user_id\":101,\"status\":\"inactive\"}},\"data\":null}\n");
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_reload_and_read_only() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, false, false);
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, false, false);
+
+        let mut control_plane = create_control_plane_with_read_only(
+            event_channel.0,
+            &repos.0,
+            &repos.1,
+            &repos.2,
+            device,
+            user,
+            true,
+        )
+        .unwrap();
+
+        let result = control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_RELOAD);
+
+        assert_eq!(result.unwrap(), request::Request::Reload);
+        assert_eq!(recv_write_event_json(&event_channel.1)["code"], 403);
+    }
+
+    #[test]
+    fn ctlplane_process_request_when_services_and_start_and_read_only() {
+        let device = create_device().unwrap();
+        let user = create_user();
+        let repos = create_repos(false, true, true);
+        let event_channel = mpsc::channel();
+        let service_mgr = create_service_mgr(false, false, true, false);
+
+        let mut control_plane = create_control_plane_with_read_only(
+            event_channel.0,
+            &repos.0,
+            &repos.1,
+            &repos.2,
+            device,
+            user,
+            true,
+        )
+        .unwrap();
+
+        let result =
+            control_plane.process_request(&service_mgr, request::PROTOCOL_REQUEST_SERVICES);
+
+        assert_eq!(result.unwrap(), request::Request::Services);
+        assert_eq!(recv_write_event_json(&event_channel.1)["code"], 200);
+
+        let result = control_plane.process_request(
+            &service_mgr,
+            &format!("{} -s Service200 -p 3000", request::PROTOCOL_REQUEST_START),
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            request::Request::Start {
+                service_name: "Service200".to_string(),
+                local_port: 3000
+            }
+        );
+        let response = recv_write_event_json(&event_channel.1);
+        assert_eq!(response["code"], 200);
+        assert_eq!(response["data"]["gateway_port"], 6000);
+    }
 }
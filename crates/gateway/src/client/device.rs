@@ -28,6 +28,12 @@ pub struct Device {
 
     /// Device certificate info
     cert_access_context: CertAccessContext,
+
+    /// Certificate serial (colon-separated hex bytes, matching CRL revoked serials)
+    cert_serial: String,
+
+    /// Whether certificate (extended) key usage permits TLS client authentication
+    client_auth_usage: bool,
 }
 
 impl Device {
@@ -75,10 +81,14 @@ impl Device {
             }
         }
 
+        let client_auth_usage = Device::has_client_auth_usage(&x509_cert)?;
+
         Ok(Self {
             cert_subj,
             cert_alt_subj,
             cert_access_context,
+            cert_serial: x509_cert.raw_serial_as_string(),
+            client_auth_usage,
         })
     }
 
@@ -97,6 +107,37 @@ impl Device {
         self.cert_access_context.clone()
     }
 
+    /// Certificate serial accessor
+    pub fn get_cert_serial(&self) -> &str {
+        &self.cert_serial
+    }
+
+    /// Whether certificate (extended) key usage permits TLS client authentication
+    pub fn is_client_auth_usage(&self) -> bool {
+        self.client_auth_usage
+    }
+
+    /// Certificate must carry the clientAuth (or any) EKU. If key usage is present, it must include digitalSignature.
+    fn has_client_auth_usage(x509_cert: &X509Certificate) -> Result<bool, AppError> {
+        let parse_err = |err| {
+            AppError::GenWithMsgAndErr(
+                "Failed to parse certificate key usage".to_string(),
+                Box::new(err),
+            )
+        };
+
+        let client_auth_eku = match x509_cert.extended_key_usage().map_err(parse_err)? {
+            Some(eku_ext) => eku_ext.value.client_auth || eku_ext.value.any,
+            None => false,
+        };
+        let signature_key_usage = match x509_cert.key_usage().map_err(parse_err)? {
+            Some(ku_ext) => ku_ext.value.digital_signature(),
+            None => true,
+        };
+
+        Ok(client_auth_eku && signature_key_usage)
+    }
+
     /// Retrieve the end-entity (aka device) certificate, must be the first one.
     fn device_cert<'a>(
         cert_chain: &'a [CertificateDer<'a>],
@@ -131,6 +172,8 @@ mod tests {
     ];
     const CERTFILE_NON_CLIENT_PATHPARTS: [&str; 3] =
         [env!("CARGO_MANIFEST_DIR"), "testdata", "non-client.crt.pem"];
+    const CERTFILE_GATEWAY_PATHPARTS: [&str; 3] =
+        [env!("CARGO_MANIFEST_DIR"), "testdata", "gateway.crt.pem"];
 
     #[test]
     fn device_new_fn_when_valid_client_cert() -> Result<(), AppError> {
@@ -142,6 +185,11 @@ mod tests {
         if let Ok(device) = &device_result {
             assert_eq!(device.cert_access_context.user_id, 100);
             assert_eq!(device.cert_access_context.platform, "Linux");
+            assert_eq!(
+                device.get_cert_serial(),
+                "37:06:62:47:05:a1:b9:cd:5f:36:4a:b5:93:d1:1b:0e:43:70:66:25"
+            );
+            assert!(device.is_client_auth_usage());
             return Ok(());
         }
 
@@ -165,12 +213,25 @@ mod tests {
                 device.cert_access_context.platform,
                 default_device.cert_access_context.platform
             );
+            assert!(!device.is_client_auth_usage());
             return Ok(());
         }
 
         panic!("Unexpected result: val={:?}", &device_result);
     }
 
+    #[test]
+    fn device_new_fn_when_server_auth_only_cert() -> Result<(), AppError> {
+        let certs_file: PathBuf = CERTFILE_GATEWAY_PATHPARTS.iter().collect();
+        let certs = load_certificates(certs_file.to_str().unwrap().to_string())?;
+
+        let device = Device::new(certs)?;
+
+        assert!(!device.is_client_auth_usage());
+
+        Ok(())
+    }
+
     #[test]
     fn device_new_fn_when_no_certificates() -> Result<(), AppError> {
         let device_result = Device::new(vec![]);
@@ -1,5 +1,8 @@
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::net::IpAddr;
+use std::process;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
 use clap::*;
 use dnsclient::sync::DNSClient;
@@ -8,18 +11,50 @@ use pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
 
 use crate::repository::access_repo::in_memory_repo::InMemAccessRepo;
 use crate::repository::access_repo::AccessRepository;
+use crate::repository::diff::{diff_datasources, DatasourceDiff, DatasourceSnapshot};
+use crate::repository::reloader::DatasourceReloader;
 use crate::repository::service_repo::in_memory_repo::InMemServiceRepo;
 use crate::repository::service_repo::ServiceRepository;
 use crate::repository::user_repo::in_memory_repo::InMemUserRepo;
 use crate::repository::user_repo::UserRepository;
+use crate::repository::validation::validate_access_references;
+use crate::service::activity::{ActivityMetricsSink, ServiceActivity};
+use crate::service::circuit_breaker::CircuitBreaker;
+use crate::service::dns_cache::ServiceAddrsCache;
+use crate::service::quota::{QuotaMetricsSink, UserByteQuotas};
+use crate::service::rate_limit::ServiceRateLimits;
+use crate::service::reauth::ServiceAuthTimes;
+use crate::service::throughput::{ServiceThroughput, ThroughputMetricsSink};
 use regex::Regex;
 use rustls::crypto::CryptoProvider;
 use rustls::server::danger::ClientCertVerifier;
-use rustls::server::WebPkiClientVerifier;
+use rustls::server::{Accepted, WebPkiClientVerifier};
+use serde_derive::Serialize;
+use trust0_common::backoff::ExponentialJitterBackoff;
+use trust0_common::clock::{Clock, SystemClock};
+use trust0_common::conn_events::{
+    ConnEventSink, NdjsonFileConnEventSink, NdjsonUdpConnEventSink, NoOpConnEventSink,
+};
 use trust0_common::crypto::alpn;
 use trust0_common::crypto::file::CRLFile;
 use trust0_common::crypto::file::{load_certificates, load_private_key};
 use trust0_common::error::AppError;
+use trust0_common::logging::warn;
+use trust0_common::metrics::{MetricsSink, NoOpMetricsSink, StatsdMetricsSink};
+use trust0_common::model::service::ClientAuth;
+use trust0_common::net::tls_server::conn_std::ReadWatermarks;
+use trust0_common::target;
+
+/// Metric name prefix (for metrics sinks supporting namespacing)
+const METRICS_PREFIX: &str = "trust0.gateway";
+/// Placeholder for secret values in configuration dumps
+const REDACTED_VALUE: &str = "<redacted>";
+/// Window (in seconds) over which rolling proxy throughput rates are averaged
+const THROUGHPUT_WINDOW_SECS: u64 = 10;
+/// Initial delay between retried connect-time service host lookups
+const DNS_CONNECT_RETRY_INITIAL_DELAY_MSECS: u64 = 100;
+/// Maximum delay between retried connect-time service host lookups
+const DNS_CONNECT_RETRY_MAX_DELAY_MSECS: u64 = 2000;
 
 /// Client response messages
 pub const RESPCODE_0403_FORBIDDEN: u16 = 403;
@@ -29,7 +64,12 @@ pub const RESPCODE_0422_INACTIVE_USER: u16 = 422;
 pub const RESPCODE_0423_INVALID_REQUEST: u16 = 423;
 pub const RESPCODE_0424_INVALID_ALPN_PROTOCOL: u16 = 424;
 pub const RESPCODE_0425_INACTIVE_SERVICE_PROXY: u16 = 425;
+pub const RESPCODE_0426_USER_QUOTA_EXCEEDED: u16 = 426;
+pub const RESPCODE_0427_REAUTH_REQUIRED: u16 = 427;
+pub const RESPCODE_0428_INVALID_CERTIFICATE_USAGE: u16 = 428;
+pub const RESPCODE_0429_SERVICE_RATE_EXCEEDED: u16 = 429;
 pub const RESPCODE_0500_SYSTEM_ERROR: u16 = 500;
+pub const RESPCODE_0503_GATEWAY_MAINTENANCE: u16 = 503;
 pub const RESPCODE_0520_UNKNOWN_CODE: u16 = 520;
 const RESPMSG_0403_FORBIDDEN: &str = "[E0403] Access is forbidden";
 const RESPMSG_0420_INVALID_CLIENT_CERTIFICATE: &str = "[E0420] Invalid client certificate";
@@ -38,7 +78,13 @@ const RESPMSG_0422_INACTIVE_USER: &str = "[E0422] User account is inactive";
 const RESPMSG_0423_INVALID_REQUEST: &str = "[E0423] Invalid request";
 const RESPMSG_0424_INVALID_ALPN_PROTOCOL: &str = "[E0424] Invalid ALPN protocol";
 const RESPMSG_0425_INACTIVE_SERVICE_PROXY: &str = "[E0425] Inactive service proxy";
+const RESPMSG_0426_USER_QUOTA_EXCEEDED: &str = "[E0426] User byte quota exceeded";
+const RESPMSG_0427_REAUTH_REQUIRED: &str = "[E0427] Service re-authorization required";
+const RESPMSG_0428_INVALID_CERTIFICATE_USAGE: &str =
+    "[E0428] Client certificate usage does not permit client authentication";
+const RESPMSG_0429_SERVICE_RATE_EXCEEDED: &str = "[E0429] Service request rate exceeded";
 const RESPMSG_0500_SYSTEM_ERROR: &str = "[E0500] System error occurred";
+const RESPMSG_0503_GATEWAY_MAINTENANCE: &str = "[E0503] Gateway is under maintenance";
 const RESPMSG_0520_UNKNOWN_CODE: &str = "[E0520] System error occurred";
 
 lazy_static! {
@@ -60,14 +106,32 @@ lazy_static! {
                 RESPCODE_0425_INACTIVE_SERVICE_PROXY,
                 RESPMSG_0425_INACTIVE_SERVICE_PROXY,
             ),
+            (
+                RESPCODE_0426_USER_QUOTA_EXCEEDED,
+                RESPMSG_0426_USER_QUOTA_EXCEEDED,
+            ),
+            (RESPCODE_0427_REAUTH_REQUIRED, RESPMSG_0427_REAUTH_REQUIRED),
+            (
+                RESPCODE_0428_INVALID_CERTIFICATE_USAGE,
+                RESPMSG_0428_INVALID_CERTIFICATE_USAGE,
+            ),
+            (
+                RESPCODE_0429_SERVICE_RATE_EXCEEDED,
+                RESPMSG_0429_SERVICE_RATE_EXCEEDED,
+            ),
             (RESPCODE_0500_SYSTEM_ERROR, RESPMSG_0500_SYSTEM_ERROR),
+            (
+                RESPCODE_0503_GATEWAY_MAINTENANCE,
+                RESPMSG_0503_GATEWAY_MAINTENANCE,
+            ),
             (RESPCODE_0520_UNKNOWN_CODE, RESPMSG_0520_UNKNOWN_CODE),
         ])
     };
 }
 
 /// Which mode the server operates in.
-#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum ServerMode {
     /// Control-plane for service gateway management
     #[default]
@@ -77,10 +141,60 @@ pub enum ServerMode {
     Proxy,
 }
 
+/// Behavior when datasource (re)loading encounters read/parse errors.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DatasourceErrorPolicy {
+    /// Keep serving using the previously loaded data
+    #[default]
+    FailOpen,
+
+    /// Deny new connections until a subsequent reload succeeds
+    FailClosed,
+}
+
+/// Service access outcome for users without an explicit access entry
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AccessDefault {
+    /// Deny access, unless explicitly granted
+    #[default]
+    Deny,
+
+    /// Allow (active) users access to all services, unless explicitly denied
+    Allow,
+}
+
+/// Behavior when a datasource file contains multiple entries with the same ID
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicateIdPolicy {
+    /// Keep the last entry (logging a warning)
+    #[default]
+    KeepLast,
+
+    /// Fail datasource loading
+    Strict,
+}
+
+/// Handling of (shared/gateway port) connections without a recognizable ALPN protocol
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnrecognizedAlpnPolicy {
+    /// Reject the connection (424 invalid ALPN protocol error)
+    #[default]
+    Reject,
+
+    /// Treat the connection as a control plane connection
+    ControlPlane,
+}
+
 /// Datasource configuration for the trust framework entities
-#[derive(Subcommand, Debug, Clone)]
+#[derive(Subcommand, Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
 pub enum DataSource {
     /// No DB configured, used in testing
+    #[serde(rename = "no-db")]
     NoDB,
 
     /// In-memory DB, with a simple backing persistence store
@@ -97,15 +211,27 @@ impl DataSource {
         Box<dyn Fn() -> Arc<Mutex<dyn ServiceRepository>>>,
         Box<dyn Fn() -> Arc<Mutex<dyn UserRepository>>>,
     ) {
+        let service_repo_factory: Box<dyn Fn() -> Arc<Mutex<dyn ServiceRepository>>> = match self {
+            DataSource::InMemoryDb(args) => {
+                let duplicate_service_ids = args.duplicate_service_ids;
+                Box::new(move || {
+                    Arc::new(Mutex::new(InMemServiceRepo::new_with_duplicate_id_policy(
+                        duplicate_service_ids,
+                    )))
+                })
+            }
+            DataSource::NoDB => Box::new(|| Arc::new(Mutex::new(InMemServiceRepo::new()))),
+        };
+
         (
             Box::new(|| Arc::new(Mutex::new(InMemAccessRepo::new()))),
-            Box::new(|| Arc::new(Mutex::new(InMemServiceRepo::new()))),
+            service_repo_factory,
             Box::new(|| Arc::new(Mutex::new(InMemUserRepo::new()))),
         )
     }
 }
 
-#[derive(Args, Debug, Clone)]
+#[derive(Args, Debug, Clone, Serialize)]
 pub struct InMemoryDb {
     /// (Service) Access entity store JSON file path
     #[arg(required = true, short = 'a', long = "access-db-file", env)]
@@ -118,6 +244,16 @@ pub struct InMemoryDb {
     /// User entity store JSON file path
     #[arg(required = true, short = 'u', long = "user-db-file", env)]
     pub user_db_file: String,
+
+    /// Behavior for multiple services with the same service ID (in the service store file)
+    #[arg(
+        required = false,
+        value_enum,
+        long = "duplicate-service-ids",
+        env,
+        default_value_t = DuplicateIdPolicy::KeepLast
+    )]
+    pub duplicate_service_ids: DuplicateIdPolicy,
 }
 
 /// Runs a trust0 gateway server on :PORT.  The default PORT is 443.
@@ -145,10 +281,32 @@ pub struct AppConfigArgs {
     pub key_file: String,
 
     /// Accept client authentication certificates signed by those roots provided in <AUTH_CERT_FILE>
-    #[arg(required=true, short='a', long="auth-cert-file", env, value_parser=trust0_common::crypto::file::verify_certificates)]
-    pub auth_cert_file: String,
+    #[arg(required_unless_present="auth_use_system_roots", short='a', long="auth-cert-file", env, value_parser=trust0_common::crypto::file::verify_certificates)]
+    pub auth_cert_file: Option<String>,
+
+    /// Accept client authentication certificates signed by the platform's (system) trust store roots. May be combined with <AUTH_CERT_FILE>.
+    /// CAUTION: any CA trusted by the platform will be able to issue acceptable client certificates
+    #[arg(
+        required = false,
+        long = "auth-use-system-roots",
+        env,
+        default_value_t = false
+    )]
+    pub auth_use_system_roots: bool,
+
+    /// TLS client certificate authentication requirement. If optional, service proxy connections without a client certificate are accepted (as the anonymous user) for services not overriding this to required. Control plane connections always require a client certificate
+    #[arg(required = false, value_enum, long = "client-auth", env)]
+    pub client_auth: Option<ClientAuth>,
+
+    /// Reject client certificates lacking the clientAuth extended key usage, or whose key usage (if present) does not permit digital signatures
+    #[arg(required = false, long = "require-client-auth-eku", env)]
+    pub require_client_auth_eku: bool,
+
+    /// Reset (RST) connections denied by authorization, rather than gracefully closing (FIN) them
+    #[arg(required = false, long = "abortive-close-on-denial", env)]
+    pub abortive_close_on_denial: bool,
 
-    /// EXPERIMENTAL. Perform client certificate revocation checking using the DER-encoded <CRL_FILE(s)>. Will update list during runtime, if file has changed.
+    /// EXPERIMENTAL. Perform client certificate revocation checking using the DER-encoded <CRL_FILE(s)>. Will update list during runtime, if file has changed, closing active service proxy connections for newly-revoked certificates.
     #[cfg(feature = "experimental-crl")]
     #[arg(required=false, long="crl-file", env, value_parser=trust0_common::crypto::file::verify_crl_list)]
     pub crl_file: Option<String>,
@@ -168,6 +326,10 @@ pub struct AppConfigArgs {
     #[arg(required=false, long="alpn-protocol", env, value_parser=trust0_common::crypto::tls::parse_alpn_protocol)]
     pub alpn_protocol: Option<Vec<Vec<u8>>>,
 
+    /// Maximum number of service ALPN protocols advertised. For service catalogs exceeding this, service protocols aren't advertised statically, rather (up to this many) are selected per connection from the recognizable trust0 protocols offered by the client
+    #[arg(required = false, long = "max-alpn-protocols", env)]
+    pub max_alpn_protocols: Option<usize>,
+
     /// Support session resumption
     #[arg(required = false, long = "session-resumption", env)]
     pub session_resumption: bool,
@@ -184,10 +346,100 @@ pub struct AppConfigArgs {
     #[arg(required=false, long="gateway-service-ports", env, value_parser=crate::config::AppConfig::parse_gateway_service_ports)]
     pub gateway_service_ports: Option<(u16, u16)>,
 
+    /// Bind each service proxy on an OS-assigned ephemeral port (reported to clients), rather than using the primary gateway port or a service proxy port range
+    #[arg(
+        required = false,
+        long = "gateway-service-ephemeral-ports",
+        conflicts_with = "gateway_service_ports",
+        env
+    )]
+    pub gateway_service_ephemeral_ports: bool,
+
+    /// Persist service proxy port assignments (for a service port range) to this state file, and reuse them (when still available) after a restart
+    #[arg(required = false, long = "service-ports-state-file", env)]
+    pub service_ports_state_file: Option<String>,
+
     /// Hostname/ip of this gateway, which is routable by UDP services, used in UDP socket replies. If not supplied, then "127.0.0.1" will be used (if necessary)
     #[arg(required = false, long = "gateway-service-reply-host", env)]
     pub gateway_service_reply_host: Option<String>,
 
+    /// Number of worker threads used to poll service proxy listeners
+    #[arg(required = false, long = "worker-threads", env, default_value_t = 4)]
+    pub worker_threads: usize,
+
+    /// Number of accept workers per (gateway and service proxy) listener, each accepting new connections from the shared listening socket. Increase to improve accept throughput under bursty connects
+    #[arg(required = false, long = "accept-workers", env, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub accept_workers: u16,
+
+    /// Number of TLS handshake workers per (gateway and service proxy) listener. Accepted connections wait (a bounded number per worker) for a free handshake worker, and are closed when all are busy and the wait queue is full
+    #[arg(required = false, long = "handshake-workers", env, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    pub handshake_workers: u16,
+
+    /// Maximum expected number of tracked service proxy connections. A warning is logged when this is exceeded
+    #[arg(
+        required = false,
+        long = "max-proxy-keys",
+        env,
+        default_value_t = 10000
+    )]
+    pub max_proxy_keys: usize,
+
+    /// Interval (in seconds) to reconcile tracked service proxy connections, dropping those no longer active
+    #[arg(
+        required = false,
+        long = "proxy-key-reconcile-interval",
+        env,
+        default_value_t = 60,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub proxy_key_reconcile_interval: u64,
+
+    /// Maximum time (in seconds) a started service proxy may remain without any connections, before it is torn
+    /// down and its port reclaimed (checked at each proxy key reconciliation). A zero value disables this
+    #[arg(
+        required = false,
+        long = "service-reservation-ttl",
+        env,
+        default_value_t = 0
+    )]
+    pub service_reservation_ttl: u64,
+
+    /// Maximum time (in seconds) a TCP service proxy connection may remain without activity (data relayed since it was
+    /// accepted), before it is closed (checked at each proxy key reconciliation). A zero value disables this
+    #[arg(required = false, long = "tcp-idle-timeout", env, default_value_t = 0)]
+    pub tcp_idle_timeout: u64,
+
+    /// Maximum time (in seconds) a UDP service proxy connection may remain without activity (datagrams relayed since it
+    /// was accepted), before it is closed (checked at each proxy key reconciliation). A zero value disables this
+    #[arg(required = false, long = "udp-idle-timeout", env, default_value_t = 0)]
+    pub udp_idle_timeout: u64,
+
+    /// Maximum number of distinct services a user may have active (service proxy) connections to at once. Further
+    /// service connections are refused, until the user's last connection to one of those services closes
+    #[arg(required = false, long = "max-services-per-user", env)]
+    pub max_services_per_user: Option<usize>,
+
+    /// Control plane connection read high watermark (in bytes). Client reads are paused while this many bytes of
+    /// (processed request) responses remain unsent, and each read is capped at the remaining headroom (so this should
+    /// exceed the largest request size). Unbounded if not given
+    #[arg(required = false, long = "read-high-water-mark", env)]
+    pub read_high_water_mark: Option<usize>,
+
+    /// Control plane connection read low watermark (in bytes). Paused client reads resume once unsent responses drop
+    /// to this. Defaults to half the high watermark
+    #[arg(required = false, long = "read-low-water-mark", env)]
+    pub read_low_water_mark: Option<usize>,
+
+    /// Maximum time (in seconds) to wait on shutdown (SIGTERM/SIGINT), once listeners have stopped, for active service
+    /// proxy connections to finish. Remaining connections are then closed. A zero value closes them immediately
+    #[arg(
+        required = false,
+        long = "shutdown-drain-timeout",
+        env,
+        default_value_t = 30
+    )]
+    pub shutdown_drain_timeout: u64,
+
     /// Enable verbose logging
     #[arg(required = false, long = "verbose", env)]
     pub verbose: bool,
@@ -196,25 +448,168 @@ pub struct AppConfigArgs {
     #[arg(required = false, long = "no-mask-addrs", default_value_t = false, env)]
     pub no_mask_addresses: bool,
 
+    /// User ID(s) permitted to issue administrative control plane commands (for instance, changing a user's status)
+    #[arg(required = false, long = "admin-user-ids", value_delimiter = ',', env)]
+    pub admin_user_ids: Option<Vec<u64>>,
+
+    /// Read-only (standby/observer) mode: mutating administrative operations (service/user/access changes, datasource reloads, runtime connection shutdowns) are rejected, while proxy connections and read queries are still served
+    #[arg(required = false, long = "read-only", env)]
+    pub read_only: bool,
+
+    /// Send metrics (connection lifecycle, auth denials, bytes transferred) to the statsd server at <METRICS_STATSD_ADDR> (format "{host}:{port}")
+    #[arg(required = false, long = "metrics-statsd-addr", env)]
+    pub metrics_statsd_addr: Option<String>,
+
+    /// Emit a per-user gauge of the user's active service proxy connection (session) count, which is zeroed once the user has no sessions
+    #[arg(required = false, long = "user-session-metrics", env)]
+    pub user_session_metrics: bool,
+
+    /// Append connection lifecycle events (connection opened/closed, auth decisions, opened events include the negotiated TLS version, cipher suite and ALPN protocol), as newline-delimited JSON, to the file at <CONN_EVENTS_FILE>
+    #[arg(required = false, long = "conn-events-file", env)]
+    pub conn_events_file: Option<String>,
+
+    /// Send connection lifecycle events (connection opened/closed, auth decisions, opened events include the negotiated TLS version, cipher suite and ALPN protocol), as newline-delimited JSON (one event per datagram), to the UDP server at <CONN_EVENTS_UDP_ADDR> (format "{host}:{port}")
+    #[arg(
+        required = false,
+        long = "conn-events-udp-addr",
+        env,
+        conflicts_with = "conn_events_file"
+    )]
+    pub conn_events_udp_addr: Option<String>,
+
+    /// Default maximum bytes each user may transfer (via service proxies) per quota window. Users with their own byte quota use that instead. New connections are refused once reached
+    #[arg(required = false, long = "user-byte-quota", env)]
+    pub user_byte_quota: Option<u64>,
+
+    /// User byte quota window (in seconds)
+    #[arg(
+        required = false,
+        long = "user-byte-quota-window",
+        env,
+        default_value_t = 86400,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub user_byte_quota_window: u64,
+
+    /// Default TTL (in seconds) for cached service upstream address resolutions (0 disables caching, unless set for the service)
+    #[arg(required = false, long = "dns-cache-ttl", env, default_value_t = 60)]
+    pub dns_cache_ttl: u64,
+
+    /// Maximum time (in seconds) past expiry, to keep using the last good service upstream addresses when re-resolution fails
+    #[arg(
+        required = false,
+        long = "dns-cache-max-stale",
+        env,
+        default_value_t = 300
+    )]
+    pub dns_cache_max_stale: u64,
+
+    /// Timeout (in milliseconds) for each service upstream host lookup made while connecting a client (0 disables the timeout)
+    #[arg(
+        required = false,
+        long = "dns-connect-timeout",
+        env,
+        default_value_t = 5000
+    )]
+    pub dns_connect_timeout: u64,
+
+    /// Number of retries for failed/timed out service upstream host lookups made while connecting a client. Connections are refused (425) once exhausted
+    #[arg(
+        required = false,
+        long = "dns-connect-retries",
+        env,
+        default_value_t = 2
+    )]
+    pub dns_connect_retries: u32,
+
+    /// Number of consecutive connect failures to a service upstream, after which new dials to it are short-circuited (for the cooldown period). A zero value disables the circuit breaker
+    #[arg(
+        required = false,
+        long = "circuit-breaker-failures",
+        env,
+        default_value_t = 0
+    )]
+    pub circuit_breaker_failures: u32,
+
+    /// Time (in seconds) an opened upstream circuit short-circuits dials, before a trial dial is permitted
+    #[arg(
+        required = false,
+        long = "circuit-breaker-cooldown",
+        env,
+        default_value_t = 30
+    )]
+    pub circuit_breaker_cooldown: u64,
+
+    /// Local (source) IP address to bind service upstream connections to (unless set for the service). Otherwise, the OS selects the source address
+    #[arg(required = false, long = "upstream-bind-addr", env)]
+    pub upstream_bind_addr: Option<IpAddr>,
+
     /// Server mode: startup server as control-plane, or as a stand-alone service gateway node
     #[arg(required = false, value_enum, long = "mode", env)]
     pub mode: Option<ServerMode>,
 
+    /// Datasource reload error policy: keep serving prior data (fail-open), or deny new connections until a good reload (fail-closed)
+    #[arg(required = false, value_enum, long = "datasource-error-policy", env)]
+    pub datasource_error_policy: Option<DatasourceErrorPolicy>,
+
+    /// Service access for users without an explicit access entry. Explicit deny entries are always enforced
+    #[arg(required = false, value_enum, long = "access-default", env)]
+    pub access_default: Option<AccessDefault>,
+
+    /// Handling of gateway port connections, whose ALPN protocol is missing or not a recognizable control plane/service protocol: reject (424 error), or treat as control plane. In shared-port mode, service connections must always supply their service ALPN protocol
+    #[arg(required = false, value_enum, long = "unrecognized-alpn-policy", env)]
+    pub unrecognized_alpn_policy: Option<UnrecognizedAlpnPolicy>,
+
+    /// Watch datasource files, and reload repositories when those files change
+    #[arg(required = false, long = "watch-db-files", env)]
+    pub watch_db_files: bool,
+
+    /// Compare the configured datasource against the given DB files, print the differences and exit
+    #[arg(required = false, long = "diff-datasource", num_args = 3, value_names = ["ACCESS_DB_FILE", "SERVICE_DB_FILE", "USER_DB_FILE"])]
+    pub diff_datasource: Option<Vec<String>>,
+
+    /// Validate the configured datasource (access entries must reference existing users and services), print any problems and exit
+    #[arg(required = false, long = "check-config")]
+    pub check_config: bool,
+
+    /// Run a startup self-test (TLS configuration, auth root certificates, datasource repositories), logging a PASS/FAIL summary. Startup is aborted if any check fails
+    #[arg(required = false, long = "self-test", env)]
+    pub self_test: bool,
+
     /// DB datasource configuration
     #[command(subcommand)]
     pub datasource: DataSource,
 }
 
 /// TLS server configuration builder
+#[derive(Serialize)]
 pub struct TlsServerConfigBuilder {
+    #[serde(rename = "cert_count", serialize_with = "config_dump::count")]
     pub certs: Vec<CertificateDer<'static>>,
+    #[serde(serialize_with = "config_dump::redacted")]
     pub key: PrivateKeyDer<'static>,
+    #[serde(serialize_with = "config_dump::cipher_suites")]
     pub cipher_suites: Vec<rustls::SupportedCipherSuite>,
+    #[serde(serialize_with = "config_dump::protocol_versions")]
     pub protocol_versions: Vec<&'static rustls::SupportedProtocolVersion>,
+    #[serde(
+        rename = "auth_root_cert_count",
+        serialize_with = "config_dump::root_cert_count"
+    )]
     pub auth_root_certs: rustls::RootCertStore,
+    #[serde(rename = "crl_enabled", serialize_with = "config_dump::is_some")]
     pub crl_file: Option<Arc<Mutex<CRLFile>>>,
     pub session_resumption: bool,
+    #[serde(serialize_with = "config_dump::alpn_protocols")]
     pub alpn_protocols: Vec<Vec<u8>>,
+    /// Maximum number of (service) ALPN protocols advertised (None is unlimited)
+    pub max_alpn_protocols: Option<usize>,
+    /// Whether service ALPN protocols are selected per connection (from those offered by the client), as the service
+    /// catalog exceeded the maximum advertised protocols
+    pub select_offered_alpn: bool,
+    /// Default client certificate authentication requirement (services may override this). The TLS handshake always
+    /// accepts clients without a certificate, connection authorization enforces the effective (per service) requirement
+    pub client_auth: ClientAuth,
 }
 
 impl TlsServerConfigBuilder {
@@ -247,45 +642,197 @@ impl TlsServerConfigBuilder {
         Ok(tls_server_config)
     }
 
-    /// Build a TLS client verifier
-    #[cfg(feature = "experimental-crl")]
-    fn build_client_cert_verifier(&self) -> Result<Arc<dyn ClientCertVerifier>, AppError> {
-        let crl_list = match &self.crl_file {
-            Some(crl_file) => vec![crl_file.lock().unwrap().crl_list()?],
-            None => vec![],
-        };
+    /// Create TLS server configuration for an accepted client hello. If service ALPN protocols are selected per
+    /// connection, the client's offered (recognizable) protocols are advertised.
+    pub fn build_for_client_hello(
+        &self,
+        accepted: &Accepted,
+    ) -> Result<rustls::ServerConfig, AppError> {
+        let mut tls_server_config = self.build()?;
+
+        if self.select_offered_alpn {
+            tls_server_config.alpn_protocols = self.select_alpn_protocols(
+                accepted
+                    .client_hello()
+                    .alpn()
+                    .map(|protocols| protocols.collect()),
+            );
+        }
 
-        Ok(
-            WebPkiClientVerifier::builder(Arc::new(self.auth_root_certs.clone()))
-                .with_crls(crl_list)
-                .build()
-                .unwrap(),
-        )
+        Ok(tls_server_config)
     }
-    #[cfg(not(feature = "experimental-crl"))]
+
+    /// Build (statically) advertised ALPN protocols for given service IDs: the control plane protocol and a protocol per
+    /// service. When the service count exceeds the maximum, only the control plane protocol is returned. Returns the
+    /// protocols and whether service protocols need to be selected per connection.
+    pub fn build_alpn_protocols(
+        service_ids: &[u64],
+        max_alpn_protocols: Option<usize>,
+    ) -> (Vec<Vec<u8>>, bool) {
+        let mut alpn_protocols = vec![alpn::Protocol::ControlPlane.to_string().into_bytes()];
+
+        if max_alpn_protocols.is_some_and(|max_protocols| service_ids.len() > max_protocols) {
+            warn(
+                &target!(),
+                &format!(
+                    "Service catalog exceeds maximum advertised ALPN protocols, selecting per connection: services={}, max={}",
+                    service_ids.len(),
+                    max_alpn_protocols.unwrap()
+                ),
+            );
+            return (alpn_protocols, true);
+        }
+
+        for service_id in service_ids {
+            alpn_protocols.push(alpn::Protocol::create_service_protocol(*service_id).into_bytes());
+        }
+        (alpn_protocols, false)
+    }
+
+    /// Select the ALPN protocols to advertise for a connection: the control plane protocol and (up to the maximum)
+    /// recognizable service (and reverse service) protocols offered by the client. Unknown services are rejected when dispatched.
+    fn select_alpn_protocols(&self, offered_protocols: Option<Vec<&[u8]>>) -> Vec<Vec<u8>> {
+        let mut alpn_protocols = vec![alpn::Protocol::ControlPlane.to_string().into_bytes()];
+
+        alpn_protocols.extend(
+            offered_protocols
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|protocol| {
+                    matches!(
+                        alpn::Protocol::try_parse(&String::from_utf8_lossy(protocol)),
+                        Ok(alpn::Protocol::Service(_))
+                            | Ok(alpn::Protocol::ServiceName(_))
+                            | Ok(alpn::Protocol::ReverseService(_))
+                    )
+                })
+                .take(self.max_alpn_protocols.unwrap_or(usize::MAX))
+                .map(|protocol| protocol.to_vec()),
+        );
+
+        alpn_protocols
+    }
+
+    /// Build a TLS client verifier
     fn build_client_cert_verifier(&self) -> Result<Arc<dyn ClientCertVerifier>, AppError> {
-        Ok(
+        #[cfg(feature = "experimental-crl")]
+        let client_cert_verifier = {
+            let crl_list = match &self.crl_file {
+                Some(crl_file) => vec![crl_file.lock().unwrap().crl_list()?],
+                None => vec![],
+            };
             WebPkiClientVerifier::builder(Arc::new(self.auth_root_certs.clone()))
-                .build()
-                .unwrap(),
-        )
+                .with_crls(crl_list)
+        };
+        #[cfg(not(feature = "experimental-crl"))]
+        let client_cert_verifier =
+            WebPkiClientVerifier::builder(Arc::new(self.auth_root_certs.clone()));
+
+        // Certificate requirement is enforced by connection authorization (per service, reflecting datasource reloads)
+        let client_cert_verifier = client_cert_verifier
+            .allow_unauthenticated()
+            .build()
+            .map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    "Error building client certificate verifier".to_string(),
+                    Box::new(err),
+                )
+            })?;
+
+        Ok(client_cert_verifier)
     }
 }
 
-/// Main application configuration/context struct
+/// Main application configuration/context struct. Serializes as the (redacted) configuration dump, runtime state
+/// handles are skipped
+#[derive(Serialize)]
 pub struct AppConfig {
     pub server_mode: ServerMode,
     pub server_port: u16,
+    #[serde(rename = "tls")]
     pub tls_server_config_builder: TlsServerConfigBuilder,
     pub verbose_logging: bool,
+    pub worker_threads: usize,
+    pub accept_workers: usize,
+    pub handshake_workers: usize,
+    pub max_proxy_keys: usize,
+    pub proxy_key_reconcile_interval: u64,
+    #[serde(serialize_with = "config_dump::duration_secs")]
+    pub service_reservation_ttl: Duration,
+    #[serde(serialize_with = "config_dump::duration_secs")]
+    pub tcp_idle_timeout: Duration,
+    #[serde(serialize_with = "config_dump::duration_secs")]
+    pub udp_idle_timeout: Duration,
+    pub max_services_per_user: Option<usize>,
+    pub read_watermarks: Option<ReadWatermarks>,
+    #[serde(serialize_with = "config_dump::duration_secs")]
+    pub shutdown_drain_timeout: Duration,
+    #[serde(skip)]
     pub access_repo: Arc<Mutex<dyn AccessRepository>>,
+    #[serde(skip)]
     pub service_repo: Arc<Mutex<dyn ServiceRepository>>,
+    #[serde(skip)]
     pub user_repo: Arc<Mutex<dyn UserRepository>>,
     pub gateway_service_host: Option<String>,
     pub gateway_service_ports: Option<(u16, u16)>,
+    pub gateway_service_ephemeral_ports: bool,
+    pub service_ports_state_file: Option<String>,
     pub gateway_service_reply_host: String,
     pub mask_addresses: bool,
-    pub dns_client: DNSClient,
+    pub admin_user_ids: Vec<u64>,
+    pub read_only: bool,
+    pub require_client_auth_eku: bool,
+    pub abortive_close_on_denial: bool,
+    /// Statsd server address (format "{host}:{port}"), the metrics sink sends to (if any)
+    pub metrics_statsd_addr: Option<String>,
+    #[serde(skip)]
+    pub metrics_sink: Arc<dyn MetricsSink>,
+    pub user_session_metrics: bool,
+    /// Connection events NDJSON file path, the connection events sink appends to (if any)
+    pub conn_events_file: Option<String>,
+    /// Connection events UDP server address (format "{host}:{port}"), the connection events sink sends to (if any)
+    pub conn_events_udp_addr: Option<String>,
+    #[serde(skip)]
+    pub conn_event_sink: Arc<dyn ConnEventSink>,
+    #[serde(serialize_with = "config_dump::shared")]
+    pub user_byte_quotas: Arc<Mutex<UserByteQuotas>>,
+    #[serde(skip)]
+    pub service_activity: Arc<ServiceActivity>,
+    #[serde(skip)]
+    pub service_throughput: Arc<ServiceThroughput>,
+    #[serde(skip)]
+    pub service_auth_times: Arc<Mutex<ServiceAuthTimes>>,
+    #[serde(skip)]
+    pub service_rate_limits: Arc<Mutex<ServiceRateLimits>>,
+    #[serde(skip)]
+    pub clock: Arc<dyn Clock>,
+    #[serde(serialize_with = "config_dump::duration_secs")]
+    pub dns_cache_ttl: Duration,
+    #[serde(serialize_with = "config_dump::shared")]
+    pub service_addrs_cache: Arc<ServiceAddrsCache>,
+    #[serde(serialize_with = "config_dump::shared")]
+    pub upstream_circuit_breaker: Arc<CircuitBreaker>,
+    pub upstream_bind_addr: Option<IpAddr>,
+    /// Configured datasource (repositories' source)
+    pub datasource: DataSource,
+    /// Whether datasource files are watched, reloading repositories when those files change
+    pub watch_db_files: bool,
+    pub datasource_error_policy: DatasourceErrorPolicy,
+    #[serde(serialize_with = "config_dump::shared")]
+    pub datasource_available: Arc<Mutex<bool>>,
+    /// Datasource consistency lock: held exclusively while a reload replaces the repositories' contents, and shared
+    /// while a connection is authorized
+    #[serde(skip)]
+    pub datasource_lock: Arc<RwLock<()>>,
+    #[serde(skip)]
+    pub datasource_reloader: Option<Arc<DatasourceReloader>>,
+    #[serde(skip)]
+    pub listener_bound: Arc<Mutex<bool>>,
+    #[serde(serialize_with = "config_dump::shared")]
+    pub maintenance_message: Arc<Mutex<Option<String>>>,
+    pub access_default: AccessDefault,
+    pub unrecognized_alpn_policy: UnrecognizedAlpnPolicy,
+    pub self_test: bool,
 }
 
 impl AppConfig {
@@ -302,9 +849,58 @@ impl AppConfig {
             &config_args.datasource.repository_factories(),
         )?;
 
+        if let Some(diff_db_files) = &config_args.diff_datasource {
+            let datasource_diff = Self::diff_datasource(&repositories, diff_db_files)?;
+            if datasource_diff.is_empty() {
+                println!("No datasource differences");
+            } else {
+                println!("{}", datasource_diff);
+            }
+            process::exit(0);
+        }
+
+        let dangling_access =
+            validate_access_references(&repositories.0, &repositories.1, &repositories.2)?;
+        if config_args.check_config {
+            if dangling_access.is_empty() {
+                println!("No datasource problems");
+                process::exit(0);
+            }
+            for access in &dangling_access {
+                println!("{}", access);
+            }
+            process::exit(1);
+        }
+
+        let datasource_error_policy = config_args.datasource_error_policy.unwrap_or_default();
+        let datasource_available = Arc::new(Mutex::new(true));
+        let datasource_lock = Arc::new(RwLock::new(()));
+
+        let datasource_reloader = match &config_args.datasource {
+            DataSource::InMemoryDb(args) => {
+                let datasource_reloader = DatasourceReloader::new(
+                    args,
+                    &repositories.0,
+                    &repositories.1,
+                    &repositories.2,
+                    datasource_error_policy,
+                    &datasource_available,
+                    &datasource_lock,
+                );
+                if config_args.watch_db_files {
+                    datasource_reloader.spawn_reloader(None);
+                }
+                Some(Arc::new(datasource_reloader))
+            }
+            _ => None,
+        };
+
         // create TLS server configuration builder
 
-        let auth_certs = load_certificates(config_args.auth_cert_file.clone()).unwrap();
+        let auth_certs = match &config_args.auth_cert_file {
+            Some(auth_cert_file) => load_certificates(auth_cert_file.clone()).unwrap(),
+            None => vec![],
+        };
         let certs = load_certificates(config_args.cert_file.clone()).unwrap();
         let key = load_private_key(config_args.key_file.clone()).unwrap();
 
@@ -326,10 +922,8 @@ impl AppConfig {
             None
         };
 
-        let mut auth_root_certs = rustls::RootCertStore::empty();
-        for auth_root_cert in auth_certs {
-            auth_root_certs.add(auth_root_cert).unwrap();
-        }
+        let auth_root_certs =
+            Self::build_auth_root_store(auth_certs, config_args.auth_use_system_roots)?;
 
         let cipher_suites: Vec<rustls::SupportedCipherSuite> = config_args
             .cipher_suite
@@ -339,11 +933,17 @@ impl AppConfig {
             .unwrap_or(rustls::ALL_VERSIONS.to_vec());
         let session_resumption = config_args.session_resumption;
 
-        let mut alpn_protocols = vec![alpn::Protocol::ControlPlane.to_string().into_bytes()];
-        for service in repositories.1.as_ref().lock().unwrap().get_all()? {
-            alpn_protocols
-                .push(alpn::Protocol::create_service_protocol(service.service_id).into_bytes())
-        }
+        let client_auth = config_args.client_auth.unwrap_or_default();
+
+        let services = repositories.1.as_ref().lock().unwrap().get_all()?;
+        let (alpn_protocols, select_offered_alpn) = TlsServerConfigBuilder::build_alpn_protocols(
+            &services
+                .iter()
+                .filter(|service| service.proxyable)
+                .map(|service| service.service_id)
+                .collect::<Vec<u64>>(),
+            config_args.max_alpn_protocols,
+        );
 
         let tls_server_config_builder = TlsServerConfigBuilder {
             certs,
@@ -354,13 +954,65 @@ impl AppConfig {
             crl_file,
             session_resumption,
             alpn_protocols,
+            max_alpn_protocols: config_args.max_alpn_protocols,
+            select_offered_alpn,
+            client_auth,
         };
 
         // Miscellaneous
 
+        let metrics_sink: Arc<dyn MetricsSink> = match &config_args.metrics_statsd_addr {
+            Some(statsd_addr) => Arc::new(StatsdMetricsSink::new(statsd_addr, METRICS_PREFIX)?),
+            None => Arc::new(NoOpMetricsSink),
+        };
+        let conn_event_sink: Arc<dyn ConnEventSink> = match (
+            &config_args.conn_events_file,
+            &config_args.conn_events_udp_addr,
+        ) {
+            (Some(filepath), _) => Arc::new(NdjsonFileConnEventSink::new(filepath)?),
+            (None, Some(server_addr)) => Arc::new(NdjsonUdpConnEventSink::new(server_addr)?),
+            (None, None) => Arc::new(NoOpConnEventSink),
+        };
+        let user_byte_quotas = Arc::new(Mutex::new(UserByteQuotas::new(
+            config_args.user_byte_quota,
+            Duration::from_secs(config_args.user_byte_quota_window),
+        )));
+        let metrics_sink: Arc<dyn MetricsSink> = Arc::new(QuotaMetricsSink::new(
+            metrics_sink,
+            user_byte_quotas.clone(),
+        ));
+        let service_activity = Arc::new(ServiceActivity::new());
+        let metrics_sink: Arc<dyn MetricsSink> = Arc::new(ActivityMetricsSink::new(
+            metrics_sink,
+            service_activity.clone(),
+        ));
+        let service_throughput = Arc::new(ServiceThroughput::new(Duration::from_secs(
+            THROUGHPUT_WINDOW_SECS,
+        )));
+        let metrics_sink: Arc<dyn MetricsSink> = Arc::new(ThroughputMetricsSink::new(
+            metrics_sink,
+            service_throughput.clone(),
+        ));
+
         let dns_client = DNSClient::new_with_system_resolvers().map_err(|err| {
             AppError::GenWithMsgAndErr("Error instantiating DNSClient".to_string(), Box::new(err))
         })?;
+        let dns_cache_ttl = Duration::from_secs(config_args.dns_cache_ttl);
+        let mut service_addrs_cache = ServiceAddrsCache::new(
+            Arc::new(dns_client),
+            dns_cache_ttl,
+            Duration::from_secs(config_args.dns_cache_max_stale),
+        );
+        service_addrs_cache.set_connect_lookup_policy(
+            Duration::from_millis(config_args.dns_connect_timeout),
+            config_args.dns_connect_retries,
+            Arc::new(ExponentialJitterBackoff::new(
+                Duration::from_millis(DNS_CONNECT_RETRY_INITIAL_DELAY_MSECS),
+                2,
+                Duration::from_millis(DNS_CONNECT_RETRY_MAX_DELAY_MSECS),
+            )),
+        );
+        let service_addrs_cache = Arc::new(service_addrs_cache);
 
         // Instantiate AppConfig
 
@@ -369,22 +1021,71 @@ impl AppConfig {
             server_port: config_args.port,
             tls_server_config_builder,
             verbose_logging: config_args.verbose,
+            worker_threads: config_args.worker_threads,
+            accept_workers: config_args.accept_workers as usize,
+            handshake_workers: config_args.handshake_workers as usize,
+            max_proxy_keys: config_args.max_proxy_keys,
+            proxy_key_reconcile_interval: config_args.proxy_key_reconcile_interval,
+            service_reservation_ttl: Duration::from_secs(config_args.service_reservation_ttl),
+            tcp_idle_timeout: Duration::from_secs(config_args.tcp_idle_timeout),
+            udp_idle_timeout: Duration::from_secs(config_args.udp_idle_timeout),
+            max_services_per_user: config_args.max_services_per_user,
+            read_watermarks: Self::build_read_watermarks(
+                config_args.read_high_water_mark,
+                config_args.read_low_water_mark,
+            )?,
+            shutdown_drain_timeout: Duration::from_secs(config_args.shutdown_drain_timeout),
             access_repo: repositories.0,
             service_repo: repositories.1,
             user_repo: repositories.2,
             gateway_service_host: config_args.gateway_service_host,
             gateway_service_ports: config_args.gateway_service_ports,
+            gateway_service_ephemeral_ports: config_args.gateway_service_ephemeral_ports,
+            service_ports_state_file: config_args.service_ports_state_file,
             gateway_service_reply_host: config_args
                 .gateway_service_reply_host
                 .unwrap_or("127.0.0.1".to_string()),
             mask_addresses: !config_args.no_mask_addresses,
-            dns_client,
+            admin_user_ids: config_args.admin_user_ids.unwrap_or_default(),
+            read_only: config_args.read_only,
+            require_client_auth_eku: config_args.require_client_auth_eku,
+            abortive_close_on_denial: config_args.abortive_close_on_denial,
+            metrics_statsd_addr: config_args.metrics_statsd_addr,
+            metrics_sink,
+            user_session_metrics: config_args.user_session_metrics,
+            conn_events_file: config_args.conn_events_file,
+            conn_events_udp_addr: config_args.conn_events_udp_addr,
+            conn_event_sink,
+            user_byte_quotas,
+            service_activity,
+            service_throughput,
+            service_auth_times: Arc::new(Mutex::new(ServiceAuthTimes::new())),
+            service_rate_limits: Arc::new(Mutex::new(ServiceRateLimits::new())),
+            clock: Arc::new(SystemClock),
+            dns_cache_ttl,
+            service_addrs_cache,
+            upstream_circuit_breaker: Arc::new(CircuitBreaker::new(
+                config_args.circuit_breaker_failures,
+                Duration::from_secs(config_args.circuit_breaker_cooldown),
+            )),
+            upstream_bind_addr: config_args.upstream_bind_addr,
+            datasource: config_args.datasource,
+            watch_db_files: config_args.watch_db_files,
+            datasource_error_policy,
+            datasource_available,
+            datasource_lock,
+            datasource_reloader,
+            listener_bound: Arc::new(Mutex::new(false)),
+            maintenance_message: Arc::new(Mutex::new(None)),
+            access_default: config_args.access_default.unwrap_or_default(),
+            unrecognized_alpn_policy: config_args.unrecognized_alpn_policy.unwrap_or_default(),
+            self_test: config_args.self_test,
         })
     }
 
     #[allow(clippy::type_complexity)]
     /// Instantiate main repositories based on datasource config. Returns tuple of access, service and user repositories.
-    fn create_datasource_repositories(
+    pub(crate) fn create_datasource_repositories(
         datasource: &DataSource,
         repo_factories: &(
             Box<dyn Fn() -> Arc<Mutex<dyn AccessRepository>>>,
@@ -421,6 +1122,132 @@ impl AppConfig {
         Ok((access_repository, service_repository, user_repository))
     }
 
+    #[allow(clippy::type_complexity)]
+    /// Compare given (loaded) repositories against the access, service and user DB files
+    fn diff_datasource(
+        repositories: &(
+            Arc<Mutex<dyn AccessRepository>>,
+            Arc<Mutex<dyn ServiceRepository>>,
+            Arc<Mutex<dyn UserRepository>>,
+        ),
+        diff_db_files: &[String],
+    ) -> Result<DatasourceDiff, AppError> {
+        let diff_datasource = DataSource::InMemoryDb(InMemoryDb {
+            access_db_file: diff_db_files[0].clone(),
+            service_db_file: diff_db_files[1].clone(),
+            user_db_file: diff_db_files[2].clone(),
+            duplicate_service_ids: DuplicateIdPolicy::default(),
+        });
+        let diff_repositories = Self::create_datasource_repositories(
+            &diff_datasource,
+            &diff_datasource.repository_factories(),
+        )?;
+
+        Ok(diff_datasources(
+            &DatasourceSnapshot::from_repositories(
+                &repositories.0,
+                &repositories.1,
+                &repositories.2,
+            )?,
+            &DatasourceSnapshot::from_repositories(
+                &diff_repositories.0,
+                &diff_repositories.1,
+                &diff_repositories.2,
+            )?,
+        ))
+    }
+
+    /// Build client authentication root certificate store, from the given (auth cert file) certificates
+    /// and (optionally) the platform's trust store roots
+    fn build_auth_root_store(
+        auth_certs: Vec<CertificateDer<'static>>,
+        use_system_roots: bool,
+    ) -> Result<rustls::RootCertStore, AppError> {
+        let mut auth_root_certs = rustls::RootCertStore::empty();
+
+        for auth_root_cert in auth_certs {
+            auth_root_certs.add(auth_root_cert).map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    "Error adding auth root certificate".to_string(),
+                    Box::new(err),
+                )
+            })?;
+        }
+
+        if use_system_roots {
+            let system_root_certs = rustls_native_certs::load_native_certs().map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    "Error loading system trust store root certificates".to_string(),
+                    Box::new(err),
+                )
+            })?;
+            let (_, ignored) = auth_root_certs.add_parsable_certificates(system_root_certs);
+            if ignored > 0 {
+                warn(
+                    &target!(),
+                    &format!(
+                        "Ignored unparsable system trust store root certificates: count={}",
+                        ignored
+                    ),
+                );
+            }
+        }
+
+        if auth_root_certs.is_empty() {
+            return Err(AppError::General(
+                "No client authentication root certificates available".to_string(),
+            ));
+        }
+
+        Ok(auth_root_certs)
+    }
+
+    /// Build control plane connection read watermarks, from the given high/low watermark byte counts. The low watermark
+    /// defaults to half the high watermark
+    fn build_read_watermarks(
+        high_water_mark: Option<usize>,
+        low_water_mark: Option<usize>,
+    ) -> Result<Option<ReadWatermarks>, AppError> {
+        let high = match high_water_mark {
+            Some(high) => high,
+            None if low_water_mark.is_some() => {
+                return Err(AppError::General(
+                    "Read low watermark given without a read high watermark".to_string(),
+                ))
+            }
+            None => return Ok(None),
+        };
+        let low = low_water_mark.unwrap_or(high / 2);
+
+        if (high == 0) || (low > high) {
+            return Err(AppError::General(format!(
+                "Invalid read watermarks (0 < high and low <= high required): high={}, low={}",
+                high, low
+            )));
+        }
+
+        Ok(Some(ReadWatermarks { high, low }))
+    }
+
+    /// Ensure mutating administrative operations are permitted (the gateway isn't in read-only mode)
+    pub fn validate_writable(&self) -> Result<(), AppError> {
+        if self.read_only {
+            return Err(AppError::GenWithCodeAndMsg(
+                RESPCODE_0403_FORBIDDEN,
+                "Gateway is in read-only mode, mutating operations are not permitted".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Effective configuration (for diagnostics), as a JSON value. Secrets (key material) are redacted, and only
+    /// summary (count) information is given for certificates
+    pub fn to_redacted_json(&self) -> Result<serde_json::Value, AppError> {
+        serde_json::to_value(self).map_err(|err| {
+            AppError::GenWithMsgAndErr("Error serializing configuration".to_string(), Box::new(err))
+        })
+    }
+
     /// Parse service port range (format "{port_start:u16}-{port_end:u16}")
     fn parse_gateway_service_ports(
         gateway_service_ports_str: &str,
@@ -449,6 +1276,89 @@ impl AppConfig {
     }
 }
 
+/// Serde serialization of configuration values, for the (redacted) configuration dump
+pub(crate) mod config_dump {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use serde::{Serialize, Serializer};
+
+    use super::REDACTED_VALUE;
+
+    pub fn duration_secs<S: Serializer>(
+        duration: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_secs())
+    }
+
+    pub fn duration_millis<S: Serializer>(
+        duration: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn redacted<T, S: Serializer>(_secret: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED_VALUE)
+    }
+
+    pub fn count<T, S: Serializer>(items: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(items.len() as u64)
+    }
+
+    pub fn is_some<T, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(value.is_some())
+    }
+
+    pub fn shared<T: Serialize + ?Sized, S: Serializer>(
+        value: &Arc<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.as_ref().serialize(serializer)
+    }
+
+    pub fn root_cert_count<S: Serializer>(
+        root_certs: &rustls::RootCertStore,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(root_certs.len() as u64)
+    }
+
+    pub fn cipher_suites<S: Serializer>(
+        cipher_suites: &[rustls::SupportedCipherSuite],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            cipher_suites
+                .iter()
+                .map(|suite| format!("{:?}", suite.suite())),
+        )
+    }
+
+    pub fn protocol_versions<S: Serializer>(
+        protocol_versions: &[&'static rustls::SupportedProtocolVersion],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            protocol_versions
+                .iter()
+                .map(|version| format!("{:?}", version.version)),
+        )
+    }
+
+    pub fn alpn_protocols<S: Serializer>(
+        alpn_protocols: &[Vec<u8>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            alpn_protocols
+                .iter()
+                .map(|protocol| String::from_utf8_lossy(protocol)),
+        )
+    }
+}
+
 /// Unit tests
 #[cfg(test)]
 pub mod tests {
@@ -463,9 +1373,9 @@ pub mod tests {
     use mockall::predicate;
     use std::path::PathBuf;
 
-    const CERTFILE_GATEWAY_PATHPARTS: [&str; 3] =
+    pub const CERTFILE_GATEWAY_PATHPARTS: [&str; 3] =
         [env!("CARGO_MANIFEST_DIR"), "testdata", "gateway.crt.pem"];
-    const KEYFILE_GATEWAY_PATHPARTS: [&str; 3] =
+    pub const KEYFILE_GATEWAY_PATHPARTS: [&str; 3] =
         [env!("CARGO_MANIFEST_DIR"), "testdata", "gateway.key.pem"];
 
     // Utilities
@@ -496,6 +1406,9 @@ pub mod tests {
             crl_file: None,
             session_resumption,
             alpn_protocols,
+            max_alpn_protocols: None,
+            select_offered_alpn: false,
+            client_auth: ClientAuth::Required,
         };
 
         Ok(AppConfig {
@@ -503,19 +1416,71 @@ pub mod tests {
             server_port: 2000,
             tls_server_config_builder,
             verbose_logging: false,
+            worker_threads: 2,
+            accept_workers: 1,
+            handshake_workers: 4,
+            max_proxy_keys: 10000,
+            proxy_key_reconcile_interval: 60,
+            service_reservation_ttl: Duration::ZERO,
+            tcp_idle_timeout: Duration::ZERO,
+            udp_idle_timeout: Duration::ZERO,
+            max_services_per_user: None,
+            read_watermarks: None,
+            shutdown_drain_timeout: Duration::ZERO,
             access_repo,
             service_repo,
             user_repo,
             gateway_service_host: None,
             gateway_service_ports: None,
+            gateway_service_ephemeral_ports: false,
+            service_ports_state_file: None,
             gateway_service_reply_host: "".to_string(),
             mask_addresses: false,
-            dns_client: DNSClient::new_with_system_resolvers().map_err(|err| {
-                AppError::GenWithMsgAndErr(
-                    "Error instantiating DNSClient".to_string(),
-                    Box::new(err),
-                )
-            })?,
+            admin_user_ids: vec![],
+            read_only: false,
+            require_client_auth_eku: false,
+            abortive_close_on_denial: false,
+            metrics_statsd_addr: None,
+            metrics_sink: Arc::new(NoOpMetricsSink),
+            user_session_metrics: false,
+            conn_events_file: None,
+            conn_events_udp_addr: None,
+            conn_event_sink: Arc::new(NoOpConnEventSink),
+            user_byte_quotas: Arc::new(Mutex::new(UserByteQuotas::new(
+                None,
+                Duration::from_secs(86400),
+            ))),
+            service_activity: Arc::new(ServiceActivity::new()),
+            service_throughput: Arc::new(ServiceThroughput::new(Duration::from_secs(
+                THROUGHPUT_WINDOW_SECS,
+            ))),
+            service_auth_times: Arc::new(Mutex::new(ServiceAuthTimes::new())),
+            service_rate_limits: Arc::new(Mutex::new(ServiceRateLimits::new())),
+            clock: Arc::new(SystemClock),
+            dns_cache_ttl: Duration::ZERO,
+            service_addrs_cache: Arc::new(ServiceAddrsCache::new(
+                Arc::new(DNSClient::new_with_system_resolvers().map_err(|err| {
+                    AppError::GenWithMsgAndErr(
+                        "Error instantiating DNSClient".to_string(),
+                        Box::new(err),
+                    )
+                })?),
+                Duration::ZERO,
+                Duration::ZERO,
+            )),
+            upstream_circuit_breaker: Arc::new(CircuitBreaker::new(0, Duration::ZERO)),
+            upstream_bind_addr: None,
+            datasource: DataSource::NoDB,
+            watch_db_files: false,
+            datasource_error_policy: DatasourceErrorPolicy::FailOpen,
+            datasource_available: Arc::new(Mutex::new(true)),
+            datasource_lock: Arc::new(RwLock::new(())),
+            datasource_reloader: None,
+            listener_bound: Arc::new(Mutex::new(false)),
+            maintenance_message: Arc::new(Mutex::new(None)),
+            access_default: AccessDefault::Deny,
+            unrecognized_alpn_policy: UnrecognizedAlpnPolicy::Reject,
+            self_test: false,
         })
     }
 
@@ -537,6 +1502,36 @@ pub mod tests {
         panic!("Unexpected result: val={:?}", &result);
     }
 
+    #[test]
+    fn appconfig_build_read_watermarks_when_not_given() {
+        assert_eq!(AppConfig::build_read_watermarks(None, None).unwrap(), None);
+    }
+
+    #[test]
+    fn appconfig_build_read_watermarks_when_high_given() {
+        assert_eq!(
+            AppConfig::build_read_watermarks(Some(1000), None).unwrap(),
+            Some(ReadWatermarks {
+                high: 1000,
+                low: 500
+            })
+        );
+        assert_eq!(
+            AppConfig::build_read_watermarks(Some(1000), Some(100)).unwrap(),
+            Some(ReadWatermarks {
+                high: 1000,
+                low: 100
+            })
+        );
+    }
+
+    #[test]
+    fn appconfig_build_read_watermarks_when_invalid() {
+        assert!(AppConfig::build_read_watermarks(None, Some(100)).is_err());
+        assert!(AppConfig::build_read_watermarks(Some(0), None).is_err());
+        assert!(AppConfig::build_read_watermarks(Some(100), Some(200)).is_err());
+    }
+
     #[test]
     pub fn appconfig_create_datasource_repositories_when_inmemdb_ds() {
         let repo_factories: (
@@ -577,6 +1572,7 @@ pub mod tests {
             access_db_file: "adf".to_string(),
             service_db_file: "sdf".to_string(),
             user_db_file: "udf".to_string(),
+            duplicate_service_ids: DuplicateIdPolicy::default(),
         });
 
         let result = AppConfig::create_datasource_repositories(&datasource, &repo_factories);
@@ -618,4 +1614,381 @@ pub mod tests {
             panic!("Unexpected result: err={:?}", err);
         }
     }
+
+    #[test]
+    pub fn appconfig_diff_datasource_when_service_removed_from_loaded_repo() {
+        let db_files: Vec<String> = ["db-access.json", "db-service.json", "db-user.json"]
+            .iter()
+            .map(|db_file| {
+                PathBuf::from_iter([env!("CARGO_MANIFEST_DIR"), "testdata", db_file])
+                    .to_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        let datasource = DataSource::InMemoryDb(InMemoryDb {
+            access_db_file: db_files[0].clone(),
+            service_db_file: db_files[1].clone(),
+            user_db_file: db_files[2].clone(),
+            duplicate_service_ids: DuplicateIdPolicy::default(),
+        });
+        let repositories = AppConfig::create_datasource_repositories(
+            &datasource,
+            &datasource.repository_factories(),
+        )
+        .unwrap();
+        repositories.1.lock().unwrap().delete(200).unwrap();
+
+        let result = AppConfig::diff_datasource(&repositories, &db_files);
+
+        match result {
+            Ok(diff) => {
+                assert_eq!(diff.services.added, vec![200]);
+                assert!(diff.services.removed.is_empty());
+                assert!(diff.services.modified.is_empty());
+                assert!(diff.users.is_empty());
+                assert!(diff.access.is_empty());
+            }
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        }
+    }
+
+    #[test]
+    pub fn appconfig_build_auth_root_store_when_only_auth_certs() {
+        let gateway_cert_file: PathBuf = CERTFILE_GATEWAY_PATHPARTS.iter().collect();
+        let auth_certs =
+            load_certificates(gateway_cert_file.to_str().unwrap().to_string()).unwrap();
+        let auth_certs_len = auth_certs.len();
+
+        let result = AppConfig::build_auth_root_store(auth_certs, false);
+
+        match result {
+            Ok(auth_root_certs) => assert_eq!(auth_root_certs.len(), auth_certs_len),
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        }
+    }
+
+    #[test]
+    pub fn appconfig_build_auth_root_store_when_only_system_roots() {
+        let result = AppConfig::build_auth_root_store(vec![], true);
+
+        match result {
+            Ok(auth_root_certs) => assert!(!auth_root_certs.is_empty()),
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        }
+    }
+
+    #[test]
+    pub fn appconfig_build_auth_root_store_when_auth_certs_and_system_roots() {
+        let gateway_cert_file: PathBuf = CERTFILE_GATEWAY_PATHPARTS.iter().collect();
+        let auth_certs =
+            load_certificates(gateway_cert_file.to_str().unwrap().to_string()).unwrap();
+        let auth_certs_len = auth_certs.len();
+
+        let result = AppConfig::build_auth_root_store(auth_certs, true);
+
+        match result {
+            Ok(auth_root_certs) => assert!(auth_root_certs.len() > auth_certs_len),
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        }
+    }
+
+    #[test]
+    pub fn appconfig_build_auth_root_store_when_no_roots() {
+        let result = AppConfig::build_auth_root_store(vec![], false);
+
+        if let Ok(auth_root_certs) = result {
+            panic!(
+                "Unexpected successful result: len={}",
+                auth_root_certs.len()
+            );
+        }
+    }
+
+    #[test]
+    pub fn tlssvrcfgbuilder_build_alpn_protocols_when_within_max() {
+        let (alpn_protocols, select_offered_alpn) =
+            TlsServerConfigBuilder::build_alpn_protocols(&[200, 201, 202], Some(3));
+
+        assert!(!select_offered_alpn);
+        assert_eq!(
+            alpn_protocols,
+            vec![
+                alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec(),
+                "T0SRV200".as_bytes().to_vec(),
+                "T0SRV201".as_bytes().to_vec(),
+                "T0SRV202".as_bytes().to_vec(),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn tlssvrcfgbuilder_build_alpn_protocols_when_large_catalog() {
+        let service_ids: Vec<u64> = (1..=10_000).collect();
+
+        let (alpn_protocols, select_offered_alpn) =
+            TlsServerConfigBuilder::build_alpn_protocols(&service_ids, Some(100));
+
+        assert!(select_offered_alpn);
+        assert_eq!(
+            alpn_protocols,
+            vec![alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec()]
+        );
+
+        let (alpn_protocols, select_offered_alpn) =
+            TlsServerConfigBuilder::build_alpn_protocols(&service_ids, None);
+
+        assert!(!select_offered_alpn);
+        assert_eq!(alpn_protocols.len(), 10_001);
+    }
+
+    #[test]
+    pub fn tlssvrcfgbuilder_select_alpn_protocols_when_offered() {
+        let mut app_config = create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        app_config.tls_server_config_builder.max_alpn_protocols = Some(3);
+        app_config.tls_server_config_builder.select_offered_alpn = true;
+
+        let offered_protocols: Vec<&[u8]> = vec![
+            "h2".as_bytes(),
+            "T0SRV9999".as_bytes(),
+            "T0SRVNAME-chat".as_bytes(),
+            "T0REVSRV200".as_bytes(),
+            "T0SRV10000".as_bytes(),
+        ];
+        let alpn_protocols = app_config
+            .tls_server_config_builder
+            .select_alpn_protocols(Some(offered_protocols));
+
+        assert_eq!(
+            alpn_protocols,
+            vec![
+                alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec(),
+                "T0SRV9999".as_bytes().to_vec(),
+                "T0SRVNAME-chat".as_bytes().to_vec(),
+                "T0REVSRV200".as_bytes().to_vec(),
+            ]
+        );
+        assert_eq!(
+            app_config
+                .tls_server_config_builder
+                .select_alpn_protocols(None),
+            vec![alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec()]
+        );
+    }
+
+    #[test]
+    pub fn tlssvrcfgbuilder_build_client_cert_verifier_when_client_auth_required() {
+        let mut app_config = create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        let gateway_cert_file: PathBuf = CERTFILE_GATEWAY_PATHPARTS.iter().collect();
+        app_config.tls_server_config_builder.auth_root_certs = AppConfig::build_auth_root_store(
+            load_certificates(gateway_cert_file.to_str().unwrap().to_string()).unwrap(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            app_config.tls_server_config_builder.client_auth,
+            ClientAuth::Required
+        );
+
+        let client_cert_verifier = app_config
+            .tls_server_config_builder
+            .build_client_cert_verifier()
+            .unwrap();
+
+        assert!(client_cert_verifier.offer_client_auth());
+        assert!(!client_cert_verifier.client_auth_mandatory());
+    }
+
+    #[test]
+    fn appcfg_to_redacted_json() {
+        let mut app_config = create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        app_config.admin_user_ids = vec![100];
+        app_config.gateway_service_ports = Some((8400, 8410));
+        app_config.tls_server_config_builder.cipher_suites =
+            vec![rustls::crypto::ring::cipher_suite::TLS13_AES_256_GCM_SHA384];
+        app_config.tls_server_config_builder.protocol_versions = vec![&rustls::version::TLS13];
+
+        *app_config.maintenance_message.lock().unwrap() = Some("upgrade".to_string());
+        app_config.metrics_statsd_addr = Some("127.0.0.1:8125".to_string());
+        app_config.conn_events_file = Some("/tmp/conn_events.ndjson".to_string());
+        app_config.shutdown_drain_timeout = Duration::from_secs(15);
+        app_config.user_byte_quotas = Arc::new(Mutex::new(UserByteQuotas::new(
+            Some(1000),
+            Duration::from_secs(3600),
+        )));
+
+        let config_json = app_config.to_redacted_json().unwrap();
+
+        assert_eq!(config_json["server_mode"], "control-plane");
+        assert_eq!(config_json["server_port"], 2000);
+        assert_eq!(config_json["accept_workers"], 1);
+        assert_eq!(config_json["admin_user_ids"], serde_json::json!([100]));
+        assert_eq!(
+            config_json["gateway_service_ports"],
+            serde_json::json!([8400, 8410])
+        );
+        assert_eq!(config_json["datasource"]["type"], "no-db");
+        assert_eq!(config_json["maintenance_message"], "upgrade");
+        assert_eq!(config_json["metrics_statsd_addr"], "127.0.0.1:8125");
+        assert_eq!(config_json["conn_events_file"], "/tmp/conn_events.ndjson");
+        assert_eq!(config_json["shutdown_drain_timeout"], 15);
+        assert_eq!(
+            config_json["user_byte_quotas"],
+            serde_json::json!({"default_byte_quota": 1000, "window": 3600})
+        );
+        assert!(config_json.get("access_repo").is_none());
+        assert!(config_json.get("metrics_sink").is_none());
+        assert_eq!(config_json["datasource_error_policy"], "fail-open");
+        assert_eq!(config_json["access_default"], "deny");
+        assert_eq!(config_json["tls"]["cert_count"], 1);
+        assert_eq!(config_json["tls"]["key"], REDACTED_VALUE);
+        assert_eq!(
+            config_json["tls"]["cipher_suites"],
+            serde_json::json!(["TLS13_AES_256_GCM_SHA384"])
+        );
+        assert_eq!(
+            config_json["tls"]["protocol_versions"],
+            serde_json::json!(["TLSv1_3"])
+        );
+        assert_eq!(
+            config_json["tls"]["alpn_protocols"],
+            serde_json::json!([alpn::PROTOCOL_CONTROL_PLANE])
+        );
+        assert_eq!(config_json["tls"]["client_auth"], "required");
+    }
+
+    #[test]
+    fn appcfg_to_redacted_json_when_in_memory_db_datasource() {
+        let mut app_config = create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        app_config.datasource = DataSource::InMemoryDb(InMemoryDb {
+            access_db_file: "access.json".to_string(),
+            service_db_file: "service.json".to_string(),
+            user_db_file: "user.json".to_string(),
+            duplicate_service_ids: DuplicateIdPolicy::Strict,
+        });
+
+        let config_json = app_config.to_redacted_json().unwrap();
+
+        assert_eq!(
+            config_json["datasource"],
+            serde_json::json!({
+                "type": "in-memory-db",
+                "access_db_file": "access.json",
+                "service_db_file": "service.json",
+                "user_db_file": "user.json",
+                "duplicate_service_ids": "strict",
+            })
+        );
+    }
+
+    #[test]
+    fn appcfg_to_redacted_json_covers_all_config_args() {
+        // Arguments dumped under a different key (possibly nested), or not retained (one-shot commands)
+        let dumped_keys_by_arg_id = HashMap::from([
+            ("port", Some("server_port")),
+            ("cert_file", Some("cert_count")),
+            ("key_file", Some("key")),
+            ("auth_cert_file", Some("auth_root_cert_count")),
+            ("auth_use_system_roots", Some("auth_root_cert_count")),
+            ("crl_file", Some("crl_enabled")),
+            ("protocol_version", Some("protocol_versions")),
+            ("cipher_suite", Some("cipher_suites")),
+            ("alpn_protocol", Some("alpn_protocols")),
+            ("tickets", None),
+            ("read_high_water_mark", Some("read_watermarks")),
+            ("read_low_water_mark", Some("read_watermarks")),
+            ("verbose", Some("verbose_logging")),
+            ("no_mask_addresses", Some("mask_addresses")),
+            ("user_byte_quota", Some("default_byte_quota")),
+            ("user_byte_quota_window", Some("window")),
+            ("dns_cache_max_stale", Some("max_stale")),
+            ("dns_connect_timeout", Some("connect_timeout")),
+            ("dns_connect_retries", Some("connect_retries")),
+            ("circuit_breaker_failures", Some("failure_threshold")),
+            ("circuit_breaker_cooldown", Some("cooldown")),
+            ("mode", Some("server_mode")),
+            ("diff_datasource", None),
+            ("check_config", None),
+        ]);
+
+        fn collect_keys(value: &serde_json::Value, keys: &mut Vec<String>) {
+            if let serde_json::Value::Object(fields) = value {
+                for (key, field_value) in fields {
+                    keys.push(key.clone());
+                    collect_keys(field_value, keys);
+                }
+            }
+        }
+
+        let app_config = create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        let mut config_keys = vec![];
+        collect_keys(&app_config.to_redacted_json().unwrap(), &mut config_keys);
+
+        let missing_arg_ids: Vec<String> = AppConfigArgs::command()
+            .get_arguments()
+            .map(|arg| arg.get_id().to_string())
+            .filter(|arg_id| (arg_id != "help") && (arg_id != "version"))
+            .filter(|arg_id| match dumped_keys_by_arg_id.get(arg_id.as_str()) {
+                Some(Some(dumped_key)) => !config_keys.iter().any(|key| key == dumped_key),
+                Some(None) => false,
+                None => !config_keys.contains(arg_id),
+            })
+            .collect();
+
+        assert!(
+            missing_arg_ids.is_empty(),
+            "Config args missing from dump: args={:?}",
+            &missing_arg_ids
+        );
+        assert!(config_keys.contains(&"datasource".to_string()));
+    }
+
+    #[test]
+    fn appcfg_to_redacted_json_excludes_private_key() {
+        let app_config = create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        let gateway_key_file: PathBuf = KEYFILE_GATEWAY_PATHPARTS.iter().collect();
+        let gateway_key_pem = std::fs::read_to_string(gateway_key_file).unwrap();
+
+        let config_str = app_config.to_redacted_json().unwrap().to_string();
+
+        assert!(!config_str.contains("PRIVATE KEY"));
+        for key_pem_line in gateway_key_pem
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+        {
+            assert!(!config_str.contains(key_pem_line));
+        }
+        let key_der = app_config.tls_server_config_builder.key.secret_der();
+        assert!(!config_str.contains(&format!("{:?}", &key_der[..8])));
+    }
 }
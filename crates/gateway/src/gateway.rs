@@ -1,19 +1,30 @@
+use std::collections::HashMap;
+use std::net::{SocketAddr, TcpStream};
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use rustls::server::Accepted;
 use rustls::ServerConfig;
 
-use crate::client::connection::ClientConnVisitor;
+use crate::client::connection::{self, ClientConnVisitor};
 use crate::client::controller::ControlPlaneServerVisitor;
+use crate::client::device::Device;
 use crate::config::{self, AppConfig};
-use crate::service::manager::ServiceMgr;
+use crate::service::manager::{ReverseClientSession, ServiceMgr};
 use crate::service::proxy::proxy_base::GatewayServiceProxyVisitor;
+use trust0_common::conn_events::{self, ConnEvent, ConnEventType};
+use trust0_common::crypto;
 use trust0_common::crypto::alpn::Protocol;
 use trust0_common::error::AppError;
+use trust0_common::model::service::{Service, Transport};
 use trust0_common::net::tls_server::conn_std::{TlsConnection, TlsServerConnection};
 use trust0_common::net::tls_server::{conn_std, server_std};
 
+/// Interval to check for active service proxy connections, while draining them on shutdown
+const SHUTDOWN_DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// The Trust0 Gateway TLS Server
 pub struct Gateway {
     _app_config: Arc<AppConfig>,
@@ -25,17 +36,23 @@ pub struct Gateway {
 impl Gateway {
     /// Gateway constructor
     pub fn new(app_config: Arc<AppConfig>, visitor: Arc<Mutex<ServerVisitor>>) -> Self {
+        let mut tls_server = server_std::Server::new(visitor.clone(), app_config.server_port);
+        tls_server.set_accept_workers(app_config.accept_workers);
+        tls_server.set_handshake_workers(app_config.handshake_workers);
+
         Self {
             _app_config: Arc::clone(&app_config),
             _server_mode: app_config.server_mode,
-            tls_server: server_std::Server::new(visitor.clone(), app_config.server_port),
+            tls_server,
             _visitor: visitor,
         }
     }
 
     /// Bind/listen on port
     pub fn bind_listener(&mut self) -> Result<(), AppError> {
-        self.tls_server.bind_listener()
+        self.tls_server.bind_listener()?;
+        *self._app_config.listener_bound.lock().unwrap() = true;
+        Ok(())
     }
 
     /// Poll and dispatch new connections
@@ -46,11 +63,66 @@ impl Gateway {
 
 unsafe impl Send for Gateway {}
 
+/// Ordered gateway shutdown: stop the gateway listener, then drain the service proxy connections (see
+/// `drain_connections`)
+pub fn shutdown_gracefully(
+    server_visitor: &Arc<Mutex<ServerVisitor>>,
+    service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
+    drain_timeout: Duration,
+) -> Result<(), AppError> {
+    server_visitor.lock().unwrap().set_shutdown_requested(true);
+    drain_connections(service_mgr, drain_timeout)
+}
+
+/// Drain service proxy connections: stop the service proxy listeners (no new connections are accepted, and in-progress
+/// TLS handshakes are cut off), wait (up to the drain timeout) for active connections to finish, then close any
+/// remaining connections. If the timeout elapsed with connections still active, an error reporting the force-closed
+/// connection count is returned
+pub fn drain_connections(
+    service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
+    drain_timeout: Duration,
+) -> Result<(), AppError> {
+    service_mgr.lock().unwrap().stop_listeners();
+
+    let drain_deadline = Instant::now() + drain_timeout;
+    let mut active_proxy_count = service_mgr.lock().unwrap().get_active_proxy_count();
+
+    while active_proxy_count > 0 && Instant::now() < drain_deadline {
+        thread::sleep(
+            SHUTDOWN_DRAIN_POLL_INTERVAL
+                .min(drain_deadline.saturating_duration_since(Instant::now())),
+        );
+        active_proxy_count = service_mgr.lock().unwrap().get_active_proxy_count();
+    }
+
+    service_mgr
+        .lock()
+        .unwrap()
+        .shutdown_connections(None, None)?;
+
+    if active_proxy_count > 0 {
+        return Err(AppError::General(format!(
+            "Drain timeout elapsed, remaining service proxy connections were force-closed: count={}",
+            active_proxy_count
+        )));
+    }
+
+    Ok(())
+}
+
+/// Handler for a newly-accepted TLS connection (determined by the connection's negotiated ALPN protocol)
+pub enum ConnectionHandler {
+    ControlPlane,
+    ServiceProxy(Arc<Mutex<dyn GatewayServiceProxyVisitor>>),
+    ReverseSession(u64),
+}
+
 /// tls_server::server_std::Server strategy visitor pattern implementation
 pub struct ServerVisitor {
     app_config: Arc<AppConfig>,
     service_mgr: Arc<Mutex<dyn ServiceMgr>>,
     control_plane_visitor: ControlPlaneServerVisitor,
+    reverse_session_users: HashMap<SocketAddr, (u64, Option<String>)>,
     shutdown_requested: bool,
 }
 
@@ -61,6 +133,7 @@ impl ServerVisitor {
             app_config: app_config.clone(),
             service_mgr: service_mgr.clone(),
             control_plane_visitor: ControlPlaneServerVisitor::new(app_config, service_mgr),
+            reverse_session_users: HashMap::new(),
             shutdown_requested: false,
         }
     }
@@ -85,6 +158,178 @@ impl ServerVisitor {
         }
     }
 
+    /// Determine connection handler based on the connection's negotiated ALPN protocol. Missing/unknown/invalid
+    /// protocols will return a 424 (invalid ALPN protocol) error, unless the unrecognized ALPN policy treats them
+    /// as control plane connections.
+    pub fn dispatch_by_alpn(
+        &self,
+        tls_conn: &dyn TlsConnection,
+    ) -> Result<ConnectionHandler, AppError> {
+        let alpn_protocol = ClientConnVisitor::resolve_alpn_protocol(
+            &tls_conn.alpn_protocol(),
+            self.app_config.unrecognized_alpn_policy,
+        )?;
+        self.dispatch_by_protocol(&alpn_protocol)
+    }
+
+    /// Determine connection handler (see [`Self::dispatch_by_alpn`]), emitting a connection denied event for
+    /// dispatch failures
+    pub fn dispatch_connection(
+        &self,
+        tls_conn: &dyn TlsConnection,
+    ) -> Result<ConnectionHandler, AppError> {
+        self.dispatch_by_alpn(tls_conn).inspect_err(|err| {
+            let mut conn_event = ConnEvent::new(
+                self.app_config.clock.as_ref(),
+                ConnEventType::AuthDenied,
+                Self::resolve_peer_user_id(tls_conn),
+                match ClientConnVisitor::parse_alpn_protocol(&tls_conn.alpn_protocol()) {
+                    Ok(Protocol::Service(service_id))
+                    | Ok(Protocol::ReverseService(service_id)) => Some(service_id),
+                    _ => None,
+                },
+                tls_conn.peer_addr(),
+                err.get_code(),
+                &conn_events::create_trace_id(),
+            );
+            conn_event.set_reason(Some(conn_events::denial_reason(err)));
+            self.app_config.conn_event_sink.emit(&conn_event);
+        })
+    }
+
+    /// Peer certificate user ID (if resolvable)
+    fn resolve_peer_user_id(tls_conn: &dyn TlsConnection) -> Option<u64> {
+        let peer_certificates = tls_conn
+            .peer_certificates()?
+            .iter()
+            .map(|c| crypto::x509::create_der_certificate(c.to_vec()))
+            .collect();
+        Device::new(peer_certificates)
+            .ok()
+            .map(|device| device.get_cert_access_context().user_id)
+            .filter(|user_id| *user_id != connection::ANONYMOUS_USER_ID)
+    }
+
+    /// Determine connection handler for given (parsed) ALPN protocol
+    fn dispatch_by_protocol(&self, protocol: &Protocol) -> Result<ConnectionHandler, AppError> {
+        match protocol {
+            Protocol::ControlPlane => Ok(ConnectionHandler::ControlPlane),
+            Protocol::Service(service_id) => Ok(ConnectionHandler::ServiceProxy(
+                self.get_service_proxy(*service_id)?,
+            )),
+            Protocol::ServiceName(service_name) => Ok(ConnectionHandler::ServiceProxy(
+                self.get_service_proxy(self.get_service_id_for_name(service_name)?)?,
+            )),
+            Protocol::ReverseService(service_id) => {
+                Ok(ConnectionHandler::ReverseSession(*service_id))
+            }
+        }
+    }
+
+    /// Get (TCP) service for given reverse proxy service ID
+    fn get_reverse_service(&self, service_id: u64) -> Result<Service, AppError> {
+        self.app_config
+            .service_repo
+            .lock()
+            .unwrap()
+            .get(service_id)?
+            .filter(|service| service.transport == Transport::TCP)
+            .ok_or(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0425_INACTIVE_SERVICE_PROXY,
+                format!("Invalid reverse proxy service: svc_id={}", service_id),
+            ))
+    }
+
+    /// Create connection for a reverse proxy client session, which is authorized like a service proxy connection
+    /// (client certificate user, service access, ...). The authorized user is kept for the session's queueing
+    fn create_reverse_session_conn(
+        &mut self,
+        tls_conn: TlsServerConnection,
+        service_id: u64,
+    ) -> Result<conn_std::Connection, AppError> {
+        let service = self.get_reverse_service(service_id)?;
+        let client_addr = tls_conn.sock.peer_addr().map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                "Unable to determine reverse proxy client session address".to_string(),
+                Box::new(err),
+            )
+        })?;
+
+        let mut conn_visitor =
+            ClientConnVisitor::new(self.app_config.clone(), self.service_mgr.clone());
+        conn_visitor.set_service(&service);
+        conn_visitor.set_client_addr(Some(client_addr));
+
+        let alpn_protocol = conn_visitor.process_authorization(&tls_conn, Some(service_id))?;
+
+        let user_id = conn_visitor.get_user().as_ref().unwrap().user_id;
+        let cert_serial = conn_visitor
+            .get_device()
+            .as_ref()
+            .map(|device| device.get_cert_serial().to_string());
+        self.reverse_session_users
+            .insert(client_addr, (user_id, cert_serial));
+
+        conn_std::Connection::new(Box::new(conn_visitor), tls_conn, alpn_protocol)
+    }
+
+    /// Queue (authorized) reverse proxy client session connection, to wait for a gateway-initiated service proxy
+    fn queue_reverse_session(
+        &mut self,
+        connection: conn_std::Connection,
+        service_id: u64,
+    ) -> Result<(), AppError> {
+        let tls_conn = connection.get_tls_conn_as_ref();
+        let (user_id, cert_serial) = tls_conn
+            .sock
+            .peer_addr()
+            .ok()
+            .and_then(|client_addr| self.reverse_session_users.remove(&client_addr))
+            .ok_or(AppError::General(format!(
+                "Unknown user for reverse proxy client session: addr={:?}",
+                tls_conn.sock.peer_addr().ok()
+            )))?;
+        let client_stream = tls_conn.sock.try_clone().map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                format!(
+                    "Unable to clone reverse proxy client session stream: client_stream={:?}",
+                    &tls_conn.sock
+                ),
+                Box::new(err),
+            )
+        })?;
+
+        let mut reverse_session = ReverseClientSession::new(
+            user_id,
+            client_stream,
+            Arc::new(Mutex::new(Box::<TlsServerConnection>::new(
+                connection.into(),
+            ))),
+        );
+        reverse_session.set_cert_serial(cert_serial);
+
+        self.service_mgr
+            .lock()
+            .unwrap()
+            .add_reverse_session(service_id, reverse_session)
+    }
+
+    /// Get service ID for given service name
+    fn get_service_id_for_name(&self, service_name: &str) -> Result<u64, AppError> {
+        self.app_config
+            .service_repo
+            .lock()
+            .unwrap()
+            .get_all()?
+            .iter()
+            .find(|service| service.name == service_name)
+            .map(|service| service.service_id)
+            .ok_or(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0425_INACTIVE_SERVICE_PROXY,
+                format!("Unknown service name: svc_name={}", service_name),
+            ))
+    }
+
     /// Set the shutdown request state
     pub fn set_shutdown_requested(&mut self, shutdown_requested: bool) {
         self.shutdown_requested = shutdown_requested;
@@ -96,32 +341,715 @@ impl server_std::ServerVisitor for ServerVisitor {
         &mut self,
         tls_conn: TlsServerConnection,
     ) -> Result<conn_std::Connection, AppError> {
-        match ClientConnVisitor::parse_alpn_protocol(&tls_conn.alpn_protocol())? {
-            Protocol::ControlPlane => self.control_plane_visitor.create_client_conn(tls_conn),
-            Protocol::Service(service_id) => self
-                .get_service_proxy(service_id)?
-                .lock()
-                .unwrap()
-                .create_client_conn(tls_conn),
+        match self.dispatch_connection(&tls_conn)? {
+            ConnectionHandler::ControlPlane => {
+                self.control_plane_visitor.create_client_conn(tls_conn)
+            }
+            ConnectionHandler::ServiceProxy(service_proxy) => {
+                service_proxy.lock().unwrap().create_client_conn(tls_conn)
+            }
+            ConnectionHandler::ReverseSession(service_id) => {
+                self.create_reverse_session_conn(tls_conn, service_id)
+            }
         }
     }
 
-    fn on_tls_handshaking(&mut self, _accepted: &Accepted) -> Result<ServerConfig, AppError> {
-        self.app_config.tls_server_config_builder.build()
+    fn on_tls_handshaking(&mut self, accepted: &Accepted) -> Result<ServerConfig, AppError> {
+        self.app_config
+            .tls_server_config_builder
+            .build_for_client_hello(accepted)
     }
 
     fn on_conn_accepted(&mut self, connection: conn_std::Connection) -> Result<(), AppError> {
-        match connection.get_alpn_protocol() {
-            Protocol::ControlPlane => self.control_plane_visitor.on_conn_accepted(connection),
-            Protocol::Service(service_id) => self
-                .get_service_proxy(*service_id)?
+        match self.dispatch_by_protocol(connection.get_alpn_protocol())? {
+            ConnectionHandler::ControlPlane => {
+                self.control_plane_visitor.on_conn_accepted(connection)
+            }
+            ConnectionHandler::ServiceProxy(service_proxy) => {
+                service_proxy.lock().unwrap().on_conn_accepted(connection)
+            }
+            ConnectionHandler::ReverseSession(service_id) => {
+                self.queue_reverse_session(connection, service_id)
+            }
+        }
+    }
+
+    fn get_upstream_dialer(
+        &mut self,
+        connection: &conn_std::Connection,
+    ) -> Result<Option<server_std::UpstreamDialer>, AppError> {
+        match self.dispatch_by_protocol(connection.get_alpn_protocol())? {
+            ConnectionHandler::ControlPlane | ConnectionHandler::ReverseSession(_) => Ok(None),
+            ConnectionHandler::ServiceProxy(service_proxy) => service_proxy
+                .lock()
+                .unwrap()
+                .get_upstream_dialer(connection),
+        }
+    }
+
+    fn on_conn_dialed(
+        &mut self,
+        connection: conn_std::Connection,
+        upstream_stream: TcpStream,
+    ) -> Result<(), AppError> {
+        match self.dispatch_by_protocol(connection.get_alpn_protocol())? {
+            ConnectionHandler::ControlPlane => {
+                self.control_plane_visitor.on_conn_accepted(connection)
+            }
+            ConnectionHandler::ServiceProxy(service_proxy) => service_proxy
                 .lock()
                 .unwrap()
-                .on_conn_accepted(connection),
+                .on_conn_dialed(connection, upstream_stream),
+            ConnectionHandler::ReverseSession(service_id) => {
+                self.queue_reverse_session(connection, service_id)
+            }
         }
     }
 
     fn get_shutdown_requested(&self) -> bool {
         self.shutdown_requested
     }
+
+    fn is_abortive_close_on_denial(&self) -> bool {
+        self.app_config.abortive_close_on_denial
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::config::UnrecognizedAlpnPolicy;
+    use crate::repository::access_repo::tests::MockAccessRepo;
+    use crate::repository::service_repo::tests::MockServiceRepo;
+    use crate::repository::user_repo::tests::MockUserRepo;
+    use crate::service::manager::tests::MockSvcMgr;
+    use crate::service::proxy::proxy_base::tests::MockGwSvcProxyVisitor;
+    use crate::testutils::{
+        create_handshaked_tls_conn_pair, CapturingConnEventSink, MockTlsSvrConn,
+    };
+    use mockall::predicate;
+    use std::net::SocketAddr;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+    use trust0_common::crypto::alpn;
+    use trust0_common::crypto::file::load_certificates;
+    use trust0_common::model::access::ServiceAccess;
+    use trust0_common::model::service::ClientAuth;
+
+    const CERTFILE_CLIENT_UID100_PATHPARTS: [&str; 3] = [
+        env!("CARGO_MANIFEST_DIR"),
+        "testdata",
+        "client-uid100.crt.pem",
+    ];
+
+    // utils
+    // =====
+
+    fn create_server_visitor(service_mgr: MockSvcMgr) -> ServerVisitor {
+        create_server_visitor_with_service_repo(service_mgr, MockServiceRepo::new())
+    }
+
+    fn create_server_visitor_with_service_repo(
+        service_mgr: MockSvcMgr,
+        service_repo: MockServiceRepo,
+    ) -> ServerVisitor {
+        let app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        ServerVisitor::new(Arc::new(app_config), Arc::new(Mutex::new(service_mgr)))
+    }
+
+    fn create_server_visitor_with_alpn_policy(
+        service_mgr: MockSvcMgr,
+        unrecognized_alpn_policy: UnrecognizedAlpnPolicy,
+    ) -> ServerVisitor {
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        app_config.unrecognized_alpn_policy = unrecognized_alpn_policy;
+        ServerVisitor::new(Arc::new(app_config), Arc::new(Mutex::new(service_mgr)))
+    }
+
+    fn create_server_visitor_for_reverse_session(
+        service_mgr: MockSvcMgr,
+        access_repo: MockAccessRepo,
+    ) -> ServerVisitor {
+        let service = Service::new(200, "Service200", &Transport::TCP, "localhost", 8200);
+        let service_copy = service.clone();
+        let mut service_repo = MockServiceRepo::new();
+        service_repo
+            .expect_get()
+            .with(predicate::eq(200))
+            .returning(move |_| Ok(Some(service.clone())));
+        service_repo
+            .expect_get_all()
+            .returning(move || Ok(vec![service_copy.clone()]));
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(access_repo)),
+        )
+        .unwrap();
+        app_config.tls_server_config_builder.client_auth = ClientAuth::Optional;
+        ServerVisitor::new(Arc::new(app_config), Arc::new(Mutex::new(service_mgr)))
+    }
+
+    fn create_tls_conn(alpn_protocol: Option<Vec<u8>>) -> MockTlsSvrConn {
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_alpn_protocol()
+            .times(1)
+            .return_once(move || alpn_protocol);
+        tls_conn
+    }
+
+    fn assert_error_code(result: Result<ConnectionHandler, AppError>, expected_code: u16) {
+        match result {
+            Ok(ConnectionHandler::ControlPlane) => {
+                panic!("Unexpected successful result: handler=ControlPlane")
+            }
+            Ok(ConnectionHandler::ServiceProxy(_)) => {
+                panic!("Unexpected successful result: handler=ServiceProxy")
+            }
+            Ok(ConnectionHandler::ReverseSession(_)) => {
+                panic!("Unexpected successful result: handler=ReverseSession")
+            }
+            Err(err) => assert_eq!(err.get_code(), Some(expected_code)),
+        }
+    }
+
+    // tests
+    // =====
+
+    #[test]
+    fn svrvisit_dispatch_by_alpn_when_reverse_service_protocol() {
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_get_service_proxy().never();
+        let server_visitor = create_server_visitor(service_mgr);
+        let tls_conn = create_tls_conn(Some(
+            alpn::Protocol::create_reverse_service_protocol(200).into_bytes(),
+        ));
+
+        match server_visitor.dispatch_by_alpn(&tls_conn) {
+            Ok(ConnectionHandler::ReverseSession(service_id)) => assert_eq!(service_id, 200),
+            Ok(ConnectionHandler::ControlPlane) => {
+                panic!("Unexpected result: handler=ControlPlane")
+            }
+            Ok(ConnectionHandler::ServiceProxy(_)) => {
+                panic!("Unexpected result: handler=ServiceProxy")
+            }
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        }
+    }
+
+    #[test]
+    fn svrvisit_accept_reverse_session_when_service_access_granted() {
+        let mut access_repo = MockAccessRepo::new();
+        access_repo
+            .expect_get()
+            .with(
+                predicate::eq(connection::ANONYMOUS_USER_ID),
+                predicate::eq(200),
+            )
+            .times(1)
+            .return_once(|_, _| Ok(Some(ServiceAccess::new(connection::ANONYMOUS_USER_ID, 200))));
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr
+            .expect_add_reverse_session()
+            .with(predicate::eq(200), predicate::always())
+            .times(1)
+            .return_once(|_, _| Ok(()));
+        let mut server_visitor =
+            create_server_visitor_for_reverse_session(service_mgr, access_repo);
+        let (tls_conn, _tls_cli_stream) = create_handshaked_tls_conn_pair(
+            alpn::Protocol::create_reverse_service_protocol(200).as_bytes(),
+        );
+
+        let connection =
+            server_std::ServerVisitor::create_client_conn(&mut server_visitor, tls_conn).unwrap();
+        assert_eq!(server_visitor.reverse_session_users.len(), 1);
+
+        server_std::ServerVisitor::on_conn_accepted(&mut server_visitor, connection).unwrap();
+        assert!(server_visitor.reverse_session_users.is_empty());
+    }
+
+    #[test]
+    fn svrvisit_accept_reverse_session_when_service_access_denied() {
+        let mut access_repo = MockAccessRepo::new();
+        access_repo
+            .expect_get()
+            .with(
+                predicate::eq(connection::ANONYMOUS_USER_ID),
+                predicate::eq(200),
+            )
+            .times(1)
+            .return_once(|_, _| Ok(None));
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_add_reverse_session().never();
+        let mut server_visitor =
+            create_server_visitor_for_reverse_session(service_mgr, access_repo);
+        let (tls_conn, _tls_cli_stream) = create_handshaked_tls_conn_pair(
+            alpn::Protocol::create_reverse_service_protocol(200).as_bytes(),
+        );
+
+        match server_std::ServerVisitor::create_client_conn(&mut server_visitor, tls_conn) {
+            Err(err) => assert_eq!(err.get_code(), Some(config::RESPCODE_0403_FORBIDDEN)),
+            Ok(_) => panic!("Unexpected successful result"),
+        }
+        assert!(server_visitor.reverse_session_users.is_empty());
+    }
+
+    #[test]
+    fn svrvisit_dispatch_by_alpn_when_control_plane_protocol() {
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_get_service_proxy().never();
+        let server_visitor = create_server_visitor(service_mgr);
+        let tls_conn = create_tls_conn(Some(alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec()));
+
+        match server_visitor.dispatch_by_alpn(&tls_conn) {
+            Ok(ConnectionHandler::ControlPlane) => {}
+            Ok(ConnectionHandler::ServiceProxy(_)) => {
+                panic!("Unexpected result: handler=ServiceProxy")
+            }
+            Ok(ConnectionHandler::ReverseSession(_)) => {
+                panic!("Unexpected result: handler=ReverseSession")
+            }
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        }
+    }
+
+    #[test]
+    fn svrvisit_dispatch_by_alpn_when_active_service_protocol() {
+        let mut service_proxy = MockGwSvcProxyVisitor::new();
+        service_proxy.expect_get_service().never();
+        let service_proxy: Arc<Mutex<dyn GatewayServiceProxyVisitor>> =
+            Arc::new(Mutex::new(service_proxy));
+        let service_proxy: &'static Arc<Mutex<dyn GatewayServiceProxyVisitor>> =
+            Box::leak(Box::new(service_proxy));
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr
+            .expect_get_service_proxy()
+            .with(predicate::eq(200))
+            .times(1)
+            .return_once(move |_| Some(service_proxy));
+        let server_visitor = create_server_visitor(service_mgr);
+        let tls_conn = create_tls_conn(Some(
+            alpn::Protocol::create_service_protocol(200).into_bytes(),
+        ));
+
+        match server_visitor.dispatch_by_alpn(&tls_conn) {
+            Ok(ConnectionHandler::ServiceProxy(handler_proxy)) => {
+                assert!(Arc::ptr_eq(&handler_proxy, service_proxy))
+            }
+            Ok(ConnectionHandler::ControlPlane) => {
+                panic!("Unexpected result: handler=ControlPlane")
+            }
+            Ok(ConnectionHandler::ReverseSession(_)) => {
+                panic!("Unexpected result: handler=ReverseSession")
+            }
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        }
+    }
+
+    #[test]
+    fn svrvisit_dispatch_by_alpn_when_inactive_service_protocol() {
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr
+            .expect_get_service_proxy()
+            .with(predicate::eq(201))
+            .times(1)
+            .return_once(move |_| None);
+        let server_visitor = create_server_visitor(service_mgr);
+        let tls_conn = create_tls_conn(Some(
+            alpn::Protocol::create_service_protocol(201).into_bytes(),
+        ));
+
+        assert_error_code(
+            server_visitor.dispatch_by_alpn(&tls_conn),
+            config::RESPCODE_0425_INACTIVE_SERVICE_PROXY,
+        );
+    }
+
+    #[test]
+    fn svrvisit_dispatch_by_alpn_when_unknown_protocol() {
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_get_service_proxy().never();
+        let server_visitor = create_server_visitor(service_mgr);
+
+        assert_error_code(
+            server_visitor.dispatch_by_alpn(&create_tls_conn(Some("h2".as_bytes().to_vec()))),
+            config::RESPCODE_0424_INVALID_ALPN_PROTOCOL,
+        );
+        assert_error_code(
+            server_visitor.dispatch_by_alpn(&create_tls_conn(None)),
+            config::RESPCODE_0424_INVALID_ALPN_PROTOCOL,
+        );
+    }
+
+    #[test]
+    fn svrvisit_dispatch_by_alpn_when_active_service_name_protocol() {
+        let service_proxy: Arc<Mutex<dyn GatewayServiceProxyVisitor>> =
+            Arc::new(Mutex::new(MockGwSvcProxyVisitor::new()));
+        let service_proxy: &'static Arc<Mutex<dyn GatewayServiceProxyVisitor>> =
+            Box::leak(Box::new(service_proxy));
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr
+            .expect_get_service_proxy()
+            .with(predicate::eq(201))
+            .times(1)
+            .return_once(move |_| Some(service_proxy));
+        let mut service_repo = MockServiceRepo::new();
+        service_repo.expect_get_all().times(1).return_once(|| {
+            Ok(vec![
+                Service::new(200, "svc200", &Transport::TCP, "localhost", 8200),
+                Service::new(201, "svc201", &Transport::TCP, "localhost", 8201),
+            ])
+        });
+        let server_visitor = create_server_visitor_with_service_repo(service_mgr, service_repo);
+        let tls_conn = create_tls_conn(Some(
+            alpn::Protocol::create_service_name_protocol("svc201").into_bytes(),
+        ));
+
+        match server_visitor.dispatch_by_alpn(&tls_conn) {
+            Ok(ConnectionHandler::ServiceProxy(handler_proxy)) => {
+                assert!(Arc::ptr_eq(&handler_proxy, service_proxy))
+            }
+            Ok(ConnectionHandler::ControlPlane) => {
+                panic!("Unexpected result: handler=ControlPlane")
+            }
+            Ok(ConnectionHandler::ReverseSession(_)) => {
+                panic!("Unexpected result: handler=ReverseSession")
+            }
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        }
+    }
+
+    #[test]
+    fn svrvisit_dispatch_by_alpn_when_unknown_service_name_protocol() {
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_get_service_proxy().never();
+        let mut service_repo = MockServiceRepo::new();
+        service_repo.expect_get_all().times(1).return_once(|| {
+            Ok(vec![Service::new(
+                200,
+                "svc200",
+                &Transport::TCP,
+                "localhost",
+                8200,
+            )])
+        });
+        let server_visitor = create_server_visitor_with_service_repo(service_mgr, service_repo);
+        let tls_conn = create_tls_conn(Some(
+            alpn::Protocol::create_service_name_protocol("svc201").into_bytes(),
+        ));
+
+        assert_error_code(
+            server_visitor.dispatch_by_alpn(&tls_conn),
+            config::RESPCODE_0425_INACTIVE_SERVICE_PROXY,
+        );
+    }
+
+    #[test]
+    fn svrvisit_dispatch_by_alpn_when_malformed_service_protocol() {
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_get_service_proxy().never();
+        let server_visitor = create_server_visitor(service_mgr);
+
+        assert_error_code(
+            server_visitor.dispatch_by_alpn(&create_tls_conn(Some(
+                "T0SRV18446744073709551616".as_bytes().to_vec(),
+            ))),
+            config::RESPCODE_0424_INVALID_ALPN_PROTOCOL,
+        );
+        assert_error_code(
+            server_visitor.dispatch_by_alpn(&create_tls_conn(Some(
+                "T0SRVNAME-bad name".as_bytes().to_vec(),
+            ))),
+            config::RESPCODE_0424_INVALID_ALPN_PROTOCOL,
+        );
+    }
+
+    #[test]
+    fn svrvisit_dispatch_by_alpn_when_control_plane_policy_and_service_protocol() {
+        let service_proxy: Arc<Mutex<dyn GatewayServiceProxyVisitor>> =
+            Arc::new(Mutex::new(MockGwSvcProxyVisitor::new()));
+        let service_proxy: &'static Arc<Mutex<dyn GatewayServiceProxyVisitor>> =
+            Box::leak(Box::new(service_proxy));
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr
+            .expect_get_service_proxy()
+            .with(predicate::eq(200))
+            .times(1)
+            .return_once(move |_| Some(service_proxy));
+        let server_visitor = create_server_visitor_with_alpn_policy(
+            service_mgr,
+            UnrecognizedAlpnPolicy::ControlPlane,
+        );
+        let tls_conn = create_tls_conn(Some(
+            alpn::Protocol::create_service_protocol(200).into_bytes(),
+        ));
+
+        match server_visitor.dispatch_by_alpn(&tls_conn) {
+            Ok(ConnectionHandler::ServiceProxy(handler_proxy)) => {
+                assert!(Arc::ptr_eq(&handler_proxy, service_proxy))
+            }
+            Ok(ConnectionHandler::ControlPlane) => {
+                panic!("Unexpected result: handler=ControlPlane")
+            }
+            Ok(ConnectionHandler::ReverseSession(_)) => {
+                panic!("Unexpected result: handler=ReverseSession")
+            }
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        }
+    }
+
+    #[test]
+    fn svrvisit_dispatch_by_alpn_when_control_plane_policy_and_control_plane_protocol() {
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_get_service_proxy().never();
+        let server_visitor = create_server_visitor_with_alpn_policy(
+            service_mgr,
+            UnrecognizedAlpnPolicy::ControlPlane,
+        );
+        let tls_conn = create_tls_conn(Some(alpn::PROTOCOL_CONTROL_PLANE.as_bytes().to_vec()));
+
+        match server_visitor.dispatch_by_alpn(&tls_conn) {
+            Ok(ConnectionHandler::ControlPlane) => {}
+            Ok(ConnectionHandler::ServiceProxy(_)) => {
+                panic!("Unexpected result: handler=ServiceProxy")
+            }
+            Ok(ConnectionHandler::ReverseSession(_)) => {
+                panic!("Unexpected result: handler=ReverseSession")
+            }
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        }
+    }
+
+    #[test]
+    fn svrvisit_dispatch_by_alpn_when_control_plane_policy_and_no_protocol() {
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_get_service_proxy().never();
+        let server_visitor = create_server_visitor_with_alpn_policy(
+            service_mgr,
+            UnrecognizedAlpnPolicy::ControlPlane,
+        );
+
+        for tls_conn in [
+            create_tls_conn(None),
+            create_tls_conn(Some("h2".as_bytes().to_vec())),
+        ] {
+            match server_visitor.dispatch_by_alpn(&tls_conn) {
+                Ok(ConnectionHandler::ControlPlane) => {}
+                Ok(ConnectionHandler::ServiceProxy(_)) => {
+                    panic!("Unexpected result: handler=ServiceProxy")
+                }
+                Ok(ConnectionHandler::ReverseSession(_)) => {
+                    panic!("Unexpected result: handler=ReverseSession")
+                }
+                Err(err) => panic!("Unexpected result: err={:?}", &err),
+            }
+        }
+    }
+
+    #[test]
+    fn svrvisit_dispatch_by_alpn_when_reject_policy_and_no_protocol() {
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr.expect_get_service_proxy().never();
+        let server_visitor =
+            create_server_visitor_with_alpn_policy(service_mgr, UnrecognizedAlpnPolicy::Reject);
+
+        assert_error_code(
+            server_visitor.dispatch_by_alpn(&create_tls_conn(None)),
+            config::RESPCODE_0424_INVALID_ALPN_PROTOCOL,
+        );
+    }
+
+    #[test]
+    fn svrvisit_dispatch_connection_when_inactive_service_proxy_emits_conn_event() {
+        let peer_certs_file: PathBuf = CERTFILE_CLIENT_UID100_PATHPARTS.iter().collect();
+        let peer_certs = load_certificates(peer_certs_file.to_str().unwrap().to_string()).unwrap();
+        let alpn_proto = alpn::Protocol::create_service_protocol(200)
+            .as_bytes()
+            .to_vec();
+
+        let mut tls_conn = MockTlsSvrConn::new();
+        tls_conn
+            .expect_alpn_protocol()
+            .times(2)
+            .returning(move || Some(alpn_proto.clone()));
+        tls_conn
+            .expect_peer_certificates()
+            .times(1)
+            .return_once(move || Some(peer_certs));
+        tls_conn
+            .expect_peer_addr()
+            .times(1)
+            .return_once(|| Some(SocketAddr::from_str("10.0.0.5:41000").unwrap()));
+
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr
+            .expect_get_service_proxy()
+            .with(predicate::eq(200))
+            .times(1)
+            .return_once(|_| None);
+        let conn_event_sink = Arc::new(CapturingConnEventSink::default());
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        app_config.conn_event_sink = conn_event_sink.clone();
+        let server_visitor =
+            ServerVisitor::new(Arc::new(app_config), Arc::new(Mutex::new(service_mgr)));
+
+        assert_error_code(
+            server_visitor.dispatch_connection(&tls_conn),
+            config::RESPCODE_0425_INACTIVE_SERVICE_PROXY,
+        );
+
+        let conn_events: Vec<ConnEvent> = conn_event_sink
+            .lines
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|line| serde_json::from_str(line.trim_end()).unwrap())
+            .collect();
+
+        assert_eq!(conn_events.len(), 1);
+        assert_eq!(conn_events[0].event_type, ConnEventType::AuthDenied);
+        assert_eq!(conn_events[0].user_id, Some(100));
+        assert_eq!(conn_events[0].service_id, Some(200));
+        assert_eq!(
+            conn_events[0].client_addr,
+            Some("10.0.0.5:41000".to_string())
+        );
+        assert_eq!(conn_events[0].response_code, Some(425));
+        assert_eq!(
+            conn_events[0].reason,
+            Some("Invalid service proxy: svc_id=200".to_string())
+        );
+        assert!(!conn_events[0].trace_id.is_empty());
+    }
+
+    fn create_shutdown_service_mgr(
+        server_visitor: &Arc<Mutex<ServerVisitor>>,
+        active_proxy_counts: Vec<usize>,
+        shutdown_steps: &Arc<Mutex<Vec<String>>>,
+    ) -> MockSvcMgr {
+        let mut service_mgr = MockSvcMgr::new();
+        let shutdown_steps_copy = shutdown_steps.clone();
+        service_mgr
+            .expect_stop_listeners()
+            .times(1)
+            .returning(move || {
+                shutdown_steps_copy
+                    .lock()
+                    .unwrap()
+                    .push("stop_listeners".to_string())
+            });
+        let mut active_proxy_counts = active_proxy_counts.into_iter();
+        let mut last_active_proxy_count = 0;
+        service_mgr
+            .expect_get_active_proxy_count()
+            .returning(move || {
+                last_active_proxy_count = active_proxy_counts
+                    .next()
+                    .unwrap_or(last_active_proxy_count);
+                last_active_proxy_count
+            });
+        let server_visitor = server_visitor.clone();
+        let shutdown_steps_copy = shutdown_steps.clone();
+        service_mgr
+            .expect_shutdown_connections()
+            .with(predicate::eq(None), predicate::eq(None))
+            .times(1)
+            .returning(move |_, _| {
+                let listener_stopped = server_std::ServerVisitor::get_shutdown_requested(
+                    &*server_visitor.lock().unwrap(),
+                );
+                shutdown_steps_copy.lock().unwrap().push(format!(
+                    "shutdown_connections(listener_stopped={})",
+                    listener_stopped
+                ));
+                Ok(())
+            });
+        service_mgr
+    }
+
+    #[test]
+    fn gateway_shutdown_gracefully_when_connections_drained() {
+        let server_visitor = Arc::new(Mutex::new(create_server_visitor(MockSvcMgr::new())));
+        let shutdown_steps = Arc::new(Mutex::new(Vec::new()));
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(
+            create_shutdown_service_mgr(&server_visitor, vec![2, 1, 0], &shutdown_steps),
+        ));
+
+        if let Err(err) =
+            shutdown_gracefully(&server_visitor, &service_mgr, Duration::from_secs(30))
+        {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+
+        assert_eq!(
+            *shutdown_steps.lock().unwrap(),
+            vec![
+                "stop_listeners".to_string(),
+                "shutdown_connections(listener_stopped=true)".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn gateway_drain_connections_when_connections_drained() {
+        let server_visitor = Arc::new(Mutex::new(create_server_visitor(MockSvcMgr::new())));
+        let shutdown_steps = Arc::new(Mutex::new(Vec::new()));
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(
+            create_shutdown_service_mgr(&server_visitor, vec![1, 0], &shutdown_steps),
+        ));
+
+        if let Err(err) = drain_connections(&service_mgr, Duration::from_secs(30)) {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+
+        assert_eq!(
+            *shutdown_steps.lock().unwrap(),
+            vec![
+                "stop_listeners".to_string(),
+                "shutdown_connections(listener_stopped=false)".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn gateway_shutdown_gracefully_when_drain_timeout_elapses() {
+        let server_visitor = Arc::new(Mutex::new(create_server_visitor(MockSvcMgr::new())));
+        let shutdown_steps = Arc::new(Mutex::new(Vec::new()));
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(
+            create_shutdown_service_mgr(&server_visitor, vec![1], &shutdown_steps),
+        ));
+        let started_at = Instant::now();
+
+        match shutdown_gracefully(&server_visitor, &service_mgr, Duration::from_millis(250)) {
+            Ok(()) => panic!("Unexpected successful result"),
+            Err(err) => assert!(format!("{}", err).contains("force-closed: count=1")),
+        }
+
+        assert!(started_at.elapsed() >= Duration::from_millis(250));
+        assert_eq!(
+            *shutdown_steps.lock().unwrap(),
+            vec![
+                "stop_listeners".to_string(),
+                "shutdown_connections(listener_stopped=true)".to_string()
+            ]
+        );
+    }
 }
@@ -0,0 +1,260 @@
+use std::sync::Arc;
+
+use serde_derive::Serialize;
+
+use crate::config::AppConfig;
+
+/// Liveness check function (returns whether the respective event loop is still running)
+pub type LivenessCheck = Arc<dyn Fn() -> bool + Send + Sync>;
+
+/// Overall gateway health status
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum HealthStatus {
+    /// Alive and ready to serve new connections
+    Ready,
+    /// Ready to serve new connections, however one or more event loops have ended
+    Degraded,
+    /// Not (yet, or currently) able to serve new connections
+    NotReady,
+}
+
+/// Result of an individual health check
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct HealthCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: Option<String>,
+}
+
+impl HealthCheck {
+    /// HealthCheck constructor
+    pub(crate) fn new(name: &str, passed: bool, detail: Option<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            passed,
+            detail,
+        }
+    }
+}
+
+/// Gateway health report, distinguishing liveness (event loops running) from readiness (certificates loaded,
+/// datasource available, listener bound)
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub alive: bool,
+    pub ready: bool,
+    pub liveness_checks: Vec<HealthCheck>,
+    pub readiness_checks: Vec<HealthCheck>,
+}
+
+/// Produces gateway health reports
+#[derive(Clone)]
+pub struct HealthMonitor {
+    app_config: Arc<AppConfig>,
+    liveness_checks: Vec<(String, LivenessCheck)>,
+}
+
+impl HealthMonitor {
+    /// HealthMonitor constructor
+    pub fn new(app_config: Arc<AppConfig>) -> Self {
+        Self {
+            app_config,
+            liveness_checks: vec![],
+        }
+    }
+
+    /// Add liveness check for named event loop
+    pub fn add_liveness_check(&mut self, name: &str, liveness_check: LivenessCheck) {
+        self.liveness_checks
+            .push((name.to_string(), liveness_check));
+    }
+
+    /// Current health report
+    pub fn health_report(&self) -> HealthReport {
+        let liveness_checks: Vec<HealthCheck> = self
+            .liveness_checks
+            .iter()
+            .map(|(name, liveness_check)| {
+                let running = liveness_check();
+                HealthCheck::new(
+                    name,
+                    running,
+                    (!running).then(|| "Event loop has ended".to_string()),
+                )
+            })
+            .collect();
+
+        let readiness_checks = vec![
+            match self.app_config.tls_server_config_builder.build() {
+                Ok(_) => HealthCheck::new("certificates", true, None),
+                Err(err) => HealthCheck::new("certificates", false, Some(format!("{:?}", err))),
+            },
+            HealthCheck::new(
+                "datasource",
+                *self.app_config.datasource_available.lock().unwrap(),
+                None,
+            ),
+            HealthCheck::new(
+                "listener",
+                *self.app_config.listener_bound.lock().unwrap(),
+                None,
+            ),
+        ];
+
+        let alive = liveness_checks.iter().all(|check| check.passed);
+        let ready = readiness_checks.iter().all(|check| check.passed);
+
+        let status = if !ready {
+            HealthStatus::NotReady
+        } else if !alive {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Ready
+        };
+
+        HealthReport {
+            status,
+            alive,
+            ready,
+            liveness_checks,
+            readiness_checks,
+        }
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::config;
+    use crate::config::tests::CERTFILE_GATEWAY_PATHPARTS;
+    use crate::repository::access_repo::tests::MockAccessRepo;
+    use crate::repository::service_repo::tests::MockServiceRepo;
+    use crate::repository::user_repo::tests::MockUserRepo;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+    use trust0_common::crypto::file::load_certificates;
+
+    // utils
+    // =====
+
+    fn create_health_monitor(
+        auth_root_loaded: bool,
+        listener_bound: bool,
+        datasource_available: bool,
+        event_loop_running: Arc<AtomicBool>,
+    ) -> HealthMonitor {
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        if auth_root_loaded {
+            let auth_cert_file: PathBuf = CERTFILE_GATEWAY_PATHPARTS.iter().collect();
+            for auth_cert in
+                load_certificates(auth_cert_file.to_str().unwrap().to_string()).unwrap()
+            {
+                app_config
+                    .tls_server_config_builder
+                    .auth_root_certs
+                    .add(auth_cert)
+                    .unwrap();
+            }
+        }
+        *app_config.listener_bound.lock().unwrap() = listener_bound;
+        *app_config.datasource_available.lock().unwrap() = datasource_available;
+
+        let mut health_monitor = HealthMonitor::new(Arc::new(app_config));
+        health_monitor.add_liveness_check(
+            "proxy_executor",
+            Arc::new(move || event_loop_running.load(Ordering::SeqCst)),
+        );
+        health_monitor
+    }
+
+    fn find_check<'a>(checks: &'a [HealthCheck], name: &str) -> &'a HealthCheck {
+        checks.iter().find(|check| check.name == name).unwrap()
+    }
+
+    // tests
+    // =====
+
+    #[test]
+    fn healthmon_health_report_when_ready() {
+        let health_monitor =
+            create_health_monitor(true, true, true, Arc::new(AtomicBool::new(true)));
+
+        let health_report = health_monitor.health_report();
+
+        assert_eq!(health_report.status, HealthStatus::Ready);
+        assert!(health_report.alive);
+        assert!(health_report.ready);
+        assert_eq!(health_report.liveness_checks.len(), 1);
+        assert_eq!(health_report.readiness_checks.len(), 3);
+    }
+
+    #[test]
+    fn healthmon_health_report_when_listener_not_bound() {
+        let health_monitor =
+            create_health_monitor(true, false, true, Arc::new(AtomicBool::new(true)));
+
+        let health_report = health_monitor.health_report();
+
+        assert_eq!(health_report.status, HealthStatus::NotReady);
+        assert!(health_report.alive);
+        assert!(!health_report.ready);
+        assert!(!find_check(&health_report.readiness_checks, "listener").passed);
+        assert!(find_check(&health_report.readiness_checks, "datasource").passed);
+    }
+
+    #[test]
+    fn healthmon_health_report_when_datasource_unavailable() {
+        let health_monitor =
+            create_health_monitor(true, true, false, Arc::new(AtomicBool::new(true)));
+
+        let health_report = health_monitor.health_report();
+
+        assert_eq!(health_report.status, HealthStatus::NotReady);
+        assert!(!find_check(&health_report.readiness_checks, "datasource").passed);
+    }
+
+    #[test]
+    fn healthmon_health_report_when_certificates_invalid() {
+        let health_monitor =
+            create_health_monitor(false, true, true, Arc::new(AtomicBool::new(true)));
+
+        let health_report = health_monitor.health_report();
+
+        assert_eq!(health_report.status, HealthStatus::NotReady);
+        let certificates_check = find_check(&health_report.readiness_checks, "certificates");
+        assert!(!certificates_check.passed);
+        assert!(certificates_check.detail.is_some());
+    }
+
+    #[test]
+    fn healthmon_health_report_when_event_loop_ended() {
+        let event_loop_running = Arc::new(AtomicBool::new(true));
+        let health_monitor = create_health_monitor(true, true, true, event_loop_running.clone());
+
+        assert_eq!(health_monitor.health_report().status, HealthStatus::Ready);
+
+        event_loop_running.store(false, Ordering::SeqCst);
+        let health_report = health_monitor.health_report();
+
+        assert_eq!(health_report.status, HealthStatus::Degraded);
+        assert!(!health_report.alive);
+        assert!(health_report.ready);
+        assert_eq!(
+            *find_check(&health_report.liveness_checks, "proxy_executor"),
+            HealthCheck::new(
+                "proxy_executor",
+                false,
+                Some("Event loop has ended".to_string())
+            )
+        );
+    }
+}
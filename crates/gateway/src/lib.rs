@@ -1,13 +1,29 @@
+pub(crate) mod admin;
 pub(crate) mod client;
 pub(crate) mod config;
 pub(crate) mod gateway;
+pub(crate) mod health;
 pub(crate) mod repository;
+pub(crate) mod self_test;
 pub(crate) mod service;
 
 #[cfg(test)]
 pub(crate) mod testutils;
 
+/// In-memory repositories (with state snapshot/restore), for use by integration tests
+#[cfg(feature = "test-util")]
+pub mod test_util {
+    pub use crate::repository::access_repo::in_memory_repo::InMemAccessRepo;
+    pub use crate::repository::access_repo::AccessRepository;
+    pub use crate::repository::service_repo::in_memory_repo::InMemServiceRepo;
+    pub use crate::repository::service_repo::ServiceRepository;
+    pub use crate::repository::snapshot::RepoSnapshot;
+    pub use crate::repository::user_repo::in_memory_repo::InMemUserRepo;
+    pub use crate::repository::user_repo::UserRepository;
+}
+
 pub mod api {
+    use std::sync::mpsc::Sender;
     use std::sync::{self, Arc, Mutex};
     use std::thread;
     use std::time::Duration;
@@ -16,9 +32,16 @@ pub mod api {
 
     use super::*;
     use crate::service::manager::ServiceMgr;
+    pub use admin::{AdminApi, Session as AdminSession};
     pub use config::AppConfig;
+    pub use health::{HealthCheck, HealthReport, HealthStatus};
+    pub use repository::config_hash::compute_config_hash;
     use trust0_common::error::AppError;
+    use trust0_common::logging::error;
+    use trust0_common::proxy::event::ProxyEvent;
     use trust0_common::proxy::executor::ProxyExecutor;
+    use trust0_common::proxy::proxy_key::ProxyKey;
+    use trust0_common::target;
 
     /// Component lifecycle methods
     pub trait ComponentLifecycle {
@@ -32,8 +55,8 @@ pub mod api {
     pub struct MainProcessor {
         app_config: Arc<AppConfig>,
         service_mgr: Arc<Mutex<dyn ServiceMgr>>,
-        _proxy_executor_handle: thread::JoinHandle<Result<(), AppError>>,
-        _proxy_events_processor_handle: thread::JoinHandle<Result<(), AppError>>,
+        proxy_events_sender: Sender<ProxyEvent>,
+        health_monitor: health::HealthMonitor,
         gateway: Option<gateway::Gateway>,
         gateway_visitor: Arc<Mutex<gateway::ServerVisitor>>,
     }
@@ -45,6 +68,7 @@ pub mod api {
 
             // Setup service manager/proxy executor
             let mut proxy_executor = ProxyExecutor::new();
+            proxy_executor.set_metrics_sink(app_config.metrics_sink.clone());
             let proxy_tasks_sender = proxy_executor.clone_proxy_tasks_sender();
 
             let proxy_executor_handle = thread::spawn(move || proxy_executor.poll_new_tasks());
@@ -54,23 +78,65 @@ pub mod api {
             let service_mgr = Arc::new(Mutex::new(service::manager::GatewayServiceMgr::new(
                 app_config.clone(),
                 proxy_tasks_sender,
-                proxy_events_sender,
+                proxy_events_sender.clone(),
             )));
 
             let service_mgr_copy = service_mgr.clone();
+            let proxy_key_reconcile_interval = app_config.proxy_key_reconcile_interval;
             let proxy_events_processor_handle = thread::spawn(move || {
                 service::manager::GatewayServiceMgr::poll_proxy_events(
                     service_mgr_copy,
                     proxy_events_receiver,
+                    Duration::from_secs(proxy_key_reconcile_interval),
                 )
             });
 
+            // Spawn service upstream address cache refresher (if caching enabled)
+            if !app_config.dns_cache_ttl.is_zero() {
+                let _ = service::dns_cache::ServiceAddrsCache::spawn_refresher(
+                    app_config.service_addrs_cache.clone(),
+                    app_config.dns_cache_ttl,
+                );
+            }
+
+            // Close service proxy connections for newly-revoked client certificates (if CRL is configured)
+            if let Some(crl_file) = &app_config.tls_server_config_builder.crl_file {
+                let service_mgr = service_mgr.clone();
+                crl_file
+                    .lock()
+                    .unwrap()
+                    .set_revoked_serials_handler(Box::new(move |cert_serials| {
+                        for cert_serial in cert_serials {
+                            if let Err(err) = service_mgr
+                                .lock()
+                                .unwrap()
+                                .shutdown_connections_by_cert_serial(cert_serial)
+                            {
+                                error(&target!(), &format!("{}", err));
+                            }
+                        }
+                    }));
+            }
+
+            // Setup health monitor (event loops are alive while their threads are running)
+            let mut health_monitor = health::HealthMonitor::new(app_config.clone());
+            let proxy_executor_handle = Arc::new(proxy_executor_handle);
+            health_monitor.add_liveness_check(
+                "proxy_executor",
+                Arc::new(move || !proxy_executor_handle.is_finished()),
+            );
+            let proxy_events_processor_handle = Arc::new(proxy_events_processor_handle);
+            health_monitor.add_liveness_check(
+                "proxy_events_processor",
+                Arc::new(move || !proxy_events_processor_handle.is_finished()),
+            );
+
             // Construct processor object
             Self {
                 app_config: app_config.clone(),
                 service_mgr: service_mgr.clone(),
-                _proxy_executor_handle: proxy_executor_handle,
-                _proxy_events_processor_handle: proxy_events_processor_handle,
+                proxy_events_sender,
+                health_monitor,
                 gateway: None,
                 gateway_visitor: Arc::new(Mutex::new(gateway::ServerVisitor::new(
                     app_config,
@@ -79,6 +145,35 @@ pub mod api {
             }
         }
 
+        /// Get a function to query gateway health (liveness and readiness)
+        pub fn get_health_report_function(&self) -> impl Fn() -> HealthReport {
+            let health_monitor = self.health_monitor.clone();
+            move || health_monitor.health_report()
+        }
+
+        /// Get an in-process admin API (users, services, access and sessions management) for the gateway
+        pub fn get_admin_api(&self) -> Box<dyn AdminApi> {
+            Box::new(admin::GatewayAdminApi::new(
+                self.app_config.clone(),
+                self.service_mgr.clone(),
+            ))
+        }
+
+        /// Get a function to connect (from the gateway) to a service upstream, relaying it to a waiting client session
+        pub fn get_connect_reverse_proxy_function(
+            &self,
+        ) -> impl Fn(u64) -> Result<ProxyKey, AppError> {
+            let service_mgr = self.service_mgr.clone();
+            let app_config = self.app_config.clone();
+            move |service_id| {
+                service::manager::GatewayServiceMgr::connect_reverse_proxy(
+                    &service_mgr,
+                    &app_config,
+                    service_id,
+                )
+            }
+        }
+
         /// Get a function to (initiate) gateway shutdown
         pub fn get_shutdown_function(&self) -> impl Fn() {
             let server_visitor = self.gateway_visitor.clone();
@@ -91,6 +186,10 @@ pub mod api {
     impl ComponentLifecycle for MainProcessor {
         /// Component start: start trust gateway
         fn start(&mut self) -> Result<(), AppError> {
+            if self.app_config.self_test {
+                self_test::SelfTestReport::run(&self.app_config).log_summary()?;
+            }
+
             let trust_gateway =
                 gateway::Gateway::new(self.app_config.clone(), self.gateway_visitor.clone());
             self.gateway = Some(trust_gateway);
@@ -101,21 +200,24 @@ pub mod api {
 
         /// Component stop: stop trust gateway
         fn stop(&mut self) -> Result<(), AppError> {
-            // Shutdown gateway listener (may already be shut down)
-            self.gateway_visitor
-                .lock()
-                .unwrap()
-                .set_shutdown_requested(true);
-
-            // Shutdown service proxies
-            self.service_mgr
-                .lock()
-                .unwrap()
-                .shutdown_connections(None, None)?;
+            // Shutdown listeners (gateway listener may already be shut down), drain then close service proxy connections
+            let shutdown_result = gateway::shutdown_gracefully(
+                &self.gateway_visitor,
+                &self.service_mgr,
+                self.app_config.shutdown_drain_timeout,
+            );
+
+            // Stop datasource files watching
+            if let Some(datasource_reloader) = &self.app_config.datasource_reloader {
+                datasource_reloader.stop_reloader();
+            }
 
             thread::sleep(Duration::from_millis(2000));
 
-            Ok(())
+            // End proxy events processing
+            let _ = self.proxy_events_sender.send(ProxyEvent::Shutdown);
+
+            shutdown_result
         }
     }
 }
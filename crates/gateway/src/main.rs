@@ -1,10 +1,10 @@
-use std::time::Duration;
-use std::{process, thread};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use anyhow::Result;
 
 use trust0_common::error::AppError;
-use trust0_common::logging::{error, LogLevel, LOG};
+use trust0_common::logging::{debug, error, LogLevel, LOG};
 use trust0_common::target;
 use trust0_gateway::api::{AppConfig, ComponentLifecycle, MainProcessor};
 
@@ -19,18 +19,30 @@ fn process_runner() -> Result<(), AppError> {
         },
         None,
     );
+    debug(
+        &target!(),
+        &format!(
+            "Effective configuration: {}",
+            app_config.to_redacted_json()?
+        ),
+    );
 
     let mut processor = MainProcessor::new(app_config);
 
+    // Signal (SIGINT/SIGTERM) initiates the graceful shutdown (performed once the processor stops), a repeat signal
+    // exits immediately
     let shutdown_fn = processor.get_shutdown_function();
+    let shutdown_initiated = AtomicBool::new(false);
     ctrlc::set_handler(move || {
+        if shutdown_initiated.swap(true, Ordering::SeqCst) {
+            error(&target!(), "Signal caught again, gateway exiting...");
+            process::exit(1);
+        }
         error(&target!(), "Signal caught, gateway shutting down...");
         shutdown_fn();
-        thread::sleep(Duration::from_millis(3000));
-        process::exit(0);
     })
     .map_err(|err| {
-        AppError::GenWithMsgAndErr("Error setting Ctrl-C handler".to_string(), Box::new(err))
+        AppError::GenWithMsgAndErr("Error setting signal handler".to_string(), Box::new(err))
     })?;
 
     processor.start()
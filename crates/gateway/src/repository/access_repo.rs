@@ -1,5 +1,8 @@
 pub mod in_memory_repo;
 
+use std::collections::{HashMap, HashSet};
+
+use crate::config::AccessDefault;
 use trust0_common::error::AppError;
 use trust0_common::model::access::ServiceAccess;
 
@@ -23,18 +26,114 @@ pub trait AccessRepository: Sync + Send {
     /// Returns a copy of the list of service accesses on success, otherwise it returns an error.
     fn get_all_for_user(&self, user_id: u64) -> Result<Vec<ServiceAccess>, AppError>;
 
+    /// Returns the list of all service accesses.
+    ///
+    /// Returns a copy of the list of service accesses on success, otherwise it returns an error.
+    fn get_all(&self) -> Result<Vec<ServiceAccess>, AppError>;
+
     /// Deletes a service access.
     ///
     /// Returns previous service access or None on success, otherwise it returns an error.
     fn delete(&self, user_id: u64, service_id: u64) -> Result<Option<ServiceAccess>, AppError>;
 }
 
+/// Resolve user's effective access for service, from an explicit (grant or deny) access entry, else the access default.
+///
+/// Returns the access (default-allowed access has no backing entry) or None if denied, otherwise it returns an error.
+pub fn resolve_access(
+    access_repo: &dyn AccessRepository,
+    access_default: AccessDefault,
+    user_id: u64,
+    service_id: u64,
+) -> Result<Option<ServiceAccess>, AppError> {
+    Ok(match access_repo.get(user_id, service_id)? {
+        Some(access) if access.deny => None,
+        Some(access) => Some(access),
+        None if access_default == AccessDefault::Allow => {
+            Some(ServiceAccess::new(user_id, service_id))
+        }
+        None => None,
+    })
+}
+
+/// Resolve user's effective access for each of the given services (see `resolve_access`), from a single retrieval
+/// of the user's access entries.
+///
+/// Returns a map of service ID to access (or None if denied) on success, otherwise it returns an error.
+pub fn resolve_access_bulk(
+    access_repo: &dyn AccessRepository,
+    access_default: AccessDefault,
+    user_id: u64,
+    service_ids: &[u64],
+) -> Result<HashMap<u64, Option<ServiceAccess>>, AppError> {
+    let user_accesses: HashMap<u64, ServiceAccess> = access_repo
+        .get_all_for_user(user_id)?
+        .into_iter()
+        .map(|access| (access.service_id, access))
+        .collect();
+
+    Ok(service_ids
+        .iter()
+        .map(|service_id| {
+            let access = match user_accesses.get(service_id) {
+                Some(access) if access.deny => None,
+                Some(access) => Some(access.clone()),
+                None if access_default == AccessDefault::Allow => {
+                    Some(ServiceAccess::new(user_id, *service_id))
+                }
+                None => None,
+            };
+            (*service_id, access)
+        })
+        .collect())
+}
+
+/// Resolve user's effective accesses (see `resolve_access`). Granted access entries are returned (in repository
+/// order), followed by default-allowed accesses for any of the given services without an access entry.
+///
+/// Returns the list of allowed service accesses on success, otherwise it returns an error.
+pub fn resolve_all_access_for_user(
+    access_repo: &dyn AccessRepository,
+    access_default: AccessDefault,
+    user_id: u64,
+    service_ids: &[u64],
+) -> Result<Vec<ServiceAccess>, AppError> {
+    let user_accesses = access_repo.get_all_for_user(user_id)?;
+    let entry_service_ids: HashSet<u64> = user_accesses
+        .iter()
+        .map(|access| access.service_id)
+        .collect();
+
+    let mut accesses: Vec<ServiceAccess> = user_accesses
+        .into_iter()
+        .filter(|access| !access.deny)
+        .collect();
+
+    if access_default == AccessDefault::Allow {
+        let mut default_service_ids: Vec<u64> = service_ids
+            .iter()
+            .filter(|service_id| !entry_service_ids.contains(service_id))
+            .cloned()
+            .collect();
+        default_service_ids.sort();
+        default_service_ids.dedup();
+
+        accesses.extend(
+            default_service_ids
+                .into_iter()
+                .map(|service_id| ServiceAccess::new(user_id, service_id)),
+        );
+    }
+
+    Ok(accesses)
+}
+
 /// Unit tests
 #[cfg(test)]
 pub mod tests {
 
     use super::*;
-    use mockall::mock;
+    use mockall::{mock, predicate};
 
     // mocks
     // =====
@@ -46,7 +145,185 @@ pub mod tests {
             fn put(&self, access: ServiceAccess) -> Result<Option<ServiceAccess>, AppError>;
             fn get(&self, user_id: u64, service_id: u64) -> Result<Option<ServiceAccess>, AppError>;
             fn get_all_for_user(&self, user_id: u64) -> Result<Vec<ServiceAccess>, AppError>;
+            fn get_all(&self) -> Result<Vec<ServiceAccess>, AppError>;
             fn delete(&self, user_id: u64, service_id: u64) -> Result<Option<ServiceAccess>, AppError>;
         }
     }
+
+    // tests
+    // =====
+
+    fn create_access_repo(user_accesses: Vec<ServiceAccess>) -> MockAccessRepo {
+        let mut access_repo = MockAccessRepo::new();
+        let user_accesses_copy = user_accesses.clone();
+        access_repo
+            .expect_get()
+            .with(predicate::eq(100), predicate::always())
+            .returning(move |_, service_id| {
+                Ok(user_accesses_copy
+                    .iter()
+                    .find(|access| access.service_id == service_id)
+                    .cloned())
+            });
+        access_repo
+            .expect_get_all_for_user()
+            .with(predicate::eq(100))
+            .returning(move |_| Ok(user_accesses.clone()));
+        access_repo
+    }
+
+    fn create_deny_access(user_id: u64, service_id: u64) -> ServiceAccess {
+        let mut access = ServiceAccess::new(user_id, service_id);
+        access.deny = true;
+        access
+    }
+
+    #[test]
+    fn accessrepo_resolve_access_when_default_deny() {
+        let access_repo = create_access_repo(vec![
+            ServiceAccess::new(100, 200),
+            create_deny_access(100, 201),
+        ]);
+
+        assert_eq!(
+            resolve_access(&access_repo, AccessDefault::Deny, 100, 200).unwrap(),
+            Some(ServiceAccess::new(100, 200))
+        );
+        assert_eq!(
+            resolve_access(&access_repo, AccessDefault::Deny, 100, 201).unwrap(),
+            None
+        );
+        assert_eq!(
+            resolve_access(&access_repo, AccessDefault::Deny, 100, 202).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn accessrepo_resolve_access_when_default_allow() {
+        let access_repo = create_access_repo(vec![
+            ServiceAccess::new(100, 200),
+            create_deny_access(100, 201),
+        ]);
+
+        assert_eq!(
+            resolve_access(&access_repo, AccessDefault::Allow, 100, 200).unwrap(),
+            Some(ServiceAccess::new(100, 200))
+        );
+        assert_eq!(
+            resolve_access(&access_repo, AccessDefault::Allow, 100, 201).unwrap(),
+            None
+        );
+        assert_eq!(
+            resolve_access(&access_repo, AccessDefault::Allow, 100, 202).unwrap(),
+            Some(ServiceAccess::new(100, 202))
+        );
+    }
+
+    #[test]
+    fn accessrepo_resolve_all_access_for_user_when_default_deny() {
+        let access_repo = create_access_repo(vec![
+            ServiceAccess::new(100, 202),
+            create_deny_access(100, 201),
+            ServiceAccess::new(100, 200),
+        ]);
+
+        assert_eq!(
+            resolve_all_access_for_user(
+                &access_repo,
+                AccessDefault::Deny,
+                100,
+                &[200, 201, 202, 203]
+            )
+            .unwrap(),
+            vec![ServiceAccess::new(100, 202), ServiceAccess::new(100, 200)]
+        );
+        assert!(resolve_all_access_for_user(
+            &create_access_repo(vec![]),
+            AccessDefault::Deny,
+            100,
+            &[200, 201]
+        )
+        .unwrap()
+        .is_empty());
+    }
+
+    #[test]
+    fn accessrepo_resolve_all_access_for_user_when_default_allow() {
+        let access_repo = create_access_repo(vec![
+            ServiceAccess::new(100, 202),
+            create_deny_access(100, 201),
+        ]);
+
+        assert_eq!(
+            resolve_all_access_for_user(
+                &access_repo,
+                AccessDefault::Allow,
+                100,
+                &[203, 200, 201, 202]
+            )
+            .unwrap(),
+            vec![
+                ServiceAccess::new(100, 202),
+                ServiceAccess::new(100, 200),
+                ServiceAccess::new(100, 203),
+            ]
+        );
+        assert_eq!(
+            resolve_all_access_for_user(
+                &create_access_repo(vec![]),
+                AccessDefault::Allow,
+                100,
+                &[201, 200]
+            )
+            .unwrap(),
+            vec![ServiceAccess::new(100, 200), ServiceAccess::new(100, 201)]
+        );
+    }
+
+    #[test]
+    fn accessrepo_resolve_access_bulk_matches_resolve_access() {
+        let access_repo = create_access_repo(vec![
+            ServiceAccess::new(100, 202),
+            create_deny_access(100, 201),
+            ServiceAccess::new(100, 200),
+            create_deny_access(100, 204),
+        ]);
+        let service_ids = [200, 201, 202, 203, 204, 205];
+
+        for access_default in [AccessDefault::Deny, AccessDefault::Allow] {
+            let accesses =
+                resolve_access_bulk(&access_repo, access_default, 100, &service_ids).unwrap();
+
+            assert_eq!(accesses.len(), service_ids.len());
+            for service_id in service_ids {
+                assert_eq!(
+                    accesses.get(&service_id).unwrap(),
+                    &resolve_access(&access_repo, access_default, 100, service_id).unwrap(),
+                    "svc_id={}, access_default={:?}",
+                    service_id,
+                    access_default
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn accessrepo_resolve_access_bulk_when_no_entries() {
+        let access_repo = create_access_repo(vec![]);
+
+        assert_eq!(
+            resolve_access_bulk(&access_repo, AccessDefault::Deny, 100, &[200, 201]).unwrap(),
+            HashMap::from([(200, None), (201, None)])
+        );
+        assert_eq!(
+            resolve_access_bulk(&access_repo, AccessDefault::Allow, 100, &[200]).unwrap(),
+            HashMap::from([(200, Some(ServiceAccess::new(100, 200)))])
+        );
+        assert!(
+            resolve_access_bulk(&access_repo, AccessDefault::Allow, 100, &[])
+                .unwrap()
+                .is_empty()
+        );
+    }
 }
@@ -3,6 +3,9 @@ use std::fs;
 use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use crate::repository::access_repo::AccessRepository;
+use crate::repository::json_file::parse_json_datasource;
+#[cfg(any(test, feature = "test-util"))]
+use crate::repository::snapshot::RepoSnapshot;
 use trust0_common::error::AppError;
 use trust0_common::model::access::ServiceAccess;
 
@@ -37,6 +40,29 @@ impl InMemAccessRepo {
     }
 }
 
+#[cfg(any(test, feature = "test-util"))]
+impl InMemAccessRepo {
+    /// Capture all entries (under the lock)
+    pub fn snapshot(&self) -> Result<RepoSnapshot<(u64, u64), ServiceAccess>, AppError> {
+        Ok(RepoSnapshot::new(self.access_data_for_read()?.clone()))
+    }
+
+    /// Replace all entries (under the lock) with those of the given snapshot
+    pub fn restore(
+        &self,
+        snapshot: RepoSnapshot<(u64, u64), ServiceAccess>,
+    ) -> Result<(), AppError> {
+        *self.access_data_for_write()? = snapshot.into_entries();
+        Ok(())
+    }
+}
+
+impl Default for InMemAccessRepo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AccessRepository for InMemAccessRepo {
     fn connect_to_datasource(&mut self, connect_spec: &str) -> Result<(), AppError> {
         let data = fs::read_to_string(connect_spec).map_err(|err| {
@@ -45,12 +71,7 @@ impl AccessRepository for InMemAccessRepo {
                 Box::new(err),
             )
         })?;
-        let accesses: Vec<ServiceAccess> = serde_json::from_str(&data).map_err(|err| {
-            AppError::GenWithMsgAndErr(
-                format!("Failed to parse JSON: path={}", connect_spec),
-                Box::new(err),
-            )
-        })?;
+        let accesses: Vec<ServiceAccess> = parse_json_datasource(connect_spec, &data)?;
 
         for access in accesses.iter().as_ref() {
             self.put(access.clone())?;
@@ -79,6 +100,15 @@ impl AccessRepository for InMemAccessRepo {
             .collect::<Vec<ServiceAccess>>())
     }
 
+    fn get_all(&self) -> Result<Vec<ServiceAccess>, AppError> {
+        let data = self.access_data_for_read()?;
+        Ok(data
+            .iter()
+            .map(|entry| entry.1)
+            .cloned()
+            .collect::<Vec<ServiceAccess>>())
+    }
+
     fn delete(&self, user_id: u64, service_id: u64) -> Result<Option<ServiceAccess>, AppError> {
         let mut data = self.access_data_for_write()?;
         Ok(data.remove(&(user_id, service_id)))
@@ -89,10 +119,16 @@ impl AccessRepository for InMemAccessRepo {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::repository::json_file::tests::INVALID_SYNTAX_DB_FILE_PATHPARTS;
     use std::path::PathBuf;
 
     const VALID_ACCESS_DB_FILE_PATHPARTS: [&str; 3] =
         [env!("CARGO_MANIFEST_DIR"), "testdata", "db-access.json"];
+    const VALID_ACCESS_DB_JSONC_FILE_PATHPARTS: [&str; 3] = [
+        env!("CARGO_MANIFEST_DIR"),
+        "testdata",
+        "db-access-jsonc.json",
+    ];
     const INVALID_ACCESS_DB_FILE_PATHPARTS: [&str; 3] = [
         env!("CARGO_MANIFEST_DIR"),
         "testdata",
@@ -111,6 +147,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn inmemaccessrepo_connect_to_datasource_when_invalid_json_syntax() {
+        let invalid_db_path: PathBuf = INVALID_SYNTAX_DB_FILE_PATHPARTS.iter().collect();
+        let invalid_db_pathstr = invalid_db_path.to_str().unwrap();
+
+        let mut access_repo = InMemAccessRepo::new();
+
+        match access_repo.connect_to_datasource(invalid_db_pathstr) {
+            Err(err) => assert!(err
+                .to_string()
+                .contains(&format!("path={}, line=4, column=5", invalid_db_pathstr))),
+            Ok(()) => panic!("Unexpected result: file={}", invalid_db_pathstr),
+        }
+    }
+
     #[test]
     fn inmemaccessrepo_connect_to_datasource_when_valid_filepath() {
         let valid_access_db_path: PathBuf = VALID_ACCESS_DB_FILE_PATHPARTS.iter().collect();
@@ -131,6 +182,8 @@ mod tests {
                 ServiceAccess {
                     user_id: 100,
                     service_id: 200,
+                    justification: Some("Service owner".to_string()),
+                    deny: false,
                 },
             ),
             (
@@ -138,6 +191,8 @@ mod tests {
                 ServiceAccess {
                     user_id: 100,
                     service_id: 203,
+                    justification: None,
+                    deny: false,
                 },
             ),
             (
@@ -145,6 +200,8 @@ mod tests {
                 ServiceAccess {
                     user_id: 100,
                     service_id: 204,
+                    justification: None,
+                    deny: false,
                 },
             ),
             (
@@ -152,6 +209,8 @@ mod tests {
                 ServiceAccess {
                     user_id: 101,
                     service_id: 202,
+                    justification: None,
+                    deny: false,
                 },
             ),
             (
@@ -159,6 +218,8 @@ mod tests {
                 ServiceAccess {
                     user_id: 101,
                     service_id: 203,
+                    justification: None,
+                    deny: false,
                 },
             ),
         ]);
@@ -183,6 +244,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn inmemaccessrepo_connect_to_datasource_when_valid_jsonc_filepath() {
+        let valid_access_db_path: PathBuf = VALID_ACCESS_DB_FILE_PATHPARTS.iter().collect();
+        let valid_access_db_jsonc_path: PathBuf =
+            VALID_ACCESS_DB_JSONC_FILE_PATHPARTS.iter().collect();
+
+        let mut access_repo = InMemAccessRepo::new();
+        let mut access_jsonc_repo = InMemAccessRepo::new();
+
+        if let Err(err) = access_repo.connect_to_datasource(valid_access_db_path.to_str().unwrap())
+        {
+            panic!("Unexpected strict JSON result: err={:?}", &err);
+        }
+        if let Err(err) =
+            access_jsonc_repo.connect_to_datasource(valid_access_db_jsonc_path.to_str().unwrap())
+        {
+            panic!("Unexpected JSONC result: err={:?}", &err);
+        }
+
+        let expected_access_db_map: HashMap<(u64, u64), ServiceAccess> =
+            access_repo.accesses.into_inner().unwrap();
+        let actual_access_db_map: HashMap<(u64, u64), ServiceAccess> =
+            access_jsonc_repo.accesses.into_inner().unwrap();
+
+        assert!(!actual_access_db_map.is_empty());
+        assert_eq!(actual_access_db_map, expected_access_db_map);
+    }
+
     #[test]
     fn inmemaccessrepo_put() {
         let access_repo = InMemAccessRepo::new();
@@ -190,6 +279,8 @@ mod tests {
         let access = ServiceAccess {
             user_id: 1,
             service_id: 2,
+            justification: None,
+            deny: false,
         };
 
         if let Err(err) = access_repo.put(access.clone()) {
@@ -210,6 +301,8 @@ mod tests {
         let access = ServiceAccess {
             user_id: 1,
             service_id: 2,
+            justification: None,
+            deny: false,
         };
 
         access_repo
@@ -234,6 +327,8 @@ mod tests {
         let access = ServiceAccess {
             user_id: 1,
             service_id: 2,
+            justification: None,
+            deny: false,
         };
 
         access_repo
@@ -259,10 +354,14 @@ mod tests {
             ServiceAccess {
                 user_id: 1,
                 service_id: 2,
+                justification: None,
+                deny: false,
             },
             ServiceAccess {
                 user_id: 3,
                 service_id: 4,
+                justification: None,
+                deny: false,
             },
         ];
 
@@ -297,14 +396,20 @@ mod tests {
             ServiceAccess {
                 user_id: 1,
                 service_id: 2,
+                justification: None,
+                deny: false,
             },
             ServiceAccess {
                 user_id: 3,
                 service_id: 4,
+                justification: None,
+                deny: false,
             },
             ServiceAccess {
                 user_id: 1,
                 service_id: 5,
+                justification: None,
+                deny: false,
             },
         ];
 
@@ -341,14 +446,20 @@ mod tests {
             ServiceAccess {
                 user_id: 1,
                 service_id: 2,
+                justification: None,
+                deny: false,
             },
             ServiceAccess {
                 user_id: 3,
                 service_id: 4,
+                justification: None,
+                deny: false,
             },
             ServiceAccess {
                 user_id: 1,
                 service_id: 5,
+                justification: None,
+                deny: false,
             },
         ];
 
@@ -383,6 +494,8 @@ mod tests {
                 ServiceAccess {
                     user_id: 1,
                     service_id: 2,
+                    justification: None,
+                    deny: false,
                 },
             ),
             (
@@ -390,6 +503,8 @@ mod tests {
                 ServiceAccess {
                     user_id: 1,
                     service_id: 5,
+                    justification: None,
+                    deny: false,
                 },
             ),
         ]);
@@ -404,6 +519,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn inmemaccessrepo_get_all() {
+        let access_repo = InMemAccessRepo::new();
+        let access_keys = [(1, 2), (3, 4)];
+        let accesses = [
+            ServiceAccess {
+                user_id: 1,
+                service_id: 2,
+                justification: None,
+                deny: false,
+            },
+            ServiceAccess {
+                user_id: 3,
+                service_id: 4,
+                justification: None,
+                deny: false,
+            },
+        ];
+
+        access_repo
+            .accesses
+            .write()
+            .unwrap()
+            .insert(access_keys[0], accesses[0].clone());
+        access_repo
+            .accesses
+            .write()
+            .unwrap()
+            .insert(access_keys[1], accesses[1].clone());
+
+        let result = access_repo.get_all();
+
+        if let Err(err) = &result {
+            panic!("Unexpected result: err={:?}", &err)
+        }
+
+        let actual_accesses = result.unwrap();
+        assert_eq!(actual_accesses.len(), 2);
+        assert!(actual_accesses.contains(&accesses[0]));
+        assert!(actual_accesses.contains(&accesses[1]));
+    }
+
     #[test]
     fn inmemaccessrepo_delete_when_invalid_user() {
         let access_repo = InMemAccessRepo::new();
@@ -411,6 +568,8 @@ mod tests {
         let access = ServiceAccess {
             user_id: 1,
             service_id: 2,
+            justification: None,
+            deny: false,
         };
 
         access_repo
@@ -435,6 +594,8 @@ mod tests {
         let access = ServiceAccess {
             user_id: 1,
             service_id: 2,
+            justification: None,
+            deny: false,
         };
 
         access_repo
@@ -459,6 +620,8 @@ mod tests {
         let access = ServiceAccess {
             user_id: 1,
             service_id: 2,
+            justification: None,
+            deny: false,
         };
 
         access_repo
@@ -478,4 +641,41 @@ mod tests {
         assert!(actual_prev_access.is_some());
         assert_eq!(actual_prev_access.unwrap(), access);
     }
+
+    #[test]
+    fn inmemaccessrepo_snapshot_and_restore() {
+        let valid_access_db_path: PathBuf = VALID_ACCESS_DB_FILE_PATHPARTS.iter().collect();
+        let valid_access_db_pathstr = valid_access_db_path.to_str().unwrap();
+
+        let mut access_repo = InMemAccessRepo::new();
+        access_repo
+            .connect_to_datasource(valid_access_db_pathstr)
+            .unwrap();
+
+        let snapshot = access_repo.snapshot().unwrap();
+        let original_accesses = access_repo.accesses.read().unwrap().clone();
+        assert_eq!(snapshot.len(), original_accesses.len());
+
+        access_repo
+            .put(ServiceAccess {
+                user_id: 100,
+                service_id: 299,
+                justification: None,
+                deny: false,
+            })
+            .unwrap();
+        access_repo.delete(100, 200).unwrap();
+        assert_ne!(*access_repo.accesses.read().unwrap(), original_accesses);
+
+        access_repo.restore(snapshot.clone()).unwrap();
+        assert_eq!(*access_repo.accesses.read().unwrap(), original_accesses);
+
+        access_repo
+            .restore(RepoSnapshot::new(HashMap::new()))
+            .unwrap();
+        assert!(access_repo.snapshot().unwrap().is_empty());
+
+        access_repo.restore(snapshot).unwrap();
+        assert_eq!(*access_repo.accesses.read().unwrap(), original_accesses);
+    }
 }
@@ -0,0 +1,182 @@
+use std::sync::{Arc, Mutex};
+
+use ring::digest;
+use serde::Serialize;
+
+use crate::repository::access_repo::AccessRepository;
+use crate::repository::service_repo::ServiceRepository;
+use crate::repository::user_repo::UserRepository;
+use trust0_common::error::AppError;
+
+/// Compute a stable (SHA-256, hex-encoded) hash over the service catalog and, if supplied, the users and access
+/// entries. The hash is independent of repository ordering, and changes whenever any record is added, removed or
+/// modified. Allows clients and monitors to cheaply detect configuration changes.
+#[allow(clippy::type_complexity)]
+pub fn compute_config_hash(
+    service_repo: &Arc<Mutex<dyn ServiceRepository>>,
+    users_and_access: Option<(
+        &Arc<Mutex<dyn UserRepository>>,
+        &Arc<Mutex<dyn AccessRepository>>,
+    )>,
+) -> Result<String, AppError> {
+    let mut context = digest::Context::new(&digest::SHA256);
+
+    hash_entities(
+        &mut context,
+        "services",
+        &service_repo.lock().unwrap().get_all()?,
+    )?;
+
+    if let Some((user_repo, access_repo)) = users_and_access {
+        hash_entities(&mut context, "users", &user_repo.lock().unwrap().get_all()?)?;
+        hash_entities(
+            &mut context,
+            "access",
+            &access_repo.lock().unwrap().get_all()?,
+        )?;
+    }
+
+    Ok(context
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Add entity section to hash. Each entity is hashed (by its serialized form), and the entity hashes are sorted,
+/// so the result doesn't depend on entity order.
+fn hash_entities<T: Serialize>(
+    context: &mut digest::Context,
+    section: &str,
+    entities: &[T],
+) -> Result<(), AppError> {
+    let mut entity_digests = entities
+        .iter()
+        .map(|entity| {
+            serde_json::to_vec(entity)
+                .map(|entity_json| digest::digest(&digest::SHA256, &entity_json))
+                .map_err(|err| {
+                    AppError::GenWithMsgAndErr(
+                        format!("Error serializing {} entity for config hash", section),
+                        Box::new(err),
+                    )
+                })
+        })
+        .collect::<Result<Vec<digest::Digest>, AppError>>()?;
+    entity_digests.sort_by(|digest1, digest2| digest1.as_ref().cmp(digest2.as_ref()));
+
+    context.update(section.as_bytes());
+    context.update(&(entity_digests.len() as u64).to_be_bytes());
+    for entity_digest in &entity_digests {
+        context.update(entity_digest.as_ref());
+    }
+
+    Ok(())
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::repository::access_repo::in_memory_repo::InMemAccessRepo;
+    use crate::repository::service_repo::in_memory_repo::InMemServiceRepo;
+    use crate::repository::user_repo::in_memory_repo::InMemUserRepo;
+    use trust0_common::model::access::ServiceAccess;
+    use trust0_common::model::service::{Service, Transport};
+    use trust0_common::model::user::{Status, User};
+
+    fn create_services() -> Vec<Service> {
+        vec![
+            Service::new(200, "Service200", &Transport::TCP, "localhost", 8200),
+            Service::new(201, "Service201", &Transport::UDP, "localhost", 8201),
+            Service::new(202, "Service202", &Transport::TCP, "localhost", 8202),
+        ]
+    }
+
+    fn create_service_repo(services: Vec<Service>) -> Arc<Mutex<dyn ServiceRepository>> {
+        let service_repo = InMemServiceRepo::new();
+        for service in services {
+            service_repo.put(service).unwrap();
+        }
+        Arc::new(Mutex::new(service_repo))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn create_user_and_access_repos(
+        user_status: Status,
+    ) -> (
+        Arc<Mutex<dyn UserRepository>>,
+        Arc<Mutex<dyn AccessRepository>>,
+    ) {
+        let user_repo = InMemUserRepo::new();
+        user_repo
+            .put(User::new(100, "User100", user_status))
+            .unwrap();
+        let access_repo = InMemAccessRepo::new();
+        access_repo.put(ServiceAccess::new(100, 200)).unwrap();
+        (
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(access_repo)),
+        )
+    }
+
+    #[test]
+    fn confighash_compute_when_different_insertion_order() {
+        let services = create_services();
+        let mut reversed_services = services.clone();
+        reversed_services.reverse();
+
+        let config_hash = compute_config_hash(&create_service_repo(services), None).unwrap();
+
+        assert_eq!(config_hash.len(), 64);
+        assert_eq!(
+            compute_config_hash(&create_service_repo(reversed_services), None).unwrap(),
+            config_hash
+        );
+    }
+
+    #[test]
+    fn confighash_compute_when_service_changed() {
+        let services = create_services();
+        let config_hash =
+            compute_config_hash(&create_service_repo(services.clone()), None).unwrap();
+
+        let mut changed_services = services.clone();
+        changed_services[1].port = 9201;
+        assert_ne!(
+            compute_config_hash(&create_service_repo(changed_services), None).unwrap(),
+            config_hash
+        );
+
+        let mut removed_services = services;
+        removed_services.pop();
+        assert_ne!(
+            compute_config_hash(&create_service_repo(removed_services), None).unwrap(),
+            config_hash
+        );
+    }
+
+    #[test]
+    fn confighash_compute_when_users_and_access() {
+        let service_repo = create_service_repo(create_services());
+        let (user_repo, access_repo) = create_user_and_access_repos(Status::Active);
+
+        let services_hash = compute_config_hash(&service_repo, None).unwrap();
+        let config_hash =
+            compute_config_hash(&service_repo, Some((&user_repo, &access_repo))).unwrap();
+
+        assert_ne!(config_hash, services_hash);
+        assert_eq!(
+            compute_config_hash(&service_repo, Some((&user_repo, &access_repo))).unwrap(),
+            config_hash
+        );
+
+        let (inactive_user_repo, access_repo) = create_user_and_access_repos(Status::Inactive);
+        assert_ne!(
+            compute_config_hash(&service_repo, Some((&inactive_user_repo, &access_repo))).unwrap(),
+            config_hash
+        );
+    }
+}
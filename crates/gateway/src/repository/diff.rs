@@ -0,0 +1,351 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+use serde_derive::Serialize;
+
+use crate::repository::access_repo::AccessRepository;
+use crate::repository::service_repo::ServiceRepository;
+use crate::repository::user_repo::UserRepository;
+use trust0_common::error::AppError;
+use trust0_common::model::access::ServiceAccess;
+use trust0_common::model::service::Service;
+use trust0_common::model::user::User;
+
+/// Point-in-time copy of all datasource entities, keyed by their respective primary keys
+#[derive(Clone, Default, Debug)]
+pub struct DatasourceSnapshot {
+    pub users: HashMap<u64, User>,
+    pub services: HashMap<u64, Service>,
+    pub access: HashMap<(u64, u64), ServiceAccess>,
+}
+
+impl DatasourceSnapshot {
+    /// Create snapshot from the current repository contents
+    pub fn from_repositories(
+        access_repo: &Arc<Mutex<dyn AccessRepository>>,
+        service_repo: &Arc<Mutex<dyn ServiceRepository>>,
+        user_repo: &Arc<Mutex<dyn UserRepository>>,
+    ) -> Result<Self, AppError> {
+        Ok(Self {
+            users: user_repo
+                .lock()
+                .unwrap()
+                .get_all()?
+                .into_iter()
+                .map(|user| (user.user_id, user))
+                .collect(),
+            services: service_repo
+                .lock()
+                .unwrap()
+                .get_all()?
+                .into_iter()
+                .map(|service| (service.service_id, service))
+                .collect(),
+            access: access_repo
+                .lock()
+                .unwrap()
+                .get_all()?
+                .into_iter()
+                .map(|access| ((access.user_id, access.service_id), access))
+                .collect(),
+        })
+    }
+}
+
+/// Keys of entities added, removed or modified between two snapshots (sorted by key)
+#[derive(Serialize, Clone, Default, PartialEq, Debug)]
+pub struct EntityDiff<K> {
+    pub added: Vec<K>,
+    pub removed: Vec<K>,
+    pub modified: Vec<K>,
+}
+
+impl<K> EntityDiff<K> {
+    /// Whether there are no changes for the entity type
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl<K: Debug> Display for EntityDiff<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "added={:?}, removed={:?}, modified={:?}",
+            &self.added, &self.removed, &self.modified
+        )
+    }
+}
+
+/// Changes between two datasource snapshots, for each entity type
+#[derive(Serialize, Clone, Default, PartialEq, Debug)]
+pub struct DatasourceDiff {
+    pub users: EntityDiff<u64>,
+    pub services: EntityDiff<u64>,
+    pub access: EntityDiff<(u64, u64)>,
+}
+
+impl DatasourceDiff {
+    /// Whether the snapshots are equivalent
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty() && self.services.is_empty() && self.access.is_empty()
+    }
+}
+
+impl Display for DatasourceDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "users: {}", &self.users)?;
+        writeln!(f, "services: {}", &self.services)?;
+        write!(f, "access: {}", &self.access)
+    }
+}
+
+/// Report entities added, removed and modified (by key) going from the old to the new snapshot
+pub fn diff_datasources(old: &DatasourceSnapshot, new: &DatasourceSnapshot) -> DatasourceDiff {
+    DatasourceDiff {
+        users: diff_entities(&old.users, &new.users),
+        services: diff_entities(&old.services, &new.services),
+        access: diff_entities(&old.access, &new.access),
+    }
+}
+
+/// Compare keyed entity maps
+fn diff_entities<K, V>(old: &HashMap<K, V>, new: &HashMap<K, V>) -> EntityDiff<K>
+where
+    K: Clone + Eq + Hash + Ord,
+    V: PartialEq,
+{
+    let mut diff = EntityDiff {
+        added: vec![],
+        removed: vec![],
+        modified: vec![],
+    };
+
+    let keys: BTreeSet<&K> = old.keys().chain(new.keys()).collect();
+
+    for key in keys {
+        match (old.get(key), new.get(key)) {
+            (None, Some(_)) => diff.added.push(key.clone()),
+            (Some(_), None) => diff.removed.push(key.clone()),
+            (Some(old_entity), Some(new_entity)) if old_entity != new_entity => {
+                diff.modified.push(key.clone())
+            }
+            _ => {}
+        }
+    }
+
+    diff
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::config::{AppConfig, DataSource, DuplicateIdPolicy, InMemoryDb};
+    use std::path::PathBuf;
+    use trust0_common::model::service::Transport;
+    use trust0_common::model::user::Status;
+
+    const ACCESS_DB_FILEPATH: &str = "testdata/db-access.json";
+    const ACCESS_JSONC_DB_FILEPATH: &str = "testdata/db-access-jsonc.json";
+    const SERVICE_DB_FILEPATH: &str = "testdata/db-service.json";
+    const SERVICE_JSONC_DB_FILEPATH: &str = "testdata/db-service-jsonc.json";
+    const USER_DB_FILEPATH: &str = "testdata/db-user.json";
+    const USER_JSONC_DB_FILEPATH: &str = "testdata/db-user-jsonc.json";
+
+    fn create_snapshot() -> DatasourceSnapshot {
+        DatasourceSnapshot {
+            users: HashMap::from([
+                (100, User::new(100, "user100", Status::Active)),
+                (101, User::new(101, "user101", Status::Active)),
+            ]),
+            services: HashMap::from([
+                (
+                    200,
+                    Service::new(200, "Service200", &Transport::TCP, "localhost", 8200),
+                ),
+                (
+                    201,
+                    Service::new(201, "Service201", &Transport::UDP, "localhost", 8201),
+                ),
+            ]),
+            access: HashMap::from([
+                (
+                    (100, 200),
+                    ServiceAccess {
+                        user_id: 100,
+                        service_id: 200,
+                        justification: None,
+                        deny: false,
+                    },
+                ),
+                (
+                    (101, 201),
+                    ServiceAccess {
+                        user_id: 101,
+                        service_id: 201,
+                        justification: None,
+                        deny: false,
+                    },
+                ),
+            ]),
+        }
+    }
+
+    fn create_datasource_snapshot(
+        access_db_file: &str,
+        service_db_file: &str,
+        user_db_file: &str,
+    ) -> DatasourceSnapshot {
+        let datasource = DataSource::InMemoryDb(InMemoryDb {
+            access_db_file: PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join(access_db_file)
+                .to_str()
+                .unwrap()
+                .to_string(),
+            service_db_file: PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join(service_db_file)
+                .to_str()
+                .unwrap()
+                .to_string(),
+            user_db_file: PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join(user_db_file)
+                .to_str()
+                .unwrap()
+                .to_string(),
+            duplicate_service_ids: DuplicateIdPolicy::default(),
+        });
+        let repos = AppConfig::create_datasource_repositories(
+            &datasource,
+            &datasource.repository_factories(),
+        )
+        .unwrap();
+
+        DatasourceSnapshot::from_repositories(&repos.0, &repos.1, &repos.2).unwrap()
+    }
+
+    #[test]
+    fn diffds_diff_datasources_when_no_changes() {
+        let diff = diff_datasources(&create_snapshot(), &create_snapshot());
+
+        assert!(diff.is_empty());
+        assert_eq!(diff, DatasourceDiff::default());
+    }
+
+    #[test]
+    fn diffds_diff_datasources_when_users_changed() {
+        let old = create_snapshot();
+        let mut new = create_snapshot();
+        new.users.remove(&100);
+        new.users.get_mut(&101).unwrap().status = Status::Inactive;
+        new.users
+            .insert(102, User::new(102, "user102", Status::Active));
+
+        let diff = diff_datasources(&old, &new);
+
+        assert_eq!(
+            diff.users,
+            EntityDiff {
+                added: vec![102],
+                removed: vec![100],
+                modified: vec![101],
+            }
+        );
+        assert!(diff.services.is_empty());
+        assert!(diff.access.is_empty());
+    }
+
+    #[test]
+    fn diffds_diff_datasources_when_services_changed() {
+        let old = create_snapshot();
+        let mut new = create_snapshot();
+        new.services.remove(&201);
+        new.services.get_mut(&200).unwrap().port = 8300;
+        new.services.insert(
+            202,
+            Service::new(202, "Service202", &Transport::TCP, "localhost", 8202),
+        );
+
+        let diff = diff_datasources(&old, &new);
+
+        assert_eq!(
+            diff.services,
+            EntityDiff {
+                added: vec![202],
+                removed: vec![201],
+                modified: vec![200],
+            }
+        );
+        assert!(diff.users.is_empty());
+        assert!(diff.access.is_empty());
+    }
+
+    #[test]
+    fn diffds_diff_datasources_when_access_changed() {
+        let old = create_snapshot();
+        let mut new = create_snapshot();
+        new.access.remove(&(101, 201));
+        new.access.insert(
+            (101, 200),
+            ServiceAccess {
+                user_id: 101,
+                service_id: 200,
+                justification: None,
+                deny: false,
+            },
+        );
+        new.access.insert(
+            (100, 200),
+            ServiceAccess {
+                user_id: 100,
+                service_id: 201,
+                justification: None,
+                deny: false,
+            },
+        );
+
+        let diff = diff_datasources(&old, &new);
+
+        assert_eq!(
+            diff.access,
+            EntityDiff {
+                added: vec![(101, 200)],
+                removed: vec![(101, 201)],
+                modified: vec![(100, 200)],
+            }
+        );
+        assert!(diff.users.is_empty());
+        assert!(diff.services.is_empty());
+    }
+
+    #[test]
+    fn diffds_diff_datasources_display() {
+        let old = create_snapshot();
+        let mut new = create_snapshot();
+        new.users.remove(&100);
+
+        assert_eq!(
+            diff_datasources(&old, &new).to_string(),
+            "users: added=[], removed=[100], modified=[]\nservices: added=[], removed=[], modified=[]\naccess: added=[], removed=[], modified=[]"
+        );
+    }
+
+    #[test]
+    fn diffds_from_repositories_when_equivalent_datasources() {
+        let strict_snapshot =
+            create_datasource_snapshot(ACCESS_DB_FILEPATH, SERVICE_DB_FILEPATH, USER_DB_FILEPATH);
+        let jsonc_snapshot = create_datasource_snapshot(
+            ACCESS_JSONC_DB_FILEPATH,
+            SERVICE_JSONC_DB_FILEPATH,
+            USER_JSONC_DB_FILEPATH,
+        );
+
+        assert!(!strict_snapshot.users.is_empty());
+        assert!(!strict_snapshot.services.is_empty());
+        assert!(!strict_snapshot.access.is_empty());
+        assert!(diff_datasources(&strict_snapshot, &jsonc_snapshot).is_empty());
+    }
+}
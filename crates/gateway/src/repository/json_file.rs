@@ -0,0 +1,73 @@
+use serde::de::DeserializeOwned;
+
+use trust0_common::error::AppError;
+
+/// Parse (JSON5) datasource file content. Errors report the file path along with the error's line and column
+/// (when known).
+pub fn parse_json_datasource<T: DeserializeOwned>(
+    connect_spec: &str,
+    data: &str,
+) -> Result<T, AppError> {
+    json5::from_str(data).map_err(|err| {
+        let location = match &err {
+            json5::Error::Message {
+                location: Some(location),
+                ..
+            } => format!(", line={}, column={}", location.line, location.column),
+            _ => String::new(),
+        };
+
+        AppError::GenWithMsgAndErr(
+            format!("Failed to parse JSON: path={}{}", connect_spec, location),
+            Box::new(err),
+        )
+    })
+}
+
+/// Unit tests
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    pub const INVALID_SYNTAX_DB_FILE_PATHPARTS: [&str; 3] = [
+        env!("CARGO_MANIFEST_DIR"),
+        "testdata",
+        "db-invalid-syntax.json",
+    ];
+
+    #[test]
+    fn jsonfile_parse_json_datasource_when_syntax_error() {
+        let db_path: PathBuf = INVALID_SYNTAX_DB_FILE_PATHPARTS.iter().collect();
+        let db_path = db_path.to_str().unwrap();
+        let data = fs::read_to_string(db_path).unwrap();
+
+        match parse_json_datasource::<Vec<u64>>(db_path, &data) {
+            Err(err) => assert!(err.to_string().contains(&format!(
+                "Failed to parse JSON: path={}, line=4, column=5",
+                db_path
+            ))),
+            Ok(_) => panic!("Unexpected successful parse result"),
+        }
+    }
+
+    #[test]
+    fn jsonfile_parse_json_datasource_when_invalid_value_type() {
+        match parse_json_datasource::<Vec<u64>>("db.json", "[1, \"two\"]") {
+            Err(err) => assert!(err
+                .to_string()
+                .contains("Failed to parse JSON: path=db.json, line=1, column=5")),
+            Ok(_) => panic!("Unexpected successful parse result"),
+        }
+    }
+
+    #[test]
+    fn jsonfile_parse_json_datasource_when_valid_content() {
+        assert_eq!(
+            parse_json_datasource::<Vec<u64>>("db.json", "[1, 2, // comment\n 3]").unwrap(),
+            vec![1, 2, 3]
+        );
+    }
+}
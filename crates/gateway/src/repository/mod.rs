@@ -1,3 +1,10 @@
 pub mod access_repo;
+pub mod config_hash;
+pub mod diff;
+pub mod json_file;
+pub mod reloader;
 pub mod service_repo;
+#[cfg(any(test, feature = "test-util"))]
+pub mod snapshot;
 pub mod user_repo;
+pub mod validation;
@@ -0,0 +1,552 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::{AppConfig, DataSource, DatasourceErrorPolicy, InMemoryDb};
+use crate::repository::access_repo::AccessRepository;
+use crate::repository::diff::{self, DatasourceDiff, DatasourceSnapshot};
+use crate::repository::service_repo::ServiceRepository;
+use crate::repository::user_repo::UserRepository;
+use crate::repository::validation;
+use trust0_common::error::AppError;
+use trust0_common::logging::{info, log_throttled, LogLevel};
+use trust0_common::{file, target};
+
+const DATASOURCE_RECHECK_DELAY_MSECS: Duration = Duration::from_millis(30_000);
+/// Interval to check for a reloader stop request, while waiting to recheck the datasource files
+const RELOADER_STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Coordinates reloading of the in-memory datasource repositories from their backing files.
+/// Reload errors are handled according to the configured datasource error policy.
+pub struct DatasourceReloader {
+    datasource: InMemoryDb,
+    access_repo: Arc<Mutex<dyn AccessRepository>>,
+    service_repo: Arc<Mutex<dyn ServiceRepository>>,
+    user_repo: Arc<Mutex<dyn UserRepository>>,
+    error_policy: DatasourceErrorPolicy,
+    datasource_available: Arc<Mutex<bool>>,
+    datasource_lock: Arc<RwLock<()>>,
+    stopping: Arc<AtomicBool>,
+}
+
+impl DatasourceReloader {
+    /// DatasourceReloader constructor
+    pub fn new(
+        datasource: &InMemoryDb,
+        access_repo: &Arc<Mutex<dyn AccessRepository>>,
+        service_repo: &Arc<Mutex<dyn ServiceRepository>>,
+        user_repo: &Arc<Mutex<dyn UserRepository>>,
+        error_policy: DatasourceErrorPolicy,
+        datasource_available: &Arc<Mutex<bool>>,
+        datasource_lock: &Arc<RwLock<()>>,
+    ) -> Self {
+        Self {
+            datasource: datasource.clone(),
+            access_repo: access_repo.clone(),
+            service_repo: service_repo.clone(),
+            user_repo: user_repo.clone(),
+            error_policy,
+            datasource_available: datasource_available.clone(),
+            datasource_lock: datasource_lock.clone(),
+            stopping: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Load all datasource files and, only if every file was successfully processed, replace the
+    /// current repository contents. On error, the error policy determines whether the datasource
+    /// remains available (fail-open) or new connections are denied until a good reload (fail-closed).
+    /// Dangling access references in the reloaded datasource are logged.
+    pub fn reload_all(&self) -> Result<(), AppError> {
+        match self.load_and_apply() {
+            Ok(()) => {
+                *self.datasource_available.lock().unwrap() = true;
+                Ok(())
+            }
+            Err(err) => {
+                if self.error_policy == DatasourceErrorPolicy::FailClosed {
+                    *self.datasource_available.lock().unwrap() = false;
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Reload all datasource files (see `reload_all`), returning the changes applied to the repositories
+    pub fn reload_all_with_diff(&self) -> Result<DatasourceDiff, AppError> {
+        let old_snapshot = DatasourceSnapshot::from_repositories(
+            &self.access_repo,
+            &self.service_repo,
+            &self.user_repo,
+        )?;
+
+        self.reload_all()?;
+
+        let new_snapshot = DatasourceSnapshot::from_repositories(
+            &self.access_repo,
+            &self.service_repo,
+            &self.user_repo,
+        )?;
+
+        Ok(diff::diff_datasources(&old_snapshot, &new_snapshot))
+    }
+
+    /// Spawn a thread to reload the repositories if any of the datasource files change, until a stop is
+    /// requested (see `stop_reloader`). If recheck delay is not supplied, a default of 30s will be used.
+    pub fn spawn_reloader(&self, recheck_delay: Option<Duration>) -> JoinHandle<()> {
+        let reloader = Self {
+            datasource: self.datasource.clone(),
+            access_repo: self.access_repo.clone(),
+            service_repo: self.service_repo.clone(),
+            user_repo: self.user_repo.clone(),
+            error_policy: self.error_policy,
+            datasource_available: self.datasource_available.clone(),
+            datasource_lock: self.datasource_lock.clone(),
+            stopping: self.stopping.clone(),
+        };
+        let recheck_delay = recheck_delay.unwrap_or(DATASOURCE_RECHECK_DELAY_MSECS);
+
+        info(
+            &target!(),
+            &format!(
+                "Starting datasource reloader: policy={:?}, files={:?}",
+                &reloader.error_policy,
+                &reloader.datasource_files()
+            ),
+        );
+
+        thread::spawn(move || {
+            let mut last_mtimes: Vec<SystemTime> = reloader
+                .datasource_files()
+                .iter()
+                .map(|filepath| {
+                    file::file_mtime(filepath.as_path()).unwrap_or(SystemTime::UNIX_EPOCH)
+                })
+                .collect();
+
+            while reloader.wait_for_recheck(recheck_delay) {
+                match reloader.process_reload(&mut last_mtimes) {
+                    Ok(reloaded) => {
+                        if reloaded {
+                            info(
+                                &target!(),
+                                "Datasource files changed, repositories reloaded",
+                            )
+                        }
+                    }
+                    Err(err) => match reloader.error_policy {
+                        DatasourceErrorPolicy::FailOpen => log_throttled(
+                            &target!(),
+                            LogLevel::WARN,
+                            &format!(
+                                "Datasource reload failed, serving prior data: err={:?}",
+                                &err
+                            ),
+                        ),
+                        DatasourceErrorPolicy::FailClosed => log_throttled(
+                            &target!(),
+                            LogLevel::ERROR,
+                            &format!(
+                                "Datasource reload failed, denying new connections: err={:?}",
+                                &err
+                            ),
+                        ),
+                    },
+                }
+            }
+
+            info(&target!(), "Datasource reloader stopped");
+        })
+    }
+
+    /// Request the (spawned) reloader thread to stop
+    pub fn stop_reloader(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+    }
+
+    /// Wait (up to the recheck delay) for the next datasource files check. Returns false if a stop was requested
+    fn wait_for_recheck(&self, recheck_delay: Duration) -> bool {
+        let recheck_deadline = Instant::now() + recheck_delay;
+
+        while !self.stopping.load(Ordering::SeqCst) {
+            let remaining_delay = recheck_deadline.saturating_duration_since(Instant::now());
+            if remaining_delay.is_zero() {
+                return true;
+            }
+            thread::sleep(RELOADER_STOP_POLL_INTERVAL.min(remaining_delay));
+        }
+
+        false
+    }
+
+    /// Reload repositories if any datasource file has changed. Returns true if reload occurred
+    fn process_reload(&self, last_mtimes: &mut [SystemTime]) -> Result<bool, AppError> {
+        let mtimes = self
+            .datasource_files()
+            .iter()
+            .map(|filepath| file::file_mtime(filepath.as_path()))
+            .collect::<Result<Vec<SystemTime>, AppError>>();
+
+        let mtimes = match mtimes {
+            Ok(mtimes) => mtimes,
+            Err(err) => {
+                if self.error_policy == DatasourceErrorPolicy::FailClosed {
+                    *self.datasource_available.lock().unwrap() = false;
+                }
+                return Err(err);
+            }
+        };
+
+        if (last_mtimes == mtimes.as_slice()) && *self.datasource_available.lock().unwrap() {
+            return Ok(false);
+        }
+        last_mtimes.clone_from_slice(&mtimes);
+
+        self.reload_all()?;
+
+        Ok(true)
+    }
+
+    /// Datasource file paths (access, service, user)
+    fn datasource_files(&self) -> Vec<PathBuf> {
+        vec![
+            PathBuf::from(&self.datasource.access_db_file),
+            PathBuf::from(&self.datasource.service_db_file),
+            PathBuf::from(&self.datasource.user_db_file),
+        ]
+    }
+
+    /// Load datasource into staging repositories (logging any dangling access references), then replace contents of
+    /// current repositories. The replacement is done while holding the datasource lock (exclusively), so connection
+    /// authorization never sees a mix of prior and reloaded repositories.
+    fn load_and_apply(&self) -> Result<(), AppError> {
+        let datasource = DataSource::InMemoryDb(self.datasource.clone());
+        let (access_repo, service_repo, user_repo) = AppConfig::create_datasource_repositories(
+            &datasource,
+            &datasource.repository_factories(),
+        )?;
+
+        validation::validate_access_references(&access_repo, &service_repo, &user_repo)?;
+
+        let users = user_repo.lock().unwrap().get_all()?;
+        let services = service_repo.lock().unwrap().get_all()?;
+        let accesses = access_repo.lock().unwrap().get_all()?;
+
+        let _datasource_guard = self.datasource_lock.write().unwrap();
+        let current_user_repo = self.user_repo.lock().unwrap();
+        let current_service_repo = self.service_repo.lock().unwrap();
+        let current_access_repo = self.access_repo.lock().unwrap();
+
+        // Users
+        let user_ids: HashSet<u64> = users.iter().map(|user| user.user_id).collect();
+        for user in current_user_repo.get_all()? {
+            if !user_ids.contains(&user.user_id) {
+                current_user_repo.delete(user.user_id)?;
+            }
+        }
+        for user in users {
+            current_user_repo.put(user)?;
+        }
+
+        // Services
+        let service_ids: HashSet<u64> = services.iter().map(|service| service.service_id).collect();
+        for service in current_service_repo.get_all()? {
+            if !service_ids.contains(&service.service_id) {
+                current_service_repo.delete(service.service_id)?;
+            }
+        }
+        for service in services {
+            current_service_repo.put(service)?;
+        }
+
+        // Accesses
+        let access_keys: HashSet<(u64, u64)> = accesses
+            .iter()
+            .map(|access| (access.user_id, access.service_id))
+            .collect();
+        for access in current_access_repo.get_all()? {
+            if !access_keys.contains(&(access.user_id, access.service_id)) {
+                current_access_repo.delete(access.user_id, access.service_id)?;
+            }
+        }
+        for access in accesses {
+            current_access_repo.put(access)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+    use crate::config::DuplicateIdPolicy;
+    use crate::repository::access_repo::in_memory_repo::InMemAccessRepo;
+    use crate::repository::service_repo::in_memory_repo::InMemServiceRepo;
+    use crate::repository::user_repo::in_memory_repo::InMemUserRepo;
+    use std::fs;
+
+    const DB_FILE_PATHPARTS: [&str; 2] = [env!("CARGO_MANIFEST_DIR"), "testdata"];
+
+    // utils
+    // =====
+
+    /// Copy testdata datasource files into a (unique) temporary directory
+    pub fn create_temp_datasource(test_name: &str) -> InMemoryDb {
+        let testdata_dir: PathBuf = DB_FILE_PATHPARTS.iter().collect();
+        let temp_dir = std::env::temp_dir().join(format!(
+            "trust0-gateway-{}-{}",
+            test_name,
+            std::process::id()
+        ));
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let copy_file = |name: &str| {
+            let target_file = temp_dir.join(name);
+            fs::copy(testdata_dir.join(name), &target_file).unwrap();
+            target_file.to_str().unwrap().to_string()
+        };
+
+        InMemoryDb {
+            access_db_file: copy_file("db-access.json"),
+            service_db_file: copy_file("db-service.json"),
+            user_db_file: copy_file("db-user.json"),
+            duplicate_service_ids: DuplicateIdPolicy::default(),
+        }
+    }
+
+    fn create_reloader(
+        datasource: &InMemoryDb,
+        error_policy: DatasourceErrorPolicy,
+    ) -> DatasourceReloader {
+        let access_repo: Arc<Mutex<dyn AccessRepository>> =
+            Arc::new(Mutex::new(InMemAccessRepo::new()));
+        let service_repo: Arc<Mutex<dyn ServiceRepository>> =
+            Arc::new(Mutex::new(InMemServiceRepo::new()));
+        let user_repo: Arc<Mutex<dyn UserRepository>> = Arc::new(Mutex::new(InMemUserRepo::new()));
+
+        DatasourceReloader::new(
+            datasource,
+            &access_repo,
+            &service_repo,
+            &user_repo,
+            error_policy,
+            &Arc::new(Mutex::new(true)),
+            &Arc::new(RwLock::new(())),
+        )
+    }
+
+    // tests
+    // =====
+
+    #[test]
+    fn dsreloader_reload_all_when_valid_files() {
+        let datasource = create_temp_datasource("dsreloader-valid");
+        let reloader = create_reloader(&datasource, DatasourceErrorPolicy::FailClosed);
+
+        if let Err(err) = reloader.reload_all() {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+
+        assert!(*reloader.datasource_available.lock().unwrap());
+        assert_eq!(
+            reloader.user_repo.lock().unwrap().get_all().unwrap().len(),
+            2
+        );
+        assert_eq!(
+            reloader
+                .service_repo
+                .lock()
+                .unwrap()
+                .get_all()
+                .unwrap()
+                .len(),
+            5
+        );
+        assert_eq!(
+            reloader
+                .access_repo
+                .lock()
+                .unwrap()
+                .get_all()
+                .unwrap()
+                .len(),
+            5
+        );
+
+        fs::write(
+            &datasource.user_db_file,
+            "[{\"userId\": 100, \"name\": \"User100\", \"status\": \"inactive\"}]",
+        )
+        .unwrap();
+
+        if let Err(err) = reloader.reload_all() {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+
+        let users = reloader.user_repo.lock().unwrap().get_all().unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].user_id, 100);
+    }
+
+    #[test]
+    fn dsreloader_reload_all_when_bad_reload_and_fail_open_policy() {
+        let datasource = create_temp_datasource("dsreloader-failopen");
+        let reloader = create_reloader(&datasource, DatasourceErrorPolicy::FailOpen);
+        reloader.reload_all().unwrap();
+
+        fs::write(&datasource.service_db_file, "[{\"serviceId\": 200,").unwrap();
+
+        if reloader.reload_all().is_ok() {
+            panic!("Unexpected successful reload");
+        }
+
+        assert!(*reloader.datasource_available.lock().unwrap());
+        assert_eq!(
+            reloader.user_repo.lock().unwrap().get_all().unwrap().len(),
+            2
+        );
+        assert_eq!(
+            reloader
+                .service_repo
+                .lock()
+                .unwrap()
+                .get_all()
+                .unwrap()
+                .len(),
+            5
+        );
+        assert_eq!(
+            reloader
+                .access_repo
+                .lock()
+                .unwrap()
+                .get_all()
+                .unwrap()
+                .len(),
+            5
+        );
+    }
+
+    #[test]
+    fn dsreloader_reload_all_when_bad_reload_and_fail_closed_policy() {
+        let datasource = create_temp_datasource("dsreloader-failclosed");
+        let reloader = create_reloader(&datasource, DatasourceErrorPolicy::FailClosed);
+        reloader.reload_all().unwrap();
+
+        let good_service_data = fs::read_to_string(&datasource.service_db_file).unwrap();
+        fs::write(&datasource.service_db_file, "[{\"serviceId\": 200,").unwrap();
+
+        if reloader.reload_all().is_ok() {
+            panic!("Unexpected successful reload");
+        }
+
+        assert!(!*reloader.datasource_available.lock().unwrap());
+        assert_eq!(
+            reloader
+                .service_repo
+                .lock()
+                .unwrap()
+                .get_all()
+                .unwrap()
+                .len(),
+            5
+        );
+
+        fs::write(&datasource.service_db_file, good_service_data).unwrap();
+
+        if let Err(err) = reloader.reload_all() {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+
+        assert!(*reloader.datasource_available.lock().unwrap());
+    }
+
+    #[test]
+    fn dsreloader_process_reload_when_missing_file_and_fail_closed_policy() {
+        let datasource = create_temp_datasource("dsreloader-missing");
+        let reloader = create_reloader(&datasource, DatasourceErrorPolicy::FailClosed);
+        let mut last_mtimes = vec![SystemTime::UNIX_EPOCH; 3];
+
+        match reloader.process_reload(&mut last_mtimes) {
+            Ok(reloaded) => assert!(reloaded),
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        }
+
+        match reloader.process_reload(&mut last_mtimes) {
+            Ok(reloaded) => assert!(!reloaded),
+            Err(err) => panic!("Unexpected result: err={:?}", &err),
+        }
+
+        fs::remove_file(&datasource.access_db_file).unwrap();
+
+        if reloader.process_reload(&mut last_mtimes).is_ok() {
+            panic!("Unexpected successful reload");
+        }
+
+        assert!(!*reloader.datasource_available.lock().unwrap());
+        assert_eq!(
+            reloader
+                .access_repo
+                .lock()
+                .unwrap()
+                .get_all()
+                .unwrap()
+                .len(),
+            5
+        );
+    }
+
+    #[test]
+    fn dsreloader_reload_all_when_authorization_holds_datasource_lock() {
+        let datasource = create_temp_datasource("dsreloader-locked");
+        let reloader = Arc::new(create_reloader(
+            &datasource,
+            DatasourceErrorPolicy::FailClosed,
+        ));
+
+        let datasource_guard = reloader.datasource_lock.read().unwrap();
+        let reload_reloader = reloader.clone();
+        let reload_handle = thread::spawn(move || reload_reloader.reload_all());
+        thread::sleep(Duration::from_millis(100));
+
+        assert!(!reload_handle.is_finished());
+        assert!(reloader
+            .user_repo
+            .lock()
+            .unwrap()
+            .get_all()
+            .unwrap()
+            .is_empty());
+
+        drop(datasource_guard);
+
+        if let Err(err) = reload_handle.join().unwrap() {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+        assert_eq!(
+            reloader.user_repo.lock().unwrap().get_all().unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn dsreloader_spawn_reloader_when_stopped() {
+        let datasource = create_temp_datasource("dsreloader-stopped");
+        let reloader = create_reloader(&datasource, DatasourceErrorPolicy::FailClosed);
+
+        let reloader_handle = reloader.spawn_reloader(Some(Duration::from_secs(30)));
+        thread::sleep(Duration::from_millis(50));
+        assert!(!reloader_handle.is_finished());
+
+        let started_at = Instant::now();
+        reloader.stop_reloader();
+        reloader_handle.join().unwrap();
+
+        assert!(started_at.elapsed() < Duration::from_secs(2));
+    }
+}
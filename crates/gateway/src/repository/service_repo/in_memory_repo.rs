@@ -1,23 +1,66 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
+use crate::config::DuplicateIdPolicy;
+use crate::repository::json_file::parse_json_datasource;
 use crate::repository::service_repo::ServiceRepository;
+#[cfg(any(test, feature = "test-util"))]
+use crate::repository::snapshot::RepoSnapshot;
 use trust0_common::error::AppError;
+use trust0_common::logging::warn;
 use trust0_common::model::service::Service;
+use trust0_common::target;
 
 pub struct InMemServiceRepo {
     services: RwLock<HashMap<u64, Service>>,
+    duplicate_id_policy: DuplicateIdPolicy,
 }
 
 impl InMemServiceRepo {
     /// Creates a new in-memory service store.
     pub fn new() -> InMemServiceRepo {
+        Self::new_with_duplicate_id_policy(DuplicateIdPolicy::default())
+    }
+
+    /// Creates a new in-memory service store, using given policy for duplicate service IDs (in datasource file)
+    pub fn new_with_duplicate_id_policy(
+        duplicate_id_policy: DuplicateIdPolicy,
+    ) -> InMemServiceRepo {
         InMemServiceRepo {
             services: RwLock::new(HashMap::new()),
+            duplicate_id_policy,
         }
     }
 
+    /// Check datasource services for duplicate service IDs (handled according to duplicate ID policy)
+    fn check_duplicate_ids(
+        &self,
+        connect_spec: &str,
+        services: &[Service],
+    ) -> Result<(), AppError> {
+        let mut service_ids = HashSet::new();
+
+        for service in services {
+            if service_ids.insert(service.service_id) {
+                continue;
+            }
+
+            let msg = format!(
+                "Duplicate service ID in datasource: path={}, svc_id={}",
+                connect_spec, service.service_id
+            );
+            match self.duplicate_id_policy {
+                DuplicateIdPolicy::Strict => return Err(AppError::General(msg)),
+                DuplicateIdPolicy::KeepLast => {
+                    warn(&target!(), &format!("{} (keeping last entry)", msg))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn access_data_for_write(&self) -> Result<RwLockWriteGuard<HashMap<u64, Service>>, AppError> {
         self.services.write().map_err(|err| {
             AppError::General(format!("Failed to access write lock to DB: err={}", err))
@@ -31,6 +74,26 @@ impl InMemServiceRepo {
     }
 }
 
+#[cfg(any(test, feature = "test-util"))]
+impl InMemServiceRepo {
+    /// Capture all entries (under the lock)
+    pub fn snapshot(&self) -> Result<RepoSnapshot<u64, Service>, AppError> {
+        Ok(RepoSnapshot::new(self.access_data_for_read()?.clone()))
+    }
+
+    /// Replace all entries (under the lock) with those of the given snapshot
+    pub fn restore(&self, snapshot: RepoSnapshot<u64, Service>) -> Result<(), AppError> {
+        *self.access_data_for_write()? = snapshot.into_entries();
+        Ok(())
+    }
+}
+
+impl Default for InMemServiceRepo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ServiceRepository for InMemServiceRepo {
     fn connect_to_datasource(&mut self, connect_spec: &str) -> Result<(), AppError> {
         let data = fs::read_to_string(connect_spec).map_err(|err| {
@@ -39,12 +102,16 @@ impl ServiceRepository for InMemServiceRepo {
                 Box::new(err),
             )
         })?;
-        let services: Vec<Service> = serde_json::from_str(&data).map_err(|err| {
-            AppError::GenWithMsgAndErr(
-                format!("Failed to parse JSON: path={}", connect_spec),
-                Box::new(err),
-            )
-        })?;
+        let mut services: Vec<Service> = parse_json_datasource(connect_spec, &data)?;
+        for service in services.iter_mut() {
+            service.normalize_host().map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    format!("Invalid service in datasource: path={}", connect_spec),
+                    Box::new(err),
+                )
+            })?;
+        }
+        self.check_duplicate_ids(connect_spec, &services)?;
 
         for service in services.iter().as_ref() {
             self.put(service.clone())?;
@@ -82,16 +149,32 @@ impl ServiceRepository for InMemServiceRepo {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::repository::json_file::tests::INVALID_SYNTAX_DB_FILE_PATHPARTS;
     use std::path::PathBuf;
     use trust0_common::model::service::Transport;
 
     const VALID_SERVICE_DB_FILE_PATHPARTS: [&str; 3] =
         [env!("CARGO_MANIFEST_DIR"), "testdata", "db-service.json"];
+    const VALID_SERVICE_DB_JSONC_FILE_PATHPARTS: [&str; 3] = [
+        env!("CARGO_MANIFEST_DIR"),
+        "testdata",
+        "db-service-jsonc.json",
+    ];
     const INVALID_SERVICE_DB_FILE_PATHPARTS: [&str; 3] = [
         env!("CARGO_MANIFEST_DIR"),
         "testdata",
         "db-service-INVALID.json",
     ];
+    const INVALID_HOST_SERVICE_DB_FILE_PATHPARTS: [&str; 3] = [
+        env!("CARGO_MANIFEST_DIR"),
+        "testdata",
+        "db-service-invalid-host.json",
+    ];
+    const DUPLICATE_IDS_SERVICE_DB_FILE_PATHPARTS: [&str; 3] = [
+        env!("CARGO_MANIFEST_DIR"),
+        "testdata",
+        "db-service-duplicate-ids.json",
+    ];
 
     #[test]
     fn inmemsvcrepo_connect_to_datasource_when_invalid_filepath() {
@@ -105,6 +188,76 @@ mod tests {
         }
     }
 
+    #[test]
+    fn inmemsvcrepo_connect_to_datasource_when_invalid_json_syntax() {
+        let invalid_db_path: PathBuf = INVALID_SYNTAX_DB_FILE_PATHPARTS.iter().collect();
+        let invalid_db_pathstr = invalid_db_path.to_str().unwrap();
+
+        let mut service_repo = InMemServiceRepo::new();
+
+        match service_repo.connect_to_datasource(invalid_db_pathstr) {
+            Err(err) => assert!(err
+                .to_string()
+                .contains(&format!("path={}, line=4, column=5", invalid_db_pathstr))),
+            Ok(()) => panic!("Unexpected result: file={}", invalid_db_pathstr),
+        }
+    }
+
+    #[test]
+    fn inmemsvcrepo_connect_to_datasource_when_duplicate_ids_and_keep_last_policy() {
+        let service_db_path: PathBuf = DUPLICATE_IDS_SERVICE_DB_FILE_PATHPARTS.iter().collect();
+        let service_db_pathstr = service_db_path.to_str().unwrap();
+
+        let mut service_repo =
+            InMemServiceRepo::new_with_duplicate_id_policy(DuplicateIdPolicy::KeepLast);
+
+        if let Err(err) = service_repo.connect_to_datasource(service_db_pathstr) {
+            panic!(
+                "Unexpected result: file={}, err={:?}",
+                service_db_pathstr, &err
+            );
+        }
+
+        let services = service_repo.services.read().unwrap();
+        assert_eq!(services.len(), 2);
+        assert_eq!(services.get(&200).unwrap().name, "Service200-copy");
+        assert_eq!(services.get(&200).unwrap().port, 8210);
+    }
+
+    #[test]
+    fn inmemsvcrepo_connect_to_datasource_when_duplicate_ids_and_strict_policy() {
+        let service_db_path: PathBuf = DUPLICATE_IDS_SERVICE_DB_FILE_PATHPARTS.iter().collect();
+        let service_db_pathstr = service_db_path.to_str().unwrap();
+
+        let mut service_repo =
+            InMemServiceRepo::new_with_duplicate_id_policy(DuplicateIdPolicy::Strict);
+
+        match service_repo.connect_to_datasource(service_db_pathstr) {
+            Err(err) => assert!(err.to_string().contains(&format!(
+                "Duplicate service ID in datasource: path={}, svc_id=200",
+                service_db_pathstr
+            ))),
+            Ok(()) => panic!("Unexpected result: file={}", service_db_pathstr),
+        }
+
+        assert!(service_repo.snapshot().unwrap().is_empty());
+    }
+
+    #[test]
+    fn inmemsvcrepo_connect_to_datasource_when_invalid_host() {
+        let service_db_path: PathBuf = INVALID_HOST_SERVICE_DB_FILE_PATHPARTS.iter().collect();
+        let service_db_pathstr = service_db_path.to_str().unwrap();
+
+        let mut service_repo = InMemServiceRepo::new();
+
+        match service_repo.connect_to_datasource(service_db_pathstr) {
+            Err(err) => assert!(format!("{:?}", err).contains("svc_id=201")),
+            Ok(()) => panic!("Unexpected result: file={}", service_db_pathstr),
+        }
+
+        assert!(service_repo.snapshot().unwrap().is_empty());
+    }
+
     #[test]
     fn inmemsvcrepo_connect_to_datasource_when_valid_filepath() {
         let valid_service_db_path: PathBuf = VALID_SERVICE_DB_FILE_PATHPARTS.iter().collect();
@@ -128,6 +281,20 @@ mod tests {
                     transport: Transport::TCP,
                     host: "localhost".to_string(),
                     port: 8200,
+                    relay_retries: 0,
+                    forward_empty_datagrams: false,
+                    allowed_client_cidrs: vec![],
+                    dns_cache_ttl: None,
+                    reauth_interval: None,
+                    upstream_bind_addr: None,
+                    fast_relay: false,
+                    client_auth: None,
+                    udp_target: None,
+                    log_sample_rate: None,
+                    max_response_bytes: None,
+                    proxyable: true,
+                    max_qps: None,
+                    upstream_prefix: vec![],
                 },
             ),
             (
@@ -138,6 +305,20 @@ mod tests {
                     transport: Transport::TCP,
                     host: "localhost".to_string(),
                     port: 8201,
+                    relay_retries: 0,
+                    forward_empty_datagrams: false,
+                    allowed_client_cidrs: vec![],
+                    dns_cache_ttl: None,
+                    reauth_interval: None,
+                    upstream_bind_addr: None,
+                    fast_relay: false,
+                    client_auth: None,
+                    udp_target: None,
+                    log_sample_rate: None,
+                    max_response_bytes: None,
+                    proxyable: true,
+                    max_qps: None,
+                    upstream_prefix: vec![],
                 },
             ),
             (
@@ -148,6 +329,20 @@ mod tests {
                     transport: Transport::TCP,
                     host: "localhost".to_string(),
                     port: 8202,
+                    relay_retries: 0,
+                    forward_empty_datagrams: false,
+                    allowed_client_cidrs: vec![],
+                    dns_cache_ttl: None,
+                    reauth_interval: None,
+                    upstream_bind_addr: None,
+                    fast_relay: false,
+                    client_auth: None,
+                    udp_target: None,
+                    log_sample_rate: None,
+                    max_response_bytes: None,
+                    proxyable: true,
+                    max_qps: None,
+                    upstream_prefix: vec![],
                 },
             ),
             (
@@ -158,6 +353,20 @@ mod tests {
                     transport: Transport::TCP,
                     host: "localhost".to_string(),
                     port: 8500,
+                    relay_retries: 0,
+                    forward_empty_datagrams: false,
+                    allowed_client_cidrs: vec![],
+                    dns_cache_ttl: None,
+                    reauth_interval: None,
+                    upstream_bind_addr: None,
+                    fast_relay: false,
+                    client_auth: None,
+                    udp_target: None,
+                    log_sample_rate: None,
+                    max_response_bytes: None,
+                    proxyable: true,
+                    max_qps: None,
+                    upstream_prefix: vec![],
                 },
             ),
             (
@@ -168,6 +377,20 @@ mod tests {
                     transport: Transport::UDP,
                     host: "localhost".to_string(),
                     port: 8600,
+                    relay_retries: 0,
+                    forward_empty_datagrams: false,
+                    allowed_client_cidrs: vec![],
+                    dns_cache_ttl: None,
+                    reauth_interval: None,
+                    upstream_bind_addr: None,
+                    fast_relay: false,
+                    client_auth: None,
+                    udp_target: None,
+                    log_sample_rate: None,
+                    max_response_bytes: None,
+                    proxyable: true,
+                    max_qps: None,
+                    upstream_prefix: vec![],
                 },
             ),
         ]);
@@ -192,6 +415,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn inmemsvcrepo_connect_to_datasource_when_valid_jsonc_filepath() {
+        let valid_service_db_path: PathBuf = VALID_SERVICE_DB_FILE_PATHPARTS.iter().collect();
+        let valid_service_db_jsonc_path: PathBuf =
+            VALID_SERVICE_DB_JSONC_FILE_PATHPARTS.iter().collect();
+
+        let mut service_repo = InMemServiceRepo::new();
+        let mut service_jsonc_repo = InMemServiceRepo::new();
+
+        if let Err(err) =
+            service_repo.connect_to_datasource(valid_service_db_path.to_str().unwrap())
+        {
+            panic!("Unexpected strict JSON result: err={:?}", &err);
+        }
+        if let Err(err) =
+            service_jsonc_repo.connect_to_datasource(valid_service_db_jsonc_path.to_str().unwrap())
+        {
+            panic!("Unexpected JSONC result: err={:?}", &err);
+        }
+
+        let expected_service_db_map: HashMap<u64, Service> =
+            service_repo.services.into_inner().unwrap();
+        let actual_service_db_map: HashMap<u64, Service> =
+            service_jsonc_repo.services.into_inner().unwrap();
+
+        assert!(!actual_service_db_map.is_empty());
+        assert_eq!(actual_service_db_map, expected_service_db_map);
+    }
+
     #[test]
     fn inmemsvcrepo_put() {
         let service_repo = InMemServiceRepo::new();
@@ -202,6 +454,20 @@ mod tests {
             transport: Transport::TCP,
             host: "site1".to_string(),
             port: 100,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
         };
 
         if let Err(err) = service_repo.put(service.clone()) {
@@ -225,6 +491,20 @@ mod tests {
             transport: Transport::TCP,
             host: "site1".to_string(),
             port: 100,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
         };
 
         service_repo
@@ -253,6 +533,20 @@ mod tests {
                 transport: Transport::TCP,
                 host: "site1".to_string(),
                 port: 100,
+                relay_retries: 0,
+                forward_empty_datagrams: false,
+                allowed_client_cidrs: vec![],
+                dns_cache_ttl: None,
+                reauth_interval: None,
+                upstream_bind_addr: None,
+                fast_relay: false,
+                client_auth: None,
+                udp_target: None,
+                log_sample_rate: None,
+                max_response_bytes: None,
+                proxyable: true,
+                max_qps: None,
+                upstream_prefix: vec![],
             },
             Service {
                 service_id: 2,
@@ -260,6 +554,20 @@ mod tests {
                 transport: Transport::TCP,
                 host: "site2".to_string(),
                 port: 200,
+                relay_retries: 0,
+                forward_empty_datagrams: false,
+                allowed_client_cidrs: vec![],
+                dns_cache_ttl: None,
+                reauth_interval: None,
+                upstream_bind_addr: None,
+                fast_relay: false,
+                client_auth: None,
+                udp_target: None,
+                log_sample_rate: None,
+                max_response_bytes: None,
+                proxyable: true,
+                max_qps: None,
+                upstream_prefix: vec![],
             },
             Service {
                 service_id: 3,
@@ -267,6 +575,20 @@ mod tests {
                 transport: Transport::UDP,
                 host: "site3".to_string(),
                 port: 300,
+                relay_retries: 0,
+                forward_empty_datagrams: false,
+                allowed_client_cidrs: vec![],
+                dns_cache_ttl: None,
+                reauth_interval: None,
+                upstream_bind_addr: None,
+                fast_relay: false,
+                client_auth: None,
+                udp_target: None,
+                log_sample_rate: None,
+                max_response_bytes: None,
+                proxyable: true,
+                max_qps: None,
+                upstream_prefix: vec![],
             },
         ];
 
@@ -309,6 +631,20 @@ mod tests {
                 transport: Transport::TCP,
                 host: "site1".to_string(),
                 port: 100,
+                relay_retries: 0,
+                forward_empty_datagrams: false,
+                allowed_client_cidrs: vec![],
+                dns_cache_ttl: None,
+                reauth_interval: None,
+                upstream_bind_addr: None,
+                fast_relay: false,
+                client_auth: None,
+                udp_target: None,
+                log_sample_rate: None,
+                max_response_bytes: None,
+                proxyable: true,
+                max_qps: None,
+                upstream_prefix: vec![],
             },
             Service {
                 service_id: 2,
@@ -316,6 +652,20 @@ mod tests {
                 transport: Transport::TCP,
                 host: "site2".to_string(),
                 port: 200,
+                relay_retries: 0,
+                forward_empty_datagrams: false,
+                allowed_client_cidrs: vec![],
+                dns_cache_ttl: None,
+                reauth_interval: None,
+                upstream_bind_addr: None,
+                fast_relay: false,
+                client_auth: None,
+                udp_target: None,
+                log_sample_rate: None,
+                max_response_bytes: None,
+                proxyable: true,
+                max_qps: None,
+                upstream_prefix: vec![],
             },
             Service {
                 service_id: 3,
@@ -323,6 +673,20 @@ mod tests {
                 transport: Transport::UDP,
                 host: "site3".to_string(),
                 port: 300,
+                relay_retries: 0,
+                forward_empty_datagrams: false,
+                allowed_client_cidrs: vec![],
+                dns_cache_ttl: None,
+                reauth_interval: None,
+                upstream_bind_addr: None,
+                fast_relay: false,
+                client_auth: None,
+                udp_target: None,
+                log_sample_rate: None,
+                max_response_bytes: None,
+                proxyable: true,
+                max_qps: None,
+                upstream_prefix: vec![],
             },
         ];
 
@@ -360,6 +724,20 @@ mod tests {
                     transport: Transport::TCP,
                     host: "site1".to_string(),
                     port: 100,
+                    relay_retries: 0,
+                    forward_empty_datagrams: false,
+                    allowed_client_cidrs: vec![],
+                    dns_cache_ttl: None,
+                    reauth_interval: None,
+                    upstream_bind_addr: None,
+                    fast_relay: false,
+                    client_auth: None,
+                    udp_target: None,
+                    log_sample_rate: None,
+                    max_response_bytes: None,
+                    proxyable: true,
+                    max_qps: None,
+                    upstream_prefix: vec![],
                 },
             ),
             (
@@ -370,6 +748,20 @@ mod tests {
                     transport: Transport::TCP,
                     host: "site2".to_string(),
                     port: 200,
+                    relay_retries: 0,
+                    forward_empty_datagrams: false,
+                    allowed_client_cidrs: vec![],
+                    dns_cache_ttl: None,
+                    reauth_interval: None,
+                    upstream_bind_addr: None,
+                    fast_relay: false,
+                    client_auth: None,
+                    udp_target: None,
+                    log_sample_rate: None,
+                    max_response_bytes: None,
+                    proxyable: true,
+                    max_qps: None,
+                    upstream_prefix: vec![],
                 },
             ),
             (
@@ -380,6 +772,20 @@ mod tests {
                     transport: Transport::UDP,
                     host: "site3".to_string(),
                     port: 300,
+                    relay_retries: 0,
+                    forward_empty_datagrams: false,
+                    allowed_client_cidrs: vec![],
+                    dns_cache_ttl: None,
+                    reauth_interval: None,
+                    upstream_bind_addr: None,
+                    fast_relay: false,
+                    client_auth: None,
+                    udp_target: None,
+                    log_sample_rate: None,
+                    max_response_bytes: None,
+                    proxyable: true,
+                    max_qps: None,
+                    upstream_prefix: vec![],
                 },
             ),
         ]);
@@ -403,6 +809,20 @@ mod tests {
             transport: Transport::TCP,
             host: "site1".to_string(),
             port: 100,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
         };
 
         service_repo
@@ -430,6 +850,20 @@ mod tests {
             transport: Transport::TCP,
             host: "site1".to_string(),
             port: 100,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
         };
 
         service_repo
@@ -449,4 +883,42 @@ mod tests {
         assert!(actual_prev_service.is_some());
         assert_eq!(actual_prev_service.unwrap(), service);
     }
+
+    #[test]
+    fn inmemsvcrepo_snapshot_and_restore() {
+        let valid_service_db_path: PathBuf = VALID_SERVICE_DB_FILE_PATHPARTS.iter().collect();
+        let valid_service_db_pathstr = valid_service_db_path.to_str().unwrap();
+
+        let mut service_repo = InMemServiceRepo::new();
+        service_repo
+            .connect_to_datasource(valid_service_db_pathstr)
+            .unwrap();
+
+        let snapshot = service_repo.snapshot().unwrap();
+        let original_services = service_repo.services.read().unwrap().clone();
+        assert_eq!(snapshot.len(), original_services.len());
+
+        service_repo
+            .put(Service::new(
+                299,
+                "Service299",
+                &Transport::TCP,
+                "localhost",
+                8299,
+            ))
+            .unwrap();
+        service_repo.delete(200).unwrap();
+        assert_ne!(*service_repo.services.read().unwrap(), original_services);
+
+        service_repo.restore(snapshot.clone()).unwrap();
+        assert_eq!(*service_repo.services.read().unwrap(), original_services);
+
+        service_repo
+            .restore(RepoSnapshot::new(HashMap::new()))
+            .unwrap();
+        assert!(service_repo.snapshot().unwrap().is_empty());
+
+        service_repo.restore(snapshot).unwrap();
+        assert_eq!(*service_repo.services.read().unwrap(), original_services);
+    }
 }
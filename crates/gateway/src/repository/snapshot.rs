@@ -0,0 +1,29 @@
+use std::collections::HashMap;
+
+/// Captured state (all entries) of an in-memory repository, which can later be restored
+#[derive(Clone, Debug)]
+pub struct RepoSnapshot<K, V> {
+    entries: HashMap<K, V>,
+}
+
+impl<K, V> RepoSnapshot<K, V> {
+    /// RepoSnapshot constructor
+    pub fn new(entries: HashMap<K, V>) -> Self {
+        Self { entries }
+    }
+
+    /// Number of captured entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether no entries were captured
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Consume snapshot, returning captured entries
+    pub fn into_entries(self) -> HashMap<K, V> {
+        self.entries
+    }
+}
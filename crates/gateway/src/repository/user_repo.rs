@@ -1,7 +1,7 @@
 pub mod in_memory_repo;
 
 use trust0_common::error::AppError;
-use trust0_common::model::user::User;
+use trust0_common::model::user::{Status, User};
 
 /// User data repository trait
 pub trait UserRepository: Sync + Send {
@@ -23,6 +23,11 @@ pub trait UserRepository: Sync + Send {
     /// Returns a copy of the list of users on success, otherwise it returns an error.
     fn get_all(&self) -> Result<Vec<User>, AppError>;
 
+    /// Returns the list of users with the given status.
+    ///
+    /// Returns a copy of the matching users on success, otherwise it returns an error.
+    fn get_by_status(&self, status: Status) -> Result<Vec<User>, AppError>;
+
     /// Deletes a user.
     ///
     /// Returns previous user or None on success, otherwise it returns an error.
@@ -46,6 +51,7 @@ pub mod tests {
             fn put(&self, user: User) -> Result<Option<User>, AppError>;
             fn get(&self, user_id: u64) -> Result<Option<User>, AppError>;
             fn get_all(&self) -> Result<Vec<User>, AppError>;
+            fn get_by_status(&self, status: Status) -> Result<Vec<User>, AppError>;
             fn delete(&self, user_id: u64) -> Result<Option<User>, AppError>;
         }
     }
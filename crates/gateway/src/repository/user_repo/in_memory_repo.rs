@@ -2,9 +2,12 @@ use std::collections::HashMap;
 use std::fs;
 use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
+use crate::repository::json_file::parse_json_datasource;
+#[cfg(any(test, feature = "test-util"))]
+use crate::repository::snapshot::RepoSnapshot;
 use crate::repository::user_repo::UserRepository;
 use trust0_common::error::AppError;
-use trust0_common::model::user::User;
+use trust0_common::model::user::{Status, User};
 
 pub struct InMemUserRepo {
     users: RwLock<HashMap<u64, User>>,
@@ -31,6 +34,26 @@ impl InMemUserRepo {
     }
 }
 
+#[cfg(any(test, feature = "test-util"))]
+impl InMemUserRepo {
+    /// Capture all entries (under the lock)
+    pub fn snapshot(&self) -> Result<RepoSnapshot<u64, User>, AppError> {
+        Ok(RepoSnapshot::new(self.access_data_for_read()?.clone()))
+    }
+
+    /// Replace all entries (under the lock) with those of the given snapshot
+    pub fn restore(&self, snapshot: RepoSnapshot<u64, User>) -> Result<(), AppError> {
+        *self.access_data_for_write()? = snapshot.into_entries();
+        Ok(())
+    }
+}
+
+impl Default for InMemUserRepo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl UserRepository for InMemUserRepo {
     fn connect_to_datasource(&mut self, connect_spec: &str) -> Result<(), AppError> {
         let data = fs::read_to_string(connect_spec).map_err(|err| {
@@ -39,12 +62,7 @@ impl UserRepository for InMemUserRepo {
                 Box::new(err),
             )
         })?;
-        let users: Vec<User> = serde_json::from_str(&data).map_err(|err| {
-            AppError::GenWithMsgAndErr(
-                format!("Failed to parse JSON: path={}", connect_spec),
-                Box::new(err),
-            )
-        })?;
+        let users: Vec<User> = parse_json_datasource(connect_spec, &data)?;
 
         for user in users.iter().as_ref() {
             self.put(user.clone())?;
@@ -72,6 +90,15 @@ impl UserRepository for InMemUserRepo {
             .collect::<Vec<User>>())
     }
 
+    fn get_by_status(&self, status: Status) -> Result<Vec<User>, AppError> {
+        let data = self.access_data_for_read()?;
+        Ok(data
+            .values()
+            .filter(|user| user.status == status)
+            .cloned()
+            .collect::<Vec<User>>())
+    }
+
     fn delete(&self, user_id: u64) -> Result<Option<User>, AppError> {
         let mut data = self.access_data_for_write()?;
         Ok(data.remove(&user_id))
@@ -82,12 +109,14 @@ impl UserRepository for InMemUserRepo {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::repository::json_file::tests::INVALID_SYNTAX_DB_FILE_PATHPARTS;
     use crate::repository::user_repo::in_memory_repo::InMemUserRepo;
     use std::path::PathBuf;
-    use trust0_common::model::user::{Status, User};
 
     const VALID_USER_DB_FILE_PATHPARTS: [&str; 3] =
         [env!("CARGO_MANIFEST_DIR"), "testdata", "db-user.json"];
+    const VALID_USER_DB_JSONC_FILE_PATHPARTS: [&str; 3] =
+        [env!("CARGO_MANIFEST_DIR"), "testdata", "db-user-jsonc.json"];
     const INVALID_USER_DB_FILE_PATHPARTS: [&str; 3] = [
         env!("CARGO_MANIFEST_DIR"),
         "testdata",
@@ -106,6 +135,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn inmemuserrepo_connect_to_datasource_when_invalid_json_syntax() {
+        let invalid_db_path: PathBuf = INVALID_SYNTAX_DB_FILE_PATHPARTS.iter().collect();
+        let invalid_db_pathstr = invalid_db_path.to_str().unwrap();
+
+        let mut user_repo = InMemUserRepo::new();
+
+        match user_repo.connect_to_datasource(invalid_db_pathstr) {
+            Err(err) => assert!(err
+                .to_string()
+                .contains(&format!("path={}, line=4, column=5", invalid_db_pathstr))),
+            Ok(()) => panic!("Unexpected result: file={}", invalid_db_pathstr),
+        }
+    }
+
     #[test]
     fn inmemuserrepo_connect_to_datasource_when_valid_filepath() {
         let valid_user_db_path: PathBuf = VALID_USER_DB_FILE_PATHPARTS.iter().collect();
@@ -127,6 +171,7 @@ mod tests {
                     user_id: 100,
                     name: "User100".to_string(),
                     status: Status::Active,
+                    byte_quota: None,
                 },
             ),
             (
@@ -135,6 +180,7 @@ mod tests {
                     user_id: 101,
                     name: "User101".to_string(),
                     status: Status::Active,
+                    byte_quota: None,
                 },
             ),
         ]);
@@ -159,6 +205,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn inmemuserrepo_connect_to_datasource_when_valid_jsonc_filepath() {
+        let valid_user_db_path: PathBuf = VALID_USER_DB_FILE_PATHPARTS.iter().collect();
+        let valid_user_db_jsonc_path: PathBuf = VALID_USER_DB_JSONC_FILE_PATHPARTS.iter().collect();
+
+        let mut user_repo = InMemUserRepo::new();
+        let mut user_jsonc_repo = InMemUserRepo::new();
+
+        if let Err(err) = user_repo.connect_to_datasource(valid_user_db_path.to_str().unwrap()) {
+            panic!("Unexpected strict JSON result: err={:?}", &err);
+        }
+        if let Err(err) =
+            user_jsonc_repo.connect_to_datasource(valid_user_db_jsonc_path.to_str().unwrap())
+        {
+            panic!("Unexpected JSONC result: err={:?}", &err);
+        }
+
+        let expected_user_db_map: HashMap<u64, User> = user_repo.users.into_inner().unwrap();
+        let actual_user_db_map: HashMap<u64, User> = user_jsonc_repo.users.into_inner().unwrap();
+
+        assert!(!actual_user_db_map.is_empty());
+        assert_eq!(actual_user_db_map, expected_user_db_map);
+    }
+
     #[test]
     fn inmemuserrepo_put() {
         let user_repo = InMemUserRepo::new();
@@ -167,6 +237,7 @@ mod tests {
             user_id: 1,
             name: "user1".to_string(),
             status: Status::Active,
+            byte_quota: None,
         };
 
         if let Err(err) = user_repo.put(user.clone()) {
@@ -188,6 +259,7 @@ mod tests {
             user_id: 1,
             name: "user1".to_string(),
             status: Status::Active,
+            byte_quota: None,
         };
 
         user_repo.users.write().unwrap().insert(user_key, user);
@@ -210,16 +282,19 @@ mod tests {
                 user_id: 1,
                 name: "user1".to_string(),
                 status: Status::Active,
+                byte_quota: None,
             },
             User {
                 user_id: 2,
                 name: "user2".to_string(),
                 status: Status::Active,
+                byte_quota: None,
             },
             User {
                 user_id: 3,
                 name: "user3".to_string(),
                 status: Status::Inactive,
+                byte_quota: None,
             },
         ];
 
@@ -260,16 +335,19 @@ mod tests {
                 user_id: 1,
                 name: "user1".to_string(),
                 status: Status::Active,
+                byte_quota: None,
             },
             User {
                 user_id: 2,
                 name: "user2".to_string(),
                 status: Status::Active,
+                byte_quota: None,
             },
             User {
                 user_id: 3,
                 name: "user3".to_string(),
                 status: Status::Inactive,
+                byte_quota: None,
             },
         ];
 
@@ -305,6 +383,7 @@ mod tests {
                     user_id: 1,
                     name: "user1".to_string(),
                     status: Status::Active,
+                    byte_quota: None,
                 },
             ),
             (
@@ -313,6 +392,7 @@ mod tests {
                     user_id: 2,
                     name: "user2".to_string(),
                     status: Status::Active,
+                    byte_quota: None,
                 },
             ),
             (
@@ -321,6 +401,7 @@ mod tests {
                     user_id: 3,
                     name: "user3".to_string(),
                     status: Status::Inactive,
+                    byte_quota: None,
                 },
             ),
         ]);
@@ -334,6 +415,68 @@ mod tests {
         );
     }
 
+    fn create_user_repo_with_mixed_statuses() -> InMemUserRepo {
+        let user_repo = InMemUserRepo::new();
+        for (user_id, status) in [
+            (1, Status::Active),
+            (2, Status::Inactive),
+            (3, Status::Active),
+            (4, Status::Inactive),
+            (5, Status::Active),
+        ] {
+            user_repo.users.write().unwrap().insert(
+                user_id,
+                User {
+                    user_id,
+                    name: format!("user{}", user_id),
+                    status,
+                    byte_quota: None,
+                },
+            );
+        }
+        user_repo
+    }
+
+    #[test]
+    fn inmemuserrepo_get_by_status_when_active() {
+        let user_repo = create_user_repo_with_mixed_statuses();
+
+        let result = user_repo.get_by_status(Status::Active);
+
+        if let Err(err) = &result {
+            panic!("Unexpected result: err={:?}", &err)
+        }
+
+        let mut user_ids: Vec<u64> = result
+            .unwrap()
+            .iter()
+            .inspect(|user| assert_eq!(user.status, Status::Active))
+            .map(|user| user.user_id)
+            .collect();
+        user_ids.sort();
+        assert_eq!(user_ids, vec![1, 3, 5]);
+    }
+
+    #[test]
+    fn inmemuserrepo_get_by_status_when_inactive() {
+        let user_repo = create_user_repo_with_mixed_statuses();
+
+        let result = user_repo.get_by_status(Status::Inactive);
+
+        if let Err(err) = &result {
+            panic!("Unexpected result: err={:?}", &err)
+        }
+
+        let mut user_ids: Vec<u64> = result
+            .unwrap()
+            .iter()
+            .inspect(|user| assert_eq!(user.status, Status::Inactive))
+            .map(|user| user.user_id)
+            .collect();
+        user_ids.sort();
+        assert_eq!(user_ids, vec![2, 4]);
+    }
+
     #[test]
     fn inmemuserrepo_delete_when_invalid_user() {
         let user_repo = InMemUserRepo::new();
@@ -342,6 +485,7 @@ mod tests {
             user_id: 1,
             name: "user1".to_string(),
             status: Status::Active,
+            byte_quota: None,
         };
 
         user_repo.users.write().unwrap().insert(user_key, user);
@@ -363,6 +507,7 @@ mod tests {
             user_id: 1,
             name: "user1".to_string(),
             status: Status::Active,
+            byte_quota: None,
         };
 
         user_repo
@@ -382,4 +527,36 @@ mod tests {
         assert!(actual_prev_user.is_some());
         assert_eq!(actual_prev_user.unwrap(), user);
     }
+
+    #[test]
+    fn inmemuserrepo_snapshot_and_restore() {
+        let valid_user_db_path: PathBuf = VALID_USER_DB_FILE_PATHPARTS.iter().collect();
+        let valid_user_db_pathstr = valid_user_db_path.to_str().unwrap();
+
+        let mut user_repo = InMemUserRepo::new();
+        user_repo
+            .connect_to_datasource(valid_user_db_pathstr)
+            .unwrap();
+
+        let snapshot = user_repo.snapshot().unwrap();
+        let original_users = user_repo.users.read().unwrap().clone();
+        assert_eq!(snapshot.len(), original_users.len());
+
+        user_repo
+            .put(User::new(199, "user199", Status::Inactive))
+            .unwrap();
+        user_repo.delete(100).unwrap();
+        assert_ne!(*user_repo.users.read().unwrap(), original_users);
+
+        user_repo.restore(snapshot.clone()).unwrap();
+        assert_eq!(*user_repo.users.read().unwrap(), original_users);
+
+        user_repo
+            .restore(RepoSnapshot::new(HashMap::new()))
+            .unwrap();
+        assert!(user_repo.snapshot().unwrap().is_empty());
+
+        user_repo.restore(snapshot).unwrap();
+        assert_eq!(*user_repo.users.read().unwrap(), original_users);
+    }
 }
@@ -0,0 +1,172 @@
+use std::fmt::{self, Display, Formatter};
+use std::sync::{Arc, Mutex};
+
+use crate::repository::access_repo::AccessRepository;
+use crate::repository::diff::DatasourceSnapshot;
+use crate::repository::service_repo::ServiceRepository;
+use crate::repository::user_repo::UserRepository;
+use trust0_common::error::AppError;
+use trust0_common::logging::warn;
+use trust0_common::target;
+
+/// Access entry, which references a non-existent user and/or service
+#[derive(Clone, PartialEq, Debug)]
+pub struct DanglingAccess {
+    pub user_id: u64,
+    pub service_id: u64,
+    pub missing_user: bool,
+    pub missing_service: bool,
+}
+
+impl Display for DanglingAccess {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut missing_refs = vec![];
+        if self.missing_user {
+            missing_refs.push("unknown user");
+        }
+        if self.missing_service {
+            missing_refs.push("unknown service");
+        }
+
+        write!(
+            f,
+            "Dangling access reference: uid={}, svc_id={}, err={}",
+            self.user_id,
+            self.service_id,
+            missing_refs.join(", ")
+        )
+    }
+}
+
+/// Report access entries referencing non-existent users or services (sorted by access key)
+pub fn find_dangling_access(snapshot: &DatasourceSnapshot) -> Vec<DanglingAccess> {
+    let mut dangling_access: Vec<DanglingAccess> = snapshot
+        .access
+        .keys()
+        .filter_map(|(user_id, service_id)| {
+            let missing_user = !snapshot.users.contains_key(user_id);
+            let missing_service = !snapshot.services.contains_key(service_id);
+
+            if missing_user || missing_service {
+                Some(DanglingAccess {
+                    user_id: *user_id,
+                    service_id: *service_id,
+                    missing_user,
+                    missing_service,
+                })
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    dangling_access.sort_by_key(|access| (access.user_id, access.service_id));
+    dangling_access
+}
+
+/// Cross-reference access entries against the user and service repositories. Each dangling reference is
+/// logged (as a warning) and returned.
+pub fn validate_access_references(
+    access_repo: &Arc<Mutex<dyn AccessRepository>>,
+    service_repo: &Arc<Mutex<dyn ServiceRepository>>,
+    user_repo: &Arc<Mutex<dyn UserRepository>>,
+) -> Result<Vec<DanglingAccess>, AppError> {
+    let dangling_access = find_dangling_access(&DatasourceSnapshot::from_repositories(
+        access_repo,
+        service_repo,
+        user_repo,
+    )?);
+
+    for access in &dangling_access {
+        warn(&target!(), &access.to_string());
+    }
+
+    Ok(dangling_access)
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::collections::HashMap;
+    use trust0_common::model::access::ServiceAccess;
+    use trust0_common::model::service::{Service, Transport};
+    use trust0_common::model::user::{Status, User};
+
+    fn create_snapshot(access_keys: &[(u64, u64)]) -> DatasourceSnapshot {
+        DatasourceSnapshot {
+            users: HashMap::from([(100, User::new(100, "user100", Status::Active))]),
+            services: HashMap::from([(
+                200,
+                Service::new(200, "Service200", &Transport::TCP, "localhost", 8200),
+            )]),
+            access: access_keys
+                .iter()
+                .map(|(user_id, service_id)| {
+                    (
+                        (*user_id, *service_id),
+                        ServiceAccess {
+                            user_id: *user_id,
+                            service_id: *service_id,
+                            justification: None,
+                            deny: false,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn validation_find_dangling_access_when_all_references_valid() {
+        assert!(find_dangling_access(&create_snapshot(&[(100, 200)])).is_empty());
+    }
+
+    #[test]
+    fn validation_find_dangling_access_when_dangling_user() {
+        let dangling_access = find_dangling_access(&create_snapshot(&[(100, 200), (101, 200)]));
+
+        assert_eq!(
+            dangling_access,
+            vec![DanglingAccess {
+                user_id: 101,
+                service_id: 200,
+                missing_user: true,
+                missing_service: false,
+            }]
+        );
+        assert_eq!(
+            dangling_access[0].to_string(),
+            "Dangling access reference: uid=101, svc_id=200, err=unknown user"
+        );
+    }
+
+    #[test]
+    fn validation_find_dangling_access_when_dangling_service() {
+        let dangling_access =
+            find_dangling_access(&create_snapshot(&[(101, 201), (100, 201), (100, 200)]));
+
+        assert_eq!(
+            dangling_access,
+            vec![
+                DanglingAccess {
+                    user_id: 100,
+                    service_id: 201,
+                    missing_user: false,
+                    missing_service: true,
+                },
+                DanglingAccess {
+                    user_id: 101,
+                    service_id: 201,
+                    missing_user: true,
+                    missing_service: true,
+                },
+            ]
+        );
+        assert_eq!(
+            dangling_access[1].to_string(),
+            "Dangling access reference: uid=101, svc_id=201, err=unknown user, unknown service"
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use serde_derive::Serialize;
+
+use crate::config::AppConfig;
+use crate::health::HealthCheck;
+use trust0_common::error::AppError;
+use trust0_common::logging::{error, info};
+use trust0_common::target;
+
+/// Startup self-test report (crypto and datasource configuration)
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub checks: Vec<HealthCheck>,
+}
+
+impl SelfTestReport {
+    /// Run self-test checks against given configuration: the TLS server configuration can be built, the auth root
+    /// store is non-empty and each datasource repository loads (users and services must be non-empty)
+    pub fn run(app_config: &AppConfig) -> Self {
+        let tls_config = &app_config.tls_server_config_builder;
+
+        let mut checks = vec![
+            match tls_config.build() {
+                Ok(_) => HealthCheck::new("tls_config", true, None),
+                Err(err) => HealthCheck::new("tls_config", false, Some(format!("{:?}", err))),
+            },
+            HealthCheck::new(
+                "auth_roots",
+                !tls_config.auth_root_certs.is_empty(),
+                Some(format!("count={}", tls_config.auth_root_certs.len())),
+            ),
+        ];
+
+        checks.push(Self::check_repo_count(
+            "users",
+            true,
+            app_config
+                .user_repo
+                .lock()
+                .unwrap()
+                .get_all()
+                .map(|users| users.len()),
+        ));
+        checks.push(Self::check_repo_count(
+            "services",
+            true,
+            app_config
+                .service_repo
+                .lock()
+                .unwrap()
+                .get_all()
+                .map(|services| services.len()),
+        ));
+        checks.push(Self::check_repo_count(
+            "accesses",
+            false,
+            app_config
+                .access_repo
+                .lock()
+                .unwrap()
+                .get_all()
+                .map(|accesses| accesses.len()),
+        ));
+
+        Self {
+            passed: checks.iter().all(|check| check.passed),
+            checks,
+        }
+    }
+
+    /// Log a concise PASS/FAIL summary. Returns an error if any check failed
+    pub fn log_summary(&self) -> Result<(), AppError> {
+        let summary = self
+            .checks
+            .iter()
+            .map(|check| {
+                format!(
+                    "{}={}{}",
+                    &check.name,
+                    if check.passed { "PASS" } else { "FAIL" },
+                    check
+                        .detail
+                        .as_ref()
+                        .map_or(String::new(), |detail| format!("({})", detail))
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        if self.passed {
+            info(&target!(), &format!("Self-test PASS: {}", &summary));
+            Ok(())
+        } else {
+            error(&target!(), &format!("Self-test FAIL: {}", &summary));
+            Err(AppError::General(format!(
+                "Startup self-test failed: {}",
+                &summary
+            )))
+        }
+    }
+
+    /// Create check for datasource repository load (and count)
+    fn check_repo_count(
+        name: &str,
+        require_entries: bool,
+        count_result: Result<usize, AppError>,
+    ) -> HealthCheck {
+        match count_result {
+            Ok(count) => HealthCheck::new(
+                name,
+                !require_entries || (count > 0),
+                Some(format!("count={}", count)),
+            ),
+            Err(err) => HealthCheck::new(name, false, Some(format!("{:?}", err))),
+        }
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::config;
+    use crate::config::tests::CERTFILE_GATEWAY_PATHPARTS;
+    use crate::repository::access_repo::tests::MockAccessRepo;
+    use crate::repository::service_repo::tests::MockServiceRepo;
+    use crate::repository::user_repo::tests::MockUserRepo;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use trust0_common::crypto::file::load_certificates;
+    use trust0_common::model::access::ServiceAccess;
+    use trust0_common::model::service::{Service, Transport};
+    use trust0_common::model::user::{Status, User};
+
+    // utils
+    // =====
+
+    fn create_app_config(auth_root_loaded: bool) -> AppConfig {
+        let mut user_repo = MockUserRepo::new();
+        user_repo.expect_get_all().return_once(|| {
+            Ok(vec![User {
+                user_id: 100,
+                name: "user100".to_string(),
+                status: Status::Active,
+                ..Default::default()
+            }])
+        });
+        let mut service_repo = MockServiceRepo::new();
+        service_repo.expect_get_all().return_once(|| {
+            Ok(vec![Service {
+                service_id: 200,
+                name: "Service200".to_string(),
+                transport: Transport::TCP,
+                host: "localhost".to_string(),
+                port: 8200,
+                ..Default::default()
+            }])
+        });
+        let mut access_repo = MockAccessRepo::new();
+        access_repo.expect_get_all().return_once(|| {
+            Ok(vec![ServiceAccess {
+                user_id: 100,
+                service_id: 200,
+                ..Default::default()
+            }])
+        });
+
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(user_repo)),
+            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(access_repo)),
+        )
+        .unwrap();
+        if auth_root_loaded {
+            let auth_cert_file: PathBuf = CERTFILE_GATEWAY_PATHPARTS.iter().collect();
+            for auth_cert in
+                load_certificates(auth_cert_file.to_str().unwrap().to_string()).unwrap()
+            {
+                app_config
+                    .tls_server_config_builder
+                    .auth_root_certs
+                    .add(auth_cert)
+                    .unwrap();
+            }
+        }
+        app_config
+    }
+
+    fn find_check<'a>(checks: &'a [HealthCheck], name: &str) -> &'a HealthCheck {
+        checks.iter().find(|check| check.name == name).unwrap()
+    }
+
+    // tests
+    // =====
+
+    #[test]
+    fn selftest_run_when_all_pass() {
+        let app_config = create_app_config(true);
+
+        let report = SelfTestReport::run(&app_config);
+
+        assert!(report.passed);
+        assert_eq!(report.checks.len(), 5);
+        assert!(report.checks.iter().all(|check| check.passed));
+        assert_eq!(
+            find_check(&report.checks, "services").detail,
+            Some("count=1".to_string())
+        );
+        assert!(report.log_summary().is_ok());
+    }
+
+    #[test]
+    fn selftest_run_when_empty_auth_root_store() {
+        let app_config = create_app_config(false);
+
+        let report = SelfTestReport::run(&app_config);
+
+        assert!(!report.passed);
+        assert!(!find_check(&report.checks, "tls_config").passed);
+        assert!(!find_check(&report.checks, "auth_roots").passed);
+        assert!(find_check(&report.checks, "users").passed);
+        assert!(find_check(&report.checks, "services").passed);
+        assert!(find_check(&report.checks, "accesses").passed);
+        assert!(report.log_summary().is_err());
+    }
+}
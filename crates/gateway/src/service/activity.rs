@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use trust0_common::metrics::MetricsSink;
+use trust0_common::proxy::proxy_key::ProxyKey;
+
+/// Tracks the most recent proxy activity (connection accepted, data relayed) per service proxy connection (proxy key)
+pub struct ServiceActivity {
+    last_activity_by_proxy_key: Mutex<HashMap<ProxyKey, Instant>>,
+}
+
+impl ServiceActivity {
+    /// ServiceActivity constructor
+    pub fn new() -> Self {
+        Self {
+            last_activity_by_proxy_key: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record activity (as of `now`) for given proxy connection. Activity never moves backwards.
+    pub fn record_activity(&self, proxy_key: &ProxyKey, now: Instant) {
+        let mut last_activity_by_proxy_key = self.last_activity_by_proxy_key.lock().unwrap();
+        let last_activity = last_activity_by_proxy_key
+            .entry(proxy_key.clone())
+            .or_insert(now);
+        if now > *last_activity {
+            *last_activity = now;
+        }
+    }
+
+    /// Most recent activity for given proxy connection, not earlier than `since` (for instance, the connection start time)
+    pub fn get_last_activity(&self, proxy_key: &ProxyKey, since: Instant) -> Instant {
+        match self
+            .last_activity_by_proxy_key
+            .lock()
+            .unwrap()
+            .get(proxy_key)
+        {
+            Some(last_activity) if *last_activity > since => *last_activity,
+            _ => since,
+        }
+    }
+
+    /// Discard activity for given proxy connection
+    pub fn remove_connection(&self, proxy_key: &ProxyKey) {
+        self.last_activity_by_proxy_key
+            .lock()
+            .unwrap()
+            .remove(proxy_key);
+    }
+
+    /// Discard activity for all of given service's proxy connections
+    pub fn remove_service(&self, service_id: u64) {
+        self.last_activity_by_proxy_key
+            .lock()
+            .unwrap()
+            .retain(|proxy_key, _| proxy_key.get_service_id() != service_id);
+    }
+}
+
+impl Default for ServiceActivity {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Metrics sink, which records proxied bytes as service activity and forwards all metrics to an underlying sink
+pub struct ActivityMetricsSink {
+    metrics_sink: Arc<dyn MetricsSink>,
+    service_activity: Arc<ServiceActivity>,
+}
+
+impl ActivityMetricsSink {
+    /// ActivityMetricsSink constructor
+    pub fn new(metrics_sink: Arc<dyn MetricsSink>, service_activity: Arc<ServiceActivity>) -> Self {
+        Self {
+            metrics_sink,
+            service_activity,
+        }
+    }
+}
+
+impl MetricsSink for ActivityMetricsSink {
+    fn incr_counter(&self, name: &str, value: u64) {
+        self.metrics_sink.incr_counter(name, value);
+    }
+
+    fn set_gauge(&self, name: &str, value: i64) {
+        self.metrics_sink.set_gauge(name, value);
+    }
+
+    fn incr_proxy_bytes(&self, proxy_key: &ProxyKey, value: u64) {
+        self.service_activity
+            .record_activity(proxy_key, Instant::now());
+        self.metrics_sink.incr_proxy_bytes(proxy_key, value);
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use trust0_common::metrics::NoOpMetricsSink;
+    use trust0_common::proxy::proxy_base::ProxyType;
+
+    fn create_proxy_key(service_id: u64, client_port: u16) -> ProxyKey {
+        ProxyKey::new(
+            ProxyType::TcpAndTcp,
+            service_id,
+            Some(SocketAddr::from(([127, 0, 0, 1], client_port))),
+            None,
+        )
+    }
+
+    #[test]
+    fn svcactivity_get_last_activity_when_idle() {
+        let service_activity = ServiceActivity::new();
+        let proxy_key = create_proxy_key(200, 5000);
+        let started_at = Instant::now();
+
+        assert_eq!(
+            service_activity.get_last_activity(&proxy_key, started_at),
+            started_at
+        );
+        assert_eq!(
+            service_activity.get_last_activity(&proxy_key, started_at),
+            started_at
+        );
+    }
+
+    #[test]
+    fn svcactivity_record_activity_advances_last_activity() {
+        let service_activity = ServiceActivity::new();
+        let proxy_key1 = create_proxy_key(200, 5000);
+        let proxy_key2 = create_proxy_key(200, 5001);
+        let started_at = Instant::now();
+
+        service_activity.record_activity(&proxy_key1, started_at + Duration::from_secs(5));
+        assert_eq!(
+            service_activity.get_last_activity(&proxy_key1, started_at),
+            started_at + Duration::from_secs(5)
+        );
+
+        service_activity.record_activity(&proxy_key1, started_at + Duration::from_secs(2));
+        assert_eq!(
+            service_activity.get_last_activity(&proxy_key1, started_at),
+            started_at + Duration::from_secs(5)
+        );
+        assert_eq!(
+            service_activity.get_last_activity(&proxy_key2, started_at),
+            started_at
+        );
+
+        service_activity.remove_connection(&proxy_key1);
+        assert_eq!(
+            service_activity.get_last_activity(&proxy_key1, started_at),
+            started_at
+        );
+    }
+
+    #[test]
+    fn svcactivity_remove_service_discards_service_connections() {
+        let service_activity = ServiceActivity::new();
+        let proxy_key1 = create_proxy_key(200, 5000);
+        let proxy_key2 = create_proxy_key(201, 5001);
+        let started_at = Instant::now();
+        let last_activity = started_at + Duration::from_secs(5);
+
+        service_activity.record_activity(&proxy_key1, last_activity);
+        service_activity.record_activity(&proxy_key2, last_activity);
+        service_activity.remove_service(200);
+
+        assert_eq!(
+            service_activity.get_last_activity(&proxy_key1, started_at),
+            started_at
+        );
+        assert_eq!(
+            service_activity.get_last_activity(&proxy_key2, started_at),
+            last_activity
+        );
+    }
+
+    #[test]
+    fn svcactivity_get_last_activity_when_activity_predates_since() {
+        let service_activity = ServiceActivity::new();
+        let proxy_key = create_proxy_key(200, 5000);
+        let prior_activity = Instant::now();
+        service_activity.record_activity(&proxy_key, prior_activity);
+
+        let started_at = prior_activity + Duration::from_secs(10);
+
+        assert_eq!(
+            service_activity.get_last_activity(&proxy_key, started_at),
+            started_at
+        );
+    }
+
+    #[test]
+    fn activitysink_incr_proxy_bytes_records_activity() {
+        let service_activity = Arc::new(ServiceActivity::new());
+        let metrics_sink =
+            ActivityMetricsSink::new(Arc::new(NoOpMetricsSink), service_activity.clone());
+        let started_at = Instant::now();
+        let proxy_key = create_proxy_key(200, 5000);
+
+        metrics_sink.incr_counter("connections", 1);
+        assert_eq!(
+            service_activity.get_last_activity(&proxy_key, started_at),
+            started_at
+        );
+
+        std::thread::sleep(Duration::from_millis(5));
+        metrics_sink.incr_proxy_bytes(&proxy_key, 100);
+
+        assert!(service_activity.get_last_activity(&proxy_key, started_at) > started_at);
+        assert_eq!(
+            service_activity.get_last_activity(&create_proxy_key(200, 5001), started_at),
+            started_at
+        );
+    }
+}
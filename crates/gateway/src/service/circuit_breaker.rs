@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_derive::Serialize;
+
+use crate::config;
+use trust0_common::error::AppError;
+use trust0_common::logging::warn;
+use trust0_common::target;
+
+/// Upstream dial circuit state
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CircuitState {
+    /// Dials are attempted
+    Closed,
+    /// Dials are short-circuited (until cooldown has elapsed)
+    Open,
+    /// Cooldown has elapsed, a single trial dial is permitted to test recovery
+    HalfOpen,
+}
+
+/// Dial failure tracking for a single `(service_id, upstream)`
+#[derive(Default)]
+struct Circuit {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    trial_in_progress: bool,
+}
+
+/// Circuit breaker for upstream dials. After `failure_threshold` consecutive connect failures to an upstream
+/// (of a service), new dials are short-circuited for the `cooldown` period. Afterwards, the circuit half-opens,
+/// letting a single dial through: success closes the circuit, failure re-opens it. A zero failure threshold
+/// disables the breaker.
+#[derive(Serialize)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    #[serde(serialize_with = "config::config_dump::duration_secs")]
+    cooldown: Duration,
+    #[serde(skip)]
+    circuits: Mutex<HashMap<(u64, SocketAddr), Circuit>>,
+}
+
+impl CircuitBreaker {
+    /// CircuitBreaker constructor
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Perform upstream dial (via given function), unless the upstream's circuit is open, in which case
+    /// an inactive service proxy (425) error is returned immediately
+    pub fn dial<T>(
+        &self,
+        service_id: u64,
+        upstream: &SocketAddr,
+        dial_fn: impl FnOnce() -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        if self.failure_threshold == 0 {
+            return dial_fn();
+        }
+
+        if !self.allow_dial(service_id, upstream, Instant::now()) {
+            return Err(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0425_INACTIVE_SERVICE_PROXY,
+                format!(
+                    "Upstream circuit open: svc_id={}, upstream={}",
+                    service_id, upstream
+                ),
+            ));
+        }
+
+        let result = dial_fn();
+        match &result {
+            Ok(_) => self.record_success(service_id, upstream),
+            Err(_) => self.record_failure(service_id, upstream, Instant::now()),
+        }
+        result
+    }
+
+    /// Current circuit state for given upstream (as of `now`)
+    pub fn get_state(&self, service_id: u64, upstream: &SocketAddr, now: Instant) -> CircuitState {
+        match self.circuits.lock().unwrap().get(&(service_id, *upstream)) {
+            Some(circuit) => self.circuit_state(circuit, now),
+            None => CircuitState::Closed,
+        }
+    }
+
+    /// Returns whether a dial may be attempted (as of `now`). In the half-open state, only the first
+    /// caller is permitted (as the trial dial).
+    fn allow_dial(&self, service_id: u64, upstream: &SocketAddr, now: Instant) -> bool {
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = match circuits.get_mut(&(service_id, *upstream)) {
+            Some(circuit) => circuit,
+            None => return true,
+        };
+
+        match self.circuit_state(circuit, now) {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen => !std::mem::replace(&mut circuit.trial_in_progress, true),
+        }
+    }
+
+    /// Successful dial, close circuit
+    fn record_success(&self, service_id: u64, upstream: &SocketAddr) {
+        self.circuits
+            .lock()
+            .unwrap()
+            .remove(&(service_id, *upstream));
+    }
+
+    /// Failed dial (as of `now`), opening (or re-opening) circuit when warranted
+    fn record_failure(&self, service_id: u64, upstream: &SocketAddr, now: Instant) {
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry((service_id, *upstream)).or_default();
+
+        circuit.consecutive_failures = circuit.consecutive_failures.saturating_add(1);
+
+        if circuit.trial_in_progress || (circuit.consecutive_failures >= self.failure_threshold) {
+            circuit.opened_at = Some(now);
+            circuit.trial_in_progress = false;
+
+            warn(
+                &target!(),
+                &format!(
+                    "Upstream circuit opened: svc_id={}, upstream={}, failures={}",
+                    service_id, upstream, circuit.consecutive_failures
+                ),
+            );
+        }
+    }
+
+    /// Derive state for given circuit (as of `now`)
+    fn circuit_state(&self, circuit: &Circuit, now: Instant) -> CircuitState {
+        match circuit.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if now.saturating_duration_since(opened_at) < self.cooldown => {
+                CircuitState::Open
+            }
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // utils
+    // =====
+
+    fn upstream() -> SocketAddr {
+        "10.0.0.1:8200".parse().unwrap()
+    }
+
+    fn failed_dial() -> Result<(), AppError> {
+        Err(AppError::General("connection refused".to_string()))
+    }
+
+    // tests
+    // =====
+
+    #[test]
+    fn circuitbrkr_dial_when_disabled() {
+        let circuit_breaker = CircuitBreaker::new(0, Duration::from_secs(30));
+
+        for _ in 0..5 {
+            assert!(circuit_breaker.dial(200, &upstream(), failed_dial).is_err());
+        }
+
+        assert!(circuit_breaker.dial(200, &upstream(), || Ok(())).is_ok());
+        assert!(circuit_breaker.circuits.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn circuitbrkr_dial_when_threshold_reached() {
+        let circuit_breaker = CircuitBreaker::new(3, Duration::from_secs(30));
+        let now = Instant::now();
+
+        for _ in 0..2 {
+            assert!(circuit_breaker.dial(200, &upstream(), failed_dial).is_err());
+        }
+        assert_eq!(
+            circuit_breaker.get_state(200, &upstream(), now),
+            CircuitState::Closed
+        );
+
+        assert!(circuit_breaker.dial(200, &upstream(), failed_dial).is_err());
+        assert_eq!(
+            circuit_breaker.get_state(200, &upstream(), Instant::now()),
+            CircuitState::Open
+        );
+        assert_eq!(
+            circuit_breaker.get_state(201, &upstream(), Instant::now()),
+            CircuitState::Closed
+        );
+
+        match circuit_breaker.dial(200, &upstream(), || -> Result<(), AppError> {
+            panic!("Unexpected dial while circuit open")
+        }) {
+            Err(err) => assert_eq!(
+                err.get_code(),
+                Some(config::RESPCODE_0425_INACTIVE_SERVICE_PROXY)
+            ),
+            Ok(_) => panic!("Unexpected successful dial result"),
+        }
+    }
+
+    #[test]
+    fn circuitbrkr_dial_when_success_resets_failures() {
+        let circuit_breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+
+        assert!(circuit_breaker.dial(200, &upstream(), failed_dial).is_err());
+        assert!(circuit_breaker.dial(200, &upstream(), || Ok(())).is_ok());
+        assert!(circuit_breaker.dial(200, &upstream(), failed_dial).is_err());
+
+        assert_eq!(
+            circuit_breaker.get_state(200, &upstream(), Instant::now()),
+            CircuitState::Closed
+        );
+    }
+
+    #[test]
+    fn circuitbrkr_allow_dial_when_cooldown_elapsed_and_trial_recovers() {
+        let circuit_breaker = CircuitBreaker::new(1, Duration::from_secs(30));
+        let opened_at = Instant::now();
+        circuit_breaker.record_failure(200, &upstream(), opened_at);
+
+        let during_cooldown = opened_at + Duration::from_secs(29);
+        assert_eq!(
+            circuit_breaker.get_state(200, &upstream(), during_cooldown),
+            CircuitState::Open
+        );
+        assert!(!circuit_breaker.allow_dial(200, &upstream(), during_cooldown));
+
+        let after_cooldown = opened_at + Duration::from_secs(30);
+        assert_eq!(
+            circuit_breaker.get_state(200, &upstream(), after_cooldown),
+            CircuitState::HalfOpen
+        );
+        assert!(circuit_breaker.allow_dial(200, &upstream(), after_cooldown));
+        assert!(!circuit_breaker.allow_dial(200, &upstream(), after_cooldown));
+
+        circuit_breaker.record_success(200, &upstream());
+
+        assert_eq!(
+            circuit_breaker.get_state(200, &upstream(), after_cooldown),
+            CircuitState::Closed
+        );
+        assert!(circuit_breaker.allow_dial(200, &upstream(), after_cooldown));
+    }
+
+    #[test]
+    fn circuitbrkr_allow_dial_when_cooldown_elapsed_and_trial_fails() {
+        let circuit_breaker = CircuitBreaker::new(3, Duration::from_secs(30));
+        let opened_at = Instant::now();
+        for _ in 0..3 {
+            circuit_breaker.record_failure(200, &upstream(), opened_at);
+        }
+
+        let after_cooldown = opened_at + Duration::from_secs(30);
+        assert!(circuit_breaker.allow_dial(200, &upstream(), after_cooldown));
+        circuit_breaker.record_failure(200, &upstream(), after_cooldown);
+
+        assert_eq!(
+            circuit_breaker.get_state(200, &upstream(), after_cooldown),
+            CircuitState::Open
+        );
+        assert!(!circuit_breaker.allow_dial(
+            200,
+            &upstream(),
+            after_cooldown + Duration::from_secs(29)
+        ));
+        assert!(circuit_breaker.allow_dial(
+            200,
+            &upstream(),
+            after_cooldown + Duration::from_secs(30)
+        ));
+    }
+}
@@ -0,0 +1,525 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use dnsclient::sync::DNSClient;
+use serde_derive::Serialize;
+
+use crate::config;
+use trust0_common::backoff::{BackoffStrategy, FixedBackoff};
+use trust0_common::error::AppError;
+use trust0_common::logging::warn;
+use trust0_common::model::service::Service;
+use trust0_common::target;
+
+/// Resolves host names to addresses
+pub trait HostResolver: Send + Sync {
+    /// Resolve given host to its address(es)
+    fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, AppError>;
+}
+
+impl HostResolver for DNSClient {
+    fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, AppError> {
+        self.query_addrs(host).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                format!("Failed resolving host: host={}", host),
+                Box::new(err),
+            )
+        })
+    }
+}
+
+/// Cached (last good) resolved address set for a host
+struct CachedAddrs {
+    addrs: Vec<IpAddr>,
+    ttl: Duration,
+    resolved_at: Instant,
+}
+
+impl CachedAddrs {
+    /// Returns whether entry is within its TTL
+    fn is_fresh(&self) -> bool {
+        self.resolved_at.elapsed() < self.ttl
+    }
+}
+
+/// Service upstream address resolution cache. Resolved addresses are reused until their TTL (service TTL, else
+/// the default TTL) expires. When a re-resolution fails, the last good address set continues to be used, for
+/// at most `max_stale` past its expiry. Connect-time lookups are bounded by the connect timeout and retried (per the
+/// connect backoff), whereas background refreshes are not.
+#[derive(Serialize)]
+pub struct ServiceAddrsCache {
+    #[serde(skip)]
+    resolver: Arc<dyn HostResolver>,
+    #[serde(serialize_with = "config::config_dump::duration_secs")]
+    default_ttl: Duration,
+    #[serde(serialize_with = "config::config_dump::duration_secs")]
+    max_stale: Duration,
+    #[serde(serialize_with = "config::config_dump::duration_millis")]
+    connect_timeout: Duration,
+    connect_retries: u32,
+    #[serde(skip)]
+    connect_backoff: Arc<dyn BackoffStrategy>,
+    #[serde(skip)]
+    cached_addrs_by_host: Mutex<HashMap<String, CachedAddrs>>,
+}
+
+impl ServiceAddrsCache {
+    /// ServiceAddrsCache constructor. A zero default TTL disables caching (unless a service sets its own TTL)
+    pub fn new(
+        resolver: Arc<dyn HostResolver>,
+        default_ttl: Duration,
+        max_stale: Duration,
+    ) -> Self {
+        Self {
+            resolver,
+            default_ttl,
+            max_stale,
+            connect_timeout: Duration::ZERO,
+            connect_retries: 0,
+            connect_backoff: Arc::new(FixedBackoff::new(Duration::ZERO)),
+            cached_addrs_by_host: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set connect-time lookup policy. A zero timeout leaves lookups unbounded.
+    pub fn set_connect_lookup_policy(
+        &mut self,
+        timeout: Duration,
+        retries: u32,
+        backoff: Arc<dyn BackoffStrategy>,
+    ) {
+        self.connect_timeout = timeout;
+        self.connect_retries = retries;
+        self.connect_backoff = backoff;
+    }
+
+    /// Resolve service host address(es), using cached addresses while fresh
+    pub fn resolve(&self, service: &Service) -> Result<Vec<IpAddr>, AppError> {
+        let ttl = service
+            .dns_cache_ttl
+            .map(Duration::from_secs)
+            .unwrap_or(self.default_ttl);
+
+        if ttl.is_zero() {
+            return self.resolve_for_connect(&service.host);
+        }
+
+        if let Some(cached_addrs) = self.cached_addrs_by_host.lock().unwrap().get(&service.host) {
+            if cached_addrs.is_fresh() {
+                return Ok(cached_addrs.addrs.clone());
+            }
+        }
+
+        self.refresh_host(&service.host, ttl)
+    }
+
+    /// Re-resolve all expired cache entries (failures retain the prior addresses, within the stale bound).
+    /// Returns number of hosts, which were refreshed successfully.
+    pub fn refresh_expired(&self) -> usize {
+        let expired_hosts: Vec<(String, Duration)> = self
+            .cached_addrs_by_host
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, cached_addrs)| !cached_addrs.is_fresh())
+            .map(|(host, cached_addrs)| (host.clone(), cached_addrs.ttl))
+            .collect();
+
+        expired_hosts
+            .into_iter()
+            .filter(|(host, ttl)| match self.resolver.resolve(host) {
+                Ok(addrs) => {
+                    self.cache_addrs(host, *ttl, addrs);
+                    true
+                }
+                Err(err) => {
+                    warn(
+                        &target!(),
+                        &format!("Background host resolution failed: err={:?}", &err),
+                    );
+                    false
+                }
+            })
+            .count()
+    }
+
+    /// Spawn thread to periodically refresh expired cache entries
+    pub fn spawn_refresher(cache: Arc<Self>, refresh_interval: Duration) -> JoinHandle<()> {
+        thread::spawn(move || loop {
+            thread::sleep(refresh_interval);
+            cache.refresh_expired();
+        })
+    }
+
+    /// Resolve host for a (pending) connection, retrying failed/timed out lookups. Once retries are exhausted,
+    /// an inactive service proxy (425) error is returned.
+    fn resolve_for_connect(&self, host: &str) -> Result<Vec<IpAddr>, AppError> {
+        let mut attempt = 0;
+
+        loop {
+            match self.resolve_with_timeout(host) {
+                Ok(addrs) => return Ok(addrs),
+                Err(err) if attempt < self.connect_retries => {
+                    attempt += 1;
+                    warn(
+                        &target!(),
+                        &format!(
+                            "Retrying host resolution: host={}, attempt={}, err={:?}",
+                            host, attempt, &err
+                        ),
+                    );
+                    thread::sleep(self.connect_backoff.next_delay(attempt));
+                }
+                Err(err) => {
+                    return Err(AppError::GenWithCodeAndMsg(
+                        config::RESPCODE_0425_INACTIVE_SERVICE_PROXY,
+                        format!("Failed resolving host: host={}, err={:?}", host, &err),
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Resolve host, bounded by the connect timeout. A timed out lookup is abandoned (its thread finishes on its own).
+    fn resolve_with_timeout(&self, host: &str) -> Result<Vec<IpAddr>, AppError> {
+        if self.connect_timeout.is_zero() {
+            return self.resolver.resolve(host);
+        }
+
+        let (result_sender, result_receiver) = mpsc::channel();
+        let resolver = self.resolver.clone();
+        let lookup_host = host.to_string();
+        thread::spawn(move || {
+            let _ = result_sender.send(resolver.resolve(&lookup_host));
+        });
+
+        result_receiver
+            .recv_timeout(self.connect_timeout)
+            .unwrap_or_else(|_| {
+                Err(AppError::General(format!(
+                    "Host resolution timed out: host={}, timeout={:?}",
+                    host, self.connect_timeout
+                )))
+            })
+    }
+
+    /// Store (newly) resolved addresses for host
+    fn cache_addrs(&self, host: &str, ttl: Duration, addrs: Vec<IpAddr>) {
+        self.cached_addrs_by_host.lock().unwrap().insert(
+            host.to_string(),
+            CachedAddrs {
+                addrs,
+                ttl,
+                resolved_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Resolve host and update cache. On failure, the last good (not overly stale) addresses are returned.
+    fn refresh_host(&self, host: &str, ttl: Duration) -> Result<Vec<IpAddr>, AppError> {
+        match self.resolve_for_connect(host) {
+            Ok(addrs) => {
+                self.cache_addrs(host, ttl, addrs.clone());
+                Ok(addrs)
+            }
+
+            Err(err) => {
+                let mut cached_addrs_by_host = self.cached_addrs_by_host.lock().unwrap();
+
+                match cached_addrs_by_host.get(host) {
+                    Some(cached_addrs)
+                        if cached_addrs.resolved_at.elapsed()
+                            < cached_addrs.ttl + self.max_stale =>
+                    {
+                        warn(
+                            &target!(),
+                            &format!(
+                                "Using last good resolved addresses: host={}, err={:?}",
+                                host, &err
+                            ),
+                        );
+                        Ok(cached_addrs.addrs.clone())
+                    }
+
+                    _ => {
+                        cached_addrs_by_host.remove(host);
+                        Err(err)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+    use mockall::{mock, predicate};
+    use std::str::FromStr;
+    use trust0_common::model::service::Transport;
+
+    // mocks
+    // =====
+
+    mock! {
+        pub HostResolv {}
+        impl HostResolver for HostResolv {
+            fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, AppError>;
+        }
+    }
+
+    // utils
+    // =====
+
+    fn create_service(dns_cache_ttl: Option<u64>) -> Service {
+        let mut service = Service::new(200, "Service200", &Transport::TCP, "host1", 8200);
+        service.dns_cache_ttl = dns_cache_ttl;
+        service
+    }
+
+    fn create_addrs(addrs: &[&str]) -> Vec<IpAddr> {
+        addrs
+            .iter()
+            .map(|addr| IpAddr::from_str(addr).unwrap())
+            .collect()
+    }
+
+    fn create_resolver(results: Vec<Result<Vec<IpAddr>, AppError>>) -> MockHostResolv {
+        let expected_calls = results.len();
+        let mut results = results;
+        results.reverse();
+
+        let mut resolver = MockHostResolv::new();
+        resolver
+            .expect_resolve()
+            .with(predicate::eq("host1"))
+            .times(expected_calls)
+            .returning(move |_| results.pop().unwrap());
+        resolver
+    }
+
+    // tests
+    // =====
+
+    #[test]
+    fn addrscache_resolve_when_cache_hit() {
+        let resolver = create_resolver(vec![Ok(create_addrs(&["10.0.0.1", "10.0.0.2"]))]);
+        let cache = ServiceAddrsCache::new(
+            Arc::new(resolver),
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+        );
+
+        for _ in 0..3 {
+            assert_eq!(
+                cache.resolve(&create_service(None)).unwrap(),
+                create_addrs(&["10.0.0.1", "10.0.0.2"])
+            );
+        }
+    }
+
+    #[test]
+    fn addrscache_resolve_when_caching_disabled() {
+        let resolver = create_resolver(vec![
+            Ok(create_addrs(&["10.0.0.1"])),
+            Ok(create_addrs(&["10.0.0.2"])),
+        ]);
+        let cache = ServiceAddrsCache::new(Arc::new(resolver), Duration::ZERO, Duration::ZERO);
+
+        assert_eq!(
+            cache.resolve(&create_service(None)).unwrap(),
+            create_addrs(&["10.0.0.1"])
+        );
+        assert_eq!(
+            cache.resolve(&create_service(None)).unwrap(),
+            create_addrs(&["10.0.0.2"])
+        );
+    }
+
+    #[test]
+    fn addrscache_resolve_when_service_ttl_overrides_default() {
+        let resolver = create_resolver(vec![Ok(create_addrs(&["10.0.0.1"]))]);
+        let cache = ServiceAddrsCache::new(Arc::new(resolver), Duration::ZERO, Duration::ZERO);
+
+        for _ in 0..2 {
+            assert_eq!(
+                cache.resolve(&create_service(Some(3600))).unwrap(),
+                create_addrs(&["10.0.0.1"])
+            );
+        }
+    }
+
+    #[test]
+    fn addrscache_resolve_when_ttl_expired() {
+        let resolver = create_resolver(vec![
+            Ok(create_addrs(&["10.0.0.1"])),
+            Ok(create_addrs(&["10.0.0.2"])),
+        ]);
+        let cache = ServiceAddrsCache::new(
+            Arc::new(resolver),
+            Duration::from_millis(50),
+            Duration::from_secs(60),
+        );
+
+        for _ in 0..2 {
+            assert_eq!(
+                cache.resolve(&create_service(None)).unwrap(),
+                create_addrs(&["10.0.0.1"])
+            );
+        }
+
+        thread::sleep(Duration::from_millis(60));
+
+        assert_eq!(
+            cache.resolve(&create_service(None)).unwrap(),
+            create_addrs(&["10.0.0.2"])
+        );
+    }
+
+    #[test]
+    fn addrscache_resolve_when_failed_refresh_retains_prior_addrs() {
+        let resolver = create_resolver(vec![
+            Ok(create_addrs(&["10.0.0.1"])),
+            Err(AppError::General("resolution failed".to_string())),
+            Err(AppError::General("resolution failed".to_string())),
+        ]);
+        let cache = ServiceAddrsCache::new(
+            Arc::new(resolver),
+            Duration::from_millis(50),
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(
+            cache.resolve(&create_service(None)).unwrap(),
+            create_addrs(&["10.0.0.1"])
+        );
+
+        thread::sleep(Duration::from_millis(60));
+
+        assert_eq!(cache.refresh_expired(), 0);
+        assert_eq!(
+            cache.resolve(&create_service(None)).unwrap(),
+            create_addrs(&["10.0.0.1"])
+        );
+    }
+
+    #[test]
+    fn addrscache_resolve_when_failed_refresh_beyond_stale_bound() {
+        let resolver = create_resolver(vec![
+            Ok(create_addrs(&["10.0.0.1"])),
+            Err(AppError::General("resolution failed".to_string())),
+        ]);
+        let cache = ServiceAddrsCache::new(
+            Arc::new(resolver),
+            Duration::from_millis(20),
+            Duration::from_millis(20),
+        );
+
+        assert!(cache.resolve(&create_service(None)).is_ok());
+
+        thread::sleep(Duration::from_millis(60));
+
+        assert!(cache.resolve(&create_service(None)).is_err());
+        assert!(cache.cached_addrs_by_host.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn addrscache_resolve_when_slow_resolver_times_out() {
+        let mut resolver = MockHostResolv::new();
+        resolver
+            .expect_resolve()
+            .with(predicate::eq("host1"))
+            .times(2)
+            .returning(|_| {
+                thread::sleep(Duration::from_millis(200));
+                Ok(create_addrs(&["10.0.0.1"]))
+            });
+        let mut cache = ServiceAddrsCache::new(Arc::new(resolver), Duration::ZERO, Duration::ZERO);
+        cache.set_connect_lookup_policy(
+            Duration::from_millis(20),
+            1,
+            Arc::new(FixedBackoff::new(Duration::from_millis(5))),
+        );
+
+        let started_at = Instant::now();
+
+        match cache.resolve(&create_service(None)) {
+            Err(AppError::GenWithCodeAndMsg(code, _)) => {
+                assert_eq!(code, config::RESPCODE_0425_INACTIVE_SERVICE_PROXY)
+            }
+            result => panic!("Unexpected result: val={:?}", &result),
+        }
+        assert!(started_at.elapsed() < Duration::from_millis(200));
+    }
+
+    #[test]
+    fn addrscache_resolve_when_flaky_resolver_recovers_on_retry() {
+        let resolver = create_resolver(vec![
+            Err(AppError::General("resolution failed".to_string())),
+            Err(AppError::General("resolution failed".to_string())),
+            Ok(create_addrs(&["10.0.0.1"])),
+        ]);
+        let mut cache =
+            ServiceAddrsCache::new(Arc::new(resolver), Duration::from_secs(60), Duration::ZERO);
+        cache.set_connect_lookup_policy(
+            Duration::from_secs(1),
+            2,
+            Arc::new(FixedBackoff::new(Duration::from_millis(5))),
+        );
+
+        assert_eq!(
+            cache.resolve(&create_service(None)).unwrap(),
+            create_addrs(&["10.0.0.1"])
+        );
+    }
+
+    #[test]
+    fn addrscache_resolve_when_retries_exhausted() {
+        let resolver = create_resolver(vec![
+            Err(AppError::General("resolution failed".to_string())),
+            Err(AppError::General("resolution failed".to_string())),
+        ]);
+        let mut cache = ServiceAddrsCache::new(Arc::new(resolver), Duration::ZERO, Duration::ZERO);
+        cache.set_connect_lookup_policy(
+            Duration::ZERO,
+            1,
+            Arc::new(FixedBackoff::new(Duration::ZERO)),
+        );
+
+        match cache.resolve(&create_service(None)) {
+            Err(AppError::GenWithCodeAndMsg(code, _)) => {
+                assert_eq!(code, config::RESPCODE_0425_INACTIVE_SERVICE_PROXY)
+            }
+            result => panic!("Unexpected result: val={:?}", &result),
+        }
+    }
+
+    #[test]
+    fn addrscache_refresh_expired_when_resolution_succeeds() {
+        let resolver = create_resolver(vec![
+            Ok(create_addrs(&["10.0.0.1"])),
+            Ok(create_addrs(&["10.0.0.2"])),
+        ]);
+        let cache = ServiceAddrsCache::new(
+            Arc::new(resolver),
+            Duration::from_millis(50),
+            Duration::from_secs(60),
+        );
+
+        assert!(cache.resolve(&create_service(None)).is_ok());
+        assert_eq!(cache.refresh_expired(), 0);
+
+        thread::sleep(Duration::from_millis(60));
+
+        assert_eq!(cache.refresh_expired(), 1);
+        assert_eq!(
+            cache.resolve(&create_service(None)).unwrap(),
+            create_addrs(&["10.0.0.2"])
+        );
+    }
+}
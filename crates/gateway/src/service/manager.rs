@@ -1,35 +1,117 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::net::TcpStream;
 use std::ops::DerefMut;
-use std::sync::mpsc::{Receiver, Sender};
+use std::path::Path;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 
 use super::proxy::proxy_base::GatewayServiceProxy;
 use super::proxy::tcp_proxy::TcpGatewayProxy;
-use crate::config::AppConfig;
+use crate::config::{self, AppConfig};
 use crate::service::proxy::proxy_base::GatewayServiceProxyVisitor;
 use crate::service::proxy::tcp_proxy::TcpGatewayProxyServerVisitor;
 use crate::service::proxy::udp_proxy::{UdpGatewayProxy, UdpGatewayProxyServerVisitor};
 use trust0_common::error::AppError;
-use trust0_common::logging::info;
+use trust0_common::logging::{error, info, warn};
+use trust0_common::metrics::{
+    user_connection_metric_name, MetricsSink, METRIC_PROXIES_ACTIVE, METRIC_SESSIONS_ACTIVE,
+};
 use trust0_common::model::service::{Service, Transport};
+use trust0_common::net::stream_utils::StreamReaderWriter;
+use trust0_common::net::worker_pool::WorkerPool;
 use trust0_common::proxy::event::ProxyEvent;
-use trust0_common::proxy::executor::ProxyExecutorEvent;
+use trust0_common::proxy::executor::{ProxyExecutorEvent, TcpProxyOptions};
+use trust0_common::proxy::proxy_base::ProxyType;
+use trust0_common::proxy::proxy_key::ProxyKey;
+use trust0_common::proxy::proxy_key_store::{InMemProxyKeyStore, ProxyKeyStore};
 use trust0_common::target;
 
 const DEFAULT_SERVICE_PORT_START: u16 = 8200;
 const DEFAULT_SERVICE_PORT_END: u16 = 8250;
 
+/// Maximum number of client sessions (per service) waiting for a reverse proxy connection
+const MAX_REVERSE_SESSIONS_PER_SERVICE: usize = 32;
+
+/// Duration a client session may wait for a reverse proxy connection, before it is dropped
+const REVERSE_SESSION_TTL: Duration = Duration::from_secs(300);
+
+/// Errors (by service ID) from failed service proxy connection shutdowns
+#[derive(Debug)]
+pub struct ShutdownErrors {
+    pub user_id: Option<u64>,
+    pub errors: HashMap<u64, Vec<AppError>>,
+}
+
+impl ShutdownErrors {
+    /// ShutdownErrors constructor
+    pub fn new(user_id: Option<u64>) -> Self {
+        Self {
+            user_id,
+            errors: HashMap::new(),
+        }
+    }
+
+    /// Add error for given service (prior errors for the service are kept)
+    pub fn add_error(&mut self, service_id: u64, error: AppError) {
+        self.errors.entry(service_id).or_default().push(error);
+    }
+
+    /// Service IDs which failed to shutdown (sorted)
+    pub fn get_failed_service_ids(&self) -> Vec<u64> {
+        let mut service_ids: Vec<u64> = self.errors.keys().cloned().collect();
+        service_ids.sort();
+        service_ids
+    }
+}
+
+impl Display for ShutdownErrors {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let errors: Vec<String> = self
+            .get_failed_service_ids()
+            .iter()
+            .flat_map(|service_id| {
+                self.errors.get(service_id).unwrap().iter().map(move |error| {
+                    format!(
+                        "Failed shutting down service proxy connection: svc_id={}, user_id={:?}, err={:?}",
+                        service_id, self.user_id, error
+                    )
+                })
+            })
+            .collect();
+
+        write!(
+            f,
+            "Error shutting down services: user_id={:?}, err(s)={}",
+            self.user_id,
+            errors.join(",")
+        )
+    }
+}
+
+impl From<ShutdownErrors> for AppError {
+    fn from(shutdown_errors: ShutdownErrors) -> Self {
+        AppError::General(shutdown_errors.to_string())
+    }
+}
+
 /// Handles management of service proxy connections
 pub trait ServiceMgr: Send {
     /// Return service ID for given proxy key, else return None
-    fn get_service_id_by_proxy_key(&self, proxy_key: &str) -> Option<u64>;
+    fn get_service_id_by_proxy_key(&self, proxy_key: &ProxyKey) -> Option<u64>;
 
     /// Active service proxy visitors accessor
     fn get_service_proxies(&self) -> Vec<Arc<Mutex<dyn GatewayServiceProxyVisitor>>>;
+
+    /// Clone of service proxy visitors for services using given transport
+    fn get_service_proxies_by_transport(
+        &self,
+        transport: Transport,
+    ) -> Vec<Arc<Mutex<dyn GatewayServiceProxyVisitor>>>;
     /// Service proxy visitor (by service ID) accessor
     fn get_service_proxy(
         &self,
@@ -47,14 +129,246 @@ pub trait ServiceMgr: Send {
     /// Returns whether there is an active service proxy for given user and service
     fn has_proxy_for_user_and_service(&mut self, user_id: u64, service_id: u64) -> bool;
     /// Shutdown service proxy connections. Consider all proxies or by service and/or user (if supplied).
+    /// Failures are reported per service, all other services will still be shutdown.
     fn shutdown_connections(
         &mut self,
         user_id: Option<u64>,
         service_id: Option<u64>,
-    ) -> Result<(), AppError>;
+    ) -> Result<(), ShutdownErrors>;
+
+    /// Shutdown service proxy connections, which were authenticated by the client certificate with given serial.
+    /// Failures are reported per service, all other services will still be shutdown.
+    fn shutdown_connections_by_cert_serial(
+        &mut self,
+        cert_serial: &str,
+    ) -> Result<(), ShutdownErrors>;
+
+    /// Stop service proxy listeners polling, so no new connections are accepted. Existing connections are unaffected
+    fn stop_listeners(&mut self);
+
+    /// Returns the number of tracked (active) service proxy connections
+    fn get_active_proxy_count(&self) -> usize;
 
     /// Perform cleanup for a closed proxy
-    fn on_closed_proxy(&mut self, proxy_key: &str);
+    fn on_closed_proxy(&mut self, proxy_key: &ProxyKey);
+
+    /// Drop tracked proxy keys, which no longer have a corresponding proxy in any service proxy visitor
+    /// (for instance, if a proxy closed event was lost). Returns the number of proxy keys removed
+    fn reconcile_proxy_keys(&mut self) -> usize;
+
+    /// Tear down service proxies, which have had no connections since their startup and whose reservation TTL
+    /// has elapsed as of `now` (their ports are reclaimed). Returns the reclaimed service IDs
+    fn reclaim_unused_services(&mut self, now: Instant) -> Vec<u64>;
+
+    /// Close the service proxy connections, which have been idle (no activity) for at least their transport's idle
+    /// timeout (as of `now`). Returns the proxy keys of the closed connections
+    fn shutdown_idle_connections(&mut self, now: Instant) -> Vec<ProxyKey>;
+
+    /// Queue (authorized) client session, to wait for a reverse (gateway-initiated) proxy connection for given
+    /// service. Disconnected/expired sessions are dropped first, and a full service queue is rejected
+    fn add_reverse_session(
+        &mut self,
+        service_id: u64,
+        reverse_session: ReverseClientSession,
+    ) -> Result<(), AppError>;
+
+    /// Reverse proxy setup: take the next (connected, unexpired) client session waiting for given (TCP) service,
+    /// provided the session's user may connect to it. Returns the service and the session
+    fn take_reverse_session(
+        &mut self,
+        service_id: u64,
+    ) -> Result<(Service, ReverseClientSession), AppError>;
+
+    /// Requeue (at the front) a client session, whose reverse proxy setup failed
+    fn requeue_reverse_session(&mut self, service_id: u64, reverse_session: ReverseClientSession);
+
+    /// Reverse proxy setup: relay the (gateway-initiated) service upstream connection to given client session, tracking
+    /// it like any other service proxy connection. Returns the new proxy's key
+    fn open_reverse_proxy(
+        &mut self,
+        service: &Service,
+        reverse_session: ReverseClientSession,
+        upstream_stream: TcpStream,
+    ) -> Result<ProxyKey, AppError>;
+}
+
+/// Client session (an authorized client connection), waiting to be relayed to a gateway-initiated upstream
+/// connection
+pub struct ReverseClientSession {
+    user_id: u64,
+    cert_serial: Option<String>,
+    client_stream: TcpStream,
+    client_reader_writer: Arc<Mutex<Box<dyn StreamReaderWriter>>>,
+    queued_at: Instant,
+}
+
+impl ReverseClientSession {
+    /// ReverseClientSession constructor (sessions are only created for connections, which passed authorization)
+    pub(crate) fn new(
+        user_id: u64,
+        client_stream: TcpStream,
+        client_reader_writer: Arc<Mutex<Box<dyn StreamReaderWriter>>>,
+    ) -> Self {
+        Self {
+            user_id,
+            cert_serial: None,
+            client_stream,
+            client_reader_writer,
+            queued_at: Instant::now(),
+        }
+    }
+
+    /// Set serial of the client certificate, which authenticated the session (its reverse proxy is closed upon the
+    /// certificate's revocation)
+    pub fn set_cert_serial(&mut self, cert_serial: Option<String>) {
+        self.cert_serial = cert_serial;
+    }
+
+    /// Session has waited (as of `now`) longer than the reverse session TTL
+    fn is_expired(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.queued_at) >= REVERSE_SESSION_TTL
+    }
+
+    /// Session's client connection is still open (not closed/errored by the peer)
+    fn is_connected(&self) -> bool {
+        if self.client_stream.set_nonblocking(true).is_err() {
+            return false;
+        }
+        match self.client_stream.peek(&mut [0u8; 1]) {
+            Ok(0) => false,
+            Ok(_) => true,
+            Err(err) => err.kind() == std::io::ErrorKind::WouldBlock,
+        }
+    }
+}
+
+/// Active reverse (gateway-initiated) proxy connection
+struct ReverseProxy {
+    user_id: u64,
+    cert_serial: Option<String>,
+    started_at: Instant,
+}
+
+/// Tracks the distinct services each user has active proxy connections to, enforcing the (optional) maximum
+/// number of distinct active services per user
+pub struct UserActiveServices {
+    max_services_per_user: Option<usize>,
+    user_services_by_proxy_key: HashMap<ProxyKey, (u64, u64)>,
+    connection_counts_by_user: HashMap<u64, HashMap<u64, usize>>,
+    session_metrics_sink: Option<Arc<dyn MetricsSink>>,
+}
+
+impl UserActiveServices {
+    /// UserActiveServices constructor (None is unlimited)
+    pub fn new(max_services_per_user: Option<usize>) -> Self {
+        Self {
+            max_services_per_user,
+            user_services_by_proxy_key: HashMap::new(),
+            connection_counts_by_user: HashMap::new(),
+            session_metrics_sink: None,
+        }
+    }
+
+    /// Set metrics sink, to which a per-user active session (proxy connection) count gauge is emitted on each change
+    pub fn set_session_metrics_sink(&mut self, session_metrics_sink: Option<Arc<dyn MetricsSink>>) {
+        self.session_metrics_sink = session_metrics_sink;
+    }
+
+    /// Validate a new connection by user to given service. Connections to services, which the user already has
+    /// active, are always permitted. Otherwise a forbidden (403) error is returned when the user is at their cap.
+    pub fn check_connection(&self, user_id: u64, service_id: u64) -> Result<(), AppError> {
+        let max_services = match self.max_services_per_user {
+            Some(max_services) => max_services,
+            None => return Ok(()),
+        };
+
+        if self
+            .connection_counts_by_user
+            .get(&user_id)
+            .is_some_and(|connection_counts| connection_counts.contains_key(&service_id))
+        {
+            return Ok(());
+        }
+
+        if self.get_active_service_count(user_id) >= max_services {
+            return Err(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0403_FORBIDDEN,
+                format!(
+                    "User active service limit reached: uid={}, svc_id={}, max={}",
+                    user_id, service_id, max_services
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Associate proxy connection with given user (the service is the proxy key's service)
+    pub fn register_proxy(&mut self, proxy_key: &ProxyKey, user_id: u64) {
+        let service_id = proxy_key.get_service_id();
+        if self
+            .user_services_by_proxy_key
+            .insert(proxy_key.clone(), (user_id, service_id))
+            .is_none()
+        {
+            *self
+                .connection_counts_by_user
+                .entry(user_id)
+                .or_default()
+                .entry(service_id)
+                .or_default() += 1;
+            self.emit_session_count(user_id);
+        }
+    }
+
+    /// Remove proxy connection. The service is no longer active for the user, once their last connection closes
+    pub fn unregister_proxy(&mut self, proxy_key: &ProxyKey) {
+        let (user_id, service_id) = match self.user_services_by_proxy_key.remove(proxy_key) {
+            Some(user_service) => user_service,
+            None => return,
+        };
+
+        if let Some(connection_counts) = self.connection_counts_by_user.get_mut(&user_id) {
+            if let Some(connection_count) = connection_counts.get_mut(&service_id) {
+                *connection_count -= 1;
+                if *connection_count == 0 {
+                    connection_counts.remove(&service_id);
+                }
+            }
+            if connection_counts.is_empty() {
+                self.connection_counts_by_user.remove(&user_id);
+            }
+        }
+
+        self.emit_session_count(user_id);
+    }
+
+    /// Number of distinct services the user has active connections to
+    pub fn get_active_service_count(&self, user_id: u64) -> usize {
+        self.connection_counts_by_user
+            .get(&user_id)
+            .map(HashMap::len)
+            .unwrap_or(0)
+    }
+
+    /// Number of active sessions (proxy connections, across all services) the user has
+    pub fn get_active_session_count(&self, user_id: u64) -> usize {
+        self.connection_counts_by_user
+            .get(&user_id)
+            .map(|connection_counts| connection_counts.values().sum())
+            .unwrap_or(0)
+    }
+
+    /// Emit user's active session count gauge (if session metrics enabled). A zero count is emitted once the user
+    /// has no sessions, so the gauge doesn't retain a stale value
+    fn emit_session_count(&self, user_id: u64) {
+        if let Some(session_metrics_sink) = &self.session_metrics_sink {
+            session_metrics_sink.set_gauge(
+                &user_connection_metric_name(METRIC_SESSIONS_ACTIVE, user_id),
+                self.get_active_session_count(user_id) as i64,
+            );
+        }
+    }
 }
 
 /// Manage (Gateway <-> Service) service connections. Only one of these should be constructed.
@@ -62,14 +376,21 @@ pub struct GatewayServiceMgr {
     app_config: Arc<AppConfig>,
     service_proxies: HashMap<u64, Arc<Mutex<dyn GatewayServiceProxy>>>,
     service_proxy_visitors: HashMap<u64, Arc<Mutex<dyn GatewayServiceProxyVisitor>>>,
-    service_proxy_threads: HashMap<u64, JoinHandle<Result<(), AppError>>>,
-    services_by_proxy_key: Arc<Mutex<HashMap<String, u64>>>,
+    services_by_proxy_key: Arc<dyn ProxyKeyStore>,
     service_ports: HashMap<u64, u16>,
     shared_service_port: Option<u16>,
+    ephemeral_service_ports: bool,
     next_service_port: u16,
     last_service_port: u16,
+    free_service_ports: Vec<u16>,
+    persisted_service_ports: HashMap<u64, u16>,
+    unused_service_reservations: HashMap<u64, Instant>,
+    user_active_services: Arc<Mutex<UserActiveServices>>,
+    reverse_sessions: HashMap<u64, VecDeque<ReverseClientSession>>,
+    reverse_proxy_keys: HashMap<ProxyKey, ReverseProxy>,
     proxy_events_sender: Sender<ProxyEvent>,
     proxy_tasks_sender: Sender<ProxyExecutorEvent>,
+    worker_pool: WorkerPool,
 }
 
 impl GatewayServiceMgr {
@@ -88,101 +409,325 @@ impl GatewayServiceMgr {
                 next_service_port = *port_start;
                 last_service_port = *port_end;
             }
+            None if app_config.gateway_service_ephemeral_ports => {}
             None => {
                 shared_service_port = Some(app_config.server_port);
             }
         };
+        let ephemeral_service_ports =
+            app_config.gateway_service_ports.is_none() && shared_service_port.is_none();
+
+        let persisted_service_ports = match &app_config.service_ports_state_file {
+            Some(state_file) if app_config.gateway_service_ports.is_some() => {
+                Self::load_service_ports(state_file)
+            }
+            _ => HashMap::new(),
+        };
+
+        let worker_pool = WorkerPool::new(app_config.worker_threads);
+        let mut user_active_services = UserActiveServices::new(app_config.max_services_per_user);
+        if app_config.user_session_metrics {
+            user_active_services.set_session_metrics_sink(Some(app_config.metrics_sink.clone()));
+        }
+        let user_active_services = Arc::new(Mutex::new(user_active_services));
 
         Self {
             app_config,
             service_proxies: HashMap::new(),
             service_proxy_visitors: HashMap::new(),
-            service_proxy_threads: HashMap::new(),
             service_ports: HashMap::new(),
-            services_by_proxy_key: Arc::new(Mutex::new(HashMap::new())),
+            services_by_proxy_key: Arc::new(InMemProxyKeyStore::new()),
             shared_service_port,
+            ephemeral_service_ports,
             next_service_port,
             last_service_port,
+            free_service_ports: vec![],
+            persisted_service_ports,
+            unused_service_reservations: HashMap::new(),
+            user_active_services,
+            reverse_sessions: HashMap::new(),
+            reverse_proxy_keys: HashMap::new(),
             proxy_events_sender,
             proxy_tasks_sender,
+            worker_pool,
+        }
+    }
+
+    /// Load service port assignments (by service ID) from given state file. A missing or invalid file results in no
+    /// prior assignments.
+    fn load_service_ports(state_file: &str) -> HashMap<u64, u16> {
+        if !Path::new(state_file).exists() {
+            return HashMap::new();
+        }
+
+        fs::read_to_string(state_file)
+            .map_err(|err| format!("{:?}", err))
+            .and_then(|data| serde_json::from_str(&data).map_err(|err| format!("{:?}", err)))
+            .unwrap_or_else(|err| {
+                warn(
+                    &target!(),
+                    &format!(
+                        "Error loading service ports state file, ignoring prior port assignments: file={}, err={}",
+                        state_file, err
+                    ),
+                );
+                HashMap::new()
+            })
+    }
+
+    /// Save service port assignments to the state file (if configured)
+    fn save_service_ports(&self) {
+        let state_file = match &self.app_config.service_ports_state_file {
+            Some(state_file) if self.app_config.gateway_service_ports.is_some() => state_file,
+            _ => return,
+        };
+
+        let result = serde_json::to_string(&self.persisted_service_ports)
+            .map_err(|err| format!("{:?}", err))
+            .and_then(|data| fs::write(state_file, data).map_err(|err| format!("{:?}", err)));
+        if let Err(err) = result {
+            warn(
+                &target!(),
+                &format!(
+                    "Error saving service ports state file: file={}, err={}",
+                    state_file, err
+                ),
+            );
+        }
+    }
+
+    /// Take service's prior (persisted) port assignment, if it's still within the service port range and isn't in
+    /// use by another service. Otherwise a warning is logged and None is returned.
+    fn take_prior_service_port(&mut self, service_id: u64) -> Option<u16> {
+        let prior_port = *self.persisted_service_ports.get(&service_id)?;
+
+        let (port_start, _) = self.app_config.gateway_service_ports?;
+        let out_of_range = (prior_port < port_start) || (prior_port > self.last_service_port);
+        let taken = self.service_ports.iter().any(|(other_service_id, port)| {
+            (*other_service_id != service_id) && (*port == prior_port)
+        });
+
+        if out_of_range || taken {
+            warn(
+                &target!(),
+                &format!(
+                    "Prior service port unavailable, allocating new port: svc_id={}, port={}, out_of_range={}, taken={}",
+                    service_id, prior_port, out_of_range, taken
+                ),
+            );
+            self.persisted_service_ports.remove(&service_id);
+            return None;
+        }
+
+        self.free_service_ports.retain(|port| *port != prior_port);
+        Some(prior_port)
+    }
+
+    /// Allocate a new service port from the service port range (reclaimed ports first). Ports in use, or assigned
+    /// (persisted) to other services, are skipped.
+    fn allocate_service_port(&mut self) -> Result<u16, AppError> {
+        let assigned_ports: HashSet<u16> = self
+            .service_ports
+            .values()
+            .chain(self.persisted_service_ports.values())
+            .copied()
+            .collect();
+
+        while let Some(port) = self.free_service_ports.pop() {
+            if !assigned_ports.contains(&port) {
+                return Ok(port);
+            }
+        }
+
+        while self.next_service_port <= self.last_service_port {
+            self.next_service_port += 1;
+            let port = self.next_service_port - 1;
+            if !assigned_ports.contains(&port) {
+                return Ok(port);
+            }
+        }
+
+        Err(AppError::General(
+            "Service ports exhausted, please extend range".to_string(),
+        ))
+    }
+
+    /// Atomically test-and-start service proxy: the already started check and (if needed) the new proxy startup are
+    /// performed within a single service manager lock critical section, so concurrent first-time startups for the same
+    /// service share the one proxy (and port). Returns the gateway service host and port.
+    pub fn startup_service(
+        service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
+        service: &Service,
+    ) -> Result<(Option<String>, u16), AppError> {
+        let mut service_mgr_guard = service_mgr.lock().unwrap();
+        service_mgr_guard.startup(service_mgr.clone(), service)
+    }
+
+    /// Reverse proxy setup: connect (from the gateway) to the service upstream and relay it to the next waiting
+    /// client session for that service. The upstream connection is made without holding the service manager lock
+    /// (on failure, the session remains waiting). Returns the new proxy's key
+    pub fn connect_reverse_proxy(
+        service_mgr: &Arc<Mutex<dyn ServiceMgr>>,
+        app_config: &AppConfig,
+        service_id: u64,
+    ) -> Result<ProxyKey, AppError> {
+        let (service, reverse_session) = service_mgr
+            .lock()
+            .unwrap()
+            .take_reverse_session(service_id)?;
+
+        match TcpGatewayProxyServerVisitor::connect_to_service(
+            &app_config.service_addrs_cache,
+            &app_config.upstream_circuit_breaker,
+            app_config.upstream_bind_addr,
+            &service,
+        ) {
+            Ok(upstream_stream) => service_mgr.lock().unwrap().open_reverse_proxy(
+                &service,
+                reverse_session,
+                upstream_stream,
+            ),
+            Err(err) => {
+                service_mgr
+                    .lock()
+                    .unwrap()
+                    .requeue_reverse_session(service_id, reverse_session);
+                Err(err)
+            }
+        }
+    }
+
+    /// Reverse proxies' keys, which match given predicate
+    fn get_reverse_proxy_keys<P>(&self, predicate: P) -> Vec<ProxyKey>
+    where
+        P: Fn(&ProxyKey, &ReverseProxy) -> bool,
+    {
+        self.reverse_proxy_keys
+            .iter()
+            .filter(|(proxy_key, reverse_proxy)| predicate(proxy_key, reverse_proxy))
+            .map(|(proxy_key, _)| proxy_key.clone())
+            .collect()
+    }
+
+    /// Shutdown the reverse proxy for given proxy key
+    fn shutdown_reverse_proxy(&mut self, proxy_key: &ProxyKey) -> Result<(), AppError> {
+        self.proxy_tasks_sender
+            .send(ProxyExecutorEvent::Close(proxy_key.clone()))
+            .map_err(|err| {
+                AppError::General(format!(
+                    "Error while sending request to close a reverse proxy connection: proxy_stream={}, err={:?}",
+                    proxy_key, err
+                ))
+            })?;
+
+        self.remove_reverse_proxy(proxy_key);
+        info(
+            &target!(),
+            &format!(
+                "Reverse service proxy connection shutdown: proxy_stream={}",
+                proxy_key
+            ),
+        );
+        Ok(())
+    }
+
+    /// Remove reverse proxy for given proxy key. Returns true if it was a tracked reverse proxy (and removed)
+    fn remove_reverse_proxy(&mut self, proxy_key: &ProxyKey) -> bool {
+        if self.reverse_proxy_keys.remove(proxy_key).is_none() {
+            return false;
         }
+
+        self.services_by_proxy_key.remove(proxy_key);
+        self.app_config
+            .user_byte_quotas
+            .lock()
+            .unwrap()
+            .unregister_proxy(proxy_key);
+        self.user_active_services
+            .lock()
+            .unwrap()
+            .unregister_proxy(proxy_key);
+        self.app_config
+            .service_activity
+            .remove_connection(proxy_key);
+        self.app_config
+            .service_throughput
+            .remove_connection(proxy_key);
+        true
     }
 
-    /// Listen and process any proxy events (blocking)
+    /// Listen and process any proxy events (blocking), until a shutdown event is received. Proxy keys are reconciled
+    /// every `reconcile_interval`. An unexpected disconnect of the proxy events channel ends processing in error.
     pub fn poll_proxy_events(
         service_mgr: Arc<Mutex<dyn ServiceMgr>>,
         proxy_events_receiver: Receiver<ProxyEvent>,
+        reconcile_interval: Duration,
     ) -> Result<(), AppError> {
+        let mut next_reconcile = Instant::now() + reconcile_interval;
+
         loop {
             // Get next request task
-            let proxy_event = proxy_events_receiver.recv().map_err(|err| {
-                AppError::GenWithMsgAndErr("Error receiving proxy event".to_string(), Box::new(err))
-            })?;
+            let proxy_event = match proxy_events_receiver
+                .recv_timeout(next_reconcile.saturating_duration_since(Instant::now()))
+            {
+                Ok(proxy_event) => Some(proxy_event),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(err) => {
+                    error(&target!(), "Proxy events channel disconnected unexpectedly");
+                    return Err(AppError::GenWithMsgAndErr(
+                        "Error receiving proxy event".to_string(),
+                        Box::new(err),
+                    ));
+                }
+            };
 
             // Process event
             match proxy_event {
-                ProxyEvent::Closed(proxy_key) => {
+                Some(ProxyEvent::Closed(proxy_key)) => {
                     service_mgr.lock().unwrap().on_closed_proxy(&proxy_key);
                 }
 
-                ProxyEvent::Message(_, _, _) => {
+                Some(ProxyEvent::Message(_, _, _)) => {
                     unimplemented!();
                 }
+
+                Some(ProxyEvent::Shutdown) => {
+                    info(&target!(), "Proxy events processing shutdown");
+                    return Ok(());
+                }
+
+                None => {}
             }
-        }
-    }
-}
 
-impl ServiceMgr for GatewayServiceMgr {
-    fn get_service_id_by_proxy_key(&self, proxy_key: &str) -> Option<u64> {
-        self.services_by_proxy_key
-            .lock()
-            .unwrap()
-            .get(proxy_key)
-            .cloned()
+            // Periodic proxy keys reconciliation (and unused service proxies reclamation, idle connections shutdown)
+            if Instant::now() >= next_reconcile {
+                let mut service_mgr = service_mgr.lock().unwrap();
+                service_mgr.reconcile_proxy_keys();
+                service_mgr.reclaim_unused_services(Instant::now());
+                service_mgr.shutdown_idle_connections(Instant::now());
+                next_reconcile = Instant::now() + reconcile_interval;
+            }
+        }
     }
 
-    fn get_service_proxies(&self) -> Vec<Arc<Mutex<dyn GatewayServiceProxyVisitor>>> {
-        self.service_proxy_visitors.values().cloned().collect()
-    }
-    fn get_service_proxy(
-        &self,
-        service_id: u64,
-    ) -> Option<&Arc<Mutex<dyn GatewayServiceProxyVisitor>>> {
-        self.service_proxy_visitors.get(&service_id)
-    }
-    fn clone_proxy_tasks_sender(&self) -> Sender<ProxyExecutorEvent> {
-        self.proxy_tasks_sender.clone()
-    }
-    fn startup(
+    /// Create service proxy (and its visitor) and, if not using the shared listener port, bind and spawn its listener.
+    /// Returns the proxy, its visitor and the bound service port
+    #[allow(clippy::type_complexity)]
+    fn start_service_proxy(
         &mut self,
         service_mgr: Arc<Mutex<dyn ServiceMgr>>,
         service: &Service,
-    ) -> Result<(Option<String>, u16), AppError> {
-        // Check if already started
-        // - - - - - - - - - - - -
-        if let Some(service_port) = self.service_ports.get(&service.service_id) {
-            return Ok((self.app_config.gateway_service_host.clone(), *service_port));
-        }
-
-        // Startup new proxy for service
-        // - - - - - - - - - - - - - - -
-        let service_port = match self.shared_service_port {
-            Some(port) => port,
-            None => {
-                if self.next_service_port > self.last_service_port {
-                    return Err(AppError::General(
-                        "Service ports exhausted, please extend range".to_string(),
-                    ));
-                }
-                self.next_service_port += 1;
-                self.next_service_port - 1
-            }
-        };
-
+        mut service_port: u16,
+    ) -> Result<
+        (
+            Arc<Mutex<dyn GatewayServiceProxy>>,
+            Arc<Mutex<dyn GatewayServiceProxyVisitor>>,
+            u16,
+        ),
+        AppError,
+    > {
         let service_proxy: Arc<Mutex<dyn GatewayServiceProxy>>;
         let service_proxy_visitor: Arc<Mutex<dyn GatewayServiceProxyVisitor>>;
-        let mut service_proxy_thread: Option<JoinHandle<Result<(), AppError>>> = None;
 
         match service.transport {
             // Starts up TCP service proxy
@@ -197,6 +742,7 @@ impl ServiceMgr for GatewayServiceMgr {
                     self.proxy_tasks_sender.clone(),
                     self.proxy_events_sender.clone(),
                     self.services_by_proxy_key.clone(),
+                    self.user_active_services.clone(),
                 )?));
 
                 service_proxy = Arc::new(Mutex::new(TcpGatewayProxy::new(
@@ -205,15 +751,22 @@ impl ServiceMgr for GatewayServiceMgr {
                     service_port,
                 )));
 
-                service_proxy_visitor = tcp_proxy_visitor;
-
                 // Startup service proxy listener (only if not using shared listener port)
                 if self.shared_service_port.is_none() {
+                    service_port = service_proxy.lock().unwrap().bind_listener()?;
+                    tcp_proxy_visitor
+                        .lock()
+                        .unwrap()
+                        .set_proxy_port(service_port);
+
+                    service_proxy.lock().unwrap().startup()?;
                     let service_proxy_closure = service_proxy.clone();
-                    service_proxy_thread = Some(thread::spawn(move || {
-                        service_proxy_closure.lock().unwrap().startup()
-                    }));
+                    self.worker_pool.submit(Box::new(move || {
+                        service_proxy_closure.lock().unwrap().poll_connections()
+                    }))?;
                 }
+
+                service_proxy_visitor = tcp_proxy_visitor;
             }
 
             // Starts up UDP service proxy
@@ -228,6 +781,7 @@ impl ServiceMgr for GatewayServiceMgr {
                     self.proxy_tasks_sender.clone(),
                     self.proxy_events_sender.clone(),
                     self.services_by_proxy_key.clone(),
+                    self.user_active_services.clone(),
                 )?));
 
                 service_proxy = Arc::new(Mutex::new(UdpGatewayProxy::new(
@@ -236,95 +790,663 @@ impl ServiceMgr for GatewayServiceMgr {
                     service_port,
                 )));
 
-                service_proxy_visitor = udp_proxy_visitor;
-
                 // Startup service proxy listener (only if not using shared listener port)
                 if self.shared_service_port.is_none() {
+                    service_port = service_proxy.lock().unwrap().bind_listener()?;
+                    udp_proxy_visitor
+                        .lock()
+                        .unwrap()
+                        .set_proxy_port(service_port);
+
+                    service_proxy.lock().unwrap().startup()?;
                     let service_proxy_closure = service_proxy.clone();
-                    service_proxy_thread = Some(thread::spawn(move || {
-                        service_proxy_closure.lock().unwrap().startup()
-                    }));
+                    self.worker_pool.submit(Box::new(move || {
+                        service_proxy_closure.lock().unwrap().poll_connections()
+                    }))?;
                 }
-            }
-        }
-
-        self.service_ports.insert(service.service_id, service_port);
-        self.service_proxies
-            .insert(service.service_id, service_proxy);
-        self.service_proxy_visitors
-            .insert(service.service_id, service_proxy_visitor);
 
-        if let Some(thread) = service_proxy_thread {
-            self.service_proxy_threads
-                .insert(service.service_id, thread);
+                service_proxy_visitor = udp_proxy_visitor;
+            }
         }
 
-        Ok((self.app_config.gateway_service_host.clone(), service_port))
+        Ok((service_proxy, service_proxy_visitor, service_port))
     }
-    fn has_proxy_for_user_and_service(&mut self, user_id: u64, service_id: u64) -> bool {
-        match self.service_proxy_visitors.get(&service_id) {
-            Some(proxy_visitor) => {
-                let proxy_visitor = proxy_visitor.lock().unwrap();
-                !proxy_visitor.get_proxy_addrs_for_user(user_id).is_empty()
-            }
 
-            None => false,
-        }
-    }
-    fn shutdown_connections(
+    /// Drop the given service's waiting client sessions, which are disconnected or expired (as of `now`). Returns
+    /// the service's (remaining) session queue
+    fn prune_reverse_sessions(
         &mut self,
-        user_id: Option<u64>,
-        service_id: Option<u64>,
-    ) -> Result<(), AppError> {
-        let mut errors: Vec<String> = vec![];
-
-        self.service_proxy_visitors.iter().for_each(|(proxy_service_id, proxy_visitor)| {
-            if service_id.is_none() || (*proxy_service_id == service_id.unwrap()) {
-                let mut proxy_visitor = proxy_visitor.lock().unwrap();
+        service_id: u64,
+        now: Instant,
+    ) -> &mut VecDeque<ReverseClientSession> {
+        let reverse_sessions = self.reverse_sessions.entry(service_id).or_default();
 
-                if let Err(err) = proxy_visitor.deref_mut().shutdown_connections(self.clone_proxy_tasks_sender(), user_id) {
-                    errors.push(format!("Failed shutting down service proxy connection: svc_id={}, user_id={:?}, err={:?}", proxy_service_id, user_id, err));
-                } else {
-                    info(&target!(), &format!("Service proxy connection shutdown: svc_id={}, user_id={:?}", proxy_service_id, user_id));
-                }
-            }
+        let queued_count = reverse_sessions.len();
+        reverse_sessions.retain(|reverse_session| {
+            !reverse_session.is_expired(now) && reverse_session.is_connected()
         });
-
-        if !errors.is_empty() {
-            return Err(AppError::General(format!(
-                "Error shutting down services: user_id={:?}, err(s)={}",
-                user_id,
-                errors.join(",")
-            )));
+        if reverse_sessions.len() < queued_count {
+            info(
+                &target!(),
+                &format!(
+                    "Dropped disconnected/expired reverse proxy client sessions: svc_id={}, count={}",
+                    service_id,
+                    queued_count - reverse_sessions.len()
+                ),
+            );
         }
 
-        Ok(())
+        reverse_sessions
     }
+}
 
-    fn on_closed_proxy(&mut self, proxy_key: &str) {
-        let service_id = self
-            .get_service_id_by_proxy_key(proxy_key)
-            .unwrap_or(u64::MAX);
-        if let Some(proxy_visitor) = self.get_service_proxy(service_id) {
-            proxy_visitor
-                .lock()
-                .unwrap()
-                .remove_proxy_for_key(proxy_key);
-        }
+impl ServiceMgr for GatewayServiceMgr {
+    fn get_service_id_by_proxy_key(&self, proxy_key: &ProxyKey) -> Option<u64> {
+        self.services_by_proxy_key.get(proxy_key)
     }
-}
 
-/// Unit tests
-#[cfg(test)]
-pub mod tests {
-    use super::*;
-    use crate::config;
-    use crate::repository::access_repo::tests::MockAccessRepo;
-    use crate::repository::service_repo::tests::MockServiceRepo;
-    use crate::repository::user_repo::tests::MockUserRepo;
-    use crate::service::proxy::proxy_base::tests::MockGwSvcProxyVisitor;
-    use mockall::{mock, predicate};
+    fn get_service_proxies(&self) -> Vec<Arc<Mutex<dyn GatewayServiceProxyVisitor>>> {
+        self.service_proxy_visitors.values().cloned().collect()
+    }
+    fn get_service_proxies_by_transport(
+        &self,
+        transport: Transport,
+    ) -> Vec<Arc<Mutex<dyn GatewayServiceProxyVisitor>>> {
+        self.service_proxy_visitors
+            .values()
+            .filter(|proxy_visitor| {
+                proxy_visitor.lock().unwrap().get_service().transport == transport
+            })
+            .cloned()
+            .collect()
+    }
+    fn get_service_proxy(
+        &self,
+        service_id: u64,
+    ) -> Option<&Arc<Mutex<dyn GatewayServiceProxyVisitor>>> {
+        self.service_proxy_visitors.get(&service_id)
+    }
+    fn clone_proxy_tasks_sender(&self) -> Sender<ProxyExecutorEvent> {
+        self.proxy_tasks_sender.clone()
+    }
+    fn startup(
+        &mut self,
+        service_mgr: Arc<Mutex<dyn ServiceMgr>>,
+        service: &Service,
+    ) -> Result<(Option<String>, u16), AppError> {
+        if !service.proxyable {
+            return Err(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0403_FORBIDDEN,
+                format!(
+                    "Service is not proxyable (control plane only): svc_id={}",
+                    service.service_id
+                ),
+            ));
+        }
+
+        // Check if already started
+        // - - - - - - - - - - - -
+        if let Some(service_port) = self.service_ports.get(&service.service_id) {
+            return Ok((self.app_config.gateway_service_host.clone(), *service_port));
+        }
+
+        // Reserve service (port) entry, before spawning its proxy listener
+        // - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+        let mut prior_port_reused = false;
+        let mut service_port = match self.shared_service_port {
+            Some(port) => port,
+            None if self.ephemeral_service_ports => 0,
+            None => match self.take_prior_service_port(service.service_id) {
+                Some(port) => {
+                    prior_port_reused = true;
+                    port
+                }
+                None => self.allocate_service_port()?,
+            },
+        };
+        self.service_ports.insert(service.service_id, service_port);
+
+        // Startup new proxy for service (falling back to a new port, if the prior port can't be bound)
+        // - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+        let mut start_result = self.start_service_proxy(service_mgr.clone(), service, service_port);
+        if prior_port_reused {
+            if let Err(err) = &start_result {
+                warn(
+                    &target!(),
+                    &format!(
+                        "Prior service port unavailable, allocating new port: svc_id={}, port={}, err={:?}",
+                        service.service_id, service_port, err
+                    ),
+                );
+                self.service_ports.remove(&service.service_id);
+                self.persisted_service_ports.remove(&service.service_id);
+                service_port = self.allocate_service_port()?;
+                self.service_ports.insert(service.service_id, service_port);
+                start_result = self.start_service_proxy(service_mgr, service, service_port);
+            }
+        }
+
+        let (service_proxy, service_proxy_visitor, service_port) = match start_result {
+            Ok(started_proxy) => started_proxy,
+            Err(err) => {
+                self.service_ports.remove(&service.service_id);
+                if self.shared_service_port.is_none() && !self.ephemeral_service_ports {
+                    self.free_service_ports.push(service_port);
+                }
+                return Err(err);
+            }
+        };
+
+        self.service_ports.insert(service.service_id, service_port);
+        if self.shared_service_port.is_none() && !self.ephemeral_service_ports {
+            self.persisted_service_ports
+                .insert(service.service_id, service_port);
+            self.save_service_ports();
+        }
+        self.service_proxies
+            .insert(service.service_id, service_proxy);
+        self.service_proxy_visitors
+            .insert(service.service_id, service_proxy_visitor);
+        if !self.app_config.service_reservation_ttl.is_zero() {
+            self.unused_service_reservations
+                .insert(service.service_id, Instant::now());
+        }
+
+        Ok((self.app_config.gateway_service_host.clone(), service_port))
+    }
+    fn has_proxy_for_user_and_service(&mut self, user_id: u64, service_id: u64) -> bool {
+        match self.service_proxy_visitors.get(&service_id) {
+            Some(proxy_visitor) => {
+                let proxy_visitor = proxy_visitor.lock().unwrap();
+                !proxy_visitor.get_proxy_addrs_for_user(user_id).is_empty()
+            }
+
+            None => false,
+        }
+    }
+    fn shutdown_connections(
+        &mut self,
+        user_id: Option<u64>,
+        service_id: Option<u64>,
+    ) -> Result<(), ShutdownErrors> {
+        let mut shutdown_errors = ShutdownErrors::new(user_id);
+
+        self.service_proxy_visitors
+            .iter()
+            .for_each(|(proxy_service_id, proxy_visitor)| {
+                if service_id.is_none() || (*proxy_service_id == service_id.unwrap()) {
+                    let mut proxy_visitor = proxy_visitor.lock().unwrap();
+
+                    if let Err(err) = proxy_visitor
+                        .deref_mut()
+                        .shutdown_connections(self.clone_proxy_tasks_sender(), user_id)
+                    {
+                        shutdown_errors.add_error(*proxy_service_id, err);
+                    } else {
+                        info(
+                            &target!(),
+                            &format!(
+                                "Service proxy connection shutdown: svc_id={}, user_id={:?}",
+                                proxy_service_id, user_id
+                            ),
+                        );
+                    }
+                }
+            });
+
+        let reverse_proxy_keys = self.get_reverse_proxy_keys(|proxy_key, reverse_proxy| {
+            (user_id.is_none() || (user_id == Some(reverse_proxy.user_id)))
+                && (service_id.is_none() || (service_id == Some(proxy_key.get_service_id())))
+        });
+        for proxy_key in reverse_proxy_keys {
+            if let Err(err) = self.shutdown_reverse_proxy(&proxy_key) {
+                shutdown_errors.add_error(proxy_key.get_service_id(), err);
+            }
+        }
+
+        if !shutdown_errors.errors.is_empty() {
+            return Err(shutdown_errors);
+        }
+
+        Ok(())
+    }
+
+    fn shutdown_connections_by_cert_serial(
+        &mut self,
+        cert_serial: &str,
+    ) -> Result<(), ShutdownErrors> {
+        let mut shutdown_errors = ShutdownErrors::new(None);
+
+        self.service_proxy_visitors
+            .iter()
+            .for_each(|(proxy_service_id, proxy_visitor)| {
+                if let Err(err) = proxy_visitor
+                    .lock()
+                    .unwrap()
+                    .shutdown_connections_by_cert_serial(
+                        self.clone_proxy_tasks_sender(),
+                        cert_serial,
+                    )
+                {
+                    shutdown_errors.add_error(*proxy_service_id, err);
+                }
+            });
+
+        let reverse_proxy_keys = self.get_reverse_proxy_keys(|_, reverse_proxy| {
+            reverse_proxy.cert_serial.as_deref() == Some(cert_serial)
+        });
+        for proxy_key in reverse_proxy_keys {
+            if let Err(err) = self.shutdown_reverse_proxy(&proxy_key) {
+                shutdown_errors.add_error(proxy_key.get_service_id(), err);
+            }
+        }
+
+        if !shutdown_errors.errors.is_empty() {
+            return Err(shutdown_errors);
+        }
+
+        info(
+            &target!(),
+            &format!(
+                "Service proxy connections shutdown for certificate: serial={}",
+                cert_serial
+            ),
+        );
+
+        Ok(())
+    }
+
+    fn stop_listeners(&mut self) {
+        for service_proxy in self.service_proxies.values() {
+            service_proxy.lock().unwrap().shutdown();
+        }
+    }
+
+    fn get_active_proxy_count(&self) -> usize {
+        self.services_by_proxy_key.len()
+    }
+
+    fn on_closed_proxy(&mut self, proxy_key: &ProxyKey) {
+        if !self.remove_reverse_proxy(proxy_key) {
+            let service_id = self
+                .get_service_id_by_proxy_key(proxy_key)
+                .unwrap_or(u64::MAX);
+            self.unused_service_reservations.remove(&service_id);
+            if let Some(proxy_visitor) = self.get_service_proxy(service_id) {
+                proxy_visitor
+                    .lock()
+                    .unwrap()
+                    .remove_proxy_for_key(proxy_key);
+            }
+        }
+
+        self.app_config.metrics_sink.set_gauge(
+            METRIC_PROXIES_ACTIVE,
+            self.services_by_proxy_key.len() as i64,
+        );
+    }
+
+    fn reconcile_proxy_keys(&mut self) -> usize {
+        // Snapshot store (visitors access the store while locked themselves, so don't hold it while checking them)
+        let tracked_proxy_keys: Vec<(ProxyKey, u64)> = self.services_by_proxy_key.get_all();
+
+        let stale_proxy_keys: Vec<ProxyKey> = tracked_proxy_keys
+            .into_iter()
+            .filter(|(proxy_key, service_id)| {
+                if self.reverse_proxy_keys.contains_key(proxy_key) {
+                    return false;
+                }
+                match self.service_proxy_visitors.get(service_id) {
+                    Some(proxy_visitor) => {
+                        !proxy_visitor.lock().unwrap().has_proxy_for_key(proxy_key)
+                    }
+                    None => true,
+                }
+            })
+            .map(|(proxy_key, _)| proxy_key)
+            .collect();
+
+        let proxy_keys_len = {
+            let mut user_byte_quotas = self.app_config.user_byte_quotas.lock().unwrap();
+            for proxy_key in &stale_proxy_keys {
+                self.services_by_proxy_key.remove(proxy_key);
+                user_byte_quotas.unregister_proxy(proxy_key);
+            }
+            self.services_by_proxy_key.len()
+        };
+
+        if !stale_proxy_keys.is_empty() {
+            warn(
+                &target!(),
+                &format!(
+                    "Removed stale proxy keys: count={}, keys={:?}",
+                    stale_proxy_keys.len(),
+                    stale_proxy_keys
+                        .iter()
+                        .map(|proxy_key| proxy_key.to_string())
+                        .collect::<Vec<String>>()
+                ),
+            );
+        }
+
+        if proxy_keys_len > self.app_config.max_proxy_keys {
+            warn(
+                &target!(),
+                &format!(
+                    "Tracked proxy keys exceeds maximum: count={}, max={}",
+                    proxy_keys_len, self.app_config.max_proxy_keys
+                ),
+            );
+        }
+
+        self.app_config
+            .metrics_sink
+            .set_gauge(METRIC_PROXIES_ACTIVE, proxy_keys_len as i64);
+
+        stale_proxy_keys.len()
+    }
+
+    fn reclaim_unused_services(&mut self, now: Instant) -> Vec<u64> {
+        // Services with (tracked) proxy connections are no longer considered unused
+        for (_, service_id) in self.services_by_proxy_key.get_all() {
+            self.unused_service_reservations.remove(&service_id);
+        }
+
+        let reservation_ttl = self.app_config.service_reservation_ttl;
+        let mut expired_service_ids: Vec<u64> = self
+            .unused_service_reservations
+            .iter()
+            .filter(|(_, started_at)| {
+                now.saturating_duration_since(**started_at) >= reservation_ttl
+            })
+            .map(|(service_id, _)| *service_id)
+            .collect();
+        expired_service_ids.sort();
+
+        for service_id in &expired_service_ids {
+            self.unused_service_reservations.remove(service_id);
+            self.service_proxy_visitors.remove(service_id);
+            if let Some(service_proxy) = self.service_proxies.remove(service_id) {
+                service_proxy.lock().unwrap().shutdown();
+            }
+            self.app_config.service_activity.remove_service(*service_id);
+            self.app_config
+                .service_throughput
+                .remove_service(*service_id);
+
+            let service_port = self.service_ports.remove(service_id);
+            if self.shared_service_port.is_none() && !self.ephemeral_service_ports {
+                self.free_service_ports.extend(service_port);
+                self.persisted_service_ports.remove(service_id);
+                self.save_service_ports();
+            }
+
+            info(
+                &target!(),
+                &format!(
+                    "Reclaimed unused service proxy: svc_id={}, port={:?}",
+                    service_id, service_port
+                ),
+            );
+        }
+
+        expired_service_ids
+    }
+
+    fn shutdown_idle_connections(&mut self, now: Instant) -> Vec<ProxyKey> {
+        let mut idle_proxy_keys = vec![];
+
+        for (transport, idle_timeout) in [
+            (Transport::TCP, self.app_config.tcp_idle_timeout),
+            (Transport::UDP, self.app_config.udp_idle_timeout),
+        ] {
+            if idle_timeout.is_zero() {
+                continue;
+            }
+
+            for proxy_visitor in self.get_service_proxies_by_transport(transport) {
+                let mut proxy_visitor = proxy_visitor.lock().unwrap();
+
+                for proxy_key in proxy_visitor.get_idle_proxy_keys(now, idle_timeout) {
+                    match proxy_visitor
+                        .shutdown_connection(self.clone_proxy_tasks_sender(), &proxy_key)
+                    {
+                        Ok(()) => {
+                            info(
+                                &target!(),
+                                &format!(
+                                    "Idle service proxy connection shutdown: proxy_key={}, idle_timeout={:?}",
+                                    &proxy_key, idle_timeout
+                                ),
+                            );
+                            idle_proxy_keys.push(proxy_key);
+                        }
+                        Err(err) => error(
+                            &target!(),
+                            &format!(
+                                "Error shutting down idle service proxy connection: proxy_key={}, err={:?}",
+                                &proxy_key, err
+                            ),
+                        ),
+                    }
+                }
+            }
+        }
+
+        // Reverse proxies (relaying TCP services)
+        let tcp_idle_timeout = self.app_config.tcp_idle_timeout;
+        if !tcp_idle_timeout.is_zero() {
+            let service_activity = self.app_config.service_activity.clone();
+            let reverse_proxy_keys = self.get_reverse_proxy_keys(|proxy_key, reverse_proxy| {
+                let last_activity =
+                    service_activity.get_last_activity(proxy_key, reverse_proxy.started_at);
+                now.saturating_duration_since(last_activity) >= tcp_idle_timeout
+            });
+            for proxy_key in reverse_proxy_keys {
+                match self.shutdown_reverse_proxy(&proxy_key) {
+                    Ok(()) => idle_proxy_keys.push(proxy_key),
+                    Err(err) => error(
+                        &target!(),
+                        &format!(
+                            "Error shutting down idle reverse service proxy connection: proxy_key={}, err={:?}",
+                            &proxy_key, err
+                        ),
+                    ),
+                }
+            }
+        }
+
+        idle_proxy_keys.sort_by_key(|proxy_key| proxy_key.to_string());
+        idle_proxy_keys
+    }
+
+    fn add_reverse_session(
+        &mut self,
+        service_id: u64,
+        reverse_session: ReverseClientSession,
+    ) -> Result<(), AppError> {
+        let reverse_sessions = self.prune_reverse_sessions(service_id, Instant::now());
+
+        if reverse_sessions.len() >= MAX_REVERSE_SESSIONS_PER_SERVICE {
+            return Err(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0429_SERVICE_RATE_EXCEEDED,
+                format!(
+                    "Too many client sessions waiting for reverse proxy: svc_id={}, max={}",
+                    service_id, MAX_REVERSE_SESSIONS_PER_SERVICE
+                ),
+            ));
+        }
+
+        reverse_sessions.push_back(reverse_session);
+        Ok(())
+    }
+
+    fn take_reverse_session(
+        &mut self,
+        service_id: u64,
+    ) -> Result<(Service, ReverseClientSession), AppError> {
+        let service = self
+            .app_config
+            .service_repo
+            .lock()
+            .unwrap()
+            .get(service_id)?
+            .ok_or(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0423_INVALID_REQUEST,
+                format!("Unknown reverse proxy service: svc_id={}", service_id),
+            ))?;
+
+        if service.transport != Transport::TCP {
+            return Err(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0423_INVALID_REQUEST,
+                format!(
+                    "Reverse proxies require a TCP service: svc_id={}",
+                    service_id
+                ),
+            ));
+        }
+
+        let reverse_session = self
+            .prune_reverse_sessions(service_id, Instant::now())
+            .pop_front()
+            .ok_or(AppError::GenWithCodeAndMsg(
+                config::RESPCODE_0425_INACTIVE_SERVICE_PROXY,
+                format!(
+                    "No client session waiting for reverse proxy: svc_id={}",
+                    service_id
+                ),
+            ))?;
+
+        let connection_check = self
+            .user_active_services
+            .lock()
+            .unwrap()
+            .check_connection(reverse_session.user_id, service_id);
+        if let Err(err) = connection_check {
+            self.requeue_reverse_session(service_id, reverse_session);
+            return Err(err);
+        }
+
+        Ok((service, reverse_session))
+    }
+
+    fn requeue_reverse_session(&mut self, service_id: u64, reverse_session: ReverseClientSession) {
+        self.reverse_sessions
+            .entry(service_id)
+            .or_default()
+            .push_front(reverse_session);
+    }
+
+    fn open_reverse_proxy(
+        &mut self,
+        service: &Service,
+        reverse_session: ReverseClientSession,
+        upstream_stream: TcpStream,
+    ) -> Result<ProxyKey, AppError> {
+        let upstream_stream_copy = upstream_stream.try_clone().map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                format!(
+                    "Unable to clone upstream stream: upstream_stream={:?}",
+                    &upstream_stream
+                ),
+                Box::new(err),
+            )
+        })?;
+        reverse_session
+            .client_stream
+            .set_nonblocking(true)
+            .map_err(|err| {
+                AppError::GenWithMsgAndErr(
+                    "Failed making client session socket non-blocking".to_string(),
+                    Box::new(err),
+                )
+            })?;
+
+        // Relay (upstream as stream 1, client session as stream 2)
+
+        let proxy_key = ProxyKey::new(
+            ProxyType::TcpAndTcp,
+            service.service_id,
+            reverse_session.client_stream.peer_addr().ok(),
+            upstream_stream.peer_addr().ok(),
+        );
+
+        self.proxy_tasks_sender
+            .send(ProxyExecutorEvent::OpenTcpAndTcpProxy(
+                proxy_key.clone(),
+                (
+                    upstream_stream,
+                    reverse_session.client_stream,
+                    Arc::new(Mutex::new(Box::new(upstream_stream_copy))),
+                    reverse_session.client_reader_writer,
+                    self.proxy_events_sender.clone(),
+                    TcpProxyOptions {
+                        log_lifecycle: true,
+                        ..Default::default()
+                    },
+                ),
+            ))
+            .map_err(|err| {
+                AppError::General(format!(
+                    "Error while sending request for new reverse proxy: proxy_key={}, err={:?}",
+                    &proxy_key, &err
+                ))
+            })?;
+
+        // Track proxy (alongside the service proxies' connections)
+
+        self.services_by_proxy_key
+            .put(&proxy_key, service.service_id);
+        self.reverse_proxy_keys.insert(
+            proxy_key.clone(),
+            ReverseProxy {
+                user_id: reverse_session.user_id,
+                cert_serial: reverse_session.cert_serial,
+                started_at: Instant::now(),
+            },
+        );
+        self.app_config
+            .service_activity
+            .record_activity(&proxy_key, Instant::now());
+        self.app_config
+            .user_byte_quotas
+            .lock()
+            .unwrap()
+            .register_proxy(&proxy_key, reverse_session.user_id);
+        self.user_active_services
+            .lock()
+            .unwrap()
+            .register_proxy(&proxy_key, reverse_session.user_id);
+
+        info(
+            &target!(),
+            &format!(
+                "Reverse service proxy connected: proxy_stream={}, uid={}",
+                &proxy_key, reverse_session.user_id
+            ),
+        );
+
+        Ok(proxy_key)
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::config;
+    use crate::repository::access_repo::tests::MockAccessRepo;
+    use crate::repository::service_repo::tests::MockServiceRepo;
+    use crate::repository::user_repo::tests::MockUserRepo;
+    use crate::service::dns_cache::tests::MockHostResolv;
+    use crate::service::dns_cache::ServiceAddrsCache;
+    use crate::service::proxy::proxy_base::tests::{MockGwSvcProxy, MockGwSvcProxyVisitor};
+    use crate::testutils::CapturingMetricsSink;
+    use mockall::{mock, predicate};
+    use std::io::{Read, Write};
+    use std::net::{IpAddr, SocketAddr, TcpListener};
     use std::sync::mpsc;
+    use trust0_common::proxy::executor::ProxyExecutor;
 
     // mocks
     // =====
@@ -332,14 +1454,25 @@ pub mod tests {
     mock! {
         pub SvcMgr {}
         impl ServiceMgr for SvcMgr {
-            fn get_service_id_by_proxy_key(&self, proxy_key: &str) -> Option<u64>;
+            fn get_service_id_by_proxy_key(&self, proxy_key: &ProxyKey) -> Option<u64>;
             fn get_service_proxies(&self) -> Vec<Arc<Mutex<dyn GatewayServiceProxyVisitor>>>;
+            fn get_service_proxies_by_transport(&self, transport: Transport) -> Vec<Arc<Mutex<dyn GatewayServiceProxyVisitor>>>;
             fn get_service_proxy(&self, service_id: u64) -> Option<&'static Arc<Mutex<dyn GatewayServiceProxyVisitor>>>;
             fn clone_proxy_tasks_sender(&self) -> Sender<ProxyExecutorEvent>;
             fn startup(&mut self, service_mgr: Arc<Mutex<dyn ServiceMgr>>, service: &Service) -> Result<(Option<String>, u16), AppError>;
             fn has_proxy_for_user_and_service(&mut self, user_id: u64, service_id: u64) -> bool;
-            fn shutdown_connections(&mut self, user_id: Option<u64>, service_id: Option<u64>) -> Result<(), AppError>;
-            fn on_closed_proxy(&mut self, proxy_key: &str);
+            fn shutdown_connections(&mut self, user_id: Option<u64>, service_id: Option<u64>) -> Result<(), ShutdownErrors>;
+            fn shutdown_connections_by_cert_serial(&mut self, cert_serial: &str) -> Result<(), ShutdownErrors>;
+            fn stop_listeners(&mut self);
+            fn get_active_proxy_count(&self) -> usize;
+            fn on_closed_proxy(&mut self, proxy_key: &ProxyKey);
+            fn reconcile_proxy_keys(&mut self) -> usize;
+            fn reclaim_unused_services(&mut self, now: Instant) -> Vec<u64>;
+            fn shutdown_idle_connections(&mut self, now: Instant) -> Vec<ProxyKey>;
+            fn add_reverse_session(&mut self, service_id: u64, reverse_session: ReverseClientSession) -> Result<(), AppError>;
+            fn take_reverse_session(&mut self, service_id: u64) -> Result<(Service, ReverseClientSession), AppError>;
+            fn requeue_reverse_session(&mut self, service_id: u64, reverse_session: ReverseClientSession);
+            fn open_reverse_proxy(&mut self, service: &Service, reverse_session: ReverseClientSession, upstream_stream: TcpStream) -> Result<ProxyKey, AppError>;
         }
     }
 
@@ -351,6 +1484,13 @@ pub mod tests {
     const GATEWAY_DISTINCT_PORT_END: u16 = 4102;
 
     fn create_gw_service_mgr(use_shared_port: bool) -> GatewayServiceMgr {
+        create_gw_service_mgr_with_reservation_ttl(use_shared_port, Duration::ZERO)
+    }
+
+    fn create_gw_service_mgr_with_reservation_ttl(
+        use_shared_port: bool,
+        service_reservation_ttl: Duration,
+    ) -> GatewayServiceMgr {
         let mut app_config = config::tests::create_app_config_with_repos(
             Arc::new(Mutex::new(MockUserRepo::new())),
             Arc::new(Mutex::new(MockServiceRepo::new())),
@@ -358,6 +1498,7 @@ pub mod tests {
         )
         .unwrap();
         app_config.gateway_service_host = Some(GATEWAY_HOST.to_string());
+        app_config.service_reservation_ttl = service_reservation_ttl;
         if !use_shared_port {
             app_config.gateway_service_ports =
                 Some((GATEWAY_DISTINCT_PORT_START, GATEWAY_DISTINCT_PORT_END));
@@ -378,6 +1519,20 @@ pub mod tests {
             transport: Transport::TCP,
             host: "localhost".to_string(),
             port: 8200,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
         };
         let mut service_mgr = create_gw_service_mgr(true);
         service_mgr
@@ -425,6 +1580,20 @@ pub mod tests {
             transport: Transport::TCP,
             host: "localhost".to_string(),
             port: 8200,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
         };
         let mut service_mgr = create_gw_service_mgr(false);
         service_mgr.next_service_port = GATEWAY_DISTINCT_PORT_END + 1;
@@ -470,6 +1639,20 @@ pub mod tests {
             transport: Transport::TCP,
             host: "localhost".to_string(),
             port: 8200,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
         };
         let service_mgr = create_gw_service_mgr(true);
         let orig_svc_ports_len = service_mgr.service_ports.len();
@@ -515,6 +1698,20 @@ pub mod tests {
             transport: Transport::UDP,
             host: "localhost".to_string(),
             port: 8200,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
         };
         let service_mgr = create_gw_service_mgr(true);
         let orig_svc_ports_len = service_mgr.service_ports.len();
@@ -553,54 +1750,450 @@ pub mod tests {
     }
 
     #[test]
-    fn gwsvcmgr_has_proxy_for_user_and_service_when_valid_user_and_svc() {
-        let mut proxy_visitor = MockGwSvcProxyVisitor::new();
-        proxy_visitor
-            .expect_get_proxy_addrs_for_user()
-            .with(predicate::eq(100))
-            .times(1)
-            .return_once(move |_| vec![("addr1".to_string(), "addr2".to_string())]);
-        let mut service_mgr = create_gw_service_mgr(true);
-        service_mgr
-            .service_proxy_visitors
-            .insert(200, Arc::new(Mutex::new(proxy_visitor)));
-
-        assert!(service_mgr.has_proxy_for_user_and_service(100, 200));
-    }
+    fn gwsvcmgr_startup_when_not_proxyable_service() {
+        let mut service = Service::new(200, "Service200", &Transport::TCP, "localhost", 8200);
+        service.proxyable = false;
+        let service_mgr = Arc::new(Mutex::new(create_gw_service_mgr(true)));
 
-    #[test]
-    fn gwsvcmgr_has_proxy_for_user_and_service_when_invalid_user() {
-        let mut proxy_visitor = MockGwSvcProxyVisitor::new();
-        proxy_visitor
-            .expect_get_proxy_addrs_for_user()
-            .with(predicate::eq(101))
-            .times(1)
-            .return_once(move |_| vec![]);
-        let mut service_mgr = create_gw_service_mgr(true);
-        service_mgr
-            .service_proxy_visitors
-            .insert(200, Arc::new(Mutex::new(proxy_visitor)));
+        match service_mgr
+            .clone()
+            .lock()
+            .unwrap()
+            .startup(service_mgr.clone(), &service)
+        {
+            Ok((host, port)) => panic!(
+                "Unexpected successful startup result: host={:?}, port={}",
+                &host, port
+            ),
+            Err(err) => {
+                assert_eq!(err.get_code(), Some(config::RESPCODE_0403_FORBIDDEN));
+                assert!(err.to_string().contains("not proxyable"));
+            }
+        }
 
-        assert!(!service_mgr.has_proxy_for_user_and_service(101, 200));
+        let service_mgr = service_mgr.lock().unwrap();
+        assert!(!service_mgr.service_ports.contains_key(&200));
+        assert!(!service_mgr.service_proxies.contains_key(&200));
     }
 
     #[test]
-    fn gwsvcmgr_has_proxy_for_user_and_service_when_invalid_service() {
-        let mut proxy_visitor = MockGwSvcProxyVisitor::new();
-        proxy_visitor
-            .expect_get_proxy_addrs_for_user()
-            .with(predicate::always())
-            .never();
+    fn gwsvcmgr_startup_when_tcp_service_and_ephemeral_port() {
+        let service = Service {
+            service_id: 200,
+            name: "Service200".to_string(),
+            transport: Transport::TCP,
+            host: "localhost".to_string(),
+            port: 8200,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
+        };
         let mut service_mgr = create_gw_service_mgr(true);
-        service_mgr
-            .service_proxy_visitors
-            .insert(200, Arc::new(Mutex::new(proxy_visitor)));
-
-        assert!(!service_mgr.has_proxy_for_user_and_service(100, 201));
-    }
+        service_mgr.shared_service_port = None;
+        service_mgr.ephemeral_service_ports = true;
+        let service_mgr = Arc::new(Mutex::new(service_mgr));
 
-    #[test]
-    fn gwsvcmgr_shutdown_connections_when_no_service_or_user_given() {
+        let bound_port = match service_mgr
+            .clone()
+            .lock()
+            .unwrap()
+            .startup(service_mgr.clone(), &service)
+        {
+            Ok((host, port)) => {
+                assert!(host.is_some());
+                assert_eq!(host.unwrap(), GATEWAY_HOST.to_string());
+                assert_ne!(port, 0);
+                port
+            }
+            Err(err) => {
+                panic!("Unexpected startup result: err={:?}", &err);
+            }
+        };
+
+        let service_mgr = service_mgr.lock().unwrap();
+        assert_eq!(service_mgr.service_ports.get(&200), Some(&bound_port));
+        assert_eq!(
+            service_mgr
+                .service_proxy_visitors
+                .get(&200)
+                .unwrap()
+                .lock()
+                .unwrap()
+                .get_proxy_port(),
+            bound_port
+        );
+    }
+
+    #[test]
+    fn gwsvcmgr_startup_when_udp_service_and_ephemeral_port() {
+        let service = Service {
+            service_id: 200,
+            name: "Service200".to_string(),
+            transport: Transport::UDP,
+            host: "localhost".to_string(),
+            port: 8200,
+            relay_retries: 0,
+            forward_empty_datagrams: false,
+            allowed_client_cidrs: vec![],
+            dns_cache_ttl: None,
+            reauth_interval: None,
+            upstream_bind_addr: None,
+            fast_relay: false,
+            client_auth: None,
+            udp_target: None,
+            log_sample_rate: None,
+            max_response_bytes: None,
+            proxyable: true,
+            max_qps: None,
+            upstream_prefix: vec![],
+        };
+        let mut service_mgr = create_gw_service_mgr(true);
+        service_mgr.shared_service_port = None;
+        service_mgr.ephemeral_service_ports = true;
+        let service_mgr = Arc::new(Mutex::new(service_mgr));
+
+        let bound_port = match service_mgr
+            .clone()
+            .lock()
+            .unwrap()
+            .startup(service_mgr.clone(), &service)
+        {
+            Ok((host, port)) => {
+                assert!(host.is_some());
+                assert_eq!(host.unwrap(), GATEWAY_HOST.to_string());
+                assert_ne!(port, 0);
+                port
+            }
+            Err(err) => {
+                panic!("Unexpected startup result: err={:?}", &err);
+            }
+        };
+
+        let service_mgr = service_mgr.lock().unwrap();
+        assert_eq!(service_mgr.service_ports.get(&200), Some(&bound_port));
+        assert_eq!(
+            service_mgr
+                .service_proxy_visitors
+                .get(&200)
+                .unwrap()
+                .lock()
+                .unwrap()
+                .get_proxy_port(),
+            bound_port
+        );
+    }
+
+    #[test]
+    fn gwsvcmgr_startup_and_shutdown_when_small_worker_pool() {
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        app_config.gateway_service_host = Some(GATEWAY_HOST.to_string());
+        app_config.gateway_service_ephemeral_ports = true;
+        app_config.worker_threads = 1;
+        let service_mgr = Arc::new(Mutex::new(GatewayServiceMgr::new(
+            Arc::new(app_config),
+            mpsc::channel().0,
+            mpsc::channel().0,
+        )));
+
+        assert_eq!(
+            service_mgr.lock().unwrap().worker_pool.get_worker_count(),
+            1
+        );
+
+        for (service_id, transport) in [
+            (200, Transport::TCP),
+            (201, Transport::UDP),
+            (202, Transport::TCP),
+        ] {
+            let service = Service::new(
+                service_id,
+                &format!("Service{}", service_id),
+                &transport,
+                "localhost",
+                8200,
+            );
+            if let Err(err) = service_mgr
+                .clone()
+                .lock()
+                .unwrap()
+                .startup(service_mgr.clone(), &service)
+            {
+                panic!("Unexpected startup result: err={:?}", &err);
+            }
+        }
+
+        assert_eq!(
+            service_mgr
+                .lock()
+                .unwrap()
+                .worker_pool
+                .get_active_task_count(),
+            3
+        );
+
+        for service_proxy in service_mgr.lock().unwrap().service_proxies.values() {
+            service_proxy.lock().unwrap().shutdown();
+        }
+
+        let start = std::time::Instant::now();
+        while service_mgr
+            .lock()
+            .unwrap()
+            .worker_pool
+            .get_active_task_count()
+            > 0
+        {
+            if start.elapsed() > std::time::Duration::from_secs(5) {
+                panic!("Timed out waiting for service proxies to stop polling");
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn gwsvcmgr_startup_service_when_concurrent_first_startups() {
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        app_config.gateway_service_host = Some(GATEWAY_HOST.to_string());
+        app_config.gateway_service_ephemeral_ports = true;
+        let service_mgr = Arc::new(Mutex::new(GatewayServiceMgr::new(
+            Arc::new(app_config),
+            mpsc::channel().0,
+            mpsc::channel().0,
+        )));
+        let service = Service::new(200, "Service200", &Transport::TCP, "localhost", 8200);
+        let startup_barrier = Arc::new(std::sync::Barrier::new(2));
+
+        let startup_threads: Vec<_> = (0..2)
+            .map(|_| {
+                let service_mgr: Arc<Mutex<dyn ServiceMgr>> = service_mgr.clone();
+                let service = service.clone();
+                let startup_barrier = startup_barrier.clone();
+                std::thread::spawn(move || {
+                    startup_barrier.wait();
+                    GatewayServiceMgr::startup_service(&service_mgr, &service)
+                })
+            })
+            .collect();
+        let service_ports: Vec<u16> = startup_threads
+            .into_iter()
+            .map(|startup_thread| match startup_thread.join().unwrap() {
+                Ok((_, service_port)) => service_port,
+                Err(err) => panic!("Unexpected startup result: err={:?}", &err),
+            })
+            .collect();
+
+        assert_ne!(service_ports[0], 0);
+        assert_eq!(service_ports[0], service_ports[1]);
+        {
+            let service_mgr = service_mgr.lock().unwrap();
+            assert_eq!(service_mgr.service_ports.len(), 1);
+            assert_eq!(
+                service_mgr.service_ports.get(&200).copied(),
+                Some(service_ports[0])
+            );
+            assert_eq!(service_mgr.service_proxies.len(), 1);
+            assert_eq!(service_mgr.service_proxy_visitors.len(), 1);
+            assert_eq!(service_mgr.worker_pool.get_active_task_count(), 1);
+        }
+
+        for service_proxy in service_mgr.lock().unwrap().service_proxies.values() {
+            service_proxy.lock().unwrap().shutdown();
+        }
+    }
+
+    fn create_gw_service_mgr_with_state_file(
+        service_ports: (u16, u16),
+        state_file: &str,
+    ) -> Arc<Mutex<GatewayServiceMgr>> {
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        app_config.gateway_service_host = Some(GATEWAY_HOST.to_string());
+        app_config.gateway_service_ports = Some(service_ports);
+        app_config.service_ports_state_file = Some(state_file.to_string());
+
+        Arc::new(Mutex::new(GatewayServiceMgr::new(
+            Arc::new(app_config),
+            mpsc::channel().0,
+            mpsc::channel().0,
+        )))
+    }
+
+    fn startup_service_port(service_mgr: &Arc<Mutex<GatewayServiceMgr>>, service_id: u64) -> u16 {
+        let service = Service::new(
+            service_id,
+            &format!("Service{}", service_id),
+            &Transport::TCP,
+            "localhost",
+            8200,
+        );
+        match service_mgr
+            .lock()
+            .unwrap()
+            .startup(service_mgr.clone(), &service)
+        {
+            Ok((_, port)) => port,
+            Err(err) => panic!("Unexpected startup result: err={:?}", &err),
+        }
+    }
+
+    fn shutdown_gw_service_mgr(service_mgr: Arc<Mutex<GatewayServiceMgr>>) {
+        for service_proxy in service_mgr.lock().unwrap().service_proxies.values() {
+            service_proxy.lock().unwrap().shutdown();
+        }
+
+        let start = std::time::Instant::now();
+        while service_mgr
+            .lock()
+            .unwrap()
+            .worker_pool
+            .get_active_task_count()
+            > 0
+        {
+            if start.elapsed() > std::time::Duration::from_secs(5) {
+                panic!("Timed out waiting for service proxies to stop polling");
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        // proxies reference the manager, so release them explicitly to close their listeners
+        service_mgr.lock().unwrap().service_proxies.clear();
+    }
+
+    #[test]
+    fn gwsvcmgr_startup_when_service_ports_state_file_and_restart() {
+        let state_file = std::env::temp_dir().join(format!(
+            "trust0-gateway-service-ports-{}.json",
+            std::process::id()
+        ));
+        let state_file = state_file.to_str().unwrap().to_string();
+        let _ = fs::remove_file(&state_file);
+
+        let service_mgr = create_gw_service_mgr_with_state_file((47100, 47103), &state_file);
+        assert_eq!(startup_service_port(&service_mgr, 200), 47100);
+        assert_eq!(startup_service_port(&service_mgr, 201), 47101);
+        shutdown_gw_service_mgr(service_mgr);
+
+        let saved_ports: HashMap<u64, u16> =
+            serde_json::from_str(&fs::read_to_string(&state_file).unwrap()).unwrap();
+        assert_eq!(saved_ports, HashMap::from([(200, 47100), (201, 47101)]));
+
+        // restart, starting services in a different order (including a new service)
+        let service_mgr = create_gw_service_mgr_with_state_file((47100, 47103), &state_file);
+        assert_eq!(startup_service_port(&service_mgr, 202), 47102);
+        assert_eq!(startup_service_port(&service_mgr, 201), 47101);
+        assert_eq!(startup_service_port(&service_mgr, 200), 47100);
+        shutdown_gw_service_mgr(service_mgr);
+
+        let _ = fs::remove_file(&state_file);
+    }
+
+    #[test]
+    fn gwsvcmgr_take_prior_service_port_when_conflicts() {
+        let state_file = std::env::temp_dir().join(format!(
+            "trust0-gateway-service-ports-conflicts-{}.json",
+            std::process::id()
+        ));
+        let state_file = state_file.to_str().unwrap().to_string();
+        fs::write(
+            &state_file,
+            "{\"200\": 47200, \"201\": 47101, \"202\": 47102}",
+        )
+        .unwrap();
+
+        let service_mgr = create_gw_service_mgr_with_state_file((47100, 47103), &state_file);
+        let mut service_mgr = service_mgr.lock().unwrap();
+        service_mgr.service_ports.insert(203, 47102);
+
+        assert_eq!(service_mgr.take_prior_service_port(200), None);
+        assert_eq!(service_mgr.take_prior_service_port(201), Some(47101));
+        assert_eq!(service_mgr.take_prior_service_port(202), None);
+        assert_eq!(service_mgr.take_prior_service_port(204), None);
+        assert_eq!(
+            service_mgr.persisted_service_ports,
+            HashMap::from([(201, 47101)])
+        );
+        service_mgr.service_ports.insert(201, 47101);
+        assert_eq!(service_mgr.allocate_service_port().unwrap(), 47100);
+        assert_eq!(service_mgr.allocate_service_port().unwrap(), 47103);
+        assert!(service_mgr.allocate_service_port().is_err());
+
+        let _ = fs::remove_file(&state_file);
+    }
+
+    #[test]
+    fn gwsvcmgr_has_proxy_for_user_and_service_when_valid_user_and_svc() {
+        let mut proxy_visitor = MockGwSvcProxyVisitor::new();
+        proxy_visitor
+            .expect_get_proxy_addrs_for_user()
+            .with(predicate::eq(100))
+            .times(1)
+            .return_once(move |_| vec![("addr1".to_string(), "addr2".to_string())]);
+        let mut service_mgr = create_gw_service_mgr(true);
+        service_mgr
+            .service_proxy_visitors
+            .insert(200, Arc::new(Mutex::new(proxy_visitor)));
+
+        assert!(service_mgr.has_proxy_for_user_and_service(100, 200));
+    }
+
+    #[test]
+    fn gwsvcmgr_has_proxy_for_user_and_service_when_invalid_user() {
+        let mut proxy_visitor = MockGwSvcProxyVisitor::new();
+        proxy_visitor
+            .expect_get_proxy_addrs_for_user()
+            .with(predicate::eq(101))
+            .times(1)
+            .return_once(move |_| vec![]);
+        let mut service_mgr = create_gw_service_mgr(true);
+        service_mgr
+            .service_proxy_visitors
+            .insert(200, Arc::new(Mutex::new(proxy_visitor)));
+
+        assert!(!service_mgr.has_proxy_for_user_and_service(101, 200));
+    }
+
+    #[test]
+    fn gwsvcmgr_has_proxy_for_user_and_service_when_invalid_service() {
+        let mut proxy_visitor = MockGwSvcProxyVisitor::new();
+        proxy_visitor
+            .expect_get_proxy_addrs_for_user()
+            .with(predicate::always())
+            .never();
+        let mut service_mgr = create_gw_service_mgr(true);
+        service_mgr
+            .service_proxy_visitors
+            .insert(200, Arc::new(Mutex::new(proxy_visitor)));
+
+        assert!(!service_mgr.has_proxy_for_user_and_service(100, 201));
+    }
+
+    #[test]
+    fn gwsvcmgr_shutdown_connections_when_no_service_or_user_given() {
         let mut proxy200_visitor = MockGwSvcProxyVisitor::new();
         proxy200_visitor
             .expect_shutdown_connections()
@@ -683,23 +2276,1169 @@ pub mod tests {
     }
 
     #[test]
-    fn gwsvcmgr_on_closed_proxy_when_valid_proxy_key() {
-        let mut proxy_visitor = MockGwSvcProxyVisitor::new();
-        proxy_visitor
-            .expect_remove_proxy_for_key()
-            .with(predicate::eq("key200"))
+    fn gwsvcmgr_shutdown_connections_when_some_services_fail() {
+        let mut proxy200_visitor = MockGwSvcProxyVisitor::new();
+        proxy200_visitor
+            .expect_shutdown_connections()
+            .with(predicate::always(), predicate::eq(Some(100)))
             .times(1)
-            .return_once(move |_| true);
+            .return_once(move |_, _| Err(AppError::General("proxy200 failure".to_string())));
+        let mut proxy201_visitor = MockGwSvcProxyVisitor::new();
+        proxy201_visitor
+            .expect_shutdown_connections()
+            .with(predicate::always(), predicate::eq(Some(100)))
+            .times(1)
+            .return_once(move |_, _| Ok(()));
+        let mut proxy202_visitor = MockGwSvcProxyVisitor::new();
+        proxy202_visitor
+            .expect_shutdown_connections()
+            .with(predicate::always(), predicate::eq(Some(100)))
+            .times(1)
+            .return_once(move |_, _| Err(AppError::General("proxy202 failure".to_string())));
         let mut service_mgr = create_gw_service_mgr(true);
         service_mgr
-            .services_by_proxy_key
-            .lock()
-            .unwrap()
-            .insert("key200".to_string(), 200);
+            .service_proxy_visitors
+            .insert(200, Arc::new(Mutex::new(proxy200_visitor)));
         service_mgr
             .service_proxy_visitors
-            .insert(200, Arc::new(Mutex::new(proxy_visitor)));
+            .insert(201, Arc::new(Mutex::new(proxy201_visitor)));
+        service_mgr
+            .service_proxy_visitors
+            .insert(202, Arc::new(Mutex::new(proxy202_visitor)));
+
+        let result = service_mgr.shutdown_connections(Some(100), None);
+
+        let shutdown_errors = match result {
+            Ok(()) => panic!("Unexpected successful shutdown result"),
+            Err(shutdown_errors) => shutdown_errors,
+        };
+
+        assert_eq!(shutdown_errors.user_id, Some(100));
+        assert_eq!(shutdown_errors.get_failed_service_ids(), vec![200, 202]);
+        assert_eq!(
+            shutdown_errors.errors.get(&200).unwrap()[0].to_string(),
+            "proxy200 failure"
+        );
+        assert_eq!(
+            shutdown_errors.errors.get(&202).unwrap()[0].to_string(),
+            "proxy202 failure"
+        );
+        assert_eq!(
+            AppError::from(shutdown_errors).to_string(),
+            "Error shutting down services: user_id=Some(100), err(s)=Failed shutting down service proxy connection: svc_id=200, user_id=Some(100), err=General(\"proxy200 failure\"),Failed shutting down service proxy connection: svc_id=202, user_id=Some(100), err=General(\"proxy202 failure\")"
+        );
+    }
+
+    #[test]
+    fn gwsvcmgr_shutdown_connections_by_cert_serial_when_some_services_fail() {
+        let mut proxy200_visitor = MockGwSvcProxyVisitor::new();
+        proxy200_visitor
+            .expect_shutdown_connections_by_cert_serial()
+            .with(predicate::always(), predicate::eq("01:2c"))
+            .times(1)
+            .return_once(move |_, _| Ok(()));
+        let mut proxy201_visitor = MockGwSvcProxyVisitor::new();
+        proxy201_visitor
+            .expect_shutdown_connections_by_cert_serial()
+            .with(predicate::always(), predicate::eq("01:2c"))
+            .times(1)
+            .return_once(move |_, _| Err(AppError::General("proxy201 failure".to_string())));
+        let mut service_mgr = create_gw_service_mgr(true);
+        service_mgr
+            .service_proxy_visitors
+            .insert(200, Arc::new(Mutex::new(proxy200_visitor)));
+        service_mgr
+            .service_proxy_visitors
+            .insert(201, Arc::new(Mutex::new(proxy201_visitor)));
+
+        let result = service_mgr.shutdown_connections_by_cert_serial("01:2c");
+
+        let shutdown_errors = match result {
+            Ok(()) => panic!("Unexpected successful shutdown result"),
+            Err(shutdown_errors) => shutdown_errors,
+        };
+
+        assert_eq!(shutdown_errors.user_id, None);
+        assert_eq!(shutdown_errors.get_failed_service_ids(), vec![201]);
+    }
+
+    #[test]
+    fn gwsvcmgr_get_service_proxies_by_transport() {
+        let mut service_mgr = create_gw_service_mgr(true);
+        for (service_id, transport) in [
+            (200, Transport::TCP),
+            (201, Transport::UDP),
+            (202, Transport::TCP),
+        ] {
+            let mut proxy_visitor = MockGwSvcProxyVisitor::new();
+            proxy_visitor
+                .expect_get_service()
+                .return_const(Service::new(
+                    service_id,
+                    &format!("Service{}", service_id),
+                    &transport,
+                    "localhost",
+                    8000 + service_id as u16,
+                ));
+            service_mgr
+                .service_proxy_visitors
+                .insert(service_id, Arc::new(Mutex::new(proxy_visitor)));
+        }
+
+        let service_ids = |transport: Transport| {
+            let mut service_ids: Vec<u64> = service_mgr
+                .get_service_proxies_by_transport(transport)
+                .iter()
+                .map(|proxy_visitor| proxy_visitor.lock().unwrap().get_service().service_id)
+                .collect();
+            service_ids.sort();
+            service_ids
+        };
+
+        assert_eq!(service_ids(Transport::TCP), vec![200, 202]);
+        assert_eq!(service_ids(Transport::UDP), vec![201]);
+    }
+
+    #[test]
+    fn gwsvcmgr_get_service_proxies_by_transport_when_no_proxies() {
+        let service_mgr = create_gw_service_mgr(true);
+
+        assert!(service_mgr
+            .get_service_proxies_by_transport(Transport::TCP)
+            .is_empty());
+        assert!(service_mgr
+            .get_service_proxies_by_transport(Transport::UDP)
+            .is_empty());
+    }
+
+    #[test]
+    fn gwsvcmgr_on_closed_proxy_when_valid_proxy_key() {
+        let proxy_key = ProxyKey::new(ProxyType::TcpAndTcp, 200, None, None);
+        let mut proxy_visitor = MockGwSvcProxyVisitor::new();
+        proxy_visitor
+            .expect_remove_proxy_for_key()
+            .with(predicate::eq(proxy_key.clone()))
+            .times(1)
+            .return_once(move |_| true);
+        let mut service_mgr = create_gw_service_mgr(true);
+        service_mgr.services_by_proxy_key.put(&proxy_key, 200);
+        service_mgr
+            .service_proxy_visitors
+            .insert(200, Arc::new(Mutex::new(proxy_visitor)));
+
+        service_mgr.on_closed_proxy(&proxy_key);
+    }
+
+    #[test]
+    fn gwsvcmgr_stop_listeners_and_get_active_proxy_count() {
+        let proxy_key = ProxyKey::new(ProxyType::TcpAndTcp, 200, None, None);
+        let mut service_proxy = MockGwSvcProxy::new();
+        service_proxy.expect_shutdown().times(1).return_const(());
+        let mut service_mgr = create_gw_service_mgr(true);
+        service_mgr
+            .service_proxies
+            .insert(200, Arc::new(Mutex::new(service_proxy)));
+        service_mgr.services_by_proxy_key.put(&proxy_key, 200);
+
+        service_mgr.stop_listeners();
+
+        assert_eq!(service_mgr.get_active_proxy_count(), 1);
+        assert_eq!(service_mgr.service_proxies.len(), 1);
+    }
+
+    #[test]
+    fn gwsvcmgr_reconcile_proxy_keys_when_stale_and_active_keys() {
+        let active_proxy_key = ProxyKey::new(ProxyType::TcpAndTcp, 200, None, None);
+        let stale_proxy_key = ProxyKey::new(
+            ProxyType::TcpAndTcp,
+            200,
+            Some("127.0.0.1:4000".parse().unwrap()),
+            None,
+        );
+        let orphan_proxy_key = ProxyKey::new(ProxyType::TcpAndUdp, 201, None, None);
+        let mut proxy_visitor = MockGwSvcProxyVisitor::new();
+        let active_proxy_key_copy = active_proxy_key.clone();
+        proxy_visitor
+            .expect_has_proxy_for_key()
+            .times(2)
+            .returning(move |proxy_key| active_proxy_key_copy.eq(proxy_key));
+        let mut service_mgr = create_gw_service_mgr(true);
+        service_mgr
+            .services_by_proxy_key
+            .put(&active_proxy_key, 200);
+        service_mgr.services_by_proxy_key.put(&stale_proxy_key, 200);
+        service_mgr
+            .services_by_proxy_key
+            .put(&orphan_proxy_key, 201);
+        service_mgr
+            .service_proxy_visitors
+            .insert(200, Arc::new(Mutex::new(proxy_visitor)));
+
+        assert_eq!(service_mgr.reconcile_proxy_keys(), 2);
+
+        assert_eq!(service_mgr.services_by_proxy_key.len(), 1);
+        assert_eq!(
+            service_mgr.services_by_proxy_key.get(&active_proxy_key),
+            Some(200)
+        );
+    }
+
+    #[test]
+    fn gwsvcmgr_reclaim_unused_services_when_never_used_proxy() {
+        let started_at = Instant::now();
+        let mut service_proxy = MockGwSvcProxy::new();
+        service_proxy.expect_shutdown().times(1).return_const(());
+        let mut service_mgr =
+            create_gw_service_mgr_with_reservation_ttl(false, Duration::from_secs(300));
+        service_mgr
+            .service_ports
+            .insert(200, GATEWAY_DISTINCT_PORT_START);
+        service_mgr
+            .service_proxies
+            .insert(200, Arc::new(Mutex::new(service_proxy)));
+        service_mgr
+            .service_proxy_visitors
+            .insert(200, Arc::new(Mutex::new(MockGwSvcProxyVisitor::new())));
+        service_mgr
+            .unused_service_reservations
+            .insert(200, started_at);
+
+        assert!(service_mgr
+            .reclaim_unused_services(started_at + Duration::from_secs(299))
+            .is_empty());
+        assert_eq!(service_mgr.service_ports.len(), 1);
+
+        assert_eq!(
+            service_mgr.reclaim_unused_services(started_at + Duration::from_secs(300)),
+            vec![200]
+        );
+        assert!(service_mgr.service_ports.is_empty());
+        assert!(service_mgr.service_proxies.is_empty());
+        assert!(service_mgr.service_proxy_visitors.is_empty());
+        assert!(service_mgr.unused_service_reservations.is_empty());
+        assert_eq!(
+            service_mgr.free_service_ports,
+            vec![GATEWAY_DISTINCT_PORT_START]
+        );
+    }
+
+    #[test]
+    fn gwsvcmgr_reclaim_unused_services_when_used_proxy() {
+        let started_at = Instant::now();
+        let mut service_proxy = MockGwSvcProxy::new();
+        service_proxy.expect_shutdown().never();
+        let mut service_mgr =
+            create_gw_service_mgr_with_reservation_ttl(false, Duration::from_secs(300));
+        service_mgr
+            .service_ports
+            .insert(200, GATEWAY_DISTINCT_PORT_START);
+        service_mgr
+            .service_proxies
+            .insert(200, Arc::new(Mutex::new(service_proxy)));
+        service_mgr
+            .unused_service_reservations
+            .insert(200, started_at);
+        service_mgr
+            .services_by_proxy_key
+            .put(&ProxyKey::new(ProxyType::TcpAndTcp, 200, None, None), 200);
+
+        assert!(service_mgr
+            .reclaim_unused_services(started_at + Duration::from_secs(600))
+            .is_empty());
+        assert_eq!(service_mgr.service_ports.len(), 1);
+        assert!(service_mgr.unused_service_reservations.is_empty());
+        assert!(service_mgr.free_service_ports.is_empty());
+    }
+
+    fn create_gw_service_mgr_with_idle_timeouts(
+        tcp_idle_timeout: Duration,
+        udp_idle_timeout: Duration,
+    ) -> GatewayServiceMgr {
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        app_config.tcp_idle_timeout = tcp_idle_timeout;
+        app_config.udp_idle_timeout = udp_idle_timeout;
+
+        GatewayServiceMgr::new(Arc::new(app_config), mpsc::channel().0, mpsc::channel().0)
+    }
+
+    /// Add (mock) service proxy visitor with given connections (each as a client port and its last activity). Returns
+    /// the connections' proxy keys
+    fn add_idle_test_proxy_visitor(
+        service_mgr: &mut GatewayServiceMgr,
+        service_id: u64,
+        transport: Transport,
+        connections: Vec<(u16, Instant)>,
+        expected_shutdowns: usize,
+    ) -> Vec<ProxyKey> {
+        let proxy_type = match transport {
+            Transport::TCP => ProxyType::TcpAndTcp,
+            Transport::UDP => ProxyType::TcpAndUdp,
+        };
+        let connections: Vec<(ProxyKey, Instant)> = connections
+            .into_iter()
+            .map(|(client_port, last_activity)| {
+                (
+                    ProxyKey::new(
+                        proxy_type,
+                        service_id,
+                        Some(SocketAddr::from(([127, 0, 0, 1], client_port))),
+                        None,
+                    ),
+                    last_activity,
+                )
+            })
+            .collect();
+        let proxy_keys: Vec<ProxyKey> = connections
+            .iter()
+            .map(|(proxy_key, _)| proxy_key.clone())
+            .collect();
+
+        let service = Service::new(service_id, "Service", &transport, "localhost", 8200);
+        let mut proxy_visitor = MockGwSvcProxyVisitor::new();
+        proxy_visitor
+            .expect_get_service()
+            .returning(move || service.clone());
+        proxy_visitor
+            .expect_get_idle_proxy_keys()
+            .returning(move |now, idle_timeout| {
+                connections
+                    .iter()
+                    .filter(|(_, last_activity)| {
+                        now.saturating_duration_since(*last_activity) >= idle_timeout
+                    })
+                    .map(|(proxy_key, _)| proxy_key.clone())
+                    .collect()
+            });
+        proxy_visitor
+            .expect_shutdown_connection()
+            .times(expected_shutdowns)
+            .returning(|_, _| Ok(()));
+        proxy_visitor.expect_shutdown_connections().never();
+
+        service_mgr
+            .service_proxy_visitors
+            .insert(service_id, Arc::new(Mutex::new(proxy_visitor)));
+        for proxy_key in &proxy_keys {
+            service_mgr.services_by_proxy_key.put(proxy_key, service_id);
+        }
+
+        proxy_keys
+    }
+
+    #[test]
+    fn gwsvcmgr_shutdown_idle_connections_uses_transport_idle_timeout() {
+        let last_activity = Instant::now();
+        let mut service_mgr = create_gw_service_mgr_with_idle_timeouts(
+            Duration::from_secs(60),
+            Duration::from_secs(300),
+        );
+        let tcp_proxy_keys = add_idle_test_proxy_visitor(
+            &mut service_mgr,
+            200,
+            Transport::TCP,
+            vec![(41000, last_activity)],
+            1,
+        );
+        add_idle_test_proxy_visitor(
+            &mut service_mgr,
+            201,
+            Transport::UDP,
+            vec![(41001, last_activity)],
+            0,
+        );
+
+        assert!(service_mgr
+            .shutdown_idle_connections(last_activity + Duration::from_secs(59))
+            .is_empty());
+        assert_eq!(
+            service_mgr.shutdown_idle_connections(last_activity + Duration::from_secs(120)),
+            tcp_proxy_keys
+        );
+    }
+
+    #[test]
+    fn gwsvcmgr_shutdown_idle_connections_when_udp_timeout_reached() {
+        let last_activity = Instant::now();
+        let mut service_mgr = create_gw_service_mgr_with_idle_timeouts(
+            Duration::from_secs(600),
+            Duration::from_secs(30),
+        );
+        add_idle_test_proxy_visitor(
+            &mut service_mgr,
+            200,
+            Transport::TCP,
+            vec![(41000, last_activity)],
+            0,
+        );
+        let udp_proxy_keys = add_idle_test_proxy_visitor(
+            &mut service_mgr,
+            201,
+            Transport::UDP,
+            vec![(41001, last_activity)],
+            1,
+        );
+
+        assert_eq!(
+            service_mgr.shutdown_idle_connections(last_activity + Duration::from_secs(30)),
+            udp_proxy_keys
+        );
+    }
+
+    #[test]
+    fn gwsvcmgr_shutdown_idle_connections_closes_only_idle_connections() {
+        let last_activity = Instant::now();
+        let mut service_mgr = create_gw_service_mgr_with_idle_timeouts(
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+        );
+        let proxy_keys = add_idle_test_proxy_visitor(
+            &mut service_mgr,
+            200,
+            Transport::TCP,
+            vec![
+                (41000, last_activity),
+                (41001, last_activity + Duration::from_secs(45)),
+            ],
+            1,
+        );
+
+        assert_eq!(
+            service_mgr.shutdown_idle_connections(last_activity + Duration::from_secs(90)),
+            vec![proxy_keys[0].clone()]
+        );
+    }
+
+    #[test]
+    fn gwsvcmgr_shutdown_idle_connections_when_timeouts_disabled() {
+        let last_activity = Instant::now();
+        let mut service_mgr =
+            create_gw_service_mgr_with_idle_timeouts(Duration::ZERO, Duration::ZERO);
+        add_idle_test_proxy_visitor(
+            &mut service_mgr,
+            200,
+            Transport::TCP,
+            vec![(41000, last_activity)],
+            0,
+        );
+        add_idle_test_proxy_visitor(
+            &mut service_mgr,
+            201,
+            Transport::UDP,
+            vec![(41001, last_activity)],
+            0,
+        );
+
+        assert!(service_mgr
+            .shutdown_idle_connections(last_activity + Duration::from_secs(86400))
+            .is_empty());
+    }
+
+    #[test]
+    fn gwsvcmgr_poll_proxy_events_when_reconcile_interval_elapsed() {
+        let (proxy_events_sender, proxy_events_receiver) = mpsc::channel();
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr
+            .expect_reconcile_proxy_keys()
+            .times(1..)
+            .return_const(0usize);
+        service_mgr
+            .expect_reclaim_unused_services()
+            .times(1..)
+            .returning(|_| vec![]);
+        service_mgr
+            .expect_shutdown_idle_connections()
+            .times(1..)
+            .returning(|_| vec![]);
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(service_mgr));
+
+        let poll_handle = std::thread::spawn(move || {
+            GatewayServiceMgr::poll_proxy_events(
+                service_mgr,
+                proxy_events_receiver,
+                Duration::from_millis(10),
+            )
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        drop(proxy_events_sender);
+
+        if poll_handle.join().unwrap().is_ok() {
+            panic!("Unexpected successful poll result");
+        }
+    }
+
+    #[test]
+    fn gwsvcmgr_poll_proxy_events_when_shutdown_evt() {
+        let (proxy_events_sender, proxy_events_receiver) = mpsc::channel();
+        let proxy_key = ProxyKey::new(ProxyType::TcpAndTcp, 200, None, None);
+        let mut service_mgr = MockSvcMgr::new();
+        service_mgr
+            .expect_on_closed_proxy()
+            .with(predicate::eq(proxy_key.clone()))
+            .times(1)
+            .return_const(());
+        service_mgr.expect_reconcile_proxy_keys().never();
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(service_mgr));
+
+        proxy_events_sender
+            .send(ProxyEvent::Closed(proxy_key))
+            .unwrap();
+        proxy_events_sender.send(ProxyEvent::Shutdown).unwrap();
+
+        if let Err(err) = GatewayServiceMgr::poll_proxy_events(
+            service_mgr,
+            proxy_events_receiver,
+            Duration::from_secs(60),
+        ) {
+            panic!("Unexpected result: err={:?}", &err);
+        }
+    }
+
+    fn create_gw_service_mgr_for_reverse_proxy(
+        upstream_port: u16,
+        upstream_bind_addr: Option<IpAddr>,
+        proxy_tasks_sender: Sender<ProxyExecutorEvent>,
+    ) -> Arc<Mutex<GatewayServiceMgr>> {
+        create_gw_service_mgr_for_reverse_proxy_with_idle_timeout(
+            upstream_port,
+            upstream_bind_addr,
+            proxy_tasks_sender,
+            Duration::ZERO,
+        )
+    }
+
+    fn create_gw_service_mgr_for_reverse_proxy_with_idle_timeout(
+        upstream_port: u16,
+        upstream_bind_addr: Option<IpAddr>,
+        proxy_tasks_sender: Sender<ProxyExecutorEvent>,
+        tcp_idle_timeout: Duration,
+    ) -> Arc<Mutex<GatewayServiceMgr>> {
+        let mut service_repo = MockServiceRepo::new();
+        service_repo
+            .expect_get()
+            .with(predicate::eq(200))
+            .returning(move |_| {
+                Ok(Some(Service::new(
+                    200,
+                    "Service200",
+                    &Transport::TCP,
+                    "upstream1",
+                    upstream_port,
+                )))
+            });
+        let mut resolver = MockHostResolv::new();
+        resolver
+            .expect_resolve()
+            .with(predicate::eq("upstream1"))
+            .returning(|_| Ok(vec![IpAddr::from([127, 0, 0, 1])]));
+
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        app_config.service_addrs_cache = Arc::new(ServiceAddrsCache::new(
+            Arc::new(resolver),
+            Duration::ZERO,
+            Duration::ZERO,
+        ));
+        app_config.upstream_bind_addr = upstream_bind_addr;
+        app_config.tcp_idle_timeout = tcp_idle_timeout;
+
+        Arc::new(Mutex::new(GatewayServiceMgr::new(
+            Arc::new(app_config),
+            proxy_tasks_sender,
+            mpsc::channel().0,
+        )))
+    }
+
+    fn connect_reverse_proxy(
+        service_mgr: &Arc<Mutex<GatewayServiceMgr>>,
+        service_id: u64,
+    ) -> Result<ProxyKey, AppError> {
+        let app_config = service_mgr.lock().unwrap().app_config.clone();
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = service_mgr.clone();
+        GatewayServiceMgr::connect_reverse_proxy(&service_mgr, &app_config, service_id)
+    }
+
+    fn create_reverse_session(user_id: u64) -> (ReverseClientSession, TcpStream) {
+        let session_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client_stream = TcpStream::connect(session_listener.local_addr().unwrap()).unwrap();
+        let session_stream = session_listener.accept().unwrap().0;
+        let session_stream_copy = session_stream.try_clone().unwrap();
+
+        (
+            ReverseClientSession::new(
+                user_id,
+                session_stream,
+                Arc::new(Mutex::new(Box::new(session_stream_copy))),
+            ),
+            client_stream,
+        )
+    }
+
+    /// Connect reverse proxy (for a session with given user and certificate serial), returning its key. The proxy
+    /// open request is consumed from the given proxy tasks receiver
+    fn open_test_reverse_proxy(
+        service_mgr: &Arc<Mutex<GatewayServiceMgr>>,
+        proxy_tasks_receiver: &Receiver<ProxyExecutorEvent>,
+        user_id: u64,
+        cert_serial: &str,
+    ) -> (ProxyKey, TcpStream) {
+        let (mut reverse_session, client_stream) = create_reverse_session(user_id);
+        reverse_session.set_cert_serial(Some(cert_serial.to_string()));
+        service_mgr
+            .lock()
+            .unwrap()
+            .add_reverse_session(200, reverse_session)
+            .unwrap();
+
+        let proxy_key = connect_reverse_proxy(service_mgr, 200).unwrap();
+        match proxy_tasks_receiver.try_recv() {
+            Ok(ProxyExecutorEvent::OpenTcpAndTcpProxy(open_proxy_key, _)) => {
+                assert_eq!(open_proxy_key, proxy_key)
+            }
+            _ => panic!("Expected open TCP proxy request"),
+        }
+
+        (proxy_key, client_stream)
+    }
+
+    fn assert_reverse_proxy_closed(
+        service_mgr: &Arc<Mutex<GatewayServiceMgr>>,
+        proxy_tasks_receiver: &Receiver<ProxyExecutorEvent>,
+        proxy_key: &ProxyKey,
+    ) {
+        match proxy_tasks_receiver.try_recv() {
+            Ok(ProxyExecutorEvent::Close(close_proxy_key)) => {
+                assert_eq!(&close_proxy_key, proxy_key)
+            }
+            _ => panic!("Expected close proxy request"),
+        }
+
+        let service_mgr = service_mgr.lock().unwrap();
+        assert!(!service_mgr.reverse_proxy_keys.contains_key(proxy_key));
+        assert_eq!(service_mgr.get_active_proxy_count(), 0);
+        assert_eq!(
+            service_mgr
+                .user_active_services
+                .lock()
+                .unwrap()
+                .get_active_service_count(100),
+            0
+        );
+    }
+
+    #[test]
+    fn gwsvcmgr_connect_reverse_proxy_relays_upstream_and_client_session() {
+        let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut proxy_executor = ProxyExecutor::new();
+        let service_mgr = create_gw_service_mgr_for_reverse_proxy(
+            upstream_listener.local_addr().unwrap().port(),
+            None,
+            proxy_executor.clone_proxy_tasks_sender(),
+        );
+        std::thread::spawn(move || proxy_executor.poll_new_tasks());
+
+        let (reverse_session, mut client_stream) = create_reverse_session(100);
+        service_mgr
+            .lock()
+            .unwrap()
+            .add_reverse_session(200, reverse_session)
+            .unwrap();
+
+        let proxy_key = connect_reverse_proxy(&service_mgr, 200).unwrap();
+        let mut upstream_stream = upstream_listener.accept().unwrap().0;
+
+        assert_eq!(proxy_key.get_service_id(), 200);
+        {
+            let service_mgr = service_mgr.lock().unwrap();
+            assert!(service_mgr.reverse_proxy_keys.contains_key(&proxy_key));
+            assert_eq!(service_mgr.get_active_proxy_count(), 1);
+            assert_eq!(
+                service_mgr
+                    .user_active_services
+                    .lock()
+                    .unwrap()
+                    .get_active_service_count(100),
+                1
+            );
+        }
+
+        upstream_stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        client_stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut buffer = [0u8; 5];
+
+        upstream_stream.write_all(b"hello").unwrap();
+        client_stream.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"hello");
+
+        client_stream.write_all(b"world").unwrap();
+        upstream_stream.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"world");
+    }
+
+    #[test]
+    fn gwsvcmgr_connect_reverse_proxy_when_upstream_bind_addr() {
+        let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream_bind_addr = IpAddr::from([127, 0, 0, 2]);
+        let (proxy_tasks_sender, proxy_tasks_receiver) = mpsc::channel();
+        let service_mgr = create_gw_service_mgr_for_reverse_proxy(
+            upstream_listener.local_addr().unwrap().port(),
+            Some(upstream_bind_addr),
+            proxy_tasks_sender,
+        );
+
+        let (reverse_session, _client_stream) = create_reverse_session(100);
+        service_mgr
+            .lock()
+            .unwrap()
+            .add_reverse_session(200, reverse_session)
+            .unwrap();
+
+        connect_reverse_proxy(&service_mgr, 200).unwrap();
+        let (_upstream_stream, upstream_peer_addr) = upstream_listener.accept().unwrap();
+
+        assert_eq!(upstream_peer_addr.ip(), upstream_bind_addr);
+        assert!(proxy_tasks_receiver.try_recv().is_ok());
+    }
+
+    #[test]
+    fn gwsvcmgr_connect_reverse_proxy_when_no_waiting_session() {
+        let (proxy_tasks_sender, proxy_tasks_receiver) = mpsc::channel();
+        let service_mgr = create_gw_service_mgr_for_reverse_proxy(8200, None, proxy_tasks_sender);
+
+        match connect_reverse_proxy(&service_mgr, 200) {
+            Err(err) => assert_eq!(
+                err.get_code(),
+                Some(config::RESPCODE_0425_INACTIVE_SERVICE_PROXY)
+            ),
+            Ok(proxy_key) => panic!("Unexpected successful result: key={}", proxy_key),
+        }
+        assert!(proxy_tasks_receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn gwsvcmgr_connect_reverse_proxy_when_upstream_unreachable() {
+        let upstream_port = {
+            let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            upstream_listener.local_addr().unwrap().port()
+        };
+        let (proxy_tasks_sender, proxy_tasks_receiver) = mpsc::channel();
+        let service_mgr =
+            create_gw_service_mgr_for_reverse_proxy(upstream_port, None, proxy_tasks_sender);
+
+        let (reverse_session, _client_stream) = create_reverse_session(100);
+        service_mgr
+            .lock()
+            .unwrap()
+            .add_reverse_session(200, reverse_session)
+            .unwrap();
+
+        assert!(connect_reverse_proxy(&service_mgr, 200).is_err());
+        assert_eq!(
+            service_mgr
+                .lock()
+                .unwrap()
+                .reverse_sessions
+                .get(&200)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(proxy_tasks_receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn gwsvcmgr_add_reverse_session_when_service_queue_full() {
+        let (proxy_tasks_sender, _proxy_tasks_receiver) = mpsc::channel();
+        let service_mgr = create_gw_service_mgr_for_reverse_proxy(8200, None, proxy_tasks_sender);
+        let mut client_streams = vec![];
+
+        for _ in 0..MAX_REVERSE_SESSIONS_PER_SERVICE {
+            let (reverse_session, client_stream) = create_reverse_session(100);
+            client_streams.push(client_stream);
+            service_mgr
+                .lock()
+                .unwrap()
+                .add_reverse_session(200, reverse_session)
+                .unwrap();
+        }
+
+        let (reverse_session, _client_stream) = create_reverse_session(100);
+        match service_mgr
+            .lock()
+            .unwrap()
+            .add_reverse_session(200, reverse_session)
+        {
+            Err(err) => assert_eq!(
+                err.get_code(),
+                Some(config::RESPCODE_0429_SERVICE_RATE_EXCEEDED)
+            ),
+            Ok(()) => panic!("Unexpected successful result"),
+        }
+
+        let (reverse_session, _client_stream) = create_reverse_session(100);
+        service_mgr
+            .lock()
+            .unwrap()
+            .add_reverse_session(201, reverse_session)
+            .unwrap();
+    }
+
+    #[test]
+    fn gwsvcmgr_connect_reverse_proxy_when_waiting_sessions_disconnected_or_expired() {
+        let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (proxy_tasks_sender, proxy_tasks_receiver) = mpsc::channel();
+        let service_mgr = create_gw_service_mgr_for_reverse_proxy(
+            upstream_listener.local_addr().unwrap().port(),
+            None,
+            proxy_tasks_sender,
+        );
+
+        let (disconnected_session, disconnected_client_stream) = create_reverse_session(100);
+        drop(disconnected_client_stream);
+        let (mut expired_session, _expired_client_stream) = create_reverse_session(100);
+        expired_session.queued_at = Instant::now() - REVERSE_SESSION_TTL;
+        let (active_session, _active_client_stream) = create_reverse_session(100);
+        let active_session_addr = active_session.client_stream.peer_addr().unwrap();
+        for reverse_session in [disconnected_session, expired_session, active_session] {
+            service_mgr
+                .lock()
+                .unwrap()
+                .reverse_sessions
+                .entry(200)
+                .or_default()
+                .push_back(reverse_session);
+        }
+
+        let proxy_key = connect_reverse_proxy(&service_mgr, 200).unwrap();
+
+        assert_eq!(proxy_key.get_client_addr(), Some(active_session_addr));
+        assert!(service_mgr.lock().unwrap().reverse_sessions[&200].is_empty());
+        assert!(proxy_tasks_receiver.try_recv().is_ok());
+    }
+
+    #[test]
+    fn gwsvcmgr_connect_reverse_proxy_dials_upstream_without_service_mgr_lock() {
+        let mut service_mgr = MockSvcMgr::new();
+        let (reverse_session, _client_stream) = create_reverse_session(100);
+        service_mgr
+            .expect_take_reverse_session()
+            .with(predicate::eq(200))
+            .times(1)
+            .return_once(move |_| {
+                Ok((
+                    Service::new(200, "Service200", &Transport::TCP, "upstream1", 8200),
+                    reverse_session,
+                ))
+            });
+        service_mgr
+            .expect_requeue_reverse_session()
+            .with(predicate::eq(200), predicate::always())
+            .times(1)
+            .return_const(());
+        service_mgr.expect_open_reverse_proxy().never();
+        let service_mgr: Arc<Mutex<dyn ServiceMgr>> = Arc::new(Mutex::new(service_mgr));
+
+        // Resolution (part of the upstream dial) checks that the service manager isn't locked
+        let mut resolver = MockHostResolv::new();
+        let service_mgr_copy = service_mgr.clone();
+        resolver
+            .expect_resolve()
+            .with(predicate::eq("upstream1"))
+            .times(1)
+            .returning(move |_| {
+                assert!(service_mgr_copy.try_lock().is_ok());
+                Err(AppError::General("unresolvable".to_string()))
+            });
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        app_config.service_addrs_cache = Arc::new(ServiceAddrsCache::new(
+            Arc::new(resolver),
+            Duration::ZERO,
+            Duration::ZERO,
+        ));
+
+        assert!(GatewayServiceMgr::connect_reverse_proxy(&service_mgr, &app_config, 200).is_err());
+    }
+
+    #[test]
+    fn gwsvcmgr_on_closed_proxy_when_reverse_proxy() {
+        let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (proxy_tasks_sender, proxy_tasks_receiver) = mpsc::channel();
+        let service_mgr = create_gw_service_mgr_for_reverse_proxy(
+            upstream_listener.local_addr().unwrap().port(),
+            None,
+            proxy_tasks_sender,
+        );
+
+        let (proxy_key, _client_stream) =
+            open_test_reverse_proxy(&service_mgr, &proxy_tasks_receiver, 100, "01:2c");
+
+        let mut service_mgr = service_mgr.lock().unwrap();
+        assert_eq!(service_mgr.reconcile_proxy_keys(), 0);
+        assert_eq!(service_mgr.get_active_proxy_count(), 1);
+
+        service_mgr.on_closed_proxy(&proxy_key);
+
+        assert!(service_mgr.reverse_proxy_keys.is_empty());
+        assert_eq!(service_mgr.get_active_proxy_count(), 0);
+        assert_eq!(
+            service_mgr
+                .user_active_services
+                .lock()
+                .unwrap()
+                .get_active_service_count(100),
+            0
+        );
+    }
+
+    #[test]
+    fn gwsvcmgr_shutdown_connections_when_reverse_proxy() {
+        let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (proxy_tasks_sender, proxy_tasks_receiver) = mpsc::channel();
+        let service_mgr = create_gw_service_mgr_for_reverse_proxy(
+            upstream_listener.local_addr().unwrap().port(),
+            None,
+            proxy_tasks_sender,
+        );
+        let (proxy_key, _client_stream) =
+            open_test_reverse_proxy(&service_mgr, &proxy_tasks_receiver, 100, "01:2c");
+
+        service_mgr
+            .lock()
+            .unwrap()
+            .shutdown_connections(Some(101), None)
+            .unwrap();
+        assert!(proxy_tasks_receiver.try_recv().is_err());
+
+        service_mgr
+            .lock()
+            .unwrap()
+            .shutdown_connections(Some(100), Some(200))
+            .unwrap();
+        assert_reverse_proxy_closed(&service_mgr, &proxy_tasks_receiver, &proxy_key);
+    }
+
+    #[test]
+    fn gwsvcmgr_shutdown_connections_when_visitor_and_reverse_proxy_fail() {
+        let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (proxy_tasks_sender, proxy_tasks_receiver) = mpsc::channel();
+        let service_mgr = create_gw_service_mgr_for_reverse_proxy(
+            upstream_listener.local_addr().unwrap().port(),
+            None,
+            proxy_tasks_sender,
+        );
+        let (_proxy_key, _client_stream) =
+            open_test_reverse_proxy(&service_mgr, &proxy_tasks_receiver, 100, "01:2c");
+        let mut proxy200_visitor = MockGwSvcProxyVisitor::new();
+        proxy200_visitor
+            .expect_shutdown_connections()
+            .with(predicate::always(), predicate::eq(Some(100)))
+            .times(1)
+            .return_once(move |_, _| Err(AppError::General("proxy200 failure".to_string())));
+        service_mgr
+            .lock()
+            .unwrap()
+            .service_proxy_visitors
+            .insert(200, Arc::new(Mutex::new(proxy200_visitor)));
+
+        // Reverse proxy close request fails to send
+        drop(proxy_tasks_receiver);
+
+        let result = service_mgr
+            .lock()
+            .unwrap()
+            .shutdown_connections(Some(100), None);
+
+        let shutdown_errors = match result {
+            Ok(()) => panic!("Unexpected successful shutdown result"),
+            Err(shutdown_errors) => shutdown_errors,
+        };
+
+        assert_eq!(shutdown_errors.get_failed_service_ids(), vec![200]);
+        let service_errors = shutdown_errors.errors.get(&200).unwrap();
+        assert_eq!(service_errors.len(), 2);
+        assert_eq!(service_errors[0].to_string(), "proxy200 failure");
+        assert!(service_errors[1]
+            .to_string()
+            .contains("close a reverse proxy connection"));
+    }
+
+    #[test]
+    fn gwsvcmgr_shutdown_connections_by_cert_serial_when_reverse_proxy() {
+        let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (proxy_tasks_sender, proxy_tasks_receiver) = mpsc::channel();
+        let service_mgr = create_gw_service_mgr_for_reverse_proxy(
+            upstream_listener.local_addr().unwrap().port(),
+            None,
+            proxy_tasks_sender,
+        );
+        let (proxy_key, _client_stream) =
+            open_test_reverse_proxy(&service_mgr, &proxy_tasks_receiver, 100, "01:2c");
+
+        service_mgr
+            .lock()
+            .unwrap()
+            .shutdown_connections_by_cert_serial("01:2d")
+            .unwrap();
+        assert!(proxy_tasks_receiver.try_recv().is_err());
+
+        service_mgr
+            .lock()
+            .unwrap()
+            .shutdown_connections_by_cert_serial("01:2c")
+            .unwrap();
+        assert_reverse_proxy_closed(&service_mgr, &proxy_tasks_receiver, &proxy_key);
+    }
+
+    #[test]
+    fn gwsvcmgr_shutdown_idle_connections_when_reverse_proxy() {
+        let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (proxy_tasks_sender, proxy_tasks_receiver) = mpsc::channel();
+        let service_mgr = create_gw_service_mgr_for_reverse_proxy_with_idle_timeout(
+            upstream_listener.local_addr().unwrap().port(),
+            None,
+            proxy_tasks_sender,
+            Duration::from_secs(60),
+        );
+        let (proxy_key, _client_stream) =
+            open_test_reverse_proxy(&service_mgr, &proxy_tasks_receiver, 100, "01:2c");
+        let now = Instant::now();
+
+        assert!(service_mgr
+            .lock()
+            .unwrap()
+            .shutdown_idle_connections(now)
+            .is_empty());
+        assert!(proxy_tasks_receiver.try_recv().is_err());
+
+        assert_eq!(
+            service_mgr
+                .lock()
+                .unwrap()
+                .shutdown_idle_connections(now + Duration::from_secs(61)),
+            vec![proxy_key.clone()]
+        );
+        assert_reverse_proxy_closed(&service_mgr, &proxy_tasks_receiver, &proxy_key);
+    }
+
+    // UserActiveServices tests
+    // ========================
+
+    fn create_user_proxy_key(service_id: u64, client_port: u16) -> ProxyKey {
+        ProxyKey::new(
+            ProxyType::TcpAndTcp,
+            service_id,
+            Some(format!("127.0.0.1:{}", client_port).parse().unwrap()),
+            None,
+        )
+    }
+
+    #[test]
+    fn useractivesvcs_check_connection_when_unlimited() {
+        let mut user_active_services = UserActiveServices::new(None);
+        for service_id in 200..210 {
+            assert!(user_active_services
+                .check_connection(100, service_id)
+                .is_ok());
+            user_active_services.register_proxy(&create_user_proxy_key(service_id, 5000), 100);
+        }
+
+        assert_eq!(user_active_services.get_active_service_count(100), 10);
+    }
+
+    #[test]
+    fn useractivesvcs_check_connection_when_cap_reached_and_service_closed() {
+        let mut user_active_services = UserActiveServices::new(Some(2));
+
+        assert!(user_active_services.check_connection(100, 200).is_ok());
+        user_active_services.register_proxy(&create_user_proxy_key(200, 5000), 100);
+        user_active_services.register_proxy(&create_user_proxy_key(200, 5001), 100);
+        assert!(user_active_services.check_connection(100, 201).is_ok());
+        user_active_services.register_proxy(&create_user_proxy_key(201, 5002), 100);
+
+        assert_eq!(user_active_services.get_active_service_count(100), 2);
+        match user_active_services.check_connection(100, 202) {
+            Err(err) => assert_eq!(err.get_code(), Some(config::RESPCODE_0403_FORBIDDEN)),
+            Ok(()) => panic!("Unexpected successful result"),
+        }
+        assert!(user_active_services.check_connection(100, 200).is_ok());
+        assert!(user_active_services.check_connection(101, 202).is_ok());
+
+        user_active_services.unregister_proxy(&create_user_proxy_key(200, 5000));
+        assert_eq!(user_active_services.get_active_service_count(100), 2);
+        assert!(user_active_services.check_connection(100, 202).is_err());
+
+        user_active_services.unregister_proxy(&create_user_proxy_key(200, 5001));
+        assert_eq!(user_active_services.get_active_service_count(100), 1);
+        assert!(user_active_services.check_connection(100, 202).is_ok());
+    }
+
+    #[test]
+    fn useractivesvcs_register_proxy_when_duplicate_proxy_key() {
+        let mut user_active_services = UserActiveServices::new(Some(1));
+        let proxy_key = create_user_proxy_key(200, 5000);
+
+        user_active_services.register_proxy(&proxy_key, 100);
+        user_active_services.register_proxy(&proxy_key, 100);
+        user_active_services.unregister_proxy(&proxy_key);
+        user_active_services.unregister_proxy(&proxy_key);
+
+        assert_eq!(user_active_services.get_active_service_count(100), 0);
+        assert!(user_active_services.check_connection(100, 201).is_ok());
+    }
+
+    #[test]
+    fn useractivesvcs_register_and_unregister_proxy_when_session_metrics() {
+        let metrics_sink = Arc::new(CapturingMetricsSink::default());
+        let mut user_active_services = UserActiveServices::new(None);
+        user_active_services.set_session_metrics_sink(Some(metrics_sink.clone()));
+
+        user_active_services.register_proxy(&create_user_proxy_key(200, 5000), 100);
+        user_active_services.register_proxy(&create_user_proxy_key(201, 5001), 100);
+        user_active_services.register_proxy(&create_user_proxy_key(200, 5002), 101);
+        assert_eq!(user_active_services.get_active_session_count(100), 2);
+
+        user_active_services.unregister_proxy(&create_user_proxy_key(200, 5000));
+        user_active_services.unregister_proxy(&create_user_proxy_key(201, 5001));
+        user_active_services.unregister_proxy(&create_user_proxy_key(201, 5001));
+        assert_eq!(user_active_services.get_active_session_count(100), 0);
+
+        assert_eq!(
+            *metrics_sink.metrics.lock().unwrap(),
+            vec![
+                ("sessions.active.user.100".to_string(), 1),
+                ("sessions.active.user.100".to_string(), 2),
+                ("sessions.active.user.101".to_string(), 1),
+                ("sessions.active.user.100".to_string(), 1),
+                ("sessions.active.user.100".to_string(), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn useractivesvcs_register_proxy_when_no_session_metrics() {
+        let mut user_active_services = UserActiveServices::new(None);
+
+        user_active_services.register_proxy(&create_user_proxy_key(200, 5000), 100);
+        user_active_services.register_proxy(&create_user_proxy_key(200, 5001), 100);
 
-        service_mgr.on_closed_proxy("key200");
+        assert_eq!(user_active_services.get_active_session_count(100), 2);
+        assert_eq!(user_active_services.get_active_session_count(101), 0);
     }
 }
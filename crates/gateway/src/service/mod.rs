@@ -1,2 +1,9 @@
+pub mod activity;
+pub mod circuit_breaker;
+pub mod dns_cache;
 pub mod manager;
 pub mod proxy;
+pub mod quota;
+pub mod rate_limit;
+pub mod reauth;
+pub mod throughput;
@@ -1,4 +1,7 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 
@@ -6,15 +9,53 @@ use trust0_common::error::AppError;
 use trust0_common::model::service::Service;
 use trust0_common::net::tls_server::server_std;
 use trust0_common::proxy::executor::ProxyExecutorEvent;
+use trust0_common::proxy::proxy_key::ProxyKey;
 
 /// Represents the gateway and client proxy stream addresses respectively for a connected proxy
 pub type ProxyAddrs = (String, String);
 
+/// Active proxy connection for a user
+#[derive(Clone, Debug)]
+pub struct ProxySession {
+    pub proxy_key: ProxyKey,
+    pub proxy_addrs: ProxyAddrs,
+    pub started_at: Instant,
+}
+
+impl ProxySession {
+    /// Opaque session handle (derived from the proxy key), which users may use to refer to the session
+    pub fn handle(&self) -> String {
+        Self::create_handle(&self.proxy_key)
+    }
+
+    /// Opaque session handle for given proxy key
+    pub fn create_handle(proxy_key: &ProxyKey) -> String {
+        let mut hasher = DefaultHasher::new();
+        proxy_key.to_string().hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+/// Live connection counts for a service proxy (opened/closed counts are since the proxy was created)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProxyStats {
+    pub active_connections: usize,
+    pub connections_opened: u64,
+    pub connections_closed: u64,
+}
+
 /// Service proxy trait for the gateway end of the proxy (implementations are transport-layer,... specific)
 pub trait GatewayServiceProxy: Send {
-    /// Startup service proxy (for clients to connect to desired service)
+    /// Bind service proxy listener (if not already bound). Returns the actual bound port
+    fn bind_listener(&mut self) -> Result<u16, AppError>;
+
+    /// Startup service proxy (for clients to connect to desired service). Binds listener, if necessary,
+    /// and enters polling state (new connections are subsequently processed via `poll_connections`)
     fn startup(&mut self) -> Result<(), AppError>;
 
+    /// Perform a single (non-blocking) poll for new connections. Returns whether polling should continue
+    fn poll_connections(&mut self) -> Result<bool, AppError>;
+
     /// Shutdown service proxy
     fn shutdown(&mut self);
 }
@@ -34,6 +75,9 @@ pub trait GatewayServiceProxyVisitor: server_std::ServerVisitor + Send {
     /// Returns list of tuple of (client address, gateway address)
     fn get_proxy_addrs_for_user(&self, user_id: u64) -> Vec<ProxyAddrs>;
 
+    /// Active proxy connections (sessions) for given user
+    fn get_proxy_sessions_for_user(&self, user_id: u64) -> Vec<ProxySession>;
+
     /// Shutdown the active service proxy connections. Consider either all connections or for given user ID.
     fn shutdown_connections(
         &mut self,
@@ -41,8 +85,33 @@ pub trait GatewayServiceProxyVisitor: server_std::ServerVisitor + Send {
         user_id: Option<u64>,
     ) -> Result<(), AppError>;
 
+    /// Shutdown the active service proxy connection for given proxy key
+    fn shutdown_connection(
+        &mut self,
+        proxy_tasks_sender: Sender<ProxyExecutorEvent>,
+        proxy_key: &ProxyKey,
+    ) -> Result<(), AppError>;
+
+    /// Shutdown the active service proxy connections, which were authenticated by the client certificate with
+    /// given serial (for instance, upon its revocation)
+    fn shutdown_connections_by_cert_serial(
+        &mut self,
+        proxy_tasks_sender: Sender<ProxyExecutorEvent>,
+        cert_serial: &str,
+    ) -> Result<(), AppError>;
+
+    /// Returns whether service proxy has an active proxy for given proxy key
+    fn has_proxy_for_key(&self, proxy_key: &ProxyKey) -> bool;
+
     /// Remove proxy for given proxy key. Returns true if service proxy contained proxy key (and removed)
-    fn remove_proxy_for_key(&mut self, proxy_key: &str) -> bool;
+    fn remove_proxy_for_key(&mut self, proxy_key: &ProxyKey) -> bool;
+
+    /// Proxy keys of the active service proxy connections, which have been without activity (connection accepted or
+    /// data relayed) for at least given idle timeout (as of `now`)
+    fn get_idle_proxy_keys(&self, now: Instant, idle_timeout: Duration) -> Vec<ProxyKey>;
+
+    /// Live connection counts for service proxy
+    fn get_proxy_stats(&self) -> ProxyStats;
 }
 
 /// Unit tests
@@ -58,6 +127,16 @@ pub mod tests {
     // mocks
     // =====
 
+    mock! {
+        pub GwSvcProxy {}
+        impl GatewayServiceProxy for GwSvcProxy {
+            fn bind_listener(&mut self) -> Result<u16, AppError>;
+            fn startup(&mut self) -> Result<(), AppError>;
+            fn poll_connections(&mut self) -> Result<bool, AppError>;
+            fn shutdown(&mut self);
+        }
+    }
+
     mock! {
         pub GwSvcProxyVisitor {}
         impl server_std::ServerVisitor for GwSvcProxyVisitor {
@@ -70,8 +149,14 @@ pub mod tests {
             fn get_proxy_host(&self) -> Option<String>;
             fn get_proxy_port(&self) -> u16;
             fn get_proxy_addrs_for_user(&self, user_id: u64) -> Vec<ProxyAddrs>;
+            fn get_proxy_sessions_for_user(&self, user_id: u64) -> Vec<ProxySession>;
             fn shutdown_connections(&mut self, proxy_tasks_sender: Sender<ProxyExecutorEvent>, user_id: Option<u64>) -> Result<(), AppError>;
-            fn remove_proxy_for_key(&mut self, proxy_key: &str) -> bool;
+            fn shutdown_connection(&mut self, proxy_tasks_sender: Sender<ProxyExecutorEvent>, proxy_key: &ProxyKey) -> Result<(), AppError>;
+            fn shutdown_connections_by_cert_serial(&mut self, proxy_tasks_sender: Sender<ProxyExecutorEvent>, cert_serial: &str) -> Result<(), AppError>;
+            fn has_proxy_for_key(&self, proxy_key: &ProxyKey) -> bool;
+            fn remove_proxy_for_key(&mut self, proxy_key: &ProxyKey) -> bool;
+            fn get_idle_proxy_keys(&self, now: Instant, idle_timeout: Duration) -> Vec<ProxyKey>;
+            fn get_proxy_stats(&self) -> ProxyStats;
         }
     }
 }
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
-use std::net::{SocketAddr, TcpStream};
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr, TcpStream};
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use rustls::server::Accepted;
@@ -9,17 +11,27 @@ use rustls::ServerConfig;
 
 use crate::client::connection::ClientConnVisitor;
 use crate::config::AppConfig;
-use crate::service::manager::ServiceMgr;
+use crate::service::circuit_breaker::CircuitBreaker;
+use crate::service::dns_cache::ServiceAddrsCache;
+use crate::service::manager::{ServiceMgr, UserActiveServices};
 use crate::service::proxy::proxy_base::{
-    GatewayServiceProxy, GatewayServiceProxyVisitor, ProxyAddrs,
+    GatewayServiceProxy, GatewayServiceProxyVisitor, ProxyAddrs, ProxySession, ProxyStats,
 };
+use trust0_common::backoff::FixedBackoff;
 use trust0_common::error::AppError;
+use trust0_common::logging::LogSampler;
 use trust0_common::model::service::Service;
+use trust0_common::net::stream_utils;
 use trust0_common::net::tls_server::conn_std::TlsServerConnection;
 use trust0_common::net::tls_server::{conn_std, server_std};
 use trust0_common::proxy::event::ProxyEvent;
-use trust0_common::proxy::executor::ProxyExecutorEvent;
+use trust0_common::proxy::executor::{ProxyExecutorEvent, TcpProxyOptions};
 use trust0_common::proxy::proxy_base::ProxyType;
+use trust0_common::proxy::proxy_key::ProxyKey;
+use trust0_common::proxy::proxy_key_store::ProxyKeyStore;
+use trust0_common::proxy::proxy_tcp_and_tcp::{RelayMode, RelayRetry};
+
+const RELAY_RETRY_DELAY_MSECS: u64 = 250;
 
 /// Gateway service proxy (TCP trust0 gateway <-> TCP service)
 pub struct TcpGatewayProxy {
@@ -30,21 +42,38 @@ pub struct TcpGatewayProxy {
 impl TcpGatewayProxy {
     /// TcpGatewayProxy constructor
     pub fn new(
-        _app_config: Arc<AppConfig>,
+        app_config: Arc<AppConfig>,
         server_visitor: Arc<Mutex<TcpGatewayProxyServerVisitor>>,
         proxy_port: u16,
     ) -> Self {
+        let mut tls_server = server_std::Server::new(server_visitor.clone(), proxy_port);
+        tls_server.set_accept_workers(app_config.accept_workers);
+        tls_server.set_handshake_workers(app_config.handshake_workers);
+
         Self {
-            tls_server: server_std::Server::new(server_visitor.clone(), proxy_port),
+            tls_server,
             _server_visitor: server_visitor,
         }
     }
 }
 
 impl GatewayServiceProxy for TcpGatewayProxy {
+    fn bind_listener(&mut self) -> Result<u16, AppError> {
+        if self.tls_server.get_bound_port().is_none() {
+            self.tls_server.bind_listener()?;
+        }
+        self.tls_server.get_bound_port().ok_or(AppError::General(
+            "Service proxy listener not bound".to_string(),
+        ))
+    }
+
     fn startup(&mut self) -> Result<(), AppError> {
-        self.tls_server.bind_listener()?;
-        self.tls_server.poll_new_connections()
+        self.bind_listener()?;
+        self.tls_server.start_polling()
+    }
+
+    fn poll_connections(&mut self) -> Result<bool, AppError> {
+        self.tls_server.poll_once()
     }
 
     fn shutdown(&mut self) {
@@ -63,10 +92,17 @@ pub struct TcpGatewayProxyServerVisitor {
     proxy_port: u16,
     proxy_tasks_sender: Sender<ProxyExecutorEvent>,
     proxy_events_sender: Sender<ProxyEvent>,
-    services_by_proxy_key: Arc<Mutex<HashMap<String, u64>>>,
+    services_by_proxy_key: Arc<dyn ProxyKeyStore>,
+    user_active_services: Arc<Mutex<UserActiveServices>>,
     users_by_proxy_addrs: HashMap<ProxyAddrs, u64>,
-    proxy_addrs_by_proxy_key: HashMap<String, ProxyAddrs>,
-    proxy_keys_by_user: HashMap<u64, Vec<String>>,
+    cert_serials_by_proxy_addrs: HashMap<ProxyAddrs, String>,
+    proxy_addrs_by_proxy_key: HashMap<ProxyKey, ProxyAddrs>,
+    proxy_keys_by_user: HashMap<u64, Vec<ProxyKey>>,
+    proxy_start_times: HashMap<ProxyKey, Instant>,
+    connections_opened: u64,
+    connections_closed: u64,
+    conn_log_sampler: LogSampler,
+    conn_log_sampled: bool,
 }
 
 impl TcpGatewayProxyServerVisitor {
@@ -80,8 +116,11 @@ impl TcpGatewayProxyServerVisitor {
         proxy_port: u16,
         proxy_tasks_sender: Sender<ProxyExecutorEvent>,
         proxy_events_sender: Sender<ProxyEvent>,
-        services_by_proxy_key: Arc<Mutex<HashMap<String, u64>>>,
+        services_by_proxy_key: Arc<dyn ProxyKeyStore>,
+        user_active_services: Arc<Mutex<UserActiveServices>>,
     ) -> Result<Self, AppError> {
+        let conn_log_sampler = LogSampler::new(service.log_sample_rate.unwrap_or(1));
+
         Ok(Self {
             app_config,
             service_mgr,
@@ -91,12 +130,109 @@ impl TcpGatewayProxyServerVisitor {
             proxy_tasks_sender,
             proxy_events_sender,
             services_by_proxy_key,
+            user_active_services,
             users_by_proxy_addrs: HashMap::new(),
+            cert_serials_by_proxy_addrs: HashMap::new(),
             proxy_addrs_by_proxy_key: HashMap::new(),
             proxy_keys_by_user: HashMap::new(),
+            proxy_start_times: HashMap::new(),
+            connections_opened: 0,
+            connections_closed: 0,
+            conn_log_sampler,
+            conn_log_sampled: true,
         })
     }
 
+    /// Connect to (first reachable) resolved service endpoint. Dials are subject to the upstream circuit breaker.
+    /// Connections are bound to the service's upstream bind address, else to the given (gateway default) one.
+    /// The service's upstream prefix (if any) is written to the new connection, before any client data is relayed.
+    pub(crate) fn connect_to_service(
+        service_addrs_cache: &ServiceAddrsCache,
+        circuit_breaker: &CircuitBreaker,
+        upstream_bind_addr: Option<IpAddr>,
+        service: &Service,
+    ) -> Result<TcpStream, AppError> {
+        let upstream_bind_addr = service.upstream_bind_addr.or(upstream_bind_addr);
+        let mut response_err = None;
+
+        let resolved_host = service_addrs_cache.resolve(service)?;
+
+        for host_addr in resolved_host.into_iter() {
+            let service_addr = SocketAddr::new(host_addr, service.port);
+
+            match circuit_breaker.dial(service.service_id, &service_addr, || {
+                stream_utils::connect_tcp_stream(&service_addr, upstream_bind_addr).map_err(|err| {
+                    AppError::GenWithMsgAndErr(
+                        format!("Failed connect to service endpoint(s): svc={:?}", service),
+                        Box::new(err),
+                    )
+                })
+            }) {
+                Ok(mut socket) => {
+                    if !service.upstream_prefix.is_empty() {
+                        socket.write_all(&service.upstream_prefix).map_err(|err| {
+                            AppError::GenWithMsgAndErr(
+                                format!(
+                                    "Failed writing service upstream prefix: svc_id={}",
+                                    service.service_id
+                                ),
+                                Box::new(err),
+                            )
+                        })?;
+                    }
+                    socket.set_nonblocking(true).map_err(|err| {
+                        AppError::GenWithMsgAndErr(
+                            format!("Failed making socket non-blocking: socket={:?}", &socket),
+                            Box::new(err),
+                        )
+                    })?;
+                    return Ok(socket);
+                }
+                Err(err) => response_err = Some(err),
+            }
+        }
+
+        match response_err {
+            Some(err) => Err(err),
+            None => Err(AppError::General(format!(
+                "No resolved service endpoints: svc={:?}",
+                service
+            ))),
+        }
+    }
+
+    /// Upstream relay retry policy (if enabled for service)
+    fn create_relay_retry(&self) -> Option<RelayRetry> {
+        if self.service.relay_retries == 0 {
+            return None;
+        }
+
+        let service_addrs_cache = self.app_config.service_addrs_cache.clone();
+        let circuit_breaker = self.app_config.upstream_circuit_breaker.clone();
+        let upstream_bind_addr = self.app_config.upstream_bind_addr;
+        let service = self.service.clone();
+
+        Some(RelayRetry::new(
+            self.service.relay_retries,
+            Arc::new(FixedBackoff::new(Duration::from_millis(
+                RELAY_RETRY_DELAY_MSECS,
+            ))),
+            Arc::new(move || {
+                Self::connect_to_service(
+                    &service_addrs_cache,
+                    &circuit_breaker,
+                    upstream_bind_addr,
+                    &service,
+                )
+            }),
+        ))
+    }
+
+    /// Update gateway port for service proxy (once actual listener port is known)
+    pub fn set_proxy_port(&mut self, proxy_port: u16) {
+        self.proxy_port = proxy_port;
+    }
+
     /// Stringified tuple client and gateway connection addresses
     fn create_proxy_addrs(tls_conn: &TlsServerConnection) -> ProxyAddrs {
         let peer_addr = match &tls_conn.sock.peer_addr() {
@@ -119,82 +255,86 @@ impl server_std::ServerVisitor for TcpGatewayProxyServerVisitor {
     ) -> Result<conn_std::Connection, AppError> {
         let mut conn_visitor =
             ClientConnVisitor::new(self.app_config.clone(), self.service_mgr.clone());
+        conn_visitor.set_service(&self.service);
+        conn_visitor.set_client_addr(tls_conn.sock.peer_addr().ok());
+        self.conn_log_sampled = self.conn_log_sampler.sample();
+        conn_visitor.set_log_sampled(self.conn_log_sampled);
 
         let alpn_protocol =
             conn_visitor.process_authorization(&tls_conn, Some(self.service.service_id))?;
 
         let user_id = conn_visitor.get_user().as_ref().unwrap().user_id;
-        self.users_by_proxy_addrs.insert(
-            TcpGatewayProxyServerVisitor::create_proxy_addrs(&tls_conn),
-            user_id,
-        );
+        self.user_active_services
+            .lock()
+            .unwrap()
+            .check_connection(user_id, self.service.service_id)?;
+        let proxy_addrs = TcpGatewayProxyServerVisitor::create_proxy_addrs(&tls_conn);
+        if let Some(device) = conn_visitor.get_device() {
+            self.cert_serials_by_proxy_addrs
+                .insert(proxy_addrs.clone(), device.get_cert_serial().to_string());
+        }
+        self.users_by_proxy_addrs.insert(proxy_addrs, user_id);
 
         conn_std::Connection::new(Box::new(conn_visitor), tls_conn, alpn_protocol)
     }
 
-    fn on_tls_handshaking(&mut self, _accepted: &Accepted) -> Result<ServerConfig, AppError> {
-        self.app_config.tls_server_config_builder.build()
+    fn on_tls_handshaking(&mut self, accepted: &Accepted) -> Result<ServerConfig, AppError> {
+        self.app_config
+            .tls_server_config_builder
+            .build_for_client_hello(accepted)
     }
 
-    fn on_conn_accepted(&mut self, connection: conn_std::Connection) -> Result<(), AppError> {
-        // Make connection to service
-
-        let mut service_stream: Option<TcpStream> = None;
-        let mut response_err = None;
-
-        let resolved_host = self
-            .app_config
-            .dns_client
-            .query_addrs(self.service.host.as_str())
-            .map_err(|err| {
-                AppError::GenWithMsgAndErr(
-                    format!("Failed resolving host: host={}", &self.service.host),
-                    Box::new(err),
-                )
-            })?;
-
-        for host_addr in resolved_host.into_iter() {
-            let service_addr = SocketAddr::new(host_addr, self.service.port);
+    fn is_conn_log_sampled(&self) -> bool {
+        self.conn_log_sampled
+    }
 
-            match TcpStream::connect(service_addr) {
-                Ok(socket) => {
-                    socket.set_nonblocking(true).map_err(|err| {
-                        AppError::GenWithMsgAndErr(
-                            format!("Failed making socket non-blocking: socket={:?}", &socket),
-                            Box::new(err),
-                        )
-                    })?;
-                    service_stream = Some(socket);
-                    break;
-                }
-                Err(err) => response_err = Some(err),
-            }
-        }
+    fn is_abortive_close_on_denial(&self) -> bool {
+        self.app_config.abortive_close_on_denial
+    }
 
-        if service_stream.is_none() {
-            return match response_err {
-                Some(err) => Err(AppError::GenWithMsgAndErr(
-                    format!(
-                        "Failed connect to service endpoint(s): svc={:?}",
-                        &self.service
-                    ),
-                    Box::new(err),
-                )),
-                None => Err(AppError::General(format!(
-                    "No resolved service endpoints: svc={:?}",
-                    &self.service
-                ))),
-            };
-        }
+    fn on_conn_accepted(&mut self, connection: conn_std::Connection) -> Result<(), AppError> {
+        let service_stream = Self::connect_to_service(
+            &self.app_config.service_addrs_cache,
+            &self.app_config.upstream_circuit_breaker,
+            self.app_config.upstream_bind_addr,
+            &self.service,
+        )?;
+
+        self.on_conn_dialed(connection, service_stream)
+    }
 
-        let service_stream = service_stream.unwrap();
+    fn get_upstream_dialer(
+        &mut self,
+        _connection: &conn_std::Connection,
+    ) -> Result<Option<server_std::UpstreamDialer>, AppError> {
+        // Connection to service is made without holding the visitor lock
+        let service_addrs_cache = self.app_config.service_addrs_cache.clone();
+        let upstream_circuit_breaker = self.app_config.upstream_circuit_breaker.clone();
+        let upstream_bind_addr = self.app_config.upstream_bind_addr;
+        let service = self.service.clone();
+
+        Ok(Some(Box::new(move || {
+            Self::connect_to_service(
+                &service_addrs_cache,
+                &upstream_circuit_breaker,
+                upstream_bind_addr,
+                &service,
+            )
+        })))
+    }
 
+    fn on_conn_dialed(
+        &mut self,
+        connection: conn_std::Connection,
+        service_stream: TcpStream,
+    ) -> Result<(), AppError> {
         // Send request to proxy executor to startup new proxy
 
         let tls_conn = connection.get_tls_conn_as_ref();
         let proxy_addrs = TcpGatewayProxyServerVisitor::create_proxy_addrs(tls_conn);
-        let proxy_key = ProxyEvent::key_value(
-            &ProxyType::TcpAndTcp,
+        let proxy_key = ProxyKey::new(
+            ProxyType::TcpAndTcp,
+            self.service.service_id,
             tls_conn.sock.peer_addr().ok(),
             service_stream.peer_addr().ok(),
         );
@@ -227,6 +367,16 @@ impl server_std::ServerVisitor for TcpGatewayProxyServerVisitor {
                 ))),
                 Arc::new(Mutex::new(Box::new(service_stream_copy))),
                 self.proxy_events_sender.clone(),
+                TcpProxyOptions {
+                    relay_retry: self.create_relay_retry(),
+                    relay_mode: match self.service.fast_relay {
+                        true => RelayMode::Fast,
+                        false => RelayMode::Standard,
+                    },
+                    log_lifecycle: self.conn_log_sampled,
+                    max_stream2_bytes: self.service.max_response_bytes,
+                    abortive_close_on_error: false,
+                },
             ),
         );
 
@@ -242,9 +392,7 @@ impl server_std::ServerVisitor for TcpGatewayProxyServerVisitor {
         // Set up proxy maps
 
         self.services_by_proxy_key
-            .lock()
-            .unwrap()
-            .insert(proxy_key.clone(), self.service.service_id);
+            .put(&proxy_key, self.service.service_id);
 
         let user_id = self
             .users_by_proxy_addrs
@@ -256,6 +404,21 @@ impl server_std::ServerVisitor for TcpGatewayProxyServerVisitor {
 
         self.proxy_addrs_by_proxy_key
             .insert(proxy_key.clone(), proxy_addrs.clone());
+        self.proxy_start_times
+            .insert(proxy_key.clone(), Instant::now());
+        self.connections_opened += 1;
+        self.app_config
+            .service_activity
+            .record_activity(&proxy_key, Instant::now());
+        self.app_config
+            .user_byte_quotas
+            .lock()
+            .unwrap()
+            .register_proxy(&proxy_key, *user_id);
+        self.user_active_services
+            .lock()
+            .unwrap()
+            .register_proxy(&proxy_key, *user_id);
 
         if let Some(proxy_keys) = self.proxy_keys_by_user.get_mut(user_id) {
             proxy_keys.push(proxy_key.clone());
@@ -294,6 +457,24 @@ impl GatewayServiceProxyVisitor for TcpGatewayProxyServerVisitor {
             .collect()
     }
 
+    fn get_proxy_sessions_for_user(&self, user_id: u64) -> Vec<ProxySession> {
+        self.proxy_keys_by_user
+            .get(&user_id)
+            .map(|proxy_keys| {
+                proxy_keys
+                    .iter()
+                    .filter_map(|proxy_key| {
+                        Some(ProxySession {
+                            proxy_key: proxy_key.clone(),
+                            proxy_addrs: self.proxy_addrs_by_proxy_key.get(proxy_key)?.clone(),
+                            started_at: *self.proxy_start_times.get(proxy_key)?,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     fn shutdown_connections(
         &mut self,
         proxy_tasks_sender: Sender<ProxyExecutorEvent>,
@@ -301,7 +482,7 @@ impl GatewayServiceProxyVisitor for TcpGatewayProxyServerVisitor {
     ) -> Result<(), AppError> {
         let mut errors: Vec<String> = vec![];
 
-        let proxy_keys_lists: Vec<Vec<String>> = self
+        let proxy_keys_lists: Vec<Vec<ProxyKey>> = self
             .proxy_keys_by_user
             .iter()
             .filter(|(uid, _)| user_id.is_none() || (**uid == user_id.unwrap()))
@@ -331,7 +512,52 @@ impl GatewayServiceProxyVisitor for TcpGatewayProxyServerVisitor {
         Ok(())
     }
 
-    fn remove_proxy_for_key(&mut self, proxy_key: &str) -> bool {
+    fn shutdown_connection(
+        &mut self,
+        proxy_tasks_sender: Sender<ProxyExecutorEvent>,
+        proxy_key: &ProxyKey,
+    ) -> Result<(), AppError> {
+        proxy_tasks_sender
+            .send(ProxyExecutorEvent::Close(proxy_key.clone()))
+            .map_err(|err| {
+                AppError::General(format!(
+                    "Error while sending request to close a TCP proxy connection: proxy_stream={}, err={:?}",
+                    proxy_key, err
+                ))
+            })?;
+
+        self.remove_proxy_for_key(proxy_key);
+        Ok(())
+    }
+
+    fn shutdown_connections_by_cert_serial(
+        &mut self,
+        proxy_tasks_sender: Sender<ProxyExecutorEvent>,
+        cert_serial: &str,
+    ) -> Result<(), AppError> {
+        let proxy_keys: Vec<ProxyKey> = self
+            .proxy_addrs_by_proxy_key
+            .iter()
+            .filter(|(_, proxy_addrs)| {
+                self.cert_serials_by_proxy_addrs
+                    .get(*proxy_addrs)
+                    .is_some_and(|serial| serial == cert_serial)
+            })
+            .map(|(proxy_key, _)| proxy_key.clone())
+            .collect();
+
+        for proxy_key in proxy_keys {
+            self.shutdown_connection(proxy_tasks_sender.clone(), &proxy_key)?;
+        }
+
+        Ok(())
+    }
+
+    fn has_proxy_for_key(&self, proxy_key: &ProxyKey) -> bool {
+        self.proxy_addrs_by_proxy_key.contains_key(proxy_key)
+    }
+
+    fn remove_proxy_for_key(&mut self, proxy_key: &ProxyKey) -> bool {
         match self.proxy_addrs_by_proxy_key.get(proxy_key) {
             Some(proxy_addrs) => {
                 let proxy_addrs = proxy_addrs.clone();
@@ -340,15 +566,374 @@ impl GatewayServiceProxyVisitor for TcpGatewayProxyServerVisitor {
                     proxy_keys.retain(|key| !key.eq(proxy_key))
                 }
                 self.proxy_addrs_by_proxy_key.remove(proxy_key);
+                self.proxy_start_times.remove(proxy_key);
+                self.connections_closed += 1;
                 self.users_by_proxy_addrs.remove(&proxy_addrs);
-                self.services_by_proxy_key
+                self.cert_serials_by_proxy_addrs.remove(&proxy_addrs);
+                self.services_by_proxy_key.remove(proxy_key);
+                self.app_config
+                    .user_byte_quotas
+                    .lock()
+                    .unwrap()
+                    .unregister_proxy(proxy_key);
+                self.app_config
+                    .service_throughput
+                    .remove_connection(proxy_key);
+                self.app_config
+                    .service_activity
+                    .remove_connection(proxy_key);
+                self.user_active_services
                     .lock()
                     .unwrap()
-                    .remove(&proxy_key.to_string());
+                    .unregister_proxy(proxy_key);
                 true
             }
 
             None => false,
         }
     }
+
+    fn get_idle_proxy_keys(&self, now: Instant, idle_timeout: Duration) -> Vec<ProxyKey> {
+        self.proxy_start_times
+            .iter()
+            .filter(|(proxy_key, started_at)| {
+                let last_activity = self
+                    .app_config
+                    .service_activity
+                    .get_last_activity(proxy_key, **started_at);
+                now.saturating_duration_since(last_activity) >= idle_timeout
+            })
+            .map(|(proxy_key, _)| proxy_key.clone())
+            .collect()
+    }
+
+    fn get_proxy_stats(&self) -> ProxyStats {
+        ProxyStats {
+            active_connections: self.proxy_addrs_by_proxy_key.len(),
+            connections_opened: self.connections_opened,
+            connections_closed: self.connections_closed,
+        }
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::config;
+    use crate::repository::access_repo::tests::MockAccessRepo;
+    use crate::repository::service_repo::tests::MockServiceRepo;
+    use crate::repository::user_repo::tests::MockUserRepo;
+    use crate::service::dns_cache::tests::MockHostResolv;
+    use crate::service::manager::tests::MockSvcMgr;
+    use crate::service::proxy::proxy_base::GatewayServiceProxyVisitor;
+    use crate::testutils::create_handshaked_tls_conn_pair;
+    use mockall::predicate;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use trust0_common::crypto::alpn;
+    use trust0_common::model::service::Transport;
+    use trust0_common::net::tls_server::server_std::ServerVisitor;
+    use trust0_common::proxy::proxy_key_store::InMemProxyKeyStore;
+
+    // utils
+    // =====
+
+    fn create_tcp_proxy_visitor(
+        proxy_tasks_sender: Sender<ProxyExecutorEvent>,
+    ) -> TcpGatewayProxyServerVisitor {
+        let app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(MockServiceRepo::new())),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+
+        TcpGatewayProxyServerVisitor::new(
+            Arc::new(app_config),
+            Arc::new(Mutex::new(MockSvcMgr::new())),
+            Service::new(200, "Service200", &Transport::TCP, "localhost", 8200),
+            None,
+            8000,
+            proxy_tasks_sender,
+            mpsc::channel().0,
+            Arc::new(InMemProxyKeyStore::new()),
+            Arc::new(Mutex::new(UserActiveServices::new(None))),
+        )
+        .unwrap()
+    }
+
+    fn add_proxy(
+        proxy_visitor: &mut TcpGatewayProxyServerVisitor,
+        user_id: u64,
+        client_port: u16,
+        cert_serial: &str,
+    ) -> ProxyKey {
+        let proxy_key = ProxyKey::new(
+            ProxyType::TcpAndTcp,
+            200,
+            Some(SocketAddr::from(([127, 0, 0, 1], client_port))),
+            Some(SocketAddr::from(([127, 0, 0, 1], 8200))),
+        );
+        let proxy_addrs = (
+            format!("127.0.0.1:{}", client_port),
+            "127.0.0.1:8000".to_string(),
+        );
+
+        proxy_visitor
+            .users_by_proxy_addrs
+            .insert(proxy_addrs.clone(), user_id);
+        proxy_visitor
+            .cert_serials_by_proxy_addrs
+            .insert(proxy_addrs.clone(), cert_serial.to_string());
+        proxy_visitor
+            .proxy_addrs_by_proxy_key
+            .insert(proxy_key.clone(), proxy_addrs);
+        proxy_visitor
+            .proxy_keys_by_user
+            .entry(user_id)
+            .or_default()
+            .push(proxy_key.clone());
+        proxy_visitor
+            .proxy_start_times
+            .insert(proxy_key.clone(), Instant::now());
+
+        proxy_key
+    }
+
+    // tests
+    // =====
+
+    #[test]
+    fn tcpgwproxyvis_shutdown_connections_by_cert_serial() {
+        let (proxy_tasks_sender, proxy_tasks_receiver) = mpsc::channel();
+        let mut proxy_visitor = create_tcp_proxy_visitor(proxy_tasks_sender.clone());
+        let revoked_proxy_key1 = add_proxy(&mut proxy_visitor, 100, 41000, "01:2c");
+        let revoked_proxy_key2 = add_proxy(&mut proxy_visitor, 100, 41001, "01:2c");
+        let active_proxy_key = add_proxy(&mut proxy_visitor, 101, 41002, "01:2d");
+
+        proxy_visitor
+            .shutdown_connections_by_cert_serial(proxy_tasks_sender, "01:2c")
+            .unwrap();
+
+        let mut closed_proxy_keys: Vec<String> = proxy_tasks_receiver
+            .try_iter()
+            .map(|proxy_task| match proxy_task {
+                ProxyExecutorEvent::Close(proxy_key) => proxy_key.to_string(),
+                _ => panic!("Unexpected proxy task"),
+            })
+            .collect();
+        closed_proxy_keys.sort();
+        let mut expected_proxy_keys = vec![
+            revoked_proxy_key1.to_string(),
+            revoked_proxy_key2.to_string(),
+        ];
+        expected_proxy_keys.sort();
+
+        assert_eq!(closed_proxy_keys, expected_proxy_keys);
+        assert!(!proxy_visitor.has_proxy_for_key(&revoked_proxy_key1));
+        assert!(!proxy_visitor.has_proxy_for_key(&revoked_proxy_key2));
+        assert!(proxy_visitor.has_proxy_for_key(&active_proxy_key));
+        assert_eq!(proxy_visitor.cert_serials_by_proxy_addrs.len(), 1);
+        assert_eq!(proxy_visitor.get_proxy_addrs_for_user(101).len(), 1);
+    }
+
+    #[test]
+    fn tcpgwproxyvis_shutdown_connections_by_cert_serial_when_no_matches() {
+        let (proxy_tasks_sender, proxy_tasks_receiver) = mpsc::channel();
+        let mut proxy_visitor = create_tcp_proxy_visitor(proxy_tasks_sender.clone());
+        let active_proxy_key = add_proxy(&mut proxy_visitor, 101, 41002, "01:2d");
+
+        proxy_visitor
+            .shutdown_connections_by_cert_serial(proxy_tasks_sender, "01:2c")
+            .unwrap();
+
+        assert!(proxy_tasks_receiver.try_recv().is_err());
+        assert!(proxy_visitor.has_proxy_for_key(&active_proxy_key));
+    }
+
+    #[test]
+    fn tcpgwproxyvis_get_idle_proxy_keys() {
+        let mut proxy_visitor = create_tcp_proxy_visitor(mpsc::channel().0);
+        let idle_proxy_key = add_proxy(&mut proxy_visitor, 100, 41000, "01:2c");
+        let active_proxy_key = add_proxy(&mut proxy_visitor, 100, 41001, "01:2c");
+        let started_at = Instant::now();
+        for proxy_key in [&idle_proxy_key, &active_proxy_key] {
+            proxy_visitor
+                .proxy_start_times
+                .insert(proxy_key.clone(), started_at);
+        }
+        proxy_visitor
+            .app_config
+            .service_activity
+            .record_activity(&active_proxy_key, started_at + Duration::from_secs(50));
+
+        assert!(proxy_visitor
+            .get_idle_proxy_keys(
+                started_at + Duration::from_secs(20),
+                Duration::from_secs(30)
+            )
+            .is_empty());
+        assert_eq!(
+            proxy_visitor.get_idle_proxy_keys(
+                started_at + Duration::from_secs(60),
+                Duration::from_secs(30)
+            ),
+            vec![idle_proxy_key.clone()]
+        );
+
+        let mut idle_proxy_keys: Vec<String> = proxy_visitor
+            .get_idle_proxy_keys(
+                started_at + Duration::from_secs(90),
+                Duration::from_secs(30),
+            )
+            .iter()
+            .map(|proxy_key| proxy_key.to_string())
+            .collect();
+        idle_proxy_keys.sort();
+        let mut expected_proxy_keys =
+            vec![idle_proxy_key.to_string(), active_proxy_key.to_string()];
+        expected_proxy_keys.sort();
+        assert_eq!(idle_proxy_keys, expected_proxy_keys);
+    }
+
+    #[test]
+    fn tcpgwproxyvis_connect_to_service_when_upstream_prefix() {
+        let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut service = Service::new(
+            200,
+            "Service200",
+            &Transport::TCP,
+            "upstream1",
+            upstream_listener.local_addr().unwrap().port(),
+        );
+        service.upstream_prefix = b"PREFIX\n".to_vec();
+        let mut resolver = MockHostResolv::new();
+        resolver
+            .expect_resolve()
+            .with(predicate::eq("upstream1"))
+            .returning(|_| Ok(vec![IpAddr::from([127, 0, 0, 1])]));
+        let service_addrs_cache =
+            ServiceAddrsCache::new(Arc::new(resolver), Duration::ZERO, Duration::ZERO);
+
+        let mut service_stream = TcpGatewayProxyServerVisitor::connect_to_service(
+            &service_addrs_cache,
+            &CircuitBreaker::new(0, Duration::ZERO),
+            None,
+            &service,
+        )
+        .unwrap();
+        service_stream.set_nonblocking(false).unwrap();
+        service_stream.write_all(b"client data").unwrap();
+        service_stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let (mut upstream_stream, _) = upstream_listener.accept().unwrap();
+        let mut received = Vec::new();
+        upstream_stream.read_to_end(&mut received).unwrap();
+
+        assert_eq!(received, b"PREFIX\nclient data".to_vec());
+    }
+
+    #[test]
+    fn tcpgwproxyvis_connect_to_service_when_no_upstream_prefix() {
+        let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let service = Service::new(
+            200,
+            "Service200",
+            &Transport::TCP,
+            "upstream1",
+            upstream_listener.local_addr().unwrap().port(),
+        );
+        let mut resolver = MockHostResolv::new();
+        resolver
+            .expect_resolve()
+            .with(predicate::eq("upstream1"))
+            .returning(|_| Ok(vec![IpAddr::from([127, 0, 0, 1])]));
+        let service_addrs_cache =
+            ServiceAddrsCache::new(Arc::new(resolver), Duration::ZERO, Duration::ZERO);
+
+        let mut service_stream = TcpGatewayProxyServerVisitor::connect_to_service(
+            &service_addrs_cache,
+            &CircuitBreaker::new(0, Duration::ZERO),
+            None,
+            &service,
+        )
+        .unwrap();
+        service_stream.set_nonblocking(false).unwrap();
+        service_stream.write_all(b"client data").unwrap();
+        service_stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let (mut upstream_stream, _) = upstream_listener.accept().unwrap();
+        let mut received = Vec::new();
+        upstream_stream.read_to_end(&mut received).unwrap();
+
+        assert_eq!(received, b"client data".to_vec());
+    }
+
+    #[test]
+    fn tcpgwproxyvis_get_upstream_dialer_when_visitor_locked() {
+        let upstream_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut resolver = MockHostResolv::new();
+        resolver
+            .expect_resolve()
+            .with(predicate::eq("upstream1"))
+            .returning(|_| Ok(vec![IpAddr::from([127, 0, 0, 1])]));
+        let mut service_repo = MockServiceRepo::new();
+        service_repo.expect_get_all().returning(|| Ok(vec![]));
+        let mut app_config = config::tests::create_app_config_with_repos(
+            Arc::new(Mutex::new(MockUserRepo::new())),
+            Arc::new(Mutex::new(service_repo)),
+            Arc::new(Mutex::new(MockAccessRepo::new())),
+        )
+        .unwrap();
+        app_config.service_addrs_cache = Arc::new(ServiceAddrsCache::new(
+            Arc::new(resolver),
+            Duration::ZERO,
+            Duration::ZERO,
+        ));
+        let app_config = Arc::new(app_config);
+
+        let mut proxy_visitor = create_tcp_proxy_visitor(mpsc::channel().0);
+        proxy_visitor.app_config = app_config.clone();
+        proxy_visitor.service = Service::new(
+            200,
+            "Service200",
+            &Transport::TCP,
+            "upstream1",
+            upstream_listener.local_addr().unwrap().port(),
+        );
+        let proxy_visitor = Arc::new(Mutex::new(proxy_visitor));
+
+        let (tls_conn, _tls_cli_stream) = create_handshaked_tls_conn_pair(
+            alpn::Protocol::create_service_protocol(200).as_bytes(),
+        );
+        let connection = conn_std::Connection::new(
+            Box::new(ClientConnVisitor::new(
+                app_config,
+                Arc::new(Mutex::new(MockSvcMgr::new())),
+            )),
+            tls_conn,
+            alpn::Protocol::Service(200),
+        )
+        .unwrap();
+
+        let upstream_dialer = proxy_visitor
+            .lock()
+            .unwrap()
+            .get_upstream_dialer(&connection)
+            .unwrap()
+            .unwrap();
+
+        // Dialer must not need the visitor lock
+        let _visitor_guard = proxy_visitor.lock().unwrap();
+        let service_stream = upstream_dialer().unwrap();
+        let (upstream_stream, _) = upstream_listener.accept().unwrap();
+
+        assert_eq!(
+            service_stream.peer_addr().unwrap(),
+            upstream_stream.local_addr().unwrap()
+        );
+    }
 }
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::net::{SocketAddr, UdpSocket};
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use rustls::server::Accepted;
@@ -9,17 +10,21 @@ use rustls::ServerConfig;
 
 use crate::client::connection::ClientConnVisitor;
 use crate::config::AppConfig;
-use crate::service::manager::ServiceMgr;
+use crate::service::manager::{ServiceMgr, UserActiveServices};
 use crate::service::proxy::proxy_base::{
-    GatewayServiceProxy, GatewayServiceProxyVisitor, ProxyAddrs,
+    GatewayServiceProxy, GatewayServiceProxyVisitor, ProxyAddrs, ProxySession, ProxyStats,
 };
 use trust0_common::error::AppError;
+use trust0_common::logging::LogSampler;
 use trust0_common::model::service::Service;
+use trust0_common::net::stream_utils;
 use trust0_common::net::tls_server::conn_std::TlsServerConnection;
 use trust0_common::net::tls_server::{conn_std, server_std};
 use trust0_common::proxy::event::ProxyEvent;
-use trust0_common::proxy::executor::ProxyExecutorEvent;
+use trust0_common::proxy::executor::{ProxyExecutorEvent, UdpProxyOptions};
 use trust0_common::proxy::proxy_base::ProxyType;
+use trust0_common::proxy::proxy_key::ProxyKey;
+use trust0_common::proxy::proxy_key_store::ProxyKeyStore;
 
 /// Gateway service proxy (TCP trust0 gateway <-> UDP service)
 pub struct UdpGatewayProxy {
@@ -30,21 +35,38 @@ pub struct UdpGatewayProxy {
 impl UdpGatewayProxy {
     /// UdpGatewayProxy constructor
     pub fn new(
-        _app_config: Arc<AppConfig>,
+        app_config: Arc<AppConfig>,
         server_visitor: Arc<Mutex<UdpGatewayProxyServerVisitor>>,
         proxy_port: u16,
     ) -> Self {
+        let mut tls_server = server_std::Server::new(server_visitor.clone(), proxy_port);
+        tls_server.set_accept_workers(app_config.accept_workers);
+        tls_server.set_handshake_workers(app_config.handshake_workers);
+
         Self {
-            tls_server: server_std::Server::new(server_visitor.clone(), proxy_port),
+            tls_server,
             _server_visitor: server_visitor,
         }
     }
 }
 
 impl GatewayServiceProxy for UdpGatewayProxy {
+    fn bind_listener(&mut self) -> Result<u16, AppError> {
+        if self.tls_server.get_bound_port().is_none() {
+            self.tls_server.bind_listener()?;
+        }
+        self.tls_server.get_bound_port().ok_or(AppError::General(
+            "Service proxy listener not bound".to_string(),
+        ))
+    }
+
     fn startup(&mut self) -> Result<(), AppError> {
-        self.tls_server.bind_listener()?;
-        self.tls_server.poll_new_connections()
+        self.bind_listener()?;
+        self.tls_server.start_polling()
+    }
+
+    fn poll_connections(&mut self) -> Result<bool, AppError> {
+        self.tls_server.poll_once()
     }
 
     fn shutdown(&mut self) {
@@ -63,10 +85,17 @@ pub struct UdpGatewayProxyServerVisitor {
     proxy_port: u16,
     proxy_tasks_sender: Sender<ProxyExecutorEvent>,
     proxy_events_sender: Sender<ProxyEvent>,
-    services_by_proxy_key: Arc<Mutex<HashMap<String, u64>>>,
+    services_by_proxy_key: Arc<dyn ProxyKeyStore>,
+    user_active_services: Arc<Mutex<UserActiveServices>>,
     users_by_proxy_addrs: HashMap<ProxyAddrs, u64>,
-    proxy_addrs_by_proxy_key: HashMap<String, ProxyAddrs>,
-    proxy_keys_by_user: HashMap<u64, Vec<String>>,
+    cert_serials_by_proxy_addrs: HashMap<ProxyAddrs, String>,
+    proxy_addrs_by_proxy_key: HashMap<ProxyKey, ProxyAddrs>,
+    proxy_keys_by_user: HashMap<u64, Vec<ProxyKey>>,
+    proxy_start_times: HashMap<ProxyKey, Instant>,
+    connections_opened: u64,
+    connections_closed: u64,
+    conn_log_sampler: LogSampler,
+    conn_log_sampled: bool,
 }
 
 impl UdpGatewayProxyServerVisitor {
@@ -80,8 +109,11 @@ impl UdpGatewayProxyServerVisitor {
         proxy_port: u16,
         proxy_tasks_sender: Sender<ProxyExecutorEvent>,
         proxy_events_sender: Sender<ProxyEvent>,
-        services_by_proxy_key: Arc<Mutex<HashMap<String, u64>>>,
+        services_by_proxy_key: Arc<dyn ProxyKeyStore>,
+        user_active_services: Arc<Mutex<UserActiveServices>>,
     ) -> Result<Self, AppError> {
+        let conn_log_sampler = LogSampler::new(service.log_sample_rate.unwrap_or(1));
+
         Ok(Self {
             app_config,
             service_mgr,
@@ -91,12 +123,24 @@ impl UdpGatewayProxyServerVisitor {
             proxy_tasks_sender,
             proxy_events_sender,
             services_by_proxy_key,
+            user_active_services,
             users_by_proxy_addrs: HashMap::new(),
+            cert_serials_by_proxy_addrs: HashMap::new(),
             proxy_addrs_by_proxy_key: HashMap::new(),
             proxy_keys_by_user: HashMap::new(),
+            proxy_start_times: HashMap::new(),
+            connections_opened: 0,
+            connections_closed: 0,
+            conn_log_sampler,
+            conn_log_sampled: true,
         })
     }
 
+    /// Update gateway port for service proxy (once actual listener port is known)
+    pub fn set_proxy_port(&mut self, proxy_port: u16) {
+        self.proxy_port = proxy_port;
+    }
+
     /// Stringified tuple client and gateway connection addresses
     fn create_proxy_addrs(tls_conn: &TlsServerConnection) -> ProxyAddrs {
         let peer_addr = match &tls_conn.sock.peer_addr() {
@@ -119,15 +163,25 @@ impl server_std::ServerVisitor for UdpGatewayProxyServerVisitor {
     ) -> Result<conn_std::Connection, AppError> {
         let mut conn_visitor =
             ClientConnVisitor::new(self.app_config.clone(), self.service_mgr.clone());
+        conn_visitor.set_service(&self.service);
+        conn_visitor.set_client_addr(tls_conn.sock.peer_addr().ok());
+        self.conn_log_sampled = self.conn_log_sampler.sample();
+        conn_visitor.set_log_sampled(self.conn_log_sampled);
 
         let alpn_protocol =
             conn_visitor.process_authorization(&tls_conn, Some(self.service.service_id))?;
 
         let user_id = conn_visitor.get_user().as_ref().unwrap().user_id;
-        self.users_by_proxy_addrs.insert(
-            UdpGatewayProxyServerVisitor::create_proxy_addrs(&tls_conn),
-            user_id,
-        );
+        self.user_active_services
+            .lock()
+            .unwrap()
+            .check_connection(user_id, self.service.service_id)?;
+        let proxy_addrs = UdpGatewayProxyServerVisitor::create_proxy_addrs(&tls_conn);
+        if let Some(device) = conn_visitor.get_device() {
+            self.cert_serials_by_proxy_addrs
+                .insert(proxy_addrs.clone(), device.get_cert_serial().to_string());
+        }
+        self.users_by_proxy_addrs.insert(proxy_addrs, user_id);
 
         let connection =
             conn_std::Connection::new(Box::new(conn_visitor), tls_conn, alpn_protocol)?;
@@ -135,8 +189,18 @@ impl server_std::ServerVisitor for UdpGatewayProxyServerVisitor {
         Ok(connection)
     }
 
-    fn on_tls_handshaking(&mut self, _accepted: &Accepted) -> Result<ServerConfig, AppError> {
-        self.app_config.tls_server_config_builder.build()
+    fn on_tls_handshaking(&mut self, accepted: &Accepted) -> Result<ServerConfig, AppError> {
+        self.app_config
+            .tls_server_config_builder
+            .build_for_client_hello(accepted)
+    }
+
+    fn is_conn_log_sampled(&self) -> bool {
+        self.conn_log_sampled
+    }
+
+    fn is_abortive_close_on_denial(&self) -> bool {
+        self.app_config.abortive_close_on_denial
     }
 
     fn on_conn_accepted(&mut self, connection: conn_std::Connection) -> Result<(), AppError> {
@@ -145,34 +209,49 @@ impl server_std::ServerVisitor for UdpGatewayProxyServerVisitor {
         let mut service_addr = None;
         let mut response_err = None;
 
-        let resolved_host = self
-            .app_config
-            .dns_client
-            .query_addrs(self.service.host.as_str())
-            .map_err(|err| {
-                AppError::GenWithMsgAndErr(
-                    format!("Failed resolving host: host={}", &self.service.host),
-                    Box::new(err),
-                )
-            })?;
+        let resolved_host = self.app_config.service_addrs_cache.resolve(&self.service)?;
 
-        let udp_socket =
-            UdpSocket::bind(format!("{}:0", &self.app_config.gateway_service_reply_host)).map_err(
-                |err| {
-                    AppError::GenWithMsgAndErr(
-                        format!(
-                            "Error binding service reply UDP socket: reply_host={}",
-                            &self.app_config.gateway_service_reply_host
-                        ),
-                        Box::new(err),
-                    )
-                },
-            )?;
+        let bind_addr = match self
+            .service
+            .upstream_bind_addr
+            .or(self.app_config.upstream_bind_addr)
+        {
+            Some(upstream_bind_addr) => SocketAddr::new(upstream_bind_addr, 0).to_string(),
+            None => format!("{}:0", &self.app_config.gateway_service_reply_host),
+        };
+
+        let udp_socket = UdpSocket::bind(&bind_addr).map_err(|err| {
+            AppError::GenWithMsgAndErr(
+                format!(
+                    "Error binding service reply UDP socket: bind_addr={}",
+                    &bind_addr
+                ),
+                Box::new(err),
+            )
+        })?;
 
         for host_addr in resolved_host.into_iter() {
             let remote_addr = SocketAddr::new(host_addr, self.service.port);
 
-            match udp_socket.connect(remote_addr) {
+            match self.app_config.upstream_circuit_breaker.dial(
+                self.service.service_id,
+                &remote_addr,
+                || match self.service.udp_target {
+                    // broadcast/multicast targets have multiple responders, so socket remains unconnected
+                    Some(udp_target) => {
+                        stream_utils::setup_udp_target_socket(&udp_socket, udp_target, &remote_addr)
+                    }
+                    None => udp_socket.connect(remote_addr).map_err(|err| {
+                        AppError::GenWithMsgAndErr(
+                            format!(
+                                "Failed connect to service endpoint(s): svc={:?}",
+                                &self.service
+                            ),
+                            Box::new(err),
+                        )
+                    }),
+                },
+            ) {
                 Ok(()) => {
                     service_addr = Some(remote_addr);
                     udp_socket.set_nonblocking(true).map_err(|err| {
@@ -192,13 +271,7 @@ impl server_std::ServerVisitor for UdpGatewayProxyServerVisitor {
 
         if service_addr.is_none() {
             return match response_err {
-                Some(err) => Err(AppError::GenWithMsgAndErr(
-                    format!(
-                        "Failed connect to service endpoint(s): svc={:?}",
-                        &self.service
-                    ),
-                    Box::new(err),
-                )),
+                Some(err) => Err(err),
                 None => Err(AppError::General(format!(
                     "No resolved service endpoints: svc={:?}",
                     &self.service
@@ -212,8 +285,9 @@ impl server_std::ServerVisitor for UdpGatewayProxyServerVisitor {
 
         let tls_conn = connection.get_tls_conn_as_ref();
         let proxy_addrs = UdpGatewayProxyServerVisitor::create_proxy_addrs(tls_conn);
-        let proxy_key = ProxyEvent::key_value(
-            &ProxyType::TcpAndUdp,
+        let proxy_key = ProxyKey::new(
+            ProxyType::TcpAndUdp,
+            self.service.service_id,
             udp_socket.local_addr().ok(),
             Some(service_addr),
         );
@@ -236,6 +310,10 @@ impl server_std::ServerVisitor for UdpGatewayProxyServerVisitor {
                     connection.into(),
                 ))),
                 self.proxy_events_sender.clone(),
+                UdpProxyOptions {
+                    udp_target_addr: self.service.udp_target.map(|_| service_addr),
+                    log_lifecycle: self.conn_log_sampled,
+                },
             ),
         );
 
@@ -251,9 +329,7 @@ impl server_std::ServerVisitor for UdpGatewayProxyServerVisitor {
         // Set up proxy maps
 
         self.services_by_proxy_key
-            .lock()
-            .unwrap()
-            .insert(proxy_key.clone(), self.service.service_id);
+            .put(&proxy_key, self.service.service_id);
 
         let user_id = self
             .users_by_proxy_addrs
@@ -265,6 +341,21 @@ impl server_std::ServerVisitor for UdpGatewayProxyServerVisitor {
 
         self.proxy_addrs_by_proxy_key
             .insert(proxy_key.clone(), proxy_addrs.clone());
+        self.proxy_start_times
+            .insert(proxy_key.clone(), Instant::now());
+        self.connections_opened += 1;
+        self.app_config
+            .service_activity
+            .record_activity(&proxy_key, Instant::now());
+        self.app_config
+            .user_byte_quotas
+            .lock()
+            .unwrap()
+            .register_proxy(&proxy_key, *user_id);
+        self.user_active_services
+            .lock()
+            .unwrap()
+            .register_proxy(&proxy_key, *user_id);
 
         if let Some(proxy_keys) = self.proxy_keys_by_user.get_mut(user_id) {
             proxy_keys.push(proxy_key.clone());
@@ -303,6 +394,24 @@ impl GatewayServiceProxyVisitor for UdpGatewayProxyServerVisitor {
             .collect()
     }
 
+    fn get_proxy_sessions_for_user(&self, user_id: u64) -> Vec<ProxySession> {
+        self.proxy_keys_by_user
+            .get(&user_id)
+            .map(|proxy_keys| {
+                proxy_keys
+                    .iter()
+                    .filter_map(|proxy_key| {
+                        Some(ProxySession {
+                            proxy_key: proxy_key.clone(),
+                            proxy_addrs: self.proxy_addrs_by_proxy_key.get(proxy_key)?.clone(),
+                            started_at: *self.proxy_start_times.get(proxy_key)?,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     fn shutdown_connections(
         &mut self,
         proxy_tasks_sender: Sender<ProxyExecutorEvent>,
@@ -310,7 +419,7 @@ impl GatewayServiceProxyVisitor for UdpGatewayProxyServerVisitor {
     ) -> Result<(), AppError> {
         let mut errors: Vec<String> = vec![];
 
-        let proxy_keys_lists: Vec<Vec<String>> = self
+        let proxy_keys_lists: Vec<Vec<ProxyKey>> = self
             .proxy_keys_by_user
             .iter()
             .filter(|(uid, _)| user_id.is_none() || (**uid == user_id.unwrap()))
@@ -340,7 +449,52 @@ impl GatewayServiceProxyVisitor for UdpGatewayProxyServerVisitor {
         Ok(())
     }
 
-    fn remove_proxy_for_key(&mut self, proxy_key: &str) -> bool {
+    fn shutdown_connection(
+        &mut self,
+        proxy_tasks_sender: Sender<ProxyExecutorEvent>,
+        proxy_key: &ProxyKey,
+    ) -> Result<(), AppError> {
+        proxy_tasks_sender
+            .send(ProxyExecutorEvent::Close(proxy_key.clone()))
+            .map_err(|err| {
+                AppError::General(format!(
+                    "Error while sending request to close a UDP proxy connection: proxy_stream={}, err={:?}",
+                    proxy_key, err
+                ))
+            })?;
+
+        self.remove_proxy_for_key(proxy_key);
+        Ok(())
+    }
+
+    fn shutdown_connections_by_cert_serial(
+        &mut self,
+        proxy_tasks_sender: Sender<ProxyExecutorEvent>,
+        cert_serial: &str,
+    ) -> Result<(), AppError> {
+        let proxy_keys: Vec<ProxyKey> = self
+            .proxy_addrs_by_proxy_key
+            .iter()
+            .filter(|(_, proxy_addrs)| {
+                self.cert_serials_by_proxy_addrs
+                    .get(*proxy_addrs)
+                    .is_some_and(|serial| serial == cert_serial)
+            })
+            .map(|(proxy_key, _)| proxy_key.clone())
+            .collect();
+
+        for proxy_key in proxy_keys {
+            self.shutdown_connection(proxy_tasks_sender.clone(), &proxy_key)?;
+        }
+
+        Ok(())
+    }
+
+    fn has_proxy_for_key(&self, proxy_key: &ProxyKey) -> bool {
+        self.proxy_addrs_by_proxy_key.contains_key(proxy_key)
+    }
+
+    fn remove_proxy_for_key(&mut self, proxy_key: &ProxyKey) -> bool {
         match self.proxy_addrs_by_proxy_key.get(proxy_key) {
             Some(proxy_addrs) => {
                 let proxy_addrs = proxy_addrs.clone();
@@ -349,15 +503,52 @@ impl GatewayServiceProxyVisitor for UdpGatewayProxyServerVisitor {
                     proxy_keys.retain(|key| !key.eq(proxy_key))
                 }
                 self.proxy_addrs_by_proxy_key.remove(proxy_key);
+                self.proxy_start_times.remove(proxy_key);
+                self.connections_closed += 1;
                 self.users_by_proxy_addrs.remove(&proxy_addrs);
-                self.services_by_proxy_key
+                self.cert_serials_by_proxy_addrs.remove(&proxy_addrs);
+                self.services_by_proxy_key.remove(proxy_key);
+                self.app_config
+                    .user_byte_quotas
                     .lock()
                     .unwrap()
-                    .remove(&proxy_key.to_string());
+                    .unregister_proxy(proxy_key);
+                self.app_config
+                    .service_throughput
+                    .remove_connection(proxy_key);
+                self.app_config
+                    .service_activity
+                    .remove_connection(proxy_key);
+                self.user_active_services
+                    .lock()
+                    .unwrap()
+                    .unregister_proxy(proxy_key);
                 true
             }
 
             None => false,
         }
     }
+
+    fn get_idle_proxy_keys(&self, now: Instant, idle_timeout: Duration) -> Vec<ProxyKey> {
+        self.proxy_start_times
+            .iter()
+            .filter(|(proxy_key, started_at)| {
+                let last_activity = self
+                    .app_config
+                    .service_activity
+                    .get_last_activity(proxy_key, **started_at);
+                now.saturating_duration_since(last_activity) >= idle_timeout
+            })
+            .map(|(proxy_key, _)| proxy_key.clone())
+            .collect()
+    }
+
+    fn get_proxy_stats(&self) -> ProxyStats {
+        ProxyStats {
+            active_connections: self.proxy_addrs_by_proxy_key.len(),
+            connections_opened: self.connections_opened,
+            connections_closed: self.connections_closed,
+        }
+    }
 }
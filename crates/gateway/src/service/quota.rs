@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde_derive::Serialize;
+
+use crate::config;
+use trust0_common::metrics::MetricsSink;
+use trust0_common::proxy::proxy_key::ProxyKey;
+
+/// Bytes transferred by a user within the current quota window
+struct UserByteUsage {
+    window_start: Instant,
+    bytes: u64,
+}
+
+/// Accumulates bytes transferred per user (across their service proxies) within a fixed quota window
+#[derive(Serialize)]
+pub struct UserByteQuotas {
+    default_byte_quota: Option<u64>,
+    #[serde(serialize_with = "config::config_dump::duration_secs")]
+    window: Duration,
+    #[serde(skip)]
+    user_ids_by_proxy_key: HashMap<ProxyKey, u64>,
+    #[serde(skip)]
+    usage_by_user: HashMap<u64, UserByteUsage>,
+}
+
+impl UserByteQuotas {
+    /// UserByteQuotas constructor. The default quota applies to users without their own quota (None is unlimited)
+    pub fn new(default_byte_quota: Option<u64>, window: Duration) -> Self {
+        Self {
+            default_byte_quota,
+            window,
+            user_ids_by_proxy_key: HashMap::new(),
+            usage_by_user: HashMap::new(),
+        }
+    }
+
+    /// Associate proxy with given user, so its transferred bytes are accounted to that user
+    pub fn register_proxy(&mut self, proxy_key: &ProxyKey, user_id: u64) {
+        self.user_ids_by_proxy_key
+            .insert(proxy_key.clone(), user_id);
+    }
+
+    /// Remove user association for given proxy
+    pub fn unregister_proxy(&mut self, proxy_key: &ProxyKey) {
+        self.user_ids_by_proxy_key.remove(proxy_key);
+    }
+
+    /// Add transferred bytes for given proxy to its user's usage (ignored for unregistered proxies)
+    pub fn record_bytes(&mut self, proxy_key: &ProxyKey, bytes: u64) {
+        if let Some(user_id) = self.user_ids_by_proxy_key.get(proxy_key).cloned() {
+            let usage = self.get_current_usage(user_id);
+            usage.bytes = usage.bytes.saturating_add(bytes);
+        }
+    }
+
+    /// Bytes transferred by user within the current quota window
+    pub fn get_usage(&mut self, user_id: u64) -> u64 {
+        self.get_current_usage(user_id).bytes
+    }
+
+    /// Returns whether user has reached their byte quota (user quota, else default quota) for the current window
+    pub fn is_quota_exceeded(&mut self, user_id: u64, user_byte_quota: Option<u64>) -> bool {
+        match user_byte_quota.or(self.default_byte_quota) {
+            Some(byte_quota) => self.get_usage(user_id) >= byte_quota,
+            None => false,
+        }
+    }
+
+    /// User usage for current window (starting a new window, if prior one has elapsed)
+    fn get_current_usage(&mut self, user_id: u64) -> &mut UserByteUsage {
+        let window = self.window;
+        let usage = self
+            .usage_by_user
+            .entry(user_id)
+            .or_insert_with(|| UserByteUsage {
+                window_start: Instant::now(),
+                bytes: 0,
+            });
+
+        if usage.window_start.elapsed() >= window {
+            usage.window_start = Instant::now();
+            usage.bytes = 0;
+        }
+
+        usage
+    }
+}
+
+/// Metrics sink, which accounts proxy bytes towards user quotas and forwards all metrics to an underlying sink
+pub struct QuotaMetricsSink {
+    metrics_sink: Arc<dyn MetricsSink>,
+    user_byte_quotas: Arc<Mutex<UserByteQuotas>>,
+}
+
+impl QuotaMetricsSink {
+    /// QuotaMetricsSink constructor
+    pub fn new(
+        metrics_sink: Arc<dyn MetricsSink>,
+        user_byte_quotas: Arc<Mutex<UserByteQuotas>>,
+    ) -> Self {
+        Self {
+            metrics_sink,
+            user_byte_quotas,
+        }
+    }
+}
+
+impl MetricsSink for QuotaMetricsSink {
+    fn incr_counter(&self, name: &str, value: u64) {
+        self.metrics_sink.incr_counter(name, value);
+    }
+
+    fn set_gauge(&self, name: &str, value: i64) {
+        self.metrics_sink.set_gauge(name, value);
+    }
+
+    fn incr_proxy_bytes(&self, proxy_key: &ProxyKey, value: u64) {
+        self.user_byte_quotas
+            .lock()
+            .unwrap()
+            .record_bytes(proxy_key, value);
+        self.metrics_sink.incr_proxy_bytes(proxy_key, value);
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::testutils::CapturingMetricsSink;
+    use std::thread;
+    use trust0_common::metrics::METRIC_BYTES_TRANSFERRED;
+    use trust0_common::proxy::proxy_base::ProxyType;
+
+    #[test]
+    fn userquotas_is_quota_exceeded_when_crossing_default_quota() {
+        let proxy_key = ProxyKey::new(ProxyType::TcpAndTcp, 200, None, None);
+        let mut user_byte_quotas = UserByteQuotas::new(Some(1000), Duration::from_secs(3600));
+        user_byte_quotas.register_proxy(&proxy_key, 100);
+
+        user_byte_quotas.record_bytes(&proxy_key, 999);
+        assert!(!user_byte_quotas.is_quota_exceeded(100, None));
+
+        user_byte_quotas.record_bytes(&proxy_key, 1);
+        assert!(user_byte_quotas.is_quota_exceeded(100, None));
+        assert_eq!(user_byte_quotas.get_usage(100), 1000);
+        assert!(!user_byte_quotas.is_quota_exceeded(101, None));
+    }
+
+    #[test]
+    fn userquotas_is_quota_exceeded_when_user_quota_overrides_default() {
+        let proxy_key = ProxyKey::new(ProxyType::TcpAndTcp, 200, None, None);
+        let mut user_byte_quotas = UserByteQuotas::new(Some(1000), Duration::from_secs(3600));
+        user_byte_quotas.register_proxy(&proxy_key, 100);
+
+        user_byte_quotas.record_bytes(&proxy_key, 1500);
+
+        assert!(user_byte_quotas.is_quota_exceeded(100, None));
+        assert!(!user_byte_quotas.is_quota_exceeded(100, Some(2000)));
+        assert!(!UserByteQuotas::new(None, Duration::from_secs(3600)).is_quota_exceeded(100, None));
+    }
+
+    #[test]
+    fn userquotas_record_bytes_when_unregistered_proxy() {
+        let proxy_key = ProxyKey::new(ProxyType::TcpAndTcp, 200, None, None);
+        let mut user_byte_quotas = UserByteQuotas::new(Some(1000), Duration::from_secs(3600));
+        user_byte_quotas.register_proxy(&proxy_key, 100);
+        user_byte_quotas.record_bytes(&proxy_key, 10);
+        user_byte_quotas.unregister_proxy(&proxy_key);
+
+        user_byte_quotas.record_bytes(&proxy_key, 2000);
+
+        assert_eq!(user_byte_quotas.get_usage(100), 10);
+        assert!(!user_byte_quotas.is_quota_exceeded(100, None));
+    }
+
+    #[test]
+    fn userquotas_is_quota_exceeded_when_window_elapsed() {
+        let proxy_key = ProxyKey::new(ProxyType::TcpAndTcp, 200, None, None);
+        let mut user_byte_quotas = UserByteQuotas::new(Some(1000), Duration::from_millis(50));
+        user_byte_quotas.register_proxy(&proxy_key, 100);
+
+        user_byte_quotas.record_bytes(&proxy_key, 1000);
+        assert!(user_byte_quotas.is_quota_exceeded(100, None));
+
+        thread::sleep(Duration::from_millis(60));
+
+        assert!(!user_byte_quotas.is_quota_exceeded(100, None));
+        assert_eq!(user_byte_quotas.get_usage(100), 0);
+    }
+
+    #[test]
+    fn quotasink_incr_proxy_bytes() {
+        let proxy_key = ProxyKey::new(ProxyType::TcpAndTcp, 200, None, None);
+        let capturing_sink = Arc::new(CapturingMetricsSink::default());
+        let user_byte_quotas = Arc::new(Mutex::new(UserByteQuotas::new(
+            None,
+            Duration::from_secs(3600),
+        )));
+        user_byte_quotas
+            .lock()
+            .unwrap()
+            .register_proxy(&proxy_key, 100);
+        let quota_sink = QuotaMetricsSink::new(capturing_sink.clone(), user_byte_quotas.clone());
+
+        quota_sink.incr_proxy_bytes(&proxy_key, 25);
+
+        assert_eq!(user_byte_quotas.lock().unwrap().get_usage(100), 25);
+        assert_eq!(
+            *capturing_sink.metrics.lock().unwrap(),
+            vec![(METRIC_BYTES_TRANSFERRED.to_string(), 25)]
+        );
+    }
+}
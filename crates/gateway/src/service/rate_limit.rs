@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use trust0_common::model::service::Service;
+
+/// Rate limit window, corresponding to the services' (per second) max QPS
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(1);
+/// Number of tracked windows, at which elapsed windows are purged
+const PURGE_THRESHOLD: usize = 1024;
+
+/// Tracks new connections made by each user to each service, to enforce services' max QPS (fixed one second windows)
+#[derive(Default)]
+pub struct ServiceRateLimits {
+    windows_by_user_service: HashMap<(u64, u64), (Instant, u32)>,
+}
+
+impl ServiceRateLimits {
+    /// ServiceRateLimits constructor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a new connection (as of `now`) by user to service. Returns whether it is allowed, which is the case if
+    /// the service has no max QPS, or the user's connections in the current window are under the max QPS. Denied
+    /// connections aren't counted.
+    pub fn try_acquire(&mut self, user_id: u64, service: &Service, now: Instant) -> bool {
+        let max_qps = match service.max_qps {
+            Some(max_qps) => max_qps,
+            None => return true,
+        };
+
+        if self.windows_by_user_service.len() >= PURGE_THRESHOLD {
+            self.purge_elapsed(now);
+        }
+
+        let (window_start, window_count) = self
+            .windows_by_user_service
+            .entry((user_id, service.service_id))
+            .or_insert((now, 0));
+
+        if now.saturating_duration_since(*window_start) >= RATE_LIMIT_WINDOW {
+            *window_start = now;
+            *window_count = 0;
+        }
+
+        if *window_count >= max_qps {
+            return false;
+        }
+
+        *window_count += 1;
+        true
+    }
+
+    /// Remove windows which have elapsed (as of `now`), as these no longer limit any connection
+    fn purge_elapsed(&mut self, now: Instant) {
+        self.windows_by_user_service.retain(|_, (window_start, _)| {
+            now.saturating_duration_since(*window_start) < RATE_LIMIT_WINDOW
+        });
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use trust0_common::model::service::Transport;
+
+    fn create_service(service_id: u64, max_qps: Option<u32>) -> Service {
+        let mut service = Service::new(service_id, "Service", &Transport::TCP, "localhost", 8200);
+        service.max_qps = max_qps;
+        service
+    }
+
+    #[test]
+    fn svcratelimits_try_acquire_when_no_max_qps() {
+        let mut service_rate_limits = ServiceRateLimits::new();
+        let service = create_service(200, None);
+        let now = Instant::now();
+
+        for _ in 0..100 {
+            assert!(service_rate_limits.try_acquire(100, &service, now));
+        }
+        assert!(service_rate_limits.windows_by_user_service.is_empty());
+    }
+
+    #[test]
+    fn svcratelimits_try_acquire_when_exceeding_and_recovering_after_window() {
+        let mut service_rate_limits = ServiceRateLimits::new();
+        let service = create_service(200, Some(3));
+        let started_at = Instant::now();
+
+        for _ in 0..3 {
+            assert!(service_rate_limits.try_acquire(100, &service, started_at));
+        }
+        assert!(!service_rate_limits.try_acquire(100, &service, started_at));
+        assert!(!service_rate_limits.try_acquire(
+            100,
+            &service,
+            started_at + Duration::from_millis(999)
+        ));
+
+        let next_window_at = started_at + Duration::from_secs(1);
+
+        for _ in 0..3 {
+            assert!(service_rate_limits.try_acquire(100, &service, next_window_at));
+        }
+        assert!(!service_rate_limits.try_acquire(100, &service, next_window_at));
+    }
+
+    #[test]
+    fn svcratelimits_try_acquire_when_keyed_by_user_and_service() {
+        let mut service_rate_limits = ServiceRateLimits::new();
+        let service200 = create_service(200, Some(1));
+        let service201 = create_service(201, Some(1));
+        let now = Instant::now();
+
+        assert!(service_rate_limits.try_acquire(100, &service200, now));
+        assert!(!service_rate_limits.try_acquire(100, &service200, now));
+        assert!(service_rate_limits.try_acquire(101, &service200, now));
+        assert!(service_rate_limits.try_acquire(100, &service201, now));
+    }
+
+    #[test]
+    fn svcratelimits_purge_elapsed() {
+        let mut service_rate_limits = ServiceRateLimits::new();
+        let service = create_service(200, Some(1));
+        let started_at = Instant::now();
+
+        service_rate_limits.try_acquire(100, &service, started_at);
+        service_rate_limits.try_acquire(101, &service, started_at + Duration::from_millis(500));
+
+        service_rate_limits.purge_elapsed(started_at + Duration::from_secs(1));
+
+        assert_eq!(service_rate_limits.windows_by_user_service.len(), 1);
+        assert!(service_rate_limits
+            .windows_by_user_service
+            .contains_key(&(101, 200)));
+    }
+}
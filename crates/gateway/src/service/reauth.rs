@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use trust0_common::model::service::Service;
+
+/// Tracks when each user last (re-)authorized for each service, to enforce services' re-authorization interval
+#[derive(Default)]
+pub struct ServiceAuthTimes {
+    last_auth_by_user_service: HashMap<(u64, u64), Instant>,
+}
+
+impl ServiceAuthTimes {
+    /// ServiceAuthTimes constructor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record (as of `now`) that user has authorized for service
+    pub fn record_auth(&mut self, user_id: u64, service_id: u64, now: Instant) {
+        self.last_auth_by_user_service
+            .insert((user_id, service_id), now);
+    }
+
+    /// Returns whether user must re-authorize (as of `now`) before connecting to service. This is the case if the
+    /// service has a re-authorization interval, and the user hasn't authorized for it within that interval.
+    /// Times are monotonic (see `Clock::now()`), and only gate new connections, so active connections are never
+    /// revoked by this check.
+    pub fn is_reauth_required(&self, user_id: u64, service: &Service, now: Instant) -> bool {
+        let reauth_interval = match service.reauth_interval {
+            Some(reauth_interval) => Duration::from_secs(reauth_interval),
+            None => return false,
+        };
+
+        match self
+            .last_auth_by_user_service
+            .get(&(user_id, service.service_id))
+        {
+            Some(last_auth) => now.saturating_duration_since(*last_auth) >= reauth_interval,
+            None => true,
+        }
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use trust0_common::model::service::Transport;
+
+    fn create_service(reauth_interval: Option<u64>) -> Service {
+        let mut service = Service::new(200, "Service200", &Transport::TCP, "localhost", 8200);
+        service.reauth_interval = reauth_interval;
+        service
+    }
+
+    #[test]
+    fn svcauthtimes_is_reauth_required_when_no_interval() {
+        let service_auth_times = ServiceAuthTimes::new();
+
+        assert!(!service_auth_times.is_reauth_required(100, &create_service(None), Instant::now()));
+    }
+
+    #[test]
+    fn svcauthtimes_is_reauth_required_when_never_authorized() {
+        let service_auth_times = ServiceAuthTimes::new();
+
+        assert!(service_auth_times.is_reauth_required(
+            100,
+            &create_service(Some(3600)),
+            Instant::now()
+        ));
+    }
+
+    #[test]
+    fn svcauthtimes_is_reauth_required_when_crossing_interval() {
+        let mut service_auth_times = ServiceAuthTimes::new();
+        let service = create_service(Some(3600));
+        let authed_at = Instant::now();
+
+        service_auth_times.record_auth(100, 200, authed_at);
+
+        assert!(!service_auth_times.is_reauth_required(
+            100,
+            &service,
+            authed_at + Duration::from_secs(3599)
+        ));
+        assert!(service_auth_times.is_reauth_required(
+            100,
+            &service,
+            authed_at + Duration::from_secs(3600)
+        ));
+        assert!(service_auth_times.is_reauth_required(101, &service, authed_at));
+
+        service_auth_times.record_auth(100, 200, authed_at + Duration::from_secs(3600));
+
+        assert!(!service_auth_times.is_reauth_required(
+            100,
+            &service,
+            authed_at + Duration::from_secs(3600)
+        ));
+    }
+}
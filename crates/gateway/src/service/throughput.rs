@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use trust0_common::metrics::{self, MetricsSink};
+use trust0_common::proxy::proxy_key::ProxyKey;
+
+/// Minimum time between consecutive service throughput gauge emissions (per service)
+const THROUGHPUT_METRIC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Exponentially weighted moving average of a byte rate (bytes/sec), with given time constant (window).
+/// Each update decays the prior rate and adds the new bytes' contribution, so updates are O(1).
+#[derive(Clone, Debug)]
+pub struct RollingRate {
+    window: Duration,
+    rate: f64,
+    updated_at: Instant,
+}
+
+impl RollingRate {
+    /// RollingRate constructor
+    pub fn new(window: Duration, now: Instant) -> Self {
+        Self {
+            window,
+            rate: 0.0,
+            updated_at: now,
+        }
+    }
+
+    /// Record bytes transferred (as of `now`)
+    pub fn record_bytes(&mut self, bytes: u64, now: Instant) {
+        self.rate = self.get_rate(now) + bytes as f64 / self.window.as_secs_f64();
+        if now > self.updated_at {
+            self.updated_at = now;
+        }
+    }
+
+    /// Current rate (bytes/sec) as of `now`
+    pub fn get_rate(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated_at);
+        self.rate * (-elapsed.as_secs_f64() / self.window.as_secs_f64()).exp()
+    }
+}
+
+/// Per service throughput state
+struct ServiceRate {
+    rolling_rate: RollingRate,
+    reported_at: Option<Instant>,
+    total_bytes: u64,
+}
+
+/// Tracks rolling throughput (bytes/sec) per proxy connection, and aggregated (with total bytes) per service
+pub struct ServiceThroughput {
+    window: Duration,
+    rates_by_proxy_key: Mutex<HashMap<ProxyKey, RollingRate>>,
+    rates_by_service: Mutex<HashMap<u64, ServiceRate>>,
+}
+
+impl ServiceThroughput {
+    /// ServiceThroughput constructor. Rates are averaged over (roughly) the given window
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            rates_by_proxy_key: Mutex::new(HashMap::new()),
+            rates_by_service: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record bytes transferred (as of `now`) for given proxy connection (and its service). Returns the service's
+    /// current rate, if due to be reported
+    pub fn record_bytes(&self, proxy_key: &ProxyKey, bytes: u64, now: Instant) -> Option<f64> {
+        self.rates_by_proxy_key
+            .lock()
+            .unwrap()
+            .entry(proxy_key.clone())
+            .or_insert_with(|| RollingRate::new(self.window, now))
+            .record_bytes(bytes, now);
+
+        let mut rates_by_service = self.rates_by_service.lock().unwrap();
+        let service_rate = rates_by_service
+            .entry(proxy_key.get_service_id())
+            .or_insert_with(|| ServiceRate {
+                rolling_rate: RollingRate::new(self.window, now),
+                reported_at: None,
+                total_bytes: 0,
+            });
+        service_rate.rolling_rate.record_bytes(bytes, now);
+        service_rate.total_bytes += bytes;
+
+        match service_rate.reported_at {
+            Some(reported_at)
+                if now.saturating_duration_since(reported_at) < THROUGHPUT_METRIC_INTERVAL =>
+            {
+                None
+            }
+            _ => {
+                service_rate.reported_at = Some(now);
+                Some(service_rate.rolling_rate.get_rate(now))
+            }
+        }
+    }
+
+    /// Current rate (bytes/sec) for given proxy connection
+    pub fn get_connection_rate(&self, proxy_key: &ProxyKey, now: Instant) -> f64 {
+        self.rates_by_proxy_key
+            .lock()
+            .unwrap()
+            .get(proxy_key)
+            .map_or(0.0, |rolling_rate| rolling_rate.get_rate(now))
+    }
+
+    /// Current rate (bytes/sec) for given service (across all of its proxy connections)
+    pub fn get_service_rate(&self, service_id: u64, now: Instant) -> f64 {
+        self.rates_by_service
+            .lock()
+            .unwrap()
+            .get(&service_id)
+            .map_or(0.0, |service_rate| service_rate.rolling_rate.get_rate(now))
+    }
+
+    /// Total bytes transferred for given service (across all of its proxy connections)
+    pub fn get_service_bytes(&self, service_id: u64) -> u64 {
+        self.rates_by_service
+            .lock()
+            .unwrap()
+            .get(&service_id)
+            .map_or(0, |service_rate| service_rate.total_bytes)
+    }
+
+    /// Discard rate for given proxy connection
+    pub fn remove_connection(&self, proxy_key: &ProxyKey) {
+        self.rates_by_proxy_key.lock().unwrap().remove(proxy_key);
+    }
+
+    /// Discard rate for given service
+    pub fn remove_service(&self, service_id: u64) {
+        self.rates_by_service.lock().unwrap().remove(&service_id);
+    }
+}
+
+/// Metrics sink, which records proxied bytes as service throughput (periodically reporting the service's rate gauge)
+/// and forwards all metrics to an underlying sink
+pub struct ThroughputMetricsSink {
+    metrics_sink: Arc<dyn MetricsSink>,
+    service_throughput: Arc<ServiceThroughput>,
+}
+
+impl ThroughputMetricsSink {
+    /// ThroughputMetricsSink constructor
+    pub fn new(
+        metrics_sink: Arc<dyn MetricsSink>,
+        service_throughput: Arc<ServiceThroughput>,
+    ) -> Self {
+        Self {
+            metrics_sink,
+            service_throughput,
+        }
+    }
+}
+
+impl MetricsSink for ThroughputMetricsSink {
+    fn incr_counter(&self, name: &str, value: u64) {
+        self.metrics_sink.incr_counter(name, value);
+    }
+
+    fn set_gauge(&self, name: &str, value: i64) {
+        self.metrics_sink.set_gauge(name, value);
+    }
+
+    fn incr_proxy_bytes(&self, proxy_key: &ProxyKey, value: u64) {
+        if let Some(service_rate) =
+            self.service_throughput
+                .record_bytes(proxy_key, value, Instant::now())
+        {
+            self.metrics_sink.set_gauge(
+                &metrics::service_metric_name(
+                    metrics::METRIC_SERVICE_THROUGHPUT,
+                    proxy_key.get_service_id(),
+                ),
+                service_rate.round() as i64,
+            );
+        }
+        self.metrics_sink.incr_proxy_bytes(proxy_key, value);
+    }
+}
+
+/// Unit tests
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::testutils::CapturingMetricsSink;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+    use trust0_common::proxy::proxy_base::ProxyType;
+
+    fn create_proxy_key(service_id: u64, client_port: u16) -> ProxyKey {
+        ProxyKey::new(
+            ProxyType::TcpAndTcp,
+            service_id,
+            Some(SocketAddr::from_str(&format!("10.0.0.5:{}", client_port)).unwrap()),
+            None,
+        )
+    }
+
+    fn assert_within_tolerance(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= expected * tolerance,
+            "Rate out of tolerance: actual={}, expected={}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn rollrate_record_bytes_when_steady_pattern() {
+        let started_at = Instant::now();
+        let mut rolling_rate = RollingRate::new(Duration::from_secs(1), started_at);
+
+        // 1000 bytes every 10ms (100,000 bytes/sec), for 10 (simulated) secs
+        for tick in 1..=1000 {
+            rolling_rate.record_bytes(1000, started_at + Duration::from_millis(tick * 10));
+        }
+
+        assert_within_tolerance(
+            rolling_rate.get_rate(started_at + Duration::from_secs(10)),
+            100_000.0,
+            0.02,
+        );
+    }
+
+    #[test]
+    fn rollrate_get_rate_when_traffic_stops() {
+        let started_at = Instant::now();
+        let mut rolling_rate = RollingRate::new(Duration::from_secs(1), started_at);
+
+        for tick in 1..=1000 {
+            rolling_rate.record_bytes(1000, started_at + Duration::from_millis(tick * 10));
+        }
+
+        // after 3 windows of idleness, rate has decayed to ~5%
+        assert_within_tolerance(
+            rolling_rate.get_rate(started_at + Duration::from_secs(13)),
+            100_000.0 * (-3.0f64).exp(),
+            0.02,
+        );
+        assert!(rolling_rate.get_rate(started_at + Duration::from_secs(60)) < 1.0);
+    }
+
+    #[test]
+    fn rollrate_record_bytes_when_rate_changes() {
+        let started_at = Instant::now();
+        let mut rolling_rate = RollingRate::new(Duration::from_secs(1), started_at);
+
+        for tick in 1..=500 {
+            rolling_rate.record_bytes(1000, started_at + Duration::from_millis(tick * 10));
+        }
+        // drop to 500 bytes every 10ms (50,000 bytes/sec)
+        for tick in 501..=1000 {
+            rolling_rate.record_bytes(500, started_at + Duration::from_millis(tick * 10));
+        }
+
+        assert_within_tolerance(
+            rolling_rate.get_rate(started_at + Duration::from_secs(10)),
+            50_000.0,
+            0.02,
+        );
+    }
+
+    #[test]
+    fn svcthroughput_record_bytes_aggregates_per_service() {
+        let service_throughput = ServiceThroughput::new(Duration::from_secs(1));
+        let proxy_key1 = create_proxy_key(200, 41000);
+        let proxy_key2 = create_proxy_key(200, 41001);
+        let proxy_key3 = create_proxy_key(201, 41002);
+        let started_at = Instant::now();
+
+        for tick in 1..=1000 {
+            let now = started_at + Duration::from_millis(tick * 10);
+            service_throughput.record_bytes(&proxy_key1, 1000, now);
+            service_throughput.record_bytes(&proxy_key2, 500, now);
+            service_throughput.record_bytes(&proxy_key3, 100, now);
+        }
+
+        let now = started_at + Duration::from_secs(10);
+        assert_within_tolerance(
+            service_throughput.get_connection_rate(&proxy_key1, now),
+            100_000.0,
+            0.02,
+        );
+        assert_within_tolerance(
+            service_throughput.get_connection_rate(&proxy_key2, now),
+            50_000.0,
+            0.02,
+        );
+        assert_within_tolerance(
+            service_throughput.get_service_rate(200, now),
+            150_000.0,
+            0.02,
+        );
+        assert_within_tolerance(
+            service_throughput.get_service_rate(201, now),
+            10_000.0,
+            0.02,
+        );
+
+        service_throughput.remove_connection(&proxy_key1);
+        service_throughput.remove_service(201);
+        assert_eq!(
+            service_throughput.get_connection_rate(&proxy_key1, now),
+            0.0
+        );
+        assert_eq!(service_throughput.get_service_rate(201, now), 0.0);
+        assert_eq!(service_throughput.get_service_bytes(200), 1_500_000);
+        assert_eq!(service_throughput.get_service_bytes(201), 0);
+    }
+
+    #[test]
+    fn svcthroughput_record_bytes_reports_service_rate_periodically() {
+        let service_throughput = ServiceThroughput::new(Duration::from_secs(1));
+        let proxy_key = create_proxy_key(200, 41000);
+        let started_at = Instant::now();
+
+        let reports: Vec<u64> = (0..=300)
+            .filter(|tick| {
+                service_throughput
+                    .record_bytes(
+                        &proxy_key,
+                        1000,
+                        started_at + Duration::from_millis(tick * 10),
+                    )
+                    .is_some()
+            })
+            .collect();
+
+        assert_eq!(reports, vec![0, 100, 200, 300]);
+    }
+
+    #[test]
+    fn throughputsink_incr_proxy_bytes_emits_service_gauge() {
+        let capturing_sink = Arc::new(CapturingMetricsSink::default());
+        let service_throughput = Arc::new(ServiceThroughput::new(Duration::from_secs(1)));
+        let metrics_sink =
+            ThroughputMetricsSink::new(capturing_sink.clone(), service_throughput.clone());
+        let proxy_key = create_proxy_key(200, 41000);
+
+        metrics_sink.incr_proxy_bytes(&proxy_key, 1000);
+        metrics_sink.incr_proxy_bytes(&proxy_key, 1000);
+
+        assert_eq!(
+            *capturing_sink.metrics.lock().unwrap(),
+            vec![
+                ("service.bytes_per_sec.service.200".to_string(), 1000),
+                ("bytes.transferred".to_string(), 1000),
+                ("bytes.transferred".to_string(), 1000)
+            ]
+        );
+        assert!(service_throughput.get_connection_rate(&proxy_key, Instant::now()) > 0.0);
+    }
+}
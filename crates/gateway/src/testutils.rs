@@ -1,7 +1,21 @@
 /// Unit tests
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
 use mockall::mock;
-use pki_types::CertificateDer;
-use trust0_common::net::tls_server::conn_std::TlsConnection;
+use pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::{DigitallySignedStruct, StreamOwned};
+use trust0_common::conn_events::{ConnEvent, ConnEventSink};
+use trust0_common::crypto::file::{load_certificates, load_private_key};
+use trust0_common::metrics::MetricsSink;
+use trust0_common::net::tls_server::conn_std::{
+    TlsConnection, TlsServerConnection, TlsSessionInfo,
+};
+
+use crate::config::tests::{CERTFILE_GATEWAY_PATHPARTS, KEYFILE_GATEWAY_PATHPARTS};
 
 // mocks
 // =====
@@ -11,5 +25,133 @@ mock! {
     impl TlsConnection for TlsSvrConn {
         fn peer_certificates(&self) -> Option<Vec<CertificateDer<'static>>>;
         fn alpn_protocol(&self) -> Option<Vec<u8>>;
+        fn session_info(&self) -> TlsSessionInfo;
+        fn peer_addr(&self) -> Option<SocketAddr>;
+    }
+}
+
+/// Metrics sink, which captures emitted metrics (as name/value tuples, in emission order)
+#[derive(Default)]
+pub struct CapturingMetricsSink {
+    pub metrics: Mutex<Vec<(String, i64)>>,
+}
+
+impl MetricsSink for CapturingMetricsSink {
+    fn incr_counter(&self, name: &str, value: u64) {
+        self.metrics
+            .lock()
+            .unwrap()
+            .push((name.to_string(), value as i64));
+    }
+
+    fn set_gauge(&self, name: &str, value: i64) {
+        self.metrics.lock().unwrap().push((name.to_string(), value));
+    }
+}
+
+/// Connection event sink, which captures emitted events (as NDJSON lines, in emission order)
+#[derive(Default)]
+pub struct CapturingConnEventSink {
+    pub lines: Mutex<Vec<String>>,
+}
+
+impl ConnEventSink for CapturingConnEventSink {
+    fn emit(&self, event: &ConnEvent) {
+        self.lines.lock().unwrap().push(event.to_ndjson().unwrap());
+    }
+}
+
+/// Server certificate verifier, which accepts any server certificate
+#[derive(Debug)]
+struct NoServerCertVerification {}
+
+impl ServerCertVerifier for NoServerCertVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        rustls::crypto::ring::default_provider()
+            .signature_verification_algorithms
+            .supported_schemes()
     }
 }
+
+/// Perform a TLS handshake (gateway certificate, no client auth) over a local TCP socket pair, returning both the
+/// server and client ends
+pub fn create_handshaked_tls_conn_pair(
+    alpn_protocol: &[u8],
+) -> (
+    TlsServerConnection,
+    StreamOwned<rustls::ClientConnection, TcpStream>,
+) {
+    let cert_file: PathBuf = CERTFILE_GATEWAY_PATHPARTS.iter().collect();
+    let key_file: PathBuf = KEYFILE_GATEWAY_PATHPARTS.iter().collect();
+    let mut server_config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(
+            load_certificates(cert_file.to_str().unwrap().to_string()).unwrap(),
+            load_private_key(key_file.to_str().unwrap().to_string()).unwrap(),
+        )
+        .unwrap();
+    server_config.alpn_protocols = vec![alpn_protocol.to_vec()];
+
+    let mut client_config = rustls::ClientConfig::builder()
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(NoServerCertVerification {}))
+        .with_no_client_auth();
+    client_config.alpn_protocols = vec![alpn_protocol.to_vec()];
+    let client_config = Arc::new(client_config);
+
+    let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server_addr = tcp_listener.local_addr().unwrap();
+
+    let client_thread = thread::spawn(move || {
+        let mut tcp_stream = TcpStream::connect(server_addr).unwrap();
+        let mut tls_cli_conn = rustls::ClientConnection::new(
+            client_config,
+            ServerName::try_from("localhost").unwrap(),
+        )
+        .unwrap();
+        while tls_cli_conn.is_handshaking() {
+            tls_cli_conn.complete_io(&mut tcp_stream).unwrap();
+        }
+        StreamOwned::new(tls_cli_conn, tcp_stream)
+    });
+
+    let (mut tcp_stream, _) = tcp_listener.accept().unwrap();
+    let mut tls_srv_conn = rustls::ServerConnection::new(Arc::new(server_config)).unwrap();
+    while tls_srv_conn.is_handshaking() {
+        tls_srv_conn.complete_io(&mut tcp_stream).unwrap();
+    }
+
+    (
+        StreamOwned::new(tls_srv_conn, tcp_stream),
+        client_thread.join().unwrap(),
+    )
+}